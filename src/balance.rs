@@ -6,7 +6,7 @@
 // accordance with one or both of these licenses.
 
 use bitcoin::secp256k1::PublicKey;
-use bitcoin::{Amount, BlockHash, Txid};
+use bitcoin::{Amount, BlockHash, OutPoint, Txid};
 use lightning::chain::channelmonitor::{Balance as LdkBalance, BalanceSource};
 use lightning::ln::types::ChannelId;
 use lightning::sign::SpendableOutputDescriptor;
@@ -30,6 +30,16 @@ pub struct BalanceDetails {
 	/// The share of our total balance that we retain as an emergency reserve to (hopefully) be
 	/// able to spend the Anchor outputs when one of our channels is closed.
 	pub total_anchor_channels_reserve_sats: u64,
+	/// The share of [`total_anchor_channels_reserve_sats`] that is currently backed by
+	/// sufficiently confirmed funds, i.e., that is readily available to spend the Anchor outputs
+	/// when one of our channels is closed.
+	///
+	/// Together with [`spendable_onchain_balance_sats`], this makes up all of our currently
+	/// spendable on-chain funds.
+	///
+	/// [`total_anchor_channels_reserve_sats`]: Self::total_anchor_channels_reserve_sats
+	/// [`spendable_onchain_balance_sats`]: Self::spendable_onchain_balance_sats
+	pub spendable_anchor_reserve_sats: u64,
 	/// The total balance that we would be able to claim across all our Lightning channels.
 	///
 	/// Note this excludes balances that we are unsure if we are able to claim (e.g., as we are
@@ -77,6 +87,8 @@ pub enum LightningBalance {
 		channel_id: ChannelId,
 		/// The identifier of our channel counterparty.
 		counterparty_node_id: PublicKey,
+		/// The funding transaction output of the channel this balance belongs to.
+		funding_txo: OutPoint,
 		/// The amount available to claim, in satoshis, excluding the on-chain fees which will be
 		/// required to do so.
 		amount_satoshis: u64,
@@ -131,6 +143,14 @@ pub enum LightningBalance {
 		channel_id: ChannelId,
 		/// The identifier of our channel counterparty.
 		counterparty_node_id: PublicKey,
+		/// The funding transaction output of the channel this balance belongs to.
+		funding_txo: OutPoint,
+		/// The outputs of the channel's on-chain transactions we're watching to claim funds from,
+		/// e.g., the HTLC outputs of a confirmed commitment transaction.
+		///
+		/// Note that these aren't attributed to individual balances. Moreover, outputs only we can
+		/// spend, such as our own delayed output, aren't watched and hence not listed.
+		claim_outpoints: Vec<OutPoint>,
 		/// The amount available to claim, in satoshis, possibly excluding the on-chain fees which
 		/// were spent in broadcasting the transaction.
 		amount_satoshis: u64,
@@ -154,6 +174,14 @@ pub enum LightningBalance {
 		channel_id: ChannelId,
 		/// The identifier of our channel counterparty.
 		counterparty_node_id: PublicKey,
+		/// The funding transaction output of the channel this balance belongs to.
+		funding_txo: OutPoint,
+		/// The outputs of the channel's on-chain transactions we're watching to claim funds from,
+		/// e.g., the HTLC outputs of a confirmed commitment transaction.
+		///
+		/// Note that these aren't attributed to individual balances. Moreover, outputs only we can
+		/// spend, such as our own delayed output, aren't watched and hence not listed.
+		claim_outpoints: Vec<OutPoint>,
 		/// The amount available to claim, in satoshis, excluding the on-chain fees which will be
 		/// required to do so.
 		amount_satoshis: u64,
@@ -173,6 +201,14 @@ pub enum LightningBalance {
 		channel_id: ChannelId,
 		/// The identifier of our channel counterparty.
 		counterparty_node_id: PublicKey,
+		/// The funding transaction output of the channel this balance belongs to.
+		funding_txo: OutPoint,
+		/// The outputs of the channel's on-chain transactions we're watching to claim funds from,
+		/// e.g., the HTLC outputs of a confirmed commitment transaction.
+		///
+		/// Note that these aren't attributed to individual balances. Moreover, outputs only we can
+		/// spend, such as our own delayed output, aren't watched and hence not listed.
+		claim_outpoints: Vec<OutPoint>,
 		/// The amount potentially available to claim, in satoshis, excluding the on-chain fees
 		/// which will be required to do so.
 		amount_satoshis: u64,
@@ -192,6 +228,14 @@ pub enum LightningBalance {
 		channel_id: ChannelId,
 		/// The identifier of our channel counterparty.
 		counterparty_node_id: PublicKey,
+		/// The funding transaction output of the channel this balance belongs to.
+		funding_txo: OutPoint,
+		/// The outputs of the channel's on-chain transactions we're watching to claim funds from,
+		/// e.g., the HTLC outputs of a confirmed commitment transaction.
+		///
+		/// Note that these aren't attributed to individual balances. Moreover, outputs only we can
+		/// spend, such as our own delayed output, aren't watched and hence not listed.
+		claim_outpoints: Vec<OutPoint>,
 		/// The amount potentially available to claim, in satoshis, excluding the on-chain fees
 		/// which will be required to do so.
 		amount_satoshis: u64,
@@ -211,6 +255,14 @@ pub enum LightningBalance {
 		channel_id: ChannelId,
 		/// The identifier of our channel counterparty.
 		counterparty_node_id: PublicKey,
+		/// The funding transaction output of the channel this balance belongs to.
+		funding_txo: OutPoint,
+		/// The outputs of the channel's on-chain transactions we're watching to claim funds from,
+		/// e.g., the HTLC outputs of a confirmed commitment transaction.
+		///
+		/// Note that these aren't attributed to individual balances. Moreover, outputs only we can
+		/// spend, such as our own delayed output, aren't watched and hence not listed.
+		claim_outpoints: Vec<OutPoint>,
 		/// The amount, in satoshis, of the output which we can claim.
 		amount_satoshis: u64,
 	},
@@ -218,7 +270,8 @@ pub enum LightningBalance {
 
 impl LightningBalance {
 	pub(crate) fn from_ldk_balance(
-		channel_id: ChannelId, counterparty_node_id: PublicKey, balance: LdkBalance,
+		channel_id: ChannelId, counterparty_node_id: PublicKey, funding_txo: OutPoint,
+		claim_outpoints: Vec<OutPoint>, balance: LdkBalance,
 	) -> Self {
 		match balance {
 			LdkBalance::ClaimableOnChannelClose {
@@ -235,6 +288,7 @@ impl LightningBalance {
 				Self::ClaimableOnChannelClose {
 					channel_id,
 					counterparty_node_id,
+					funding_txo,
 					amount_satoshis: balance.amount_satoshis,
 					transaction_fee_satoshis: balance.transaction_fee_satoshis,
 					outbound_payment_htlc_rounded_msat,
@@ -250,6 +304,8 @@ impl LightningBalance {
 			} => Self::ClaimableAwaitingConfirmations {
 				channel_id,
 				counterparty_node_id,
+				funding_txo,
+				claim_outpoints,
 				amount_satoshis,
 				confirmation_height,
 				source,
@@ -262,6 +318,8 @@ impl LightningBalance {
 			} => Self::ContentiousClaimable {
 				channel_id,
 				counterparty_node_id,
+				funding_txo,
+				claim_outpoints,
 				amount_satoshis,
 				timeout_height,
 				payment_hash,
@@ -275,6 +333,8 @@ impl LightningBalance {
 			} => Self::MaybeTimeoutClaimableHTLC {
 				channel_id,
 				counterparty_node_id,
+				funding_txo,
+				claim_outpoints,
 				amount_satoshis,
 				claimable_height,
 				payment_hash,
@@ -287,6 +347,8 @@ impl LightningBalance {
 			} => Self::MaybePreimageClaimableHTLC {
 				channel_id,
 				counterparty_node_id,
+				funding_txo,
+				claim_outpoints,
 				amount_satoshis,
 				expiry_height,
 				payment_hash,
@@ -295,6 +357,8 @@ impl LightningBalance {
 				Self::CounterpartyRevokedOutputClaimable {
 					channel_id,
 					counterparty_node_id,
+					funding_txo,
+					claim_outpoints,
 					amount_satoshis,
 				}
 			},
@@ -388,6 +452,49 @@ impl PendingSweepBalance {
 	}
 }
 
+/// Details about an output recovered from a closed channel that is awaiting its sweep, as
+/// returned by [`Node::pending_sweeps`].
+///
+/// [`Node::pending_sweeps`]: crate::Node::pending_sweeps
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingSweep {
+	/// The identifier of the channel the output was recovered from.
+	pub channel_id: Option<ChannelId>,
+	/// The outpoint of the output to be swept.
+	pub outpoint: OutPoint,
+	/// The amount, in satoshis, of the output to be swept.
+	pub amount_satoshis: u64,
+	/// The block height from which on the output may be swept.
+	///
+	/// Will be `None` if the output is already mature.
+	pub maturity_height: Option<u32>,
+	/// The identifier of the transaction spending the output we last broadcast.
+	///
+	/// Will be `None` if we have yet to broadcast a sweeping transaction, e.g., as the output is
+	/// still immature or as we're waiting for further outputs to batch the sweep with.
+	pub latest_spending_txid: Option<Txid>,
+}
+
+impl PendingSweep {
+	pub(crate) fn from_tracked_spendable_output(
+		output_info: TrackedSpendableOutput, cur_height: u32,
+	) -> Option<Self> {
+		let (maturity_height, latest_spending_txid) = match output_info.status {
+			OutputSpendStatus::PendingInitialBroadcast { delayed_until_height } => {
+				(delayed_until_height.filter(|height| *height > cur_height), None)
+			},
+			OutputSpendStatus::PendingFirstConfirmation { latest_spending_tx, .. } => {
+				(None, Some(latest_spending_tx.compute_txid()))
+			},
+			OutputSpendStatus::PendingThresholdConfirmations { .. } => return None,
+		};
+		let channel_id = output_info.channel_id;
+		let outpoint = output_info.descriptor.spendable_outpoint().into_bitcoin_outpoint();
+		let amount_satoshis = value_from_descriptor(&output_info.descriptor).to_sat();
+		Some(Self { channel_id, outpoint, amount_satoshis, maturity_height, latest_spending_txid })
+	}
+}
+
 fn value_from_descriptor(descriptor: &SpendableOutputDescriptor) -> Amount {
 	match &descriptor {
 		SpendableOutputDescriptor::StaticOutput { output, .. } => output.value,
@@ -19,7 +19,9 @@ use bip39::Mnemonic;
 use bitcoin::bip32::{ChildNumber, Xpriv};
 use bitcoin::secp256k1::PublicKey;
 use bitcoin::{BlockHash, Network};
+use lightning::chain::channelmonitor::ChannelMonitor;
 use lightning::chain::{chainmonitor, BestBlock, Watch};
+use lightning::events::bump_transaction::Wallet as LdkWallet;
 use lightning::io::Cursor;
 use lightning::ln::channelmanager::{self, ChainParameters, ChannelManagerReadArgs};
 use lightning::ln::msgs::{RoutingMessageHandler, SocketAddress};
@@ -31,7 +33,7 @@ use lightning::routing::scoring::{
 	CombinedScorer, ProbabilisticScorer, ProbabilisticScoringDecayParameters,
 	ProbabilisticScoringFeeParameters,
 };
-use lightning::sign::{EntropySource, NodeSigner};
+use lightning::sign::{EntropySource, InMemorySigner, NodeSigner};
 use lightning::util::persist::{
 	KVStoreSync, CHANNEL_MANAGER_PERSISTENCE_KEY, CHANNEL_MANAGER_PERSISTENCE_PRIMARY_NAMESPACE,
 	CHANNEL_MANAGER_PERSISTENCE_SECONDARY_NAMESPACE,
@@ -41,44 +43,68 @@ use lightning::util::sweep::OutputSweeper;
 use lightning_persister::fs_store::FilesystemStore;
 use vss_client::headers::{FixedHeaders, LnurlAuthToJwtProvider, VssHeaderProvider};
 
-use crate::chain::ChainSource;
+use crate::backup::StaticChannelBackup;
+use crate::chain::{parse_electrum_server_url, ChainSource};
 use crate::config::{
-	default_user_config, may_announce_channel, AnnounceError, AsyncPaymentsRole,
-	BitcoindRestClientConfig, Config, ElectrumSyncConfig, EsploraSyncConfig,
-	DEFAULT_ESPLORA_SERVER_URL, DEFAULT_LOG_FILENAME, DEFAULT_LOG_LEVEL, WALLET_KEYS_SEED_LEN,
+	default_user_config, is_cltv_expiry_delta_dangerously_low, is_routable_address,
+	is_valid_cltv_expiry_delta_config, may_announce_channel, AnnounceError, AsyncPaymentsRole,
+	BitcoindRestClientConfig, BitcoindSyncConfig, Config, ElectrumSyncConfig, EsploraSyncConfig,
+	TorConfig, DEFAULT_ESPLORA_SERVER_URL, DEFAULT_JSON_LOG_FILENAME, DEFAULT_LOG_FILENAME,
+	DEFAULT_LOG_LEVEL, WALLET_KEYS_SEED_LEN,
 };
 use crate::connection::ConnectionManager;
 use crate::event::EventQueue;
-use crate::fee_estimator::OnchainFeeEstimator;
+use crate::fee_estimator::{FeeEstimator, OnchainFeeEstimator};
 use crate::gossip::GossipSource;
+use crate::io::encrypted_store::{is_encrypted_value, EncryptedStore};
 use crate::io::sqlite_store::SqliteStore;
 use crate::io::utils::{
-	read_external_pathfinding_scores_from_cache, read_node_metrics, write_node_metrics,
+	read_default_offer, read_external_pathfinding_scores_from_cache, read_node_metrics,
+	read_peer_connection_policy, write_node_metrics,
 };
 use crate::io::vss_store::VssStore;
 use crate::io::{
-	self, PAYMENT_INFO_PERSISTENCE_PRIMARY_NAMESPACE, PAYMENT_INFO_PERSISTENCE_SECONDARY_NAMESPACE,
+	self, CLOSED_CHANNEL_INFO_PERSISTENCE_PRIMARY_NAMESPACE,
+	CLOSED_CHANNEL_INFO_PERSISTENCE_SECONDARY_NAMESPACE,
+	PAYMENT_INFO_PERSISTENCE_PRIMARY_NAMESPACE, PAYMENT_INFO_PERSISTENCE_SECONDARY_NAMESPACE,
+	RECURRING_PAYMENT_INFO_PERSISTENCE_PRIMARY_NAMESPACE,
+	RECURRING_PAYMENT_INFO_PERSISTENCE_SECONDARY_NAMESPACE, STORAGE_ENCRYPTION_CHECK_KEY,
+	STORAGE_ENCRYPTION_CHECK_PRIMARY_NAMESPACE, STORAGE_ENCRYPTION_CHECK_SECONDARY_NAMESPACE,
 };
+use crate::label_store::LabelStore;
 use crate::liquidity::{
 	LSPS1ClientConfig, LSPS2ClientConfig, LSPS2ServiceConfig, LiquiditySourceBuilder,
 };
-use crate::logger::{log_error, LdkLogger, LogLevel, LogWriter, Logger};
-use crate::message_handler::NodeCustomMessageHandler;
+use crate::logger::{
+	log_error, log_info, log_warn, LdkLogger, LogLevel, LogLevelFilter, LogWriter, Logger,
+};
+use crate::message_handler::{
+	NodeChannelMessageHandler, NodeCustomMessageHandler, NodeCustomOnionMessageHandler,
+	NodeRoutingMessageHandler,
+};
 use crate::payment::asynchronous::om_mailbox::OnionMessageMailbox;
+use crate::payment::claim_watchdog::ClaimDeadlineWatchdog;
+use crate::payment::idempotency::PaymentIdempotencyKeys;
 use crate::peer_store::PeerStore;
 use crate::runtime::Runtime;
+use crate::tor::OnionService;
 use crate::tx_broadcaster::TransactionBroadcaster;
 use crate::types::{
-	ChainMonitor, ChannelManager, DynStore, GossipSync, Graph, KeysManager, MessageRouter,
-	OnionMessenger, PaymentStore, PeerManager, Persister,
+	BumpTransactionEventHandler, ChainMonitor, ChannelManager, ClosedChannelStore,
+	DynMigratableStore, DynStore, Graph, KeysManager, MessageRouter, OnionMessenger, PaymentStore,
+	PeerManager, Persister, PowerMode, RecurringPaymentStore,
 };
+use crate::wallet::external::OnchainWalletInterface;
 use crate::wallet::persist::KVStoreWalletPersister;
 use crate::wallet::Wallet;
+use crate::watchtower::{WatchtowerClient, WatchtowerPersister};
 use crate::{Node, NodeMetrics};
 
 const VSS_HARDENED_CHILD_INDEX: u32 = 877;
+const STATIC_CHANNEL_BACKUP_HARDENED_CHILD_INDEX: u32 = 878;
 const VSS_LNURL_AUTH_HARDENED_CHILD_INDEX: u32 = 138;
 const LSPS_HARDENED_CHILD_INDEX: u32 = 577;
+const STORAGE_ENCRYPTION_HARDENED_CHILD_INDEX: u32 = 879;
 const PERSISTER_MAX_PENDING_UPDATES: u64 = 100;
 
 #[derive(Debug, Clone)]
@@ -95,12 +121,18 @@ enum ChainDataSourceConfig {
 	Bitcoind {
 		rpc_host: String,
 		rpc_port: u16,
-		rpc_user: String,
-		rpc_password: String,
+		rpc_auth: BitcoindRpcAuth,
+		sync_config: Option<BitcoindSyncConfig>,
 		rest_client_config: Option<BitcoindRestClientConfig>,
 	},
 }
 
+#[derive(Debug, Clone)]
+enum BitcoindRpcAuth {
+	UserPass { rpc_user: String, rpc_password: String },
+	CookieFile(String),
+}
+
 #[derive(Debug, Clone)]
 enum EntropySourceConfig {
 	SeedFile(String),
@@ -111,7 +143,7 @@ enum EntropySourceConfig {
 #[derive(Debug, Clone)]
 enum GossipSourceConfig {
 	P2PNetwork,
-	RapidGossipSync(String),
+	RapidGossipSync { server_url: String, p2p_gossip: bool },
 }
 
 #[derive(Debug, Clone)]
@@ -119,6 +151,25 @@ struct PathfindingScoresSyncConfig {
 	url: String,
 }
 
+#[derive(Debug, Clone)]
+struct WatchtowerConfig {
+	tower_uri: String,
+	tower_pubkey: PublicKey,
+}
+
+#[derive(Debug, Clone)]
+struct StaticChannelBackupConfig {
+	static_channel_backup: Vec<u8>,
+	broadcast_latest_commitments: bool,
+}
+
+#[derive(Debug, Clone)]
+struct VssStoreConfig {
+	base_url: String,
+	store_id: String,
+	headers: HashMap<String, String>,
+}
+
 #[derive(Debug, Clone, Default)]
 struct LiquiditySourceConfig {
 	// Act as an LSPS1 client connecting to the given service.
@@ -131,7 +182,16 @@ struct LiquiditySourceConfig {
 
 #[derive(Clone)]
 enum LogWriterConfig {
-	File { log_file_path: Option<String>, max_log_level: Option<LogLevel> },
+	File {
+		log_file_path: Option<String>,
+		max_log_level: Option<LogLevel>,
+		module_log_levels: Option<HashMap<String, LogLevel>>,
+	},
+	Json {
+		log_file_path: Option<String>,
+		max_log_level: Option<LogLevel>,
+		module_log_levels: Option<HashMap<String, LogLevel>>,
+	},
 	Log,
 	Custom(Arc<dyn LogWriter>),
 }
@@ -139,10 +199,17 @@ enum LogWriterConfig {
 impl std::fmt::Debug for LogWriterConfig {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		match self {
-			LogWriterConfig::File { max_log_level, log_file_path } => f
+			LogWriterConfig::File { max_log_level, log_file_path, module_log_levels } => f
 				.debug_struct("LogWriterConfig")
 				.field("max_log_level", max_log_level)
 				.field("log_file_path", log_file_path)
+				.field("module_log_levels", module_log_levels)
+				.finish(),
+			LogWriterConfig::Json { max_log_level, log_file_path, module_log_levels } => f
+				.debug_struct("LogWriterConfig::Json")
+				.field("max_log_level", max_log_level)
+				.field("log_file_path", log_file_path)
+				.field("module_log_levels", module_log_levels)
 				.finish(),
 			LogWriterConfig::Log => write!(f, "LogWriterConfig::Log"),
 			LogWriterConfig::Custom(_) => {
@@ -152,6 +219,28 @@ impl std::fmt::Debug for LogWriterConfig {
 	}
 }
 
+#[derive(Clone)]
+struct CustomFeeEstimatorConfig(Arc<dyn FeeEstimator>);
+
+impl std::fmt::Debug for CustomFeeEstimatorConfig {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_tuple("CustomFeeEstimatorConfig")
+			.field(&"<config internal to custom fee estimator>")
+			.finish()
+	}
+}
+
+#[derive(Clone)]
+struct ExternalOnchainWalletConfig(Arc<dyn OnchainWalletInterface>);
+
+impl std::fmt::Debug for ExternalOnchainWalletConfig {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_tuple("ExternalOnchainWalletConfig")
+			.field(&"<config internal to external on-chain wallet>")
+			.finish()
+	}
+}
+
 /// An error encountered during building a [`Node`].
 ///
 /// [`Node`]: crate::Node
@@ -171,6 +260,15 @@ pub enum BuildError {
 	InvalidAnnouncementAddresses,
 	/// The provided alias is invalid.
 	InvalidNodeAlias,
+	/// The given chain source URL is invalid, e.g., it uses an unsupported scheme.
+	InvalidChainSourceUrl,
+	/// The given Tor configuration is invalid, e.g., it's missing required options.
+	InvalidTorConfig,
+	/// The given Bitcoin Core RPC cookie file is invalid, e.g., is malformed, or could not be read.
+	InvalidRpcCookieFile,
+	/// The given static channel backup is invalid, e.g., it was created with a different seed, or
+	/// the node already has channel state.
+	InvalidStaticChannelBackup,
 	/// An attempt to setup a runtime has failed.
 	RuntimeSetupFailed,
 	/// We failed to read data from the [`KVStore`].
@@ -191,10 +289,28 @@ pub enum BuildError {
 	WalletSetupFailed,
 	/// We failed to setup the logger.
 	LoggerSetupFailed,
+	/// Both a filesystem and a JSON logger were configured, which are mutually exclusive.
+	ConflictingLoggers,
 	/// The given network does not match the node's previously configured network.
 	NetworkMismatch,
 	/// The role of the node in an asynchronous payments context is not compatible with the current configuration.
 	AsyncPaymentsConfigMismatch,
+	/// The given channel HTLC limits are invalid, e.g., are out of range.
+	InvalidChannelHtlcLimits,
+	/// The given feature configuration is invalid, e.g., enables a feature without its
+	/// prerequisite.
+	InvalidFeatureConfig,
+	/// The given sweep destination is invalid, e.g., is not valid for the configured network.
+	InvalidSweepDestination,
+	/// The given CLTV expiry deltas are invalid, e.g., are below the minimums enforced by LDK.
+	InvalidCltvExpiryDelta,
+	/// The given peer reconnection policy is invalid.
+	InvalidPeerReconnectPolicy,
+	/// The storage encryption key is missing or doesn't match the key the stored data was
+	/// encrypted with.
+	InvalidStorageEncryptionKey,
+	/// The existing data could not be migrated to encrypted storage.
+	StorageEncryptionMigrationFailed,
 }
 
 impl fmt::Display for BuildError {
@@ -219,7 +335,16 @@ impl fmt::Display for BuildError {
 			Self::KVStoreSetupFailed => write!(f, "Failed to setup KVStore."),
 			Self::WalletSetupFailed => write!(f, "Failed to setup onchain wallet."),
 			Self::LoggerSetupFailed => write!(f, "Failed to setup the logger."),
+			Self::ConflictingLoggers => {
+				write!(f, "Filesystem and JSON loggers are mutually exclusive.")
+			},
 			Self::InvalidNodeAlias => write!(f, "Given node alias is invalid."),
+			Self::InvalidChainSourceUrl => write!(f, "Given chain source URL is invalid."),
+			Self::InvalidTorConfig => write!(f, "Given Tor configuration is invalid."),
+			Self::InvalidRpcCookieFile => write!(f, "Given RPC cookie file is invalid."),
+			Self::InvalidStaticChannelBackup => {
+				write!(f, "Given static channel backup is invalid or cannot be applied.")
+			},
 			Self::NetworkMismatch => {
 				write!(f, "Given network does not match the node's previously configured network.")
 			},
@@ -229,6 +354,19 @@ impl fmt::Display for BuildError {
 					"The async payments role is not compatible with the current configuration."
 				)
 			},
+			Self::InvalidChannelHtlcLimits => write!(f, "Given channel HTLC limits are invalid."),
+			Self::InvalidFeatureConfig => write!(f, "Given feature configuration is invalid."),
+			Self::InvalidSweepDestination => write!(f, "Given sweep destination is invalid."),
+			Self::InvalidCltvExpiryDelta => write!(f, "Given CLTV expiry deltas are invalid."),
+			Self::InvalidPeerReconnectPolicy => {
+				write!(f, "Given peer reconnection policy is invalid.")
+			},
+			Self::InvalidStorageEncryptionKey => {
+				write!(f, "Given storage encryption key is missing or invalid.")
+			},
+			Self::StorageEncryptionMigrationFailed => {
+				write!(f, "Failed to migrate existing data to encrypted storage.")
+			},
 		}
 	}
 }
@@ -250,9 +388,17 @@ pub struct NodeBuilder {
 	gossip_source_config: Option<GossipSourceConfig>,
 	liquidity_source_config: Option<LiquiditySourceConfig>,
 	log_writer_config: Option<LogWriterConfig>,
+	conflicting_loggers: bool,
+	custom_fee_estimator_config: Option<CustomFeeEstimatorConfig>,
+	external_onchain_wallet_config: Option<ExternalOnchainWalletConfig>,
 	async_payments_role: Option<AsyncPaymentsRole>,
 	runtime_handle: Option<tokio::runtime::Handle>,
 	pathfinding_scores_sync_config: Option<PathfindingScoresSyncConfig>,
+	tor_config: Option<TorConfig>,
+	vss_store_config: Option<VssStoreConfig>,
+	sqlite_db_path: Option<PathBuf>,
+	static_channel_backup_config: Option<StaticChannelBackupConfig>,
+	watchtower_config: Option<WatchtowerConfig>,
 }
 
 impl NodeBuilder {
@@ -269,8 +415,15 @@ impl NodeBuilder {
 		let gossip_source_config = None;
 		let liquidity_source_config = None;
 		let log_writer_config = None;
+		let custom_fee_estimator_config = None;
+		let external_onchain_wallet_config = None;
 		let runtime_handle = None;
 		let pathfinding_scores_sync_config = None;
+		let tor_config = None;
+		let vss_store_config = None;
+		let sqlite_db_path = None;
+		let static_channel_backup_config = None;
+		let watchtower_config = None;
 		Self {
 			config,
 			entropy_source_config,
@@ -278,9 +431,17 @@ impl NodeBuilder {
 			gossip_source_config,
 			liquidity_source_config,
 			log_writer_config,
+			conflicting_loggers: false,
+			custom_fee_estimator_config,
+			external_onchain_wallet_config,
 			runtime_handle,
 			async_payments_role: None,
 			pathfinding_scores_sync_config,
+			tor_config,
+			vss_store_config,
+			sqlite_db_path,
+			static_channel_backup_config,
+			watchtower_config,
 		}
 	}
 
@@ -312,7 +473,13 @@ impl NodeBuilder {
 
 	/// Configures the [`Node`] instance to source its wallet entropy from a [BIP 39] mnemonic.
 	///
+	/// A [`Mnemonic`] can be parsed from a 12- to 24-word phrase via [`parse_entropy_mnemonic`],
+	/// which validates the words and checksum. New mnemonics can be generated via
+	/// [`generate_entropy_mnemonic`].
+	///
 	/// [BIP 39]: https://github.com/bitcoin/bips/blob/master/bip-0039.mediawiki
+	/// [`parse_entropy_mnemonic`]: crate::parse_entropy_mnemonic
+	/// [`generate_entropy_mnemonic`]: crate::generate_entropy_mnemonic
 	pub fn set_entropy_bip39_mnemonic(
 		&mut self, mnemonic: Mnemonic, passphrase: Option<String>,
 	) -> &mut Self {
@@ -354,6 +521,10 @@ impl NodeBuilder {
 
 	/// Configures the [`Node`] instance to source its chain data from the given Electrum server.
 	///
+	/// The `server_url` needs to include the port and may use an `ssl://` (or `tls://`) scheme for
+	/// TLS endpoints or a `tcp://` scheme for plaintext endpoints. If no scheme is given, a
+	/// plaintext endpoint is assumed.
+	///
 	/// If no `sync_config` is given, default values are used. See [`ElectrumSyncConfig`] for more
 	/// information.
 	pub fn set_chain_source_electrum(
@@ -369,17 +540,51 @@ impl NodeBuilder {
 	/// This method establishes an RPC connection that enables all essential chain operations including
 	/// transaction broadcasting and chain data synchronization.
 	///
+	/// Blocks are retrieved via `getblockheader`/`getblock`, fee rates are estimated via
+	/// `estimatesmartfee`, and transactions are broadcast via `sendrawtransaction`.
+	///
 	/// ## Parameters:
 	/// * `rpc_host`, `rpc_port`, `rpc_user`, `rpc_password` - Required parameters for the Bitcoin Core RPC
 	///   connection.
+	/// * `sync_config` - If no `sync_config` is given, default values are used. See
+	///   [`BitcoindSyncConfig`] for more information.
 	pub fn set_chain_source_bitcoind_rpc(
 		&mut self, rpc_host: String, rpc_port: u16, rpc_user: String, rpc_password: String,
+		sync_config: Option<BitcoindSyncConfig>,
 	) -> &mut Self {
 		self.chain_data_source_config = Some(ChainDataSourceConfig::Bitcoind {
 			rpc_host,
 			rpc_port,
-			rpc_user,
-			rpc_password,
+			rpc_auth: BitcoindRpcAuth::UserPass { rpc_user, rpc_password },
+			sync_config,
+			rest_client_config: None,
+		});
+		self
+	}
+
+	/// Configures the [`Node`] instance to connect to a Bitcoin Core node via RPC, authenticating
+	/// via the given cookie file.
+	///
+	/// This is equivalent to [`Self::set_chain_source_bitcoind_rpc`], but reads the RPC credentials
+	/// from the cookie file Bitcoin Core creates in its data directory (`.cookie`, by default).
+	///
+	/// **Note:** The cookie file is read when the node is built. As Bitcoin Core recreates the
+	/// cookie on every restart, the node needs to be rebuilt after Bitcoin Core was restarted.
+	///
+	/// ## Parameters:
+	/// * `rpc_host`, `rpc_port` - Required parameters for the Bitcoin Core RPC connection.
+	/// * `rpc_cookie_file_path` - The path to Bitcoin Core's RPC cookie file.
+	/// * `sync_config` - If no `sync_config` is given, default values are used. See
+	///   [`BitcoindSyncConfig`] for more information.
+	pub fn set_chain_source_bitcoind_rpc_with_cookie(
+		&mut self, rpc_host: String, rpc_port: u16, rpc_cookie_file_path: String,
+		sync_config: Option<BitcoindSyncConfig>,
+	) -> &mut Self {
+		self.chain_data_source_config = Some(ChainDataSourceConfig::Bitcoind {
+			rpc_host,
+			rpc_port,
+			rpc_auth: BitcoindRpcAuth::CookieFile(rpc_cookie_file_path),
+			sync_config,
 			rest_client_config: None,
 		});
 		self
@@ -394,15 +599,17 @@ impl NodeBuilder {
 	/// * `rest_host`, `rest_port` - Required parameters for the Bitcoin Core REST connection.
 	/// * `rpc_host`, `rpc_port`, `rpc_user`, `rpc_password` - Required parameters for the Bitcoin Core RPC
 	///   connection
+	/// * `sync_config` - Optional synchronization configuration. If `None`, defaults are used. See
+	///   [`BitcoindSyncConfig`] for more information.
 	pub fn set_chain_source_bitcoind_rest(
 		&mut self, rest_host: String, rest_port: u16, rpc_host: String, rpc_port: u16,
-		rpc_user: String, rpc_password: String,
+		rpc_user: String, rpc_password: String, sync_config: Option<BitcoindSyncConfig>,
 	) -> &mut Self {
 		self.chain_data_source_config = Some(ChainDataSourceConfig::Bitcoind {
 			rpc_host,
 			rpc_port,
-			rpc_user,
-			rpc_password,
+			rpc_auth: BitcoindRpcAuth::UserPass { rpc_user, rpc_password },
+			sync_config,
 			rest_client_config: Some(BitcoindRestClientConfig { rest_host, rest_port }),
 		});
 
@@ -419,7 +626,24 @@ impl NodeBuilder {
 	/// Configures the [`Node`] instance to source its gossip data from the given RapidGossipSync
 	/// server.
 	pub fn set_gossip_source_rgs(&mut self, rgs_server_url: String) -> &mut Self {
-		self.gossip_source_config = Some(GossipSourceConfig::RapidGossipSync(rgs_server_url));
+		self.gossip_source_config = Some(GossipSourceConfig::RapidGossipSync {
+			server_url: rgs_server_url,
+			p2p_gossip: false,
+		});
+		self
+	}
+
+	/// Configures the [`Node`] instance to bootstrap its gossip data from the given
+	/// RapidGossipSync server, while also receiving gossip updates from the Lightning
+	/// peer-to-peer network.
+	///
+	/// This allows to quickly have a usable network graph available after startup, while staying
+	/// up-to-date in-between RapidGossipSync snapshots.
+	pub fn set_gossip_source_rgs_and_p2p(&mut self, rgs_server_url: String) -> &mut Self {
+		self.gossip_source_config = Some(GossipSourceConfig::RapidGossipSync {
+			server_url: rgs_server_url,
+			p2p_gossip: true,
+		});
 		self
 	}
 
@@ -494,6 +718,94 @@ impl NodeBuilder {
 		self
 	}
 
+	/// Configures the [`Node`] instance to persist its data to the given [VSS] backend when
+	/// calling [`build`].
+	///
+	/// The given `headers` are included as is in all requests made to VSS, typically used for
+	/// authentication purposes. See [`build_with_vss_store_and_fixed_headers`] for more
+	/// information.
+	///
+	/// [VSS]: https://github.com/lightningdevkit/vss-server/blob/main/README.md
+	/// [`build`]: Self::build
+	/// [`build_with_vss_store_and_fixed_headers`]: Self::build_with_vss_store_and_fixed_headers
+	pub fn set_storage_vss(
+		&mut self, base_url: String, store_id: String, headers: HashMap<String, String>,
+	) -> &mut Self {
+		self.vss_store_config = Some(VssStoreConfig { base_url, store_id, headers });
+		self.sqlite_db_path = None;
+		self
+	}
+
+	/// Configures the [`Node`] instance to persist its data to the SQLite database file at the
+	/// given `db_path` when calling [`build`].
+	///
+	/// If not set, the database is kept in [`SQLITE_DB_FILE_NAME`] in the configured
+	/// [`Config::storage_dir_path`]. The database file and its schema are created on first use.
+	///
+	/// Data of a node previously built via [`build_with_fs_store`] can be moved over via
+	/// [`SqliteStore::migrate_from_filesystem`] before building.
+	///
+	/// [`build`]: Self::build
+	/// [`build_with_fs_store`]: Self::build_with_fs_store
+	/// [`SQLITE_DB_FILE_NAME`]: io::sqlite_store::SQLITE_DB_FILE_NAME
+	pub fn set_storage_sqlite(&mut self, db_path: String) -> &mut Self {
+		self.sqlite_db_path = Some(db_path.into());
+		self.vss_store_config = None;
+		self
+	}
+
+	/// Configures the [`Node`] instance to recover funds from the given static channel backup
+	/// (SCB), as previously created via [`Node::export_static_channel_backup`].
+	///
+	/// This is a last-resort recovery mechanism and may only be used on a fresh node that is
+	/// configured with the same seed as the node that created the backup. On build, we'll restore
+	/// the backed-up channel monitors and reconnect to the channel counterparties which will then
+	/// be requested to force-close the channels. Once the counterparties' commitment transactions
+	/// confirm, the funds are swept to the on-chain wallet.
+	///
+	/// If `broadcast_latest_commitments` is set, we'll additionally broadcast the latest
+	/// commitment transactions known to the backed-up channel monitors, which allows recovering
+	/// funds even if the counterparties are unreachable.
+	///
+	/// **Caution**: Recovering from a static channel backup forfeits the ability to continue using
+	/// the backed-up channels, it only recovers the funds. Only ever set
+	/// `broadcast_latest_commitments` if you're certain the backup is the most recent one, as
+	/// broadcasting an outdated commitment transaction allows the counterparty to claim all funds
+	/// of the respective channel.
+	///
+	/// Building will fail with [`BuildError::InvalidStaticChannelBackup`] if the backup can't be
+	/// decrypted with our seed or if the node already has channel state.
+	pub fn recover_from_static_channel_backup(
+		&mut self, static_channel_backup: Vec<u8>, broadcast_latest_commitments: bool,
+	) -> &mut Self {
+		self.static_channel_backup_config =
+			Some(StaticChannelBackupConfig { static_channel_backup, broadcast_latest_commitments });
+		self
+	}
+
+	/// Configures the [`Node`] instance to back up its channel states to the watchtower reachable
+	/// at the given `tower_uri`.
+	///
+	/// The tower needs to speak the HTTP API of [The Eye of Satoshi], with which we register
+	/// using our node ID before handing it any channel states. Whenever our counterparty revokes
+	/// one of their commitment transactions, we hand the tower an encrypted justice transaction
+	/// allowing it to claim the channel's `to_local` output on our behalf, should the counterparty
+	/// broadcast the revoked state while our node is offline. The tower will only be able to
+	/// decrypt the justice transaction once the revoked state hits the chain.
+	///
+	/// The tower's receipts are authenticated against the given `tower_pubkey`. Channel states are
+	/// delivered in the background and are queued for later delivery if the tower is unavailable,
+	/// i.e., channel operations are never blocked on the tower.
+	///
+	/// **Note:** Only channel states revoked after the tower was configured are backed up. HTLC
+	/// outputs of revoked states are not covered.
+	///
+	/// [The Eye of Satoshi]: https://github.com/talaia-labs/rust-teos
+	pub fn set_watchtower(&mut self, tower_uri: String, tower_pubkey: PublicKey) -> &mut Self {
+		self.watchtower_config = Some(WatchtowerConfig { tower_uri, tower_pubkey });
+		self
+	}
+
 	/// Configures the [`Node`] instance to write logs to the filesystem.
 	///
 	/// The `log_file_path` defaults to [`DEFAULT_LOG_FILENAME`] in the configured
@@ -502,23 +814,97 @@ impl NodeBuilder {
 	/// If set, the `max_log_level` sets the maximum log level. Otherwise, the latter defaults to
 	/// [`DEFAULT_LOG_LEVEL`].
 	///
+	/// If set, the `module_log_levels` override the maximum log level for records whose target,
+	/// i.e., module path, starts with the given module prefix, e.g., `ldk_node::liquidity` or
+	/// `lightning::routing`. If multiple prefixes match, the most specific one takes precedence.
+	///
 	/// [`DEFAULT_LOG_FILENAME`]: crate::config::DEFAULT_LOG_FILENAME
 	pub fn set_filesystem_logger(
 		&mut self, log_file_path: Option<String>, max_log_level: Option<LogLevel>,
+		module_log_levels: Option<HashMap<String, LogLevel>>,
+	) -> &mut Self {
+		if matches!(self.log_writer_config, Some(LogWriterConfig::Json { .. })) {
+			self.conflicting_loggers = true;
+		}
+		self.log_writer_config =
+			Some(LogWriterConfig::File { log_file_path, max_log_level, module_log_levels });
+		self
+	}
+
+	/// Configures the [`Node`] instance to write logs to the filesystem as JSON, emitting one
+	/// object per line.
+	///
+	/// Each object holds the `timestamp`, `level`, `target`, `line`, and `message` of the log
+	/// record, as well as the structured `peer_id`, `channel_id`, and `payment_hash` fields if they
+	/// are known.
+	///
+	/// The `log_file_path` defaults to [`DEFAULT_JSON_LOG_FILENAME`] in the configured
+	/// [`Config::storage_dir_path`] if set to `None`.
+	///
+	/// If set, the `max_log_level` sets the maximum log level. Otherwise, the latter defaults to
+	/// [`DEFAULT_LOG_LEVEL`].
+	///
+	/// If set, the `module_log_levels` override the maximum log level for records whose target,
+	/// i.e., module path, starts with the given module prefix, e.g., `ldk_node::liquidity` or
+	/// `lightning::routing`. If multiple prefixes match, the most specific one takes precedence.
+	///
+	/// Note that this is mutually exclusive with [`Self::set_filesystem_logger`]. If both are set,
+	/// building the node will fail with [`BuildError::ConflictingLoggers`].
+	///
+	/// [`DEFAULT_JSON_LOG_FILENAME`]: crate::config::DEFAULT_JSON_LOG_FILENAME
+	pub fn set_json_logger(
+		&mut self, log_file_path: Option<String>, max_log_level: Option<LogLevel>,
+		module_log_levels: Option<HashMap<String, LogLevel>>,
 	) -> &mut Self {
-		self.log_writer_config = Some(LogWriterConfig::File { log_file_path, max_log_level });
+		if matches!(self.log_writer_config, Some(LogWriterConfig::File { .. })) {
+			self.conflicting_loggers = true;
+		}
+		self.log_writer_config =
+			Some(LogWriterConfig::Json { log_file_path, max_log_level, module_log_levels });
 		self
 	}
 
 	/// Configures the [`Node`] instance to write logs to the [`log`](https://crates.io/crates/log) facade.
 	pub fn set_log_facade_logger(&mut self) -> &mut Self {
 		self.log_writer_config = Some(LogWriterConfig::Log);
+		self.conflicting_loggers = false;
 		self
 	}
 
 	/// Configures the [`Node`] instance to write logs to the provided custom [`LogWriter`].
 	pub fn set_custom_logger(&mut self, log_writer: Arc<dyn LogWriter>) -> &mut Self {
 		self.log_writer_config = Some(LogWriterConfig::Custom(log_writer));
+		self.conflicting_loggers = false;
+		self
+	}
+
+	/// Configures the [`Node`] instance to source fee rate estimates from the provided custom
+	/// [`FeeEstimator`].
+	///
+	/// If set, the fee rate estimates will no longer be retrieved from the configured chain data
+	/// source. The custom estimator's values will still be cached and can be retrieved via
+	/// [`Node::current_fee_rate`].
+	///
+	/// Note that any fee rate overrides set via [`Node::set_fee_rate_override`] still take precedence
+	/// over the custom estimator's values.
+	pub fn set_custom_fee_estimator(&mut self, fee_estimator: Arc<dyn FeeEstimator>) -> &mut Self {
+		self.custom_fee_estimator_config = Some(CustomFeeEstimatorConfig(fee_estimator));
+		self
+	}
+
+	/// Configures the [`Node`] instance to fund its channels from, and send any funds recovered
+	/// from them to, the provided externally managed on-chain wallet.
+	///
+	/// This allows to run the node purely for its channels while an existing wallet owns the keys
+	/// to the on-chain funds. Please refer to [`OnchainWalletInterface`] for which operations are
+	/// routed through the external wallet, and when it needs to be available.
+	///
+	/// Note that the node's internal on-chain wallet is still used for on-chain payments via
+	/// [`Node::onchain_payment`].
+	pub fn set_external_onchain_wallet(
+		&mut self, external_wallet: Arc<dyn OnchainWalletInterface>,
+	) -> &mut Self {
+		self.external_onchain_wallet_config = Some(ExternalOnchainWalletConfig(external_wallet));
 		self
 	}
 
@@ -528,11 +914,17 @@ impl NodeBuilder {
 		self
 	}
 
-	/// Sets the IP address and TCP port on which [`Node`] will listen for incoming network connections.
+	/// Sets the addresses on which [`Node`] will listen for incoming network connections.
+	///
+	/// Any mix of IPv4, IPv6, and DNS hostname addresses is supported, allowing to run dual-stack
+	/// nodes. Hostnames are resolved on startup, and the node will listen on all of the resolved
+	/// addresses. If announcing, all addresses will be included in our node announcement.
+	///
+	/// Returns an error if more than 100 addresses are given or the list contains duplicates.
 	pub fn set_listening_addresses(
 		&mut self, listening_addresses: Vec<SocketAddress>,
 	) -> Result<&mut Self, BuildError> {
-		if listening_addresses.len() > 100 {
+		if listening_addresses.len() > 100 || has_duplicate_addresses(&listening_addresses) {
 			return Err(BuildError::InvalidListeningAddresses);
 		}
 
@@ -548,7 +940,7 @@ impl NodeBuilder {
 	pub fn set_announcement_addresses(
 		&mut self, announcement_addresses: Vec<SocketAddress>,
 	) -> Result<&mut Self, BuildError> {
-		if announcement_addresses.len() > 100 {
+		if announcement_addresses.len() > 100 || has_duplicate_addresses(&announcement_addresses) {
 			return Err(BuildError::InvalidAnnouncementAddresses);
 		}
 
@@ -556,6 +948,27 @@ impl NodeBuilder {
 		Ok(self)
 	}
 
+	/// Configures the [`Node`] instance to connect to peers via Tor.
+	///
+	/// Connections to onion peer addresses will be routed through the configured SOCKS5 proxy,
+	/// while connections to other peer addresses are established directly.
+	///
+	/// If [`TorConfig::hidden_service`] is set, we'll publish an onion service forwarding to the
+	/// port of our first listening address on `127.0.0.1` via the Tor control port, and include
+	/// its address in our node announcements. If Tor is unavailable, we'll keep retrying in the
+	/// background rather than failing to start.
+	///
+	/// This will return an error if [`TorConfig::hidden_service`] is set but no
+	/// [`TorConfig::control_port`] is given.
+	pub fn set_tor_config(&mut self, tor_config: TorConfig) -> Result<&mut Self, BuildError> {
+		if tor_config.hidden_service && tor_config.control_port.is_none() {
+			return Err(BuildError::InvalidTorConfig);
+		}
+
+		self.tor_config = Some(tor_config);
+		Ok(self)
+	}
+
 	/// Sets the node alias that will be used when broadcasting announcements to the gossip
 	/// network.
 	///
@@ -567,6 +980,17 @@ impl NodeBuilder {
 		Ok(self)
 	}
 
+	/// Sets the RGB color that will be used when broadcasting announcements to the gossip
+	/// network.
+	///
+	/// A color may be parsed from its hex representation via [`parse_node_color`].
+	///
+	/// [`parse_node_color`]: crate::config::parse_node_color
+	pub fn set_node_color(&mut self, node_color: [u8; 3]) -> &mut Self {
+		self.config.node_color = node_color;
+		self
+	}
+
 	/// Sets the role of the node in an asynchronous payments context.
 	///
 	/// See <https://github.com/lightning/bolts/pull/1149> for more information about the async payments protocol.
@@ -584,19 +1008,47 @@ impl NodeBuilder {
 
 	/// Builds a [`Node`] instance with a [`SqliteStore`] backend and according to the options
 	/// previously configured.
+	///
+	/// If a [VSS] backend was configured via [`set_storage_vss`], it will be used instead. The
+	/// location of the SQLite database can be configured via [`set_storage_sqlite`].
+	///
+	/// [VSS]: https://github.com/lightningdevkit/vss-server/blob/main/README.md
+	/// [`set_storage_vss`]: Self::set_storage_vss
+	/// [`set_storage_sqlite`]: Self::set_storage_sqlite
 	pub fn build(&self) -> Result<Node, BuildError> {
-		let storage_dir_path = self.config.storage_dir_path.clone();
-		fs::create_dir_all(storage_dir_path.clone())
-			.map_err(|_| BuildError::StoragePathAccessFailed)?;
+		if let Some(vss_store_config) = self.vss_store_config.as_ref() {
+			return self.build_with_vss_store_and_fixed_headers(
+				vss_store_config.base_url.clone(),
+				vss_store_config.store_id.clone(),
+				vss_store_config.headers.clone(),
+			);
+		}
+
+		let (data_dir, db_file_name) = match self.sqlite_db_path.as_ref() {
+			Some(db_path) => {
+				let db_file_name = db_path
+					.file_name()
+					.ok_or(BuildError::StoragePathAccessFailed)?
+					.to_string_lossy()
+					.into_owned();
+				let data_dir = db_path.parent().map(|p| p.to_path_buf()).unwrap_or_default();
+				(data_dir, db_file_name)
+			},
+			None => (
+				self.config.storage_dir_path.clone().into(),
+				io::sqlite_store::SQLITE_DB_FILE_NAME.to_string(),
+			),
+		};
+		fs::create_dir_all(data_dir.clone()).map_err(|_| BuildError::StoragePathAccessFailed)?;
 		let kv_store = Arc::new(
 			SqliteStore::new(
-				storage_dir_path.into(),
-				Some(io::sqlite_store::SQLITE_DB_FILE_NAME.to_string()),
+				data_dir,
+				Some(db_file_name),
 				Some(io::sqlite_store::KV_TABLE_NAME.to_string()),
 			)
 			.map_err(|_| BuildError::KVStoreSetupFailed)?,
 		);
-		self.build_with_store(kv_store)
+		self.build_with_migratable_store(kv_store.clone(), Some(kv_store))
 	}
 
 	/// Builds a [`Node`] instance with a [`FilesystemStore`] backend and according to the options
@@ -608,7 +1060,7 @@ impl NodeBuilder {
 		fs::create_dir_all(storage_dir_path.clone())
 			.map_err(|_| BuildError::StoragePathAccessFailed)?;
 		let kv_store = Arc::new(FilesystemStore::new(storage_dir_path));
-		self.build_with_store(kv_store)
+		self.build_with_migratable_store(kv_store.clone(), Some(kv_store))
 	}
 
 	/// Builds a [`Node`] instance with a [VSS] backend and according to the options
@@ -634,7 +1086,7 @@ impl NodeBuilder {
 	) -> Result<Node, BuildError> {
 		use bitcoin::key::Secp256k1;
 
-		let logger = setup_logger(&self.log_writer_config, &self.config)?;
+		let logger = setup_logger(&self.log_writer_config, self.conflicting_loggers, &self.config)?;
 
 		let seed_bytes = seed_bytes_from_config(
 			&self.config,
@@ -703,7 +1155,7 @@ impl NodeBuilder {
 	pub fn build_with_vss_store_and_header_provider(
 		&self, vss_url: String, store_id: String, header_provider: Arc<dyn VssHeaderProvider>,
 	) -> Result<Node, BuildError> {
-		let logger = setup_logger(&self.log_writer_config, &self.config)?;
+		let logger = setup_logger(&self.log_writer_config, self.conflicting_loggers, &self.config)?;
 
 		let runtime = if let Some(handle) = self.runtime_handle.as_ref() {
 			Arc::new(Runtime::with_handle(handle.clone(), Arc::clone(&logger)))
@@ -731,25 +1183,47 @@ impl NodeBuilder {
 
 		let vss_seed_bytes: [u8; 32] = vss_xprv.private_key.secret_bytes();
 
-		let vss_store =
-			VssStore::new(vss_url, store_id, vss_seed_bytes, header_provider, Arc::clone(&runtime));
+		let vss_store = Arc::new(VssStore::new(
+			vss_url,
+			store_id,
+			vss_seed_bytes,
+			header_provider,
+			Arc::clone(&runtime),
+		));
 		build_with_store_internal(
 			config,
 			self.chain_data_source_config.as_ref(),
 			self.gossip_source_config.as_ref(),
 			self.liquidity_source_config.as_ref(),
 			self.pathfinding_scores_sync_config.as_ref(),
+			self.tor_config.as_ref(),
 			self.async_payments_role,
+			self.static_channel_backup_config.as_ref(),
+			self.watchtower_config.as_ref(),
+			self.custom_fee_estimator_config.as_ref().map(|c| Arc::clone(&c.0)),
+			self.external_onchain_wallet_config.as_ref().map(|c| Arc::clone(&c.0)),
 			seed_bytes,
 			runtime,
 			logger,
-			Arc::new(vss_store),
+			Some(Arc::clone(&vss_store)),
+			Some(vss_store.clone()),
+			vss_store,
 		)
 	}
 
 	/// Builds a [`Node`] instance according to the options previously configured.
+	///
+	/// Note that as we can't enumerate the keys of custom stores, enabling
+	/// [`Config::encrypt_storage`] for a custom store that was previously used without encryption
+	/// will fail with [`BuildError::StorageEncryptionMigrationFailed`].
 	pub fn build_with_store(&self, kv_store: Arc<DynStore>) -> Result<Node, BuildError> {
-		let logger = setup_logger(&self.log_writer_config, &self.config)?;
+		self.build_with_migratable_store(kv_store, None)
+	}
+
+	fn build_with_migratable_store(
+		&self, kv_store: Arc<DynStore>, migratable_store: Option<Arc<DynMigratableStore>>,
+	) -> Result<Node, BuildError> {
+		let logger = setup_logger(&self.log_writer_config, self.conflicting_loggers, &self.config)?;
 
 		let runtime = if let Some(handle) = self.runtime_handle.as_ref() {
 			Arc::new(Runtime::with_handle(handle.clone(), Arc::clone(&logger)))
@@ -773,10 +1247,17 @@ impl NodeBuilder {
 			self.gossip_source_config.as_ref(),
 			self.liquidity_source_config.as_ref(),
 			self.pathfinding_scores_sync_config.as_ref(),
+			self.tor_config.as_ref(),
 			self.async_payments_role,
+			self.static_channel_backup_config.as_ref(),
+			self.watchtower_config.as_ref(),
+			self.custom_fee_estimator_config.as_ref().map(|c| Arc::clone(&c.0)),
+			self.external_onchain_wallet_config.as_ref().map(|c| Arc::clone(&c.0)),
 			seed_bytes,
 			runtime,
 			logger,
+			None,
+			migratable_store,
 			kv_store,
 		)
 	}
@@ -870,6 +1351,10 @@ impl ArcedNodeBuilder {
 
 	/// Configures the [`Node`] instance to source its chain data from the given Electrum server.
 	///
+	/// The `server_url` needs to include the port and may use an `ssl://` (or `tls://`) scheme for
+	/// TLS endpoints or a `tcp://` scheme for plaintext endpoints. If no scheme is given, a
+	/// plaintext endpoint is assumed.
+	///
 	/// If no `sync_config` is given, default values are used. See [`ElectrumSyncConfig`] for more
 	/// information.
 	pub fn set_chain_source_electrum(
@@ -883,17 +1368,50 @@ impl ArcedNodeBuilder {
 	/// This method establishes an RPC connection that enables all essential chain operations including
 	/// transaction broadcasting and chain data synchronization.
 	///
+	/// Blocks are retrieved via `getblockheader`/`getblock`, fee rates are estimated via
+	/// `estimatesmartfee`, and transactions are broadcast via `sendrawtransaction`.
+	///
 	/// ## Parameters:
 	/// * `rpc_host`, `rpc_port`, `rpc_user`, `rpc_password` - Required parameters for the Bitcoin Core RPC
 	///   connection.
+	/// * `sync_config` - If no `sync_config` is given, default values are used. See
+	///   [`BitcoindSyncConfig`] for more information.
 	pub fn set_chain_source_bitcoind_rpc(
 		&self, rpc_host: String, rpc_port: u16, rpc_user: String, rpc_password: String,
+		sync_config: Option<BitcoindSyncConfig>,
 	) {
 		self.inner.write().unwrap().set_chain_source_bitcoind_rpc(
 			rpc_host,
 			rpc_port,
 			rpc_user,
 			rpc_password,
+			sync_config,
+		);
+	}
+
+	/// Configures the [`Node`] instance to connect to a Bitcoin Core node via RPC, authenticating
+	/// via the given cookie file.
+	///
+	/// This is equivalent to [`Self::set_chain_source_bitcoind_rpc`], but reads the RPC credentials
+	/// from the cookie file Bitcoin Core creates in its data directory (`.cookie`, by default).
+	///
+	/// **Note:** The cookie file is read when the node is built. As Bitcoin Core recreates the
+	/// cookie on every restart, the node needs to be rebuilt after Bitcoin Core was restarted.
+	///
+	/// ## Parameters:
+	/// * `rpc_host`, `rpc_port` - Required parameters for the Bitcoin Core RPC connection.
+	/// * `rpc_cookie_file_path` - The path to Bitcoin Core's RPC cookie file.
+	/// * `sync_config` - If no `sync_config` is given, default values are used. See
+	///   [`BitcoindSyncConfig`] for more information.
+	pub fn set_chain_source_bitcoind_rpc_with_cookie(
+		&self, rpc_host: String, rpc_port: u16, rpc_cookie_file_path: String,
+		sync_config: Option<BitcoindSyncConfig>,
+	) {
+		self.inner.write().unwrap().set_chain_source_bitcoind_rpc_with_cookie(
+			rpc_host,
+			rpc_port,
+			rpc_cookie_file_path,
+			sync_config,
 		);
 	}
 
@@ -906,9 +1424,11 @@ impl ArcedNodeBuilder {
 	/// * `rest_host`, `rest_port` - Required parameters for the Bitcoin Core REST connection.
 	/// * `rpc_host`, `rpc_port`, `rpc_user`, `rpc_password` - Required parameters for the Bitcoin Core RPC
 	///   connection
+	/// * `sync_config` - Optional synchronization configuration. If `None`, defaults are used. See
+	///   [`BitcoindSyncConfig`] for more information.
 	pub fn set_chain_source_bitcoind_rest(
 		&self, rest_host: String, rest_port: u16, rpc_host: String, rpc_port: u16,
-		rpc_user: String, rpc_password: String,
+		rpc_user: String, rpc_password: String, sync_config: Option<BitcoindSyncConfig>,
 	) {
 		self.inner.write().unwrap().set_chain_source_bitcoind_rest(
 			rest_host,
@@ -917,6 +1437,7 @@ impl ArcedNodeBuilder {
 			rpc_port,
 			rpc_user,
 			rpc_password,
+			sync_config,
 		);
 	}
 
@@ -932,6 +1453,16 @@ impl ArcedNodeBuilder {
 		self.inner.write().unwrap().set_gossip_source_rgs(rgs_server_url);
 	}
 
+	/// Configures the [`Node`] instance to bootstrap its gossip data from the given
+	/// RapidGossipSync server, while also receiving gossip updates from the Lightning
+	/// peer-to-peer network.
+	///
+	/// This allows to quickly have a usable network graph available after startup, while staying
+	/// up-to-date in-between RapidGossipSync snapshots.
+	pub fn set_gossip_source_rgs_and_p2p(&self, rgs_server_url: String) {
+		self.inner.write().unwrap().set_gossip_source_rgs_and_p2p(rgs_server_url);
+	}
+
 	/// Configures the [`Node`] instance to source its external scores from the given URL.
 	///
 	/// The external scores are merged into the local scoring system to improve routing.
@@ -982,6 +1513,90 @@ impl ArcedNodeBuilder {
 		self.inner.write().unwrap().set_storage_dir_path(storage_dir_path);
 	}
 
+	/// Configures the [`Node`] instance to persist its data to the given [VSS] backend when
+	/// calling [`build`].
+	///
+	/// The given `headers` are included as is in all requests made to VSS, typically used for
+	/// authentication purposes. See [`build_with_vss_store_and_fixed_headers`] for more
+	/// information.
+	///
+	/// [VSS]: https://github.com/lightningdevkit/vss-server/blob/main/README.md
+	/// [`build`]: Self::build
+	/// [`build_with_vss_store_and_fixed_headers`]: Self::build_with_vss_store_and_fixed_headers
+	pub fn set_storage_vss(
+		&self, base_url: String, store_id: String, headers: HashMap<String, String>,
+	) {
+		self.inner.write().unwrap().set_storage_vss(base_url, store_id, headers);
+	}
+
+	/// Configures the [`Node`] instance to persist its data to the SQLite database file at the
+	/// given `db_path` when calling [`build`].
+	///
+	/// If not set, the database is kept in [`SQLITE_DB_FILE_NAME`] in the configured
+	/// [`Config::storage_dir_path`]. The database file and its schema are created on first use.
+	///
+	/// Data of a node previously built via [`build_with_fs_store`] can be moved over via
+	/// [`SqliteStore::migrate_from_filesystem`] before building.
+	///
+	/// [`build`]: Self::build
+	/// [`build_with_fs_store`]: Self::build_with_fs_store
+	/// [`SQLITE_DB_FILE_NAME`]: io::sqlite_store::SQLITE_DB_FILE_NAME
+	pub fn set_storage_sqlite(&self, db_path: String) {
+		self.inner.write().unwrap().set_storage_sqlite(db_path);
+	}
+
+	/// Configures the [`Node`] instance to recover funds from the given static channel backup
+	/// (SCB), as previously created via [`Node::export_static_channel_backup`].
+	///
+	/// This is a last-resort recovery mechanism and may only be used on a fresh node that is
+	/// configured with the same seed as the node that created the backup. On build, we'll restore
+	/// the backed-up channel monitors and reconnect to the channel counterparties which will then
+	/// be requested to force-close the channels. Once the counterparties' commitment transactions
+	/// confirm, the funds are swept to the on-chain wallet.
+	///
+	/// If `broadcast_latest_commitments` is set, we'll additionally broadcast the latest
+	/// commitment transactions known to the backed-up channel monitors, which allows recovering
+	/// funds even if the counterparties are unreachable.
+	///
+	/// **Caution**: Recovering from a static channel backup forfeits the ability to continue using
+	/// the backed-up channels, it only recovers the funds. Only ever set
+	/// `broadcast_latest_commitments` if you're certain the backup is the most recent one, as
+	/// broadcasting an outdated commitment transaction allows the counterparty to claim all funds
+	/// of the respective channel.
+	///
+	/// Building will fail with [`BuildError::InvalidStaticChannelBackup`] if the backup can't be
+	/// decrypted with our seed or if the node already has channel state.
+	pub fn recover_from_static_channel_backup(
+		&self, static_channel_backup: Vec<u8>, broadcast_latest_commitments: bool,
+	) {
+		self.inner.write().unwrap().recover_from_static_channel_backup(
+			static_channel_backup,
+			broadcast_latest_commitments,
+		);
+	}
+
+	/// Configures the [`Node`] instance to back up its channel states to the watchtower reachable
+	/// at the given `tower_uri`.
+	///
+	/// The tower needs to speak the HTTP API of [The Eye of Satoshi], with which we register
+	/// using our node ID before handing it any channel states. Whenever our counterparty revokes
+	/// one of their commitment transactions, we hand the tower an encrypted justice transaction
+	/// allowing it to claim the channel's `to_local` output on our behalf, should the counterparty
+	/// broadcast the revoked state while our node is offline. The tower will only be able to
+	/// decrypt the justice transaction once the revoked state hits the chain.
+	///
+	/// The tower's receipts are authenticated against the given `tower_pubkey`. Channel states are
+	/// delivered in the background and are queued for later delivery if the tower is unavailable,
+	/// i.e., channel operations are never blocked on the tower.
+	///
+	/// **Note:** Only channel states revoked after the tower was configured are backed up. HTLC
+	/// outputs of revoked states are not covered.
+	///
+	/// [The Eye of Satoshi]: https://github.com/talaia-labs/rust-teos
+	pub fn set_watchtower(&self, tower_uri: String, tower_pubkey: PublicKey) {
+		self.inner.write().unwrap().set_watchtower(tower_uri, tower_pubkey);
+	}
+
 	/// Configures the [`Node`] instance to write logs to the filesystem.
 	///
 	/// The `log_file_path` defaults to [`DEFAULT_LOG_FILENAME`] in the configured
@@ -990,11 +1605,52 @@ impl ArcedNodeBuilder {
 	/// If set, the `max_log_level` sets the maximum log level. Otherwise, the latter defaults to
 	/// [`DEFAULT_LOG_LEVEL`].
 	///
+	/// If set, the `module_log_levels` override the maximum log level for records whose target,
+	/// i.e., module path, starts with the given module prefix, e.g., `ldk_node::liquidity` or
+	/// `lightning::routing`. If multiple prefixes match, the most specific one takes precedence.
+	///
 	/// [`DEFAULT_LOG_FILENAME`]: crate::config::DEFAULT_LOG_FILENAME
 	pub fn set_filesystem_logger(
 		&self, log_file_path: Option<String>, log_level: Option<LogLevel>,
+		module_log_levels: Option<HashMap<String, LogLevel>>,
 	) {
-		self.inner.write().unwrap().set_filesystem_logger(log_file_path, log_level);
+		self.inner.write().unwrap().set_filesystem_logger(
+			log_file_path,
+			log_level,
+			module_log_levels,
+		);
+	}
+
+	/// Configures the [`Node`] instance to write logs to the filesystem as JSON, emitting one
+	/// object per line.
+	///
+	/// Each object holds the `timestamp`, `level`, `target`, `line`, and `message` of the log
+	/// record, as well as the structured `peer_id`, `channel_id`, and `payment_hash` fields if they
+	/// are known.
+	///
+	/// The `log_file_path` defaults to [`DEFAULT_JSON_LOG_FILENAME`] in the configured
+	/// [`Config::storage_dir_path`] if set to `None`.
+	///
+	/// If set, the `max_log_level` sets the maximum log level. Otherwise, the latter defaults to
+	/// [`DEFAULT_LOG_LEVEL`].
+	///
+	/// If set, the `module_log_levels` override the maximum log level for records whose target,
+	/// i.e., module path, starts with the given module prefix, e.g., `ldk_node::liquidity` or
+	/// `lightning::routing`. If multiple prefixes match, the most specific one takes precedence.
+	///
+	/// Note that this is mutually exclusive with [`Self::set_filesystem_logger`]. If both are set,
+	/// building the node will fail with [`BuildError::ConflictingLoggers`].
+	///
+	/// [`DEFAULT_JSON_LOG_FILENAME`]: crate::config::DEFAULT_JSON_LOG_FILENAME
+	pub fn set_json_logger(
+		&self, log_file_path: Option<String>, max_log_level: Option<LogLevel>,
+		module_log_levels: Option<HashMap<String, LogLevel>>,
+	) {
+		self.inner.write().unwrap().set_json_logger(
+			log_file_path,
+			max_log_level,
+			module_log_levels,
+		);
 	}
 
 	/// Configures the [`Node`] instance to write logs to the [`log`](https://crates.io/crates/log) facade.
@@ -1007,12 +1663,44 @@ impl ArcedNodeBuilder {
 		self.inner.write().unwrap().set_custom_logger(log_writer);
 	}
 
+	/// Configures the [`Node`] instance to source fee rate estimates from the provided custom
+	/// [`FeeEstimator`].
+	///
+	/// If set, the fee rate estimates will no longer be retrieved from the configured chain data
+	/// source. The custom estimator's values will still be cached and can be retrieved via
+	/// [`Node::current_fee_rate`].
+	///
+	/// Note that any fee rate overrides set via [`Node::set_fee_rate_override`] still take precedence
+	/// over the custom estimator's values.
+	pub fn set_custom_fee_estimator(&self, fee_estimator: Arc<dyn FeeEstimator>) {
+		self.inner.write().unwrap().set_custom_fee_estimator(fee_estimator);
+	}
+
+	/// Configures the [`Node`] instance to fund its channels from, and send any funds recovered
+	/// from them to, the provided externally managed on-chain wallet.
+	///
+	/// This allows to run the node purely for its channels while an existing wallet owns the keys
+	/// to the on-chain funds. Please refer to [`OnchainWalletInterface`] for which operations are
+	/// routed through the external wallet, and when it needs to be available.
+	///
+	/// Note that the node's internal on-chain wallet is still used for on-chain payments via
+	/// [`Node::onchain_payment`].
+	pub fn set_external_onchain_wallet(&self, external_wallet: Arc<dyn OnchainWalletInterface>) {
+		self.inner.write().unwrap().set_external_onchain_wallet(external_wallet);
+	}
+
 	/// Sets the Bitcoin network used.
 	pub fn set_network(&self, network: Network) {
 		self.inner.write().unwrap().set_network(network);
 	}
 
-	/// Sets the IP address and TCP port on which [`Node`] will listen for incoming network connections.
+	/// Sets the addresses on which [`Node`] will listen for incoming network connections.
+	///
+	/// Any mix of IPv4, IPv6, and DNS hostname addresses is supported, allowing to run dual-stack
+	/// nodes. Hostnames are resolved on startup, and the node will listen on all of the resolved
+	/// addresses. If announcing, all addresses will be included in our node announcement.
+	///
+	/// Returns an error if more than 100 addresses are given or the list contains duplicates.
 	pub fn set_listening_addresses(
 		&self, listening_addresses: Vec<SocketAddress>,
 	) -> Result<(), BuildError> {
@@ -1030,6 +1718,22 @@ impl ArcedNodeBuilder {
 		self.inner.write().unwrap().set_announcement_addresses(announcement_addresses).map(|_| ())
 	}
 
+	/// Configures the [`Node`] instance to connect to peers via Tor.
+	///
+	/// Connections to onion peer addresses will be routed through the configured SOCKS5 proxy,
+	/// while connections to other peer addresses are established directly.
+	///
+	/// If [`TorConfig::hidden_service`] is set, we'll publish an onion service forwarding to the
+	/// port of our first listening address on `127.0.0.1` via the Tor control port, and include
+	/// its address in our node announcements. If Tor is unavailable, we'll keep retrying in the
+	/// background rather than failing to start.
+	///
+	/// This will return an error if [`TorConfig::hidden_service`] is set but no
+	/// [`TorConfig::control_port`] is given.
+	pub fn set_tor_config(&self, tor_config: TorConfig) -> Result<(), BuildError> {
+		self.inner.write().unwrap().set_tor_config(tor_config).map(|_| ())
+	}
+
 	/// Sets the node alias that will be used when broadcasting announcements to the gossip
 	/// network.
 	///
@@ -1038,6 +1742,12 @@ impl ArcedNodeBuilder {
 		self.inner.write().unwrap().set_node_alias(node_alias).map(|_| ())
 	}
 
+	/// Sets the RGB color that will be used when broadcasting announcements to the gossip
+	/// network.
+	pub fn set_node_color(&self, node_color: [u8; 3]) {
+		self.inner.write().unwrap().set_node_color(node_color);
+	}
+
 	/// Sets the role of the node in an asynchronous payments context.
 	pub fn set_async_payments_role(
 		&self, role: Option<AsyncPaymentsRole>,
@@ -1047,6 +1757,11 @@ impl ArcedNodeBuilder {
 
 	/// Builds a [`Node`] instance with a [`SqliteStore`] backend and according to the options
 	/// previously configured.
+	///
+	/// If a [VSS] backend was configured via [`set_storage_vss`], it will be used instead.
+	///
+	/// [VSS]: https://github.com/lightningdevkit/vss-server/blob/main/README.md
+	/// [`set_storage_vss`]: Self::set_storage_vss
 	pub fn build(&self) -> Result<Arc<Node>, BuildError> {
 		self.inner.read().unwrap().build().map(Arc::new)
 	}
@@ -1140,8 +1855,13 @@ fn build_with_store_internal(
 	gossip_source_config: Option<&GossipSourceConfig>,
 	liquidity_source_config: Option<&LiquiditySourceConfig>,
 	pathfinding_scores_sync_config: Option<&PathfindingScoresSyncConfig>,
-	async_payments_role: Option<AsyncPaymentsRole>, seed_bytes: [u8; 64], runtime: Arc<Runtime>,
-	logger: Arc<Logger>, kv_store: Arc<DynStore>,
+	tor_config: Option<&TorConfig>, async_payments_role: Option<AsyncPaymentsRole>,
+	static_channel_backup_config: Option<&StaticChannelBackupConfig>,
+	watchtower_config: Option<&WatchtowerConfig>,
+	custom_fee_estimator: Option<Arc<dyn FeeEstimator>>,
+	external_onchain_wallet: Option<Arc<dyn OnchainWalletInterface>>, seed_bytes: [u8; 64],
+	runtime: Arc<Runtime>, logger: Arc<Logger>, vss_store: Option<Arc<VssStore>>,
+	migratable_store: Option<Arc<DynMigratableStore>>, kv_store: Arc<DynStore>,
 ) -> Result<Node, BuildError> {
 	optionally_install_rustls_cryptoprovider();
 
@@ -1162,6 +1882,104 @@ fn build_with_store_internal(
 		}
 	}
 
+	if config.channel_htlc_limits.map_or(false, |limits| !limits.is_valid()) {
+		log_error!(logger, "Channel HTLC limits are out of range.");
+		return Err(BuildError::InvalidChannelHtlcLimits);
+	}
+
+	if !config.features.is_valid() {
+		log_error!(logger, "Feature configuration enables a feature without its prerequisite.");
+		return Err(BuildError::InvalidFeatureConfig);
+	}
+
+	if !is_valid_cltv_expiry_delta_config(&config) {
+		log_error!(logger, "CLTV expiry deltas are below the minimums enforced by LDK.");
+		return Err(BuildError::InvalidCltvExpiryDelta);
+	}
+
+	if is_cltv_expiry_delta_dangerously_low(&config) {
+		log_warn!(
+			logger,
+			"The default CLTV expiry delta of {} blocks is dangerously low for mainnet, forwarded payments might not be claimable on-chain in time.",
+			config.default_cltv_expiry_delta,
+		);
+	}
+
+	if !config.peer_reconnect_policy.is_valid() {
+		log_error!(logger, "Peer reconnection policy is invalid.");
+		return Err(BuildError::InvalidPeerReconnectPolicy);
+	}
+
+	if let Some(destination) = config.spendable_output_sweep_destination.as_ref() {
+		if !destination.as_unchecked().is_valid_for_network(config.network) {
+			log_error!(logger, "Sweep destination is not valid for network {}.", config.network);
+			return Err(BuildError::InvalidSweepDestination);
+		}
+	}
+
+	if config.listening_addresses.as_ref().map_or(false, |addrs| has_duplicate_addresses(addrs)) {
+		log_error!(logger, "Listening addresses must not contain duplicates.");
+		return Err(BuildError::InvalidListeningAddresses);
+	}
+
+	if config.announcement_addresses.as_ref().map_or(false, |addrs| has_duplicate_addresses(addrs))
+	{
+		log_error!(logger, "Announcement addresses must not contain duplicates.");
+		return Err(BuildError::InvalidAnnouncementAddresses);
+	}
+
+	if may_announce_channel(&config).is_ok() {
+		// Make sure we announce at least one address other nodes could actually reach us on. If we
+		// publish an onion service, its address will be added to our announcements later on.
+		let (addresses, build_error) = match config.announcement_addresses.as_ref() {
+			Some(addrs) => (addrs, BuildError::InvalidAnnouncementAddresses),
+			None => (
+				// unwrap safety: we checked whether the node may announce above.
+				config.listening_addresses.as_ref().unwrap(),
+				BuildError::InvalidListeningAddresses,
+			),
+		};
+		let has_routable_address =
+			addresses.iter().any(|addr| is_routable_address(addr, config.network));
+		let publishes_onion_service = tor_config.map_or(false, |c| c.hidden_service);
+		if !has_routable_address && !publishes_onion_service {
+			log_error!(
+				logger,
+				"Failed to setup node announcements as none of the addresses to announce are routable: {:?}",
+				addresses
+			);
+			return Err(build_error);
+		}
+	}
+
+	// Encrypt all persisted values at rest, if we're asked to.
+	let encrypted_store = if config.encrypt_storage || config.encrypt_storage_with.is_some() {
+		let encryption_key = match config.encrypt_storage_with {
+			Some(encryption_key) => encryption_key,
+			None => derive_xprv(
+				Arc::clone(&config),
+				&seed_bytes,
+				STORAGE_ENCRYPTION_HARDENED_CHILD_INDEX,
+				Arc::clone(&logger),
+			)?
+			.private_key
+			.secret_bytes(),
+		};
+		Some(Arc::new(EncryptedStore::new(Arc::clone(&kv_store), encryption_key)))
+	} else {
+		None
+	};
+	setup_storage_encryption(
+		&kv_store,
+		encrypted_store.as_ref(),
+		migratable_store.as_ref(),
+		&logger,
+	)?;
+	let kv_store: Arc<DynStore> = match encrypted_store.as_ref() {
+		Some(encrypted_store) => Arc::clone(encrypted_store) as Arc<DynStore>,
+		None => kv_store,
+	};
+
 	// Initialize the status fields.
 	let node_metrics = match read_node_metrics(Arc::clone(&kv_store), Arc::clone(&logger)) {
 		Ok(metrics) => Arc::new(RwLock::new(metrics)),
@@ -1221,8 +2039,12 @@ fn build_with_store_internal(
 			})?,
 	};
 
-	let tx_broadcaster = Arc::new(TransactionBroadcaster::new(Arc::clone(&logger)));
-	let fee_estimator = Arc::new(OnchainFeeEstimator::new());
+	let tx_broadcaster =
+		Arc::new(TransactionBroadcaster::new(external_onchain_wallet.clone(), Arc::clone(&logger)));
+	let output_spending_target =
+		config.spendable_output_sweep_config.map(|sweep_config| sweep_config.confirmation_target);
+	let fee_estimator =
+		Arc::new(OnchainFeeEstimator::new(custom_fee_estimator, output_spending_target));
 
 	let payment_store = match io::utils::read_payments(Arc::clone(&kv_store), Arc::clone(&logger)) {
 		Ok(payments) => Arc::new(PaymentStore::new(
@@ -1237,16 +2059,57 @@ fn build_with_store_internal(
 		},
 	};
 
+	let closed_channel_store =
+		match io::utils::read_closed_channels(Arc::clone(&kv_store), Arc::clone(&logger)) {
+			Ok(closed_channels) => Arc::new(ClosedChannelStore::new(
+				closed_channels,
+				CLOSED_CHANNEL_INFO_PERSISTENCE_PRIMARY_NAMESPACE.to_string(),
+				CLOSED_CHANNEL_INFO_PERSISTENCE_SECONDARY_NAMESPACE.to_string(),
+				Arc::clone(&kv_store),
+				Arc::clone(&logger),
+			)),
+			Err(_) => {
+				return Err(BuildError::ReadFailed);
+			},
+		};
+
+	let recurring_payment_store =
+		match io::utils::read_recurring_payments(Arc::clone(&kv_store), Arc::clone(&logger)) {
+			Ok(recurring_payments) => Arc::new(RecurringPaymentStore::new(
+				recurring_payments,
+				RECURRING_PAYMENT_INFO_PERSISTENCE_PRIMARY_NAMESPACE.to_string(),
+				RECURRING_PAYMENT_INFO_PERSISTENCE_SECONDARY_NAMESPACE.to_string(),
+				Arc::clone(&kv_store),
+				Arc::clone(&logger),
+			)),
+			Err(_) => {
+				return Err(BuildError::ReadFailed);
+			},
+		};
+
 	let wallet = Arc::new(Wallet::new(
 		bdk_wallet,
 		wallet_persister,
 		Arc::clone(&tx_broadcaster),
 		Arc::clone(&fee_estimator),
 		Arc::clone(&payment_store),
+		external_onchain_wallet,
 		Arc::clone(&config),
 		Arc::clone(&logger),
 	));
 
+	let event_queue = match io::utils::read_event_queue(Arc::clone(&kv_store), Arc::clone(&logger))
+	{
+		Ok(event_queue) => Arc::new(event_queue),
+		Err(e) => {
+			if e.kind() == std::io::ErrorKind::NotFound {
+				Arc::new(EventQueue::new(Arc::clone(&kv_store), Arc::clone(&logger)))
+			} else {
+				return Err(BuildError::ReadFailed);
+			}
+		},
+	};
+
 	let chain_source = match chain_data_source_config {
 		Some(ChainDataSourceConfig::Esplora { server_url, headers, sync_config }) => {
 			let sync_config = sync_config.unwrap_or(EsploraSyncConfig::default());
@@ -1264,14 +2127,19 @@ fn build_with_store_internal(
 			))
 		},
 		Some(ChainDataSourceConfig::Electrum { server_url, sync_config }) => {
+			let server_url = parse_electrum_server_url(server_url).map_err(|()| {
+				log_error!(logger, "Failed to parse Electrum server URL: {}", server_url);
+				BuildError::InvalidChainSourceUrl
+			})?;
 			let sync_config = sync_config.unwrap_or(ElectrumSyncConfig::default());
 			Arc::new(ChainSource::new_electrum(
-				server_url.clone(),
+				server_url,
 				sync_config,
 				Arc::clone(&wallet),
 				Arc::clone(&fee_estimator),
 				Arc::clone(&tx_broadcaster),
 				Arc::clone(&kv_store),
+				Arc::clone(&event_queue),
 				Arc::clone(&config),
 				Arc::clone(&logger),
 				Arc::clone(&node_metrics),
@@ -1280,37 +2148,60 @@ fn build_with_store_internal(
 		Some(ChainDataSourceConfig::Bitcoind {
 			rpc_host,
 			rpc_port,
-			rpc_user,
-			rpc_password,
+			rpc_auth,
+			sync_config,
 			rest_client_config,
-		}) => match rest_client_config {
-			Some(rest_client_config) => Arc::new(ChainSource::new_bitcoind_rest(
-				rpc_host.clone(),
-				*rpc_port,
-				rpc_user.clone(),
-				rpc_password.clone(),
-				Arc::clone(&wallet),
-				Arc::clone(&fee_estimator),
-				Arc::clone(&tx_broadcaster),
-				Arc::clone(&kv_store),
-				Arc::clone(&config),
-				rest_client_config.clone(),
-				Arc::clone(&logger),
-				Arc::clone(&node_metrics),
-			)),
-			None => Arc::new(ChainSource::new_bitcoind_rpc(
-				rpc_host.clone(),
-				*rpc_port,
-				rpc_user.clone(),
-				rpc_password.clone(),
-				Arc::clone(&wallet),
-				Arc::clone(&fee_estimator),
-				Arc::clone(&tx_broadcaster),
-				Arc::clone(&kv_store),
-				Arc::clone(&config),
-				Arc::clone(&logger),
-				Arc::clone(&node_metrics),
-			)),
+		}) => {
+			let (rpc_user, rpc_password) = match rpc_auth {
+				BitcoindRpcAuth::UserPass { rpc_user, rpc_password } => {
+					(rpc_user.clone(), rpc_password.clone())
+				},
+				BitcoindRpcAuth::CookieFile(cookie_file_path) => {
+					read_bitcoind_rpc_cookie(cookie_file_path).map_err(|e| {
+						log_error!(
+							logger,
+							"Failed to read RPC cookie file {}: {}",
+							cookie_file_path,
+							e
+						);
+						BuildError::InvalidRpcCookieFile
+					})?
+				},
+			};
+			let sync_config = sync_config.unwrap_or(BitcoindSyncConfig::default());
+			match rest_client_config {
+				Some(rest_client_config) => Arc::new(ChainSource::new_bitcoind_rest(
+					rpc_host.clone(),
+					*rpc_port,
+					rpc_user,
+					rpc_password,
+					sync_config,
+					Arc::clone(&wallet),
+					Arc::clone(&fee_estimator),
+					Arc::clone(&tx_broadcaster),
+					Arc::clone(&kv_store),
+					Arc::clone(&event_queue),
+					Arc::clone(&config),
+					rest_client_config.clone(),
+					Arc::clone(&logger),
+					Arc::clone(&node_metrics),
+				)),
+				None => Arc::new(ChainSource::new_bitcoind_rpc(
+					rpc_host.clone(),
+					*rpc_port,
+					rpc_user,
+					rpc_password,
+					sync_config,
+					Arc::clone(&wallet),
+					Arc::clone(&fee_estimator),
+					Arc::clone(&tx_broadcaster),
+					Arc::clone(&kv_store),
+					Arc::clone(&event_queue),
+					Arc::clone(&config),
+					Arc::clone(&logger),
+					Arc::clone(&node_metrics),
+				)),
+			}
 		},
 
 		None => {
@@ -1359,7 +2250,7 @@ fn build_with_store_internal(
 	));
 
 	// Read ChannelMonitor state from store
-	let channel_monitors = match persister.read_all_channel_monitors_with_updates() {
+	let mut channel_monitors = match persister.read_all_channel_monitors_with_updates() {
 		Ok(monitors) => monitors,
 		Err(e) => {
 			if e.kind() == lightning::io::ErrorKind::NotFound {
@@ -1371,13 +2262,97 @@ fn build_with_store_internal(
 		},
 	};
 
+	let static_channel_backup_key = derive_xprv(
+		Arc::clone(&config),
+		&seed_bytes,
+		STATIC_CHANNEL_BACKUP_HARDENED_CHILD_INDEX,
+		Arc::clone(&logger),
+	)?
+	.private_key
+	.secret_bytes();
+
+	// Restore ChannelMonitor state from the static channel backup, if we're asked to recover from one.
+	let mut recovered_channel_ids = Vec::new();
+	let mut recovered_peers = Vec::new();
+	if let Some(scb_config) = static_channel_backup_config {
+		let has_channel_manager = KVStoreSync::read(
+			&*kv_store,
+			CHANNEL_MANAGER_PERSISTENCE_PRIMARY_NAMESPACE,
+			CHANNEL_MANAGER_PERSISTENCE_SECONDARY_NAMESPACE,
+			CHANNEL_MANAGER_PERSISTENCE_KEY,
+		)
+		.is_ok();
+		if has_channel_manager || !channel_monitors.is_empty() {
+			log_error!(
+				logger,
+				"Refusing to recover from static channel backup as the node already has channel state."
+			);
+			return Err(BuildError::InvalidStaticChannelBackup);
+		}
+
+		let backup = StaticChannelBackup::decrypt(
+			&scb_config.static_channel_backup,
+			static_channel_backup_key,
+		)
+		.map_err(|()| {
+			log_error!(logger, "Failed to decrypt static channel backup.");
+			BuildError::InvalidStaticChannelBackup
+		})?;
+
+		for serialized_monitor in backup.channel_monitors {
+			let mut reader = Cursor::new(serialized_monitor);
+			let (block_hash, channel_monitor) =
+				<(BlockHash, ChannelMonitor<InMemorySigner>)>::read(
+					&mut reader,
+					(&*keys_manager, &*keys_manager),
+				)
+				.map_err(|e| {
+					log_error!(
+						logger,
+						"Failed to read channel monitor from static channel backup: {}",
+						e
+					);
+					BuildError::InvalidStaticChannelBackup
+				})?;
+			recovered_channel_ids.push(channel_monitor.channel_id());
+			channel_monitors.push((block_hash, channel_monitor));
+		}
+		recovered_peers = backup.peers;
+
+		log_info!(
+			logger,
+			"Recovering funds of {} channel(s) from static channel backup.",
+			recovered_channel_ids.len()
+		);
+	}
+
+	let watchtower_client = match watchtower_config {
+		Some(wc) => Some(Arc::new(
+			WatchtowerClient::new(
+				wc.tower_uri.clone(),
+				wc.tower_pubkey,
+				Arc::clone(&keys_manager),
+				Arc::clone(&fee_estimator),
+				Arc::clone(&kv_store),
+				Arc::clone(&logger),
+			)
+			.map_err(|e| {
+				log_error!(logger, "Failed to setup watchtower client: {}", e);
+				BuildError::ReadFailed
+			})?,
+		)),
+		None => None,
+	};
+	let watchtower_persister =
+		Arc::new(WatchtowerPersister::new(Arc::clone(&persister), watchtower_client.clone()));
+
 	// Initialize the ChainMonitor
 	let chain_monitor: Arc<ChainMonitor> = Arc::new(chainmonitor::ChainMonitor::new(
 		Some(Arc::clone(&chain_source)),
 		Arc::clone(&tx_broadcaster),
 		Arc::clone(&logger),
 		Arc::clone(&fee_estimator),
-		Arc::clone(&persister),
+		watchtower_persister,
 		Arc::clone(&keys_manager),
 		peer_storage_key,
 	));
@@ -1427,7 +2402,7 @@ fn build_with_store_internal(
 		},
 	}
 
-	let scoring_fee_params = ProbabilisticScoringFeeParameters::default();
+	let scoring_fee_params = ProbabilisticScoringFeeParameters::from(config.scoring_params);
 	let router = Arc::new(DefaultRouter::new(
 		Arc::clone(&network_graph),
 		Arc::clone(&logger),
@@ -1523,6 +2498,11 @@ fn build_with_store_internal(
 
 	let channel_manager = Arc::new(channel_manager);
 
+	// Track the funding spends of our channels, which allows us to report the closing fees.
+	for funding_txo in channel_manager.list_channels().into_iter().filter_map(|c| c.funding_txo) {
+		tx_broadcaster.watch_funding_outpoint(funding_txo.into_bitcoin_outpoint());
+	}
+
 	// Give ChannelMonitors to ChainMonitor
 	for (_blockhash, channel_monitor) in channel_monitors.into_iter() {
 		let channel_id = channel_monitor.channel_id();
@@ -1532,7 +2512,27 @@ fn build_with_store_internal(
 		})?;
 	}
 
+	// We can't continue operating channels recovered from a static channel backup, so broadcast
+	// their latest commitment transactions to get the funds back on-chain if we're asked to. As
+	// the backup might be outdated, by default we only rely on the counterparties to force-close.
+	if static_channel_backup_config.map_or(false, |c| c.broadcast_latest_commitments) {
+		for channel_id in recovered_channel_ids {
+			if let Ok(channel_monitor) = chain_monitor.get_monitor(channel_id) {
+				channel_monitor.broadcast_latest_holder_commitment_txn(
+					&tx_broadcaster,
+					&fee_estimator,
+					&logger,
+				);
+			}
+		}
+	}
+
 	// Initialize the PeerManager
+	let custom_onion_message_handler = Arc::new(NodeCustomOnionMessageHandler::new(
+		config.accept_custom_onion_messages,
+		Arc::clone(&event_queue),
+		Arc::clone(&logger),
+	));
 	let onion_messenger: Arc<OnionMessenger> =
 		if let Some(AsyncPaymentsRole::Server) = async_payments_role {
 			Arc::new(OnionMessenger::new_with_offline_peer_interception(
@@ -1540,11 +2540,11 @@ fn build_with_store_internal(
 				Arc::clone(&keys_manager),
 				Arc::clone(&logger),
 				Arc::clone(&channel_manager),
-				message_router,
+				Arc::clone(&message_router),
 				Arc::clone(&channel_manager),
 				Arc::clone(&channel_manager),
 				IgnoringMessageHandler {},
-				IgnoringMessageHandler {},
+				Arc::clone(&custom_onion_message_handler),
 			))
 		} else {
 			Arc::new(OnionMessenger::new(
@@ -1552,11 +2552,11 @@ fn build_with_store_internal(
 				Arc::clone(&keys_manager),
 				Arc::clone(&logger),
 				Arc::clone(&channel_manager),
-				message_router,
+				Arc::clone(&message_router),
 				Arc::clone(&channel_manager),
 				Arc::clone(&channel_manager),
 				IgnoringMessageHandler {},
-				IgnoringMessageHandler {},
+				Arc::clone(&custom_onion_message_handler),
 			))
 		};
 	let ephemeral_bytes: [u8; 32] = keys_manager.get_secure_random_bytes();
@@ -1586,12 +2586,13 @@ fn build_with_store_internal(
 			}
 			p2p_source
 		},
-		GossipSourceConfig::RapidGossipSync(rgs_server) => {
+		GossipSourceConfig::RapidGossipSync { server_url, p2p_gossip } => {
 			let latest_sync_timestamp =
 				node_metrics.read().unwrap().latest_rgs_snapshot_timestamp.unwrap_or(0);
 			Arc::new(GossipSource::new_rgs(
-				rgs_server.clone(),
+				server_url.clone(),
 				latest_sync_timestamp,
+				*p2p_gossip,
 				Arc::clone(&network_graph),
 				Arc::clone(&logger),
 			))
@@ -1607,6 +2608,7 @@ fn build_with_store_internal(
 				Arc::clone(&chain_source),
 				Arc::clone(&tx_broadcaster),
 				Arc::clone(&kv_store),
+				Arc::clone(&event_queue),
 				Arc::clone(&config),
 				Arc::clone(&logger),
 			);
@@ -1649,28 +2651,64 @@ fn build_with_store_internal(
 			(None, Arc::new(NodeCustomMessageHandler::new_ignoring()))
 		};
 
-	let msg_handler = match gossip_source.as_gossip_sync() {
-		GossipSync::P2P(p2p_gossip_sync) => MessageHandler {
-			chan_handler: Arc::clone(&channel_manager),
-			route_handler: Arc::clone(&p2p_gossip_sync)
-				as Arc<dyn RoutingMessageHandler + Sync + Send>,
-			onion_message_handler: Arc::clone(&onion_messenger),
-			custom_message_handler,
-			send_only_message_handler: Arc::clone(&chain_monitor),
-		},
-		GossipSync::Rapid(_) => MessageHandler {
-			chan_handler: Arc::clone(&channel_manager),
-			route_handler: Arc::new(IgnoringMessageHandler {})
-				as Arc<dyn RoutingMessageHandler + Sync + Send>,
-			onion_message_handler: Arc::clone(&onion_messenger),
-			custom_message_handler,
-			send_only_message_handler: Arc::clone(&chain_monitor),
+	let peer_connection_policy =
+		match read_peer_connection_policy(Arc::clone(&kv_store), Arc::clone(&logger)) {
+			Ok(policy) => Arc::new(RwLock::new(policy)),
+			Err(e) => {
+				if e.kind() == std::io::ErrorKind::NotFound {
+					Arc::new(RwLock::new(config.peer_connection_policy.clone()))
+				} else {
+					return Err(BuildError::ReadFailed);
+				}
+			},
+		};
+
+	let power_mode = Arc::new(RwLock::new(PowerMode::Normal));
+
+	let default_offer = match read_default_offer(Arc::clone(&kv_store), Arc::clone(&logger)) {
+		Ok(offer) => Arc::new(Mutex::new(Some(offer))),
+		Err(e) => {
+			if e.kind() == std::io::ErrorKind::NotFound {
+				Arc::new(Mutex::new(None))
+			} else {
+				return Err(BuildError::ReadFailed);
+			}
 		},
-		GossipSync::None => {
-			unreachable!("We must always have a gossip sync!");
+	};
+
+	let peer_store = match io::utils::read_peer_info(Arc::clone(&kv_store), Arc::clone(&logger)) {
+		Ok(peer_store) => Arc::new(peer_store),
+		Err(e) => {
+			if e.kind() == std::io::ErrorKind::NotFound {
+				Arc::new(PeerStore::new(Arc::clone(&kv_store), Arc::clone(&logger)))
+			} else {
+				return Err(BuildError::ReadFailed);
+			}
 		},
 	};
 
+	let gossip_handler = match gossip_source.p2p_gossip_sync() {
+		Some(p2p_gossip_sync) => p2p_gossip_sync as Arc<dyn RoutingMessageHandler + Sync + Send>,
+		None => Arc::new(IgnoringMessageHandler {}) as Arc<dyn RoutingMessageHandler + Sync + Send>,
+	};
+	let route_handler = Arc::new(NodeRoutingMessageHandler::new(
+		gossip_handler,
+		Arc::clone(&peer_connection_policy),
+		Arc::clone(&power_mode),
+		Arc::clone(&logger),
+	)) as Arc<dyn RoutingMessageHandler + Sync + Send>;
+	let msg_handler = MessageHandler {
+		chan_handler: Arc::new(NodeChannelMessageHandler::new(
+			Arc::clone(&channel_manager),
+			config.advertised_features(),
+			Arc::clone(&peer_store),
+		)),
+		route_handler,
+		onion_message_handler: Arc::clone(&onion_messenger),
+		custom_message_handler,
+		send_only_message_handler: Arc::clone(&chain_monitor),
+	};
+
 	let cur_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map_err(|e| {
 		log_error!(logger, "Failed to get current time: {}", e);
 		BuildError::InvalidSystemTime
@@ -1695,8 +2733,38 @@ fn build_with_store_internal(
 		Arc::clone(&runtime),
 	);
 
-	let connection_manager =
-		Arc::new(ConnectionManager::new(Arc::clone(&peer_manager), Arc::clone(&logger)));
+	let connection_manager = Arc::new(ConnectionManager::new(
+		Arc::clone(&peer_manager),
+		tor_config.map(|c| c.socks_proxy.clone()),
+		config.peer_reconnect_policy,
+		Arc::clone(&logger),
+	));
+
+	let onion_service = match tor_config {
+		Some(TorConfig { control_port: Some(control_port), hidden_service: true, .. }) => {
+			let target_port = match config.listening_addresses.as_ref().and_then(|a| a.first()) {
+				Some(
+					SocketAddress::TcpIpV4 { port, .. }
+					| SocketAddress::TcpIpV6 { port, .. }
+					| SocketAddress::Hostname { port, .. },
+				) => *port,
+				_ => {
+					log_error!(
+						logger,
+						"Failed to setup onion service: a TCP listening address is required."
+					);
+					return Err(BuildError::InvalidTorConfig);
+				},
+			};
+			Some(Arc::new(OnionService::new(
+				control_port.clone(),
+				target_port,
+				Arc::clone(&kv_store),
+				Arc::clone(&logger),
+			)))
+		},
+		_ => None,
+	};
 
 	let output_sweeper = match io::utils::read_output_sweeper(
 		Arc::clone(&tx_broadcaster),
@@ -1725,29 +2793,77 @@ fn build_with_store_internal(
 		},
 	};
 
-	let event_queue = match io::utils::read_event_queue(Arc::clone(&kv_store), Arc::clone(&logger))
-	{
-		Ok(event_queue) => Arc::new(event_queue),
+	let label_store = match io::utils::read_labels(Arc::clone(&kv_store), Arc::clone(&logger)) {
+		Ok(label_store) => Arc::new(label_store),
 		Err(e) => {
 			if e.kind() == std::io::ErrorKind::NotFound {
-				Arc::new(EventQueue::new(Arc::clone(&kv_store), Arc::clone(&logger)))
+				Arc::new(LabelStore::new(Arc::clone(&kv_store), Arc::clone(&logger)))
 			} else {
 				return Err(BuildError::ReadFailed);
 			}
 		},
 	};
 
-	let peer_store = match io::utils::read_peer_info(Arc::clone(&kv_store), Arc::clone(&logger)) {
-		Ok(peer_store) => Arc::new(peer_store),
-		Err(e) => {
-			if e.kind() == std::io::ErrorKind::NotFound {
-				Arc::new(PeerStore::new(Arc::clone(&kv_store), Arc::clone(&logger)))
-			} else {
-				return Err(BuildError::ReadFailed);
-			}
+	// Remember the counterparties of recovered channels, so that we reconnect to them. Upon
+	// reconnection, they'll be requested to force-close the respective channels.
+	for peer_info in recovered_peers {
+		peer_store.add_peer(peer_info).map_err(|e| {
+			log_error!(logger, "Failed to persist recovered peer: {}", e);
+			BuildError::WriteFailed
+		})?;
+	}
+
+	// Drop the labels of channels that were closed and peers we forgot about in the meantime.
+	let open_channels = channel_manager.list_channels();
+	label_store
+		.retain(
+			|channel_id| open_channels.iter().any(|c| &c.channel_id == channel_id),
+			|node_id| {
+				peer_store.get_peer(node_id).is_some()
+					|| open_channels.iter().any(|c| &c.counterparty.node_id == node_id)
+			},
+		)
+		.map_err(|e| {
+			log_error!(logger, "Failed to prune channel and peer labels: {}", e);
+			BuildError::WriteFailed
+		})?;
+
+	let claim_watchdog = Arc::new(ClaimDeadlineWatchdog::new(
+		Arc::clone(&channel_manager),
+		Arc::clone(&payment_store),
+		Arc::clone(&event_queue),
+		Arc::clone(&config),
+		Arc::clone(&logger),
+	));
+
+	let payment_retry_strategies = Arc::new(Mutex::new(HashMap::new()));
+	let payment_idempotency_keys = match io::utils::read_payment_idempotency_keys(
+		Arc::clone(&kv_store),
+		Arc::clone(&logger),
+	) {
+		Ok(entries) => Arc::new(PaymentIdempotencyKeys::new(
+			entries,
+			Arc::clone(&payment_store),
+			Arc::clone(&kv_store),
+			Arc::clone(&logger),
+		)),
+		Err(_) => {
+			return Err(BuildError::ReadFailed);
 		},
 	};
 
+	let bump_tx_event_handler = Arc::new(BumpTransactionEventHandler::new(
+		Arc::clone(&tx_broadcaster),
+		Arc::new(LdkWallet::new(Arc::clone(&wallet), Arc::clone(&logger))),
+		Arc::clone(&keys_manager),
+		Arc::clone(&logger),
+	));
+	let pending_channel_close_bumps = Arc::new(Mutex::new(HashMap::new()));
+	let channel_ready_waiters = Arc::new(Mutex::new(HashMap::new()));
+	let funding_confirmation_targets = Arc::new(Mutex::new(HashMap::new()));
+	let pending_external_fundings = Arc::new(Mutex::new(HashMap::new()));
+	let pending_bolt12_invoices = Arc::new(Mutex::new(HashMap::new()));
+
 	let om_mailbox = if let Some(AsyncPaymentsRole::Server) = async_payments_role {
 		Some(Arc::new(OnionMessageMailbox::new()))
 	} else {
@@ -1757,6 +2873,24 @@ fn build_with_store_internal(
 	let (stop_sender, _) = tokio::sync::watch::channel(());
 	let (background_processor_stop_sender, _) = tokio::sync::watch::channel(());
 	let is_running = Arc::new(RwLock::new(false));
+	// A previously persisted alias, i.e., one set via `Node::set_node_alias`, takes precedence over
+	// the configured one, as long as we're still configured to announce ourselves.
+	let node_alias = if config.node_alias.is_some() {
+		match io::utils::read_node_alias(Arc::clone(&kv_store), Arc::clone(&logger)) {
+			Ok(node_alias) => Some(node_alias),
+			Err(e) => {
+				if e.kind() == std::io::ErrorKind::NotFound {
+					config.node_alias
+				} else {
+					log_error!(logger, "Failed to read node alias: {}", e);
+					return Err(BuildError::ReadFailed);
+				}
+			},
+		}
+	} else {
+		None
+	};
+	let node_alias = Arc::new(RwLock::new(node_alias));
 
 	let pathfinding_scores_sync_url = pathfinding_scores_sync_config.map(|c| c.url.clone());
 
@@ -1767,13 +2901,21 @@ fn build_with_store_internal(
 		config,
 		wallet,
 		chain_source,
+		fee_estimator,
 		tx_broadcaster,
+		bump_tx_event_handler,
+		pending_channel_close_bumps,
+		channel_ready_waiters,
+		funding_confirmation_targets,
+		pending_external_fundings,
+		pending_bolt12_invoices,
 		event_queue,
 		channel_manager,
 		chain_monitor,
 		output_sweeper,
 		peer_manager,
 		onion_messenger,
+		message_router,
 		connection_manager,
 		keys_manager,
 		network_graph,
@@ -1782,14 +2924,30 @@ fn build_with_store_internal(
 		liquidity_source,
 		kv_store,
 		logger,
-		_router: router,
+		router,
 		scorer,
 		peer_store,
+		label_store,
+		peer_connection_policy,
+		power_mode,
+		default_offer,
 		payment_store,
+		closed_channel_store,
+		recurring_payment_store,
+		watchtower_client,
+		payment_retry_strategies,
+		payment_idempotency_keys,
+		claim_watchdog,
 		is_running,
+		started_at_unix_secs: RwLock::new(None),
+		node_alias,
 		node_metrics,
 		om_mailbox,
 		async_payments_role,
+		onion_service,
+		vss_store,
+		static_channel_backup_key,
+		event_dispatcher: Mutex::new(None),
 	})
 }
 
@@ -1812,18 +2970,48 @@ fn optionally_install_rustls_cryptoprovider() {
 	});
 }
 
+/// Reads the RPC credentials from the given Bitcoin Core cookie file.
+fn read_bitcoind_rpc_cookie(cookie_file_path: &str) -> Result<(String, String), std::io::Error> {
+	let cookie = fs::read_to_string(cookie_file_path)?;
+	let (rpc_user, rpc_password) = cookie.trim().split_once(':').ok_or_else(|| {
+		std::io::Error::new(
+			std::io::ErrorKind::InvalidData,
+			"Cookie is not of format user:password",
+		)
+	})?;
+	Ok((rpc_user.to_string(), rpc_password.to_string()))
+}
+
 /// Sets up the node logger.
 fn setup_logger(
-	log_writer_config: &Option<LogWriterConfig>, config: &Config,
+	log_writer_config: &Option<LogWriterConfig>, conflicting_loggers: bool, config: &Config,
 ) -> Result<Arc<Logger>, BuildError> {
+	if conflicting_loggers {
+		eprintln!("ERROR: Filesystem and JSON loggers are mutually exclusive.");
+		return Err(BuildError::ConflictingLoggers);
+	}
+
 	let logger = match log_writer_config {
-		Some(LogWriterConfig::File { log_file_path, max_log_level }) => {
+		Some(LogWriterConfig::File { log_file_path, max_log_level, module_log_levels }) => {
 			let log_file_path = log_file_path
 				.clone()
 				.unwrap_or_else(|| format!("{}/{}", config.storage_dir_path, DEFAULT_LOG_FILENAME));
 			let max_log_level = max_log_level.unwrap_or_else(|| DEFAULT_LOG_LEVEL);
+			let filter =
+				LogLevelFilter::new(max_log_level, module_log_levels.clone().unwrap_or_default());
+
+			Logger::new_fs_writer(log_file_path, filter)
+				.map_err(|_| BuildError::LoggerSetupFailed)?
+		},
+		Some(LogWriterConfig::Json { log_file_path, max_log_level, module_log_levels }) => {
+			let log_file_path = log_file_path.clone().unwrap_or_else(|| {
+				format!("{}/{}", config.storage_dir_path, DEFAULT_JSON_LOG_FILENAME)
+			});
+			let max_log_level = max_log_level.unwrap_or_else(|| DEFAULT_LOG_LEVEL);
+			let filter =
+				LogLevelFilter::new(max_log_level, module_log_levels.clone().unwrap_or_default());
 
-			Logger::new_fs_writer(log_file_path, max_log_level)
+			Logger::new_json_fs_writer(log_file_path, filter)
 				.map_err(|_| BuildError::LoggerSetupFailed)?
 		},
 		Some(LogWriterConfig::Log) => Logger::new_log_facade(),
@@ -1834,8 +3022,8 @@ fn setup_logger(
 		None => {
 			// Default to use `FileWriter`
 			let log_file_path = format!("{}/{}", config.storage_dir_path, DEFAULT_LOG_FILENAME);
-			let log_level = DEFAULT_LOG_LEVEL;
-			Logger::new_fs_writer(log_file_path, log_level)
+			let filter = LogLevelFilter::new(DEFAULT_LOG_LEVEL, HashMap::new());
+			Logger::new_fs_writer(log_file_path, filter)
 				.map_err(|_| BuildError::LoggerSetupFailed)?
 		},
 	};
@@ -1882,6 +3070,119 @@ fn derive_xprv(
 		})
 }
 
+// Checks the storage encryption key is consistent with the one the store was previously encrypted
+// with. If the store wasn't encrypted before, we first encrypt all values persisted so far and then
+// persist the check value, marking the migration complete.
+fn setup_storage_encryption(
+	kv_store: &Arc<DynStore>, encrypted_store: Option<&Arc<EncryptedStore>>,
+	migratable_store: Option<&Arc<DynMigratableStore>>, logger: &Arc<Logger>,
+) -> Result<(), BuildError> {
+	match encrypted_store {
+		Some(encrypted_store) => {
+			match KVStoreSync::read(
+				&**encrypted_store,
+				STORAGE_ENCRYPTION_CHECK_PRIMARY_NAMESPACE,
+				STORAGE_ENCRYPTION_CHECK_SECONDARY_NAMESPACE,
+				STORAGE_ENCRYPTION_CHECK_KEY,
+			) {
+				Ok(_) => return Ok(()),
+				Err(e) if e.kind() == lightning::io::ErrorKind::NotFound => {},
+				Err(e) if e.kind() == lightning::io::ErrorKind::InvalidData => {
+					log_error!(
+						logger,
+						"Failed to decrypt the store with the given encryption key."
+					);
+					return Err(BuildError::InvalidStorageEncryptionKey);
+				},
+				Err(e) => {
+					log_error!(logger, "Failed to read storage encryption check: {}", e);
+					return Err(BuildError::ReadFailed);
+				},
+			}
+
+			let keys = match migratable_store {
+				Some(migratable_store) => migratable_store.list_all_keys().map_err(|e| {
+					log_error!(logger, "Failed to list keys to encrypt: {}", e);
+					BuildError::ReadFailed
+				})?,
+				None => {
+					// We can't enumerate the keys of custom stores, so we only allow to start
+					// encrypting them if they're still empty, i.e., hold no channel manager yet.
+					match KVStoreSync::read(
+						&**kv_store,
+						CHANNEL_MANAGER_PERSISTENCE_PRIMARY_NAMESPACE,
+						CHANNEL_MANAGER_PERSISTENCE_SECONDARY_NAMESPACE,
+						CHANNEL_MANAGER_PERSISTENCE_KEY,
+					) {
+						Err(e) if e.kind() == lightning::io::ErrorKind::NotFound => Vec::new(),
+						Ok(_) => {
+							log_error!(
+								logger,
+								"Unable to encrypt existing data as the store's keys can't be listed."
+							);
+							return Err(BuildError::StorageEncryptionMigrationFailed);
+						},
+						Err(e) => {
+							log_error!(logger, "Failed to read channel manager: {}", e);
+							return Err(BuildError::ReadFailed);
+						},
+					}
+				},
+			};
+
+			if !keys.is_empty() {
+				log_info!(logger, "Encrypting {} persisted values.", keys.len());
+			}
+			encrypted_store.encrypt_plaintext_values(keys).map_err(|e| {
+				if e.kind() == lightning::io::ErrorKind::InvalidData {
+					log_error!(
+						logger,
+						"Failed to decrypt the store with the given encryption key."
+					);
+					BuildError::InvalidStorageEncryptionKey
+				} else {
+					log_error!(logger, "Failed to encrypt persisted values: {}", e);
+					BuildError::StorageEncryptionMigrationFailed
+				}
+			})?;
+
+			KVStoreSync::write(
+				&**encrypted_store,
+				STORAGE_ENCRYPTION_CHECK_PRIMARY_NAMESPACE,
+				STORAGE_ENCRYPTION_CHECK_SECONDARY_NAMESPACE,
+				STORAGE_ENCRYPTION_CHECK_KEY,
+				STORAGE_ENCRYPTION_CHECK_KEY.as_bytes().to_vec(),
+			)
+			.map_err(|e| {
+				log_error!(logger, "Failed to persist storage encryption check: {}", e);
+				BuildError::WriteFailed
+			})
+		},
+		None => match KVStoreSync::read(
+			&**kv_store,
+			STORAGE_ENCRYPTION_CHECK_PRIMARY_NAMESPACE,
+			STORAGE_ENCRYPTION_CHECK_SECONDARY_NAMESPACE,
+			STORAGE_ENCRYPTION_CHECK_KEY,
+		) {
+			Ok(buf) if is_encrypted_value(&buf) => {
+				log_error!(logger, "The store is encrypted but storage encryption is disabled.");
+				Err(BuildError::InvalidStorageEncryptionKey)
+			},
+			Ok(_) => Ok(()),
+			Err(e) if e.kind() == lightning::io::ErrorKind::NotFound => Ok(()),
+			Err(e) => {
+				log_error!(logger, "Failed to read storage encryption check: {}", e);
+				Err(BuildError::ReadFailed)
+			},
+		},
+	}
+}
+
+/// Returns whether the given list of addresses contains any address more than once.
+fn has_duplicate_addresses(addresses: &[SocketAddress]) -> bool {
+	addresses.iter().enumerate().any(|(i, addr)| addresses[..i].contains(addr))
+}
+
 /// Sanitize the user-provided node alias to ensure that it is a valid protocol-specified UTF-8 string.
 pub(crate) fn sanitize_alias(alias_str: &str) -> Result<NodeAlias, BuildError> {
 	let alias = alias_str.trim();
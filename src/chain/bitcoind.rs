@@ -28,29 +28,35 @@ use lightning_block_sync::{
 };
 use serde::Serialize;
 
-use super::{periodically_archive_fully_resolved_monitors, WalletSyncStatus};
+use super::{periodically_archive_fully_resolved_monitors, ServerReachability, WalletSyncStatus};
 use crate::config::{
-	BitcoindRestClientConfig, Config, FEE_RATE_CACHE_UPDATE_TIMEOUT_SECS, TX_BROADCAST_TIMEOUT_SECS,
+	BitcoindRestClientConfig, BitcoindSyncConfig, Config, FEE_RATE_CACHE_UPDATE_TIMEOUT_SECS,
+	TX_BROADCAST_TIMEOUT_SECS, WALLET_SYNC_INTERVAL_MINIMUM_SECS,
 };
+use crate::event::{Event, EventQueue};
 use crate::fee_estimator::{
 	apply_post_estimation_adjustments, get_all_conf_targets, get_num_block_defaults_for_target,
 	ConfirmationTarget, OnchainFeeEstimator,
 };
 use crate::io::utils::write_node_metrics;
 use crate::logger::{log_bytes, log_error, log_info, log_trace, LdkLogger, Logger};
-use crate::types::{ChainMonitor, ChannelManager, DynStore, Sweeper, Wallet};
+use crate::payment::claim_watchdog::ClaimDeadlineWatchdog;
+use crate::types::{ChainMonitor, ChannelManager, DynStore, PowerMode, Sweeper, Wallet};
 use crate::{Error, NodeMetrics};
 
 const CHAIN_POLLING_INTERVAL_SECS: u64 = 2;
 
 pub(super) struct BitcoindChainSource {
 	api_client: Arc<BitcoindClient>,
+	sync_config: BitcoindSyncConfig,
 	header_cache: tokio::sync::Mutex<BoundedHeaderCache>,
 	latest_chain_tip: RwLock<Option<ValidatedBlockHeader>>,
 	onchain_wallet: Arc<Wallet>,
 	wallet_polling_status: Mutex<WalletSyncStatus>,
 	fee_estimator: Arc<OnchainFeeEstimator>,
+	server_reachability: Mutex<ServerReachability>,
 	kv_store: Arc<DynStore>,
+	event_queue: Arc<EventQueue<Arc<Logger>>>,
 	config: Arc<Config>,
 	logger: Arc<Logger>,
 	node_metrics: Arc<RwLock<NodeMetrics>>,
@@ -59,8 +65,9 @@ pub(super) struct BitcoindChainSource {
 impl BitcoindChainSource {
 	pub(crate) fn new_rpc(
 		rpc_host: String, rpc_port: u16, rpc_user: String, rpc_password: String,
-		onchain_wallet: Arc<Wallet>, fee_estimator: Arc<OnchainFeeEstimator>,
-		kv_store: Arc<DynStore>, config: Arc<Config>, logger: Arc<Logger>,
+		sync_config: BitcoindSyncConfig, onchain_wallet: Arc<Wallet>,
+		fee_estimator: Arc<OnchainFeeEstimator>, kv_store: Arc<DynStore>,
+		event_queue: Arc<EventQueue<Arc<Logger>>>, config: Arc<Config>, logger: Arc<Logger>,
 		node_metrics: Arc<RwLock<NodeMetrics>>,
 	) -> Self {
 		let api_client = Arc::new(BitcoindClient::new_rpc(
@@ -73,14 +80,18 @@ impl BitcoindChainSource {
 		let header_cache = tokio::sync::Mutex::new(BoundedHeaderCache::new());
 		let latest_chain_tip = RwLock::new(None);
 		let wallet_polling_status = Mutex::new(WalletSyncStatus::Completed);
+		let server_reachability = Mutex::new(ServerReachability::new());
 		Self {
 			api_client,
+			sync_config,
 			header_cache,
 			latest_chain_tip,
 			onchain_wallet,
 			wallet_polling_status,
 			fee_estimator,
+			server_reachability,
 			kv_store,
+			event_queue,
 			config,
 			logger: Arc::clone(&logger),
 			node_metrics,
@@ -89,9 +100,11 @@ impl BitcoindChainSource {
 
 	pub(crate) fn new_rest(
 		rpc_host: String, rpc_port: u16, rpc_user: String, rpc_password: String,
-		onchain_wallet: Arc<Wallet>, fee_estimator: Arc<OnchainFeeEstimator>,
-		kv_store: Arc<DynStore>, config: Arc<Config>, rest_client_config: BitcoindRestClientConfig,
-		logger: Arc<Logger>, node_metrics: Arc<RwLock<NodeMetrics>>,
+		sync_config: BitcoindSyncConfig, onchain_wallet: Arc<Wallet>,
+		fee_estimator: Arc<OnchainFeeEstimator>, kv_store: Arc<DynStore>,
+		event_queue: Arc<EventQueue<Arc<Logger>>>, config: Arc<Config>,
+		rest_client_config: BitcoindRestClientConfig, logger: Arc<Logger>,
+		node_metrics: Arc<RwLock<NodeMetrics>>,
 	) -> Self {
 		let api_client = Arc::new(BitcoindClient::new_rest(
 			rest_client_config.rest_host,
@@ -105,15 +118,19 @@ impl BitcoindChainSource {
 		let header_cache = tokio::sync::Mutex::new(BoundedHeaderCache::new());
 		let latest_chain_tip = RwLock::new(None);
 		let wallet_polling_status = Mutex::new(WalletSyncStatus::Completed);
+		let server_reachability = Mutex::new(ServerReachability::new());
 
 		Self {
 			api_client,
+			sync_config,
 			header_cache,
 			latest_chain_tip,
 			wallet_polling_status,
 			onchain_wallet,
 			fee_estimator,
+			server_reachability,
 			kv_store,
+			event_queue,
 			config,
 			logger: Arc::clone(&logger),
 			node_metrics,
@@ -127,7 +144,8 @@ impl BitcoindChainSource {
 	pub(super) async fn continuously_sync_wallets(
 		&self, mut stop_sync_receiver: tokio::sync::watch::Receiver<()>,
 		channel_manager: Arc<ChannelManager>, chain_monitor: Arc<ChainMonitor>,
-		output_sweeper: Arc<Sweeper>,
+		output_sweeper: Arc<Sweeper>, claim_watchdog: Arc<ClaimDeadlineWatchdog>,
+		power_mode: Arc<RwLock<PowerMode>>,
 	) {
 		// First register for the wallet polling status to make sure `Node::sync_wallets` calls
 		// wait on the result before proceeding.
@@ -191,6 +209,7 @@ impl BitcoindChainSource {
 			.await
 			{
 				Ok(chain_tip) => {
+					self.update_server_reachability(true);
 					{
 						log_info!(
 							self.logger,
@@ -219,6 +238,7 @@ impl BitcoindChainSource {
 
 				Err(e) => {
 					log_error!(self.logger, "Failed to synchronize chain listeners: {:?}", e);
+					self.update_server_reachability(false);
 					if e.kind() == BlockSourceErrorKind::Transient {
 						log_info!(
 									self.logger,
@@ -244,12 +264,28 @@ impl BitcoindChainSource {
 		// Now propagate the initial result to unblock waiting subscribers.
 		self.wallet_polling_status.lock().unwrap().propagate_result_to_subscribers(Ok(()));
 
+		let background_sync_config = match self.sync_config.background_sync_config {
+			Some(background_sync_config) => background_sync_config,
+			None => {
+				log_info!(
+					self.logger,
+					"Background syncing is disabled. Manual syncing required for onchain wallet, lightning wallet, and fee rate updates.",
+				);
+				return;
+			},
+		};
+
+		// As polling our own node for new blocks is cheap, we keep doing so at a fixed, short
+		// interval rather than at the configured wallet sync intervals.
 		let mut chain_polling_interval =
 			tokio::time::interval(Duration::from_secs(CHAIN_POLLING_INTERVAL_SECS));
 		chain_polling_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
 
+		let fee_rate_cache_update_interval_secs = background_sync_config
+			.fee_rate_cache_update_interval_secs
+			.max(WALLET_SYNC_INTERVAL_MINIMUM_SECS);
 		let mut fee_rate_update_interval =
-			tokio::time::interval(Duration::from_secs(CHAIN_POLLING_INTERVAL_SECS));
+			tokio::time::interval(Duration::from_secs(fee_rate_cache_update_interval_secs));
 		// When starting up, we just blocked on updating, so skip the first tick.
 		fee_rate_update_interval.reset();
 		fee_rate_update_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
@@ -268,13 +304,20 @@ impl BitcoindChainSource {
 					return;
 				}
 				_ = chain_polling_interval.tick() => {
+					if *power_mode.read().unwrap() == PowerMode::LowPower {
+						continue;
+					}
 					let _ = self.poll_and_update_listeners(
 						Arc::clone(&channel_manager),
 						Arc::clone(&chain_monitor),
-						Arc::clone(&output_sweeper)
+						Arc::clone(&output_sweeper),
+						Arc::clone(&claim_watchdog),
 					).await;
 				}
 				_ = fee_rate_update_interval.tick() => {
+					if *power_mode.read().unwrap() == PowerMode::LowPower {
+						continue;
+					}
 					if last_best_block_hash != Some(channel_manager.current_best_block().block_hash) {
 						let update_res = self.update_fee_rate_estimates().await;
 						if update_res.is_ok() {
@@ -288,7 +331,7 @@ impl BitcoindChainSource {
 
 	pub(super) async fn poll_and_update_listeners(
 		&self, channel_manager: Arc<ChannelManager>, chain_monitor: Arc<ChainMonitor>,
-		output_sweeper: Arc<Sweeper>,
+		output_sweeper: Arc<Sweeper>, claim_watchdog: Arc<ClaimDeadlineWatchdog>,
 	) -> Result<(), Error> {
 		let receiver_res = {
 			let mut status_lock = self.wallet_polling_status.lock().unwrap();
@@ -305,7 +348,12 @@ impl BitcoindChainSource {
 		}
 
 		let res = self
-			.poll_and_update_listeners_inner(channel_manager, chain_monitor, output_sweeper)
+			.poll_and_update_listeners_inner(
+				channel_manager,
+				chain_monitor,
+				output_sweeper,
+				claim_watchdog,
+			)
 			.await;
 
 		self.wallet_polling_status.lock().unwrap().propagate_result_to_subscribers(res);
@@ -315,7 +363,7 @@ impl BitcoindChainSource {
 
 	async fn poll_and_update_listeners_inner(
 		&self, channel_manager: Arc<ChannelManager>, chain_monitor: Arc<ChainMonitor>,
-		output_sweeper: Arc<Sweeper>,
+		output_sweeper: Arc<Sweeper>, claim_watchdog: Arc<ClaimDeadlineWatchdog>,
 	) -> Result<(), Error> {
 		let latest_chain_tip_opt = self.latest_chain_tip.read().unwrap().clone();
 		let chain_tip = if let Some(tip) = latest_chain_tip_opt {
@@ -328,6 +376,7 @@ impl BitcoindChainSource {
 				},
 				Err(e) => {
 					log_error!(self.logger, "Failed to poll for chain data: {:?}", e);
+					self.update_server_reachability(false);
 					return Err(Error::TxSyncFailed);
 				},
 			}
@@ -340,6 +389,7 @@ impl BitcoindChainSource {
 			channel_manager: Arc::clone(&channel_manager),
 			chain_monitor: Arc::clone(&chain_monitor),
 			output_sweeper,
+			claim_watchdog,
 		};
 		let mut spv_client =
 			SpvClient::new(chain_tip, chain_poller, &mut *locked_header_cache, &chain_listener);
@@ -365,6 +415,7 @@ impl BitcoindChainSource {
 			Ok(_) => {},
 			Err(e) => {
 				log_error!(self.logger, "Failed to poll for chain data: {:?}", e);
+				self.update_server_reachability(false);
 				return Err(Error::TxSyncFailed);
 			},
 		}
@@ -394,10 +445,13 @@ impl BitcoindChainSource {
 			},
 			Err(e) => {
 				log_error!(self.logger, "Failed to poll for mempool transactions: {:?}", e);
+				self.update_server_reachability(false);
 				return Err(Error::TxSyncFailed);
 			},
 		}
 
+		self.update_server_reachability(true);
+
 		let unix_time_secs_opt =
 			SystemTime::now().duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs());
 		let mut locked_node_metrics = self.node_metrics.write().unwrap();
@@ -414,6 +468,11 @@ impl BitcoindChainSource {
 	}
 
 	pub(super) async fn update_fee_rate_estimates(&self) -> Result<(), Error> {
+		if self.fee_estimator.update_from_custom_fee_estimator() {
+			// A custom fee estimator takes the place of the chain source's fee estimation.
+			return Ok(());
+		}
+
 		macro_rules! get_fee_rate_update {
 			($estimation_fut:expr) => {{
 				let update_res = tokio::time::timeout(
@@ -434,20 +493,24 @@ impl BitcoindChainSource {
 		let now = Instant::now();
 		for target in confirmation_targets {
 			let fee_rate_update_res = match target {
-				ConfirmationTarget::Lightning(
-					LdkConfirmationTarget::MinAllowedAnchorChannelRemoteFee,
-				) => {
+				ConfirmationTarget::Lightning {
+					target: LdkConfirmationTarget::MinAllowedAnchorChannelRemoteFee,
+				} => {
 					let estimation_fut = self.api_client.get_mempool_minimum_fee_rate();
 					get_fee_rate_update!(estimation_fut)
 				},
-				ConfirmationTarget::Lightning(LdkConfirmationTarget::MaximumFeeEstimate) => {
+				ConfirmationTarget::Lightning {
+					target: LdkConfirmationTarget::MaximumFeeEstimate,
+				} => {
 					let num_blocks = get_num_block_defaults_for_target(target);
 					let estimation_mode = FeeRateEstimationMode::Conservative;
 					let estimation_fut =
 						self.api_client.get_fee_estimate_for_target(num_blocks, estimation_mode);
 					get_fee_rate_update!(estimation_fut)
 				},
-				ConfirmationTarget::Lightning(LdkConfirmationTarget::UrgentOnChainSweep) => {
+				ConfirmationTarget::Lightning {
+					target: LdkConfirmationTarget::UrgentOnChainSweep,
+				} => {
 					let num_blocks = get_num_block_defaults_for_target(target);
 					let estimation_mode = FeeRateEstimationMode::Conservative;
 					let estimation_fut =
@@ -531,6 +594,33 @@ impl BitcoindChainSource {
 		Ok(())
 	}
 
+	fn update_server_reachability(&self, reachable: bool) {
+		let unreachable_duration = {
+			let mut locked_reachability = self.server_reachability.lock().unwrap();
+			if reachable {
+				locked_reachability.record_success();
+				return;
+			}
+
+			let unavailable_timeout =
+				Duration::from_secs(self.sync_config.server_unavailable_timeout_secs);
+			locked_reachability.record_failure(Instant::now(), unavailable_timeout)
+		};
+
+		if let Some(unreachable_duration) = unreachable_duration {
+			log_error!(
+				self.logger,
+				"Unable to reach the Bitcoin Core node for {}s.",
+				unreachable_duration.as_secs()
+			);
+			let event =
+				Event::ChainSourceUnavailable { unreachable_secs: unreachable_duration.as_secs() };
+			if let Err(e) = self.event_queue.add_event(event) {
+				log_error!(self.logger, "Failed to push to event queue: {}", e);
+			}
+		}
+	}
+
 	pub(crate) async fn process_broadcast_package(&self, package: Vec<Transaction>) {
 		// While it's a bit unclear when we'd be able to lean on Bitcoin Core >v28
 		// features, we should eventually switch to use `submitpackage` via the
@@ -1364,6 +1454,7 @@ pub(crate) struct ChainListener {
 	pub(crate) channel_manager: Arc<ChannelManager>,
 	pub(crate) chain_monitor: Arc<ChainMonitor>,
 	pub(crate) output_sweeper: Arc<Sweeper>,
+	pub(crate) claim_watchdog: Arc<ClaimDeadlineWatchdog>,
 }
 
 impl Listen for ChainListener {
@@ -1375,12 +1466,14 @@ impl Listen for ChainListener {
 		self.channel_manager.filtered_block_connected(header, txdata, height);
 		self.chain_monitor.filtered_block_connected(header, txdata, height);
 		self.output_sweeper.filtered_block_connected(header, txdata, height);
+		self.claim_watchdog.filtered_block_connected(header, txdata, height);
 	}
 	fn block_connected(&self, block: &bitcoin::Block, height: u32) {
 		self.onchain_wallet.block_connected(block, height);
 		self.channel_manager.block_connected(block, height);
 		self.chain_monitor.block_connected(block, height);
 		self.output_sweeper.block_connected(block, height);
+		self.claim_watchdog.block_connected(block, height);
 	}
 
 	fn blocks_disconnected(&self, fork_point_block: lightning::chain::BestBlock) {
@@ -1388,6 +1481,7 @@ impl Listen for ChainListener {
 		self.channel_manager.blocks_disconnected(fork_point_block);
 		self.chain_monitor.blocks_disconnected(fork_point_block);
 		self.output_sweeper.blocks_disconnected(fork_point_block);
+		self.claim_watchdog.blocks_disconnected(fork_point_block);
 	}
 }
 
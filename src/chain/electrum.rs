@@ -23,18 +23,20 @@ use lightning::chain::{Confirm, Filter, WatchedOutput};
 use lightning::util::ser::Writeable;
 use lightning_transaction_sync::ElectrumSyncClient;
 
-use super::{periodically_archive_fully_resolved_monitors, WalletSyncStatus};
+use super::{periodically_archive_fully_resolved_monitors, ServerReachability, WalletSyncStatus};
 use crate::config::{
 	Config, ElectrumSyncConfig, BDK_CLIENT_STOP_GAP, BDK_WALLET_SYNC_TIMEOUT_SECS,
 	FEE_RATE_CACHE_UPDATE_TIMEOUT_SECS, LDK_WALLET_SYNC_TIMEOUT_SECS, TX_BROADCAST_TIMEOUT_SECS,
 };
 use crate::error::Error;
+use crate::event::{Event, EventQueue};
 use crate::fee_estimator::{
 	apply_post_estimation_adjustments, get_all_conf_targets, get_num_block_defaults_for_target,
 	ConfirmationTarget, OnchainFeeEstimator,
 };
 use crate::io::utils::write_node_metrics;
 use crate::logger::{log_bytes, log_error, log_info, log_trace, LdkLogger, Logger};
+use crate::payment::claim_watchdog::ClaimDeadlineWatchdog;
 use crate::runtime::Runtime;
 use crate::types::{ChainMonitor, ChannelManager, DynStore, Sweeper, Wallet};
 use crate::NodeMetrics;
@@ -43,6 +45,28 @@ const BDK_ELECTRUM_CLIENT_BATCH_SIZE: usize = 5;
 const ELECTRUM_CLIENT_NUM_RETRIES: u8 = 3;
 const ELECTRUM_CLIENT_TIMEOUT_SECS: u8 = 10;
 
+/// Parses the given Electrum server URL into the form expected by the Electrum client.
+///
+/// TLS endpoints may be given with an `ssl://` or `tls://` scheme, plaintext endpoints with a
+/// `tcp://` scheme. If no scheme is given, we assume a plaintext endpoint. In any case, the URL
+/// needs to include the port.
+pub(crate) fn parse_electrum_server_url(server_url: &str) -> Result<String, ()> {
+	let (scheme, host_and_port) = match server_url.trim().split_once("://") {
+		Some(("ssl", host_and_port)) | Some(("tls", host_and_port)) => ("ssl", host_and_port),
+		Some(("tcp", host_and_port)) => ("tcp", host_and_port),
+		Some(_) => return Err(()),
+		None => ("tcp", server_url.trim()),
+	};
+
+	let host_and_port = host_and_port.strip_suffix('/').unwrap_or(host_and_port);
+	let (host, port) = host_and_port.rsplit_once(':').ok_or(())?;
+	if host.is_empty() || host.contains('/') || port.parse::<u16>().is_err() {
+		return Err(());
+	}
+
+	Ok(format!("{}://{}:{}", scheme, host, port))
+}
+
 pub(super) struct ElectrumChainSource {
 	server_url: String,
 	pub(super) sync_config: ElectrumSyncConfig,
@@ -51,7 +75,9 @@ pub(super) struct ElectrumChainSource {
 	onchain_wallet_sync_status: Mutex<WalletSyncStatus>,
 	lightning_wallet_sync_status: Mutex<WalletSyncStatus>,
 	fee_estimator: Arc<OnchainFeeEstimator>,
+	server_reachability: Mutex<ServerReachability>,
 	kv_store: Arc<DynStore>,
+	event_queue: Arc<EventQueue<Arc<Logger>>>,
 	config: Arc<Config>,
 	logger: Arc<Logger>,
 	node_metrics: Arc<RwLock<NodeMetrics>>,
@@ -60,12 +86,14 @@ pub(super) struct ElectrumChainSource {
 impl ElectrumChainSource {
 	pub(super) fn new(
 		server_url: String, sync_config: ElectrumSyncConfig, onchain_wallet: Arc<Wallet>,
-		fee_estimator: Arc<OnchainFeeEstimator>, kv_store: Arc<DynStore>, config: Arc<Config>,
-		logger: Arc<Logger>, node_metrics: Arc<RwLock<NodeMetrics>>,
+		fee_estimator: Arc<OnchainFeeEstimator>, kv_store: Arc<DynStore>,
+		event_queue: Arc<EventQueue<Arc<Logger>>>, config: Arc<Config>, logger: Arc<Logger>,
+		node_metrics: Arc<RwLock<NodeMetrics>>,
 	) -> Self {
 		let electrum_runtime_status = RwLock::new(ElectrumRuntimeStatus::new());
 		let onchain_wallet_sync_status = Mutex::new(WalletSyncStatus::Completed);
 		let lightning_wallet_sync_status = Mutex::new(WalletSyncStatus::Completed);
+		let server_reachability = Mutex::new(ServerReachability::new());
 		Self {
 			server_url,
 			sync_config,
@@ -74,7 +102,9 @@ impl ElectrumChainSource {
 			onchain_wallet_sync_status,
 			lightning_wallet_sync_status,
 			fee_estimator,
+			server_reachability,
 			kv_store,
+			event_queue,
 			config,
 			logger: Arc::clone(&logger),
 			node_metrics,
@@ -126,6 +156,8 @@ impl ElectrumChainSource {
 				);
 				return Err(Error::FeerateEstimationUpdateFailed);
 			};
+		self.check_server_reconnect_backoff()?;
+
 		// If this is our first sync, do a full scan with the configured gap limit.
 		// Otherwise just do an incremental sync.
 		let incremental_sync =
@@ -169,6 +201,7 @@ impl ElectrumChainSource {
 
 			let now = Instant::now();
 			let update_res = incremental_sync_fut.await.map(|u| u.into());
+			self.update_server_reachability(&update_res);
 			apply_wallet_update(update_res, now)
 		} else {
 			let full_scan_request = self.onchain_wallet.get_full_scan_request();
@@ -176,6 +209,7 @@ impl ElectrumChainSource {
 				electrum_client.get_full_scan_wallet_update(full_scan_request, cached_txs);
 			let now = Instant::now();
 			let update_res = full_scan_fut.await.map(|u| u.into());
+			self.update_server_reachability(&update_res);
 			apply_wallet_update(update_res, now)
 		};
 
@@ -184,7 +218,7 @@ impl ElectrumChainSource {
 
 	pub(crate) async fn sync_lightning_wallet(
 		&self, channel_manager: Arc<ChannelManager>, chain_monitor: Arc<ChainMonitor>,
-		output_sweeper: Arc<Sweeper>,
+		output_sweeper: Arc<Sweeper>, claim_watchdog: Arc<ClaimDeadlineWatchdog>,
 	) -> Result<(), Error> {
 		let receiver_res = {
 			let mut status_lock = self.lightning_wallet_sync_status.lock().unwrap();
@@ -199,8 +233,14 @@ impl ElectrumChainSource {
 			})?;
 		}
 
-		let res =
-			self.sync_lightning_wallet_inner(channel_manager, chain_monitor, output_sweeper).await;
+		let res = self
+			.sync_lightning_wallet_inner(
+				channel_manager,
+				chain_monitor,
+				output_sweeper,
+				claim_watchdog,
+			)
+			.await;
 
 		self.lightning_wallet_sync_status.lock().unwrap().propagate_result_to_subscribers(res);
 
@@ -209,15 +249,17 @@ impl ElectrumChainSource {
 
 	async fn sync_lightning_wallet_inner(
 		&self, channel_manager: Arc<ChannelManager>, chain_monitor: Arc<ChainMonitor>,
-		output_sweeper: Arc<Sweeper>,
+		output_sweeper: Arc<Sweeper>, claim_watchdog: Arc<ClaimDeadlineWatchdog>,
 	) -> Result<(), Error> {
 		let sync_cman = Arc::clone(&channel_manager);
 		let sync_cmon = Arc::clone(&chain_monitor);
 		let sync_sweeper = Arc::clone(&output_sweeper);
+		let sync_claim_watchdog = Arc::clone(&claim_watchdog);
 		let confirmables = vec![
 			sync_cman as Arc<dyn Confirm + Sync + Send>,
 			sync_cmon as Arc<dyn Confirm + Sync + Send>,
 			sync_sweeper as Arc<dyn Confirm + Sync + Send>,
+			sync_claim_watchdog as Arc<dyn Confirm + Sync + Send>,
 		];
 
 		let electrum_client: Arc<ElectrumRuntimeClient> =
@@ -230,8 +272,10 @@ impl ElectrumChainSource {
 				);
 				return Err(Error::TxSyncFailed);
 			};
+		self.check_server_reconnect_backoff()?;
 
 		let res = electrum_client.sync_confirmables(confirmables).await;
+		self.update_server_reachability(&res);
 
 		if let Ok(_) = res {
 			let unix_time_secs_opt =
@@ -259,6 +303,11 @@ impl ElectrumChainSource {
 	}
 
 	pub(crate) async fn update_fee_rate_estimates(&self) -> Result<(), Error> {
+		if self.fee_estimator.update_from_custom_fee_estimator() {
+			// A custom fee estimator takes the place of the chain source's fee estimation.
+			return Ok(());
+		}
+
 		let electrum_client: Arc<ElectrumRuntimeClient> = if let Some(client) =
 			self.electrum_runtime_status.read().unwrap().client().as_ref()
 		{
@@ -267,10 +316,13 @@ impl ElectrumChainSource {
 			debug_assert!(false, "We should have started the chain source before updating fees");
 			return Err(Error::FeerateEstimationUpdateFailed);
 		};
+		self.check_server_reconnect_backoff()?;
 
 		let now = Instant::now();
 
-		let new_fee_rate_cache = electrum_client.get_fee_rate_cache_update().await?;
+		let update_res = electrum_client.get_fee_rate_cache_update().await;
+		self.update_server_reachability(&update_res);
+		let new_fee_rate_cache = update_res?;
 		self.fee_estimator.set_fee_rate_cache(new_fee_rate_cache);
 
 		log_info!(
@@ -307,6 +359,47 @@ impl ElectrumChainSource {
 			electrum_client.broadcast(tx).await;
 		}
 	}
+
+	fn check_server_reconnect_backoff(&self) -> Result<(), Error> {
+		// Note the Electrum client transparently reconnects when issuing the next request, so all
+		// we need to do here is to refrain from hammering an unreachable server in the meantime.
+		if self.server_reachability.lock().unwrap().should_attempt(Instant::now()) {
+			Ok(())
+		} else {
+			log_trace!(
+				self.logger,
+				"Skipping request as we're backing off from reconnecting to the Electrum server."
+			);
+			Err(Error::ConnectionFailed)
+		}
+	}
+
+	fn update_server_reachability<T>(&self, res: &Result<T, Error>) {
+		let unreachable_duration = {
+			let mut locked_reachability = self.server_reachability.lock().unwrap();
+			if res.is_ok() {
+				locked_reachability.record_success();
+				return;
+			}
+
+			let unavailable_timeout =
+				Duration::from_secs(self.sync_config.server_unavailable_timeout_secs);
+			locked_reachability.record_failure(Instant::now(), unavailable_timeout)
+		};
+
+		if let Some(unreachable_duration) = unreachable_duration {
+			log_error!(
+				self.logger,
+				"Unable to reach the Electrum server for {}s.",
+				unreachable_duration.as_secs()
+			);
+			let event =
+				Event::ChainSourceUnavailable { unreachable_secs: unreachable_duration.as_secs() };
+			if let Err(e) = self.event_queue.add_event(event) {
+				log_error!(self.logger, "Failed to push to event queue: {}", e);
+			}
+		}
+	}
 }
 
 impl Filter for ElectrumChainSource {
@@ -658,3 +751,35 @@ impl Filter for ElectrumRuntimeClient {
 		self.tx_sync.register_output(output)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn electrum_server_url_parsing() {
+		assert_eq!(
+			parse_electrum_server_url("ssl://electrum.example.com:50002"),
+			Ok("ssl://electrum.example.com:50002".to_string())
+		);
+		assert_eq!(
+			parse_electrum_server_url("tls://electrum.example.com:50002/"),
+			Ok("ssl://electrum.example.com:50002".to_string())
+		);
+		assert_eq!(
+			parse_electrum_server_url("tcp://127.0.0.1:50001"),
+			Ok("tcp://127.0.0.1:50001".to_string())
+		);
+		assert_eq!(
+			parse_electrum_server_url("127.0.0.1:50001"),
+			Ok("tcp://127.0.0.1:50001".to_string())
+		);
+		assert_eq!(parse_electrum_server_url("[::1]:50001"), Ok("tcp://[::1]:50001".to_string()));
+
+		assert!(parse_electrum_server_url("https://electrum.example.com:50002").is_err());
+		assert!(parse_electrum_server_url("ssl://electrum.example.com").is_err());
+		assert!(parse_electrum_server_url("tcp://:50001").is_err());
+		assert!(parse_electrum_server_url("tcp://127.0.0.1:port").is_err());
+		assert!(parse_electrum_server_url("tcp://127.0.0.1/path:50001").is_err());
+	}
+}
@@ -28,6 +28,7 @@ use crate::fee_estimator::{
 };
 use crate::io::utils::write_node_metrics;
 use crate::logger::{log_bytes, log_error, log_info, log_trace, LdkLogger, Logger};
+use crate::payment::claim_watchdog::ClaimDeadlineWatchdog;
 use crate::types::{ChainMonitor, ChannelManager, DynStore, Sweeper, Wallet};
 use crate::{Error, NodeMetrics};
 
@@ -204,7 +205,7 @@ impl EsploraChainSource {
 
 	pub(super) async fn sync_lightning_wallet(
 		&self, channel_manager: Arc<ChannelManager>, chain_monitor: Arc<ChainMonitor>,
-		output_sweeper: Arc<Sweeper>,
+		output_sweeper: Arc<Sweeper>, claim_watchdog: Arc<ClaimDeadlineWatchdog>,
 	) -> Result<(), Error> {
 		let receiver_res = {
 			let mut status_lock = self.lightning_wallet_sync_status.lock().unwrap();
@@ -219,8 +220,14 @@ impl EsploraChainSource {
 			})?;
 		}
 
-		let res =
-			self.sync_lightning_wallet_inner(channel_manager, chain_monitor, output_sweeper).await;
+		let res = self
+			.sync_lightning_wallet_inner(
+				channel_manager,
+				chain_monitor,
+				output_sweeper,
+				claim_watchdog,
+			)
+			.await;
 
 		self.lightning_wallet_sync_status.lock().unwrap().propagate_result_to_subscribers(res);
 
@@ -229,15 +236,17 @@ impl EsploraChainSource {
 
 	async fn sync_lightning_wallet_inner(
 		&self, channel_manager: Arc<ChannelManager>, chain_monitor: Arc<ChainMonitor>,
-		output_sweeper: Arc<Sweeper>,
+		output_sweeper: Arc<Sweeper>, claim_watchdog: Arc<ClaimDeadlineWatchdog>,
 	) -> Result<(), Error> {
 		let sync_cman = Arc::clone(&channel_manager);
 		let sync_cmon = Arc::clone(&chain_monitor);
 		let sync_sweeper = Arc::clone(&output_sweeper);
+		let sync_claim_watchdog = Arc::clone(&claim_watchdog);
 		let confirmables = vec![
 			&*sync_cman as &(dyn Confirm + Sync + Send),
 			&*sync_cmon as &(dyn Confirm + Sync + Send),
 			&*sync_sweeper as &(dyn Confirm + Sync + Send),
+			&*sync_claim_watchdog as &(dyn Confirm + Sync + Send),
 		];
 
 		let timeout_fut = tokio::time::timeout(
@@ -289,6 +298,11 @@ impl EsploraChainSource {
 	}
 
 	pub(crate) async fn update_fee_rate_estimates(&self) -> Result<(), Error> {
+		if self.fee_estimator.update_from_custom_fee_estimator() {
+			// A custom fee estimator takes the place of the chain source's fee estimation.
+			return Ok(());
+		}
+
 		let now = Instant::now();
 		let estimates = tokio::time::timeout(
 			Duration::from_secs(FEE_RATE_CACHE_UPDATE_TIMEOUT_SECS),
@@ -11,26 +11,35 @@ mod esplora;
 
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use bitcoin::{Script, Txid};
 use lightning::chain::Filter;
 use lightning_block_sync::gossip::UtxoSource;
 
 use crate::chain::bitcoind::BitcoindChainSource;
+pub(crate) use crate::chain::electrum::parse_electrum_server_url;
 use crate::chain::electrum::ElectrumChainSource;
 use crate::chain::esplora::EsploraChainSource;
 use crate::config::{
-	BackgroundSyncConfig, BitcoindRestClientConfig, Config, ElectrumSyncConfig, EsploraSyncConfig,
-	RESOLVED_CHANNEL_MONITOR_ARCHIVAL_INTERVAL, WALLET_SYNC_INTERVAL_MINIMUM_SECS,
+	BackgroundSyncConfig, BitcoindRestClientConfig, BitcoindSyncConfig, Config, ElectrumSyncConfig,
+	EsploraSyncConfig, RESOLVED_CHANNEL_MONITOR_ARCHIVAL_INTERVAL,
+	WALLET_SYNC_INTERVAL_MINIMUM_SECS,
 };
+use crate::event::EventQueue;
 use crate::fee_estimator::OnchainFeeEstimator;
 use crate::io::utils::write_node_metrics;
 use crate::logger::{log_debug, log_info, log_trace, LdkLogger, Logger};
+use crate::payment::claim_watchdog::ClaimDeadlineWatchdog;
 use crate::runtime::Runtime;
-use crate::types::{Broadcaster, ChainMonitor, ChannelManager, DynStore, Sweeper, Wallet};
+use crate::types::{
+	Broadcaster, ChainMonitor, ChannelManager, DynStore, PowerMode, Sweeper, Wallet,
+};
 use crate::{Error, NodeMetrics};
 
+const SERVER_RECONNECT_INITIAL_BACKOFF_SECS: u64 = 5;
+const SERVER_RECONNECT_MAX_BACKOFF_SECS: u64 = 60 * 5;
+
 pub(crate) enum WalletSyncStatus {
 	Completed,
 	InProgress { subscribers: tokio::sync::broadcast::Sender<Result<(), Error>> },
@@ -84,6 +93,53 @@ impl WalletSyncStatus {
 	}
 }
 
+/// Tracks whether we're able to reach the chain source's server, backing off exponentially from
+/// further attempts while we're not.
+pub(super) struct ServerReachability {
+	unreachable_since: Option<Instant>,
+	next_attempt_at: Option<Instant>,
+	backoff: Duration,
+	unavailable_event_emitted: bool,
+}
+
+impl ServerReachability {
+	pub(super) fn new() -> Self {
+		Self {
+			unreachable_since: None,
+			next_attempt_at: None,
+			backoff: Duration::from_secs(SERVER_RECONNECT_INITIAL_BACKOFF_SECS),
+			unavailable_event_emitted: false,
+		}
+	}
+
+	pub(super) fn should_attempt(&self, now: Instant) -> bool {
+		self.next_attempt_at.map_or(true, |next_attempt_at| now >= next_attempt_at)
+	}
+
+	pub(super) fn record_success(&mut self) {
+		*self = Self::new();
+	}
+
+	// Records a failed attempt to reach the server. Returns for how long the server has been
+	// unreachable if this is the first time we exceeded the given timeout during this outage.
+	pub(super) fn record_failure(
+		&mut self, now: Instant, unavailable_timeout: Duration,
+	) -> Option<Duration> {
+		let unreachable_since = *self.unreachable_since.get_or_insert(now);
+		self.next_attempt_at = Some(now + self.backoff);
+		self.backoff =
+			(self.backoff * 2).min(Duration::from_secs(SERVER_RECONNECT_MAX_BACKOFF_SECS));
+
+		let unreachable_duration = now.duration_since(unreachable_since);
+		if !self.unavailable_event_emitted && unreachable_duration >= unavailable_timeout {
+			self.unavailable_event_emitted = true;
+			Some(unreachable_duration)
+		} else {
+			None
+		}
+	}
+}
+
 pub(crate) struct ChainSource {
 	kind: ChainSourceKind,
 	tx_broadcaster: Arc<Broadcaster>,
@@ -93,7 +149,7 @@ pub(crate) struct ChainSource {
 enum ChainSourceKind {
 	Esplora(EsploraChainSource),
 	Electrum(ElectrumChainSource),
-	Bitcoind(BitcoindChainSource),
+	Bitcoind(Box<BitcoindChainSource>),
 }
 
 impl ChainSource {
@@ -121,8 +177,8 @@ impl ChainSource {
 	pub(crate) fn new_electrum(
 		server_url: String, sync_config: ElectrumSyncConfig, onchain_wallet: Arc<Wallet>,
 		fee_estimator: Arc<OnchainFeeEstimator>, tx_broadcaster: Arc<Broadcaster>,
-		kv_store: Arc<DynStore>, config: Arc<Config>, logger: Arc<Logger>,
-		node_metrics: Arc<RwLock<NodeMetrics>>,
+		kv_store: Arc<DynStore>, event_queue: Arc<EventQueue<Arc<Logger>>>, config: Arc<Config>,
+		logger: Arc<Logger>, node_metrics: Arc<RwLock<NodeMetrics>>,
 	) -> Self {
 		let electrum_chain_source = ElectrumChainSource::new(
 			server_url,
@@ -130,6 +186,7 @@ impl ChainSource {
 			onchain_wallet,
 			fee_estimator,
 			kv_store,
+			event_queue,
 			config,
 			Arc::clone(&logger),
 			node_metrics,
@@ -140,8 +197,9 @@ impl ChainSource {
 
 	pub(crate) fn new_bitcoind_rpc(
 		rpc_host: String, rpc_port: u16, rpc_user: String, rpc_password: String,
-		onchain_wallet: Arc<Wallet>, fee_estimator: Arc<OnchainFeeEstimator>,
-		tx_broadcaster: Arc<Broadcaster>, kv_store: Arc<DynStore>, config: Arc<Config>,
+		sync_config: BitcoindSyncConfig, onchain_wallet: Arc<Wallet>,
+		fee_estimator: Arc<OnchainFeeEstimator>, tx_broadcaster: Arc<Broadcaster>,
+		kv_store: Arc<DynStore>, event_queue: Arc<EventQueue<Arc<Logger>>>, config: Arc<Config>,
 		logger: Arc<Logger>, node_metrics: Arc<RwLock<NodeMetrics>>,
 	) -> Self {
 		let bitcoind_chain_source = BitcoindChainSource::new_rpc(
@@ -149,21 +207,24 @@ impl ChainSource {
 			rpc_port,
 			rpc_user,
 			rpc_password,
+			sync_config,
 			onchain_wallet,
 			fee_estimator,
 			kv_store,
+			event_queue,
 			config,
 			Arc::clone(&logger),
 			node_metrics,
 		);
-		let kind = ChainSourceKind::Bitcoind(bitcoind_chain_source);
+		let kind = ChainSourceKind::Bitcoind(Box::new(bitcoind_chain_source));
 		Self { kind, tx_broadcaster, logger }
 	}
 
 	pub(crate) fn new_bitcoind_rest(
 		rpc_host: String, rpc_port: u16, rpc_user: String, rpc_password: String,
-		onchain_wallet: Arc<Wallet>, fee_estimator: Arc<OnchainFeeEstimator>,
-		tx_broadcaster: Arc<Broadcaster>, kv_store: Arc<DynStore>, config: Arc<Config>,
+		sync_config: BitcoindSyncConfig, onchain_wallet: Arc<Wallet>,
+		fee_estimator: Arc<OnchainFeeEstimator>, tx_broadcaster: Arc<Broadcaster>,
+		kv_store: Arc<DynStore>, event_queue: Arc<EventQueue<Arc<Logger>>>, config: Arc<Config>,
 		rest_client_config: BitcoindRestClientConfig, logger: Arc<Logger>,
 		node_metrics: Arc<RwLock<NodeMetrics>>,
 	) -> Self {
@@ -172,15 +233,17 @@ impl ChainSource {
 			rpc_port,
 			rpc_user,
 			rpc_password,
+			sync_config,
 			onchain_wallet,
 			fee_estimator,
 			kv_store,
+			event_queue,
 			config,
 			rest_client_config,
 			Arc::clone(&logger),
 			node_metrics,
 		);
-		let kind = ChainSourceKind::Bitcoind(bitcoind_chain_source);
+		let kind = ChainSourceKind::Bitcoind(Box::new(bitcoind_chain_source));
 		Self { kind, tx_broadcaster, logger }
 	}
 
@@ -225,7 +288,8 @@ impl ChainSource {
 	pub(crate) async fn continuously_sync_wallets(
 		&self, stop_sync_receiver: tokio::sync::watch::Receiver<()>,
 		channel_manager: Arc<ChannelManager>, chain_monitor: Arc<ChainMonitor>,
-		output_sweeper: Arc<Sweeper>,
+		output_sweeper: Arc<Sweeper>, claim_watchdog: Arc<ClaimDeadlineWatchdog>,
+		power_mode: Arc<RwLock<PowerMode>>,
 	) {
 		match &self.kind {
 			ChainSourceKind::Esplora(esplora_chain_source) => {
@@ -237,6 +301,8 @@ impl ChainSource {
 						channel_manager,
 						chain_monitor,
 						output_sweeper,
+						claim_watchdog,
+						power_mode,
 						background_sync_config,
 						Arc::clone(&self.logger),
 					)
@@ -259,6 +325,8 @@ impl ChainSource {
 						channel_manager,
 						chain_monitor,
 						output_sweeper,
+						claim_watchdog,
+						power_mode,
 						background_sync_config,
 						Arc::clone(&self.logger),
 					)
@@ -279,6 +347,8 @@ impl ChainSource {
 						channel_manager,
 						chain_monitor,
 						output_sweeper,
+						claim_watchdog,
+						power_mode,
 					)
 					.await
 			},
@@ -288,7 +358,8 @@ impl ChainSource {
 	async fn start_tx_based_sync_loop(
 		&self, mut stop_sync_receiver: tokio::sync::watch::Receiver<()>,
 		channel_manager: Arc<ChannelManager>, chain_monitor: Arc<ChainMonitor>,
-		output_sweeper: Arc<Sweeper>, background_sync_config: &BackgroundSyncConfig,
+		output_sweeper: Arc<Sweeper>, claim_watchdog: Arc<ClaimDeadlineWatchdog>,
+		power_mode: Arc<RwLock<PowerMode>>, background_sync_config: &BackgroundSyncConfig,
 		logger: Arc<Logger>,
 	) {
 		// Setup syncing intervals
@@ -328,16 +399,26 @@ impl ChainSource {
 					return;
 				}
 				_ = onchain_wallet_sync_interval.tick() => {
+					if *power_mode.read().unwrap() == PowerMode::LowPower {
+						continue;
+					}
 					let _ = self.sync_onchain_wallet().await;
 				}
 				_ = fee_rate_update_interval.tick() => {
+					if *power_mode.read().unwrap() == PowerMode::LowPower {
+						continue;
+					}
 					let _ = self.update_fee_rate_estimates().await;
 				}
 				_ = lightning_wallet_sync_interval.tick() => {
+					if *power_mode.read().unwrap() == PowerMode::LowPower {
+						continue;
+					}
 					let _ = self.sync_lightning_wallet(
 						Arc::clone(&channel_manager),
 						Arc::clone(&chain_monitor),
 						Arc::clone(&output_sweeper),
+						Arc::clone(&claim_watchdog),
 						).await;
 				}
 			}
@@ -366,17 +447,27 @@ impl ChainSource {
 	// etc.)
 	pub(crate) async fn sync_lightning_wallet(
 		&self, channel_manager: Arc<ChannelManager>, chain_monitor: Arc<ChainMonitor>,
-		output_sweeper: Arc<Sweeper>,
+		output_sweeper: Arc<Sweeper>, claim_watchdog: Arc<ClaimDeadlineWatchdog>,
 	) -> Result<(), Error> {
 		match &self.kind {
 			ChainSourceKind::Esplora(esplora_chain_source) => {
 				esplora_chain_source
-					.sync_lightning_wallet(channel_manager, chain_monitor, output_sweeper)
+					.sync_lightning_wallet(
+						channel_manager,
+						chain_monitor,
+						output_sweeper,
+						claim_watchdog,
+					)
 					.await
 			},
 			ChainSourceKind::Electrum(electrum_chain_source) => {
 				electrum_chain_source
-					.sync_lightning_wallet(channel_manager, chain_monitor, output_sweeper)
+					.sync_lightning_wallet(
+						channel_manager,
+						chain_monitor,
+						output_sweeper,
+						claim_watchdog,
+					)
 					.await
 			},
 			ChainSourceKind::Bitcoind { .. } => {
@@ -389,7 +480,7 @@ impl ChainSource {
 
 	pub(crate) async fn poll_and_update_listeners(
 		&self, channel_manager: Arc<ChannelManager>, chain_monitor: Arc<ChainMonitor>,
-		output_sweeper: Arc<Sweeper>,
+		output_sweeper: Arc<Sweeper>, claim_watchdog: Arc<ClaimDeadlineWatchdog>,
 	) -> Result<(), Error> {
 		match &self.kind {
 			ChainSourceKind::Esplora { .. } => {
@@ -404,7 +495,12 @@ impl ChainSource {
 			},
 			ChainSourceKind::Bitcoind(bitcoind_chain_source) => {
 				bitcoind_chain_source
-					.poll_and_update_listeners(channel_manager, chain_monitor, output_sweeper)
+					.poll_and_update_listeners(
+						channel_manager,
+						chain_monitor,
+						output_sweeper,
+						claim_watchdog,
+					)
 					.await
 			},
 		}
@@ -439,6 +535,7 @@ impl ChainSource {
 					return;
 				}
 				Some(next_package) = receiver.recv() => {
+					self.tx_broadcaster.broadcast_via_external_wallet(&next_package).await;
 					match &self.kind {
 						ChainSourceKind::Esplora(esplora_chain_source) => {
 							esplora_chain_source.process_broadcast_package(next_package).await
@@ -450,6 +547,7 @@ impl ChainSource {
 							bitcoind_chain_source.process_broadcast_package(next_package).await
 						},
 					}
+					self.tx_broadcaster.package_processed();
 				}
 			}
 		}
@@ -499,3 +597,48 @@ fn periodically_archive_fully_resolved_monitors(
 	}
 	Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn server_reachability_backs_off_and_signals_unavailability() {
+		let timeout = Duration::from_secs(60);
+		let start = Instant::now();
+		let mut reachability = ServerReachability::new();
+		assert!(reachability.should_attempt(start));
+
+		// The first failure starts the backoff, but doesn't exceed the timeout yet.
+		assert_eq!(reachability.record_failure(start, timeout), None);
+		assert!(!reachability.should_attempt(start));
+		let first_retry = start + Duration::from_secs(SERVER_RECONNECT_INITIAL_BACKOFF_SECS);
+		assert!(reachability.should_attempt(first_retry));
+
+		// The backoff doubles with every further failure.
+		assert_eq!(reachability.record_failure(first_retry, timeout), None);
+		let second_retry =
+			first_retry + Duration::from_secs(2 * SERVER_RECONNECT_INITIAL_BACKOFF_SECS);
+		assert!(!reachability.should_attempt(second_retry - Duration::from_secs(1)));
+		assert!(reachability.should_attempt(second_retry));
+
+		// Once the timeout is exceeded we signal unavailability, but only once per outage.
+		let later = start + timeout;
+		assert_eq!(reachability.record_failure(later, timeout), Some(timeout));
+		let much_later = later + timeout;
+		assert_eq!(reachability.record_failure(much_later, timeout), None);
+
+		// The backoff is capped.
+		for _ in 0..10 {
+			reachability.record_failure(much_later, timeout);
+		}
+		assert!(reachability
+			.should_attempt(much_later + Duration::from_secs(SERVER_RECONNECT_MAX_BACKOFF_SECS)));
+
+		// After a success we start from scratch.
+		reachability.record_success();
+		assert!(reachability.should_attempt(much_later));
+		assert_eq!(reachability.record_failure(much_later, timeout), None);
+		assert_eq!(reachability.record_failure(much_later + timeout, timeout), Some(timeout));
+	}
+}
@@ -0,0 +1,172 @@
+// This file is Copyright its original authors, visible in version control history.
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. You may not use this file except in
+// accordance with one or both of these licenses.
+
+//! Holds the history of channels that have been closed.
+
+use bitcoin::secp256k1::PublicKey;
+use bitcoin::{OutPoint, Txid};
+use lightning::events::ClosureReason;
+use lightning::ln::types::ChannelId;
+use lightning::{impl_writeable_tlv_based, impl_writeable_tlv_based_enum};
+
+use crate::data_store::{StorableObject, StorableObjectId, StorableObjectUpdate};
+use crate::hex_utils;
+use crate::types::UserChannelId;
+
+/// The way in which a channel was closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloseType {
+	/// The channel was closed cooperatively, i.e., by a mutually-signed closing transaction.
+	Cooperative,
+	/// The channel was force-closed by us, e.g., as requested by the user or due to a protocol
+	/// error.
+	ForceLocal,
+	/// The channel was force-closed by the counterparty broadcasting their commitment transaction.
+	ForceRemote,
+	/// The channel was force-closed by us as HTLCs were about to time out on-chain.
+	HTLCTimeout,
+}
+
+impl_writeable_tlv_based_enum!(CloseType,
+	(0, Cooperative) => {},
+	(2, ForceLocal) => {},
+	(4, ForceRemote) => {},
+	(6, HTLCTimeout) => {}
+);
+
+impl From<&ClosureReason> for CloseType {
+	fn from(reason: &ClosureReason) -> Self {
+		match reason {
+			ClosureReason::LegacyCooperativeClosure
+			| ClosureReason::CounterpartyInitiatedCooperativeClosure
+			| ClosureReason::LocallyInitiatedCooperativeClosure
+			| ClosureReason::CounterpartyCoopClosedUnfundedChannel
+			| ClosureReason::LocallyCoopClosedUnfundedChannel => Self::Cooperative,
+			ClosureReason::CounterpartyForceClosed { .. }
+			| ClosureReason::CommitmentTxConfirmed => Self::ForceRemote,
+			ClosureReason::HTLCsTimedOut { .. } => Self::HTLCTimeout,
+			_ => Self::ForceLocal,
+		}
+	}
+}
+
+/// Details of a channel that has been closed.
+///
+/// Retrieved via [`Node::list_closed_channels`].
+///
+/// [`Node::list_closed_channels`]: crate::Node::list_closed_channels
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClosedChannelDetails {
+	/// The channel's ID.
+	pub channel_id: ChannelId,
+	/// The local `user_channel_id` of the channel.
+	pub user_channel_id: UserChannelId,
+	/// The node ID of the channel's counterparty.
+	pub counterparty_node_id: Option<PublicKey>,
+	/// The channel's funding transaction output.
+	pub funding_txo: Option<OutPoint>,
+	/// The value, in satoshis, of the channel as it appeared in the funding transaction.
+	pub channel_capacity_sats: Option<u64>,
+	/// The way in which the channel was closed.
+	pub close_type: CloseType,
+	/// The ID of the transaction spending the channel funding output.
+	///
+	/// This will be `None` if we didn't broadcast the transaction ourselves.
+	pub closing_txid: Option<Txid>,
+	/// The fee, in satoshis, paid by the transaction spending the channel funding output.
+	///
+	/// This will be `None` if we didn't broadcast the transaction ourselves.
+	pub closing_fee_sat: Option<u64>,
+	/// The amount, in satoshis, we recovered to our on-chain wallet from the channel.
+	///
+	/// For force-closes, our funds are only claimable after on-chain timelocks expire. This will
+	/// therefore be `None` at the time of closing and will be updated once the claimable outputs
+	/// are handed to our on-chain sweeper.
+	pub balance_recovered_sat: Option<u64>,
+	/// The timestamp, in seconds since start of the UNIX epoch, when the channel was closed.
+	pub closed_at_timestamp: u64,
+	/// The reason given when we force-closed the channel, e.g., via
+	/// [`Node::force_close_channel`] or [`Node::force_close_all_channels_with_peer`].
+	///
+	/// Will be `None` if the channel wasn't force-closed by us or if no reason was given.
+	///
+	/// [`Node::force_close_channel`]: crate::Node::force_close_channel
+	/// [`Node::force_close_all_channels_with_peer`]: crate::Node::force_close_all_channels_with_peer
+	pub force_close_reason: Option<String>,
+}
+
+impl_writeable_tlv_based!(ClosedChannelDetails, {
+	(0, channel_id, required),
+	(2, user_channel_id, required),
+	(4, counterparty_node_id, option),
+	(6, funding_txo, option),
+	(8, channel_capacity_sats, option),
+	(10, close_type, required),
+	(12, closing_txid, option),
+	(14, closing_fee_sat, option),
+	(16, balance_recovered_sat, option),
+	(18, closed_at_timestamp, required),
+	(20, force_close_reason, option),
+});
+
+impl StorableObjectId for ChannelId {
+	fn encode_to_hex_str(&self) -> String {
+		hex_utils::to_string(&self.0)
+	}
+}
+
+impl StorableObject for ClosedChannelDetails {
+	type Id = ChannelId;
+	type Update = ClosedChannelDetailsUpdate;
+	type IndexKey = ();
+	type Aggregate = ();
+
+	fn id(&self) -> Self::Id {
+		self.channel_id
+	}
+
+	fn index_key(&self) -> Option<Self::IndexKey> {
+		None
+	}
+
+	fn update(&mut self, update: &Self::Update) -> bool {
+		debug_assert_eq!(
+			self.channel_id, update.channel_id,
+			"We should only ever override closed channel data for the same channel id"
+		);
+
+		let mut updated = false;
+
+		if let Some(balance_recovered_sat) = update.balance_recovered_sat {
+			if self.balance_recovered_sat != balance_recovered_sat {
+				self.balance_recovered_sat = balance_recovered_sat;
+				updated = true;
+			}
+		}
+
+		updated
+	}
+
+	fn to_update(&self) -> Self::Update {
+		ClosedChannelDetailsUpdate {
+			channel_id: self.channel_id,
+			balance_recovered_sat: Some(self.balance_recovered_sat),
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct ClosedChannelDetailsUpdate {
+	pub channel_id: ChannelId,
+	pub balance_recovered_sat: Option<Option<u64>>,
+}
+
+impl StorableObjectUpdate<ClosedChannelDetails> for ClosedChannelDetailsUpdate {
+	fn id(&self) -> <ClosedChannelDetails as StorableObject>::Id {
+		self.channel_id
+	}
+}
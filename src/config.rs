@@ -7,19 +7,31 @@
 
 //! Objects for configuring the node.
 
+use std::collections::HashMap;
 use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr};
 use std::time::Duration;
 
+use bitcoin::hex::FromHex;
 use bitcoin::secp256k1::PublicKey;
-use bitcoin::Network;
+use bitcoin::{Address, Network};
+use lightning::chain::chaininterface::ConfirmationTarget as LdkConfirmationTarget;
+use lightning::ln::channelmanager::{Retry, MIN_CLTV_EXPIRY_DELTA, MIN_FINAL_CLTV_EXPIRY_DELTA};
 use lightning::ln::msgs::SocketAddress;
+use lightning::ln::types::ChannelId;
 use lightning::routing::gossip::NodeAlias;
-use lightning::routing::router::RouteParametersConfig;
+use lightning::routing::router::{PaymentParameters, RouteParameters, RouteParametersConfig};
+use lightning::routing::scoring::ProbabilisticScoringFeeParameters;
+use lightning::types::features::{InitFeatures, NodeFeatures};
 use lightning::util::config::{
-	ChannelConfig as LdkChannelConfig, MaxDustHTLCExposure as LdkMaxDustHTLCExposure, UserConfig,
+	ChannelConfig as LdkChannelConfig, ChannelConfigUpdate as LdkChannelConfigUpdate,
+	MaxDustHTLCExposure as LdkMaxDustHTLCExposure, UserConfig,
 };
+use lightning::{impl_writeable_tlv_based, impl_writeable_tlv_based_enum};
 
+use crate::fee_estimator::ConfirmationTarget;
 use crate::logger::LogLevel;
+use crate::Error;
 
 // Config defaults
 const DEFAULT_NETWORK: Network = Network::Bitcoin;
@@ -28,6 +40,27 @@ const DEFAULT_LDK_WALLET_SYNC_INTERVAL_SECS: u64 = 30;
 const DEFAULT_FEE_RATE_CACHE_UPDATE_INTERVAL_SECS: u64 = 60 * 10;
 const DEFAULT_PROBING_LIQUIDITY_LIMIT_MULTIPLIER: u64 = 3;
 const DEFAULT_ANCHOR_PER_CHANNEL_RESERVE_SATS: u64 = 25_000;
+const DEFAULT_ELECTRUM_SERVER_UNAVAILABLE_TIMEOUT_SECS: u64 = 60 * 5;
+const DEFAULT_BITCOIND_SERVER_UNAVAILABLE_TIMEOUT_SECS: u64 = 60 * 5;
+const DEFAULT_INBOUND_MIN_FUNDING_SATS: u64 = 1000;
+const DEFAULT_INBOUND_MAX_FUNDING_SATS: u64 = (1 << 24) - 1;
+const DEFAULT_SCORER_PERSIST_INTERVAL_SECS: u64 = 60 * 10;
+const DEFAULT_SCORING_BASE_PENALTY_MSAT: u64 = 1024;
+const DEFAULT_SCORING_HISTORICAL_LIQUIDITY_PENALTY_MULTIPLIER_MSAT: u64 = 10_000;
+const DEFAULT_SWEEP_BATCH_WINDOW_SECS: u64 = 60 * 10;
+const DEFAULT_PEER_RECONNECT_INITIAL_BACKOFF_SECS: u64 = 60;
+const DEFAULT_PEER_RECONNECT_MAX_BACKOFF_SECS: u64 = 60 * 60;
+const DEFAULT_CLTV_EXPIRY_DELTA: u16 = 144;
+
+// The maximum number of HTLCs a channel counterparty may offer us, as per BOLT 2.
+const MAX_ACCEPTED_HTLCS_LIMIT: u16 = 483;
+
+// The CLTV expiry delta below which we consider forwarding over our channels on mainnet unsafe, i.e.,
+// LDK's default of 12 hours.
+const MAINNET_RECOMMENDED_MIN_CLTV_EXPIRY_DELTA: u16 = 6 * 12;
+
+// The maximum number of route hints we include in a BOLT11 invoice.
+pub(crate) const MAX_INVOICE_ROUTE_HINTS: usize = 3;
 
 /// The default log level.
 pub const DEFAULT_LOG_LEVEL: LogLevel = LogLevel::Debug;
@@ -35,6 +68,9 @@ pub const DEFAULT_LOG_LEVEL: LogLevel = LogLevel::Debug;
 /// The default log file name.
 pub const DEFAULT_LOG_FILENAME: &'static str = "ldk_node.log";
 
+/// The default JSON log file name.
+pub const DEFAULT_JSON_LOG_FILENAME: &str = "ldk_node.json.log";
+
 /// The default storage directory.
 pub const DEFAULT_STORAGE_DIR_PATH: &str = "/tmp/ldk_node";
 
@@ -57,8 +93,26 @@ pub(crate) const LDK_PAYMENT_RETRY_TIMEOUT: Duration = Duration::from_secs(10);
 // The interval (in block height) after which we retry archiving fully resolved channel monitors.
 pub(crate) const RESOLVED_CHANNEL_MONITOR_ARCHIVAL_INTERVAL: u32 = 6;
 
-// The time in-between peer reconnection attempts.
-pub(crate) const PEER_RECONNECTION_INTERVAL: Duration = Duration::from_secs(60);
+// The time we wait for the counterparty to accept a channel we open with an externally signed
+// funding transaction.
+pub(crate) const EXTERNAL_FUNDING_ACCEPT_TIMEOUT: Duration = Duration::from_secs(30);
+
+// The time after which we abandon a channel open if the signed funding PSBT wasn't handed back.
+//
+// Note that LDK abandons unfunded channels after roughly an hour, so this needs to stay well below.
+pub(crate) const EXTERNAL_FUNDING_PSBT_TIMEOUT: Duration = Duration::from_secs(60 * 30);
+
+// The time in-between checking whether any pending external funding expired.
+pub(crate) const EXTERNAL_FUNDING_TIMEOUT_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+// The time in-between checking whether any sweeps of our spendable outputs confirmed.
+pub(crate) const SWEEP_CONFIRMATION_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+// The time in-between checking whether any batched sweeps of our spendable outputs are due.
+pub(crate) const SWEEP_BATCH_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+// The time in-between checking the status of pending LSPS1 orders.
+pub(crate) const LSPS1_ORDER_STATUS_POLL_INTERVAL: Duration = Duration::from_secs(60);
 
 // The time in-between RGS sync attempts.
 pub(crate) const RGS_SYNC_INTERVAL: Duration = Duration::from_secs(60 * 60);
@@ -66,12 +120,29 @@ pub(crate) const RGS_SYNC_INTERVAL: Duration = Duration::from_secs(60 * 60);
 // The time in-between external scores sync attempts.
 pub(crate) const EXTERNAL_PATHFINDING_SCORES_SYNC_INTERVAL: Duration = Duration::from_secs(60 * 60);
 
+// The timeout after which we abort a request to an LNURL service.
+#[cfg(feature = "lnurl")]
+pub(crate) const LNURL_REQUEST_TIMEOUT_SECS: u64 = 30;
+
+// The expiry of the invoices we hand to LNURL-withdraw services.
+#[cfg(feature = "lnurl")]
+pub(crate) const LNURL_WITHDRAW_INVOICE_EXPIRY_SECS: u32 = 3600;
+
+// The length of the time buckets we account forwarding fees in.
+pub(crate) const FORWARDING_FEES_BUCKET_SECS: u64 = 60 * 60 * 24;
+
+// The number of most recently accounted forwards we remember to detect them being replayed.
+pub(crate) const MAX_RECENT_FORWARDS: usize = 100;
+
 // The time in-between node announcement broadcast attempts.
 pub(crate) const NODE_ANN_BCAST_INTERVAL: Duration = Duration::from_secs(60 * 60);
 
 // The lower limit which we apply to any configured wallet sync intervals.
 pub(crate) const WALLET_SYNC_INTERVAL_MINIMUM_SECS: u64 = 10;
 
+// The lower limit which we apply to the configured scorer persistence interval.
+pub(crate) const SCORER_PERSIST_INTERVAL_MINIMUM_SECS: u64 = 10;
+
 // The timeout after which we abort a wallet syncing operation.
 pub(crate) const BDK_WALLET_SYNC_TIMEOUT_SECS: u64 = 20;
 
@@ -93,12 +164,68 @@ pub(crate) const TX_BROADCAST_TIMEOUT_SECS: u64 = 5;
 // The timeout after which we abort a RGS sync operation.
 pub(crate) const RGS_SYNC_TIMEOUT_SECS: u64 = 5;
 
+// The time in-between attempts to deliver queued channel states to the watchtower.
+pub(crate) const WATCHTOWER_SYNC_INTERVAL: Duration = Duration::from_secs(30);
+
+// The timeout after which we abort a request to the watchtower.
+pub(crate) const WATCHTOWER_REQUEST_TIMEOUT_SECS: u64 = 10;
+
+// The interval in which we check whether our channels were closed when closing all channels.
+pub(crate) const CLOSE_ALL_CHANNELS_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// The maximum dust limit we accept for inbound channels, i.e., the dust threshold of the most
+// restrictive standard output type.
+pub(crate) const MAX_INBOUND_DUST_LIMIT_SATS: u64 = 546;
+
+// A conservative estimate of the weight of a child transaction bumping a commitment transaction,
+// i.e., spending our anchor output and a single P2WPKH wallet input to a P2WPKH change output.
+pub(crate) const ANCHOR_CHILD_TX_WEIGHT_ESTIMATE: u64 = 720;
+
+// The interval at which the event dispatcher checks whether it should stop while waiting for events.
+pub(crate) const EVENT_DISPATCHER_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// The delay after which we redeliver an event that a registered event handler failed to handle.
+pub(crate) const EVENT_HANDLER_REPLAY_DELAY: Duration = Duration::from_secs(1);
+
 /// The length in bytes of our wallets' keys seed.
 pub const WALLET_KEYS_SEED_LEN: usize = 64;
 
+// The time we wait for the recipient to respond to an invoice request sent via
+// `Bolt12Payment::request_invoice`.
+pub(crate) const BOLT12_INVOICE_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+// The interval in which we persist any changes to the connection history of our persisted peers.
+pub(crate) const PEER_CONNECTION_HISTORY_PERSIST_INTERVAL: Duration = Duration::from_secs(60);
+
+// The interval in which we abandon the payments of BOLT12 invoices that were requested via
+// `Bolt12Payment::request_invoice` but expired before they were paid.
+pub(crate) const BOLT12_INVOICE_EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+// The interval in which we forget about the fee bumps of channel closes whose commitment
+// transaction confirmed in the meantime.
+pub(crate) const PENDING_CHANNEL_CLOSE_BUMPS_PRUNE_INTERVAL: Duration = Duration::from_secs(600);
+
 // The timeout after which we abort a external scores sync operation.
 pub(crate) const EXTERNAL_PATHFINDING_SCORES_SYNC_TIMEOUT_SECS: u64 = 5;
 
+/// The lowest TLV type that may be used for custom onion messages.
+///
+/// Note that some TLV types above this are used by protocols handled by LDK, e.g., BOLT12 offers,
+/// and can't be used for custom onion messages either.
+pub const MIN_CUSTOM_ONION_MESSAGE_TLV_TYPE: u64 = 64;
+
+/// The maximum number of intermediate nodes on a path given to send a custom onion message along.
+pub const MAX_ONION_MESSAGE_PATH_INTERMEDIATE_NODES: usize = 20;
+
+// The time window over which we bound the number of custom onion messages we surface.
+pub(crate) const CUSTOM_ONION_MESSAGE_RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+// The maximum number of custom onion messages of any single TLV type we surface per time window.
+pub(crate) const MAX_CUSTOM_ONION_MESSAGES_PER_TLV_TYPE: usize = 20;
+
+// The maximum number of custom onion messages we surface per time window across all TLV types.
+pub(crate) const MAX_CUSTOM_ONION_MESSAGES_PER_WINDOW: usize = 100;
+
 #[derive(Debug, Clone)]
 /// Represents the configuration of an [`Node`] instance.
 ///
@@ -111,7 +238,7 @@ pub(crate) const EXTERNAL_PATHFINDING_SCORES_SYNC_TIMEOUT_SECS: u64 = 5;
 /// | `network`                              | Bitcoin            |
 /// | `listening_addresses`                  | None               |
 /// | `node_alias`                           | None               |
-/// | `default_cltv_expiry_delta`            | 144                |
+/// | `node_color`                           | [0, 0, 0]          |
 /// | `onchain_wallet_sync_interval_secs`    | 80                 |
 /// | `wallet_sync_interval_secs`            | 30                 |
 /// | `fee_rate_cache_update_interval_secs`  | 600                |
@@ -120,9 +247,31 @@ pub(crate) const EXTERNAL_PATHFINDING_SCORES_SYNC_TIMEOUT_SECS: u64 = 5;
 /// | `log_level`                            | Debug              |
 /// | `anchor_channels_config`               | Some(..)           |
 /// | `route_parameters`                   | None               |
+/// | `manual_claim_auto_fail_margin_blocks` | None               |
+/// | `inbound_channel_limits`               | Default::default() |
+/// | `enable_rbf_by_default`                | true               |
+/// | `scorer_persist_interval_secs`         | 600                |
+/// | `scoring_params`                       | Default::default() |
+/// | `accept_intercept_htlcs`               | false              |
+/// | `peer_connection_policy`               | Default::default() |
+/// | `channel_htlc_limits`                  | None               |
+/// | `accept_spontaneous_payments`          | false              |
+/// | `invoice_route_hint_policy`            | AllUsable          |
+/// | `max_dust_htlc_exposure_msat`          | None               |
+/// | `features`                             | Default::default() |
+/// | `spendable_output_sweep_destination`   | None               |
+/// | `spendable_output_sweep_config`        | None               |
+/// | `default_cltv_expiry_delta`            | 144                |
+/// | `min_final_cltv_expiry_delta`          | 42                 |
+/// | `peer_reconnect_policy`                | Default::default() |
+/// | `encrypt_storage`                      | false              |
+/// | `encrypt_storage_with`                 | None               |
+/// | `accept_custom_onion_messages`         | false              |
+/// | `enable_bolt12_invoice_requests`       | false              |
 ///
-/// See [`AnchorChannelsConfig`] and [`RouteParametersConfig`] for more information regarding their
-/// respective default values.
+/// See [`AnchorChannelsConfig`], [`RouteParametersConfig`], [`InboundChannelLimits`],
+/// [`FeatureConfig`], [`SpendableOutputSweepConfig`], and [`PeerReconnectPolicy`] for more
+/// information regarding their respective default values.
 ///
 /// [`Node`]: crate::Node
 pub struct Config {
@@ -148,11 +297,28 @@ pub struct Config {
 	/// **Note**: We will only allow opening and accepting public channels if the `node_alias` and the
 	/// `listening_addresses` are set.
 	pub node_alias: Option<NodeAlias>,
+	/// The RGB color that will be used when broadcasting announcements to the gossip network.
+	///
+	/// Like the [`node_alias`], this is shown for our node in graph explorers and visualizers. It
+	/// is only included in our announcements if the node may announce channels.
+	///
+	/// [`node_alias`]: Config::node_alias
+	pub node_color: [u8; 3],
 	/// A list of peers that we allow to establish zero confirmation channels to us.
 	///
+	/// Channels opened to us by these peers are usable immediately, i.e., we'll emit an
+	/// [`Event::ChannelReady`] without waiting for the funding transaction to confirm. This allows
+	/// LSPs opening just-in-time channels to forward the payment over the channel right away.
+	/// Channels opened by any other peer will be accepted, but only become usable once the funding
+	/// transaction has confirmed. However, requests from other peers that explicitly negotiate the
+	/// zero-conf channel type will be rejected with [`ChannelRejectReason::UntrustedZeroConf`].
+	///
 	/// **Note:** Allowing payments via zero-confirmation channels is potentially insecure if the
 	/// funding transaction ends up never being confirmed on-chain. Zero-confirmation channels
 	/// should therefore only be accepted from trusted peers.
+	///
+	/// [`Event::ChannelReady`]: crate::Event::ChannelReady
+	/// [`ChannelRejectReason::UntrustedZeroConf`]: crate::ChannelRejectReason::UntrustedZeroConf
 	pub trusted_peers_0conf: Vec<PublicKey>,
 	/// The liquidity factor by which we filter the outgoing channels used for sending probes.
 	///
@@ -184,6 +350,223 @@ pub struct Config {
 	/// **Note:** If unset, default parameters will be used, and you will be able to override the
 	/// parameters on a per-payment basis in the corresponding method calls.
 	pub route_parameters: Option<RouteParametersConfig>,
+	/// The number of blocks before a manually-claimable payment's `claim_deadline` at which we
+	/// automatically fail it back.
+	///
+	/// Payments registered via the `_for_hash` variants (e.g., [`receive_for_hash`] or
+	/// [`receive_via_jit_channel_for_hash`]) need to be claimed manually via [`claim_for_hash`]. If
+	/// they are neither claimed nor failed before the HTLCs expire, our counterparty will
+	/// force-close the channel to claim them back on-chain. If set, we'll call the equivalent of
+	/// [`fail_for_hash`] once the current best block height is within this many blocks of the
+	/// `claim_deadline`, and emit an [`Event::PaymentAutoFailed`].
+	///
+	/// Payments that we claim automatically are unaffected by this setting.
+	///
+	/// **Note:** If unset, manually-claimable payments will never be failed back automatically.
+	///
+	/// [`receive_for_hash`]: crate::payment::Bolt11Payment::receive_for_hash
+	/// [`receive_via_jit_channel_for_hash`]: crate::payment::Bolt11Payment::receive_via_jit_channel_for_hash
+	/// [`claim_for_hash`]: crate::payment::Bolt11Payment::claim_for_hash
+	/// [`fail_for_hash`]: crate::payment::Bolt11Payment::fail_for_hash
+	/// [`Event::PaymentAutoFailed`]: crate::Event::PaymentAutoFailed
+	pub manual_claim_auto_fail_margin_blocks: Option<u32>,
+	/// The limits we enforce on channels opened to us by our peers.
+	///
+	/// Please refer to [`InboundChannelLimits`] for further information.
+	pub inbound_channel_limits: InboundChannelLimits,
+	/// Whether the on-chain transactions created by our wallet signal replaceability via
+	/// replace-by-fee (RBF, as per BIP 125).
+	///
+	/// Signalling RBF allows to bump the fee of stuck transactions via
+	/// [`OnchainPayment::bump_fee`] later on. If set to `false`, transactions will be created
+	/// non-replaceable.
+	///
+	/// [`OnchainPayment::bump_fee`]: crate::payment::OnchainPayment::bump_fee
+	pub enable_rbf_by_default: bool,
+	/// The time in-between persisting the state of our pathfinding scorer, in seconds.
+	///
+	/// The scorer learns about the liquidity available in the channels of the network as we send
+	/// payments. Persisting it allows to retain this knowledge across restarts. The scorer is
+	/// additionally persisted when the node is stopped.
+	///
+	/// **Note:** A minimum of 10 seconds is enforced.
+	pub scorer_persist_interval_secs: u64,
+	/// Parameters tuning the penalties our pathfinding scorer assigns to channels.
+	///
+	/// Please refer to [`ScoringParameters`] for further information.
+	pub scoring_params: ScoringParameters,
+	/// Whether we intercept HTLCs forwarded to intercept SCIDs and hand them to the user.
+	///
+	/// If set, HTLCs that are to be forwarded over an SCID retrieved via
+	/// [`Node::new_intercept_scid`] are held and an [`Event::HTLCIntercepted`] is emitted. The user
+	/// then needs to either forward them via [`Node::forward_intercepted_htlc`], e.g., after
+	/// opening a just-in-time channel to the recipient, or fail them via
+	/// [`Node::fail_intercepted_htlc`].
+	///
+	/// **Note:** If we also act as an LSPS2 service, the event is emitted for all intercepted HTLCs,
+	/// including the ones paying for just-in-time channels negotiated by the service. As these are
+	/// handled automatically, only HTLCs requesting to be forwarded over an SCID previously
+	/// retrieved via [`Node::new_intercept_scid`] should be acted upon.
+	///
+	/// [`Node::new_intercept_scid`]: crate::Node::new_intercept_scid
+	/// [`Event::HTLCIntercepted`]: crate::Event::HTLCIntercepted
+	/// [`Node::forward_intercepted_htlc`]: crate::Node::forward_intercepted_htlc
+	/// [`Node::fail_intercepted_htlc`]: crate::Node::fail_intercepted_htlc
+	pub accept_intercept_htlcs: bool,
+	/// The policy deciding which peers may open inbound connections to us.
+	///
+	/// Note that once the policy was updated at runtime via [`Node::set_peer_connection_policy`],
+	/// the persisted policy takes precedence over this value.
+	///
+	/// Please refer to [`PeerConnectionPolicy`] for further information.
+	///
+	/// [`Node::set_peer_connection_policy`]: crate::Node::set_peer_connection_policy
+	pub peer_connection_policy: PeerConnectionPolicy,
+	/// The limits on in-flight HTLCs we negotiate for new channels.
+	///
+	/// Please refer to [`ChannelHtlcLimits`] for further information.
+	///
+	/// **Note:** If unset, LDK's defaults will be used.
+	pub channel_htlc_limits: Option<ChannelHtlcLimits>,
+	/// Whether we accept spontaneous inbound payments, also known as 'keysend' payments.
+	///
+	/// Spontaneous payments carry their preimage in the onion and hence can be received without
+	/// handing out an invoice first, e.g., by streaming or donation endpoints. If set, they are
+	/// claimed automatically and surfaced via [`Event::PaymentReceived`]. Otherwise, any inbound
+	/// spontaneous payment is failed back.
+	///
+	/// [`Event::PaymentReceived`]: crate::Event::PaymentReceived
+	pub accept_spontaneous_payments: bool,
+	/// The policy deciding which of our channels we include as route hints in BOLT11 invoices.
+	///
+	/// May be overridden per invoice via [`Bolt11Payment::receive_with_route_hints`].
+	///
+	/// Please refer to [`InvoiceRouteHintPolicy`] for further information.
+	///
+	/// [`Bolt11Payment::receive_with_route_hints`]: crate::payment::Bolt11Payment::receive_with_route_hints
+	pub invoice_route_hint_policy: InvoiceRouteHintPolicy,
+	/// The fixed limit, in millisatoshis, on the total dust exposure of our channels.
+	///
+	/// HTLCs that would be trimmed from the commitment transaction, i.e., that are below the dust
+	/// limit, are not enforceable on-chain and would be lost to fees on force-closure. LDK
+	/// therefore limits the total value of such HTLCs per channel and rejects any further dust
+	/// HTLCs once the limit is reached. The default limit, which scales with the current fee rate,
+	/// may be too conservative for nodes routing many small payments.
+	///
+	/// **Note:** Raising this limit increases the value we may lose to fee griefing, i.e., if a
+	/// malicious counterparty fills the channel with dust HTLCs and forces us to go on-chain, or if
+	/// fee rates spike while dust HTLCs are pending. Only raise it as far as you are comfortable
+	/// losing the full amount per channel.
+	///
+	/// If set, the limit is applied to newly opened channels, unless overridden by a
+	/// [`ChannelConfig`] given when opening the channel, and to all existing channels whenever the
+	/// node is started. If unset, LDK's default is used for new channels and existing channels
+	/// retain their current limit.
+	///
+	/// Note that the on-chain reserve kept for Anchor channels can be tuned via
+	/// [`AnchorChannelsConfig::per_channel_reserve_sats`].
+	pub max_dust_htlc_exposure_msat: Option<u64>,
+	/// The optional features we advertise to our peers and the network.
+	///
+	/// Please refer to [`FeatureConfig`] for further information.
+	pub features: FeatureConfig,
+	/// The address to which any outputs recovered from our closed channels are swept.
+	///
+	/// After channels are force-closed, the funds we recover from them can only be spent after
+	/// some delay. If set, we'll sweep them to the given address, e.g., to a cold storage wallet,
+	/// once they become spendable. If unset, they're swept into our on-chain wallet.
+	///
+	/// Note that this doesn't affect cooperative closures, whose funds are paid directly to our
+	/// on-chain wallet. An [`Event::SpendableOutputsSwept`] is emitted once a sweep confirmed.
+	///
+	/// The address needs to be valid for the configured [`Network`].
+	///
+	/// [`Event::SpendableOutputsSwept`]: crate::Event::SpendableOutputsSwept
+	pub spendable_output_sweep_destination: Option<Address>,
+	/// Configuration options for batching the sweeps of outputs recovered from closed channels.
+	///
+	/// If unset, we'll sweep any such output as soon as it becomes spendable, using the fee rate
+	/// of the `OutputSpendingFee` [`ConfirmationTarget::Lightning`] target.
+	///
+	/// Please refer to [`SpendableOutputSweepConfig`] for further information.
+	pub spendable_output_sweep_config: Option<SpendableOutputSweepConfig>,
+	/// The difference in the CLTV value between incoming HTLCs and an outbound HTLC forwarded over
+	/// our channels.
+	///
+	/// This is the time, in blocks, we have to claim an incoming HTLC on-chain after the outbound
+	/// HTLC it was forwarded as got resolved. It applies to newly opened channels, unless
+	/// overridden by a [`ChannelConfig`] given when opening the channel, and can be updated for
+	/// existing channels via [`Node::update_channel_config`].
+	///
+	/// Building the node will fail with [`BuildError::InvalidCltvExpiryDelta`] if this is set
+	/// below LDK's minimum of [`MIN_CLTV_EXPIRY_DELTA`]. We'll also log a warning if it is set
+	/// below 72 blocks on mainnet.
+	///
+	/// [`Node::update_channel_config`]: crate::Node::update_channel_config
+	/// [`BuildError::InvalidCltvExpiryDelta`]: crate::BuildError::InvalidCltvExpiryDelta
+	pub default_cltv_expiry_delta: u16,
+	/// The `min_final_cltv_expiry_delta` included in the BOLT11 invoices we create.
+	///
+	/// This is the number of blocks we require to be left before an inbound payment's HTLCs expire
+	/// once they reach us, i.e., the time we have to claim them on-chain if need be. It may be
+	/// overridden for individual invoices via
+	/// [`Bolt11Payment::receive_with_min_final_cltv_expiry_delta`]. For invoices requesting
+	/// payments via a just-in-time channel, we'll add 2 blocks on top, as required by LSPS2.
+	///
+	/// Building the node will fail with [`BuildError::InvalidCltvExpiryDelta`] if this is set
+	/// below LDK's minimum of [`MIN_FINAL_CLTV_EXPIRY_DELTA`].
+	///
+	/// [`Bolt11Payment::receive_with_min_final_cltv_expiry_delta`]: crate::payment::Bolt11Payment::receive_with_min_final_cltv_expiry_delta
+	/// [`BuildError::InvalidCltvExpiryDelta`]: crate::BuildError::InvalidCltvExpiryDelta
+	pub min_final_cltv_expiry_delta: u16,
+	/// The policy deciding how we retry connecting to persisted peers we got disconnected from.
+	///
+	/// Please refer to [`PeerReconnectPolicy`] for further information.
+	pub peer_reconnect_policy: PeerReconnectPolicy,
+	/// Whether all data persisted by the node should be encrypted at rest.
+	///
+	/// If enabled, every value written to the underlying [`KVStore`] is encrypted and
+	/// authenticated individually using ChaCha20-Poly1305, binding it to its namespaces and key.
+	/// Unless a key is given via [`encrypt_storage_with`], the encryption key is derived from the
+	/// node's seed. Namespaces and keys are not encrypted.
+	///
+	/// Existing stores persisted without encryption can be migrated by simply enabling this
+	/// option: all values persisted so far are encrypted while building the node. From then on,
+	/// any value found in plaintext is rejected. Note that existing custom stores given via
+	/// [`Builder::build_with_store`] can't be migrated.
+	///
+	/// Building the node will fail with [`BuildError::InvalidStorageEncryptionKey`] if the store
+	/// was encrypted with a different key, or if this is disabled for a store that was previously
+	/// encrypted.
+	///
+	/// [`KVStore`]: lightning::util::persist::KVStore
+	/// [`encrypt_storage_with`]: Config::encrypt_storage_with
+	/// [`Builder::build_with_store`]: crate::Builder::build_with_store
+	/// [`BuildError::InvalidStorageEncryptionKey`]: crate::BuildError::InvalidStorageEncryptionKey
+	pub encrypt_storage: bool,
+	/// The key used to encrypt all data persisted by the node at rest.
+	///
+	/// Setting this implies [`encrypt_storage`]. The same key needs to be given whenever the node
+	/// is built from the same store.
+	///
+	/// [`encrypt_storage`]: Config::encrypt_storage
+	pub encrypt_storage_with: Option<[u8; 32]>,
+	/// If set to `true`, custom onion messages we receive will be surfaced as
+	/// [`Event::OnionMessageReceived`].
+	///
+	/// As anybody can send us onion messages, we only surface a bounded number of messages per
+	/// TLV type and minute, dropping any messages beyond that.
+	///
+	/// [`Event::OnionMessageReceived`]: crate::Event::OnionMessageReceived
+	pub accept_custom_onion_messages: bool,
+	/// If set to `true`, invoices for BOLT12 offers may be requested via
+	/// [`Bolt12Payment::request_invoice`] and inspected before they're paid.
+	///
+	/// This requires us to handle all BOLT12 invoices we receive ourselves rather than leaving it
+	/// to LDK, which is why it's disabled by default.
+	///
+	/// [`Bolt12Payment::request_invoice`]: crate::payment::Bolt12Payment::request_invoice
+	pub enable_bolt12_invoice_requests: bool,
 }
 
 impl Default for Config {
@@ -198,10 +581,42 @@ impl Default for Config {
 			anchor_channels_config: Some(AnchorChannelsConfig::default()),
 			route_parameters: None,
 			node_alias: None,
+			node_color: [0; 3],
+			manual_claim_auto_fail_margin_blocks: None,
+			inbound_channel_limits: InboundChannelLimits::default(),
+			enable_rbf_by_default: true,
+			scorer_persist_interval_secs: DEFAULT_SCORER_PERSIST_INTERVAL_SECS,
+			scoring_params: ScoringParameters::default(),
+			accept_intercept_htlcs: false,
+			peer_connection_policy: PeerConnectionPolicy::default(),
+			channel_htlc_limits: None,
+			accept_spontaneous_payments: false,
+			invoice_route_hint_policy: InvoiceRouteHintPolicy::default(),
+			max_dust_htlc_exposure_msat: None,
+			features: FeatureConfig::default(),
+			spendable_output_sweep_destination: None,
+			spendable_output_sweep_config: None,
+			default_cltv_expiry_delta: DEFAULT_CLTV_EXPIRY_DELTA,
+			min_final_cltv_expiry_delta: MIN_FINAL_CLTV_EXPIRY_DELTA,
+			peer_reconnect_policy: PeerReconnectPolicy::default(),
+			encrypt_storage: false,
+			encrypt_storage_with: None,
+			accept_custom_onion_messages: false,
+			enable_bolt12_invoice_requests: false,
 		}
 	}
 }
 
+impl Config {
+	/// Returns the features we advertise, i.e., [`Config::features`] with keysend support cleared
+	/// if we don't accept spontaneous payments.
+	pub(crate) fn advertised_features(&self) -> FeatureConfig {
+		let mut features = self.features;
+		features.keysend &= self.accept_spontaneous_payments;
+		features
+	}
+}
+
 /// Configuration options pertaining to 'Anchor' channels, i.e., channels for which the
 /// `option_anchors_zero_fee_htlc_tx` channel type is negotiated.
 ///
@@ -226,6 +641,7 @@ impl Default for Config {
 /// |----------------------------|--------|
 /// | `trusted_peers_no_reserve` | []     |
 /// | `per_channel_reserve_sats` | 25000  |
+/// | `require_reserve`          | true   |
 ///
 ///
 /// [BOLT 3]: https://github.com/lightning/bolts/blob/master/03-transactions.md#htlc-timeout-and-htlc-success-transactions
@@ -260,6 +676,26 @@ pub struct AnchorChannelsConfig {
 	/// might not suffice to successfully spend the Anchor output and have the HTLC transactions
 	/// confirmed on-chain, i.e., you may want to adjust this value accordingly.
 	pub per_channel_reserve_sats: u64,
+	/// Whether we maintain an on-chain reserve for Anchor channels at all.
+	///
+	/// If set to `false`, no reserve will be retained for any channel, i.e., as if all peers were
+	/// listed in [`AnchorChannelsConfig::trusted_peers_no_reserve`], except that we'll still try
+	/// to get channel closing transactions confirmed ourselves. On-chain funds can then be spent
+	/// freely, and channels will be opened and accepted regardless of the remaining balance.
+	///
+	/// **Warning:** This is dangerous and should only be used in controlled environments, e.g.,
+	/// for testing or nodes that exclusively have channels with a trusted LSP. If the on-chain
+	/// wallet is empty when a channel is force-closed, we won't be able to bump the fee of the
+	/// commitment and HTLC transactions, which may then fail to confirm in time and result in
+	/// loss of funds.
+	pub require_reserve: bool,
+}
+
+impl AnchorChannelsConfig {
+	/// Returns whether we need to retain a reserve for Anchor channels with the given peer.
+	pub(crate) fn requires_reserve_for(&self, counterparty_node_id: &PublicKey) -> bool {
+		self.require_reserve && !self.trusted_peers_no_reserve.contains(counterparty_node_id)
+	}
 }
 
 impl Default for AnchorChannelsConfig {
@@ -267,6 +703,491 @@ impl Default for AnchorChannelsConfig {
 		Self {
 			trusted_peers_no_reserve: Vec::new(),
 			per_channel_reserve_sats: DEFAULT_ANCHOR_PER_CHANNEL_RESERVE_SATS,
+			require_reserve: true,
+		}
+	}
+}
+
+/// Limits on the HTLCs our channel counterparties may have in-flight to us at any given time.
+///
+/// These limits are negotiated during the channel opening handshake and hence only apply to
+/// channels opened after they were configured, both the ones we open and the ones we accept.
+/// Existing channels are unaffected.
+///
+/// Building the node will fail with [`BuildError::InvalidChannelHtlcLimits`] if any of the limits
+/// is out of range.
+///
+/// [`BuildError::InvalidChannelHtlcLimits`]: crate::BuildError::InvalidChannelHtlcLimits
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ChannelHtlcLimits {
+	/// The maximum number of HTLCs our counterparty may offer us at once.
+	///
+	/// Needs to be between `1` and `483`, the maximum allowed by the protocol.
+	pub max_accepted_htlcs: u16,
+	/// The maximum total value of HTLCs our counterparty may offer us at once, as a percentage of
+	/// the channel value.
+	///
+	/// Needs to be between `1` and `100`.
+	pub max_htlc_value_in_flight_percent: u8,
+}
+
+impl ChannelHtlcLimits {
+	pub(crate) fn is_valid(&self) -> bool {
+		(1..=MAX_ACCEPTED_HTLCS_LIMIT).contains(&self.max_accepted_htlcs)
+			&& (1..=100).contains(&self.max_htlc_value_in_flight_percent)
+	}
+}
+
+/// Options allowing to disable optional features we'd otherwise advertise to our peers in `init`
+/// messages and to the network in our node announcement.
+///
+/// Some counterparties misbehave if certain features are advertised, so disabling them may be
+/// necessary for compatibility or be useful for testing.
+///
+/// Some features depend on others, e.g., `option_zeroconf` may only be advertised alongside
+/// `option_scid_alias`. Building the node will fail with [`BuildError::InvalidFeatureConfig`] if a
+/// feature is enabled without its prerequisite.
+///
+/// ### Defaults
+///
+/// | Parameter        | Value |
+/// |------------------|-------|
+/// | `scid_alias`     | true  |
+/// | `zero_conf`      | true  |
+/// | `keysend`        | true  |
+/// | `route_blinding` | true  |
+///
+/// [`BuildError::InvalidFeatureConfig`]: crate::BuildError::InvalidFeatureConfig
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FeatureConfig {
+	/// Whether we advertise support for `option_scid_alias`, i.e., for referring to private
+	/// channels by an alias rather than by their real short channel id.
+	pub scid_alias: bool,
+	/// Whether we advertise support for `option_zeroconf`, i.e., for channels that may be used
+	/// before their funding transaction confirms.
+	///
+	/// If disabled, we'll also reject zero-confirmation channels from
+	/// [`Config::trusted_peers_0conf`].
+	///
+	/// Requires [`FeatureConfig::scid_alias`] to be enabled.
+	pub zero_conf: bool,
+	/// Whether we advertise support for receiving keysend payments in our node announcement.
+	///
+	/// Note that this doesn't affect whether we accept such payments, which is governed by
+	/// [`Config::accept_spontaneous_payments`]. If we don't accept them, we never advertise
+	/// support.
+	pub keysend: bool,
+	/// Whether we advertise support for `option_route_blinding`, i.e., for forwarding payments
+	/// along blinded paths.
+	///
+	/// Note that we may still include blinded paths in the BOLT12 offers and invoices we create.
+	pub route_blinding: bool,
+}
+
+impl FeatureConfig {
+	pub(crate) fn is_valid(&self) -> bool {
+		self.scid_alias || !self.zero_conf
+	}
+
+	pub(crate) fn clear_disabled_init_features(&self, features: &mut InitFeatures) {
+		if !self.scid_alias {
+			features.clear_scid_privacy();
+		}
+		if !self.zero_conf {
+			*features = InitFeatures::from_le_bytes(clear_zero_conf_bits(features.le_flags()));
+		}
+		if !self.route_blinding {
+			features.clear_route_blinding();
+		}
+	}
+
+	pub(crate) fn clear_disabled_node_features(&self, features: &mut NodeFeatures) {
+		if !self.scid_alias {
+			features.clear_scid_privacy();
+		}
+		if !self.zero_conf {
+			*features = NodeFeatures::from_le_bytes(clear_zero_conf_bits(features.le_flags()));
+		}
+		if !self.keysend {
+			features.clear_keysend();
+		}
+		if !self.route_blinding {
+			features.clear_route_blinding();
+		}
+	}
+}
+
+// LDK doesn't provide a setter clearing `option_zeroconf`, so we clear its required (50) and
+// optional (51) bits from the little-endian feature flags directly.
+fn clear_zero_conf_bits(le_flags: &[u8]) -> Vec<u8> {
+	let mut flags = le_flags.to_vec();
+	if let Some(byte) = flags.get_mut(50 / 8) {
+		*byte &= !((1 << (50 % 8)) | (1 << (51 % 8)));
+	}
+	while flags.last() == Some(&0) {
+		flags.pop();
+	}
+	flags
+}
+
+impl Default for FeatureConfig {
+	fn default() -> Self {
+		Self { scid_alias: true, zero_conf: true, keysend: true, route_blinding: true }
+	}
+}
+
+/// Options for batching the sweeps of outputs recovered from closed channels.
+///
+/// By default, any output recovered after a channel closure is swept as soon as it becomes
+/// spendable, which results in a sweeping transaction per output if many channels are closed at
+/// about the same time, e.g., if a peer force-closes all of its channels with us. If configured,
+/// we'll instead wait for up to [`batch_window_secs`] after an output became spendable for
+/// further outputs to become spendable, and sweep all of them in a single transaction.
+///
+/// Outputs are never swept before they mature, i.e., before any relative or absolute timelocks
+/// they're encumbered with expired. The outputs currently awaiting a sweep can be retrieved via
+/// [`Node::pending_sweeps`].
+///
+/// ### Defaults
+///
+/// | Parameter             | Value                        |
+/// |-----------------------|------------------------------|
+/// | `batch_window_secs`   | 600                          |
+/// | `confirmation_target` | Lightning(OutputSpendingFee) |
+///
+/// [`batch_window_secs`]: Self::batch_window_secs
+/// [`Node::pending_sweeps`]: crate::Node::pending_sweeps
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SpendableOutputSweepConfig {
+	/// The time, in seconds, we wait after an output became spendable for further outputs to
+	/// become spendable before sweeping all of them in a single transaction.
+	///
+	/// Note that the batch window restarts for any outputs still awaiting their sweep when the
+	/// node is restarted.
+	pub batch_window_secs: u64,
+	/// The confirmation target determining the fee rate of our sweeping transactions.
+	///
+	/// This also applies to any replacements we broadcast if a sweeping transaction doesn't
+	/// confirm in time.
+	pub confirmation_target: ConfirmationTarget,
+}
+
+impl Default for SpendableOutputSweepConfig {
+	fn default() -> Self {
+		Self {
+			batch_window_secs: DEFAULT_SWEEP_BATCH_WINDOW_SECS,
+			confirmation_target: LdkConfirmationTarget::OutputSpendingFee.into(),
+		}
+	}
+}
+
+/// The policy deciding how we retry connecting to persisted peers we're not connected to.
+///
+/// We try to reconnect to any such peer every [`initial_backoff_secs`]. For every consecutive
+/// failed attempt, we double the time we wait before the next attempt, up to
+/// [`max_backoff_secs`]. If [`max_attempts`] is set, we stop trying to reconnect to a peer after
+/// that many consecutive failed attempts and emit an [`Event::PeerConnectionAbandoned`].
+///
+/// Building the node will fail with [`BuildError::InvalidPeerReconnectPolicy`] if
+/// [`initial_backoff_secs`] is zero or exceeds [`max_backoff_secs`], or if [`max_attempts`] is
+/// set to zero.
+///
+/// ### Defaults
+///
+/// | Parameter              | Value |
+/// |------------------------|-------|
+/// | `initial_backoff_secs` | 60    |
+/// | `max_backoff_secs`     | 3600  |
+/// | `max_attempts`         | None  |
+///
+/// [`initial_backoff_secs`]: Self::initial_backoff_secs
+/// [`max_backoff_secs`]: Self::max_backoff_secs
+/// [`max_attempts`]: Self::max_attempts
+/// [`Event::PeerConnectionAbandoned`]: crate::Event::PeerConnectionAbandoned
+/// [`BuildError::InvalidPeerReconnectPolicy`]: crate::BuildError::InvalidPeerReconnectPolicy
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PeerReconnectPolicy {
+	/// The time, in seconds, in-between reconnection attempts to a peer that we haven't failed to
+	/// reconnect to yet.
+	pub initial_backoff_secs: u64,
+	/// The maximum time, in seconds, we back off before retrying to connect to a peer.
+	pub max_backoff_secs: u64,
+	/// The number of consecutive failed attempts after which we stop trying to reconnect to a
+	/// peer.
+	///
+	/// Once abandoned, we won't try to reconnect to the peer until the node is restarted or we
+	/// successfully connected to it via [`Node::connect`]. If unset, we'll keep retrying
+	/// indefinitely.
+	///
+	/// [`Node::connect`]: crate::Node::connect
+	pub max_attempts: Option<u32>,
+}
+
+impl PeerReconnectPolicy {
+	pub(crate) fn is_valid(&self) -> bool {
+		self.initial_backoff_secs > 0
+			&& self.initial_backoff_secs <= self.max_backoff_secs
+			&& self.max_attempts != Some(0)
+	}
+
+	/// Returns the time we wait before the next attempt after the given number of consecutive
+	/// failed attempts.
+	pub(crate) fn backoff(&self, consecutive_failures: u32) -> Duration {
+		let exponent = consecutive_failures.saturating_sub(1).min(16);
+		let backoff_secs = self.initial_backoff_secs.saturating_mul(1 << exponent);
+		Duration::from_secs(backoff_secs.min(self.max_backoff_secs))
+	}
+}
+
+impl Default for PeerReconnectPolicy {
+	fn default() -> Self {
+		Self {
+			initial_backoff_secs: DEFAULT_PEER_RECONNECT_INITIAL_BACKOFF_SECS,
+			max_backoff_secs: DEFAULT_PEER_RECONNECT_MAX_BACKOFF_SECS,
+			max_attempts: None,
+		}
+	}
+}
+
+/// The policy deciding which of our channels we include as route hints in BOLT11 invoices.
+///
+/// Route hints allow payers to reach us via channels they can't learn about from the gossip
+/// network, e.g., our private channels. To keep invoices scannable, we include at most
+/// three route hints, preferring the channels with the highest inbound capacity.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvoiceRouteHintPolicy {
+	/// Include route hints for any channels we may receive the payment over, as selected by LDK.
+	AllUsable,
+	/// Include route hints for the usable channels with the highest inbound capacity.
+	HighestInbound,
+	/// Only include route hints for the given channels.
+	///
+	/// Creating an invoice will fail with [`Error::InvalidChannelId`] if none of the given
+	/// channels is usable.
+	///
+	/// [`Error::InvalidChannelId`]: crate::NodeError::InvalidChannelId
+	Explicit {
+		/// The IDs of the channels to include route hints for.
+		channel_ids: Vec<ChannelId>,
+	},
+}
+
+impl Default for InvoiceRouteHintPolicy {
+	fn default() -> Self {
+		Self::AllUsable
+	}
+}
+
+/// Limits we enforce on channels opened to us by our peers.
+///
+/// Inbound channel requests violating these limits will be rejected, for which we emit an
+/// [`Event::ChannelRejected`] with [`ChannelRejectReason::ExceedsLimits`].
+///
+/// ### Defaults
+///
+/// | Parameter                            | Value      |
+/// |--------------------------------------|------------|
+/// | `min_funding_satoshis`               | 1000       |
+/// | `max_funding_satoshis`               | 16777215   |
+/// | `max_htlc_minimum_msat`              | u64::MAX   |
+/// | `force_announced_channel_preference` | false      |
+/// | `per_peer_overrides`                 | {}         |
+///
+/// [`Event::ChannelRejected`]: crate::Event::ChannelRejected
+/// [`ChannelRejectReason::ExceedsLimits`]: crate::ChannelRejectReason::ExceedsLimits
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InboundChannelLimits {
+	/// The minimum channel value, in satoshis, we accept for inbound channels.
+	pub min_funding_satoshis: u64,
+	/// The maximum channel value, in satoshis, we accept for inbound channels.
+	///
+	/// **Note:** Channels larger than `16777215` satoshis (i.e., 'wumbo' channels) are only
+	/// accepted if our peer supports them.
+	pub max_funding_satoshis: u64,
+	/// The maximum value, in millisatoshis, our peer may require as the minimum size of HTLCs we
+	/// send to them.
+	pub max_htlc_minimum_msat: u64,
+	/// Whether we require inbound channels to match our announcement preference, i.e., whether to
+	/// reject unannounced inbound channels if we're configured to announce channels, and vice
+	/// versa.
+	///
+	/// This corresponds to LDK's [`ChannelHandshakeLimits::force_announced_channel_preference`].
+	///
+	/// **Note:** We will always reject inbound announced channels if the node isn't configured to
+	/// announce channels, i.e., if [`Config::node_alias`] or [`Config::listening_addresses`] are
+	/// unset.
+	///
+	/// [`ChannelHandshakeLimits::force_announced_channel_preference`]: lightning::util::config::ChannelHandshakeLimits::force_announced_channel_preference
+	pub force_announced_channel_preference: bool,
+	/// Limits applying to inbound channels from specific peers, e.g., trusted LSPs, in lieu of the
+	/// above defaults.
+	///
+	/// Any limit left unset in an [`InboundChannelLimitsOverride`] falls back to the respective
+	/// default.
+	pub per_peer_overrides: HashMap<PublicKey, InboundChannelLimitsOverride>,
+}
+
+impl Default for InboundChannelLimits {
+	fn default() -> Self {
+		Self {
+			min_funding_satoshis: DEFAULT_INBOUND_MIN_FUNDING_SATS,
+			max_funding_satoshis: DEFAULT_INBOUND_MAX_FUNDING_SATS,
+			max_htlc_minimum_msat: u64::MAX,
+			force_announced_channel_preference: false,
+			per_peer_overrides: HashMap::new(),
+		}
+	}
+}
+
+impl InboundChannelLimits {
+	/// Returns the limits applying to inbound channels from the given peer.
+	pub(crate) fn for_peer(&self, counterparty_node_id: &PublicKey) -> InboundChannelLimits {
+		let mut limits = self.without_overrides();
+		if let Some(peer_override) = self.per_peer_overrides.get(counterparty_node_id) {
+			if let Some(min_funding_satoshis) = peer_override.min_funding_satoshis {
+				limits.min_funding_satoshis = min_funding_satoshis;
+			}
+			if let Some(max_funding_satoshis) = peer_override.max_funding_satoshis {
+				limits.max_funding_satoshis = max_funding_satoshis;
+			}
+			if let Some(max_htlc_minimum_msat) = peer_override.max_htlc_minimum_msat {
+				limits.max_htlc_minimum_msat = max_htlc_minimum_msat;
+			}
+			if let Some(force_preference) = peer_override.force_announced_channel_preference {
+				limits.force_announced_channel_preference = force_preference;
+			}
+		}
+		limits
+	}
+
+	/// Returns the most permissive limits across the defaults and all per-peer overrides.
+	fn most_permissive(&self) -> InboundChannelLimits {
+		let mut limits = self.without_overrides();
+		for peer_override in self.per_peer_overrides.values() {
+			if let Some(min_funding_satoshis) = peer_override.min_funding_satoshis {
+				limits.min_funding_satoshis = limits.min_funding_satoshis.min(min_funding_satoshis);
+			}
+			if let Some(max_funding_satoshis) = peer_override.max_funding_satoshis {
+				limits.max_funding_satoshis = limits.max_funding_satoshis.max(max_funding_satoshis);
+			}
+			if let Some(max_htlc_minimum_msat) = peer_override.max_htlc_minimum_msat {
+				limits.max_htlc_minimum_msat =
+					limits.max_htlc_minimum_msat.max(max_htlc_minimum_msat);
+			}
+			if peer_override.force_announced_channel_preference == Some(false) {
+				limits.force_announced_channel_preference = false;
+			}
+		}
+		limits
+	}
+
+	fn without_overrides(&self) -> InboundChannelLimits {
+		InboundChannelLimits {
+			min_funding_satoshis: self.min_funding_satoshis,
+			max_funding_satoshis: self.max_funding_satoshis,
+			max_htlc_minimum_msat: self.max_htlc_minimum_msat,
+			force_announced_channel_preference: self.force_announced_channel_preference,
+			per_peer_overrides: HashMap::new(),
+		}
+	}
+}
+
+/// Limits overriding the [`InboundChannelLimits`] defaults for a specific peer.
+///
+/// Any limit left unset falls back to the respective default.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InboundChannelLimitsOverride {
+	/// Overrides [`InboundChannelLimits::min_funding_satoshis`].
+	pub min_funding_satoshis: Option<u64>,
+	/// Overrides [`InboundChannelLimits::max_funding_satoshis`].
+	pub max_funding_satoshis: Option<u64>,
+	/// Overrides [`InboundChannelLimits::max_htlc_minimum_msat`].
+	pub max_htlc_minimum_msat: Option<u64>,
+	/// Overrides [`InboundChannelLimits::force_announced_channel_preference`].
+	pub force_announced_channel_preference: Option<bool>,
+}
+
+/// A policy deciding which peers may open inbound connections to us.
+///
+/// The policy is enforced as soon as the peer's node id is known after the transport handshake
+/// completed, i.e., before any of our message handlers processed the connection. Note that it only
+/// applies to inbound connections, we may still connect to any peer ourselves.
+///
+/// ### Defaults
+///
+/// | Parameter       | Value |
+/// |-----------------|-------|
+/// | `allowed_peers` | []    |
+/// | `denied_peers`  | []    |
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PeerConnectionPolicy {
+	/// If non-empty, we'll only accept inbound connections from the given peers.
+	pub allowed_peers: Vec<PublicKey>,
+	/// Peers we'll never accept inbound connections from.
+	///
+	/// Takes precedence over [`PeerConnectionPolicy::allowed_peers`].
+	pub denied_peers: Vec<PublicKey>,
+}
+
+impl PeerConnectionPolicy {
+	pub(crate) fn allows_inbound(&self, node_id: &PublicKey) -> bool {
+		if self.denied_peers.contains(node_id) {
+			return false;
+		}
+		self.allowed_peers.is_empty() || self.allowed_peers.contains(node_id)
+	}
+}
+
+impl_writeable_tlv_based!(PeerConnectionPolicy, {
+	(0, allowed_peers, required_vec),
+	(2, denied_peers, required_vec),
+});
+
+/// Parameters tuning the penalties our probabilistic scorer assigns to channels during
+/// pathfinding, i.e., how much we're willing to pay in fees to avoid a channel.
+///
+/// Please refer to LDK's [`ProbabilisticScoringFeeParameters`] for further information.
+///
+/// ### Defaults
+///
+/// | Parameter                                      | Value  |
+/// |------------------------------------------------|--------|
+/// | `base_penalty_msat`                            | 1024   |
+/// | `liquidity_penalty_multiplier_msat`            | 0      |
+/// | `historical_liquidity_penalty_multiplier_msat` | 10000  |
+///
+/// [`ProbabilisticScoringFeeParameters`]: lightning::routing::scoring::ProbabilisticScoringFeeParameters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScoringParameters {
+	/// A fixed penalty, in millisatoshis, applied to each channel.
+	pub base_penalty_msat: u64,
+	/// A multiplier, in millisatoshis, for the penalty based on our current estimate of the
+	/// success probability of routing through a channel.
+	pub liquidity_penalty_multiplier_msat: u64,
+	/// A multiplier, in millisatoshis, for the penalty based on the historical estimates of the
+	/// success probability of routing through a channel.
+	pub historical_liquidity_penalty_multiplier_msat: u64,
+}
+
+impl Default for ScoringParameters {
+	fn default() -> Self {
+		Self {
+			base_penalty_msat: DEFAULT_SCORING_BASE_PENALTY_MSAT,
+			liquidity_penalty_multiplier_msat: 0,
+			historical_liquidity_penalty_multiplier_msat:
+				DEFAULT_SCORING_HISTORICAL_LIQUIDITY_PENALTY_MULTIPLIER_MSAT,
+		}
+	}
+}
+
+impl From<ScoringParameters> for ProbabilisticScoringFeeParameters {
+	fn from(value: ScoringParameters) -> Self {
+		ProbabilisticScoringFeeParameters {
+			base_penalty_msat: value.base_penalty_msat,
+			liquidity_penalty_multiplier_msat: value.liquidity_penalty_multiplier_msat,
+			historical_liquidity_penalty_multiplier_msat: value
+				.historical_liquidity_penalty_multiplier_msat,
+			..Default::default()
 		}
 	}
 }
@@ -281,6 +1202,14 @@ pub fn default_config() -> Config {
 	Config::default()
 }
 
+/// Parses an RGB node color, as used for [`Config::node_color`], from its hex representation,
+/// e.g., `"3399ff"`.
+///
+/// Returns [`Error::InvalidNodeColor`] if the given string isn't exactly three hex-encoded bytes.
+pub fn parse_node_color(hex: &str) -> Result<[u8; 3], Error> {
+	<[u8; 3]>::from_hex(hex).map_err(|_| Error::InvalidNodeColor)
+}
+
 #[derive(Debug, PartialEq)]
 pub(crate) enum AnnounceError {
 	MissingNodeAlias,
@@ -314,17 +1243,92 @@ pub(crate) fn may_announce_channel(config: &Config) -> Result<(), AnnounceError>
 	}
 }
 
+/// Returns whether other nodes may be able to reach us via the given address if we announced it.
+///
+/// Unspecified addresses and port 0 are never considered routable. On mainnet, loopback,
+/// link-local, and private addresses are further considered unroutable. Hostnames and onion
+/// addresses are always considered routable.
+pub(crate) fn is_routable_address(address: &SocketAddress, network: Network) -> bool {
+	match address {
+		SocketAddress::TcpIpV4 { addr, port } => {
+			let ip = Ipv4Addr::from(*addr);
+			if ip.is_unspecified() || ip.is_broadcast() || *port == 0 {
+				return false;
+			}
+			network != Network::Bitcoin
+				|| !(ip.is_loopback() || ip.is_link_local() || ip.is_private())
+		},
+		SocketAddress::TcpIpV6 { addr, port } => {
+			let ip = Ipv6Addr::from(*addr);
+			if ip.is_unspecified() || *port == 0 {
+				return false;
+			}
+			let is_unique_local = (ip.segments()[0] & 0xfe00) == 0xfc00;
+			let is_link_local = (ip.segments()[0] & 0xffc0) == 0xfe80;
+			network != Network::Bitcoin || !(ip.is_loopback() || is_unique_local || is_link_local)
+		},
+		SocketAddress::OnionV2(_) => true,
+		SocketAddress::OnionV3 { port, .. } | SocketAddress::Hostname { port, .. } => *port != 0,
+	}
+}
+
+// Returns whether the given CLTV expiry deltas are at least the minimums enforced by LDK.
+pub(crate) fn is_valid_cltv_expiry_delta_config(config: &Config) -> bool {
+	config.default_cltv_expiry_delta >= MIN_CLTV_EXPIRY_DELTA
+		&& config.min_final_cltv_expiry_delta >= MIN_FINAL_CLTV_EXPIRY_DELTA
+}
+
+// Returns whether the forwarding CLTV expiry delta is set dangerously low for the configured
+// network, i.e., whether we should warn about it.
+pub(crate) fn is_cltv_expiry_delta_dangerously_low(config: &Config) -> bool {
+	config.network == Network::Bitcoin
+		&& config.default_cltv_expiry_delta < MAINNET_RECOMMENDED_MIN_CLTV_EXPIRY_DELTA
+}
+
+// Returns the channel reserve counterparties using LDK's defaults require us to keep when we open a
+// channel, i.e., 1% of the channel value, but at least 1000 sats.
+pub(crate) fn expected_channel_reserve_sats(channel_amount_sats: u64) -> u64 {
+	(channel_amount_sats / 100).max(1000)
+}
+
 pub(crate) fn default_user_config(config: &Config) -> UserConfig {
 	// Initialize the default config values.
 	//
 	// Note that methods such as Node::open_channel and Node::open_announced_channel might override
 	// some of the values set here, e.g. the ChannelHandshakeConfig, meaning these default values
 	// will mostly be relevant for inbound channels.
-	let mut user_config = UserConfig::default();
-	user_config.channel_handshake_limits.force_announced_channel_preference = false;
-	user_config.manually_accept_inbound_channels = true;
+	let mut user_config =
+		UserConfig { manually_accept_inbound_channels: true, ..UserConfig::default() };
+
+	// We enforce per-peer inbound channel limits when handling `OpenChannelRequest`s, so only have
+	// LDK reject channels that no peer would be allowed to open.
+	let inbound_limits = config.inbound_channel_limits.most_permissive();
+	user_config.channel_handshake_limits.min_funding_satoshis = inbound_limits.min_funding_satoshis;
+	user_config.channel_handshake_limits.max_funding_satoshis = inbound_limits.max_funding_satoshis;
+	user_config.channel_handshake_limits.max_htlc_minimum_msat =
+		inbound_limits.max_htlc_minimum_msat;
+	user_config.channel_handshake_limits.force_announced_channel_preference =
+		inbound_limits.force_announced_channel_preference;
 	user_config.channel_handshake_config.negotiate_anchors_zero_fee_htlc_tx =
 		config.anchor_channels_config.is_some();
+	if let Some(htlc_limits) = config.channel_htlc_limits {
+		user_config.channel_handshake_config.our_max_accepted_htlcs =
+			htlc_limits.max_accepted_htlcs;
+		user_config.channel_handshake_config.max_inbound_htlc_value_in_flight_percent_of_channel =
+			htlc_limits.max_htlc_value_in_flight_percent;
+	}
+	// Accept splices initiated by our counterparties, e.g., other LDK Node instances using
+	// `Node::splice_in`/`Node::splice_out`.
+	user_config.reject_inbound_splices = false;
+	user_config.accept_intercept_htlcs = config.accept_intercept_htlcs;
+	// If enabled, we pay received BOLT12 invoices ourselves, allowing to hand them out to the user
+	// first if they were requested via `Bolt12Payment::request_invoice`.
+	user_config.manually_handle_bolt12_invoices = config.enable_bolt12_invoice_requests;
+	user_config.channel_config.cltv_expiry_delta = config.default_cltv_expiry_delta;
+	if let Some(limit_msat) = config.max_dust_htlc_exposure_msat {
+		user_config.channel_config.max_dust_htlc_exposure =
+			LdkMaxDustHTLCExposure::FixedLimitMsat(limit_msat);
+	}
 
 	if may_announce_channel(config).is_err() {
 		user_config.accept_forwards_to_priv_channels = false;
@@ -397,6 +1401,12 @@ impl Default for EsploraSyncConfig {
 ///
 /// Background syncing is enabled by default, using the default values specified in
 /// [`BackgroundSyncConfig`].
+///
+/// ### Defaults
+///
+/// | Parameter                          | Value              |
+/// |------------------------------------|--------------------|
+/// | `server_unavailable_timeout_secs`  | 300                |
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct ElectrumSyncConfig {
 	/// Background sync configuration.
@@ -406,11 +1416,62 @@ pub struct ElectrumSyncConfig {
 	///
 	/// [`Node::sync_wallets`]: crate::Node::sync_wallets
 	pub background_sync_config: Option<BackgroundSyncConfig>,
+	/// The time after which we'll emit an [`Event::ChainSourceUnavailable`] if we were unable to
+	/// reach the Electrum server, in seconds.
+	///
+	/// The event is emitted at most once per outage, i.e., it will only be emitted again after we
+	/// were able to reach the server in the meantime.
+	///
+	/// [`Event::ChainSourceUnavailable`]: crate::Event::ChainSourceUnavailable
+	pub server_unavailable_timeout_secs: u64,
 }
 
 impl Default for ElectrumSyncConfig {
 	fn default() -> Self {
-		Self { background_sync_config: Some(BackgroundSyncConfig::default()) }
+		Self {
+			background_sync_config: Some(BackgroundSyncConfig::default()),
+			server_unavailable_timeout_secs: DEFAULT_ELECTRUM_SERVER_UNAVAILABLE_TIMEOUT_SECS,
+		}
+	}
+}
+
+/// Configuration for syncing with a Bitcoin Core backend via RPC or REST.
+///
+/// Background syncing is enabled by default, using the default values specified in
+/// [`BackgroundSyncConfig`]. Note that as we sync by polling the Bitcoin Core node for new blocks,
+/// which is cheap, the on-chain and Lightning wallets are always synced together every few
+/// seconds, i.e., only [`BackgroundSyncConfig::fee_rate_cache_update_interval_secs`] is considered.
+///
+/// ### Defaults
+///
+/// | Parameter                          | Value              |
+/// |------------------------------------|--------------------|
+/// | `server_unavailable_timeout_secs`  | 300                |
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BitcoindSyncConfig {
+	/// Background sync configuration.
+	///
+	/// If set to `None`, background syncing will be disabled. Users will need to manually
+	/// sync via [`Node::sync_wallets`] for the wallets and fee rate updates.
+	///
+	/// [`Node::sync_wallets`]: crate::Node::sync_wallets
+	pub background_sync_config: Option<BackgroundSyncConfig>,
+	/// The time after which we'll emit an [`Event::ChainSourceUnavailable`] if we were unable to
+	/// reach the Bitcoin Core node, in seconds.
+	///
+	/// The event is emitted at most once per outage, i.e., it will only be emitted again after we
+	/// were able to reach the node in the meantime.
+	///
+	/// [`Event::ChainSourceUnavailable`]: crate::Event::ChainSourceUnavailable
+	pub server_unavailable_timeout_secs: u64,
+}
+
+impl Default for BitcoindSyncConfig {
+	fn default() -> Self {
+		Self {
+			background_sync_config: Some(BackgroundSyncConfig::default()),
+			server_unavailable_timeout_secs: DEFAULT_BITCOIND_SERVER_UNAVAILABLE_TIMEOUT_SECS,
+		}
 	}
 }
 
@@ -423,6 +1484,29 @@ pub struct BitcoindRestClientConfig {
 	pub rest_port: u16,
 }
 
+/// Configuration for connecting to peers via Tor.
+///
+/// See [`Builder::set_tor_config`] for details.
+///
+/// [`Builder::set_tor_config`]: crate::Builder::set_tor_config
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TorConfig {
+	/// The address of the Tor SOCKS5 proxy, e.g., `127.0.0.1:9050`.
+	///
+	/// Connections to onion (`.onion`) peer addresses will be routed through this proxy.
+	pub socks_proxy: SocketAddress,
+	/// The address of the Tor control port, e.g., `127.0.0.1:9051`.
+	///
+	/// Required if [`TorConfig::hidden_service`] is set. We support Tor's `NULL` and `COOKIE`
+	/// authentication methods.
+	pub control_port: Option<SocketAddress>,
+	/// Whether we should publish an onion service forwarding to the port of our first listening
+	/// address on `127.0.0.1`.
+	///
+	/// If set, the onion address will be included in our node announcements.
+	pub hidden_service: bool,
+}
+
 /// Options which apply on a per-channel basis and may change at runtime or based on negotiation
 /// with our counterparty.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -496,6 +1580,236 @@ impl Default for ChannelConfig {
 	}
 }
 
+/// A partial update to a [`ChannelConfig`].
+///
+/// Only the fields that are set will be changed, all other fields retain their current values.
+///
+/// See [`Node::update_partial_channel_config`] for details.
+///
+/// [`Node::update_partial_channel_config`]: crate::Node::update_partial_channel_config
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ChannelConfigUpdate {
+	/// Updates [`ChannelConfig::forwarding_fee_proportional_millionths`] if set.
+	pub forwarding_fee_proportional_millionths: Option<u32>,
+	/// Updates [`ChannelConfig::forwarding_fee_base_msat`] if set.
+	pub forwarding_fee_base_msat: Option<u32>,
+	/// Updates [`ChannelConfig::cltv_expiry_delta`] if set.
+	pub cltv_expiry_delta: Option<u16>,
+	/// Updates [`ChannelConfig::max_dust_htlc_exposure`] if set.
+	pub max_dust_htlc_exposure: Option<MaxDustHTLCExposure>,
+	/// Updates [`ChannelConfig::force_close_avoidance_max_fee_satoshis`] if set.
+	pub force_close_avoidance_max_fee_satoshis: Option<u64>,
+}
+
+impl From<ChannelConfigUpdate> for LdkChannelConfigUpdate {
+	fn from(value: ChannelConfigUpdate) -> Self {
+		Self {
+			forwarding_fee_proportional_millionths: value.forwarding_fee_proportional_millionths,
+			forwarding_fee_base_msat: value.forwarding_fee_base_msat,
+			cltv_expiry_delta: value.cltv_expiry_delta,
+			max_dust_htlc_exposure_msat: value.max_dust_htlc_exposure.map(|e| e.into()),
+			force_close_avoidance_max_fee_satoshis: value.force_close_avoidance_max_fee_satoshis,
+			accept_underpaying_htlcs: None,
+		}
+	}
+}
+
+/// Parameters for opening an individual channel.
+///
+/// See [`Node::open_channel_with_params`] and [`Node::open_announced_channel_with_params`] for
+/// details.
+///
+/// [`Node::open_channel_with_params`]: crate::Node::open_channel_with_params
+/// [`Node::open_announced_channel_with_params`]: crate::Node::open_announced_channel_with_params
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ChannelOpenParams {
+	/// The amount, in millisatoshis, to push (read: send) to the channel counterparty on channel
+	/// open.
+	///
+	/// This can be useful to start out with the balance not entirely shifted to one side, therefore
+	/// allowing to receive payments from the getgo. May not exceed the channel value minus the
+	/// channel reserve we're expected to keep.
+	pub push_to_counterparty_msat: Option<u64>,
+	/// The config to use for the channel.
+	///
+	/// If not set, the default [`ChannelConfig`] is used.
+	pub channel_config: Option<ChannelConfig>,
+	/// The confirmation target determining the fee rate of the funding transaction.
+	///
+	/// Choosing a target that aims for confirmation within more blocks allows to open channels
+	/// more cheaply during low-fee periods, at the cost of it potentially taking longer until the
+	/// channel is ready. For example, [`ConfirmationTarget::ChannelFunding`] aims for confirmation
+	/// within 12 blocks, whereas the `ChannelCloseMinimum` [`ConfirmationTarget::Lightning`] target
+	/// aims for 144 blocks.
+	///
+	/// If not set, [`ConfirmationTarget::ChannelFunding`] is used.
+	pub funding_confirmation_target: Option<ConfirmationTarget>,
+}
+
+/// Parameters for routing and retrying an individual outbound payment.
+///
+/// Any routing parameter that is not set falls back to the node-wide [`Config::route_parameters`],
+/// or to the defaults if those aren't set either.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SendPaymentParams {
+	/// The maximum total fees, in millisatoshis, that may accrue during route finding.
+	///
+	/// See [`RouteParametersConfig::max_total_routing_fee_msat`] for details.
+	pub max_total_routing_fee_msat: Option<u64>,
+	/// The maximum total CLTV delta we accept for the route.
+	///
+	/// See [`RouteParametersConfig::max_total_cltv_expiry_delta`] for details.
+	pub max_total_cltv_expiry_delta: Option<u32>,
+	/// The maximum number of paths that may be used by MPP payments.
+	///
+	/// See [`RouteParametersConfig::max_path_count`] for details.
+	pub max_path_count: Option<u8>,
+	/// Selects the maximum share of a channel's total capacity which will be sent over a channel,
+	/// as a power of 1/2.
+	///
+	/// See [`RouteParametersConfig::max_channel_saturation_power_of_half`] for details.
+	pub max_channel_saturation_power_of_half: Option<u8>,
+	/// The strategy used to retry the payment if individual attempts fail.
+	///
+	/// If not set, we'll keep retrying for a short, internally-defined time.
+	pub retry_strategy: Option<RetryStrategy>,
+	/// A caller-chosen key identifying the payment attempt.
+	///
+	/// If a payment was previously sent with the same key, we won't dispatch another payment but
+	/// handle the send as configured via [`Self::on_duplicate`]. This allows to safely retry a
+	/// send, e.g., after a timeout left it unclear whether the first attempt was initiated. The
+	/// key is persisted and reserved before the payment is initiated, so concurrent sends with
+	/// the same key will also be handled as duplicates.
+	///
+	/// The [`PaymentId`] sent with a key can also be retrieved via
+	/// [`Node::payment_id_for_idempotency_key`]. Keys are forgotten when the respective payment is
+	/// removed via [`Node::remove_payment`].
+	///
+	/// [`PaymentId`]: lightning::ln::channelmanager::PaymentId
+	/// [`Node::payment_id_for_idempotency_key`]: crate::Node::payment_id_for_idempotency_key
+	/// [`Node::remove_payment`]: crate::Node::remove_payment
+	pub idempotency_key: Option<String>,
+	/// How to handle a send reusing the [`Self::idempotency_key`] of an earlier payment.
+	///
+	/// If not set, [`DuplicatePaymentBehavior::ReturnExisting`] is used.
+	pub on_duplicate: Option<DuplicatePaymentBehavior>,
+	/// Whether we should attempt to pay a BOLT11 invoice even though it has expired.
+	///
+	/// By default, payments to expired invoices are rejected. As some payees still accept
+	/// payments after the invoice expired, this allows to attempt the payment anyway, e.g., if the
+	/// invoice only expired due to clock skew or as the user took a long time to confirm it.
+	pub ignore_expiry: bool,
+}
+
+impl SendPaymentParams {
+	pub(crate) fn route_parameters(&self, config: &Config) -> RouteParametersConfig {
+		let mut route_parameters = config.route_parameters.unwrap_or_default();
+		if let Some(max_total_routing_fee_msat) = self.max_total_routing_fee_msat {
+			route_parameters.max_total_routing_fee_msat = Some(max_total_routing_fee_msat);
+		}
+		if let Some(max_total_cltv_expiry_delta) = self.max_total_cltv_expiry_delta {
+			route_parameters.max_total_cltv_expiry_delta = max_total_cltv_expiry_delta;
+		}
+		if let Some(max_path_count) = self.max_path_count {
+			route_parameters.max_path_count = max_path_count;
+		}
+		if let Some(max_channel_saturation_power_of_half) =
+			self.max_channel_saturation_power_of_half
+		{
+			route_parameters.max_channel_saturation_power_of_half =
+				max_channel_saturation_power_of_half;
+		}
+		route_parameters
+	}
+
+	/// Returns the parameters to find a route paying `amount_msat` with the given payment
+	/// parameters, see [`Self::route_parameters`].
+	pub(crate) fn route_params(
+		&self, config: &Config, payment_params: PaymentParameters, amount_msat: u64,
+	) -> RouteParameters {
+		route_params_from_config(payment_params, amount_msat, &self.route_parameters(config))
+	}
+
+	pub(crate) fn retry_strategy_or_default(&self) -> RetryStrategy {
+		self.retry_strategy
+			.unwrap_or(RetryStrategy::Timeout { timeout_secs: LDK_PAYMENT_RETRY_TIMEOUT.as_secs() })
+	}
+}
+
+/// Returns the parameters to find a route paying `amount_msat`, applying the given
+/// [`RouteParametersConfig`] to the given payment parameters.
+///
+/// Note that, mirroring LDK, we only override the default routing fee cap if a maximum fee is set.
+pub(crate) fn route_params_from_config(
+	payment_params: PaymentParameters, amount_msat: u64, route_parameters: &RouteParametersConfig,
+) -> RouteParameters {
+	let mut route_params =
+		RouteParameters::from_payment_params_and_value(payment_params, amount_msat);
+	if let Some(max_total_routing_fee_msat) = route_parameters.max_total_routing_fee_msat {
+		route_params.max_total_routing_fee_msat = Some(max_total_routing_fee_msat);
+	}
+	route_params.payment_params.max_total_cltv_expiry_delta =
+		route_parameters.max_total_cltv_expiry_delta;
+	route_params.payment_params.max_path_count = route_parameters.max_path_count;
+	route_params.payment_params.max_channel_saturation_power_of_half =
+		route_parameters.max_channel_saturation_power_of_half;
+	route_params
+}
+
+/// Options for how to handle a send reusing the idempotency key of an earlier payment.
+///
+/// See [`SendPaymentParams::idempotency_key`] for details.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum DuplicatePaymentBehavior {
+	/// Return the [`PaymentId`] of the existing payment.
+	///
+	/// If the existing payment has failed, [`Error::DuplicateIdempotencyKey`] is returned instead.
+	///
+	/// [`PaymentId`]: lightning::ln::channelmanager::PaymentId
+	/// [`Error::DuplicateIdempotencyKey`]: crate::Error::DuplicateIdempotencyKey
+	#[default]
+	ReturnExisting,
+	/// Return [`Error::DuplicateIdempotencyKey`], independently of the existing payment's status.
+	///
+	/// [`Error::DuplicateIdempotencyKey`]: crate::Error::DuplicateIdempotencyKey
+	Fail,
+}
+
+/// Options for how long we keep retrying an outbound payment before failing it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RetryStrategy {
+	/// Retry the payment at most the given number of times.
+	Attempts {
+		/// The maximum number of retries.
+		max_attempts: u32,
+	},
+	/// Keep retrying the payment until the given time has elapsed since it was initiated.
+	Timeout {
+		/// The time after which we stop retrying, in seconds.
+		timeout_secs: u64,
+	},
+}
+
+impl From<RetryStrategy> for Retry {
+	fn from(value: RetryStrategy) -> Self {
+		match value {
+			RetryStrategy::Attempts { max_attempts } => Retry::Attempts(max_attempts),
+			RetryStrategy::Timeout { timeout_secs } => {
+				Retry::Timeout(Duration::from_secs(timeout_secs))
+			},
+		}
+	}
+}
+
+impl_writeable_tlv_based_enum!(RetryStrategy,
+	(0, Attempts) => {
+		(0, max_attempts, required),
+	},
+	(2, Timeout) => {
+		(0, timeout_secs, required),
+	}
+);
+
 /// Options for how to set the max dust exposure allowed on a channel.
 ///
 /// See [`LdkChannelConfig::max_dust_htlc_exposure`] for details.
@@ -555,8 +1869,21 @@ pub enum AsyncPaymentsRole {
 #[cfg(test)]
 mod tests {
 	use std::str::FromStr;
-
-	use super::{may_announce_channel, AnnounceError, Config, NodeAlias, SocketAddress};
+	use std::time::Duration;
+
+	use bitcoin::secp256k1::PublicKey;
+	use bitcoin::Network;
+
+	use super::{
+		default_user_config, is_cltv_expiry_delta_dangerously_low, is_routable_address,
+		is_valid_cltv_expiry_delta_config, may_announce_channel, parse_node_color,
+		AnchorChannelsConfig, AnnounceError, ChannelHtlcLimits, Config, FeatureConfig,
+		InboundChannelLimitsOverride, LdkChannelConfig, LdkMaxDustHTLCExposure, NodeAlias,
+		NodeFeatures, PaymentParameters, PeerConnectionPolicy, PeerReconnectPolicy, RetryStrategy,
+		RouteParameters, RouteParametersConfig, SendPaymentParams, SocketAddress,
+		LDK_PAYMENT_RETRY_TIMEOUT, MIN_CLTV_EXPIRY_DELTA, MIN_FINAL_CLTV_EXPIRY_DELTA,
+	};
+	use crate::Error;
 
 	#[test]
 	fn node_announce_channel() {
@@ -603,4 +1930,311 @@ mod tests {
 		}
 		assert!(may_announce_channel(&node_config).is_ok());
 	}
+
+	#[test]
+	fn peer_connection_policy() {
+		let peer_a = PublicKey::from_str(
+			"0276607124ebe6a6c9338517b6f485825b27c2dcc0b9fc2aa6a4c0df91194e5993",
+		)
+		.unwrap();
+		let peer_b = PublicKey::from_str(
+			"0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+		)
+		.unwrap();
+
+		// By default, we accept everyone.
+		let mut policy = PeerConnectionPolicy::default();
+		assert!(policy.allows_inbound(&peer_a));
+		assert!(policy.allows_inbound(&peer_b));
+
+		// Denied peers are rejected.
+		policy.denied_peers = vec![peer_b];
+		assert!(policy.allows_inbound(&peer_a));
+		assert!(!policy.allows_inbound(&peer_b));
+
+		// If set, only allowed peers are accepted, but denials still take precedence.
+		policy.denied_peers = Vec::new();
+		policy.allowed_peers = vec![peer_a];
+		assert!(policy.allows_inbound(&peer_a));
+		assert!(!policy.allows_inbound(&peer_b));
+
+		policy.denied_peers = vec![peer_a];
+		assert!(!policy.allows_inbound(&peer_a));
+	}
+
+	#[test]
+	fn routable_addresses() {
+		let addr = |s: &str| SocketAddress::from_str(s).expect("Socket address conversion failed.");
+
+		for network in [Network::Bitcoin, Network::Regtest] {
+			assert!(is_routable_address(&addr("123.45.67.89:9735"), network));
+			assert!(is_routable_address(&addr("[2001:db8::1]:9735"), network));
+			assert!(is_routable_address(&addr("example.com:9735"), network));
+			assert!(!is_routable_address(&addr("0.0.0.0:9735"), network));
+			assert!(!is_routable_address(&addr("[::]:9735"), network));
+			assert!(!is_routable_address(&addr("123.45.67.89:0"), network));
+		}
+
+		for local_addr in ["127.0.0.1:9735", "192.168.0.1:9735", "[::1]:9735", "[fd00::1]:9735"] {
+			assert!(!is_routable_address(&addr(local_addr), Network::Bitcoin));
+			assert!(is_routable_address(&addr(local_addr), Network::Regtest));
+		}
+	}
+
+	#[test]
+	fn send_payment_params_override_per_field() {
+		let mut config = Config::default();
+		config.route_parameters = Some(RouteParametersConfig {
+			max_total_routing_fee_msat: Some(1_000),
+			max_total_cltv_expiry_delta: 500,
+			max_path_count: 5,
+			max_channel_saturation_power_of_half: 1,
+		});
+
+		let send_params = SendPaymentParams {
+			max_path_count: Some(2),
+			retry_strategy: Some(RetryStrategy::Attempts { max_attempts: 3 }),
+			..Default::default()
+		};
+		let route_parameters = send_params.route_parameters(&config);
+		assert_eq!(route_parameters.max_total_routing_fee_msat, Some(1_000));
+		assert_eq!(route_parameters.max_total_cltv_expiry_delta, 500);
+		assert_eq!(route_parameters.max_path_count, 2);
+		assert_eq!(route_parameters.max_channel_saturation_power_of_half, 1);
+		assert_eq!(
+			send_params.retry_strategy_or_default(),
+			RetryStrategy::Attempts { max_attempts: 3 }
+		);
+
+		let default_params = SendPaymentParams::default();
+		assert_eq!(
+			default_params.retry_strategy_or_default(),
+			RetryStrategy::Timeout { timeout_secs: LDK_PAYMENT_RETRY_TIMEOUT.as_secs() }
+		);
+	}
+
+	#[test]
+	fn send_payment_params_keep_default_routing_fee_cap() {
+		let payment_params = PaymentParameters::from_node_id(
+			PublicKey::from_str(
+				"02eadbd9e7557375161df8b646776a547c5cbc2e95b3071ec81553f8ec2cea3b8c",
+			)
+			.unwrap(),
+			40,
+		);
+		let amount_msat = 100_000_000;
+		let default_route_params =
+			RouteParameters::from_payment_params_and_value(payment_params.clone(), amount_msat);
+		assert!(default_route_params.max_total_routing_fee_msat.is_some());
+
+		// Without a configured maximum fee, LDK's default cap is kept.
+		let mut config = Config::default();
+		let route_params =
+			SendPaymentParams::default().route_params(&config, payment_params.clone(), amount_msat);
+		assert_eq!(
+			route_params.max_total_routing_fee_msat,
+			default_route_params.max_total_routing_fee_msat
+		);
+
+		config.route_parameters = Some(RouteParametersConfig {
+			max_total_routing_fee_msat: Some(1_000),
+			..Default::default()
+		});
+		let route_params =
+			SendPaymentParams::default().route_params(&config, payment_params, amount_msat);
+		assert_eq!(route_params.max_total_routing_fee_msat, Some(1_000));
+	}
+
+	#[test]
+	fn inbound_channel_limits_per_peer_overrides() {
+		let trusted_lsp = PublicKey::from_str(
+			"0276607124ebe6a6c9338517b6f485825b27c2dcc0b9fc2aa6a4c0df91194e5993",
+		)
+		.unwrap();
+		let other_peer = PublicKey::from_str(
+			"0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+		)
+		.unwrap();
+
+		let mut config = Config::default();
+		let peer_override = InboundChannelLimitsOverride {
+			max_funding_satoshis: Some(100_000_000),
+			..Default::default()
+		};
+		config.inbound_channel_limits.per_peer_overrides.insert(trusted_lsp, peer_override);
+
+		// Overridden limits apply to the given peer only, the rest falls back to the defaults.
+		let lsp_limits = config.inbound_channel_limits.for_peer(&trusted_lsp);
+		assert_eq!(lsp_limits.max_funding_satoshis, 100_000_000);
+		assert_eq!(
+			lsp_limits.min_funding_satoshis,
+			config.inbound_channel_limits.min_funding_satoshis
+		);
+		let other_limits = config.inbound_channel_limits.for_peer(&other_peer);
+		assert_eq!(other_limits.max_funding_satoshis, (1 << 24) - 1);
+
+		// LDK is configured with the most permissive limits, as we check them per peer.
+		let user_config = default_user_config(&config);
+		assert_eq!(user_config.channel_handshake_limits.max_funding_satoshis, 100_000_000);
+	}
+
+	#[test]
+	fn anchor_reserve_can_be_disabled_globally() {
+		let trusted_peer = PublicKey::from_str(
+			"0276607124ebe6a6c9338517b6f485825b27c2dcc0b9fc2aa6a4c0df91194e5993",
+		)
+		.unwrap();
+		let other_peer = PublicKey::from_str(
+			"0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+		)
+		.unwrap();
+
+		let mut anchor_channels_config = AnchorChannelsConfig::default();
+		anchor_channels_config.trusted_peers_no_reserve.push(trusted_peer);
+		assert!(!anchor_channels_config.requires_reserve_for(&trusted_peer));
+		assert!(anchor_channels_config.requires_reserve_for(&other_peer));
+
+		anchor_channels_config.require_reserve = false;
+		assert!(!anchor_channels_config.requires_reserve_for(&trusted_peer));
+		assert!(!anchor_channels_config.requires_reserve_for(&other_peer));
+	}
+
+	#[test]
+	fn channel_htlc_limits_validation() {
+		let mut config = Config::default();
+		let limits =
+			ChannelHtlcLimits { max_accepted_htlcs: 30, max_htlc_value_in_flight_percent: 50 };
+		assert!(limits.is_valid());
+		config.channel_htlc_limits = Some(limits);
+
+		let user_config = default_user_config(&config);
+		assert_eq!(user_config.channel_handshake_config.our_max_accepted_htlcs, 30);
+		assert_eq!(
+			user_config
+				.channel_handshake_config
+				.max_inbound_htlc_value_in_flight_percent_of_channel,
+			50
+		);
+
+		assert!(!ChannelHtlcLimits { max_accepted_htlcs: 0, ..limits }.is_valid());
+		assert!(ChannelHtlcLimits { max_accepted_htlcs: 483, ..limits }.is_valid());
+		assert!(!ChannelHtlcLimits { max_accepted_htlcs: 484, ..limits }.is_valid());
+		assert!(!ChannelHtlcLimits { max_htlc_value_in_flight_percent: 0, ..limits }.is_valid());
+		assert!(ChannelHtlcLimits { max_htlc_value_in_flight_percent: 100, ..limits }.is_valid());
+		assert!(!ChannelHtlcLimits { max_htlc_value_in_flight_percent: 101, ..limits }.is_valid());
+	}
+
+	#[test]
+	fn peer_reconnect_policy_validation() {
+		let policy = PeerReconnectPolicy::default();
+		assert!(policy.is_valid());
+		assert!(!PeerReconnectPolicy { initial_backoff_secs: 0, ..policy }.is_valid());
+		assert!(!PeerReconnectPolicy { max_backoff_secs: 59, ..policy }.is_valid());
+		assert!(PeerReconnectPolicy { max_backoff_secs: 60, ..policy }.is_valid());
+		assert!(!PeerReconnectPolicy { max_attempts: Some(0), ..policy }.is_valid());
+		assert!(PeerReconnectPolicy { max_attempts: Some(1), ..policy }.is_valid());
+
+		assert_eq!(policy.backoff(1), Duration::from_secs(60));
+		assert_eq!(policy.backoff(2), Duration::from_secs(120));
+		assert_eq!(policy.backoff(6), Duration::from_secs(1920));
+		assert_eq!(policy.backoff(7), Duration::from_secs(3600));
+		assert_eq!(policy.backoff(u32::MAX), Duration::from_secs(3600));
+	}
+
+	#[test]
+	fn feature_config_validation() {
+		let feature_config = FeatureConfig::default();
+		assert!(feature_config.is_valid());
+		assert!(FeatureConfig { zero_conf: false, ..feature_config }.is_valid());
+		assert!(FeatureConfig { scid_alias: false, zero_conf: false, ..feature_config }.is_valid());
+		assert!(!FeatureConfig { scid_alias: false, ..feature_config }.is_valid());
+
+		let mut node_features = NodeFeatures::empty();
+		node_features.set_scid_privacy_optional();
+		node_features.set_zero_conf_optional();
+		node_features.set_keysend_optional();
+		node_features.set_route_blinding_optional();
+		node_features.set_static_remote_key_required();
+
+		let mut features = node_features.clone();
+		feature_config.clear_disabled_node_features(&mut features);
+		assert_eq!(features, node_features);
+
+		let feature_config = FeatureConfig {
+			scid_alias: false,
+			zero_conf: false,
+			keysend: false,
+			route_blinding: false,
+		};
+		feature_config.clear_disabled_node_features(&mut features);
+		let mut expected_features = NodeFeatures::empty();
+		expected_features.set_static_remote_key_required();
+		assert_eq!(features, expected_features);
+	}
+
+	#[test]
+	fn keysend_is_only_advertised_if_accepted() {
+		let mut config = Config::default();
+		assert!(config.features.keysend);
+		assert!(!config.advertised_features().keysend);
+
+		config.accept_spontaneous_payments = true;
+		assert!(config.advertised_features().keysend);
+
+		config.features.keysend = false;
+		assert!(!config.advertised_features().keysend);
+	}
+
+	#[test]
+	fn max_dust_htlc_exposure_applies_to_user_config() {
+		let mut config = Config::default();
+		let user_config = default_user_config(&config);
+		assert_eq!(
+			user_config.channel_config.max_dust_htlc_exposure,
+			LdkChannelConfig::default().max_dust_htlc_exposure
+		);
+
+		config.max_dust_htlc_exposure_msat = Some(25_000_000);
+		let user_config = default_user_config(&config);
+		assert_eq!(
+			user_config.channel_config.max_dust_htlc_exposure,
+			LdkMaxDustHTLCExposure::FixedLimitMsat(25_000_000)
+		);
+	}
+
+	#[test]
+	fn cltv_expiry_delta_validation() {
+		let mut config = Config::default();
+		assert!(is_valid_cltv_expiry_delta_config(&config));
+		assert!(!is_cltv_expiry_delta_dangerously_low(&config));
+		assert_eq!(default_user_config(&config).channel_config.cltv_expiry_delta, 144);
+
+		config.default_cltv_expiry_delta = MIN_CLTV_EXPIRY_DELTA;
+		assert!(is_valid_cltv_expiry_delta_config(&config));
+		assert!(is_cltv_expiry_delta_dangerously_low(&config));
+		assert_eq!(
+			default_user_config(&config).channel_config.cltv_expiry_delta,
+			MIN_CLTV_EXPIRY_DELTA
+		);
+
+		// We only warn about low deltas on mainnet.
+		config.network = Network::Testnet;
+		assert!(!is_cltv_expiry_delta_dangerously_low(&config));
+
+		config.default_cltv_expiry_delta = MIN_CLTV_EXPIRY_DELTA - 1;
+		assert!(!is_valid_cltv_expiry_delta_config(&config));
+
+		config.default_cltv_expiry_delta = MIN_CLTV_EXPIRY_DELTA;
+		config.min_final_cltv_expiry_delta = MIN_FINAL_CLTV_EXPIRY_DELTA - 1;
+		assert!(!is_valid_cltv_expiry_delta_config(&config));
+	}
+
+	#[test]
+	fn node_color_parsing() {
+		assert_eq!(parse_node_color("3399ff"), Ok([0x33, 0x99, 0xff]));
+		assert_eq!(parse_node_color("3399"), Err(Error::InvalidNodeColor));
+		assert_eq!(parse_node_color("3399ff0"), Err(Error::InvalidNodeColor));
+		assert_eq!(parse_node_color("3399ff00"), Err(Error::InvalidNodeColor));
+		assert_eq!(parse_node_color("33990g"), Err(Error::InvalidNodeColor));
+	}
 }
@@ -6,25 +6,46 @@
 // accordance with one or both of these licenses.
 
 use std::collections::hash_map::{self, HashMap};
-use std::net::ToSocketAddrs;
+use std::future::Future;
+use std::io;
+use std::net::{Shutdown, TcpStream, ToSocketAddrs};
 use std::ops::Deref;
+use std::pin::Pin;
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use bitcoin::secp256k1::PublicKey;
 use lightning::ln::msgs::SocketAddress;
 
+use crate::config::PeerReconnectPolicy;
 use crate::logger::{log_error, log_info, LdkLogger};
-use crate::types::PeerManager;
+use crate::tor::connect_via_socks5_proxy;
+use crate::types::{ConnectResult, ConnectionFailureReason, PeerManager};
 use crate::Error;
 
+// The time we wait for a TCP connection to a peer to be established, unless the caller gave us a
+// timeout. This mirrors the timeout `lightning_net_tokio::connect_outbound` applies.
+const TCP_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Tracks our latest outbound connection attempt to a peer.
+struct ConnectionAttempt {
+	timestamp: Option<u64>,
+	instant: Instant,
+	failure_reason: Option<ConnectionFailureReason>,
+	consecutive_failures: u32,
+}
+
 pub(crate) struct ConnectionManager<L: Deref + Clone + Sync + Send>
 where
 	L::Target: LdkLogger,
 {
-	pending_connections:
-		Mutex<HashMap<PublicKey, Vec<tokio::sync::oneshot::Sender<Result<(), Error>>>>>,
+	pending_connections: Mutex<
+		HashMap<PublicKey, Vec<tokio::sync::oneshot::Sender<Result<(), ConnectionFailureReason>>>>,
+	>,
+	connection_attempts: Mutex<HashMap<PublicKey, ConnectionAttempt>>,
 	peer_manager: Arc<PeerManager>,
+	tor_proxy: Option<SocketAddress>,
+	reconnect_policy: PeerReconnectPolicy,
 	logger: L,
 }
 
@@ -32,9 +53,20 @@ impl<L: Deref + Clone + Sync + Send> ConnectionManager<L>
 where
 	L::Target: LdkLogger,
 {
-	pub(crate) fn new(peer_manager: Arc<PeerManager>, logger: L) -> Self {
+	pub(crate) fn new(
+		peer_manager: Arc<PeerManager>, tor_proxy: Option<SocketAddress>,
+		reconnect_policy: PeerReconnectPolicy, logger: L,
+	) -> Self {
 		let pending_connections = Mutex::new(HashMap::new());
-		Self { pending_connections, peer_manager, logger }
+		let connection_attempts = Mutex::new(HashMap::new());
+		Self {
+			pending_connections,
+			connection_attempts,
+			peer_manager,
+			tor_proxy,
+			reconnect_policy,
+			logger,
+		}
 	}
 
 	pub(crate) async fn connect_peer_if_necessary(
@@ -47,75 +79,256 @@ where
 		self.do_connect_peer(node_id, addr).await
 	}
 
+	pub(crate) async fn connect_peer_with_timeout(
+		&self, node_id: PublicKey, addr: SocketAddress, timeout: Duration,
+	) -> ConnectResult {
+		if self.peer_manager.peer_by_node_id(&node_id).is_some() {
+			return ConnectResult::AlreadyConnected;
+		}
+
+		match self.connect_peer_inner(node_id, addr, Some(timeout)).await {
+			Ok(()) => ConnectResult::Connected,
+			Err(reason) => ConnectResult::Failed { reason },
+		}
+	}
+
 	pub(crate) async fn do_connect_peer(
 		&self, node_id: PublicKey, addr: SocketAddress,
 	) -> Result<(), Error> {
+		self.connect_peer_inner(node_id, addr, None).await.map_err(|reason| match reason {
+			ConnectionFailureReason::DnsResolutionFailed
+			| ConnectionFailureReason::UnsupportedAddress => Error::InvalidSocketAddress,
+			_ => Error::ConnectionFailed,
+		})
+	}
+
+	/// Reconnects to the given peer, unless we're still backing off after previously failed
+	/// attempts or already gave up on reconnecting to it.
+	///
+	/// Returns `true` if we just gave up on reconnecting to the peer as this attempt failed and
+	/// we reached the [`PeerReconnectPolicy::max_attempts`].
+	pub(crate) async fn reconnect_peer_if_due(
+		&self, node_id: PublicKey, addr: SocketAddress,
+	) -> bool {
+		let is_due = match self.connection_attempts.lock().unwrap().get(&node_id) {
+			Some(attempt) if attempt.consecutive_failures > 0 => {
+				if self.is_abandoned(attempt) {
+					return false;
+				}
+				let backoff = self.reconnect_policy.backoff(attempt.consecutive_failures);
+				attempt.instant.elapsed() >= backoff
+			},
+			_ => true,
+		};
+
+		if !is_due || self.do_connect_peer(node_id, addr).await.is_ok() {
+			return false;
+		}
+
+		match self.connection_attempts.lock().unwrap().get(&node_id) {
+			Some(attempt) if self.is_abandoned(attempt) => {
+				log_info!(
+					self.logger,
+					"Giving up on reconnecting to peer {} after {} failed attempts",
+					node_id,
+					attempt.consecutive_failures
+				);
+				true
+			},
+			_ => false,
+		}
+	}
+
+	fn is_abandoned(&self, attempt: &ConnectionAttempt) -> bool {
+		self.reconnect_policy
+			.max_attempts
+			.map_or(false, |max_attempts| attempt.consecutive_failures >= max_attempts)
+	}
+
+	/// Returns the interval in which we check whether we should reconnect to any peers.
+	pub(crate) fn reconnection_interval(&self) -> Duration {
+		Duration::from_secs(self.reconnect_policy.initial_backoff_secs)
+	}
+
+	/// Forgets about any previous connection attempts to the given peer, resetting any backoff.
+	pub(crate) fn clear_connection_attempts(&self, node_id: &PublicKey) {
+		self.connection_attempts.lock().unwrap().remove(node_id);
+	}
+
+	/// Returns the time of our last connection attempt to the given peer, in seconds since the
+	/// UNIX epoch, and the reason it failed, if it did.
+	pub(crate) fn last_connection_attempt(
+		&self, node_id: &PublicKey,
+	) -> (Option<u64>, Option<ConnectionFailureReason>) {
+		match self.connection_attempts.lock().unwrap().get(node_id) {
+			Some(attempt) => (attempt.timestamp, attempt.failure_reason),
+			None => (None, None),
+		}
+	}
+
+	async fn connect_peer_inner(
+		&self, node_id: PublicKey, addr: SocketAddress, timeout: Option<Duration>,
+	) -> Result<(), ConnectionFailureReason> {
 		// First, we check if there is already an outbound connection in flight, if so, we just
 		// await on the corresponding watch channel. The task driving the connection future will
 		// send us the result..
 		let pending_ready_receiver_opt = self.register_or_subscribe_pending_connection(&node_id);
 		if let Some(pending_connection_ready_receiver) = pending_ready_receiver_opt {
-			return pending_connection_ready_receiver.await.map_err(|e| {
+			let res = match timeout {
+				Some(timeout) => {
+					match tokio::time::timeout(timeout, pending_connection_ready_receiver).await {
+						Ok(res) => res,
+						Err(_) => return Err(ConnectionFailureReason::Timeout),
+					}
+				},
+				None => pending_connection_ready_receiver.await,
+			};
+			return res.map_err(|e| {
 				debug_assert!(false, "Failed to receive connection result: {:?}", e);
 				log_error!(self.logger, "Failed to receive connection result: {:?}", e);
-				Error::ConnectionFailed
+				ConnectionFailureReason::ConnectionFailed
 			})?;
 		}
 
 		log_info!(self.logger, "Connecting to peer: {}@{}", node_id, addr);
 
-		let socket_addr = addr
-			.to_socket_addrs()
-			.map_err(|e| {
-				log_error!(self.logger, "Failed to resolve network address {}: {}", addr, e);
-				self.propagate_result_to_subscribers(&node_id, Err(Error::InvalidSocketAddress));
-				Error::InvalidSocketAddress
-			})?
-			.next()
-			.ok_or_else(|| {
-				log_error!(self.logger, "Failed to resolve network address {}", addr);
-				self.propagate_result_to_subscribers(&node_id, Err(Error::InvalidSocketAddress));
-				Error::InvalidSocketAddress
-			})?;
-
-		let connection_future = lightning_net_tokio::connect_outbound(
-			Arc::clone(&self.peer_manager),
-			node_id,
-			socket_addr,
-		);
-
-		let res = match connection_future.await {
-			Some(connection_closed_future) => {
-				let mut connection_closed_future = Box::pin(connection_closed_future);
-				loop {
-					tokio::select! {
-						_ = &mut connection_closed_future => {
-							log_info!(self.logger, "Peer connection closed: {}@{}", node_id, addr);
-							break Err(Error::ConnectionFailed);
-						},
-						_ = tokio::time::sleep(Duration::from_millis(10)) => {},
-					};
-
-					match self.peer_manager.peer_by_node_id(&node_id) {
-						Some(_) => break Ok(()),
-						None => continue,
-					}
+		let mut stream = None;
+		let res = match timeout {
+			Some(timeout) => {
+				let connection_future =
+					self.establish_connection(node_id, &addr, timeout, &mut stream);
+				match tokio::time::timeout(timeout, connection_future).await {
+					Ok(res) => res,
+					Err(_) if self.peer_manager.peer_by_node_id(&node_id).is_some() => Ok(()),
+					Err(_) => {
+						// The connection is driven by a spawned task, so we need to shut down the
+						// socket to keep it from completing after we reported the timeout.
+						if let Some(stream) = stream {
+							let _ = stream.shutdown(Shutdown::Both);
+						}
+						log_error!(
+							self.logger,
+							"Timed out connecting to peer after {}s: {}@{}",
+							timeout.as_secs(),
+							node_id,
+							addr
+						);
+						Err(ConnectionFailureReason::Timeout)
+					},
 				}
 			},
 			None => {
-				log_error!(self.logger, "Failed to connect to peer: {}@{}", node_id, addr);
-				Err(Error::ConnectionFailed)
+				self.establish_connection(node_id, &addr, TCP_CONNECT_TIMEOUT, &mut stream).await
 			},
 		};
 
+		self.record_connection_attempt(node_id, res);
 		self.propagate_result_to_subscribers(&node_id, res);
 
 		res
 	}
 
+	// Connects to the given peer, handing out a handle to the socket via `stream_handle` once it
+	// was opened. Fails if the socket couldn't be opened within `connect_timeout`.
+	async fn establish_connection(
+		&self, node_id: PublicKey, addr: &SocketAddress, connect_timeout: Duration,
+		stream_handle: &mut Option<TcpStream>,
+	) -> Result<(), ConnectionFailureReason> {
+		let stream = if let SocketAddress::OnionV2(_) | SocketAddress::OnionV3 { .. } = addr {
+			let tor_proxy = self.tor_proxy.as_ref().ok_or_else(|| {
+				log_error!(
+					self.logger,
+					"Failed to connect to onion address {} as no Tor proxy is configured",
+					addr
+				);
+				ConnectionFailureReason::UnsupportedAddress
+			})?;
+
+			connect_via_socks5_proxy(tor_proxy, addr).await.map_err(|e| {
+				log_error!(self.logger, "Failed to connect via Tor proxy: {}", e);
+				connection_failure_reason_from_io_error(&e)
+			})?
+		} else {
+			let socket_addr = addr
+				.to_socket_addrs()
+				.map_err(|e| {
+					log_error!(self.logger, "Failed to resolve network address {}: {}", addr, e);
+					ConnectionFailureReason::DnsResolutionFailed
+				})?
+				.next()
+				.ok_or_else(|| {
+					log_error!(self.logger, "Failed to resolve network address {}", addr);
+					ConnectionFailureReason::DnsResolutionFailed
+				})?;
+
+			let connect_future = tokio::net::TcpStream::connect(socket_addr);
+			match tokio::time::timeout(connect_timeout, connect_future).await {
+				Ok(res) => res.and_then(|stream| stream.into_std()).map_err(|e| {
+					log_error!(
+						self.logger,
+						"Failed to connect to peer {}@{}: {}",
+						node_id,
+						addr,
+						e
+					);
+					connection_failure_reason_from_io_error(&e)
+				})?,
+				Err(_) => {
+					log_error!(
+						self.logger,
+						"Timed out connecting to peer after {}s: {}@{}",
+						connect_timeout.as_secs(),
+						node_id,
+						addr
+					);
+					return Err(ConnectionFailureReason::Timeout);
+				},
+			}
+		};
+
+		*stream_handle = stream.try_clone().ok();
+		let mut connection_closed_future: Pin<Box<dyn Future<Output = ()> + Send>> = Box::pin(
+			lightning_net_tokio::setup_outbound(Arc::clone(&self.peer_manager), node_id, stream),
+		);
+
+		loop {
+			tokio::select! {
+				_ = &mut connection_closed_future => {
+					log_info!(self.logger, "Peer connection closed: {}@{}", node_id, addr);
+					return Err(ConnectionFailureReason::HandshakeFailed);
+				},
+				_ = tokio::time::sleep(Duration::from_millis(10)) => {},
+			};
+
+			match self.peer_manager.peer_by_node_id(&node_id) {
+				Some(_) => return Ok(()),
+				None => continue,
+			}
+		}
+	}
+
+	fn record_connection_attempt(
+		&self, node_id: PublicKey, res: Result<(), ConnectionFailureReason>,
+	) {
+		let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs());
+		let mut locked_attempts = self.connection_attempts.lock().unwrap();
+		let prev_failures = locked_attempts.get(&node_id).map_or(0, |a| a.consecutive_failures);
+		let (failure_reason, consecutive_failures) = match res {
+			Ok(()) => (None, 0),
+			Err(reason) => (Some(reason), prev_failures.saturating_add(1)),
+		};
+		let attempt = ConnectionAttempt {
+			timestamp,
+			instant: Instant::now(),
+			failure_reason,
+			consecutive_failures,
+		};
+		locked_attempts.insert(node_id, attempt);
+	}
+
 	fn register_or_subscribe_pending_connection(
 		&self, node_id: &PublicKey,
-	) -> Option<tokio::sync::oneshot::Receiver<Result<(), Error>>> {
+	) -> Option<tokio::sync::oneshot::Receiver<Result<(), ConnectionFailureReason>>> {
 		let mut pending_connections_lock = self.pending_connections.lock().unwrap();
 		match pending_connections_lock.entry(*node_id) {
 			hash_map::Entry::Occupied(mut entry) => {
@@ -130,7 +343,9 @@ where
 		}
 	}
 
-	fn propagate_result_to_subscribers(&self, node_id: &PublicKey, res: Result<(), Error>) {
+	fn propagate_result_to_subscribers(
+		&self, node_id: &PublicKey, res: Result<(), ConnectionFailureReason>,
+	) {
 		// Send the result to any other tasks that might be waiting on it by now.
 		let mut pending_connections_lock = self.pending_connections.lock().unwrap();
 		if let Some(connection_ready_senders) = pending_connections_lock.remove(node_id) {
@@ -151,3 +366,11 @@ where
 		}
 	}
 }
+
+fn connection_failure_reason_from_io_error(e: &io::Error) -> ConnectionFailureReason {
+	match e.kind() {
+		io::ErrorKind::ConnectionRefused => ConnectionFailureReason::ConnectionRefused,
+		io::ErrorKind::TimedOut => ConnectionFailureReason::Timeout,
+		_ => ConnectionFailureReason::ConnectionFailed,
+	}
+}
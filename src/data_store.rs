@@ -19,8 +19,13 @@ use crate::Error;
 pub(crate) trait StorableObject: Clone + Readable + Writeable {
 	type Id: StorableObjectId;
 	type Update: StorableObjectUpdate<Self>;
+	/// A secondary key the object can be looked up by via [`DataStore::get_by_index_key`].
+	type IndexKey: std::hash::Hash + PartialEq + Eq;
+	/// Running totals maintained over all objects, as returned by [`DataStore::aggregate`].
+	type Aggregate: StorableObjectAggregate<Self>;
 
 	fn id(&self) -> Self::Id;
+	fn index_key(&self) -> Option<Self::IndexKey>;
 	fn update(&mut self, update: &Self::Update) -> bool;
 	fn to_update(&self) -> Self::Update;
 }
@@ -33,6 +38,16 @@ pub(crate) trait StorableObjectUpdate<SO: StorableObject> {
 	fn id(&self) -> SO::Id;
 }
 
+pub(crate) trait StorableObjectAggregate<SO>: Clone + Default {
+	fn add(&mut self, object: &SO);
+	fn remove(&mut self, object: &SO);
+}
+
+impl<SO> StorableObjectAggregate<SO> for () {
+	fn add(&mut self, _object: &SO) {}
+	fn remove(&mut self, _object: &SO) {}
+}
+
 #[derive(PartialEq, Eq, Debug, Clone, Copy)]
 pub(crate) enum DataStoreUpdateResult {
 	Updated,
@@ -45,6 +60,8 @@ where
 	L::Target: LdkLogger,
 {
 	objects: Mutex<HashMap<SO::Id, SO>>,
+	index: Mutex<HashMap<SO::IndexKey, SO::Id>>,
+	aggregate: Mutex<SO::Aggregate>,
 	primary_namespace: String,
 	secondary_namespace: String,
 	kv_store: Arc<DynStore>,
@@ -59,17 +76,47 @@ where
 		objects: Vec<SO>, primary_namespace: String, secondary_namespace: String,
 		kv_store: Arc<DynStore>, logger: L,
 	) -> Self {
+		let index = Mutex::new(HashMap::from_iter(
+			objects.iter().filter_map(|obj| obj.index_key().map(|key| (key, obj.id()))),
+		));
+		let mut aggregate = SO::Aggregate::default();
+		objects.iter().for_each(|obj| aggregate.add(obj));
+		let aggregate = Mutex::new(aggregate);
 		let objects =
 			Mutex::new(HashMap::from_iter(objects.into_iter().map(|obj| (obj.id(), obj))));
-		Self { objects, primary_namespace, secondary_namespace, kv_store, logger }
+		Self { objects, index, aggregate, primary_namespace, secondary_namespace, kv_store, logger }
 	}
 
 	pub(crate) fn insert(&self, object: SO) -> Result<bool, Error> {
 		let mut locked_objects = self.objects.lock().unwrap();
 
 		self.persist(&object)?;
-		let updated = locked_objects.insert(object.id(), object).is_some();
-		Ok(updated)
+		let old_index_key = locked_objects.get(&object.id()).and_then(|obj| obj.index_key());
+		self.reindex(old_index_key, &object);
+		let mut locked_aggregate = self.aggregate.lock().unwrap();
+		locked_aggregate.add(&object);
+		let old_object = locked_objects.insert(object.id(), object);
+		if let Some(old_object) = old_object.as_ref() {
+			locked_aggregate.remove(old_object);
+		}
+		Ok(old_object.is_some())
+	}
+
+	/// Inserts the object unless one with the same id exists already, in which case the existing
+	/// object is returned and nothing is inserted.
+	pub(crate) fn insert_if_absent(&self, object: SO) -> Result<Option<SO>, Error> {
+		let mut locked_objects = self.objects.lock().unwrap();
+
+		match locked_objects.entry(object.id()) {
+			hash_map::Entry::Occupied(e) => Ok(Some(e.get().clone())),
+			hash_map::Entry::Vacant(e) => {
+				self.persist(&object)?;
+				self.reindex(None, &object);
+				self.aggregate.lock().unwrap().add(&object);
+				e.insert(object);
+				Ok(None)
+			},
+		}
 	}
 
 	pub(crate) fn insert_or_update(&self, object: SO) -> Result<bool, Error> {
@@ -79,12 +126,19 @@ where
 		match locked_objects.entry(object.id()) {
 			hash_map::Entry::Occupied(mut e) => {
 				let update = object.to_update();
+				let old_index_key = e.get().index_key();
+				let mut locked_aggregate = self.aggregate.lock().unwrap();
+				locked_aggregate.remove(e.get());
 				updated = e.get_mut().update(&update);
+				locked_aggregate.add(e.get());
 				if updated {
+					self.reindex(old_index_key, e.get());
 					self.persist(&e.get())?;
 				}
 			},
 			hash_map::Entry::Vacant(e) => {
+				self.reindex(None, &object);
+				self.aggregate.lock().unwrap().add(&object);
 				e.insert(object.clone());
 				self.persist(&object)?;
 				updated = true;
@@ -95,7 +149,20 @@ where
 	}
 
 	pub(crate) fn remove(&self, id: &SO::Id) -> Result<(), Error> {
-		let removed = self.objects.lock().unwrap().remove(id).is_some();
+		let removed = {
+			let mut locked_objects = self.objects.lock().unwrap();
+			let removed_object = locked_objects.remove(id);
+			if let Some(removed_object) = removed_object.as_ref() {
+				self.aggregate.lock().unwrap().remove(removed_object);
+			}
+			if let Some(index_key) = removed_object.as_ref().and_then(|obj| obj.index_key()) {
+				let mut locked_index = self.index.lock().unwrap();
+				if locked_index.get(&index_key) == Some(id) {
+					locked_index.remove(&index_key);
+				}
+			}
+			removed_object.is_some()
+		};
 		if removed {
 			let store_key = id.encode_to_hex_str();
 			KVStoreSync::remove(
@@ -123,12 +190,28 @@ where
 		self.objects.lock().unwrap().get(id).cloned()
 	}
 
+	/// Returns the object indexed under the given key, if any.
+	///
+	/// If several objects share an index key, the one most recently inserted or updated to carry
+	/// the key is returned.
+	pub(crate) fn get_by_index_key(&self, index_key: &SO::IndexKey) -> Option<SO> {
+		let locked_objects = self.objects.lock().unwrap();
+		let locked_index = self.index.lock().unwrap();
+		locked_index.get(index_key).and_then(|id| locked_objects.get(id)).cloned()
+	}
+
 	pub(crate) fn update(&self, update: &SO::Update) -> Result<DataStoreUpdateResult, Error> {
 		let mut locked_objects = self.objects.lock().unwrap();
 
 		if let Some(object) = locked_objects.get_mut(&update.id()) {
+			let old_index_key = object.index_key();
+			let mut locked_aggregate = self.aggregate.lock().unwrap();
+			locked_aggregate.remove(object);
 			let updated = object.update(update);
+			locked_aggregate.add(object);
+			drop(locked_aggregate);
 			if updated {
+				self.reindex(old_index_key, object);
 				self.persist(&object)?;
 				Ok(DataStoreUpdateResult::Updated)
 			} else {
@@ -143,6 +226,55 @@ where
 		self.objects.lock().unwrap().values().filter(f).cloned().collect::<Vec<SO>>()
 	}
 
+	/// Returns the objects matching the given predicate, sorted by the given key.
+	///
+	/// Only the page of objects selected via `offset` and `limit` will be cloned.
+	pub(crate) fn list_filter_sorted<F: FnMut(&&SO) -> bool, K: Ord, C: FnMut(&SO) -> K>(
+		&self, f: F, mut sort_key: C, offset: usize, limit: Option<usize>,
+	) -> Vec<SO> {
+		let locked_objects = self.objects.lock().unwrap();
+		let mut matching = locked_objects.values().filter(f).collect::<Vec<&SO>>();
+		matching.sort_unstable_by_key(|object| sort_key(object));
+		matching
+			.into_iter()
+			.skip(offset)
+			.take(limit.unwrap_or(usize::MAX))
+			.cloned()
+			.collect::<Vec<SO>>()
+	}
+
+	pub(crate) fn count_filter<F: FnMut(&&SO) -> bool>(&self, f: F) -> usize {
+		self.objects.lock().unwrap().values().filter(f).count()
+	}
+
+	/// Returns the running totals maintained over all objects.
+	pub(crate) fn aggregate(&self) -> SO::Aggregate {
+		self.aggregate.lock().unwrap().clone()
+	}
+
+	/// Folds all objects into an accumulator without cloning them.
+	pub(crate) fn fold<B, F: FnMut(B, &SO) -> B>(&self, init: B, f: F) -> B {
+		self.objects.lock().unwrap().values().fold(init, f)
+	}
+
+	// Needs to be called while holding the `objects` lock.
+	fn reindex(&self, old_index_key: Option<SO::IndexKey>, object: &SO) {
+		let new_index_key = object.index_key();
+		if old_index_key == new_index_key {
+			return;
+		}
+
+		let mut locked_index = self.index.lock().unwrap();
+		if let Some(old_index_key) = old_index_key {
+			if locked_index.get(&old_index_key) == Some(&object.id()) {
+				locked_index.remove(&old_index_key);
+			}
+		}
+		if let Some(new_index_key) = new_index_key {
+			locked_index.insert(new_index_key, object.id());
+		}
+	}
+
 	fn persist(&self, object: &SO) -> Result<(), Error> {
 		let store_key = object.id().encode_to_hex_str();
 		let data = object.encode();
@@ -207,11 +339,17 @@ mod tests {
 	impl StorableObject for TestObject {
 		type Id = TestObjectId;
 		type Update = TestObjectUpdate;
+		type IndexKey = [u8; 3];
+		type Aggregate = ();
 
 		fn id(&self) -> Self::Id {
 			self.id
 		}
 
+		fn index_key(&self) -> Option<Self::IndexKey> {
+			Some(self.data)
+		}
+
 		fn update(&mut self, update: &Self::Update) -> bool {
 			if self.data != update.data {
 				self.data = update.data;
@@ -294,4 +432,71 @@ mod tests {
 		new_iou_object.data[0] += 1;
 		assert_eq!(Ok(true), data_store.insert_or_update(new_iou_object));
 	}
+
+	#[test]
+	fn objects_are_indexed() {
+		let store: Arc<DynStore> = Arc::new(TestStore::new(false));
+		let logger = Arc::new(TestLogger::new());
+		let id = TestObjectId { id: [42u8; 4] };
+		let object = TestObject { id, data: [23u8; 3] };
+		let data_store: DataStore<TestObject, Arc<TestLogger>> = DataStore::new(
+			vec![object],
+			"datastore_test_primary".to_string(),
+			"datastore_test_secondary".to_string(),
+			Arc::clone(&store),
+			logger,
+		);
+
+		// Check objects we were initialized with are indexed.
+		assert_eq!(Some(object), data_store.get_by_index_key(&[23u8; 3]));
+		assert_eq!(None, data_store.get_by_index_key(&[24u8; 3]));
+
+		// Check the index follows updates.
+		let update = TestObjectUpdate { id, data: [24u8; 3] };
+		assert_eq!(Ok(DataStoreUpdateResult::Updated), data_store.update(&update));
+		assert_eq!(None, data_store.get_by_index_key(&[23u8; 3]));
+		assert_eq!(id, data_store.get_by_index_key(&[24u8; 3]).unwrap().id);
+
+		// Check the most recently inserted object wins if the index key is shared.
+		let other_id = TestObjectId { id: [43u8; 4] };
+		let other_object = TestObject { id: other_id, data: [24u8; 3] };
+		assert_eq!(Ok(false), data_store.insert(other_object));
+		assert_eq!(Some(other_object), data_store.get_by_index_key(&[24u8; 3]));
+
+		// Check removing an object that isn't indexed anymore leaves the index intact.
+		data_store.remove(&id).unwrap();
+		assert_eq!(Some(other_object), data_store.get_by_index_key(&[24u8; 3]));
+
+		data_store.remove(&other_id).unwrap();
+		assert_eq!(None, data_store.get_by_index_key(&[24u8; 3]));
+	}
+
+	#[test]
+	fn sorted_listing_is_paginated() {
+		let store: Arc<DynStore> = Arc::new(TestStore::new(false));
+		let logger = Arc::new(TestLogger::new());
+		let objects = (0..10u8)
+			.map(|i| TestObject { id: TestObjectId { id: [i; 4] }, data: [i; 3] })
+			.collect::<Vec<_>>();
+		let data_store: DataStore<TestObject, Arc<TestLogger>> = DataStore::new(
+			objects,
+			"datastore_test_primary".to_string(),
+			"datastore_test_secondary".to_string(),
+			store,
+			logger,
+		);
+
+		let is_even = |o: &&TestObject| o.data[0] % 2 == 0;
+		let descending = |o: &TestObject| std::cmp::Reverse(o.data[0]);
+		assert_eq!(data_store.count_filter(is_even), 5);
+
+		let all = data_store.list_filter_sorted(is_even, descending, 0, None);
+		assert_eq!(all.iter().map(|o| o.data[0]).collect::<Vec<_>>(), vec![8, 6, 4, 2, 0]);
+
+		let page = data_store.list_filter_sorted(is_even, descending, 1, Some(2));
+		assert_eq!(page.iter().map(|o| o.data[0]).collect::<Vec<_>>(), vec![6, 4]);
+
+		let past_end = data_store.list_filter_sorted(is_even, descending, 5, Some(2));
+		assert!(past_end.is_empty());
+	}
 }
@@ -7,6 +7,8 @@
 
 use std::fmt;
 
+use lightning::ln::channelmanager::PaymentId;
+
 use bdk_chain::bitcoin::psbt::ExtractTxError as BdkExtractTxError;
 use bdk_chain::local_chain::CannotConnectError as BdkChainConnectionError;
 use bdk_chain::tx_graph::CalculateFeeError as BdkChainCalculateFeeError;
@@ -45,6 +47,12 @@ pub enum Error {
 	ChannelClosingFailed,
 	/// A channel configuration could not be updated.
 	ChannelConfigUpdateFailed,
+	/// A channel could not be spliced.
+	ChannelSplicingFailed,
+	/// The given channel could not be found.
+	ChannelNotFound,
+	/// An intercepted HTLC could not be forwarded.
+	HtlcForwardingFailed,
 	/// Persistence failed.
 	PersistenceFailed,
 	/// A fee rate estimation update failed.
@@ -61,6 +69,8 @@ pub enum Error {
 	TxSyncFailed,
 	/// A transaction sync operation timed out.
 	TxSyncTimeout,
+	/// A transaction could not be broadcast.
+	TxBroadcastFailed,
 	/// A gossip updating operation failed.
 	GossipUpdateFailed,
 	/// A gossip updating operation timed out.
@@ -105,26 +115,93 @@ pub enum Error {
 	InvalidUri,
 	/// The given quantity is invalid.
 	InvalidQuantity,
+	/// The given payer note is invalid.
+	InvalidPayerNote,
 	/// The given node alias is invalid.
 	InvalidNodeAlias,
+	/// The given node color is invalid.
+	InvalidNodeColor,
 	/// The given date time is invalid.
 	InvalidDateTime,
 	/// The given fee rate is invalid.
 	InvalidFeeRate,
+	/// The given route is invalid.
+	InvalidRoute,
+	/// The given recurrence interval is invalid.
+	InvalidRecurrenceInterval,
+	/// The given intercept id is invalid.
+	InvalidInterceptId,
+	/// The given local channel or peer label is invalid.
+	InvalidLabel,
+	/// The given description hash is invalid.
+	InvalidDescriptionHash,
+	/// The given LNURL is invalid.
+	InvalidLnurl,
+	/// The given comment is invalid.
+	InvalidComment,
+	/// A request to an LNURL service failed.
+	LnurlRequestFailed,
+	/// The given Lightning Address is invalid.
+	InvalidLightningAddress,
+	/// The requested LNURL service or Lightning Address doesn't exist.
+	LnurlNotFound,
+	/// No route for the given payment could be found.
+	RouteNotFound,
 	/// A payment with the given hash has already been initiated.
 	DuplicatePayment,
+	/// A payment was already sent with the given idempotency key.
+	DuplicateIdempotencyKey {
+		/// The id of the payment that was sent with the key.
+		existing_payment_id: PaymentId,
+	},
 	/// The provided offer was denonminated in an unsupported currency.
 	UnsupportedCurrency,
 	/// The available funds are insufficient to complete the given operation.
 	InsufficientFunds,
 	/// The given operation failed due to the required liquidity source being unavailable.
 	LiquiditySourceUnavailable,
-	/// The given operation failed due to the LSP's required opening fee being too high.
-	LiquidityFeeTooHigh,
+	/// The given liquidity parameters are invalid, e.g., as they expired.
+	InvalidLiquidityParams,
+	/// The given operation failed due to the LSP's quoted opening fee exceeding our limit.
+	///
+	/// Both values are given in millisatoshis, or in parts-per-million millisatoshis if the
+	/// proportional fee limit was exceeded.
+	LspFeeExceedsLimit {
+		/// The fee quoted by the LSP.
+		quoted: u64,
+		/// The limit we authorized.
+		limit: u64,
+	},
 	/// The given blinded paths are invalid.
 	InvalidBlindedPaths,
 	/// Asynchronous payment services are disabled.
 	AsyncPaymentServicesDisabled,
+	/// Requesting BOLT12 invoices without paying them is disabled.
+	InvoiceRequestsDisabled,
+	/// The given operation requires listening addresses to be configured.
+	NoListeningAddress,
+	/// The given mnemonic is invalid, e.g., it has an invalid checksum or contains unknown words.
+	InvalidMnemonic,
+	/// The given transaction can't be replaced, e.g., as it didn't signal RBF or is already
+	/// confirmed.
+	NotReplaceable,
+	/// The channel was closed before it became ready to be used.
+	ChannelClosedBeforeReady,
+	/// The channel didn't become ready to be used in time.
+	ChannelReadyTimeout,
+	/// The given PSBT is invalid, e.g., it isn't fully signed or doesn't pay the expected output.
+	InvalidPsbt,
+	/// The given onion message is invalid, e.g., its TLV type isn't permitted or it's too large.
+	InvalidOnionMessage,
+	/// Sending an onion message has failed.
+	OnionMessageSendingFailed,
+	/// The given offer has expired.
+	OfferExpired,
+	/// The recipient of an invoice request failed to respond with a valid invoice, e.g., as it
+	/// rejected the request.
+	InvoiceRequestFailed,
+	/// The recipient of an invoice request didn't respond in time, e.g., as it is unreachable.
+	InvoiceRequestTimeout,
 }
 
 impl fmt::Display for Error {
@@ -146,6 +223,9 @@ impl fmt::Display for Error {
 			Self::ChannelCreationFailed => write!(f, "Failed to create channel."),
 			Self::ChannelClosingFailed => write!(f, "Failed to close channel."),
 			Self::ChannelConfigUpdateFailed => write!(f, "Failed to update channel config."),
+			Self::ChannelSplicingFailed => write!(f, "Failed to splice channel."),
+			Self::ChannelNotFound => write!(f, "The given channel could not be found."),
+			Self::HtlcForwardingFailed => write!(f, "Failed to forward intercepted HTLC."),
 			Self::PersistenceFailed => write!(f, "Failed to persist data."),
 			Self::FeerateEstimationUpdateFailed => {
 				write!(f, "Failed to update fee rate estimates.")
@@ -158,6 +238,7 @@ impl fmt::Display for Error {
 			Self::OnchainTxSigningFailed => write!(f, "Failed to sign given transaction."),
 			Self::TxSyncFailed => write!(f, "Failed to sync transactions."),
 			Self::TxSyncTimeout => write!(f, "Syncing transactions timed out."),
+			Self::TxBroadcastFailed => write!(f, "Failed to broadcast transaction."),
 			Self::GossipUpdateFailed => write!(f, "Failed to update gossip data."),
 			Self::GossipUpdateTimeout => write!(f, "Updating gossip data timed out."),
 			Self::LiquidityRequestFailed => write!(f, "Failed to request inbound liquidity."),
@@ -180,12 +261,36 @@ impl fmt::Display for Error {
 			Self::InvalidNetwork => write!(f, "The given network is invalid."),
 			Self::InvalidUri => write!(f, "The given URI is invalid."),
 			Self::InvalidQuantity => write!(f, "The given quantity is invalid."),
+			Self::InvalidPayerNote => write!(f, "The given payer note is invalid."),
 			Self::InvalidNodeAlias => write!(f, "The given node alias is invalid."),
+			Self::InvalidNodeColor => write!(f, "The given node color is invalid."),
 			Self::InvalidDateTime => write!(f, "The given date time is invalid."),
 			Self::InvalidFeeRate => write!(f, "The given fee rate is invalid."),
+			Self::InvalidRoute => write!(f, "The given route is invalid."),
+			Self::InvalidRecurrenceInterval => {
+				write!(f, "The given recurrence interval is invalid.")
+			},
+			Self::InvalidInterceptId => write!(f, "The given intercept id is invalid."),
+			Self::InvalidLabel => write!(f, "The given local label is invalid."),
+			Self::InvalidDescriptionHash => write!(f, "The given description hash is invalid."),
+			Self::InvalidLnurl => write!(f, "The given LNURL is invalid."),
+			Self::InvalidComment => write!(f, "The given comment is invalid."),
+			Self::LnurlRequestFailed => write!(f, "A request to an LNURL service failed."),
+			Self::InvalidLightningAddress => write!(f, "The given Lightning Address is invalid."),
+			Self::LnurlNotFound => {
+				write!(f, "The requested LNURL service or Lightning Address doesn't exist.")
+			},
+			Self::RouteNotFound => write!(f, "Failed to find a route for the given payment."),
 			Self::DuplicatePayment => {
 				write!(f, "A payment with the given hash has already been initiated.")
 			},
+			Self::DuplicateIdempotencyKey { existing_payment_id } => {
+				write!(
+					f,
+					"Payment {} was already sent with the given idempotency key.",
+					existing_payment_id
+				)
+			},
 			Self::InsufficientFunds => {
 				write!(f, "The available funds are insufficient to complete the given operation.")
 			},
@@ -195,13 +300,44 @@ impl fmt::Display for Error {
 			Self::LiquiditySourceUnavailable => {
 				write!(f, "The given operation failed due to the required liquidity source being unavailable.")
 			},
-			Self::LiquidityFeeTooHigh => {
-				write!(f, "The given operation failed due to the LSP's required opening fee being too high.")
+			Self::InvalidLiquidityParams => {
+				write!(f, "The given liquidity parameters are invalid.")
+			},
+			Self::LspFeeExceedsLimit { quoted, limit } => {
+				write!(
+					f,
+					"The given operation failed due to the LSP's quoted opening fee of {} exceeding our limit of {}.",
+					quoted, limit
+				)
 			},
 			Self::InvalidBlindedPaths => write!(f, "The given blinded paths are invalid."),
 			Self::AsyncPaymentServicesDisabled => {
 				write!(f, "Asynchronous payment services are disabled.")
 			},
+			Self::InvoiceRequestsDisabled => {
+				write!(f, "Requesting invoices without paying them is disabled.")
+			},
+			Self::NoListeningAddress => {
+				write!(f, "The given operation requires listening addresses to be configured.")
+			},
+			Self::InvalidMnemonic => write!(f, "The given mnemonic is invalid."),
+			Self::NotReplaceable => write!(f, "The given transaction can't be replaced."),
+			Self::ChannelClosedBeforeReady => {
+				write!(f, "The channel was closed before it became ready to be used.")
+			},
+			Self::ChannelReadyTimeout => {
+				write!(f, "The channel didn't become ready to be used in time.")
+			},
+			Self::InvalidPsbt => write!(f, "The given PSBT is invalid."),
+			Self::InvalidOnionMessage => write!(f, "The given onion message is invalid."),
+			Self::OnionMessageSendingFailed => write!(f, "Failed to send the given onion message."),
+			Self::OfferExpired => write!(f, "The given offer has expired."),
+			Self::InvoiceRequestFailed => {
+				write!(f, "The recipient failed to respond to the invoice request.")
+			},
+			Self::InvoiceRequestTimeout => {
+				write!(f, "The recipient didn't respond to the invoice request in time.")
+			},
 		}
 	}
 }
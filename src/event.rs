@@ -6,22 +6,34 @@
 // accordance with one or both of these licenses.
 
 use core::future::Future;
-use core::task::{Poll, Waker};
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
 use std::collections::VecDeque;
 use std::ops::Deref;
-use std::sync::{Arc, Condvar, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
+use bitcoin::address::NetworkUnchecked;
 use bitcoin::blockdata::locktime::absolute::LockTime;
+use bitcoin::hashes::sha256::Hash as Sha256;
+use bitcoin::hashes::Hash;
 use bitcoin::secp256k1::PublicKey;
-use bitcoin::{Amount, OutPoint};
+use bitcoin::{Address, Amount, Network, OutPoint, Txid};
+use futures_core::Stream;
 use lightning::events::bump_transaction::BumpTransactionEvent;
 use lightning::events::{
-	ClosureReason, Event as LdkEvent, PaymentFailureReason, PaymentPurpose, ReplayEvent,
+	ClosureReason, Event as LdkEvent, FundingInfo, PaymentFailureReason, PaymentPurpose,
+	ReplayEvent,
 };
 use lightning::impl_writeable_tlv_based_enum;
-use lightning::ln::channelmanager::PaymentId;
+use lightning::ln::channelmanager::{Bolt12PaymentError, InterceptId, PaymentId};
 use lightning::ln::types::ChannelId;
+use lightning::offers::invoice_error::InvoiceError;
+use lightning::offers::parse::Bolt12SemanticError;
+use lightning::onion_message::offers::OffersMessage;
 use lightning::routing::gossip::NodeId;
+use lightning::sign::SpendableOutputDescriptor;
 use lightning::util::config::{
 	ChannelConfigOverrides, ChannelConfigUpdate, ChannelHandshakeConfigUpdate,
 };
@@ -30,12 +42,18 @@ use lightning::util::persist::KVStoreSync;
 use lightning::util::ser::{Readable, ReadableArgs, Writeable, Writer};
 use lightning_liquidity::lsps2::utils::compute_opening_fee;
 use lightning_types::payment::{PaymentHash, PaymentPreimage};
+use lightning_types::string::UntrustedString;
 use rand::{thread_rng, Rng};
 
-use crate::config::{may_announce_channel, Config};
+use crate::closed_channel::{CloseType, ClosedChannelDetails, ClosedChannelDetailsUpdate};
+use crate::config::{
+	may_announce_channel, Config, RetryStrategy, EVENT_DISPATCHER_POLL_INTERVAL,
+	EVENT_HANDLER_REPLAY_DELAY, EXTERNAL_FUNDING_PSBT_TIMEOUT, MAX_INBOUND_DUST_LIMIT_SATS,
+};
 use crate::connection::ConnectionManager;
 use crate::data_store::DataStoreUpdateResult;
 use crate::fee_estimator::ConfirmationTarget;
+use crate::io::utils::write_node_metrics;
 use crate::io::{
 	EVENT_QUEUE_PERSISTENCE_KEY, EVENT_QUEUE_PERSISTENCE_PRIMARY_NAMESPACE,
 	EVENT_QUEUE_PERSISTENCE_SECONDARY_NAMESPACE,
@@ -44,19 +62,34 @@ use crate::liquidity::LiquiditySource;
 use crate::logger::{log_debug, log_error, log_info, log_trace, LdkLogger, Logger};
 use crate::payment::asynchronous::om_mailbox::OnionMessageMailbox;
 use crate::payment::asynchronous::static_invoice_store::StaticInvoiceStore;
+use crate::payment::claim_watchdog::ClaimDeadlineWatchdog;
+use crate::payment::recurring::RecurringPaymentId;
 use crate::payment::store::{
 	PaymentDetails, PaymentDetailsUpdate, PaymentDirection, PaymentKind, PaymentStatus,
 };
 use crate::runtime::Runtime;
-use crate::types::{CustomTlvRecord, DynStore, OnionMessenger, PaymentStore, Sweeper, Wallet};
+use crate::types::{
+	Broadcaster, ChannelReadyWaiters, ClosedChannelStore, CustomTlvRecord, DynStore,
+	FundingConfirmationTargets, OnionMessenger, PaymentRetryStrategies, PaymentStore,
+	PendingBolt12Invoice, PendingBolt12Invoices, PendingChannelCloseBumps, PendingExternalFunding,
+	PendingExternalFundings, Sweeper, Wallet,
+};
 use crate::{
-	hex_utils, BumpTransactionEventHandler, ChannelManager, Error, Graph, PeerInfo, PeerStore,
-	UserChannelId,
+	hex_utils, BumpTransactionEventHandler, ChannelManager, Error, Graph, NodeMetrics, PeerInfo,
+	PeerStore, UserChannelId,
 };
 
 /// An event emitted by [`Node`], which should be handled by the user.
 ///
+/// Events are delivered *at least once*: they are persisted as part of the event queue and only
+/// removed from it once handling is confirmed via [`Node::event_handled`]. If the process crashes
+/// or is restarted before the confirmation, all unconfirmed events will be redelivered in their
+/// original order. Note this means users have to be prepared to see the same event more than
+/// once, e.g., by applying any side effects of handling an event idempotently before confirming
+/// its handling.
+///
 /// [`Node`]: [`crate::Node`]
+/// [`Node::event_handled`]: crate::Node::event_handled
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Event {
 	/// A sent payment was successful.
@@ -93,6 +126,12 @@ pub enum Event {
 		///
 		/// This will be `None` for events serialized by LDK Node v0.2.1 and prior.
 		reason: Option<PaymentFailureReason>,
+		/// The retry strategy whose bound was hit, if the payment failed because its retries were
+		/// exhausted.
+		///
+		/// This will be `None` if the payment failed for another reason, or if the node was
+		/// restarted while the payment was pending.
+		retry_strategy: Option<RetryStrategy>,
 	},
 	/// A payment has been received.
 	PaymentReceived {
@@ -106,6 +145,13 @@ pub enum Event {
 		amount_msat: u64,
 		/// Custom TLV records received on the payment
 		custom_records: Vec<CustomTlvRecord>,
+		/// The note the payer provided when requesting the invoice for a [BOLT 12] offer payment.
+		///
+		/// Truncated to [`PAYER_NOTE_LIMIT`] bytes. Will be `None` for other kinds of payments.
+		///
+		/// [BOLT 12]: https://github.com/lightning/bolts/blob/master/12-offer-encoding.md
+		/// [`PAYER_NOTE_LIMIT`]: lightning::offers::invoice_request::PAYER_NOTE_LIMIT
+		payer_note: Option<UntrustedString>,
 	},
 	/// A payment has been forwarded.
 	PaymentForwarded {
@@ -182,8 +228,33 @@ pub enum Event {
 		/// The block height at which this payment will be failed back and will no longer be
 		/// eligible for claiming.
 		claim_deadline: Option<u32>,
+		/// An estimate of the time at which the `claim_deadline` will be reached, as seconds since
+		/// the UNIX epoch.
+		///
+		/// The estimate is based on the average block interval of the used network. It will be
+		/// `None` if no `claim_deadline` is known, if we haven't synced the chain yet, or on
+		/// networks without a meaningful block interval such as regtest.
+		///
+		/// Will also be `None` for events serialized with LDK Node v0.7.0 or prior.
+		claim_deadline_unix_secs: Option<u64>,
 		/// Custom TLV records attached to the payment
 		custom_records: Vec<CustomTlvRecord>,
+		/// Whether the payment was received via a just-in-time channel opened by our LSP, i.e., it
+		/// was paid to an invoice retrieved via one of the `receive_via_jit_channel` variants.
+		///
+		/// Will be `false` for events serialized with LDK Node v0.7.0 or prior.
+		via_jit_channel: bool,
+		/// Whether the funding transactions of all channels the payment was received over have
+		/// been confirmed on-chain.
+		///
+		/// If this is `false`, the payment was received over a zero-confirmation channel, e.g., a
+		/// just-in-time channel opened by a trusted LSP. Claiming the payment then settles it over
+		/// a channel that might never confirm, so it may be preferable to wait for the
+		/// corresponding [`Event::ChannelReady`] or confirmation before releasing the preimage,
+		/// as long as the `claim_deadline` permits.
+		///
+		/// Will be `true` for events serialized with LDK Node v0.7.0 or prior.
+		channel_confirmed: bool,
 	},
 	/// A channel has been created and is pending confirmation on-chain.
 	ChannelPending {
@@ -221,7 +292,313 @@ pub enum Event {
 		counterparty_node_id: Option<PublicKey>,
 		/// This will be `None` for events serialized by LDK Node v0.2.1 and prior.
 		reason: Option<ClosureReason>,
+		/// The fee, in satoshis, paid by the transaction spending the channel funding output, e.g.,
+		/// the cooperative closing transaction.
+		///
+		/// This will be `None` if we didn't broadcast the transaction ourselves, or for events
+		/// serialized by LDK Node v0.7.0 and prior.
+		closing_fee_sat: Option<u64>,
+		/// The ID of the transaction spending the channel funding output.
+		///
+		/// This will be `None` if we didn't broadcast the transaction ourselves, or for events
+		/// serialized by LDK Node v0.7.0 and prior.
+		closing_txid: Option<Txid>,
+		/// The way in which the channel was closed.
+		///
+		/// For events serialized by LDK Node v0.7.0 and prior, this is derived from `reason`.
+		close_type: CloseType,
+		/// The amount, in satoshis, we recovered to our on-chain wallet from the channel.
+		///
+		/// For force-closes, our funds are only claimable after on-chain timelocks expire, hence
+		/// this will be `None`. Once our outputs are handed to our on-chain sweeper, the recovered
+		/// balance will be reflected in [`ClosedChannelDetails::balance_recovered_sat`] as
+		/// retrieved via [`Node::list_closed_channels`].
+		///
+		/// [`Node::list_closed_channels`]: crate::Node::list_closed_channels
+		balance_recovered_sat: Option<u64>,
+	},
+	/// A manually-claimable payment has been failed back automatically.
+	///
+	/// This will only be emitted if [`Config::manual_claim_auto_fail_margin_blocks`] is set, if
+	/// the payment was registered with a claim timeout, e.g., via
+	/// [`Bolt11Payment::receive_for_hash_with_claim_timeout`], or if all held payments were failed
+	/// via [`Node::fail_all_pending_claimable`].
+	///
+	/// [`Config::manual_claim_auto_fail_margin_blocks`]: crate::config::Config::manual_claim_auto_fail_margin_blocks
+	/// [`Bolt11Payment::receive_for_hash_with_claim_timeout`]: crate::payment::Bolt11Payment::receive_for_hash_with_claim_timeout
+	/// [`Node::fail_all_pending_claimable`]: crate::Node::fail_all_pending_claimable
+	PaymentAutoFailed {
+		/// The hash of the payment.
+		payment_hash: PaymentHash,
+		/// The reason why the payment was failed back.
+		reason: PaymentAutoFailReason,
+	},
+	/// We were unable to reach our chain source for longer than the configured timeout.
+	///
+	/// Until we're able to reach the chain source again, we won't learn about new blocks or
+	/// relevant transactions, and broadcasts might fail.
+	///
+	/// This is currently only emitted when sourcing chain data from an Electrum server or a
+	/// Bitcoin Core node, once [`ElectrumSyncConfig::server_unavailable_timeout_secs`] or
+	/// [`BitcoindSyncConfig::server_unavailable_timeout_secs`] have passed, respectively.
+	///
+	/// [`ElectrumSyncConfig::server_unavailable_timeout_secs`]: crate::config::ElectrumSyncConfig::server_unavailable_timeout_secs
+	/// [`BitcoindSyncConfig::server_unavailable_timeout_secs`]: crate::config::BitcoindSyncConfig::server_unavailable_timeout_secs
+	ChainSourceUnavailable {
+		/// The time since we first failed to reach the chain source, in seconds.
+		unreachable_secs: u64,
+	},
+	/// A channel request we placed with an LSPS1 liquidity source failed.
+	///
+	/// This is emitted if the LSP refunded our payment for the order, or if all payment options
+	/// expired before the order was paid.
+	///
+	/// Note that we only track orders placed via [`LSPS1Liquidity::request_channel`] while the
+	/// node is running, i.e., orders aren't persisted across restarts.
+	///
+	/// [`LSPS1Liquidity::request_channel`]: crate::liquidity::LSPS1Liquidity::request_channel
+	LiquidityRequestFailed {
+		/// The node id of the LSP we placed the order with.
+		lsp_node_id: PublicKey,
+		/// The id of the failed order.
+		order_id: String,
+	},
+	/// A payment probe sent via [`Bolt11Payment::send_probes`] or
+	/// [`SpontaneousPayment::send_probes`] reached the recipient, i.e., the probed path likely
+	/// is able to route a payment of the probed amount.
+	///
+	/// Probes are sent with an unknown payment hash and hence always get failed back by the
+	/// recipient. They are never tracked in the payment store.
+	///
+	/// [`Bolt11Payment::send_probes`]: crate::payment::Bolt11Payment::send_probes
+	/// [`SpontaneousPayment::send_probes`]: crate::payment::SpontaneousPayment::send_probes
+	ProbeSuccessful {
+		/// The hash of the probe.
+		payment_hash: PaymentHash,
+		/// The total routing fees, in thousandths of a satoshi, that a payment over the probed path
+		/// would incur.
+		path_fees_msat: u64,
+		/// The number of hops of the probed path.
+		path_hops: u32,
+	},
+	/// A payment probe sent via [`Bolt11Payment::send_probes`] or
+	/// [`SpontaneousPayment::send_probes`] failed before reaching the recipient.
+	///
+	/// [`Bolt11Payment::send_probes`]: crate::payment::Bolt11Payment::send_probes
+	/// [`SpontaneousPayment::send_probes`]: crate::payment::SpontaneousPayment::send_probes
+	ProbeFailed {
+		/// The hash of the probe.
+		payment_hash: PaymentHash,
+		/// The number of hops of the probed path.
+		path_hops: u32,
+		/// The zero-based index of the channel along the probed path that failed to forward the
+		/// probe.
+		///
+		/// This will be `None` if the failing channel couldn't be identified.
+		failed_at_hop: Option<u32>,
+	},
+	/// We hit a hard failure communicating with our remote storage backend, e.g., as it remained
+	/// unreachable after all retries were exhausted.
+	///
+	/// This is currently only emitted when using a [VSS] backend, and only once per outage. While
+	/// the backend is unavailable, reads will be served from a local cache where possible, but
+	/// writes will fail.
+	///
+	/// As we might not be able to persist it, this event is never persisted, i.e., it won't be
+	/// replayed after a restart.
+	///
+	/// [VSS]: https://github.com/lightningdevkit/vss-server/blob/main/README.md
+	StorageUnavailable {
+		/// A description of the failure.
+		reason: String,
+	},
+	/// We rejected a request to open an inbound channel.
+	ChannelRejected {
+		/// The `node_id` of the peer that requested to open the channel.
+		counterparty_node_id: PublicKey,
+		/// The value, in satoshis, of the requested channel.
+		funding_satoshis: u64,
+		/// The reason why we rejected the channel.
+		reason: ChannelRejectReason,
+	},
+	/// A splice of a channel has been locked, i.e., the channel has been resized.
+	///
+	/// This is emitted once the splice transaction confirmed and both parties exchanged
+	/// `splice_locked`, no matter which party initiated the splice via [`Node::splice_in`] or
+	/// [`Node::splice_out`].
+	///
+	/// [`Node::splice_in`]: crate::Node::splice_in
+	/// [`Node::splice_out`]: crate::Node::splice_out
+	ChannelSpliced {
+		/// The `channel_id` of the channel.
+		channel_id: ChannelId,
+		/// The `user_channel_id` of the channel.
+		user_channel_id: UserChannelId,
+		/// The `node_id` of the channel counterparty.
+		counterparty_node_id: PublicKey,
+		/// The new value, in satoshis, of the channel's funding output.
+		new_capacity_sats: u64,
+	},
+	/// The initial Rapid Gossip Sync snapshot has been applied to our network graph.
+	///
+	/// This is only emitted when sourcing gossip data from a RapidGossipSync server, once per
+	/// start of the node after the first successful sync. Subsequent incremental syncs won't
+	/// generate any events.
+	GossipSyncCompleted {
+		/// The number of nodes in our network graph.
+		total_nodes: u64,
+		/// The number of channels in our network graph.
+		total_channels: u64,
 	},
+	/// A payment of a schedule created via [`Node::schedule_recurring_payment`] succeeded.
+	///
+	/// [`Node::schedule_recurring_payment`]: crate::Node::schedule_recurring_payment
+	RecurringPaymentExecuted {
+		/// The identifier of the schedule.
+		recurring_payment_id: RecurringPaymentId,
+		/// The ID of the payment made.
+		payment_id: PaymentId,
+		/// The number of payments of the schedule that succeeded so far.
+		payments_made: u32,
+	},
+	/// A payment of a schedule created via [`Node::schedule_recurring_payment`] failed.
+	///
+	/// The schedule stays active and the payment will be reattempted on the next interval.
+	///
+	/// [`Node::schedule_recurring_payment`]: crate::Node::schedule_recurring_payment
+	RecurringPaymentFailed {
+		/// The identifier of the schedule.
+		recurring_payment_id: RecurringPaymentId,
+		/// The ID of the failed payment.
+		///
+		/// This will be `None` if we failed to initiate the payment in the first place.
+		payment_id: Option<PaymentId>,
+	},
+	/// A JIT channel we opened as an [bLIP-52 / LSPS2] service provider to a client became ready.
+	///
+	/// This is only emitted if we're configured as an LSPS2 service via
+	/// [`Builder::set_liquidity_provider_lsps2`]. Payments intercepted for the client will be
+	/// forwarded over the new channel.
+	///
+	/// [bLIP-52 / LSPS2]: https://github.com/lightning/blips/blob/master/blip-0052.md
+	/// [`Builder::set_liquidity_provider_lsps2`]: crate::Builder::set_liquidity_provider_lsps2
+	LiquidityChannelOpened {
+		/// The `channel_id` of the channel.
+		channel_id: ChannelId,
+		/// The `user_channel_id` of the channel.
+		user_channel_id: UserChannelId,
+		/// The `node_id` of the client we opened the channel to.
+		client_node_id: PublicKey,
+		/// The value, in satoshis, of the channel.
+		amount_sat: u64,
+	},
+	/// An HTLC that is to be forwarded over an intercept SCID was intercepted.
+	///
+	/// This will only be emitted if [`Config::accept_intercept_htlcs`] is set. The HTLC is held
+	/// until it's either forwarded via [`Node::forward_intercepted_htlc`] or failed back via
+	/// [`Node::fail_intercepted_htlc`], one of which needs to be called in response to this event.
+	///
+	/// **Note:** If neither happens in time, the HTLC is failed back automatically at the latest
+	/// once the chain tip reaches LDK's `HTLC_FAIL_BACK_BUFFER`, i.e., 39 blocks, before `expiry`.
+	/// As the deadline actually depends on the CLTV expiry the sender requested for the outbound
+	/// HTLC, which is lower than `expiry`, the HTLC should be handled well ahead of it.
+	///
+	/// [`Config::accept_intercept_htlcs`]: crate::config::Config::accept_intercept_htlcs
+	/// [`Node::forward_intercepted_htlc`]: crate::Node::forward_intercepted_htlc
+	/// [`Node::fail_intercepted_htlc`]: crate::Node::fail_intercepted_htlc
+	HTLCIntercepted {
+		/// The identifier of the intercepted HTLC, to be passed to
+		/// [`Node::forward_intercepted_htlc`] or [`Node::fail_intercepted_htlc`].
+		///
+		/// [`Node::forward_intercepted_htlc`]: crate::Node::forward_intercepted_htlc
+		/// [`Node::fail_intercepted_htlc`]: crate::Node::fail_intercepted_htlc
+		intercept_id: InterceptId,
+		/// The hash of the payment the HTLC is part of.
+		payment_hash: PaymentHash,
+		/// The intercept SCID over which the HTLC is requested to be forwarded, as previously
+		/// retrieved via [`Node::new_intercept_scid`].
+		///
+		/// [`Node::new_intercept_scid`]: crate::Node::new_intercept_scid
+		requested_next_hop_scid: u64,
+		/// The amount, in thousands of a satoshi, the sender expects to be forwarded to the next
+		/// hop.
+		///
+		/// Note that the inbound HTLC may carry a larger amount, if the sender accounted for our
+		/// forwarding fees.
+		amount_msat: u64,
+		/// The block height at which the inbound HTLC expires.
+		///
+		/// Will be `None` if the inbound HTLC couldn't be found in our channels.
+		expiry: Option<u32>,
+	},
+	/// Outputs recovered from our closed channels were swept and the sweeping transaction
+	/// confirmed.
+	///
+	/// The funds are swept to [`Config::spendable_output_sweep_destination`] if set, or to our
+	/// on-chain wallet otherwise.
+	///
+	/// [`Config::spendable_output_sweep_destination`]: crate::config::Config::spendable_output_sweep_destination
+	SpendableOutputsSwept {
+		/// The `txid` of the sweeping transaction.
+		txid: Txid,
+		/// The amount, in satoshis, swept to `destination`, i.e., net of the on-chain fees.
+		amount_sat: u64,
+		/// The address the funds were swept to.
+		destination: Address,
+	},
+	/// A custom onion message was received.
+	///
+	/// Custom onion messages can be sent via [`Node::send_onion_message`].
+	///
+	/// Only emitted if [`Config::accept_custom_onion_messages`] is set. Note that onion messages
+	/// don't reveal their sender, so applications requiring to know it need to authenticate it as
+	/// part of `data`.
+	///
+	/// [`Node::send_onion_message`]: crate::Node::send_onion_message
+	/// [`Config::accept_custom_onion_messages`]: crate::config::Config::accept_custom_onion_messages
+	OnionMessageReceived {
+		/// The TLV type of the message.
+		tlv_type: u64,
+		/// The message's contents.
+		data: Vec<u8>,
+	},
+	/// A refund we created via [`Bolt12Payment::create_refund`] or
+	/// [`Bolt12Payment::initiate_refund`] was paid out to its recipient.
+	///
+	/// This event is emitted in addition to, and after, the corresponding
+	/// [`Event::PaymentSuccessful`].
+	///
+	/// [`Bolt12Payment::create_refund`]: crate::payment::Bolt12Payment::create_refund
+	/// [`Bolt12Payment::initiate_refund`]: crate::payment::Bolt12Payment::initiate_refund
+	RefundPaid {
+		/// A local identifier used to track the refund.
+		payment_id: PaymentId,
+		/// The hash of the payment.
+		payment_hash: PaymentHash,
+		/// The amount refunded, in thousandths of a satoshi.
+		amount_msat: u64,
+		/// The total fee which was spent at intermediate hops in this payment.
+		fee_paid_msat: Option<u64>,
+	},
+	/// We gave up on reconnecting to a persisted peer after
+	/// [`PeerReconnectPolicy::max_attempts`] consecutive failed attempts.
+	///
+	/// We won't try to reconnect to the peer until the node is restarted or it is connected
+	/// manually via [`Node::connect`].
+	///
+	/// [`PeerReconnectPolicy::max_attempts`]: crate::config::PeerReconnectPolicy::max_attempts
+	/// [`Node::connect`]: crate::Node::connect
+	PeerConnectionAbandoned {
+		/// The node ID of the peer.
+		node_id: PublicKey,
+	},
+}
+
+impl Event {
+	/// Returns whether the event is never persisted, i.e., won't be replayed after a restart.
+	fn is_ephemeral(&self) -> bool {
+		matches!(self, Self::StorageUnavailable { .. })
+	}
 }
 
 impl_writeable_tlv_based_enum!(Event,
@@ -235,12 +612,14 @@ impl_writeable_tlv_based_enum!(Event,
 		(0, payment_hash, option),
 		(1, reason, upgradable_option),
 		(3, payment_id, option),
+		(5, retry_strategy, option),
 	},
 	(2, PaymentReceived) => {
 		(0, payment_hash, required),
 		(1, payment_id, option),
 		(2, amount_msat, required),
 		(3, custom_records, optional_vec),
+		(5, payer_note, option),
 	},
 	(3, ChannelReady) => {
 		(0, channel_id, required),
@@ -259,6 +638,10 @@ impl_writeable_tlv_based_enum!(Event,
 		(1, counterparty_node_id, option),
 		(2, user_channel_id, required),
 		(3, reason, upgradable_option),
+		(5, closing_fee_sat, option),
+		(7, closing_txid, option),
+		(9, close_type, (default_value, reason.as_ref().map_or(CloseType::ForceLocal, CloseType::from))),
+		(11, balance_recovered_sat, option),
 	},
 	(6, PaymentClaimable) => {
 		(0, payment_hash, required),
@@ -266,6 +649,9 @@ impl_writeable_tlv_based_enum!(Event,
 		(4, claimable_amount_msat, required),
 		(6, claim_deadline, option),
 		(7, custom_records, optional_vec),
+		(9, claim_deadline_unix_secs, option),
+		(11, via_jit_channel, (default_value, false)),
+		(13, channel_confirmed, (default_value, true)),
 	},
 	(7, PaymentForwarded) => {
 		(0, prev_channel_id, required),
@@ -278,9 +664,169 @@ impl_writeable_tlv_based_enum!(Event,
 		(10, skimmed_fee_msat, option),
 		(12, claim_from_onchain_tx, required),
 		(14, outbound_amount_forwarded_msat, option),
+	},
+	(8, PaymentAutoFailed) => {
+		(0, payment_hash, required),
+		(2, reason, required),
+	},
+	(9, ChainSourceUnavailable) => {
+		(0, unreachable_secs, required),
+	},
+	(10, LiquidityRequestFailed) => {
+		(0, lsp_node_id, required),
+		(2, order_id, required),
+	},
+	(11, ProbeSuccessful) => {
+		(0, payment_hash, required),
+		(2, path_fees_msat, required),
+		(4, path_hops, required),
+	},
+	(12, ProbeFailed) => {
+		(0, payment_hash, required),
+		(1, failed_at_hop, option),
+		(2, path_hops, required),
+	},
+	(13, StorageUnavailable) => {
+		(0, reason, required),
+	},
+	(14, ChannelRejected) => {
+		(0, counterparty_node_id, required),
+		(2, funding_satoshis, required),
+		(4, reason, required),
+	},
+	(15, ChannelSpliced) => {
+		(0, channel_id, required),
+		(2, user_channel_id, required),
+		(4, counterparty_node_id, required),
+		(6, new_capacity_sats, required),
+	},
+	(16, GossipSyncCompleted) => {
+		(0, total_nodes, required),
+		(2, total_channels, required),
+	},
+	(17, RecurringPaymentExecuted) => {
+		(0, recurring_payment_id, required),
+		(2, payment_id, required),
+		(4, payments_made, required),
+	},
+	(18, RecurringPaymentFailed) => {
+		(0, recurring_payment_id, required),
+		(2, payment_id, option),
+	},
+	(19, LiquidityChannelOpened) => {
+		(0, channel_id, required),
+		(2, user_channel_id, required),
+		(4, client_node_id, required),
+		(6, amount_sat, required),
+	},
+	(20, HTLCIntercepted) => {
+		(0, intercept_id, required),
+		(2, payment_hash, required),
+		(4, requested_next_hop_scid, required),
+		(6, amount_msat, required),
+		(8, expiry, option),
+	},
+	(21, SpendableOutputsSwept) => {
+		(0, txid, required),
+		(2, amount_sat, required),
+		(4, destination_str, (legacy, String, |us: &Event| match us {
+			Event::SpendableOutputsSwept { destination, .. } => Some(destination.to_string()),
+			_ => None,
+		})),
+		(_unused, destination, (static_value, {
+			let destination_str =
+				destination_str.ok_or(lightning::ln::msgs::DecodeError::InvalidValue)?;
+			destination_str
+				.parse::<Address<NetworkUnchecked>>()
+				.map_err(|_| lightning::ln::msgs::DecodeError::InvalidValue)?
+				.assume_checked()
+		})),
+	},
+	(22, OnionMessageReceived) => {
+		(2, tlv_type, required),
+		(4, data, required),
+	},
+	(23, RefundPaid) => {
+		(0, payment_id, required),
+		(1, fee_paid_msat, option),
+		(2, payment_hash, required),
+		(4, amount_msat, required),
+	},
+	(24, PeerConnectionAbandoned) => {
+		(0, node_id, required),
 	}
 );
 
+/// The reason why a manually-claimable payment was failed back automatically.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PaymentAutoFailReason {
+	/// The current best block height came within
+	/// [`Config::manual_claim_auto_fail_margin_blocks`] of the payment's `claim_deadline`.
+	///
+	/// [`Config::manual_claim_auto_fail_margin_blocks`]: crate::config::Config::manual_claim_auto_fail_margin_blocks
+	ClaimDeadlineApproaching,
+	/// The payment wasn't claimed within the claim timeout it was registered with.
+	ClaimTimeoutElapsed,
+	/// All held payments were failed back via [`Node::fail_all_pending_claimable`].
+	///
+	/// [`Node::fail_all_pending_claimable`]: crate::Node::fail_all_pending_claimable
+	FailAllRequested,
+}
+
+impl_writeable_tlv_based_enum!(PaymentAutoFailReason,
+	(0, ClaimDeadlineApproaching) => {},
+	(2, ClaimTimeoutElapsed) => {},
+	(4, FailAllRequested) => {}
+);
+
+/// The reason why we rejected a request to open an inbound channel.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ChannelRejectReason {
+	/// The peer requested a zero-confirmation channel, but isn't listed in
+	/// [`Config::trusted_peers_0conf`].
+	///
+	/// [`Config::trusted_peers_0conf`]: crate::config::Config::trusted_peers_0conf
+	UntrustedZeroConf,
+	/// The channel violates our [`Config::inbound_channel_limits`].
+	///
+	/// [`Config::inbound_channel_limits`]: crate::config::Config::inbound_channel_limits
+	ExceedsLimits,
+	/// The dust limit requested by the peer exceeds the maximum we accept.
+	DustLimit,
+	/// We don't have sufficient on-chain funds available to maintain the emergency reserve for an
+	/// additional Anchor channel.
+	///
+	/// See [`AnchorChannelsConfig::per_channel_reserve_sats`] for more information.
+	///
+	/// [`AnchorChannelsConfig::per_channel_reserve_sats`]: crate::config::AnchorChannelsConfig::per_channel_reserve_sats
+	InsufficientReserve,
+	/// The peer requested an Anchor channel, but Anchor channels are disabled via
+	/// [`Config::anchor_channels_config`].
+	///
+	/// [`Config::anchor_channels_config`]: crate::config::Config::anchor_channels_config
+	AnchorsDisabled,
+	/// The peer requested an announced channel, but we're not configured to announce channels.
+	AnnouncementUnsupported,
+}
+
+impl_writeable_tlv_based_enum!(ChannelRejectReason,
+	(0, UntrustedZeroConf) => {},
+	(2, ExceedsLimits) => {},
+	(4, DustLimit) => {},
+	(6, InsufficientReserve) => {},
+	(8, AnchorsDisabled) => {},
+	(10, AnnouncementUnsupported) => {}
+);
+
+// Returns the average time in-between blocks on the given network, if it's meaningful.
+fn average_block_interval_secs(network: Network) -> Option<u64> {
+	match network {
+		Network::Bitcoin | Network::Testnet | Network::Testnet4 | Network::Signet => Some(600),
+		// Block times on regtest are arbitrary.
+		Network::Regtest => None,
+	}
+}
+
 pub struct EventQueue<L: Deref>
 where
 	L::Target: LdkLogger,
@@ -318,11 +864,51 @@ where
 		Ok(())
 	}
 
+	/// Adds the given events to the queue, persisting them at once, i.e., either all or none of
+	/// them will be added.
+	pub(crate) fn add_events(&self, events: Vec<Event>) -> Result<(), Error> {
+		{
+			let mut locked_queue = self.queue.lock().unwrap();
+			let prev_len = locked_queue.len();
+			locked_queue.extend(events);
+			if let Err(e) = self.persist_queue(&locked_queue) {
+				locked_queue.truncate(prev_len);
+				return Err(e);
+			}
+		}
+
+		self.notifier.notify_one();
+
+		if let Some(waker) = self.waker.lock().unwrap().take() {
+			waker.wake();
+		}
+		Ok(())
+	}
+
+	/// Adds an event to the queue without persisting it, i.e., it will be lost on restart.
+	///
+	/// This allows to surface failures of the storage backend itself, which we might not be able
+	/// to persist to.
+	pub(crate) fn add_ephemeral_event(&self, event: Event) {
+		debug_assert!(event.is_ephemeral());
+		self.queue.lock().unwrap().push_back(event);
+
+		self.notifier.notify_one();
+
+		if let Some(waker) = self.waker.lock().unwrap().take() {
+			waker.wake();
+		}
+	}
+
 	pub(crate) fn next_event(&self) -> Option<Event> {
 		let locked_queue = self.queue.lock().unwrap();
 		locked_queue.front().cloned()
 	}
 
+	pub(crate) fn pending_event_count(&self) -> usize {
+		self.queue.lock().unwrap().len()
+	}
+
 	pub(crate) async fn next_event_async(&self) -> Event {
 		EventFuture { event_queue: Arc::clone(&self.queue), waker: Arc::clone(&self.waker) }.await
 	}
@@ -333,11 +919,47 @@ where
 		locked_queue.front().unwrap().clone()
 	}
 
+	pub(crate) fn wait_next_event_timeout(&self, timeout: Duration) -> Option<Event> {
+		let (locked_queue, _) = self
+			.notifier
+			.wait_timeout_while(self.queue.lock().unwrap(), timeout, |queue| queue.is_empty())
+			.unwrap();
+		locked_queue.front().cloned()
+	}
+
+	pub(crate) fn poll_next_event(&self, cx: &mut Context<'_>) -> Poll<Event> {
+		// We register the waker while holding the queue lock so we can't miss a concurrently added
+		// event.
+		let locked_queue = self.queue.lock().unwrap();
+		if let Some(event) = locked_queue.front() {
+			Poll::Ready(event.clone())
+		} else {
+			*self.waker.lock().unwrap() = Some(cx.waker().clone());
+			Poll::Pending
+		}
+	}
+
 	pub(crate) fn event_handled(&self) -> Result<(), Error> {
+		self.event_handled_if(|_| true)
+	}
+
+	// Marks the next event handled only if it's the given one, i.e., if it wasn't marked handled
+	// via another path in the meantime.
+	fn event_handled_if_next(&self, event: &Event) -> Result<(), Error> {
+		self.event_handled_if(|next_event| next_event == event)
+	}
+
+	fn event_handled_if<F: Fn(&Event) -> bool>(&self, is_handled: F) -> Result<(), Error> {
 		{
 			let mut locked_queue = self.queue.lock().unwrap();
-			locked_queue.pop_front();
-			self.persist_queue(&locked_queue)?;
+			if !locked_queue.front().map_or(false, is_handled) {
+				return Ok(());
+			}
+			let handled_event = locked_queue.pop_front();
+			// Ephemeral events were never persisted, so there's no need to persist their removal.
+			if !handled_event.map_or(false, |e| e.is_ephemeral()) {
+				self.persist_queue(&locked_queue)?;
+			}
 		}
 		self.notifier.notify_one();
 
@@ -407,8 +1029,9 @@ struct EventQueueSerWrapper<'a>(&'a VecDeque<Event>);
 
 impl Writeable for EventQueueSerWrapper<'_> {
 	fn write<W: Writer>(&self, writer: &mut W) -> Result<(), lightning::io::Error> {
-		(self.0.len() as u16).write(writer)?;
-		for e in self.0.iter() {
+		let persisted_events = self.0.iter().filter(|e| !e.is_ephemeral());
+		(persisted_events.clone().count() as u16).write(writer)?;
+		for e in persisted_events {
 			e.write(writer)?;
 		}
 		Ok(())
@@ -435,6 +1058,194 @@ impl Future for EventFuture {
 	}
 }
 
+/// A [`Stream`] of [`Event`]s, retrieved via [`Node::event_stream`].
+///
+/// Just as [`Node::next_event_async`], the stream will keep yielding the same event until
+/// handling is confirmed via [`Node::event_handled`]. If the stream was created with `auto_ack`
+/// set, the previously yielded event is instead automatically marked handled once the next event
+/// is requested from the stream, unless it was already marked handled otherwise.
+///
+/// As events are only removed from the queue once handled, no events will be dropped if the
+/// consumer is slow.
+///
+/// [`Node::event_stream`]: crate::Node::event_stream
+/// [`Node::next_event_async`]: crate::Node::next_event_async
+/// [`Node::event_handled`]: crate::Node::event_handled
+pub struct EventStream {
+	event_queue: Arc<EventQueue<Arc<Logger>>>,
+	auto_ack: bool,
+	awaiting_ack: Option<Event>,
+}
+
+impl EventStream {
+	pub(crate) fn new(event_queue: Arc<EventQueue<Arc<Logger>>>, auto_ack: bool) -> Self {
+		Self { event_queue, auto_ack, awaiting_ack: None }
+	}
+}
+
+impl Stream for EventStream {
+	type Item = Event;
+
+	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+		if let Some(event) = self.awaiting_ack.take() {
+			// Requesting the next event confirms the previously yielded one has been handled.
+			if let Err(e) = self.event_queue.event_handled_if_next(&event) {
+				log_error!(
+					self.event_queue.logger,
+					"Couldn't mark event handled due to persistence failure: {}",
+					e
+				);
+			}
+		}
+
+		match self.event_queue.poll_next_event(cx) {
+			Poll::Ready(event) => {
+				if self.auto_ack {
+					self.awaiting_ack = Some(event.clone());
+				}
+				Poll::Ready(Some(event))
+			},
+			Poll::Pending => Poll::Pending,
+		}
+	}
+}
+
+/// A handler for [`Event`]s, registered via [`Node::set_event_handler`].
+///
+/// [`Node::set_event_handler`]: crate::Node::set_event_handler
+pub trait NodeEventHandler: Send + Sync {
+	/// Handles the given event.
+	///
+	/// If `Ok(())` is returned, the event is marked handled. Otherwise, the event is left in the
+	/// event queue and will be redelivered after a short delay.
+	fn handle_event(&self, event: Event) -> Result<(), ReplayEvent>;
+}
+
+/// Dispatches the events in the event queue to a [`NodeEventHandler`] until dropped.
+///
+/// Dropping the dispatcher waits for the dispatching thread to finish handling the current event,
+/// unless it's dropped from that thread itself, i.e., by the handler.
+pub(crate) struct EventDispatcher {
+	stop_flag: Arc<AtomicBool>,
+	join_handle: Option<std::thread::JoinHandle<()>>,
+}
+
+impl EventDispatcher {
+	pub(crate) fn spawn(
+		event_queue: Arc<EventQueue<Arc<Logger>>>, handler: Arc<dyn NodeEventHandler>,
+	) -> Self {
+		let stop_flag = Arc::new(AtomicBool::new(false));
+		let thread_stop_flag = Arc::clone(&stop_flag);
+
+		// We dispatch from a dedicated thread rather than a runtime task, so that the handler may
+		// call into any blocking `Node` API without stalling or deadlocking our runtime.
+		let join_handle = std::thread::Builder::new()
+			.name("ldk-node-event-dispatcher".to_string())
+			.spawn(move || {
+				while !thread_stop_flag.load(Ordering::Acquire) {
+					let event =
+						match event_queue.wait_next_event_timeout(EVENT_DISPATCHER_POLL_INTERVAL) {
+							Some(event) => event,
+							None => continue,
+						};
+
+					if thread_stop_flag.load(Ordering::Acquire) {
+						break;
+					}
+
+					match handler.handle_event(event) {
+						Ok(()) => {
+							if let Err(e) = event_queue.event_handled() {
+								log_error!(
+									event_queue.logger,
+									"Couldn't mark event handled due to persistence failure: {}",
+									e
+								);
+							}
+						},
+						Err(ReplayEvent()) => {
+							log_debug!(
+								event_queue.logger,
+								"Event handler failed to handle event, redelivering it in {}s",
+								EVENT_HANDLER_REPLAY_DELAY.as_secs()
+							);
+							std::thread::sleep(EVENT_HANDLER_REPLAY_DELAY);
+						},
+					}
+				}
+			})
+			.expect("Failed to spawn event dispatcher thread");
+
+		Self { stop_flag, join_handle: Some(join_handle) }
+	}
+}
+
+impl Drop for EventDispatcher {
+	fn drop(&mut self) {
+		self.stop_flag.store(true, Ordering::Release);
+		if let Some(join_handle) = self.join_handle.take() {
+			// Joining from the dispatching thread itself would deadlock. In that case it will exit
+			// as soon as the handler returns.
+			if join_handle.thread().id() != std::thread::current().id() {
+				let _ = join_handle.join();
+			}
+		}
+	}
+}
+
+/// Detects [`LdkEvent::PaymentForwarded`] events that are replayed after a restart, i.e., that
+/// we already accounted the fees of before the [`ChannelManager`] was re-persisted.
+///
+/// As LDK delivers pending events in order, any replayed forwards are a prefix of the forwards we
+/// see after restarting, and match a contiguous tail of the ones we accounted for before. As
+/// distinct forwards may have identical ids, we only consider forwards replayed once they matched
+/// such a tail in full.
+struct ForwardReplayTracker {
+	recent_forward_ids: Vec<[u8; 32]>,
+	// The positions in `recent_forward_ids` the next replayed forward could be at.
+	candidate_positions: Vec<usize>,
+	// The forwards that matched so far, which we need to account for if they turn out not to be
+	// replays after all.
+	tentative_replays: Vec<([u8; 32], u64)>,
+}
+
+impl ForwardReplayTracker {
+	fn new(recent_forward_ids: Vec<[u8; 32]>) -> Self {
+		let candidate_positions = (0..recent_forward_ids.len()).collect();
+		Self { recent_forward_ids, candidate_positions, tentative_replays: Vec::new() }
+	}
+
+	/// Returns the forwards, as pairs of forward id and fee, we need to account for.
+	fn track_forward(
+		&mut self, forward_id: [u8; 32], fee_earned_msat: u64,
+	) -> Vec<([u8; 32], u64)> {
+		let next_positions = self
+			.candidate_positions
+			.iter()
+			.filter(|&&pos| self.recent_forward_ids[pos] == forward_id)
+			.map(|pos| pos + 1)
+			.collect::<Vec<_>>();
+
+		if next_positions.is_empty() {
+			// Not a replay, so neither were the forwards that matched before.
+			self.candidate_positions.clear();
+			let mut forwards = core::mem::take(&mut self.tentative_replays);
+			forwards.push((forward_id, fee_earned_msat));
+			return forwards;
+		}
+
+		if next_positions.contains(&self.recent_forward_ids.len()) {
+			// We matched a tail in full, so we're done replaying.
+			self.candidate_positions.clear();
+			self.tentative_replays.clear();
+		} else {
+			self.candidate_positions = next_positions;
+			self.tentative_replays.push((forward_id, fee_earned_msat));
+		}
+		Vec::new()
+	}
+}
+
 pub(crate) struct EventHandler<L: Deref + Clone + Sync + Send + 'static>
 where
 	L::Target: LdkLogger,
@@ -442,13 +1253,25 @@ where
 	event_queue: Arc<EventQueue<L>>,
 	wallet: Arc<Wallet>,
 	bump_tx_event_handler: Arc<BumpTransactionEventHandler>,
+	pending_channel_close_bumps: Arc<PendingChannelCloseBumps>,
+	channel_ready_waiters: Arc<ChannelReadyWaiters>,
+	funding_confirmation_targets: Arc<FundingConfirmationTargets>,
+	pending_external_fundings: Arc<PendingExternalFundings>,
+	pending_bolt12_invoices: Arc<PendingBolt12Invoices>,
 	channel_manager: Arc<ChannelManager>,
 	connection_manager: Arc<ConnectionManager<L>>,
 	output_sweeper: Arc<Sweeper>,
+	tx_broadcaster: Arc<Broadcaster>,
 	network_graph: Arc<Graph>,
 	liquidity_source: Option<Arc<LiquiditySource<Arc<Logger>>>>,
 	payment_store: Arc<PaymentStore>,
+	closed_channel_store: Arc<ClosedChannelStore>,
+	retry_strategies: Arc<PaymentRetryStrategies>,
 	peer_store: Arc<PeerStore<L>>,
+	claim_watchdog: Arc<ClaimDeadlineWatchdog>,
+	node_metrics: Arc<RwLock<NodeMetrics>>,
+	forward_replay_tracker: Mutex<ForwardReplayTracker>,
+	kv_store: Arc<DynStore>,
 	runtime: Arc<Runtime>,
 	logger: L,
 	config: Arc<Config>,
@@ -461,28 +1284,50 @@ impl<L: Deref + Clone + Sync + Send + 'static> EventHandler<L>
 where
 	L::Target: LdkLogger,
 {
+	#[allow(clippy::too_many_arguments)]
 	pub fn new(
 		event_queue: Arc<EventQueue<L>>, wallet: Arc<Wallet>,
 		bump_tx_event_handler: Arc<BumpTransactionEventHandler>,
-		channel_manager: Arc<ChannelManager>, connection_manager: Arc<ConnectionManager<L>>,
-		output_sweeper: Arc<Sweeper>, network_graph: Arc<Graph>,
+		pending_channel_close_bumps: Arc<PendingChannelCloseBumps>,
+		channel_ready_waiters: Arc<ChannelReadyWaiters>,
+		funding_confirmation_targets: Arc<FundingConfirmationTargets>,
+		pending_external_fundings: Arc<PendingExternalFundings>,
+		pending_bolt12_invoices: Arc<PendingBolt12Invoices>, channel_manager: Arc<ChannelManager>,
+		connection_manager: Arc<ConnectionManager<L>>, output_sweeper: Arc<Sweeper>,
+		tx_broadcaster: Arc<Broadcaster>, network_graph: Arc<Graph>,
 		liquidity_source: Option<Arc<LiquiditySource<Arc<Logger>>>>,
-		payment_store: Arc<PaymentStore>, peer_store: Arc<PeerStore<L>>,
-		static_invoice_store: Option<StaticInvoiceStore>, onion_messenger: Arc<OnionMessenger>,
-		om_mailbox: Option<Arc<OnionMessageMailbox>>, runtime: Arc<Runtime>, logger: L,
-		config: Arc<Config>,
+		payment_store: Arc<PaymentStore>, closed_channel_store: Arc<ClosedChannelStore>,
+		retry_strategies: Arc<PaymentRetryStrategies>, peer_store: Arc<PeerStore<L>>,
+		claim_watchdog: Arc<ClaimDeadlineWatchdog>, node_metrics: Arc<RwLock<NodeMetrics>>,
+		kv_store: Arc<DynStore>, static_invoice_store: Option<StaticInvoiceStore>,
+		onion_messenger: Arc<OnionMessenger>, om_mailbox: Option<Arc<OnionMessageMailbox>>,
+		runtime: Arc<Runtime>, logger: L, config: Arc<Config>,
 	) -> Self {
+		let recent_forward_ids = node_metrics.read().unwrap().recent_forward_ids().to_vec();
+		let forward_replay_tracker = Mutex::new(ForwardReplayTracker::new(recent_forward_ids));
 		Self {
 			event_queue,
 			wallet,
 			bump_tx_event_handler,
+			pending_channel_close_bumps,
+			channel_ready_waiters,
+			funding_confirmation_targets,
+			pending_external_fundings,
+			pending_bolt12_invoices,
 			channel_manager,
 			connection_manager,
 			output_sweeper,
+			tx_broadcaster,
 			network_graph,
 			liquidity_source,
 			payment_store,
+			closed_channel_store,
+			retry_strategies,
 			peer_store,
+			claim_watchdog,
+			node_metrics,
+			forward_replay_tracker,
+			kv_store,
 			logger,
 			runtime,
 			config,
@@ -492,6 +1337,68 @@ where
 		}
 	}
 
+	fn notify_channel_ready_waiters(&self, user_channel_id: u128, result: Result<(), Error>) {
+		let waiters = self.channel_ready_waiters.lock().unwrap().remove(&user_channel_id);
+		for waiter in waiters.into_iter().flatten() {
+			// The caller might have timed out already, in which case there's nobody to notify.
+			let _ = waiter.send(result);
+		}
+	}
+
+	fn reject_inbound_channel(
+		&self, temporary_channel_id: &ChannelId, counterparty_node_id: PublicKey,
+		funding_satoshis: u64, reason: ChannelRejectReason,
+	) -> Result<(), ReplayEvent> {
+		self.channel_manager
+			.force_close_broadcasting_latest_txn(
+				temporary_channel_id,
+				&counterparty_node_id,
+				"Channel request rejected".to_string(),
+			)
+			.unwrap_or_else(|e| log_error!(self.logger, "Failed to reject channel: {:?}", e));
+
+		let event = Event::ChannelRejected { counterparty_node_id, funding_satoshis, reason };
+		match self.event_queue.add_event(event) {
+			Ok(_) => Ok(()),
+			Err(e) => {
+				log_error!(self.logger, "Failed to push to event queue: {}", e);
+				Err(ReplayEvent())
+			},
+		}
+	}
+
+	fn record_recovered_balance(&self, channel_id: ChannelId, amount_sats: u64) {
+		if amount_sats == 0 {
+			return;
+		}
+
+		let balance_recovered_sat = match self.closed_channel_store.get(&channel_id) {
+			Some(details) => details.balance_recovered_sat.unwrap_or(0).saturating_add(amount_sats),
+			None => return,
+		};
+
+		let update = ClosedChannelDetailsUpdate {
+			channel_id,
+			balance_recovered_sat: Some(Some(balance_recovered_sat)),
+		};
+		if let Err(e) = self.closed_channel_store.update(&update) {
+			log_error!(self.logger, "Failed to access closed channel store: {}", e);
+		}
+	}
+
+	fn estimate_claim_deadline_unix_secs(&self, claim_deadline: Option<u32>) -> Option<u64> {
+		let claim_deadline = claim_deadline?;
+		let block_interval_secs = average_block_interval_secs(self.config.network)?;
+
+		// Only trust our view of the current height if we synced the chain at least once.
+		self.node_metrics.read().unwrap().latest_lightning_wallet_sync_timestamp?;
+
+		let cur_height = self.channel_manager.current_best_block().height;
+		let remaining_blocks = claim_deadline.saturating_sub(cur_height) as u64;
+		let now_secs = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+		Some(now_secs.saturating_add(remaining_blocks.saturating_mul(block_interval_secs)))
+	}
+
 	pub async fn handle_event(&self, event: LdkEvent) -> Result<(), ReplayEvent> {
 		match event {
 			LdkEvent::FundingGenerationReady {
@@ -499,11 +1406,65 @@ where
 				counterparty_node_id,
 				channel_value_satoshis,
 				output_script,
+				user_channel_id,
 				..
 			} => {
+				{
+					let mut locked_pending_fundings =
+						self.pending_external_fundings.lock().unwrap();
+					if let Some(pending_funding) = locked_pending_fundings.get_mut(&user_channel_id)
+					{
+						if let PendingExternalFunding::Abandoned = pending_funding {
+							// `Node::open_channel_psbt` already gave up on this channel, so we must
+							// not fund it from our on-chain wallet. Just make sure it gets closed.
+							log_error!(
+								self.logger,
+								"Refusing to fund channel {} as its external funding was abandoned",
+								temporary_channel_id
+							);
+							self.channel_manager
+								.force_close_broadcasting_latest_txn(
+									&temporary_channel_id,
+									&counterparty_node_id,
+									"External funding was abandoned".to_string(),
+								)
+								.unwrap_or_else(|e| {
+									log_error!(
+										self.logger,
+										"Failed to close abandoned channel {}: {:?}",
+										temporary_channel_id,
+										e
+									);
+								});
+							return Ok(());
+						}
+
+						// The funding transaction will be signed externally, so we just record the
+						// funding output and notify `Node::open_channel_psbt` to hand out the PSBT.
+						let awaiting_psbt = PendingExternalFunding::AwaitingSignedPsbt {
+							temporary_channel_id,
+							counterparty_node_id,
+							output_script,
+							channel_value_sats: channel_value_satoshis,
+							deadline: Instant::now() + EXTERNAL_FUNDING_PSBT_TIMEOUT,
+						};
+						if let PendingExternalFunding::AwaitingFundingGeneration { sender } =
+							std::mem::replace(pending_funding, awaiting_psbt)
+						{
+							let _ = sender.send(Ok(()));
+						}
+						return Ok(());
+					}
+				}
+
 				// Construct the raw transaction with the output that is paid the amount of the
 				// channel.
-				let confirmation_target = ConfirmationTarget::ChannelFunding;
+				let confirmation_target = self
+					.funding_confirmation_targets
+					.lock()
+					.unwrap()
+					.remove(&user_channel_id)
+					.unwrap_or(ConfirmationTarget::ChannelFunding);
 
 				// We set nLockTime to the current height to discourage fee sniping.
 				let cur_height = self.channel_manager.current_best_block().height;
@@ -572,10 +1533,31 @@ where
 				claim_deadline,
 				onion_fields,
 				counterparty_skimmed_fee_msat,
+				ref receiving_channel_ids,
 				..
 			} => {
 				let payment_id = PaymentId(payment_hash.0);
 				if let Some(info) = self.payment_store.get(&payment_id) {
+					if let PaymentKind::Rebalance { .. } = info.kind {
+						// Rebalancing payments are circular, i.e., we're their recipient, too.
+						if let Some(preimage) = purpose.preimage() {
+							log_info!(
+								self.logger,
+								"Claiming rebalancing payment with ID {} of {}msat",
+								payment_id,
+								amount_msat,
+							);
+							self.channel_manager.claim_funds(preimage);
+						} else {
+							debug_assert!(
+								false,
+								"We always generate rebalancing payments with a known preimage"
+							);
+							self.channel_manager.fail_htlc_backwards(&payment_hash);
+						}
+						return Ok(());
+					}
+
 					if info.direction == PaymentDirection::Outbound {
 						log_info!(
 							self.logger,
@@ -699,15 +1681,43 @@ where
 										cf.custom_tlvs().into_iter().map(|tlv| tlv.into()).collect()
 									})
 									.unwrap_or_default();
+								let claim_deadline_unix_secs =
+									self.estimate_claim_deadline_unix_secs(claim_deadline);
+								let via_jit_channel =
+									matches!(info.kind, PaymentKind::Bolt11Jit { .. });
+								let receiving_channels = self
+									.channel_manager
+									.list_channels()
+									.into_iter()
+									.filter(|c| {
+										receiving_channel_ids
+											.iter()
+											.any(|(channel_id, _)| *channel_id == c.channel_id)
+									})
+									.collect::<Vec<_>>();
+								let channel_confirmed = !receiving_channels.is_empty()
+									&& receiving_channels
+										.iter()
+										.all(|c| c.confirmations.map_or(false, |confs| confs > 0));
 								let event = Event::PaymentClaimable {
 									payment_id,
 									payment_hash,
 									claimable_amount_msat: amount_msat,
 									claim_deadline,
+									claim_deadline_unix_secs,
 									custom_records,
+									via_jit_channel,
+									channel_confirmed,
 								};
 								match self.event_queue.add_event(event) {
-									Ok(_) => return Ok(()),
+									Ok(_) => {
+										self.claim_watchdog.register_claimable(
+											payment_hash,
+											claim_deadline,
+											info.claim_timeout_secs,
+										);
+										return Ok(());
+									},
 									Err(e) => {
 										log_error!(
 											self.logger,
@@ -786,6 +1796,29 @@ where
 						payment_preimage
 					},
 					PaymentPurpose::SpontaneousPayment(preimage) => {
+						if !self.config.accept_spontaneous_payments {
+							log_info!(
+								self.logger,
+								"Refused spontaneous inbound payment from payment hash {} of {}msat as we don't accept spontaneous payments.",
+								hex_utils::to_string(&payment_hash.0),
+								amount_msat,
+							);
+							self.channel_manager.fail_htlc_backwards(&payment_hash);
+							return Ok(());
+						}
+
+						let expected_payment_hash =
+							PaymentHash(Sha256::hash(&preimage.0).to_byte_array());
+						if expected_payment_hash != payment_hash {
+							log_error!(
+								self.logger,
+								"Refused spontaneous inbound payment as the preimage doesn't match the payment hash {}",
+								hex_utils::to_string(&payment_hash.0),
+							);
+							self.channel_manager.fail_htlc_backwards(&payment_hash);
+							return Ok(());
+						}
+
 						// Since it's spontaneous, we insert it now into our store.
 						let kind = PaymentKind::Spontaneous {
 							hash: payment_hash,
@@ -869,6 +1902,25 @@ where
 					amount_msat,
 				);
 
+				if matches!(
+					self.payment_store.get(&payment_id).map(|p| p.kind),
+					Some(PaymentKind::Rebalance { .. })
+				) {
+					// Rebalancing payments are updated once we learn they were sent successfully.
+					return Ok(());
+				}
+
+				let payer_note = match purpose {
+					PaymentPurpose::Bolt12OfferPayment { ref payment_context, .. } => {
+						payment_context.invoice_request.payer_note_truncated.clone()
+					},
+					_ => None,
+				};
+
+				let settled_at_unix_secs = SystemTime::now()
+					.duration_since(UNIX_EPOCH)
+					.unwrap_or(Duration::from_secs(0))
+					.as_secs();
 				let update = match purpose {
 					PaymentPurpose::Bolt11InvoicePayment {
 						payment_preimage,
@@ -879,6 +1931,7 @@ where
 						secret: Some(Some(payment_secret)),
 						amount_msat: Some(Some(amount_msat)),
 						status: Some(PaymentStatus::Succeeded),
+						settled_at_unix_secs: Some(Some(settled_at_unix_secs)),
 						..PaymentDetailsUpdate::new(payment_id)
 					},
 					PaymentPurpose::Bolt12OfferPayment {
@@ -888,6 +1941,7 @@ where
 						secret: Some(Some(payment_secret)),
 						amount_msat: Some(Some(amount_msat)),
 						status: Some(PaymentStatus::Succeeded),
+						settled_at_unix_secs: Some(Some(settled_at_unix_secs)),
 						..PaymentDetailsUpdate::new(payment_id)
 					},
 					PaymentPurpose::Bolt12RefundPayment {
@@ -899,12 +1953,14 @@ where
 						secret: Some(Some(payment_secret)),
 						amount_msat: Some(Some(amount_msat)),
 						status: Some(PaymentStatus::Succeeded),
+						settled_at_unix_secs: Some(Some(settled_at_unix_secs)),
 						..PaymentDetailsUpdate::new(payment_id)
 					},
 					PaymentPurpose::SpontaneousPayment(preimage) => PaymentDetailsUpdate {
 						preimage: Some(Some(preimage)),
 						amount_msat: Some(Some(amount_msat)),
 						status: Some(PaymentStatus::Succeeded),
+						settled_at_unix_secs: Some(Some(settled_at_unix_secs)),
 						..PaymentDetailsUpdate::new(payment_id)
 					},
 				};
@@ -939,6 +1995,7 @@ where
 					custom_records: onion_fields
 						.map(|cf| cf.custom_tlvs().into_iter().map(|tlv| tlv.into()).collect())
 						.unwrap_or_default(),
+					payer_note,
 				};
 				match self.event_queue.add_event(event) {
 					Ok(_) => return Ok(()),
@@ -962,11 +2019,18 @@ where
 					return Ok(());
 				};
 
+				self.retry_strategies.lock().unwrap().remove(&payment_id);
+
+				let settled_at_unix_secs = SystemTime::now()
+					.duration_since(UNIX_EPOCH)
+					.unwrap_or(Duration::from_secs(0))
+					.as_secs();
 				let update = PaymentDetailsUpdate {
 					hash: Some(Some(payment_hash)),
 					preimage: Some(Some(payment_preimage)),
 					fee_paid_msat: Some(fee_paid_msat),
 					status: Some(PaymentStatus::Succeeded),
+					settled_at_unix_secs: Some(Some(settled_at_unix_secs)),
 					..PaymentDetailsUpdate::new(payment_id)
 				};
 
@@ -978,7 +2042,8 @@ where
 					},
 				};
 
-				self.payment_store.get(&payment_id).map(|payment| {
+				let payment = self.payment_store.get(&payment_id);
+				if let Some(payment) = &payment {
 					log_info!(
 						self.logger,
 						"Successfully sent payment of {}msat{} from \
@@ -992,23 +2057,63 @@ where
 						hex_utils::to_string(&payment_hash.0),
 						hex_utils::to_string(&payment_preimage.0)
 					);
-				});
-				let event = Event::PaymentSuccessful {
+				}
+				let mut events = vec![Event::PaymentSuccessful {
 					payment_id: Some(payment_id),
 					payment_hash,
 					payment_preimage: Some(payment_preimage),
 					fee_paid_msat,
-				};
+				}];
 
-				match self.event_queue.add_event(event) {
-					Ok(_) => return Ok(()),
-					Err(e) => {
-						log_error!(self.logger, "Failed to push to event queue: {}", e);
-						return Err(ReplayEvent());
+				let refund_amount_msat = payment.and_then(|payment| match payment.kind {
+					PaymentKind::Bolt12Refund { .. }
+						if payment.direction == PaymentDirection::Outbound =>
+					{
+						payment.amount_msat
 					},
-				};
+					_ => None,
+				});
+				if let Some(amount_msat) = refund_amount_msat {
+					events.push(Event::RefundPaid {
+						payment_id,
+						payment_hash,
+						amount_msat,
+						fee_paid_msat,
+					});
+				}
+
+				// Add the events at once, so that we don't emit them twice when replaying.
+				if let Err(e) = self.event_queue.add_events(events) {
+					log_error!(self.logger, "Failed to push to event queue: {}", e);
+					return Err(ReplayEvent());
+				}
+				return Ok(());
 			},
 			LdkEvent::PaymentFailed { payment_id, payment_hash, reason, .. } => {
+				let pending_invoice =
+					self.pending_bolt12_invoices.lock().unwrap().remove(&payment_id);
+				if let Some(pending_invoice) = pending_invoice {
+					// The payment was only tracked for an invoice requested via
+					// `Bolt12Payment::request_invoice`, so there's nothing to report to the user.
+					log_info!(
+						self.logger,
+						"Dropped BOLT12 invoice request with payment ID {} due to {:?}.",
+						payment_id,
+						reason
+					);
+					if let PendingBolt12Invoice::AwaitingInvoice { sender, .. } = pending_invoice {
+						let err = match reason {
+							Some(PaymentFailureReason::InvoiceRequestExpired)
+							| Some(PaymentFailureReason::UserAbandoned) => Error::InvoiceRequestTimeout,
+							_ => Error::InvoiceRequestFailed,
+						};
+						// The caller might have timed out already, in which case there's nobody to
+						// notify.
+						let _ = sender.send(Err(err));
+					}
+					return Ok(());
+				}
+
 				log_info!(
 					self.logger,
 					"Failed to send payment with ID {} due to {:?}.",
@@ -1029,10 +2134,23 @@ where
 					},
 				};
 
-				let event =
-					Event::PaymentFailed { payment_id: Some(payment_id), payment_hash, reason };
+				let retry_strategy = if reason == Some(PaymentFailureReason::RetriesExhausted) {
+					self.retry_strategies.lock().unwrap().get(&payment_id).copied()
+				} else {
+					None
+				};
+
+				let event = Event::PaymentFailed {
+					payment_id: Some(payment_id),
+					payment_hash,
+					reason,
+					retry_strategy,
+				};
 				match self.event_queue.add_event(event) {
-					Ok(_) => return Ok(()),
+					Ok(_) => {
+						self.retry_strategies.lock().unwrap().remove(&payment_id);
+						return Ok(());
+					},
 					Err(e) => {
 						log_error!(self.logger, "Failed to push to event queue: {}", e);
 						return Err(ReplayEvent());
@@ -1040,22 +2158,99 @@ where
 				};
 			},
 
-			LdkEvent::PaymentPathSuccessful { .. } => {},
+			LdkEvent::PaymentPathSuccessful { payment_id, path, .. } => {
+				let route_hops = path.hops.iter().map(|hop| hop.pubkey).collect();
+				let update = PaymentDetailsUpdate {
+					route_hops: Some(Some(route_hops)),
+					..PaymentDetailsUpdate::new(payment_id)
+				};
+				match self.payment_store.update(&update) {
+					Ok(_) => {},
+					Err(e) => {
+						log_error!(self.logger, "Failed to access payment store: {}", e);
+						return Err(ReplayEvent());
+					},
+				};
+			},
 			LdkEvent::PaymentPathFailed { .. } => {},
-			LdkEvent::ProbeSuccessful { .. } => {},
-			LdkEvent::ProbeFailed { .. } => {},
+			LdkEvent::ProbeSuccessful { payment_hash, path, .. } => {
+				let path_fees_msat = path.fee_msat();
+				let path_hops = path.hops.len() as u32;
+				log_debug!(
+					self.logger,
+					"Probe with hash {} succeeded over {} hops with {}msat in fees.",
+					payment_hash,
+					path_hops,
+					path_fees_msat
+				);
+
+				let event = Event::ProbeSuccessful { payment_hash, path_fees_msat, path_hops };
+				match self.event_queue.add_event(event) {
+					Ok(_) => {},
+					Err(e) => {
+						log_error!(self.logger, "Failed to push to event queue: {}", e);
+						return Err(ReplayEvent());
+					},
+				};
+			},
+			LdkEvent::ProbeFailed { payment_hash, path, short_channel_id, .. } => {
+				let path_hops = path.hops.len() as u32;
+				let failed_at_hop = short_channel_id.and_then(|scid| {
+					path.hops.iter().position(|hop| hop.short_channel_id == scid).map(|i| i as u32)
+				});
+				log_debug!(
+					self.logger,
+					"Probe with hash {} failed at hop {:?} of {}.",
+					payment_hash,
+					failed_at_hop,
+					path_hops
+				);
+
+				let event = Event::ProbeFailed { payment_hash, path_hops, failed_at_hop };
+				match self.event_queue.add_event(event) {
+					Ok(_) => {},
+					Err(e) => {
+						log_error!(self.logger, "Failed to push to event queue: {}", e);
+						return Err(ReplayEvent());
+					},
+				};
+			},
 			LdkEvent::HTLCHandlingFailed { failure_type, .. } => {
 				if let Some(liquidity_source) = self.liquidity_source.as_ref() {
 					liquidity_source.handle_htlc_handling_failed(failure_type).await;
 				}
 			},
 			LdkEvent::SpendableOutputs { outputs, channel_id } => {
+				// The event may be replayed, in which case the sweeper already tracks (some of) the
+				// outputs. We only account for the ones it doesn't track yet so we don't count the
+				// recovered balance twice.
+				let already_tracked_descriptors = self
+					.output_sweeper
+					.tracked_spendable_outputs()
+					.into_iter()
+					.map(|o| o.descriptor)
+					.collect::<Vec<_>>();
+				let output_value_sats = outputs
+					.iter()
+					.filter(|descriptor| !already_tracked_descriptors.contains(descriptor))
+					.map(|descriptor| match descriptor {
+						SpendableOutputDescriptor::StaticOutput { output, .. } => output.value,
+						SpendableOutputDescriptor::DelayedPaymentOutput(d) => d.output.value,
+						SpendableOutputDescriptor::StaticPaymentOutput(d) => d.output.value,
+					})
+					.map(|value| value.to_sat())
+					.sum::<u64>();
 				match self
 					.output_sweeper
 					.track_spendable_outputs(outputs, channel_id, true, None)
 					.await
 				{
-					Ok(_) => return Ok(()),
+					Ok(_) => {
+						if let Some(channel_id) = channel_id {
+							self.record_recovered_balance(channel_id, output_value_sats);
+						}
+						return Ok(());
+					},
 					Err(_) => {
 						log_error!(self.logger, "Failed to track spendable outputs");
 						return Err(ReplayEvent());
@@ -1069,23 +2264,91 @@ where
 				channel_type,
 				channel_negotiation_type: _,
 				is_announced,
-				params: _,
+				params,
 			} => {
-				if is_announced {
-					if let Err(err) = may_announce_channel(&*self.config) {
+				let announce_for_forwarding = match may_announce_channel(&*self.config) {
+					Ok(()) => true,
+					Err(err) if is_announced => {
 						log_error!(self.logger, "Rejecting inbound announced channel from peer {} due to missing configuration: {}", counterparty_node_id, err);
+						return self.reject_inbound_channel(
+							&temporary_channel_id,
+							counterparty_node_id,
+							funding_satoshis,
+							ChannelRejectReason::AnnouncementUnsupported,
+						);
+					},
+					Err(_) => false,
+				};
 
-						self.channel_manager
-							.force_close_broadcasting_latest_txn(
-								&temporary_channel_id,
-								&counterparty_node_id,
-								"Channel request rejected".to_string(),
-							)
-							.unwrap_or_else(|e| {
-								log_error!(self.logger, "Failed to reject channel: {:?}", e)
-							});
-						return Ok(());
-					}
+				let limits = self.config.inbound_channel_limits.for_peer(&counterparty_node_id);
+				let limit_violation = if funding_satoshis < limits.min_funding_satoshis {
+					Some(format!(
+						"channel value of {}sats is below our minimum of {}sats",
+						funding_satoshis, limits.min_funding_satoshis
+					))
+				} else if funding_satoshis > limits.max_funding_satoshis {
+					Some(format!(
+						"channel value of {}sats exceeds our maximum of {}sats",
+						funding_satoshis, limits.max_funding_satoshis
+					))
+				} else if params.htlc_minimum_msat > limits.max_htlc_minimum_msat {
+					Some(format!(
+						"HTLC minimum of {}msat exceeds our maximum of {}msat",
+						params.htlc_minimum_msat, limits.max_htlc_minimum_msat
+					))
+				} else if limits.force_announced_channel_preference
+					&& is_announced != announce_for_forwarding
+				{
+					Some("unannounced channels are not accepted".to_string())
+				} else {
+					None
+				};
+
+				if let Some(violation) = limit_violation {
+					log_error!(
+						self.logger,
+						"Rejecting inbound channel from peer {} as it violates our limits: {}",
+						counterparty_node_id,
+						violation,
+					);
+					return self.reject_inbound_channel(
+						&temporary_channel_id,
+						counterparty_node_id,
+						funding_satoshis,
+						ChannelRejectReason::ExceedsLimits,
+					);
+				}
+
+				if params.dust_limit_satoshis > MAX_INBOUND_DUST_LIMIT_SATS {
+					log_error!(
+						self.logger,
+						"Rejecting inbound channel from peer {} due to its dust limit of {}sats exceeding our maximum of {}sats",
+						counterparty_node_id,
+						params.dust_limit_satoshis,
+						MAX_INBOUND_DUST_LIMIT_SATS,
+					);
+					return self.reject_inbound_channel(
+						&temporary_channel_id,
+						counterparty_node_id,
+						funding_satoshis,
+						ChannelRejectReason::DustLimit,
+					);
+				}
+
+				let allow_0conf = self.config.features.zero_conf
+					&& self.config.trusted_peers_0conf.contains(&counterparty_node_id);
+				if channel_type.requires_zero_conf() && !allow_0conf {
+					log_error!(
+						self.logger,
+						"Rejecting inbound zero-confirmation channel from untrusted peer {}",
+						counterparty_node_id,
+					);
+					return self.reject_inbound_channel(
+						&temporary_channel_id,
+						counterparty_node_id,
+						funding_satoshis,
+						ChannelRejectReason::UntrustedZeroConf,
+					);
 				}
 
 				let anchor_channel = channel_type.requires_anchors_zero_fee_htlc_tx();
@@ -1102,14 +2365,12 @@ where
 							.get_spendable_amount_sats(cur_anchor_reserve_sats)
 							.unwrap_or(0);
 
-						let required_amount_sats = if anchor_channels_config
-							.trusted_peers_no_reserve
-							.contains(&counterparty_node_id)
-						{
-							0
-						} else {
-							anchor_channels_config.per_channel_reserve_sats
-						};
+						let required_amount_sats =
+							if anchor_channels_config.requires_reserve_for(&counterparty_node_id) {
+								anchor_channels_config.per_channel_reserve_sats
+							} else {
+								0
+							};
 
 						if spendable_amount_sats < required_amount_sats {
 							log_error!(
@@ -1119,16 +2380,12 @@ where
 								spendable_amount_sats,
 								required_amount_sats,
 							);
-							self.channel_manager
-								.force_close_broadcasting_latest_txn(
-									&temporary_channel_id,
-									&counterparty_node_id,
-									"Channel request rejected".to_string(),
-								)
-								.unwrap_or_else(|e| {
-									log_error!(self.logger, "Failed to reject channel: {:?}", e)
-								});
-							return Ok(());
+							return self.reject_inbound_channel(
+								&temporary_channel_id,
+								counterparty_node_id,
+								funding_satoshis,
+								ChannelRejectReason::InsufficientReserve,
+							);
 						}
 					} else {
 						log_error!(
@@ -1136,21 +2393,16 @@ where
 							"Rejecting inbound channel from peer {} due to Anchor channels being disabled.",
 							counterparty_node_id,
 						);
-						self.channel_manager
-							.force_close_broadcasting_latest_txn(
-								&temporary_channel_id,
-								&counterparty_node_id,
-								"Channel request rejected".to_string(),
-							)
-							.unwrap_or_else(|e| {
-								log_error!(self.logger, "Failed to reject channel: {:?}", e)
-							});
-						return Ok(());
+						return self.reject_inbound_channel(
+							&temporary_channel_id,
+							counterparty_node_id,
+							funding_satoshis,
+							ChannelRejectReason::AnchorsDisabled,
+						);
 					}
 				}
 
 				let user_channel_id: u128 = thread_rng().gen::<u128>();
-				let allow_0conf = self.config.trusted_peers_0conf.contains(&counterparty_node_id);
 				let mut channel_override_config = None;
 				if let Some((lsp_node_id, _)) = self
 					.liquidity_source
@@ -1295,6 +2547,24 @@ where
 						.await;
 				}
 
+				// We identify the forward by its contents, which are identical when it's replayed.
+				let forward_id = Sha256::hash(
+					&LdkEvent::PaymentForwarded {
+						prev_channel_id,
+						next_channel_id,
+						prev_user_channel_id,
+						next_user_channel_id,
+						prev_node_id,
+						next_node_id,
+						total_fee_earned_msat,
+						skimmed_fee_msat,
+						claim_from_onchain_tx,
+						outbound_amount_forwarded_msat,
+					}
+					.encode(),
+				)
+				.to_byte_array();
+
 				let event = Event::PaymentForwarded {
 					prev_channel_id: prev_channel_id.expect("prev_channel_id expected for events generated by LDK versions greater than 0.0.107."),
 					next_channel_id: next_channel_id.expect("next_channel_id expected for events generated by LDK versions greater than 0.0.107."),
@@ -1311,6 +2581,35 @@ where
 					log_error!(self.logger, "Failed to push to event queue: {}", e);
 					ReplayEvent()
 				})?;
+
+				// We only account for the fees once the event was queued, as we'd otherwise count
+				// them twice when the event gets replayed. Likewise, we skip forwards we already
+				// accounted for before restarting.
+				if let Some(fee_earned_msat) = total_fee_earned_msat {
+					let forwards = self
+						.forward_replay_tracker
+						.lock()
+						.unwrap()
+						.track_forward(forward_id, fee_earned_msat);
+					if forwards.is_empty() {
+						log_debug!(
+							self.logger,
+							"Not accounting fees of possibly replayed forward."
+						);
+					} else {
+						let mut locked_node_metrics = self.node_metrics.write().unwrap();
+						for (forward_id, fee_earned_msat) in forwards {
+							locked_node_metrics.record_forwarding_fee(forward_id, fee_earned_msat);
+						}
+						if let Err(e) = write_node_metrics(
+							&*locked_node_metrics,
+							Arc::clone(&self.kv_store),
+							self.logger.clone(),
+						) {
+							log_error!(self.logger, "Failed to persist node metrics: {}", e);
+						}
+					}
+				}
 			},
 			LdkEvent::ChannelPending {
 				channel_id,
@@ -1342,6 +2641,8 @@ where
 					},
 				};
 
+				self.tx_broadcaster.watch_funding_outpoint(funding_txo);
+
 				let network_graph = self.network_graph.read_only();
 				let channels =
 					self.channel_manager.list_channels_with_counterparty(&counterparty_node_id);
@@ -1357,10 +2658,7 @@ where
 							.and_then(|node_info| node_info.announcement_info.as_ref())
 							.and_then(|ann_info| ann_info.addresses().first())
 						{
-							let peer = PeerInfo {
-								node_id: counterparty_node_id,
-								address: address.clone(),
-							};
+							let peer = PeerInfo::new(counterparty_node_id, address.clone());
 
 							self.peer_store.add_peer(peer).unwrap_or_else(|e| {
 								log_error!(
@@ -1375,8 +2673,61 @@ where
 				}
 			},
 			LdkEvent::ChannelReady {
-				channel_id, user_channel_id, counterparty_node_id, ..
+				channel_id,
+				user_channel_id,
+				counterparty_node_id,
+				funding_txo,
+				..
 			} => {
+				// As we only ever open v1 channels, a channel ID not matching the funding outpoint
+				// means that the channel has been spliced.
+				let is_splice = funding_txo.map_or(false, |funding_txo| {
+					ChannelId::v1_from_funding_txid(
+						funding_txo.txid.as_byte_array(),
+						funding_txo.vout as u16,
+					) != channel_id
+				});
+				if is_splice {
+					let new_capacity_sats = match self
+						.channel_manager
+						.list_channels_with_counterparty(&counterparty_node_id)
+						.iter()
+						.find(|c| c.channel_id == channel_id)
+					{
+						Some(channel_details) => channel_details.channel_value_satoshis,
+						None => {
+							log_error!(
+								self.logger,
+								"Splice of channel {} locked, but the channel could not be found",
+								channel_id,
+							);
+							return Ok(());
+						},
+					};
+					log_info!(
+						self.logger,
+						"Splice of channel {} with counterparty {} locked. New capacity: {}sats",
+						channel_id,
+						counterparty_node_id,
+						new_capacity_sats,
+					);
+
+					let event = Event::ChannelSpliced {
+						channel_id,
+						user_channel_id: UserChannelId(user_channel_id),
+						counterparty_node_id,
+						new_capacity_sats,
+					};
+					match self.event_queue.add_event(event) {
+						Ok(_) => {},
+						Err(e) => {
+							log_error!(self.logger, "Failed to push to event queue: {}", e);
+							return Err(ReplayEvent());
+						},
+					};
+					return Ok(());
+				}
+
 				log_info!(
 					self.logger,
 					"Channel {} with counterparty {} ready to be used.",
@@ -1384,39 +2735,109 @@ where
 					counterparty_node_id,
 				);
 
-				if let Some(liquidity_source) = self.liquidity_source.as_ref() {
-					liquidity_source
-						.handle_channel_ready(user_channel_id, &channel_id, &counterparty_node_id)
-						.await;
-				}
-
-				let event = Event::ChannelReady {
+				let mut events = vec![Event::ChannelReady {
 					channel_id,
 					user_channel_id: UserChannelId(user_channel_id),
 					counterparty_node_id: Some(counterparty_node_id),
-				};
-				match self.event_queue.add_event(event) {
+				}];
+				// If we opened a JIT channel to one of our LSPS2 clients, we queue both events at
+				// once so that they get replayed together if queuing fails.
+				if let Some(liquidity_source) = self.liquidity_source.as_ref() {
+					events.extend(liquidity_source.jit_channel_opened_event(
+						user_channel_id,
+						&channel_id,
+						&counterparty_node_id,
+					));
+				}
+				match self.event_queue.add_events(events) {
 					Ok(_) => {},
 					Err(e) => {
 						log_error!(self.logger, "Failed to push to event queue: {}", e);
 						return Err(ReplayEvent());
 					},
 				};
+
+				self.notify_channel_ready_waiters(user_channel_id, Ok(()));
+
+				if let Some(liquidity_source) = self.liquidity_source.as_ref() {
+					liquidity_source
+						.handle_channel_ready(user_channel_id, &channel_id, &counterparty_node_id)
+						.await;
+				}
 			},
 			LdkEvent::ChannelClosed {
 				channel_id,
 				reason,
 				user_channel_id,
 				counterparty_node_id,
+				channel_capacity_sats,
+				channel_funding_txo,
 				..
 			} => {
 				log_info!(self.logger, "Channel {} closed due to: {}", channel_id, reason);
 
+				let close_type = CloseType::from(&reason);
+				let funding_txo = channel_funding_txo.map(|txo| txo.into_bitcoin_outpoint());
+				let closing_tx =
+					funding_txo.and_then(|txo| self.tx_broadcaster.funding_spend(&txo));
+				let closing_txid = closing_tx.as_ref().map(|tx| tx.compute_txid());
+				let closing_fee_sat = closing_tx.as_ref().zip(channel_capacity_sats).and_then(
+					|(tx, capacity_sats)| {
+						let output_value_sats =
+							tx.output.iter().map(|o| o.value.to_sat()).sum::<u64>();
+						capacity_sats.checked_sub(output_value_sats)
+					},
+				);
+				let balance_recovered_sat = closing_tx
+					.as_ref()
+					.filter(|_| close_type == CloseType::Cooperative)
+					.map(|tx| {
+						tx.output
+							.iter()
+							.filter(|o| self.wallet.is_mine(o.script_pubkey.clone()))
+							.map(|o| o.value.to_sat())
+							.sum::<u64>()
+					});
+
+				if funding_txo.is_some() {
+					let closed_at_timestamp = SystemTime::now()
+						.duration_since(UNIX_EPOCH)
+						.unwrap_or(Duration::from_secs(0))
+						.as_secs();
+					let force_close_reason = match &reason {
+						ClosureReason::HolderForceClosed { message, .. } if !message.is_empty() => {
+							Some(message.clone())
+						},
+						_ => None,
+					};
+					let closed_channel = ClosedChannelDetails {
+						channel_id,
+						user_channel_id: UserChannelId(user_channel_id),
+						counterparty_node_id,
+						funding_txo,
+						channel_capacity_sats,
+						close_type,
+						closing_txid,
+						closing_fee_sat,
+						balance_recovered_sat,
+						closed_at_timestamp,
+						force_close_reason,
+					};
+					if let Err(e) = self.closed_channel_store.insert(closed_channel) {
+						log_error!(self.logger, "Failed to access closed channel store: {}", e);
+						return Err(ReplayEvent());
+					}
+				}
+
 				let event = Event::ChannelClosed {
 					channel_id,
 					user_channel_id: UserChannelId(user_channel_id),
 					counterparty_node_id,
 					reason: Some(reason),
+					closing_fee_sat,
+					closing_txid,
+					close_type,
+					balance_recovered_sat,
 				};
 
 				match self.event_queue.add_event(event) {
@@ -1426,14 +2847,35 @@ where
 						return Err(ReplayEvent());
 					},
 				};
+
+				if let Some(funding_txo) = funding_txo {
+					self.tx_broadcaster.unwatch_funding_outpoint(&funding_txo);
+				}
+				self.notify_channel_ready_waiters(
+					user_channel_id,
+					Err(Error::ChannelClosedBeforeReady),
+				);
+				self.funding_confirmation_targets.lock().unwrap().remove(&user_channel_id);
+				if let Some(PendingExternalFunding::AwaitingFundingGeneration { sender }) =
+					self.pending_external_fundings.lock().unwrap().remove(&user_channel_id)
+				{
+					let _ = sender.send(Err(Error::ChannelCreationFailed));
+				}
+				if let Some(liquidity_source) = self.liquidity_source.as_ref() {
+					liquidity_source.forget_pending_jit_channel(user_channel_id);
+				}
+			},
+			LdkEvent::DiscardFunding { funding_info, .. } => {
+				if let FundingInfo::Tx { transaction } = funding_info {
+					self.wallet.release_external_utxos(&transaction);
+				}
 			},
-			LdkEvent::DiscardFunding { .. } => {},
 			LdkEvent::HTLCIntercepted {
 				requested_next_hop_scid,
 				intercept_id,
 				expected_outbound_amount_msat,
 				payment_hash,
-				..
+				inbound_amount_msat,
 			} => {
 				if let Some(liquidity_source) = self.liquidity_source.as_ref() {
 					liquidity_source
@@ -1445,9 +2887,121 @@ where
 						)
 						.await;
 				}
+
+				if self.config.accept_intercept_htlcs {
+					// LDK doesn't give us the inbound HTLC's CLTV expiry, so we look it up.
+					let expiry = self
+						.channel_manager
+						.list_channels()
+						.iter()
+						.flat_map(|c| c.pending_inbound_htlcs.iter())
+						.filter(|htlc| {
+							htlc.payment_hash == payment_hash
+								&& htlc.amount_msat == inbound_amount_msat
+						})
+						.map(|htlc| htlc.cltv_expiry)
+						.min();
+
+					let event = Event::HTLCIntercepted {
+						intercept_id,
+						payment_hash,
+						requested_next_hop_scid,
+						amount_msat: expected_outbound_amount_msat,
+						expiry,
+					};
+					match self.event_queue.add_event(event) {
+						Ok(_) => {},
+						Err(e) => {
+							log_error!(self.logger, "Failed to push to event queue: {}", e);
+							return Err(ReplayEvent());
+						},
+					};
+				}
 			},
-			LdkEvent::InvoiceReceived { .. } => {
-				debug_assert!(false, "We currently don't handle BOLT12 invoices manually, so this event should never be emitted.");
+			LdkEvent::InvoiceReceived { payment_id, invoice, context, responder } => {
+				let mut locked_pending_invoices = self.pending_bolt12_invoices.lock().unwrap();
+				match locked_pending_invoices.remove(&payment_id) {
+					Some(PendingBolt12Invoice::AwaitingInvoice { sender }) => {
+						// Hand out the invoice to the caller of `Bolt12Payment::request_invoice`
+						// rather than paying it.
+						if sender.send(Ok(invoice.clone())).is_err() {
+							// The caller timed out already, so we'll never pay the invoice.
+							log_info!(
+								self.logger,
+								"Received BOLT12 invoice for payment ID {} after the request timed out.",
+								payment_id
+							);
+							self.channel_manager.abandon_payment(payment_id);
+						}
+						let pending_invoice =
+							PendingBolt12Invoice::InvoiceReceived { invoice, context };
+						locked_pending_invoices.insert(payment_id, pending_invoice);
+					},
+					Some(pending_invoice) => {
+						debug_assert!(false, "We should only ever receive one invoice per payment");
+						locked_pending_invoices.insert(payment_id, pending_invoice);
+					},
+					None if self.payment_store.get(&payment_id).is_none() => {
+						// The invoice was requested via `Bolt12Payment::request_invoice` prior to
+						// a restart. As nobody is waiting for it anymore, we'll never pay it.
+						log_info!(
+							self.logger,
+							"Abandoning unknown BOLT12 invoice for payment ID {}.",
+							payment_id
+						);
+						self.channel_manager.abandon_payment(payment_id);
+						let pending_invoice =
+							PendingBolt12Invoice::InvoiceReceived { invoice, context };
+						locked_pending_invoices.insert(payment_id, pending_invoice);
+					},
+					None => {
+						drop(locked_pending_invoices);
+						if let Err(e) = self
+							.channel_manager
+							.send_payment_for_bolt12_invoice(&invoice, context.as_ref())
+						{
+							log_error!(
+								self.logger,
+								"Failed to pay BOLT12 invoice for payment ID {}: {:?}",
+								payment_id,
+								e
+							);
+
+							// Let the recipient know why we didn't pay, as LDK would if it handled
+							// the invoice itself.
+							let invoice_error = match e {
+								Bolt12PaymentError::UnknownRequiredFeatures => {
+									InvoiceError::from(Bolt12SemanticError::UnknownRequiredFeatures)
+								},
+								Bolt12PaymentError::SendingFailed(e) => {
+									InvoiceError::from_string(format!("{:?}", e))
+								},
+								Bolt12PaymentError::BlindedPathCreationFailed => {
+									InvoiceError::from_string(
+										"Failed to create a blinded path back to ourselves"
+											.to_string(),
+									)
+								},
+								Bolt12PaymentError::UnexpectedInvoice
+								| Bolt12PaymentError::DuplicateInvoice => return Ok(()),
+							};
+							if let Some(responder) = responder {
+								let message = OffersMessage::InvoiceError(invoice_error);
+								if let Err(e) = self
+									.onion_messenger
+									.handle_onion_message_response(message, responder.respond())
+								{
+									log_error!(
+										self.logger,
+										"Failed to respond with invoice error for payment ID {}: {:?}",
+										payment_id,
+										e
+									);
+								}
+							}
+						}
+					},
+				}
 			},
 			LdkEvent::ConnectionNeeded { node_id, addresses } => {
 				let spawn_logger = self.logger.clone();
@@ -1479,6 +3033,12 @@ where
 						ref counterparty_node_id,
 						..
 					} => {
+						// Remember the latest claim so the user may bump it further on demand.
+						self.pending_channel_close_bumps
+							.lock()
+							.unwrap()
+							.insert(*channel_id, bte.clone());
+
 						// Skip bumping channel closes if our counterparty is trusted.
 						if let Some(anchor_channels_config) =
 							self.config.anchor_channels_config.as_ref()
@@ -1592,19 +3152,51 @@ where
 					}
 				}
 			},
-			LdkEvent::FundingTransactionReadyForSigning { .. } => {
-				debug_assert!(false, "We currently don't support interactive-tx, so this event should never be emitted.");
+			LdkEvent::FundingTransactionReadyForSigning {
+				channel_id,
+				counterparty_node_id,
+				unsigned_transaction,
+				..
+			} => match self.wallet.sign_owned_inputs(unsigned_transaction) {
+				Ok(signed_tx) => {
+					if let Err(e) = self.channel_manager.funding_transaction_signed(
+						&channel_id,
+						&counterparty_node_id,
+						signed_tx,
+					) {
+						log_error!(
+							self.logger,
+							"Failed to provide signed splice transaction for channel {}: {:?}",
+							channel_id,
+							e
+						);
+					}
+				},
+				Err(()) => {
+					log_error!(
+						self.logger,
+						"Failed to sign splice transaction for channel {}",
+						channel_id
+					);
+				},
 			},
-			LdkEvent::SplicePending { .. } => {
-				debug_assert!(
-					false,
-					"We currently don't support splicing, so this event should never be emitted."
+			LdkEvent::SplicePending {
+				channel_id, counterparty_node_id, new_funding_txo, ..
+			} => {
+				log_info!(
+					self.logger,
+					"Splice of channel {} with counterparty {} pending. New funding outpoint: {}",
+					channel_id,
+					counterparty_node_id,
+					new_funding_txo,
 				);
 			},
-			LdkEvent::SpliceFailed { .. } => {
-				debug_assert!(
-					false,
-					"We currently don't support splicing, so this event should never be emitted."
+			LdkEvent::SpliceFailed { channel_id, counterparty_node_id, .. } => {
+				log_error!(
+					self.logger,
+					"Splice of channel {} with counterparty {} failed",
+					channel_id,
+					counterparty_node_id,
 				);
 			},
 		}
@@ -1638,6 +3230,10 @@ mod tests {
 		// Check we get the expected event and that it is returned until we mark it handled.
 		for _ in 0..5 {
 			assert_eq!(event_queue.wait_next_event(), expected_event);
+			assert_eq!(
+				event_queue.wait_next_event_timeout(Duration::from_secs(1)),
+				Some(expected_event.clone())
+			);
 			assert_eq!(event_queue.next_event_async().await, expected_event);
 			assert_eq!(event_queue.next_event(), Some(expected_event.clone()));
 		}
@@ -1656,6 +3252,228 @@ mod tests {
 
 		event_queue.event_handled().unwrap();
 		assert_eq!(event_queue.next_event(), None);
+		assert_eq!(event_queue.wait_next_event_timeout(Duration::from_millis(100)), None);
+	}
+
+	#[test]
+	fn events_are_added_all_or_nothing() {
+		let event_a = Event::ChannelReady {
+			channel_id: ChannelId([23u8; 32]),
+			user_channel_id: UserChannelId(2323),
+			counterparty_node_id: None,
+		};
+		let event_b = Event::ChannelReady {
+			channel_id: ChannelId([42u8; 32]),
+			user_channel_id: UserChannelId(4242),
+			counterparty_node_id: None,
+		};
+
+		// If persisting fails, none of the events are added.
+		let read_only_store: Arc<DynStore> = Arc::new(TestStore::new(true));
+		let logger = Arc::new(TestLogger::new());
+		let event_queue = EventQueue::new(read_only_store, Arc::clone(&logger));
+		assert!(event_queue.add_events(vec![event_a.clone(), event_b.clone()]).is_err());
+		assert_eq!(event_queue.next_event(), None);
+
+		let store: Arc<DynStore> = Arc::new(TestStore::new(false));
+		let event_queue = EventQueue::new(store, logger);
+		event_queue.add_events(vec![event_a.clone(), event_b.clone()]).unwrap();
+		assert_eq!(event_queue.next_event(), Some(event_a));
+		event_queue.event_handled().unwrap();
+		assert_eq!(event_queue.next_event(), Some(event_b));
+	}
+
+	#[test]
+	fn ephemeral_events_are_delivered_without_persisting() {
+		// Writes to a read-only store fail, just as they would during a storage outage.
+		let store: Arc<DynStore> = Arc::new(TestStore::new(true));
+		let logger = Arc::new(TestLogger::new());
+		let event_queue = EventQueue::new(Arc::clone(&store), Arc::clone(&logger));
+
+		let expected_event = Event::StorageUnavailable { reason: "outage".to_string() };
+		event_queue.add_ephemeral_event(expected_event.clone());
+		assert_eq!(event_queue.next_event(), Some(expected_event));
+		event_queue.event_handled().unwrap();
+		assert_eq!(event_queue.next_event(), None);
+
+		// Ephemeral events are never written to the persisted queue.
+		let store: Arc<DynStore> = Arc::new(TestStore::new(false));
+		let event_queue = EventQueue::new(Arc::clone(&store), Arc::clone(&logger));
+		event_queue.add_ephemeral_event(Event::StorageUnavailable { reason: "outage".to_string() });
+		let mut bytes = Vec::new();
+		EventQueueSerWrapper(&event_queue.queue.lock().unwrap()).write(&mut bytes).unwrap();
+		let deser_event_queue =
+			EventQueue::read(&mut &bytes[..], (Arc::clone(&store), logger)).unwrap();
+		assert_eq!(deser_event_queue.next_event(), None);
+	}
+
+	#[test]
+	fn replayed_forwards_are_only_accounted_once() {
+		let (a, b, c) = ([1u8; 32], [2u8; 32], [3u8; 32]);
+
+		// Forwards that were accounted for before restarting are skipped.
+		let mut tracker = ForwardReplayTracker::new(vec![a, b, c]);
+		assert!(tracker.track_forward(b, 10).is_empty());
+		assert!(tracker.track_forward(c, 10).is_empty());
+		assert_eq!(tracker.track_forward(c, 20), vec![(c, 20)]);
+
+		// Forwards that happen to match before they don't continue to do so are accounted for.
+		let mut tracker = ForwardReplayTracker::new(vec![a, b, c]);
+		assert!(tracker.track_forward(a, 10).is_empty());
+		assert_eq!(tracker.track_forward(a, 20), vec![(a, 10), (a, 20)]);
+		assert_eq!(tracker.track_forward(b, 30), vec![(b, 30)]);
+
+		// Without prior forwards nothing is skipped.
+		let mut tracker = ForwardReplayTracker::new(Vec::new());
+		assert_eq!(tracker.track_forward(a, 10), vec![(a, 10)]);
+	}
+
+	#[test]
+	fn event_dispatcher_redelivers_unhandled_events() {
+		struct FlakyEventHandler {
+			handled_events: Mutex<Vec<Event>>,
+			attempts: AtomicU16,
+		}
+
+		impl NodeEventHandler for FlakyEventHandler {
+			fn handle_event(&self, event: Event) -> Result<(), ReplayEvent> {
+				// Fail every other attempt.
+				if self.attempts.fetch_add(1, Ordering::SeqCst) % 2 == 0 {
+					return Err(ReplayEvent());
+				}
+				self.handled_events.lock().unwrap().push(event);
+				Ok(())
+			}
+		}
+
+		let store: Arc<DynStore> = Arc::new(TestStore::new(false));
+		let logger = Arc::new(Logger::new_log_facade());
+		let event_queue = Arc::new(EventQueue::new(Arc::clone(&store), Arc::clone(&logger)));
+
+		let expected_event = Event::ChannelReady {
+			channel_id: ChannelId([23u8; 32]),
+			user_channel_id: UserChannelId(2323),
+			counterparty_node_id: None,
+		};
+		event_queue.add_event(expected_event.clone()).unwrap();
+
+		let handler = Arc::new(FlakyEventHandler {
+			handled_events: Mutex::new(Vec::new()),
+			attempts: AtomicU16::new(0),
+		});
+		let dispatcher = EventDispatcher::spawn(Arc::clone(&event_queue), handler.clone());
+
+		let start = std::time::Instant::now();
+		while handler.handled_events.lock().unwrap().is_empty() {
+			assert!(start.elapsed() < Duration::from_secs(10));
+			std::thread::sleep(Duration::from_millis(10));
+		}
+		assert_eq!(*handler.handled_events.lock().unwrap(), vec![expected_event.clone()]);
+		assert_eq!(handler.attempts.load(Ordering::SeqCst), 2);
+
+		// Dropping the dispatcher waits for it to stop, after which no further events are handled.
+		drop(dispatcher);
+		assert_eq!(event_queue.next_event(), None);
+
+		event_queue.add_event(expected_event.clone()).unwrap();
+		std::thread::sleep(Duration::from_millis(500));
+		assert_eq!(handler.handled_events.lock().unwrap().len(), 1);
+		assert_eq!(event_queue.next_event(), Some(expected_event));
+	}
+
+	#[tokio::test]
+	async fn event_stream_acknowledgement() {
+		let store: Arc<DynStore> = Arc::new(TestStore::new(false));
+		let logger = Arc::new(Logger::new_log_facade());
+		let event_queue = Arc::new(EventQueue::new(Arc::clone(&store), Arc::clone(&logger)));
+
+		let first_event = Event::ChannelReady {
+			channel_id: ChannelId([23u8; 32]),
+			user_channel_id: UserChannelId(2323),
+			counterparty_node_id: None,
+		};
+		let second_event = Event::ChannelReady {
+			channel_id: ChannelId([42u8; 32]),
+			user_channel_id: UserChannelId(4242),
+			counterparty_node_id: None,
+		};
+		event_queue.add_event(first_event.clone()).unwrap();
+		event_queue.add_event(second_event.clone()).unwrap();
+
+		async fn next(stream: &mut EventStream) -> Option<Event> {
+			std::future::poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)).await
+		}
+
+		// Without auto-acknowledgement, the same event is yielded until it's marked handled.
+		let mut stream = EventStream::new(Arc::clone(&event_queue), false);
+		assert_eq!(next(&mut stream).await, Some(first_event.clone()));
+		assert_eq!(next(&mut stream).await, Some(first_event.clone()));
+		event_queue.event_handled().unwrap();
+		assert_eq!(next(&mut stream).await, Some(second_event.clone()));
+
+		// With auto-acknowledgement, requesting the next event marks the previous one handled.
+		event_queue.add_event(first_event.clone()).unwrap();
+		let mut stream = EventStream::new(Arc::clone(&event_queue), true);
+		assert_eq!(next(&mut stream).await, Some(second_event.clone()));
+		assert_eq!(next(&mut stream).await, Some(first_event.clone()));
+		assert!(event_queue.next_event().is_some());
+
+		// Once the queue is drained, the stream waits for new events.
+		tokio::select! {
+			_ = tokio::time::sleep(Duration::from_millis(100)) => {}
+			_ = next(&mut stream) => panic!(),
+		}
+		assert_eq!(event_queue.next_event(), None);
+
+		// Events that were marked handled manually aren't acknowledged a second time.
+		event_queue.add_event(second_event.clone()).unwrap();
+		event_queue.add_event(first_event.clone()).unwrap();
+		assert_eq!(next(&mut stream).await, Some(second_event));
+		event_queue.event_handled().unwrap();
+		assert_eq!(next(&mut stream).await, Some(first_event));
+	}
+
+	#[test]
+	fn event_queue_redelivers_unhandled_events_in_order() {
+		let store: Arc<DynStore> = Arc::new(TestStore::new(false));
+		let logger = Arc::new(TestLogger::new());
+		let event_queue = EventQueue::new(Arc::clone(&store), Arc::clone(&logger));
+
+		let events = (0..5u8)
+			.map(|i| Event::ChannelReady {
+				channel_id: ChannelId([i; 32]),
+				user_channel_id: UserChannelId(i as u128),
+				counterparty_node_id: None,
+			})
+			.collect::<Vec<_>>();
+		for event in &events {
+			event_queue.add_event(event.clone()).unwrap();
+		}
+		assert_eq!(event_queue.pending_event_count(), 5);
+
+		// Handle the first event and "crash" before handling the second one.
+		assert_eq!(event_queue.next_event(), Some(events[0].clone()));
+		event_queue.event_handled().unwrap();
+		assert_eq!(event_queue.next_event(), Some(events[1].clone()));
+		drop(event_queue);
+
+		// Check all unhandled events are redelivered in their original order after a restart.
+		let persisted_bytes = KVStoreSync::read(
+			&*store,
+			EVENT_QUEUE_PERSISTENCE_PRIMARY_NAMESPACE,
+			EVENT_QUEUE_PERSISTENCE_SECONDARY_NAMESPACE,
+			EVENT_QUEUE_PERSISTENCE_KEY,
+		)
+		.unwrap();
+		let event_queue =
+			EventQueue::read(&mut &persisted_bytes[..], (Arc::clone(&store), logger)).unwrap();
+		assert_eq!(event_queue.pending_event_count(), 4);
+		for event in &events[1..] {
+			assert_eq!(event_queue.next_event(), Some(event.clone()));
+			event_queue.event_handled().unwrap();
+		}
+		assert_eq!(event_queue.next_event(), None);
+		assert_eq!(event_queue.pending_event_count(), 0);
 	}
 
 	#[tokio::test]
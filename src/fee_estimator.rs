@@ -6,7 +6,7 @@
 // accordance with one or both of these licenses.
 
 use std::collections::HashMap;
-use std::sync::RwLock;
+use std::sync::{Arc, RwLock};
 
 use bitcoin::FeeRate;
 use lightning::chain::chaininterface::{
@@ -14,34 +14,104 @@ use lightning::chain::chaininterface::{
 	FEERATE_FLOOR_SATS_PER_KW,
 };
 
+/// The priority at which we want a transaction to confirm, used for fee rate estimation.
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
-pub(crate) enum ConfirmationTarget {
+pub enum ConfirmationTarget {
 	/// The default target for onchain payments.
 	OnchainPayment,
 	/// The target used for funding transactions.
 	ChannelFunding,
 	/// Targets used by LDK.
-	Lightning(LdkConfirmationTarget),
+	Lightning {
+		/// The LDK confirmation target.
+		target: LdkConfirmationTarget,
+	},
 }
 
-pub(crate) trait FeeEstimator {
-	fn estimate_fee_rate(&self, confirmation_target: ConfirmationTarget) -> FeeRate;
+/// A source of fee rate estimates, e.g., an internal mempool model, that can be used in place of
+/// the estimates retrieved from the configured chain source.
+///
+/// Can be set via [`Builder::set_custom_fee_estimator`].
+///
+/// [`Builder::set_custom_fee_estimator`]: crate::Builder::set_custom_fee_estimator
+#[cfg(not(feature = "uniffi"))]
+pub trait FeeEstimator: Send + Sync {
+	/// Returns the fee rate at which a transaction should be broadcast to confirm according to the
+	/// given [`ConfirmationTarget`].
+	fn estimate_fee_rate(&self, target: ConfirmationTarget) -> FeeRate;
+}
+
+/// A source of fee rate estimates, e.g., an internal mempool model, that can be used in place of
+/// the estimates retrieved from the configured chain source.
+///
+/// Can be set via [`Builder::set_custom_fee_estimator`].
+///
+/// [`Builder::set_custom_fee_estimator`]: crate::Builder::set_custom_fee_estimator
+#[cfg(feature = "uniffi")]
+pub trait FeeEstimator: Send + Sync {
+	/// Returns the fee rate at which a transaction should be broadcast to confirm according to the
+	/// given [`ConfirmationTarget`].
+	fn estimate_fee_rate(&self, target: ConfirmationTarget) -> Arc<FeeRate>;
 }
 
 impl From<LdkConfirmationTarget> for ConfirmationTarget {
 	fn from(value: LdkConfirmationTarget) -> Self {
-		Self::Lightning(value)
+		Self::Lightning { target: value }
 	}
 }
 
 pub(crate) struct OnchainFeeEstimator {
 	fee_rate_cache: RwLock<HashMap<ConfirmationTarget, FeeRate>>,
+	// Manually overridden fee rates, which take precedence over the cached estimates. Note these
+	// are deliberately not persisted.
+	fee_rate_overrides: RwLock<HashMap<ConfirmationTarget, FeeRate>>,
+	// A user-provided estimator which, if set, is used to populate the fee rate cache in place of
+	// the chain source.
+	custom_fee_estimator: Option<Arc<dyn FeeEstimator>>,
+	// The target used in place of LDK's `OutputSpendingFee` target when sweeping spendable
+	// outputs, if configured.
+	output_spending_target: Option<ConfirmationTarget>,
 }
 
 impl OnchainFeeEstimator {
-	pub(crate) fn new() -> Self {
+	pub(crate) fn new(
+		custom_fee_estimator: Option<Arc<dyn FeeEstimator>>,
+		output_spending_target: Option<ConfirmationTarget>,
+	) -> Self {
 		let fee_rate_cache = RwLock::new(HashMap::new());
-		Self { fee_rate_cache }
+		let fee_rate_overrides = RwLock::new(HashMap::new());
+		Self { fee_rate_cache, fee_rate_overrides, custom_fee_estimator, output_spending_target }
+	}
+
+	// Updates the fee rate cache from the custom fee estimator, if any. Returns whether a custom
+	// fee estimator is set, in which case the chain source shouldn't be queried for estimates.
+	pub(crate) fn update_from_custom_fee_estimator(&self) -> bool {
+		let custom_fee_estimator = match self.custom_fee_estimator.as_ref() {
+			Some(custom_fee_estimator) => custom_fee_estimator,
+			None => return false,
+		};
+
+		let mut new_fee_rate_cache = HashMap::with_capacity(10);
+		for target in get_all_conf_targets() {
+			#[cfg(not(feature = "uniffi"))]
+			let fee_rate = custom_fee_estimator.estimate_fee_rate(target);
+			#[cfg(feature = "uniffi")]
+			let fee_rate = *custom_fee_estimator.estimate_fee_rate(target);
+			new_fee_rate_cache.insert(target, fee_rate);
+		}
+		self.set_fee_rate_cache(new_fee_rate_cache);
+		true
+	}
+
+	// Sets or, if `None` is given, clears the fee rate override for the given target.
+	pub(crate) fn set_fee_rate_override(
+		&self, confirmation_target: ConfirmationTarget, fee_rate: Option<FeeRate>,
+	) {
+		let mut locked_fee_rate_overrides = self.fee_rate_overrides.write().unwrap();
+		match fee_rate {
+			Some(fee_rate) => locked_fee_rate_overrides.insert(confirmation_target, fee_rate),
+			None => locked_fee_rate_overrides.remove(&confirmation_target),
+		};
 	}
 
 	// Updates the fee rate cache and returns if the new values changed.
@@ -56,10 +126,9 @@ impl OnchainFeeEstimator {
 			false
 		}
 	}
-}
 
-impl FeeEstimator for OnchainFeeEstimator {
-	fn estimate_fee_rate(&self, confirmation_target: ConfirmationTarget) -> FeeRate {
+	pub(crate) fn estimate_fee_rate(&self, confirmation_target: ConfirmationTarget) -> FeeRate {
+		let locked_fee_rate_overrides = self.fee_rate_overrides.read().unwrap();
 		let locked_fee_rate_cache = self.fee_rate_cache.read().unwrap();
 
 		let fallback_sats_kwu = get_fallback_rate_for_target(confirmation_target);
@@ -67,7 +136,10 @@ impl FeeEstimator for OnchainFeeEstimator {
 		// We'll fall back on this, if we really don't have any other information.
 		let fallback_rate = FeeRate::from_sat_per_kwu(fallback_sats_kwu as u64);
 
-		let estimate = *locked_fee_rate_cache.get(&confirmation_target).unwrap_or(&fallback_rate);
+		let estimate = *locked_fee_rate_overrides
+			.get(&confirmation_target)
+			.or_else(|| locked_fee_rate_cache.get(&confirmation_target))
+			.unwrap_or(&fallback_rate);
 
 		// Currently we assume every transaction needs to at least be relayable, which is why we
 		// enforce a lower bound of `FEERATE_FLOOR_SATS_PER_KW`.
@@ -77,7 +149,13 @@ impl FeeEstimator for OnchainFeeEstimator {
 
 impl LdkFeeEstimator for OnchainFeeEstimator {
 	fn get_est_sat_per_1000_weight(&self, confirmation_target: LdkConfirmationTarget) -> u32 {
-		self.estimate_fee_rate(confirmation_target.into())
+		let target = match (confirmation_target, self.output_spending_target) {
+			(LdkConfirmationTarget::OutputSpendingFee, Some(output_spending_target)) => {
+				output_spending_target
+			},
+			_ => confirmation_target.into(),
+		};
+		self.estimate_fee_rate(target)
 			.to_sat_per_kwu()
 			.try_into()
 			.unwrap_or_else(|_| get_fallback_rate_for_ldk_target(confirmation_target))
@@ -88,7 +166,7 @@ pub(crate) fn get_num_block_defaults_for_target(target: ConfirmationTarget) -> u
 	match target {
 		ConfirmationTarget::OnchainPayment => 6,
 		ConfirmationTarget::ChannelFunding => 12,
-		ConfirmationTarget::Lightning(ldk_target) => match ldk_target {
+		ConfirmationTarget::Lightning { target: ldk_target } => match ldk_target {
 			LdkConfirmationTarget::MaximumFeeEstimate => 1,
 			LdkConfirmationTarget::UrgentOnChainSweep => 6,
 			LdkConfirmationTarget::MinAllowedAnchorChannelRemoteFee => 1008,
@@ -105,7 +183,9 @@ pub(crate) fn get_fallback_rate_for_target(target: ConfirmationTarget) -> u32 {
 	match target {
 		ConfirmationTarget::OnchainPayment => 5000,
 		ConfirmationTarget::ChannelFunding => 1000,
-		ConfirmationTarget::Lightning(ldk_target) => get_fallback_rate_for_ldk_target(ldk_target),
+		ConfirmationTarget::Lightning { target: ldk_target } => {
+			get_fallback_rate_for_ldk_target(ldk_target)
+		},
 	}
 }
 
@@ -141,16 +221,16 @@ pub(crate) fn apply_post_estimation_adjustments(
 	target: ConfirmationTarget, estimated_rate: FeeRate,
 ) -> FeeRate {
 	match target {
-		ConfirmationTarget::Lightning(
-			LdkConfirmationTarget::MinAllowedNonAnchorChannelRemoteFee,
-		) => {
+		ConfirmationTarget::Lightning {
+			target: LdkConfirmationTarget::MinAllowedNonAnchorChannelRemoteFee,
+		} => {
 			let slightly_less_than_background = estimated_rate
 				.to_sat_per_kwu()
 				.saturating_sub(250)
 				.max(FEERATE_FLOOR_SATS_PER_KW as u64);
 			FeeRate::from_sat_per_kwu(slightly_less_than_background)
 		},
-		ConfirmationTarget::Lightning(LdkConfirmationTarget::MaximumFeeEstimate) => {
+		ConfirmationTarget::Lightning { target: LdkConfirmationTarget::MaximumFeeEstimate } => {
 			// MaximumFeeEstimate is mostly used for protection against fee-inflation attacks. As
 			// users were previously impacted by this limit being too restrictive (read: too low),
 			// we bump it here a bit to give them some leeway.
@@ -164,3 +244,90 @@ pub(crate) fn apply_post_estimation_adjustments(
 		_ => estimated_rate,
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn fee_rate_overrides_take_precedence() {
+		let fee_estimator = OnchainFeeEstimator::new(None, None);
+		let target = ConfirmationTarget::OnchainPayment;
+
+		let cached_rate = FeeRate::from_sat_per_kwu(1000);
+		fee_estimator.set_fee_rate_cache(HashMap::from([(target, cached_rate)]));
+		assert_eq!(fee_estimator.estimate_fee_rate(target), cached_rate);
+
+		let override_rate = FeeRate::from_sat_per_kwu(5000);
+		fee_estimator.set_fee_rate_override(target, Some(override_rate));
+		assert_eq!(fee_estimator.estimate_fee_rate(target), override_rate);
+
+		// Other targets are unaffected.
+		let other_target = ConfirmationTarget::ChannelFunding;
+		let fallback_rate =
+			FeeRate::from_sat_per_kwu(get_fallback_rate_for_target(other_target) as u64);
+		assert_eq!(fee_estimator.estimate_fee_rate(other_target), fallback_rate);
+
+		// Cache updates don't clear the override, but clearing it restores the cached estimate.
+		let new_cached_rate = FeeRate::from_sat_per_kwu(2000);
+		fee_estimator.set_fee_rate_cache(HashMap::from([(target, new_cached_rate)]));
+		assert_eq!(fee_estimator.estimate_fee_rate(target), override_rate);
+		fee_estimator.set_fee_rate_override(target, None);
+		assert_eq!(fee_estimator.estimate_fee_rate(target), new_cached_rate);
+	}
+
+	struct FixedFeeEstimator(FeeRate);
+
+	impl FeeEstimator for FixedFeeEstimator {
+		#[cfg(not(feature = "uniffi"))]
+		fn estimate_fee_rate(&self, _target: ConfirmationTarget) -> FeeRate {
+			self.0
+		}
+
+		#[cfg(feature = "uniffi")]
+		fn estimate_fee_rate(&self, _target: ConfirmationTarget) -> Arc<FeeRate> {
+			Arc::new(self.0)
+		}
+	}
+
+	#[test]
+	fn custom_fee_estimator_populates_cache() {
+		let fee_estimator = OnchainFeeEstimator::new(None, None);
+		assert!(!fee_estimator.update_from_custom_fee_estimator());
+
+		let custom_rate = FeeRate::from_sat_per_kwu(3000);
+		let fee_estimator =
+			OnchainFeeEstimator::new(Some(Arc::new(FixedFeeEstimator(custom_rate))), None);
+		let target = ConfirmationTarget::ChannelFunding;
+		let fallback_rate = FeeRate::from_sat_per_kwu(get_fallback_rate_for_target(target) as u64);
+		assert_eq!(fee_estimator.estimate_fee_rate(target), fallback_rate);
+
+		assert!(fee_estimator.update_from_custom_fee_estimator());
+		for target in get_all_conf_targets() {
+			assert_eq!(fee_estimator.estimate_fee_rate(target), custom_rate);
+		}
+	}
+
+	#[test]
+	fn output_spending_target_is_remapped() {
+		let output_spending_target = ConfirmationTarget::OnchainPayment;
+		let fee_estimator = OnchainFeeEstimator::new(None, Some(output_spending_target));
+
+		let target_rate = FeeRate::from_sat_per_kwu(4000);
+		let spending_rate = FeeRate::from_sat_per_kwu(1500);
+		fee_estimator.set_fee_rate_cache(HashMap::from([
+			(output_spending_target, target_rate),
+			(LdkConfirmationTarget::OutputSpendingFee.into(), spending_rate),
+		]));
+
+		// LDK's estimates for spending outputs use the configured target instead.
+		assert_eq!(
+			fee_estimator.get_est_sat_per_1000_weight(LdkConfirmationTarget::OutputSpendingFee),
+			target_rate.to_sat_per_kwu() as u32
+		);
+		assert_eq!(
+			fee_estimator.estimate_fee_rate(LdkConfirmationTarget::OutputSpendingFee.into()),
+			spending_rate
+		);
+	}
+}
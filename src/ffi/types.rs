@@ -16,13 +16,18 @@ use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
 
+use base64::prelude::BASE64_STANDARD;
+use base64::Engine;
 pub use bip39::Mnemonic;
-use bitcoin::hashes::sha256::Hash as Sha256;
+pub use bitcoin::hashes::sha256::Hash as Sha256;
 use bitcoin::hashes::Hash;
+pub use bitcoin::psbt::Psbt;
 use bitcoin::secp256k1::PublicKey;
-pub use bitcoin::{Address, BlockHash, FeeRate, Network, OutPoint, Txid};
+pub use bitcoin::{Address, BlockHash, Network, Txid};
+pub use lightning::chain::chaininterface::ConfirmationTarget as LightningConfirmationTarget;
 pub use lightning::chain::channelmonitor::BalanceSource;
 pub use lightning::events::{ClosureReason, PaymentFailureReason};
+pub use lightning::ln::channelmanager::InterceptId;
 use lightning::ln::channelmanager::PaymentId;
 pub use lightning::ln::types::ChannelId;
 use lightning::offers::invoice::Bolt12Invoice as LdkBolt12Invoice;
@@ -30,7 +35,6 @@ pub use lightning::offers::offer::OfferId;
 use lightning::offers::offer::{Amount as LdkAmount, Offer as LdkOffer};
 use lightning::offers::refund::Refund as LdkRefund;
 pub use lightning::routing::gossip::{NodeAlias, NodeId, RoutingFees};
-pub use lightning::routing::router::RouteParametersConfig;
 use lightning::util::ser::Writeable;
 use lightning_invoice::{Bolt11Invoice as LdkBolt11Invoice, Bolt11InvoiceDescriptionRef};
 pub use lightning_invoice::{Description, SignedRawBolt11Invoice};
@@ -38,23 +42,29 @@ pub use lightning_liquidity::lsps0::ser::LSPSDateTime;
 pub use lightning_liquidity::lsps1::msgs::{
 	LSPS1ChannelInfo, LSPS1OrderId, LSPS1OrderParams, LSPS1PaymentState,
 };
+pub use lightning_liquidity::lsps2::msgs::LSPS2OpeningFeeParams;
+pub use lightning_types::features::NodeFeatures;
 pub use lightning_types::payment::{PaymentHash, PaymentPreimage, PaymentSecret};
 pub use lightning_types::string::UntrustedString;
 pub use vss_client::headers::{VssHeaderProvider, VssHeaderProviderError};
 
 use crate::builder::sanitize_alias;
 pub use crate::config::{
-	default_config, AnchorChannelsConfig, BackgroundSyncConfig, ElectrumSyncConfig,
-	EsploraSyncConfig, MaxDustHTLCExposure,
+	default_config, AnchorChannelsConfig, BackgroundSyncConfig, BitcoindSyncConfig,
+	ChannelHtlcLimits, DuplicatePaymentBehavior, ElectrumSyncConfig, EsploraSyncConfig,
+	FeatureConfig, InboundChannelLimits, InboundChannelLimitsOverride, InvoiceRouteHintPolicy,
+	MaxDustHTLCExposure, PeerReconnectPolicy, RetryStrategy, ScoringParameters, SendPaymentParams,
+	SpendableOutputSweepConfig, TorConfig,
 };
 use crate::error::Error;
 pub use crate::graph::{ChannelInfo, ChannelUpdateInfo, NodeAnnouncementInfo, NodeInfo};
-pub use crate::liquidity::{LSPS1OrderStatus, LSPS2ServiceConfig};
+pub use crate::liquidity::{JitChannelFeeLimits, LSPS1OrderStatus, LSPS2ServiceConfig};
 pub use crate::logger::{LogLevel, LogRecord, LogWriter};
-pub use crate::payment::store::{
-	ConfirmationStatus, LSPFeeLimits, PaymentDirection, PaymentKind, PaymentStatus,
+pub use crate::payment::store::{ConfirmationStatus, LSPFeeLimits, PaymentKindFilter};
+pub use crate::payment::{
+	FeeEstimate, PaymentPreference, QrPaymentResult, RecurringPaymentId, RouteHop, SpendableUtxo,
+	UnifiedPayment,
 };
-pub use crate::payment::QrPaymentResult;
 use crate::{hex_utils, SocketAddress, UniffiCustomTypeConverter, UserChannelId};
 
 impl UniffiCustomTypeConverter for PublicKey {
@@ -638,6 +648,42 @@ impl UniffiCustomTypeConverter for PaymentId {
 	}
 }
 
+impl UniffiCustomTypeConverter for RecurringPaymentId {
+	type Builtin = String;
+
+	fn into_custom(val: Self::Builtin) -> uniffi::Result<Self> {
+		if let Some(bytes_vec) = hex_utils::to_vec(&val) {
+			let bytes_res = bytes_vec.try_into();
+			if let Ok(bytes) = bytes_res {
+				return Ok(RecurringPaymentId(bytes));
+			}
+		}
+		Err(Error::InvalidPaymentId.into())
+	}
+
+	fn from_custom(obj: Self) -> Self::Builtin {
+		hex_utils::to_string(&obj.0)
+	}
+}
+
+impl UniffiCustomTypeConverter for InterceptId {
+	type Builtin = String;
+
+	fn into_custom(val: Self::Builtin) -> uniffi::Result<Self> {
+		if let Some(bytes_vec) = hex_utils::to_vec(&val) {
+			let bytes_res = bytes_vec.try_into();
+			if let Ok(bytes) = bytes_res {
+				return Ok(InterceptId(bytes));
+			}
+		}
+		Err(Error::InvalidInterceptId.into())
+	}
+
+	fn from_custom(obj: Self) -> Self::Builtin {
+		hex_utils::to_string(&obj.0)
+	}
+}
+
 impl UniffiCustomTypeConverter for PaymentHash {
 	type Builtin = String;
 
@@ -654,6 +700,22 @@ impl UniffiCustomTypeConverter for PaymentHash {
 	}
 }
 
+impl UniffiCustomTypeConverter for Sha256 {
+	type Builtin = String;
+
+	fn into_custom(val: Self::Builtin) -> uniffi::Result<Self> {
+		if let Ok(hash) = Sha256::from_str(&val) {
+			Ok(hash)
+		} else {
+			Err(Error::InvalidDescriptionHash.into())
+		}
+	}
+
+	fn from_custom(obj: Self) -> Self::Builtin {
+		obj.to_string()
+	}
+}
+
 impl UniffiCustomTypeConverter for PaymentPreimage {
 	type Builtin = String;
 
@@ -746,7 +808,7 @@ impl UniffiCustomTypeConverter for BlockHash {
 impl UniffiCustomTypeConverter for Mnemonic {
 	type Builtin = String;
 	fn into_custom(val: Self::Builtin) -> uniffi::Result<Self> {
-		Ok(Mnemonic::from_str(&val).map_err(|_| Error::InvalidSecretKey)?)
+		Ok(crate::parse_entropy_mnemonic(&val)?)
 	}
 
 	fn from_custom(obj: Self) -> Self::Builtin {
@@ -788,6 +850,68 @@ impl UniffiCustomTypeConverter for NodeAlias {
 	}
 }
 
+/// The RGB color of a node, as used in node announcements.
+pub type NodeColor = [u8; 3];
+
+impl UniffiCustomTypeConverter for NodeColor {
+	type Builtin = String;
+
+	fn into_custom(val: Self::Builtin) -> uniffi::Result<Self> {
+		Ok(crate::config::parse_node_color(&val)?)
+	}
+
+	fn from_custom(obj: Self) -> Self::Builtin {
+		hex_utils::to_string(&obj)
+	}
+}
+
+/// A key used to encrypt the node's persisted data at rest.
+pub type StorageEncryptionKey = [u8; 32];
+
+impl UniffiCustomTypeConverter for StorageEncryptionKey {
+	type Builtin = String;
+
+	fn into_custom(val: Self::Builtin) -> uniffi::Result<Self> {
+		if let Some(hex_vec) = hex_utils::to_vec(&val) {
+			if hex_vec.len() == 32 {
+				let mut key = [0u8; 32];
+				key.copy_from_slice(&hex_vec[..]);
+				return Ok(key);
+			}
+		}
+		Err(Error::InvalidSecretKey.into())
+	}
+
+	fn from_custom(obj: Self) -> Self::Builtin {
+		hex_utils::to_string(&obj)
+	}
+}
+
+impl UniffiCustomTypeConverter for NodeFeatures {
+	type Builtin = Vec<u8>;
+
+	fn into_custom(val: Self::Builtin) -> uniffi::Result<Self> {
+		Ok(NodeFeatures::from_be_bytes(val))
+	}
+
+	fn from_custom(obj: Self) -> Self::Builtin {
+		obj.le_flags().iter().rev().copied().collect()
+	}
+}
+
+impl UniffiCustomTypeConverter for Psbt {
+	type Builtin = String;
+
+	fn into_custom(val: Self::Builtin) -> uniffi::Result<Self> {
+		let bytes = BASE64_STANDARD.decode(val).map_err(|_| Error::InvalidPsbt)?;
+		Ok(Psbt::deserialize(&bytes).map_err(|_| Error::InvalidPsbt)?)
+	}
+
+	fn from_custom(obj: Self) -> Self::Builtin {
+		BASE64_STANDARD.encode(obj.serialize())
+	}
+}
+
 /// Represents the description of an invoice which has to be either a directly included string or
 /// a hash of a description provided out of band.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -1055,6 +1179,15 @@ impl std::fmt::Display for Bolt11Invoice {
 	}
 }
 
+/// An output of an on-chain payment to multiple addresses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OnchainPaymentOutput {
+	/// The address to pay.
+	pub address: Address,
+	/// The amount to send to the address, in satoshis.
+	pub amount_sats: u64,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct LSPS1PaymentInfo {
 	/// A Lightning payment using BOLT 11.
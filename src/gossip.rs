@@ -26,6 +26,7 @@ pub(crate) enum GossipSource {
 	},
 	RapidGossipSync {
 		gossip_sync: Arc<RapidGossipSync>,
+		p2p_gossip_sync: Option<Arc<P2PGossipSync>>,
 		server_url: String,
 		latest_sync_timestamp: AtomicU32,
 		logger: Arc<Logger>,
@@ -43,12 +44,27 @@ impl GossipSource {
 	}
 
 	pub fn new_rgs(
-		server_url: String, latest_sync_timestamp: u32, network_graph: Arc<Graph>,
-		logger: Arc<Logger>,
+		server_url: String, latest_sync_timestamp: u32, p2p_gossip: bool,
+		network_graph: Arc<Graph>, logger: Arc<Logger>,
 	) -> Self {
+		let p2p_gossip_sync = if p2p_gossip {
+			Some(Arc::new(P2PGossipSync::new(
+				Arc::clone(&network_graph),
+				None::<Arc<UtxoLookup>>,
+				Arc::clone(&logger),
+			)))
+		} else {
+			None
+		};
 		let gossip_sync = Arc::new(RapidGossipSync::new(network_graph, Arc::clone(&logger)));
 		let latest_sync_timestamp = AtomicU32::new(latest_sync_timestamp);
-		Self::RapidGossipSync { gossip_sync, server_url, latest_sync_timestamp, logger }
+		Self::RapidGossipSync {
+			gossip_sync,
+			p2p_gossip_sync,
+			server_url,
+			latest_sync_timestamp,
+			logger,
+		}
 	}
 
 	pub fn is_rgs(&self) -> bool {
@@ -62,31 +78,42 @@ impl GossipSource {
 		}
 	}
 
+	/// Returns the gossip sync handling gossip messages received from our peers, if any.
+	pub(crate) fn p2p_gossip_sync(&self) -> Option<Arc<P2PGossipSync>> {
+		match self {
+			Self::P2PNetwork { gossip_sync } => Some(Arc::clone(gossip_sync)),
+			Self::RapidGossipSync { p2p_gossip_sync, .. } => p2p_gossip_sync.clone(),
+		}
+	}
+
 	pub(crate) fn set_gossip_verifier(
 		&self, chain_source: Arc<ChainSource>, peer_manager: Arc<PeerManager>,
 		runtime: Arc<Runtime>,
 	) {
-		match self {
-			Self::P2PNetwork { gossip_sync } => {
-				if let Some(utxo_source) = chain_source.as_utxo_source() {
-					let spawner = RuntimeSpawner::new(Arc::clone(&runtime));
-					let gossip_verifier = Arc::new(GossipVerifier::new(
-						utxo_source,
-						spawner,
-						Arc::clone(gossip_sync),
-						peer_manager,
-					));
-					gossip_sync.add_utxo_lookup(Some(gossip_verifier));
-				}
-			},
-			_ => (),
+		if let Some(gossip_sync) = self.p2p_gossip_sync() {
+			if let Some(utxo_source) = chain_source.as_utxo_source() {
+				let spawner = RuntimeSpawner::new(Arc::clone(&runtime));
+				let gossip_verifier = Arc::new(GossipVerifier::new(
+					utxo_source,
+					spawner,
+					Arc::clone(&gossip_sync),
+					peer_manager,
+				));
+				gossip_sync.add_utxo_lookup(Some(gossip_verifier));
+			}
 		}
 	}
 
 	pub async fn update_rgs_snapshot(&self) -> Result<u32, Error> {
 		match self {
 			Self::P2PNetwork { gossip_sync: _, .. } => Ok(0),
-			Self::RapidGossipSync { gossip_sync, server_url, latest_sync_timestamp, logger } => {
+			Self::RapidGossipSync {
+				gossip_sync,
+				server_url,
+				latest_sync_timestamp,
+				logger,
+				..
+			} => {
 				let query_timestamp = latest_sync_timestamp.load(Ordering::Acquire);
 				let query_url = format!("{}/{}", server_url, query_timestamp);
 
@@ -6,6 +6,9 @@
 // accordance with one or both of these licenses.
 
 //! Objects for querying the network graph.
+//!
+//! All queries operate on a snapshot of the graph taken at the time of the call, i.e., no locks
+//! are held once they return.
 
 use std::sync::Arc;
 
@@ -48,6 +51,21 @@ impl NetworkGraph {
 	pub fn node(&self, node_id: &NodeId) -> Option<NodeInfo> {
 		self.inner.read_only().nodes().get(node_id).cloned().map(|n| n.into())
 	}
+
+	/// Returns the list of channels in the graph the node with the given id is a party to.
+	pub fn list_channels_for_node(&self, node_id: &NodeId) -> Vec<u64> {
+		self.inner.read_only().nodes().get(node_id).map(|n| n.channels.clone()).unwrap_or_default()
+	}
+
+	/// Returns the number of channels in the graph.
+	pub fn total_channels(&self) -> u64 {
+		self.inner.read_only().channels().len() as u64
+	}
+
+	/// Returns the number of nodes in the graph.
+	pub fn total_nodes(&self) -> u64 {
+		self.inner.read_only().nodes().len() as u64
+	}
 }
 
 /// Details about a channel (both directions).
@@ -155,6 +173,8 @@ pub struct NodeAnnouncementInfo {
 	/// May be invalid or malicious (eg control chars),
 	/// should not be exposed to the user.
 	pub alias: String,
+	/// Protocol RGB color assigned to the node.
+	pub rgb: [u8; 3],
 	/// List of addresses on which this node is reachable
 	pub addresses: Vec<SocketAddress>,
 }
@@ -165,6 +185,7 @@ impl From<lightning::routing::gossip::NodeAnnouncementInfo> for NodeAnnouncement
 		Self {
 			last_update: value.last_update(),
 			alias: value.alias().to_string(),
+			rgb: value.rgb(),
 			addresses: value.addresses().iter().cloned().collect(),
 		}
 	}
@@ -0,0 +1,315 @@
+// This file is Copyright its original authors, visible in version control history.
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. You may not use this file except in
+// accordance with one or both of these licenses.
+
+//! Holds a [`KVStore`] wrapper encrypting all persisted values at rest.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use bitcoin::hashes::{sha256, Hash, HashEngine, Hmac, HmacEngine};
+use lightning::io::{self, Error, ErrorKind};
+use lightning::util::persist::{KVStore, KVStoreSync};
+use rand::RngCore;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN};
+
+use crate::types::DynStore;
+
+/// The prefix of values encrypted by the [`EncryptedStore`], allowing to tell them apart from
+/// plaintext values persisted before encryption was enabled. The last byte denotes the format
+/// version.
+const ENCRYPTED_VALUE_PREFIX: &[u8; 8] = b"\xffLDKENC\x01";
+
+/// The domain separator used when deriving the per-key encryption keys.
+const KEY_DERIVATION_TAG: &[u8] = b"LDK Node storage encryption";
+
+/// A [`KVStore`] wrapper that encrypts all values before handing them to the underlying store, and
+/// decrypts them again on read.
+///
+/// Values are encrypted and authenticated individually using ChaCha20-Poly1305 with a fresh
+/// random nonce. Each value is encrypted with a key derived from the encryption key and its
+/// location, i.e., its namespaces and key, which are also authenticated as associated data. This
+/// ensures values can't be swapped between locations. Namespaces and keys are left untouched.
+///
+/// Values persisted in plaintext are rejected on read. Stores persisted before encryption was
+/// enabled need to be migrated via [`encrypt_plaintext_values`] first.
+///
+/// [`encrypt_plaintext_values`]: Self::encrypt_plaintext_values
+pub(crate) struct EncryptedStore {
+	inner: Arc<DynStore>,
+	encryption_key: [u8; 32],
+}
+
+impl EncryptedStore {
+	pub(crate) fn new(inner: Arc<DynStore>, encryption_key: [u8; 32]) -> Self {
+		Self { inner, encryption_key }
+	}
+
+	/// Encrypts all plaintext values stored under the given keys in place.
+	///
+	/// Values that are already encrypted, e.g., by a previously interrupted migration, are only
+	/// checked to be decryptable with our key. Needs to be called before anything else may
+	/// concurrently write to the store, as migrating a value could otherwise overwrite a more
+	/// recent write.
+	pub(crate) fn encrypt_plaintext_values(
+		&self, keys: Vec<(String, String, String)>,
+	) -> io::Result<()> {
+		for (primary_namespace, secondary_namespace, key) in keys {
+			let buf =
+				KVStoreSync::read(&*self.inner, &primary_namespace, &secondary_namespace, &key)?;
+			if is_encrypted_value(&buf) {
+				decrypt_value(
+					&buf,
+					&self.encryption_key,
+					&primary_namespace,
+					&secondary_namespace,
+					&key,
+				)?;
+				continue;
+			}
+			let encrypted = encrypt_value(
+				buf,
+				&self.encryption_key,
+				&primary_namespace,
+				&secondary_namespace,
+				&key,
+			);
+			KVStoreSync::write(
+				&*self.inner,
+				&primary_namespace,
+				&secondary_namespace,
+				&key,
+				encrypted,
+			)?;
+		}
+		Ok(())
+	}
+}
+
+impl KVStore for EncryptedStore {
+	fn read(
+		&self, primary_namespace: &str, secondary_namespace: &str, key: &str,
+	) -> Pin<Box<dyn Future<Output = Result<Vec<u8>, io::Error>> + Send>> {
+		let fut = KVStore::read(&*self.inner, primary_namespace, secondary_namespace, key);
+		let encryption_key = self.encryption_key;
+		let primary_namespace = primary_namespace.to_string();
+		let secondary_namespace = secondary_namespace.to_string();
+		let key = key.to_string();
+		Box::pin(async move {
+			let buf = fut.await?;
+			decrypt_value(&buf, &encryption_key, &primary_namespace, &secondary_namespace, &key)
+		})
+	}
+
+	fn write(
+		&self, primary_namespace: &str, secondary_namespace: &str, key: &str, buf: Vec<u8>,
+	) -> Pin<Box<dyn Future<Output = Result<(), io::Error>> + Send>> {
+		// Note we encrypt and hand the value to the underlying store right away, preserving the
+		// order of writes.
+		let encrypted =
+			encrypt_value(buf, &self.encryption_key, primary_namespace, secondary_namespace, key);
+		KVStore::write(&*self.inner, primary_namespace, secondary_namespace, key, encrypted)
+	}
+
+	fn remove(
+		&self, primary_namespace: &str, secondary_namespace: &str, key: &str,
+	) -> Pin<Box<dyn Future<Output = Result<(), io::Error>> + Send>> {
+		KVStore::remove(&*self.inner, primary_namespace, secondary_namespace, key)
+	}
+
+	fn list(
+		&self, primary_namespace: &str, secondary_namespace: &str,
+	) -> Pin<Box<dyn Future<Output = Result<Vec<String>, io::Error>> + Send>> {
+		KVStore::list(&*self.inner, primary_namespace, secondary_namespace)
+	}
+}
+
+impl KVStoreSync for EncryptedStore {
+	fn read(
+		&self, primary_namespace: &str, secondary_namespace: &str, key: &str,
+	) -> io::Result<Vec<u8>> {
+		let buf = KVStoreSync::read(&*self.inner, primary_namespace, secondary_namespace, key)?;
+		decrypt_value(&buf, &self.encryption_key, primary_namespace, secondary_namespace, key)
+	}
+
+	fn write(
+		&self, primary_namespace: &str, secondary_namespace: &str, key: &str, buf: Vec<u8>,
+	) -> io::Result<()> {
+		let encrypted =
+			encrypt_value(buf, &self.encryption_key, primary_namespace, secondary_namespace, key);
+		KVStoreSync::write(&*self.inner, primary_namespace, secondary_namespace, key, encrypted)
+	}
+
+	fn remove(
+		&self, primary_namespace: &str, secondary_namespace: &str, key: &str,
+	) -> io::Result<()> {
+		KVStoreSync::remove(&*self.inner, primary_namespace, secondary_namespace, key)
+	}
+
+	fn list(&self, primary_namespace: &str, secondary_namespace: &str) -> io::Result<Vec<String>> {
+		KVStoreSync::list(&*self.inner, primary_namespace, secondary_namespace)
+	}
+}
+
+/// Returns whether the given value was encrypted by an [`EncryptedStore`].
+pub(crate) fn is_encrypted_value(buf: &[u8]) -> bool {
+	buf.starts_with(ENCRYPTED_VALUE_PREFIX)
+}
+
+// Encodes the location of a value, which we use as associated data and to derive the key it's
+// encrypted with.
+fn encode_location(primary_namespace: &str, secondary_namespace: &str, key: &str) -> Vec<u8> {
+	let mut location = Vec::new();
+	for part in [primary_namespace, secondary_namespace, key] {
+		location.extend_from_slice(&(part.len() as u32).to_be_bytes());
+		location.extend_from_slice(part.as_bytes());
+	}
+	location
+}
+
+fn derive_value_key(encryption_key: &[u8; 32], location: &[u8]) -> LessSafeKey {
+	let mut hmac_engine = HmacEngine::<sha256::Hash>::new(encryption_key);
+	hmac_engine.input(KEY_DERIVATION_TAG);
+	hmac_engine.input(location);
+	let value_key = Hmac::from_engine(hmac_engine).to_byte_array();
+	let unbound_key = UnboundKey::new(&CHACHA20_POLY1305, &value_key)
+		.expect("ChaCha20-Poly1305 keys are always 32 bytes long");
+	LessSafeKey::new(unbound_key)
+}
+
+fn encrypt_value(
+	mut buf: Vec<u8>, encryption_key: &[u8; 32], primary_namespace: &str,
+	secondary_namespace: &str, key: &str,
+) -> Vec<u8> {
+	let location = encode_location(primary_namespace, secondary_namespace, key);
+	let value_key = derive_value_key(encryption_key, &location);
+
+	let mut nonce_bytes = [0u8; NONCE_LEN];
+	rand::thread_rng().fill_bytes(&mut nonce_bytes);
+	let nonce = Nonce::assume_unique_for_key(nonce_bytes);
+	value_key
+		.seal_in_place_append_tag(nonce, Aad::from(&location), &mut buf)
+		.expect("Values are always small enough to be encrypted");
+
+	let mut encrypted = Vec::with_capacity(ENCRYPTED_VALUE_PREFIX.len() + NONCE_LEN + buf.len());
+	encrypted.extend_from_slice(ENCRYPTED_VALUE_PREFIX);
+	encrypted.extend_from_slice(&nonce_bytes);
+	encrypted.extend_from_slice(&buf);
+	encrypted
+}
+
+// Returns an error if the given value isn't encrypted or couldn't be decrypted, e.g., as it was
+// encrypted with a different key or was moved from another location.
+fn decrypt_value(
+	buf: &[u8], encryption_key: &[u8; 32], primary_namespace: &str, secondary_namespace: &str,
+	key: &str,
+) -> io::Result<Vec<u8>> {
+	let encrypted = buf.strip_prefix(ENCRYPTED_VALUE_PREFIX).ok_or_else(|| {
+		Error::new(
+			ErrorKind::InvalidData,
+			"Refusing to read unencrypted value from encrypted store",
+		)
+	})?;
+
+	let decryption_error = || {
+		Error::new(
+			ErrorKind::InvalidData,
+			"Failed to decrypt stored value, is the storage encryption key correct?",
+		)
+	};
+	if encrypted.len() < NONCE_LEN {
+		return Err(decryption_error());
+	}
+	let (nonce_bytes, ciphertext) = encrypted.split_at(NONCE_LEN);
+	let nonce = Nonce::try_assume_unique_for_key(nonce_bytes).map_err(|_| decryption_error())?;
+
+	let location = encode_location(primary_namespace, secondary_namespace, key);
+	let value_key = derive_value_key(encryption_key, &location);
+	let mut in_out = ciphertext.to_vec();
+	let plaintext_len = value_key
+		.open_in_place(nonce, Aad::from(&location), &mut in_out)
+		.map_err(|_| decryption_error())?
+		.len();
+	in_out.truncate(plaintext_len);
+	Ok(in_out)
+}
+
+#[cfg(test)]
+mod tests {
+	use lightning::util::test_utils::TestStore;
+
+	use super::*;
+
+	#[test]
+	fn encrypted_store_round_trip() {
+		let inner: Arc<DynStore> = Arc::new(TestStore::new(false));
+		let store = EncryptedStore::new(Arc::clone(&inner), [42u8; 32]);
+
+		// Values are only persisted encrypted.
+		KVStoreSync::write(&store, "ns", "", "new", vec![6; 100]).unwrap();
+		let raw = KVStoreSync::read(&*inner, "ns", "", "new").unwrap();
+		assert!(is_encrypted_value(&raw));
+		assert!(!raw.windows(100).any(|w| w == &[6; 100][..]));
+		assert_eq!(KVStoreSync::read(&store, "ns", "", "new").unwrap(), vec![6; 100]);
+
+		// Reading with the wrong key fails rather than returning garbage.
+		let wrong_store = EncryptedStore::new(Arc::clone(&inner), [23u8; 32]);
+		let err = KVStoreSync::read(&wrong_store, "ns", "", "new").unwrap_err();
+		assert_eq!(err.kind(), ErrorKind::InvalidData);
+	}
+
+	#[test]
+	fn encrypted_store_rejects_plaintext_and_moved_values() {
+		let inner: Arc<DynStore> = Arc::new(TestStore::new(false));
+		let store = EncryptedStore::new(Arc::clone(&inner), [42u8; 32]);
+
+		// Values injected in plaintext are rejected.
+		KVStoreSync::write(&*inner, "ns", "", "injected", vec![1, 2, 3]).unwrap();
+		let err = KVStoreSync::read(&store, "ns", "", "injected").unwrap_err();
+		assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+		// Encrypted values copied to another location are rejected.
+		KVStoreSync::write(&store, "ns", "", "a", vec![4, 5]).unwrap();
+		let raw = KVStoreSync::read(&*inner, "ns", "", "a").unwrap();
+		KVStoreSync::write(&*inner, "ns", "", "b", raw.clone()).unwrap();
+		KVStoreSync::write(&*inner, "other_ns", "", "a", raw).unwrap();
+		let err = KVStoreSync::read(&store, "ns", "", "b").unwrap_err();
+		assert_eq!(err.kind(), ErrorKind::InvalidData);
+		let err = KVStoreSync::read(&store, "other_ns", "", "a").unwrap_err();
+		assert_eq!(err.kind(), ErrorKind::InvalidData);
+	}
+
+	#[test]
+	fn encrypted_store_migrates_plaintext_values() {
+		let inner: Arc<DynStore> = Arc::new(TestStore::new(false));
+		KVStoreSync::write(&*inner, "ns", "", "legacy", vec![1, 2, 3]).unwrap();
+		KVStoreSync::write(&*inner, "ns", "sub", "legacy", vec![4, 5]).unwrap();
+
+		let store = EncryptedStore::new(Arc::clone(&inner), [42u8; 32]);
+		KVStoreSync::write(&store, "ns", "", "new", vec![6]).unwrap();
+		let keys = vec![
+			("ns".to_string(), "".to_string(), "legacy".to_string()),
+			("ns".to_string(), "sub".to_string(), "legacy".to_string()),
+			("ns".to_string(), "".to_string(), "new".to_string()),
+		];
+		store.encrypt_plaintext_values(keys.clone()).unwrap();
+
+		for (primary_namespace, secondary_namespace, key) in &keys {
+			let raw =
+				KVStoreSync::read(&*inner, primary_namespace, secondary_namespace, key).unwrap();
+			assert!(is_encrypted_value(&raw));
+		}
+		assert_eq!(KVStoreSync::read(&store, "ns", "", "legacy").unwrap(), vec![1, 2, 3]);
+		assert_eq!(KVStoreSync::read(&store, "ns", "sub", "legacy").unwrap(), vec![4, 5]);
+		assert_eq!(KVStoreSync::read(&store, "ns", "", "new").unwrap(), vec![6]);
+
+		// Resuming the migration with a different key fails.
+		let wrong_store = EncryptedStore::new(Arc::clone(&inner), [23u8; 32]);
+		let err = wrong_store.encrypt_plaintext_values(keys).unwrap_err();
+		assert_eq!(err.kind(), ErrorKind::InvalidData);
+	}
+}
@@ -7,6 +7,7 @@
 
 //! Objects and traits for data persistence.
 
+pub(crate) mod encrypted_store;
 pub mod sqlite_store;
 #[cfg(test)]
 pub(crate) mod test_utils;
@@ -23,15 +24,79 @@ pub(crate) const PEER_INFO_PERSISTENCE_PRIMARY_NAMESPACE: &str = "";
 pub(crate) const PEER_INFO_PERSISTENCE_SECONDARY_NAMESPACE: &str = "";
 pub(crate) const PEER_INFO_PERSISTENCE_KEY: &str = "peers";
 
+/// The local channel and peer labels will be persisted under this key.
+pub(crate) const LABEL_PERSISTENCE_PRIMARY_NAMESPACE: &str = "";
+pub(crate) const LABEL_PERSISTENCE_SECONDARY_NAMESPACE: &str = "";
+pub(crate) const LABEL_PERSISTENCE_KEY: &str = "labels";
+
 /// The payment information will be persisted under this prefix.
 pub(crate) const PAYMENT_INFO_PERSISTENCE_PRIMARY_NAMESPACE: &str = "payments";
 pub(crate) const PAYMENT_INFO_PERSISTENCE_SECONDARY_NAMESPACE: &str = "";
 
+/// The details of closed channels will be persisted under this prefix.
+pub(crate) const CLOSED_CHANNEL_INFO_PERSISTENCE_PRIMARY_NAMESPACE: &str = "closed_channels";
+pub(crate) const CLOSED_CHANNEL_INFO_PERSISTENCE_SECONDARY_NAMESPACE: &str = "";
+
+/// The idempotency keys of outbound payments will be persisted under this prefix.
+pub(crate) const PAYMENT_IDEMPOTENCY_KEY_PERSISTENCE_PRIMARY_NAMESPACE: &str =
+	"payment_idempotency_keys";
+pub(crate) const PAYMENT_IDEMPOTENCY_KEY_PERSISTENCE_SECONDARY_NAMESPACE: &str = "";
+
+/// The schedules of recurring payments will be persisted under this prefix.
+pub(crate) const RECURRING_PAYMENT_INFO_PERSISTENCE_PRIMARY_NAMESPACE: &str = "recurring_payments";
+pub(crate) const RECURRING_PAYMENT_INFO_PERSISTENCE_SECONDARY_NAMESPACE: &str = "";
+
+/// The state of our watchtower client will be persisted under this key.
+pub(crate) const WATCHTOWER_CLIENT_PERSISTENCE_PRIMARY_NAMESPACE: &str = "";
+pub(crate) const WATCHTOWER_CLIENT_PERSISTENCE_SECONDARY_NAMESPACE: &str = "";
+pub(crate) const WATCHTOWER_CLIENT_PERSISTENCE_KEY: &str = "watchtower_client";
+
+/// The appointments queued for delivery to our watchtower will be persisted under this prefix.
+pub(crate) const WATCHTOWER_APPOINTMENT_PERSISTENCE_PRIMARY_NAMESPACE: &str =
+	"watchtower_appointments";
+pub(crate) const WATCHTOWER_APPOINTMENT_PERSISTENCE_SECONDARY_NAMESPACE: &str = "";
+
+/// A marker allowing to check the storage encryption key will be persisted under this key.
+pub(crate) const STORAGE_ENCRYPTION_CHECK_PRIMARY_NAMESPACE: &str = "";
+pub(crate) const STORAGE_ENCRYPTION_CHECK_SECONDARY_NAMESPACE: &str = "";
+pub(crate) const STORAGE_ENCRYPTION_CHECK_KEY: &str = "storage_encryption_check";
+
 /// The node metrics will be persisted under this key.
 pub(crate) const NODE_METRICS_PRIMARY_NAMESPACE: &str = "";
 pub(crate) const NODE_METRICS_SECONDARY_NAMESPACE: &str = "";
 pub(crate) const NODE_METRICS_KEY: &str = "node_metrics";
 
+/// The peer connection policy will be persisted under this key.
+pub(crate) const PEER_CONNECTION_POLICY_PRIMARY_NAMESPACE: &str = "";
+pub(crate) const PEER_CONNECTION_POLICY_SECONDARY_NAMESPACE: &str = "";
+pub(crate) const PEER_CONNECTION_POLICY_KEY: &str = "peer_connection_policy";
+
+/// The sweeps of our spendable outputs we track will be persisted under this key.
+pub(crate) const SWEEP_TRACKER_PERSISTENCE_PRIMARY_NAMESPACE: &str = "";
+pub(crate) const SWEEP_TRACKER_PERSISTENCE_SECONDARY_NAMESPACE: &str = "";
+pub(crate) const SWEEP_TRACKER_PERSISTENCE_KEY: &str = "sweep_tracker";
+
+/// The node alias set via `Node::set_node_alias` will be persisted under this key.
+pub(crate) const NODE_ALIAS_PRIMARY_NAMESPACE: &str = "";
+pub(crate) const NODE_ALIAS_SECONDARY_NAMESPACE: &str = "";
+pub(crate) const NODE_ALIAS_KEY: &str = "node_alias";
+
+/// The default BOLT12 offer will be persisted under this key.
+pub(crate) const DEFAULT_OFFER_PRIMARY_NAMESPACE: &str = "";
+pub(crate) const DEFAULT_OFFER_SECONDARY_NAMESPACE: &str = "";
+pub(crate) const DEFAULT_OFFER_KEY: &str = "default_offer";
+
+/// The JIT channels our LSPS2 service is currently opening will be persisted under this key.
+pub(crate) const LSPS2_SERVICE_PENDING_JIT_CHANNELS_PRIMARY_NAMESPACE: &str = "";
+pub(crate) const LSPS2_SERVICE_PENDING_JIT_CHANNELS_SECONDARY_NAMESPACE: &str = "";
+pub(crate) const LSPS2_SERVICE_PENDING_JIT_CHANNELS_KEY: &str =
+	"lsps2_service_pending_jit_channels";
+
+/// The private key of our onion service will be persisted under this key.
+pub(crate) const ONION_SERVICE_KEY_PERSISTENCE_PRIMARY_NAMESPACE: &str = "";
+pub(crate) const ONION_SERVICE_KEY_PERSISTENCE_SECONDARY_NAMESPACE: &str = "";
+pub(crate) const ONION_SERVICE_KEY_PERSISTENCE_KEY: &str = "onion_service_key";
+
 /// The BDK wallet's [`ChangeSet::descriptor`] will be persisted under this key.
 ///
 /// [`ChangeSet::descriptor`]: bdk_wallet::ChangeSet::descriptor
@@ -16,7 +16,8 @@ use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 
 use lightning::io;
-use lightning::util::persist::{KVStore, KVStoreSync};
+use lightning::util::persist::{KVStore, KVStoreSync, MigratableKVStore};
+use lightning_persister::fs_store::FilesystemStore;
 use lightning_types::string::PrintableString;
 use rusqlite::{named_params, Connection};
 
@@ -87,6 +88,34 @@ impl SqliteStore {
 	pub fn get_data_dir(&self) -> PathBuf {
 		self.inner.data_dir.clone()
 	}
+
+	/// Copies all data from the [`FilesystemStore`] located at the given `fs_store_dir` into this
+	/// store.
+	///
+	/// This can be used to move a node previously built via [`Builder::build_with_fs_store`],
+	/// which keeps its data in the `fs_store` sub-directory of the storage directory, over to the
+	/// default [`Builder::build`].
+	///
+	/// All entries are written in a single transaction, i.e., either all or none of the data will
+	/// be migrated. This assumes this store is empty, as any data present under the migrated keys
+	/// will be overwritten. The source store must not be modified while the migration is in
+	/// progress, i.e., the node must not be running.
+	///
+	/// [`Builder::build_with_fs_store`]: crate::Builder::build_with_fs_store
+	/// [`Builder::build`]: crate::Builder::build
+	pub fn migrate_from_filesystem(&self, fs_store_dir: PathBuf) -> io::Result<()> {
+		let fs_store = FilesystemStore::new(fs_store_dir);
+		let keys = fs_store.list_all_keys()?;
+
+		let mut entries = Vec::with_capacity(keys.len());
+		for (primary_namespace, secondary_namespace, key) in keys {
+			let value =
+				KVStoreSync::read(&fs_store, &primary_namespace, &secondary_namespace, &key)?;
+			entries.push((primary_namespace, secondary_namespace, key, value));
+		}
+
+		self.inner.write_all_internal(entries)
+	}
 }
 
 impl KVStore for SqliteStore {
@@ -224,6 +253,12 @@ impl KVStoreSync for SqliteStore {
 	}
 }
 
+impl MigratableKVStore for SqliteStore {
+	fn list_all_keys(&self) -> io::Result<Vec<(String, String, String)>> {
+		self.inner.list_all_keys_internal()
+	}
+}
+
 struct SqliteStoreInner {
 	connection: Arc<Mutex<Connection>>,
 	data_dir: PathBuf,
@@ -255,6 +290,16 @@ impl SqliteStoreInner {
 			io::Error::new(io::ErrorKind::Other, msg)
 		})?;
 
+		// Use write-ahead logging, which commits by appending to the log rather than rewriting
+		// the database file. Note that all our operations are still serialized via the single
+		// connection, i.e., this doesn't allow for concurrent access.
+		connection
+			.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))
+			.map_err(|e| {
+				let msg = format!("Failed to set PRAGMA journal_mode: {}", e);
+				io::Error::new(io::ErrorKind::Other, msg)
+			})?;
+
 		let sql = format!("SELECT user_version FROM pragma_user_version");
 		let version_res: u16 = connection.query_row(&sql, [], |row| row.get(0)).unwrap();
 
@@ -398,6 +443,61 @@ impl SqliteStoreInner {
 		})
 	}
 
+	fn write_all_internal(
+		&self, entries: Vec<(String, String, String, Vec<u8>)>,
+	) -> io::Result<()> {
+		for (primary_namespace, secondary_namespace, key, _) in &entries {
+			check_namespace_key_validity(
+				primary_namespace,
+				secondary_namespace,
+				Some(key),
+				"write",
+			)?;
+		}
+
+		let mut locked_conn = self.connection.lock().unwrap();
+		let tx = locked_conn.transaction().map_err(|e| {
+			let msg = format!("Failed to start transaction: {}", e);
+			io::Error::new(io::ErrorKind::Other, msg)
+		})?;
+
+		{
+			let sql = format!(
+				"INSERT OR REPLACE INTO {} (primary_namespace, secondary_namespace, key, value) VALUES (:primary_namespace, :secondary_namespace, :key, :value);",
+				self.kv_table_name
+			);
+
+			let mut stmt = tx.prepare_cached(&sql).map_err(|e| {
+				let msg = format!("Failed to prepare statement: {}", e);
+				io::Error::new(io::ErrorKind::Other, msg)
+			})?;
+
+			for (primary_namespace, secondary_namespace, key, value) in entries {
+				stmt.execute(named_params! {
+					":primary_namespace": primary_namespace,
+					":secondary_namespace": secondary_namespace,
+					":key": key,
+					":value": value,
+				})
+				.map_err(|e| {
+					let msg = format!(
+						"Failed to write to key {}/{}/{}: {}",
+						PrintableString(&primary_namespace),
+						PrintableString(&secondary_namespace),
+						PrintableString(&key),
+						e
+					);
+					io::Error::new(io::ErrorKind::Other, msg)
+				})?;
+			}
+		}
+
+		tx.commit().map_err(|e| {
+			let msg = format!("Failed to commit transaction: {}", e);
+			io::Error::new(io::ErrorKind::Other, msg)
+		})
+	}
+
 	fn remove_internal(
 		&self, inner_lock_ref: Arc<Mutex<u64>>, locking_key: String, version: u64,
 		primary_namespace: &str, secondary_namespace: &str, key: &str,
@@ -474,6 +574,36 @@ impl SqliteStoreInner {
 		Ok(keys)
 	}
 
+	fn list_all_keys_internal(&self) -> io::Result<Vec<(String, String, String)>> {
+		let locked_conn = self.connection.lock().unwrap();
+
+		let sql = format!(
+			"SELECT primary_namespace, secondary_namespace, key FROM {}",
+			self.kv_table_name
+		);
+		let mut stmt = locked_conn.prepare_cached(&sql).map_err(|e| {
+			let msg = format!("Failed to prepare statement: {}", e);
+			io::Error::new(io::ErrorKind::Other, msg)
+		})?;
+
+		let mut keys = Vec::new();
+
+		let rows_iter =
+			stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?))).map_err(|e| {
+				let msg = format!("Failed to retrieve queried rows: {}", e);
+				io::Error::new(io::ErrorKind::Other, msg)
+			})?;
+
+		for k in rows_iter {
+			keys.push(k.map_err(|e| {
+				let msg = format!("Failed to retrieve queried rows: {}", e);
+				io::Error::new(io::ErrorKind::Other, msg)
+			})?);
+		}
+
+		Ok(keys)
+	}
+
 	fn execute_locked_write<F: FnOnce() -> Result<(), lightning::io::Error>>(
 		&self, inner_lock_ref: Arc<Mutex<u64>>, locking_key: String, version: u64, callback: F,
 	) -> Result<(), lightning::io::Error> {
@@ -562,6 +692,100 @@ mod tests {
 		.unwrap();
 		do_test_store(&store_0, &store_1)
 	}
+
+	#[test]
+	fn migrate_from_filesystem_store() {
+		let mut fs_store_path = random_storage_path();
+		fs_store_path.push("fs_store");
+		let fs_store = FilesystemStore::new(fs_store_path.clone());
+		KVStoreSync::write(&fs_store, "primary", "secondary", "key_0", vec![0u8; 32]).unwrap();
+		KVStoreSync::write(&fs_store, "primary", "", "key_1", vec![1u8; 32]).unwrap();
+		KVStoreSync::write(&fs_store, "", "", "key_2", vec![2u8; 32]).unwrap();
+
+		let mut temp_path = random_storage_path();
+		temp_path.push("migrate_from_filesystem_store");
+		let store = SqliteStore::new(
+			temp_path,
+			Some("test_db".to_string()),
+			Some("test_table".to_string()),
+		)
+		.unwrap();
+		store.migrate_from_filesystem(fs_store_path.clone()).unwrap();
+
+		assert_eq!(KVStoreSync::read(&store, "primary", "secondary", "key_0").unwrap(), [0u8; 32]);
+		assert_eq!(KVStoreSync::read(&store, "primary", "", "key_1").unwrap(), [1u8; 32]);
+		assert_eq!(KVStoreSync::read(&store, "", "", "key_2").unwrap(), [2u8; 32]);
+		assert_eq!(KVStoreSync::list(&store, "primary", "").unwrap(), vec!["key_1".to_string()]);
+
+		let mut all_keys = store.list_all_keys().unwrap();
+		all_keys.sort();
+		assert_eq!(
+			all_keys,
+			vec![
+				("".to_string(), "".to_string(), "key_2".to_string()),
+				("primary".to_string(), "".to_string(), "key_1".to_string()),
+				("primary".to_string(), "secondary".to_string(), "key_0".to_string()),
+			]
+		);
+
+		fs::remove_dir_all(fs_store_path).unwrap();
+	}
+
+	#[test]
+	fn concurrent_reads_and_writes() {
+		let mut temp_path = random_storage_path();
+		temp_path.push("concurrent_reads_and_writes");
+		let store = Arc::new(
+			SqliteStore::new(
+				temp_path,
+				Some("test_db".to_string()),
+				Some("test_table".to_string()),
+			)
+			.unwrap(),
+		);
+
+		let num_threads = 4;
+		let num_iterations = 100u64;
+		let mut handles = Vec::new();
+		for i in 0..num_threads {
+			// Writers continuously update their own key, while readers check on all of them.
+			let writer_store = Arc::clone(&store);
+			handles.push(std::thread::spawn(move || {
+				let key = format!("key_{}", i);
+				for j in 0..num_iterations {
+					KVStoreSync::write(
+						&*writer_store,
+						"primary",
+						"",
+						&key,
+						j.to_be_bytes().to_vec(),
+					)
+					.unwrap();
+				}
+			}));
+
+			let reader_store = Arc::clone(&store);
+			handles.push(std::thread::spawn(move || {
+				for j in 0..num_iterations {
+					let key = format!("key_{}", j % num_threads);
+					match KVStoreSync::read(&*reader_store, "primary", "", &key) {
+						Ok(value) => assert_eq!(value.len(), 8),
+						Err(e) => assert_eq!(e.kind(), io::ErrorKind::NotFound),
+					}
+					KVStoreSync::list(&*reader_store, "primary", "").unwrap();
+				}
+			}));
+		}
+
+		for handle in handles {
+			handle.join().unwrap();
+		}
+
+		for i in 0..num_threads {
+			let value = KVStoreSync::read(&*store, "primary", "", &format!("key_{}", i)).unwrap();
+			assert_eq!(value, (num_iterations - 1).to_be_bytes());
+		}
+	}
 }
 
 #[cfg(ldk_bench)]
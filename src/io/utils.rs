@@ -5,6 +5,7 @@
 // http://opensource.org/licenses/MIT>, at your option. You may not use this file except in
 // accordance with one or both of these licenses.
 
+use std::collections::BTreeMap;
 use std::fs;
 use std::io::Write;
 use std::ops::Deref;
@@ -21,7 +22,8 @@ use bip39::Mnemonic;
 use bitcoin::Network;
 use lightning::io::Cursor;
 use lightning::ln::msgs::DecodeError;
-use lightning::routing::gossip::NetworkGraph;
+use lightning::offers::offer::Offer;
+use lightning::routing::gossip::{NetworkGraph, NodeAlias};
 use lightning::routing::scoring::{
 	ChannelLiquidities, ProbabilisticScorer, ProbabilisticScoringDecayParameters,
 };
@@ -39,20 +41,26 @@ use rand::{thread_rng, RngCore};
 
 use super::*;
 use crate::chain::ChainSource;
-use crate::config::WALLET_KEYS_SEED_LEN;
+use crate::closed_channel::ClosedChannelDetails;
+use crate::config::{PeerConnectionPolicy, WALLET_KEYS_SEED_LEN};
 use crate::fee_estimator::OnchainFeeEstimator;
 use crate::io::{
 	NODE_METRICS_KEY, NODE_METRICS_PRIMARY_NAMESPACE, NODE_METRICS_SECONDARY_NAMESPACE,
 };
+use crate::label_store::LabelStore;
 use crate::logger::{log_error, LdkLogger, Logger};
+use crate::payment::idempotency::IdempotencyKeyEntry;
+use crate::payment::recurring::RecurringPaymentSchedule;
 use crate::peer_store::PeerStore;
-use crate::types::{Broadcaster, DynStore, KeysManager, Sweeper};
+use crate::types::{Broadcaster, DynStore, KeysManager, Scorer, Sweeper, WordCount};
 use crate::wallet::ser::{ChangeSetDeserWrapper, ChangeSetSerWrapper};
 use crate::{Error, EventQueue, NodeMetrics, PaymentDetails};
 
 pub const EXTERNAL_PATHFINDING_SCORES_CACHE_KEY: &str = "external_pathfinding_scores_cache";
 
-/// Generates a random [BIP 39] mnemonic.
+/// Generates a random [BIP 39] mnemonic with the given number of words.
+///
+/// If no `word_count` is given, a 24-word mnemonic is generated.
 ///
 /// The result may be used to initialize the [`Node`] entropy, i.e., can be given to
 /// [`Builder::set_entropy_bip39_mnemonic`].
@@ -60,13 +68,28 @@ pub const EXTERNAL_PATHFINDING_SCORES_CACHE_KEY: &str = "external_pathfinding_sc
 /// [BIP 39]: https://github.com/bitcoin/bips/blob/master/bip-0039.mediawiki
 /// [`Node`]: crate::Node
 /// [`Builder::set_entropy_bip39_mnemonic`]: crate::Builder::set_entropy_bip39_mnemonic
-pub fn generate_entropy_mnemonic() -> Mnemonic {
-	// bip39::Mnemonic supports 256 bit entropy max
-	let mut entropy = [0; 32];
+pub fn generate_entropy_mnemonic(word_count: Option<WordCount>) -> Mnemonic {
+	let word_count = word_count.unwrap_or(WordCount::Words24);
+	let mut entropy = vec![0; word_count.entropy_len()];
 	thread_rng().fill_bytes(&mut entropy);
 	Mnemonic::from_entropy(&entropy).unwrap()
 }
 
+/// Parses a [BIP 39] mnemonic from the given phrase of 12 to 24 words.
+///
+/// Returns [`Error::InvalidMnemonic`] if the phrase contains unknown words, has an unsupported
+/// number of words, or if its checksum doesn't match.
+///
+/// The result may be used to initialize the [`Node`] entropy, i.e., can be given to
+/// [`Builder::set_entropy_bip39_mnemonic`].
+///
+/// [BIP 39]: https://github.com/bitcoin/bips/blob/master/bip-0039.mediawiki
+/// [`Node`]: crate::Node
+/// [`Builder::set_entropy_bip39_mnemonic`]: crate::Builder::set_entropy_bip39_mnemonic
+pub fn parse_entropy_mnemonic(phrase: &str) -> Result<Mnemonic, Error> {
+	Mnemonic::parse_normalized(phrase).map_err(|_| Error::InvalidMnemonic)
+}
+
 pub(crate) fn read_or_generate_seed_file<L: Deref>(
 	keys_seed_path: &str, logger: L,
 ) -> std::io::Result<[u8; WALLET_KEYS_SEED_LEN]>
@@ -168,6 +191,33 @@ where
 	})
 }
 
+/// Persist the local state of the given scorer to the store.
+pub(crate) fn write_scorer<L: Deref>(
+	scorer: &Scorer, kv_store: Arc<DynStore>, logger: L,
+) -> Result<(), Error>
+where
+	L::Target: LdkLogger,
+{
+	KVStoreSync::write(
+		&*kv_store,
+		SCORER_PERSISTENCE_PRIMARY_NAMESPACE,
+		SCORER_PERSISTENCE_SECONDARY_NAMESPACE,
+		SCORER_PERSISTENCE_KEY,
+		scorer.encode(),
+	)
+	.map_err(|e| {
+		log_error!(
+			logger,
+			"Writing data to key {}/{}/{} failed due to: {}",
+			SCORER_PERSISTENCE_PRIMARY_NAMESPACE,
+			SCORER_PERSISTENCE_SECONDARY_NAMESPACE,
+			SCORER_PERSISTENCE_KEY,
+			e
+		);
+		Error::PersistenceFailed
+	})
+}
+
 /// Read previously persisted external pathfinding scores from the cache.
 pub(crate) fn read_external_pathfinding_scores_from_cache<L: Deref>(
 	kv_store: Arc<DynStore>, logger: L,
@@ -253,6 +303,25 @@ where
 	})
 }
 
+/// Read previously persisted local channel and peer labels from the store.
+pub(crate) fn read_labels<L: Deref + Clone>(
+	kv_store: Arc<DynStore>, logger: L,
+) -> Result<LabelStore<L>, std::io::Error>
+where
+	L::Target: LdkLogger,
+{
+	let mut reader = Cursor::new(KVStoreSync::read(
+		&*kv_store,
+		LABEL_PERSISTENCE_PRIMARY_NAMESPACE,
+		LABEL_PERSISTENCE_SECONDARY_NAMESPACE,
+		LABEL_PERSISTENCE_KEY,
+	)?);
+	LabelStore::read(&mut reader, (kv_store, logger.clone())).map_err(|e| {
+		log_error!(logger, "Failed to deserialize label store: {}", e);
+		std::io::Error::new(std::io::ErrorKind::InvalidData, "Failed to deserialize LabelStore")
+	})
+}
+
 /// Read previously persisted payments information from the store.
 pub(crate) fn read_payments<L: Deref>(
 	kv_store: Arc<DynStore>, logger: L,
@@ -285,6 +354,102 @@ where
 	Ok(res)
 }
 
+/// Read previously persisted closed channel information from the store.
+pub(crate) fn read_closed_channels<L: Deref>(
+	kv_store: Arc<DynStore>, logger: L,
+) -> Result<Vec<ClosedChannelDetails>, std::io::Error>
+where
+	L::Target: LdkLogger,
+{
+	let mut res = Vec::new();
+
+	for stored_key in KVStoreSync::list(
+		&*kv_store,
+		CLOSED_CHANNEL_INFO_PERSISTENCE_PRIMARY_NAMESPACE,
+		CLOSED_CHANNEL_INFO_PERSISTENCE_SECONDARY_NAMESPACE,
+	)? {
+		let mut reader = Cursor::new(KVStoreSync::read(
+			&*kv_store,
+			CLOSED_CHANNEL_INFO_PERSISTENCE_PRIMARY_NAMESPACE,
+			CLOSED_CHANNEL_INFO_PERSISTENCE_SECONDARY_NAMESPACE,
+			&stored_key,
+		)?);
+		let closed_channel = ClosedChannelDetails::read(&mut reader).map_err(|e| {
+			log_error!(logger, "Failed to deserialize ClosedChannelDetails: {}", e);
+			std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				"Failed to deserialize ClosedChannelDetails",
+			)
+		})?;
+		res.push(closed_channel);
+	}
+	Ok(res)
+}
+
+/// Read previously persisted recurring payment schedules from the store.
+pub(crate) fn read_recurring_payments<L: Deref>(
+	kv_store: Arc<DynStore>, logger: L,
+) -> Result<Vec<RecurringPaymentSchedule>, std::io::Error>
+where
+	L::Target: LdkLogger,
+{
+	let mut res = Vec::new();
+
+	for stored_key in KVStoreSync::list(
+		&*kv_store,
+		RECURRING_PAYMENT_INFO_PERSISTENCE_PRIMARY_NAMESPACE,
+		RECURRING_PAYMENT_INFO_PERSISTENCE_SECONDARY_NAMESPACE,
+	)? {
+		let mut reader = Cursor::new(KVStoreSync::read(
+			&*kv_store,
+			RECURRING_PAYMENT_INFO_PERSISTENCE_PRIMARY_NAMESPACE,
+			RECURRING_PAYMENT_INFO_PERSISTENCE_SECONDARY_NAMESPACE,
+			&stored_key,
+		)?);
+		let schedule = RecurringPaymentSchedule::read(&mut reader).map_err(|e| {
+			log_error!(logger, "Failed to deserialize RecurringPaymentSchedule: {}", e);
+			std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				"Failed to deserialize RecurringPaymentSchedule",
+			)
+		})?;
+		res.push(schedule);
+	}
+	Ok(res)
+}
+
+/// Read previously persisted payment idempotency keys from the store.
+pub(crate) fn read_payment_idempotency_keys<L: Deref>(
+	kv_store: Arc<DynStore>, logger: L,
+) -> Result<Vec<IdempotencyKeyEntry>, std::io::Error>
+where
+	L::Target: LdkLogger,
+{
+	let mut res = Vec::new();
+
+	for stored_key in KVStoreSync::list(
+		&*kv_store,
+		PAYMENT_IDEMPOTENCY_KEY_PERSISTENCE_PRIMARY_NAMESPACE,
+		PAYMENT_IDEMPOTENCY_KEY_PERSISTENCE_SECONDARY_NAMESPACE,
+	)? {
+		let mut reader = Cursor::new(KVStoreSync::read(
+			&*kv_store,
+			PAYMENT_IDEMPOTENCY_KEY_PERSISTENCE_PRIMARY_NAMESPACE,
+			PAYMENT_IDEMPOTENCY_KEY_PERSISTENCE_SECONDARY_NAMESPACE,
+			&stored_key,
+		)?);
+		let entry = IdempotencyKeyEntry::read(&mut reader).map_err(|e| {
+			log_error!(logger, "Failed to deserialize IdempotencyKeyEntry: {}", e);
+			std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				"Failed to deserialize IdempotencyKeyEntry",
+			)
+		})?;
+		res.push(entry);
+	}
+	Ok(res)
+}
+
 /// Read `OutputSweeper` state from the store.
 pub(crate) fn read_output_sweeper(
 	broadcaster: Arc<Broadcaster>, fee_estimator: Arc<OnchainFeeEstimator>,
@@ -358,6 +523,194 @@ where
 	})
 }
 
+pub(crate) fn read_peer_connection_policy<L: Deref>(
+	kv_store: Arc<DynStore>, logger: L,
+) -> Result<PeerConnectionPolicy, std::io::Error>
+where
+	L::Target: LdkLogger,
+{
+	let mut reader = Cursor::new(KVStoreSync::read(
+		&*kv_store,
+		PEER_CONNECTION_POLICY_PRIMARY_NAMESPACE,
+		PEER_CONNECTION_POLICY_SECONDARY_NAMESPACE,
+		PEER_CONNECTION_POLICY_KEY,
+	)?);
+	PeerConnectionPolicy::read(&mut reader).map_err(|e| {
+		log_error!(logger, "Failed to deserialize PeerConnectionPolicy: {}", e);
+		std::io::Error::new(
+			std::io::ErrorKind::InvalidData,
+			"Failed to deserialize PeerConnectionPolicy",
+		)
+	})
+}
+
+pub(crate) fn write_peer_connection_policy<L: Deref>(
+	policy: &PeerConnectionPolicy, kv_store: Arc<DynStore>, logger: L,
+) -> Result<(), Error>
+where
+	L::Target: LdkLogger,
+{
+	let data = policy.encode();
+	KVStoreSync::write(
+		&*kv_store,
+		PEER_CONNECTION_POLICY_PRIMARY_NAMESPACE,
+		PEER_CONNECTION_POLICY_SECONDARY_NAMESPACE,
+		PEER_CONNECTION_POLICY_KEY,
+		data,
+	)
+	.map_err(|e| {
+		log_error!(
+			logger,
+			"Writing data to key {}/{}/{} failed due to: {}",
+			PEER_CONNECTION_POLICY_PRIMARY_NAMESPACE,
+			PEER_CONNECTION_POLICY_SECONDARY_NAMESPACE,
+			PEER_CONNECTION_POLICY_KEY,
+			e
+		);
+		Error::PersistenceFailed
+	})
+}
+
+pub(crate) fn read_node_alias<L: Deref>(
+	kv_store: Arc<DynStore>, logger: L,
+) -> Result<NodeAlias, std::io::Error>
+where
+	L::Target: LdkLogger,
+{
+	let mut reader = Cursor::new(KVStoreSync::read(
+		&*kv_store,
+		NODE_ALIAS_PRIMARY_NAMESPACE,
+		NODE_ALIAS_SECONDARY_NAMESPACE,
+		NODE_ALIAS_KEY,
+	)?);
+	NodeAlias::read(&mut reader).map_err(|e| {
+		log_error!(logger, "Failed to deserialize node alias: {}", e);
+		std::io::Error::new(std::io::ErrorKind::InvalidData, "Failed to deserialize node alias")
+	})
+}
+
+pub(crate) fn write_node_alias<L: Deref>(
+	node_alias: &NodeAlias, kv_store: Arc<DynStore>, logger: L,
+) -> Result<(), Error>
+where
+	L::Target: LdkLogger,
+{
+	let data = node_alias.encode();
+	KVStoreSync::write(
+		&*kv_store,
+		NODE_ALIAS_PRIMARY_NAMESPACE,
+		NODE_ALIAS_SECONDARY_NAMESPACE,
+		NODE_ALIAS_KEY,
+		data,
+	)
+	.map_err(|e| {
+		log_error!(
+			logger,
+			"Writing data to key {}/{}/{} failed due to: {}",
+			NODE_ALIAS_PRIMARY_NAMESPACE,
+			NODE_ALIAS_SECONDARY_NAMESPACE,
+			NODE_ALIAS_KEY,
+			e
+		);
+		Error::PersistenceFailed
+	})
+}
+
+pub(crate) fn read_default_offer<L: Deref>(
+	kv_store: Arc<DynStore>, logger: L,
+) -> Result<Offer, std::io::Error>
+where
+	L::Target: LdkLogger,
+{
+	let bytes = KVStoreSync::read(
+		&*kv_store,
+		DEFAULT_OFFER_PRIMARY_NAMESPACE,
+		DEFAULT_OFFER_SECONDARY_NAMESPACE,
+		DEFAULT_OFFER_KEY,
+	)?;
+	Offer::try_from(bytes).map_err(|e| {
+		log_error!(logger, "Failed to deserialize default offer: {:?}", e);
+		std::io::Error::new(std::io::ErrorKind::InvalidData, "Failed to deserialize default offer")
+	})
+}
+
+pub(crate) fn write_default_offer<L: Deref>(
+	offer: &Offer, kv_store: Arc<DynStore>, logger: L,
+) -> Result<(), Error>
+where
+	L::Target: LdkLogger,
+{
+	let data = offer.as_ref().to_vec();
+	KVStoreSync::write(
+		&*kv_store,
+		DEFAULT_OFFER_PRIMARY_NAMESPACE,
+		DEFAULT_OFFER_SECONDARY_NAMESPACE,
+		DEFAULT_OFFER_KEY,
+		data,
+	)
+	.map_err(|e| {
+		log_error!(
+			logger,
+			"Writing data to key {}/{}/{} failed due to: {}",
+			DEFAULT_OFFER_PRIMARY_NAMESPACE,
+			DEFAULT_OFFER_SECONDARY_NAMESPACE,
+			DEFAULT_OFFER_KEY,
+			e
+		);
+		Error::PersistenceFailed
+	})
+}
+
+/// Read the JIT channels our LSPS2 service is currently opening, i.e., the channel value in
+/// satoshis by `user_channel_id`.
+pub(crate) fn read_lsps2_service_pending_jit_channels<L: Deref>(
+	kv_store: Arc<DynStore>, logger: L,
+) -> Result<BTreeMap<u128, u64>, std::io::Error>
+where
+	L::Target: LdkLogger,
+{
+	let mut reader = Cursor::new(KVStoreSync::read(
+		&*kv_store,
+		LSPS2_SERVICE_PENDING_JIT_CHANNELS_PRIMARY_NAMESPACE,
+		LSPS2_SERVICE_PENDING_JIT_CHANNELS_SECONDARY_NAMESPACE,
+		LSPS2_SERVICE_PENDING_JIT_CHANNELS_KEY,
+	)?);
+	BTreeMap::read(&mut reader).map_err(|e| {
+		log_error!(logger, "Failed to deserialize pending JIT channels: {}", e);
+		std::io::Error::new(
+			std::io::ErrorKind::InvalidData,
+			"Failed to deserialize pending JIT channels",
+		)
+	})
+}
+
+pub(crate) fn write_lsps2_service_pending_jit_channels<L: Deref>(
+	pending_jit_channels: &BTreeMap<u128, u64>, kv_store: Arc<DynStore>, logger: L,
+) -> Result<(), Error>
+where
+	L::Target: LdkLogger,
+{
+	let data = pending_jit_channels.encode();
+	KVStoreSync::write(
+		&*kv_store,
+		LSPS2_SERVICE_PENDING_JIT_CHANNELS_PRIMARY_NAMESPACE,
+		LSPS2_SERVICE_PENDING_JIT_CHANNELS_SECONDARY_NAMESPACE,
+		LSPS2_SERVICE_PENDING_JIT_CHANNELS_KEY,
+		data,
+	)
+	.map_err(|e| {
+		log_error!(
+			logger,
+			"Writing data to key {}/{}/{} failed due to: {}",
+			LSPS2_SERVICE_PENDING_JIT_CHANNELS_PRIMARY_NAMESPACE,
+			LSPS2_SERVICE_PENDING_JIT_CHANNELS_SECONDARY_NAMESPACE,
+			LSPS2_SERVICE_PENDING_JIT_CHANNELS_KEY,
+			e
+		);
+		Error::PersistenceFailed
+	})
+}
+
 pub(crate) fn is_valid_kvstore_str(key: &str) -> bool {
 	key.len() <= KVSTORE_NAMESPACE_KEY_MAX_LEN
 		&& key.chars().all(|c| KVSTORE_NAMESPACE_KEY_ALPHABET.contains(c))
@@ -623,9 +976,37 @@ mod tests {
 
 	#[test]
 	fn mnemonic_to_entropy_to_mnemonic() {
-		let mnemonic = generate_entropy_mnemonic();
+		let mnemonic = generate_entropy_mnemonic(None);
+		assert_eq!(mnemonic.word_count(), 24);
 
 		let entropy = mnemonic.to_entropy();
 		assert_eq!(mnemonic, Mnemonic::from_entropy(&entropy).unwrap());
 	}
+
+	#[test]
+	fn mnemonic_word_counts_and_validation() {
+		let word_counts = [
+			(WordCount::Words12, 12),
+			(WordCount::Words15, 15),
+			(WordCount::Words18, 18),
+			(WordCount::Words21, 21),
+			(WordCount::Words24, 24),
+		];
+		for (word_count, expected_words) in word_counts {
+			let mnemonic = generate_entropy_mnemonic(Some(word_count));
+			assert_eq!(mnemonic.word_count(), expected_words);
+			assert_eq!(parse_entropy_mnemonic(&mnemonic.to_string()), Ok(mnemonic));
+		}
+
+		// Mnemonics with an invalid checksum, unknown words, or too few words are rejected.
+		let valid = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+		assert!(parse_entropy_mnemonic(valid).is_ok());
+		let invalid_checksum = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon";
+		assert_eq!(parse_entropy_mnemonic(invalid_checksum), Err(Error::InvalidMnemonic));
+		let unknown_word = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon notaword";
+		assert_eq!(parse_entropy_mnemonic(unknown_word), Err(Error::InvalidMnemonic));
+		let too_short =
+			"abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+		assert_eq!(parse_entropy_mnemonic(too_short), Err(Error::InvalidMnemonic));
+	}
 }
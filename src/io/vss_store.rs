@@ -6,18 +6,18 @@
 // accordance with one or both of these licenses.
 
 use std::boxed::Box;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::future::Future;
 #[cfg(test)]
 use std::panic::RefUnwindSafe;
 use std::pin::Pin;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use bitcoin::hashes::{sha256, Hash, HashEngine, Hmac, HmacEngine};
 use lightning::io::{self, Error, ErrorKind};
-use lightning::util::persist::{KVStore, KVStoreSync};
+use lightning::util::persist::{KVStore, KVStoreSync, MigratableKVStore};
 use prost::Message;
 use rand::RngCore;
 use vss_client::client::VssClient;
@@ -45,6 +45,10 @@ type CustomRetryPolicy = FilteredRetryPolicy<
 >;
 
 /// A [`KVStoreSync`] implementation that writes to and reads from a [VSS](https://github.com/lightningdevkit/vss-server/blob/main/README.md) backend.
+///
+/// Writes make use of VSS' key-level versioning, i.e., they will fail rather than overwrite data
+/// if another writer modified the same key in the meantime. Reads are cached locally, allowing us
+/// to serve previously read or written values if the VSS backend is temporarily unreachable.
 pub struct VssStore {
 	inner: Arc<VssStoreInner>,
 	// Version counter to ensure that writes are applied in the correct order. It is assumed that read and list
@@ -88,6 +92,21 @@ impl VssStore {
 
 		(inner_lock_ref, version)
 	}
+
+	/// Waits until we hit a hard failure communicating with the VSS backend, returning a
+	/// description of the failure.
+	///
+	/// Failures are only reported once per outage, i.e., we'll only report again after the
+	/// backend was reachable in the meantime.
+	pub(crate) async fn wait_for_storage_unavailable(&self) -> String {
+		loop {
+			let reason = self.inner.unavailability_reason.lock().unwrap().take();
+			if let Some(reason) = reason {
+				return reason;
+			}
+			self.inner.unavailability_notifier.notified().await;
+		}
+	}
 }
 
 impl KVStoreSync for VssStore {
@@ -137,6 +156,13 @@ impl KVStoreSync for VssStore {
 	}
 }
 
+impl MigratableKVStore for VssStore {
+	fn list_all_keys(&self) -> io::Result<Vec<(String, String, String)>> {
+		let fut = self.inner.list_all_namespaced_keys();
+		self.runtime.block_on(fut)
+	}
+}
+
 impl KVStore for VssStore {
 	fn read(
 		&self, primary_namespace: &str, secondary_namespace: &str, key: &str,
@@ -212,6 +238,76 @@ struct VssStoreInner {
 	// Per-key locks that ensures that we don't have concurrent writes to the same namespace/key.
 	// The lock also encapsulates the latest written version per key.
 	locks: Mutex<HashMap<String, Arc<tokio::sync::Mutex<u64>>>>,
+	// Read-through cache holding the latest known value and VSS key version per obfuscated key.
+	cache: Mutex<VssCache>,
+	is_available: AtomicBool,
+	unavailability_reason: Mutex<Option<String>>,
+	unavailability_notifier: tokio::sync::Notify,
+}
+
+struct CachedEntry {
+	vss_version: i64,
+	value: Vec<u8>,
+}
+
+// The maximum total size of the values we keep in our read-through cache.
+const MAX_CACHE_SIZE_BYTES: usize = 32 * 1024 * 1024;
+
+/// A cache of the values we read from or wrote to VSS, bounded by their total size.
+///
+/// If the cache is full, we evict the entries that were inserted first. Values exceeding the
+/// bound on their own are not cached at all.
+struct VssCache {
+	entries: HashMap<String, CachedEntry>,
+	insertion_order: VecDeque<String>,
+	total_size_bytes: usize,
+	max_size_bytes: usize,
+}
+
+impl VssCache {
+	fn new(max_size_bytes: usize) -> Self {
+		let entries = HashMap::new();
+		let insertion_order = VecDeque::new();
+		Self { entries, insertion_order, total_size_bytes: 0, max_size_bytes }
+	}
+
+	fn get(&self, obfuscated_key: &str) -> Option<&CachedEntry> {
+		self.entries.get(obfuscated_key)
+	}
+
+	fn insert(&mut self, obfuscated_key: String, vss_version: i64, value: Vec<u8>) {
+		// Reads might race with writes. Make sure we never go back to a stale version.
+		if self.entries.get(&obfuscated_key).map_or(false, |e| e.vss_version > vss_version) {
+			return;
+		}
+
+		self.remove(&obfuscated_key);
+		if value.len() > self.max_size_bytes {
+			return;
+		}
+
+		while self.total_size_bytes + value.len() > self.max_size_bytes {
+			match self.insertion_order.pop_front() {
+				Some(evicted_key) => {
+					if let Some(evicted) = self.entries.remove(&evicted_key) {
+						self.total_size_bytes -= evicted.value.len();
+					}
+				},
+				None => break,
+			}
+		}
+
+		self.total_size_bytes += value.len();
+		self.insertion_order.push_back(obfuscated_key.clone());
+		self.entries.insert(obfuscated_key, CachedEntry { vss_version, value });
+	}
+
+	fn remove(&mut self, obfuscated_key: &str) {
+		if let Some(removed) = self.entries.remove(obfuscated_key) {
+			self.total_size_bytes -= removed.value.len();
+			self.insertion_order.retain(|k| k != obfuscated_key);
+		}
+	}
 }
 
 impl VssStoreInner {
@@ -238,7 +334,63 @@ impl VssStoreInner {
 
 		let client = VssClient::new_with_headers(base_url, retry_policy, header_provider);
 		let locks = Mutex::new(HashMap::new());
-		Self { client, store_id, storable_builder, key_obfuscator, locks }
+		let cache = Mutex::new(VssCache::new(MAX_CACHE_SIZE_BYTES));
+		let is_available = AtomicBool::new(true);
+		let unavailability_reason = Mutex::new(None);
+		let unavailability_notifier = tokio::sync::Notify::new();
+		Self {
+			client,
+			store_id,
+			storable_builder,
+			key_obfuscator,
+			locks,
+			cache,
+			is_available,
+			unavailability_reason,
+			unavailability_notifier,
+		}
+	}
+
+	fn is_hard_failure(e: &VssError) -> bool {
+		matches!(
+			e,
+			VssError::AuthError(..)
+				| VssError::InternalServerError(..)
+				| VssError::InternalError(..)
+		)
+	}
+
+	fn record_storage_available(&self) {
+		self.is_available.store(true, Ordering::Release);
+	}
+
+	fn record_storage_unavailable(&self, reason: String) {
+		// Only notify on the transition to unavailable, so that we don't report the same outage
+		// over and over again.
+		if self.is_available.swap(false, Ordering::AcqRel) {
+			*self.unavailability_reason.lock().unwrap() = Some(reason);
+			self.unavailability_notifier.notify_one();
+		}
+	}
+
+	fn update_cache(&self, obfuscated_key: String, vss_version: i64, value: Vec<u8>) {
+		self.cache.lock().unwrap().insert(obfuscated_key, vss_version, value);
+	}
+
+	// Returns the current VSS key version to be used for a conditional write, i.e., `0` if the key
+	// doesn't exist yet.
+	async fn current_vss_version(&self, obfuscated_key: &str) -> Result<i64, VssError> {
+		if let Some(entry) = self.cache.lock().unwrap().get(obfuscated_key) {
+			return Ok(entry.vss_version);
+		}
+
+		let request =
+			GetObjectRequest { store_id: self.store_id.clone(), key: obfuscated_key.to_string() };
+		match self.client.get_object(&request).await {
+			Ok(resp) => Ok(resp.value.map(|kv| kv.version).unwrap_or(0)),
+			Err(VssError::NoSuchKeyError(..)) => Ok(0),
+			Err(e) => Err(e),
+		}
 	}
 
 	fn get_inner_lock_ref(&self, locking_key: String) -> Arc<tokio::sync::Mutex<u64>> {
@@ -288,8 +440,12 @@ impl VssStoreInner {
 					"Failed to list keys in {}/{}: {}",
 					primary_namespace, secondary_namespace, e
 				);
+				if Self::is_hard_failure(&e) {
+					self.record_storage_unavailable(msg.clone());
+				}
 				Error::new(ErrorKind::Other, msg)
 			})?;
+			self.record_storage_available();
 
 			for kv in response.key_versions {
 				keys.push(self.extract_key(&kv.key)?);
@@ -299,27 +455,85 @@ impl VssStoreInner {
 		Ok(keys)
 	}
 
+	async fn list_all_namespaced_keys(&self) -> io::Result<Vec<(String, String, String)>> {
+		let mut page_token = None;
+		let mut keys = vec![];
+		while page_token != Some("".to_string()) {
+			let request = ListKeyVersionsRequest {
+				store_id: self.store_id.clone(),
+				key_prefix: None,
+				page_token,
+				page_size: None,
+			};
+
+			let response = self.client.list_key_versions(&request).await.map_err(|e| {
+				let msg = format!("Failed to list all keys: {}", e);
+				if Self::is_hard_failure(&e) {
+					self.record_storage_unavailable(msg.clone());
+				}
+				Error::new(ErrorKind::Other, msg)
+			})?;
+			self.record_storage_available();
+
+			for kv in response.key_versions {
+				// Keys in the root namespace aren't prefixed, see `build_obfuscated_key`.
+				let mut parts = kv.key.splitn(3, '#');
+				let (primary_namespace, secondary_namespace, obfuscated_key) =
+					match (parts.next(), parts.next(), parts.next()) {
+						(Some(primary), Some(secondary), Some(obfuscated_key)) => {
+							(primary.to_string(), secondary.to_string(), obfuscated_key)
+						},
+						(Some(obfuscated_key), None, None) => {
+							(String::new(), String::new(), obfuscated_key)
+						},
+						_ => return Err(Error::new(ErrorKind::InvalidData, "Invalid key format")),
+					};
+				let key = self.key_obfuscator.deobfuscate(obfuscated_key)?;
+				keys.push((primary_namespace, secondary_namespace, key));
+			}
+			page_token = response.next_page_token;
+		}
+		Ok(keys)
+	}
+
 	async fn read_internal(
 		&self, primary_namespace: &str, secondary_namespace: &str, key: &str,
 	) -> io::Result<Vec<u8>> {
 		check_namespace_key_validity(primary_namespace, secondary_namespace, Some(key), "read")?;
 
 		let obfuscated_key = self.build_obfuscated_key(primary_namespace, secondary_namespace, key);
-		let request = GetObjectRequest { store_id: self.store_id.clone(), key: obfuscated_key };
-		let resp = self.client.get_object(&request).await.map_err(|e| {
-			let msg = format!(
-				"Failed to read from key {}/{}/{}: {}",
-				primary_namespace, secondary_namespace, key, e
-			);
-			match e {
-				VssError::NoSuchKeyError(..) => Error::new(ErrorKind::NotFound, msg),
-				_ => Error::new(ErrorKind::Other, msg),
-			}
-		})?;
+		let request =
+			GetObjectRequest { store_id: self.store_id.clone(), key: obfuscated_key.clone() };
+		let resp = match self.client.get_object(&request).await {
+			Ok(resp) => resp,
+			Err(e) => {
+				let msg = format!(
+					"Failed to read from key {}/{}/{}: {}",
+					primary_namespace, secondary_namespace, key, e
+				);
+				if let VssError::NoSuchKeyError(..) = e {
+					self.record_storage_available();
+					self.cache.lock().unwrap().remove(&obfuscated_key);
+					return Err(Error::new(ErrorKind::NotFound, msg));
+				}
+
+				if Self::is_hard_failure(&e) {
+					self.record_storage_unavailable(msg.clone());
+				}
+
+				// Fall back to the latest value we know about, if any.
+				if let Some(entry) = self.cache.lock().unwrap().get(&obfuscated_key) {
+					return Ok(entry.value.clone());
+				}
+				return Err(Error::new(ErrorKind::Other, msg));
+			},
+		};
+		self.record_storage_available();
 
 		// unwrap safety: resp.value must be always present for a non-erroneous VSS response, otherwise
 		// it is an API-violation which is converted to [`VssError::InternalServerError`] in [`VssClient`]
-		let storable = Storable::decode(&resp.value.unwrap().value[..]).map_err(|e| {
+		let key_value = resp.value.unwrap();
+		let storable = Storable::decode(&key_value.value[..]).map_err(|e| {
 			let msg = format!(
 				"Failed to decode data read from key {}/{}/{}: {}",
 				primary_namespace, secondary_namespace, key, e
@@ -327,7 +541,9 @@ impl VssStoreInner {
 			Error::new(ErrorKind::Other, msg)
 		})?;
 
-		Ok(self.storable_builder.deconstruct(storable)?.0)
+		let value = self.storable_builder.deconstruct(storable)?.0;
+		self.update_cache(obfuscated_key, key_value.version, value.clone());
+		Ok(value)
 	}
 
 	async fn write_internal(
@@ -339,27 +555,45 @@ impl VssStoreInner {
 		self.execute_locked_write(inner_lock_ref, locking_key, version, async move || {
 			let obfuscated_key =
 				self.build_obfuscated_key(primary_namespace, secondary_namespace, key);
-			let vss_version = -1;
-			let storable = self.storable_builder.build(buf, vss_version);
+			let map_write_err = |e: VssError| {
+				let msg = format!(
+					"Failed to write to key {}/{}/{}: {}",
+					primary_namespace, secondary_namespace, key, e
+				);
+				match e {
+					VssError::ConflictError(..) => {
+						// Somebody else modified the key in the meantime. Drop what we know about it
+						// and let the caller decide how to proceed, rather than clobbering their data.
+						// Note this is a regular error rather than an outage of the backend.
+						self.cache.lock().unwrap().remove(&obfuscated_key);
+					},
+					ref e if Self::is_hard_failure(e) => {
+						self.record_storage_unavailable(msg.clone());
+					},
+					_ => {},
+				}
+				Error::new(ErrorKind::Other, msg)
+			};
+
+			let vss_version =
+				self.current_vss_version(&obfuscated_key).await.map_err(map_write_err)?;
+			let storable = self.storable_builder.build(buf.clone(), vss_version);
 			let request = PutObjectRequest {
 				store_id: self.store_id.clone(),
 				global_version: None,
 				transaction_items: vec![KeyValue {
-					key: obfuscated_key,
+					key: obfuscated_key.clone(),
 					version: vss_version,
 					value: storable.encode_to_vec(),
 				}],
 				delete_items: vec![],
 			};
 
-			self.client.put_object(&request).await.map_err(|e| {
-				let msg = format!(
-					"Failed to write to key {}/{}/{}: {}",
-					primary_namespace, secondary_namespace, key, e
-				);
-				Error::new(ErrorKind::Other, msg)
-			})?;
+			self.client.put_object(&request).await.map_err(map_write_err)?;
+			self.record_storage_available();
 
+			// The server increments the key version on every successful write.
+			self.update_cache(obfuscated_key.clone(), vss_version + 1, buf);
 			Ok(())
 		})
 		.await
@@ -376,7 +610,11 @@ impl VssStoreInner {
 				self.build_obfuscated_key(primary_namespace, secondary_namespace, key);
 			let request = DeleteObjectRequest {
 				store_id: self.store_id.clone(),
-				key_value: Some(KeyValue { key: obfuscated_key, version: -1, value: vec![] }),
+				key_value: Some(KeyValue {
+					key: obfuscated_key.clone(),
+					version: -1,
+					value: vec![],
+				}),
 			};
 
 			self.client.delete_object(&request).await.map_err(|e| {
@@ -384,9 +622,14 @@ impl VssStoreInner {
 					"Failed to delete key {}/{}/{}: {}",
 					primary_namespace, secondary_namespace, key, e
 				);
+				if Self::is_hard_failure(&e) {
+					self.record_storage_unavailable(msg.clone());
+				}
 				Error::new(ErrorKind::Other, msg)
 			})?;
+			self.record_storage_available();
 
+			self.cache.lock().unwrap().remove(&obfuscated_key);
 			Ok(())
 		})
 		.await
@@ -523,3 +766,46 @@ mod tests {
 		drop(vss_store)
 	}
 }
+
+#[cfg(test)]
+mod cache_tests {
+	use super::*;
+
+	#[test]
+	fn cache_is_bounded_by_total_size() {
+		let mut cache = VssCache::new(10);
+		cache.insert("a".to_string(), 0, vec![0; 4]);
+		cache.insert("b".to_string(), 0, vec![0; 4]);
+		assert_eq!(cache.total_size_bytes, 8);
+
+		// Inserting another entry evicts the oldest one.
+		cache.insert("c".to_string(), 0, vec![0; 4]);
+		assert!(cache.get("a").is_none());
+		assert!(cache.get("b").is_some());
+		assert!(cache.get("c").is_some());
+		assert_eq!(cache.total_size_bytes, 8);
+
+		// Values exceeding the bound on their own are not cached.
+		cache.insert("d".to_string(), 0, vec![0; 11]);
+		assert!(cache.get("d").is_none());
+		assert_eq!(cache.total_size_bytes, 8);
+
+		cache.remove("b");
+		assert!(cache.get("b").is_none());
+		assert_eq!(cache.total_size_bytes, 4);
+		assert_eq!(cache.insertion_order.len(), 1);
+	}
+
+	#[test]
+	fn cache_never_goes_back_to_stale_version() {
+		let mut cache = VssCache::new(10);
+		cache.insert("a".to_string(), 2, vec![2]);
+		cache.insert("a".to_string(), 1, vec![1]);
+		assert_eq!(cache.get("a").unwrap().value, vec![2]);
+
+		cache.insert("a".to_string(), 3, vec![3, 3]);
+		assert_eq!(cache.get("a").unwrap().value, vec![3, 3]);
+		assert_eq!(cache.total_size_bytes, 2);
+		assert_eq!(cache.insertion_order.len(), 1);
+	}
+}
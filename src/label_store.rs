@@ -0,0 +1,240 @@
+// This file is Copyright its original authors, visible in version control history.
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. You may not use this file except in
+// accordance with one or both of these licenses.
+
+use std::collections::BTreeMap;
+use std::ops::Deref;
+use std::sync::{Arc, RwLock};
+
+use bitcoin::secp256k1::PublicKey;
+use lightning::ln::types::ChannelId;
+use lightning::util::persist::KVStoreSync;
+use lightning::util::ser::{Readable, ReadableArgs, Writeable, Writer};
+
+use crate::io::{
+	LABEL_PERSISTENCE_KEY, LABEL_PERSISTENCE_PRIMARY_NAMESPACE,
+	LABEL_PERSISTENCE_SECONDARY_NAMESPACE,
+};
+use crate::logger::{log_error, log_info, LdkLogger};
+use crate::types::DynStore;
+use crate::Error;
+
+/// The maximum length in bytes of a local channel or peer label.
+pub(crate) const MAX_LOCAL_LABEL_LEN: usize = 64;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct Labels {
+	channels: BTreeMap<ChannelId, String>,
+	peers: BTreeMap<PublicKey, String>,
+}
+
+impl Writeable for Labels {
+	fn write<W: Writer>(&self, writer: &mut W) -> Result<(), lightning::io::Error> {
+		self.channels.write(writer)?;
+		self.peers.write(writer)
+	}
+}
+
+impl Readable for Labels {
+	fn read<R: lightning::io::Read>(
+		reader: &mut R,
+	) -> Result<Self, lightning::ln::msgs::DecodeError> {
+		let channels = Readable::read(reader)?;
+		let peers = Readable::read(reader)?;
+		Ok(Self { channels, peers })
+	}
+}
+
+/// Keeps track of the local, human-readable labels the user assigned to channels and peers.
+///
+/// Note these labels are purely local and are never shared with the network.
+pub(crate) struct LabelStore<L: Deref>
+where
+	L::Target: LdkLogger,
+{
+	labels: RwLock<Labels>,
+	kv_store: Arc<DynStore>,
+	logger: L,
+}
+
+impl<L: Deref> LabelStore<L>
+where
+	L::Target: LdkLogger,
+{
+	pub(crate) fn new(kv_store: Arc<DynStore>, logger: L) -> Self {
+		let labels = RwLock::new(Labels::default());
+		Self { labels, kv_store, logger }
+	}
+
+	/// Sets the label of the given channel, or clears it if `label` is empty.
+	pub(crate) fn set_channel_label(
+		&self, channel_id: ChannelId, label: String,
+	) -> Result<(), Error> {
+		self.check_label(&label)?;
+		let mut locked_labels = self.labels.write().unwrap();
+		if label.is_empty() {
+			locked_labels.channels.remove(&channel_id);
+		} else {
+			locked_labels.channels.insert(channel_id, label);
+		}
+		self.persist_labels(&locked_labels)
+	}
+
+	/// Sets the label of the given peer, or clears it if `label` is empty.
+	pub(crate) fn set_peer_label(&self, node_id: PublicKey, label: String) -> Result<(), Error> {
+		self.check_label(&label)?;
+		let mut locked_labels = self.labels.write().unwrap();
+		if label.is_empty() {
+			locked_labels.peers.remove(&node_id);
+		} else {
+			locked_labels.peers.insert(node_id, label);
+		}
+		self.persist_labels(&locked_labels)
+	}
+
+	pub(crate) fn channel_label(&self, channel_id: &ChannelId) -> Option<String> {
+		self.labels.read().unwrap().channels.get(channel_id).cloned()
+	}
+
+	pub(crate) fn peer_label(&self, node_id: &PublicKey) -> Option<String> {
+		self.labels.read().unwrap().peers.get(node_id).cloned()
+	}
+
+	/// Drops the labels of all channels and peers for which the given predicates return `false`,
+	/// e.g., as the channel was closed in the meantime.
+	pub(crate) fn retain<C, P>(&self, keep_channel: C, keep_peer: P) -> Result<(), Error>
+	where
+		C: Fn(&ChannelId) -> bool,
+		P: Fn(&PublicKey) -> bool,
+	{
+		let mut locked_labels = self.labels.write().unwrap();
+		let num_labels = locked_labels.channels.len() + locked_labels.peers.len();
+		locked_labels.channels.retain(|channel_id, _| keep_channel(channel_id));
+		locked_labels.peers.retain(|node_id, _| keep_peer(node_id));
+
+		let num_pruned = num_labels - locked_labels.channels.len() - locked_labels.peers.len();
+		if num_pruned == 0 {
+			return Ok(());
+		}
+
+		log_info!(self.logger, "Pruning {} stale channel and peer label(s).", num_pruned);
+		self.persist_labels(&locked_labels)
+	}
+
+	fn check_label(&self, label: &str) -> Result<(), Error> {
+		if label.len() > MAX_LOCAL_LABEL_LEN {
+			log_error!(
+				self.logger,
+				"Failed to set label as it exceeds the maximum length of {} bytes.",
+				MAX_LOCAL_LABEL_LEN
+			);
+			return Err(Error::InvalidLabel);
+		}
+		if label.chars().any(char::is_control) {
+			log_error!(self.logger, "Failed to set label as it contains control characters.");
+			return Err(Error::InvalidLabel);
+		}
+		Ok(())
+	}
+
+	fn persist_labels(&self, locked_labels: &Labels) -> Result<(), Error> {
+		let data = locked_labels.encode();
+		KVStoreSync::write(
+			&*self.kv_store,
+			LABEL_PERSISTENCE_PRIMARY_NAMESPACE,
+			LABEL_PERSISTENCE_SECONDARY_NAMESPACE,
+			LABEL_PERSISTENCE_KEY,
+			data,
+		)
+		.map_err(|e| {
+			log_error!(
+				self.logger,
+				"Write for key {}/{}/{} failed due to: {}",
+				LABEL_PERSISTENCE_PRIMARY_NAMESPACE,
+				LABEL_PERSISTENCE_SECONDARY_NAMESPACE,
+				LABEL_PERSISTENCE_KEY,
+				e
+			);
+			Error::PersistenceFailed
+		})?;
+		Ok(())
+	}
+}
+
+impl<L: Deref> ReadableArgs<(Arc<DynStore>, L)> for LabelStore<L>
+where
+	L::Target: LdkLogger,
+{
+	#[inline]
+	fn read<R: lightning::io::Read>(
+		reader: &mut R, args: (Arc<DynStore>, L),
+	) -> Result<Self, lightning::ln::msgs::DecodeError> {
+		let (kv_store, logger) = args;
+		let labels = RwLock::new(Readable::read(reader)?);
+		Ok(Self { labels, kv_store, logger })
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::str::FromStr;
+	use std::sync::Arc;
+
+	use lightning::util::test_utils::{TestLogger, TestStore};
+
+	use super::*;
+
+	#[test]
+	fn label_persistence() {
+		let store: Arc<DynStore> = Arc::new(TestStore::new(false));
+		let logger = Arc::new(TestLogger::new());
+		let label_store = LabelStore::new(Arc::clone(&store), Arc::clone(&logger));
+
+		let node_id = PublicKey::from_str(
+			"0276607124ebe6a6c9338517b6f485825b27c2dcc0b9fc2aa6a4c0df91194e5993",
+		)
+		.unwrap();
+		let channel_id = ChannelId([42u8; 32]);
+		let other_channel_id = ChannelId([43u8; 32]);
+		let closed_channel_id = ChannelId([44u8; 32]);
+
+		label_store.set_peer_label(node_id, "bitfinex".to_string()).unwrap();
+		label_store.set_channel_label(channel_id, "bitfinex-main".to_string()).unwrap();
+		label_store.set_channel_label(other_channel_id, "bitfinex-backup".to_string()).unwrap();
+		label_store.set_channel_label(closed_channel_id, "bitfinex-old".to_string()).unwrap();
+
+		// Labels exceeding the maximum length or containing control characters are rejected.
+		let too_long = "a".repeat(MAX_LOCAL_LABEL_LEN + 1);
+		assert_eq!(label_store.set_channel_label(channel_id, too_long), Err(Error::InvalidLabel));
+		let control_chars = "bitfinex\nmain".to_string();
+		assert_eq!(
+			label_store.set_channel_label(channel_id, control_chars),
+			Err(Error::InvalidLabel)
+		);
+
+		// An empty label clears it.
+		label_store.set_channel_label(other_channel_id, String::new()).unwrap();
+
+		// Labels of channels that are gone are pruned.
+		label_store.retain(|id| *id != closed_channel_id, |_| true).unwrap();
+
+		// Check we can read back what we persisted.
+		let persisted_bytes = KVStoreSync::read(
+			&*store,
+			LABEL_PERSISTENCE_PRIMARY_NAMESPACE,
+			LABEL_PERSISTENCE_SECONDARY_NAMESPACE,
+			LABEL_PERSISTENCE_KEY,
+		)
+		.unwrap();
+		let deser_label_store =
+			LabelStore::read(&mut &persisted_bytes[..], (Arc::clone(&store), logger)).unwrap();
+
+		assert_eq!(deser_label_store.peer_label(&node_id), Some("bitfinex".to_string()));
+		assert_eq!(deser_label_store.channel_label(&channel_id), Some("bitfinex-main".to_string()));
+		assert_eq!(deser_label_store.channel_label(&other_channel_id), None);
+		assert_eq!(deser_label_store.channel_label(&closed_channel_id), None);
+	}
+}
@@ -26,6 +26,7 @@
 //! # #[cfg(not(feature = "uniffi"))]
 //! # {
 //! use std::str::FromStr;
+//! use std::time::Duration;
 //!
 //! use ldk_node::bitcoin::secp256k1::PublicKey;
 //! use ldk_node::bitcoin::Network;
@@ -53,9 +54,10 @@
 //! 	let node_addr = SocketAddress::from_str("IP_ADDR:PORT").unwrap();
 //! 	node.open_channel(node_id, node_addr, 10000, None, None).unwrap();
 //!
-//! 	let event = node.wait_next_event();
-//! 	println!("EVENT: {:?}", event);
-//! 	node.event_handled();
+//! 	while let Some(event) = node.wait_next_event_timeout(Duration::from_secs(60)) {
+//! 		println!("EVENT: {:?}", event);
+//! 		node.event_handled();
+//! 	}
 //!
 //! 	let invoice = Bolt11Invoice::from_str("INVOICE_STR").unwrap();
 //! 	node.bolt11_payment().send(&invoice, None).unwrap();
@@ -77,9 +79,11 @@
 #![allow(ellipsis_inclusive_range_patterns)]
 #![cfg_attr(docsrs, feature(doc_auto_cfg))]
 
+mod backup;
 mod balance;
 mod builder;
 mod chain;
+mod closed_channel;
 pub mod config;
 mod connection;
 mod data_store;
@@ -91,6 +95,7 @@ mod gossip;
 pub mod graph;
 mod hex_utils;
 pub mod io;
+mod label_store;
 pub mod liquidity;
 pub mod logger;
 mod message_handler;
@@ -98,66 +103,132 @@ pub mod payment;
 mod peer_store;
 mod runtime;
 mod scoring;
+mod sweep_tracker;
+mod tor;
 mod tx_broadcaster;
 mod types;
 mod wallet;
+mod watchtower;
 
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::default::Default;
 use std::net::ToSocketAddrs;
 use std::sync::{Arc, Mutex, RwLock};
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use crate::scoring::setup_background_pathfinding_scores_sync;
-pub use balance::{BalanceDetails, LightningBalance, PendingSweepBalance};
+use crate::backup::StaticChannelBackup;
+use crate::label_store::LabelStore;
+use crate::scoring::{
+	setup_background_pathfinding_scores_sync, setup_background_scorer_persistence,
+};
+use crate::sweep_tracker::SweepTracker;
+use crate::tor::OnionService;
+pub use balance::{BalanceDetails, LightningBalance, PendingSweep, PendingSweepBalance};
+use bitcoin::absolute::LockTime;
+use bitcoin::psbt::Psbt;
 use bitcoin::secp256k1::PublicKey;
+use bitcoin::transaction::Version;
+use bitcoin::{Address, Amount, FeeRate, OutPoint, Transaction, TxOut, Txid};
+use builder::sanitize_alias;
 #[cfg(feature = "uniffi")]
 pub use builder::ArcedNodeBuilder as Builder;
 pub use builder::BuildError;
 #[cfg(not(feature = "uniffi"))]
 pub use builder::NodeBuilder as Builder;
 use chain::ChainSource;
+pub use closed_channel::{CloseType, ClosedChannelDetails};
 use config::{
-	default_user_config, may_announce_channel, AsyncPaymentsRole, ChannelConfig, Config,
-	NODE_ANN_BCAST_INTERVAL, PEER_RECONNECTION_INTERVAL, RGS_SYNC_INTERVAL,
+	default_user_config, expected_channel_reserve_sats, may_announce_channel,
+	route_params_from_config, AnnounceError, AsyncPaymentsRole, ChannelConfig, ChannelConfigUpdate,
+	ChannelOpenParams, Config, PeerConnectionPolicy, ANCHOR_CHILD_TX_WEIGHT_ESTIMATE,
+	BOLT12_INVOICE_EXPIRY_CHECK_INTERVAL, CLOSE_ALL_CHANNELS_POLL_INTERVAL,
+	EXTERNAL_FUNDING_ACCEPT_TIMEOUT, EXTERNAL_FUNDING_TIMEOUT_CHECK_INTERVAL,
+	FORWARDING_FEES_BUCKET_SECS, LSPS1_ORDER_STATUS_POLL_INTERVAL,
+	MAX_ONION_MESSAGE_PATH_INTERMEDIATE_NODES, MAX_RECENT_FORWARDS, NODE_ANN_BCAST_INTERVAL,
+	PEER_CONNECTION_HISTORY_PERSIST_INTERVAL, PENDING_CHANNEL_CLOSE_BUMPS_PRUNE_INTERVAL,
+	RGS_SYNC_INTERVAL, SCORER_PERSIST_INTERVAL_MINIMUM_SECS, SWEEP_BATCH_CHECK_INTERVAL,
+	SWEEP_CONFIRMATION_CHECK_INTERVAL, TX_BROADCAST_TIMEOUT_SECS, WATCHTOWER_SYNC_INTERVAL,
 };
 use connection::ConnectionManager;
 pub use error::Error as NodeError;
 use error::Error;
-pub use event::Event;
-use event::{EventHandler, EventQueue};
+pub use event::{ChannelRejectReason, Event, EventStream, NodeEventHandler, PaymentAutoFailReason};
+use event::{EventDispatcher, EventHandler, EventQueue};
+use fee_estimator::OnchainFeeEstimator;
+pub use fee_estimator::{ConfirmationTarget, FeeEstimator};
 #[cfg(feature = "uniffi")]
 use ffi::*;
 use gossip::GossipSource;
 use graph::NetworkGraph;
-pub use io::utils::generate_entropy_mnemonic;
-use io::utils::write_node_metrics;
-use lightning::chain::BestBlock;
-use lightning::events::bump_transaction::Wallet as LdkWallet;
+pub use io::utils::{generate_entropy_mnemonic, parse_entropy_mnemonic};
+use io::utils::{write_node_alias, write_node_metrics, write_peer_connection_policy, write_scorer};
+use io::vss_store::VssStore;
+use lightning::chain::{BestBlock, ClaimId};
+use lightning::events::bump_transaction::BumpTransactionEvent;
 use lightning::impl_writeable_tlv_based;
 use lightning::ln::channel_state::ChannelShutdownState;
-use lightning::ln::channelmanager::PaymentId;
-use lightning::ln::msgs::SocketAddress;
+use lightning::ln::channelmanager::{
+	InterceptId, PaymentId, RecipientOnionFields, MIN_FINAL_CLTV_EXPIRY_DELTA,
+};
+use lightning::ln::funding::SpliceContribution;
+use lightning::ln::msgs::{BaseMessageHandler, SocketAddress};
+use lightning::ln::peer_handler::CustomMessageHandler;
+use lightning::ln::types::ChannelId;
+use lightning::offers::offer::Amount as LdkOfferAmount;
+use lightning::onion_message::messenger::{Destination, MessageSendInstructions, SendError};
 use lightning::routing::gossip::NodeAlias;
+use lightning::routing::router::{
+	PaymentParameters, RouteHop as LdkRouteHop, RouteParameters, RouteParametersConfig,
+	Router as LdkRouter,
+};
+use lightning::routing::scoring::{
+	CombinedScorer, ProbabilisticScorer, ProbabilisticScoringDecayParameters,
+};
+use lightning::types::features::{ChannelFeatures, NodeFeatures};
+use lightning::types::payment::PaymentHash;
+use lightning::util::config::{
+	ChannelConfigUpdate as LdkChannelConfigUpdate, MaxDustHTLCExposure as LdkMaxDustHTLCExposure,
+};
+use lightning::util::errors::APIError;
 use lightning::util::persist::KVStoreSync;
+use lightning::util::ser::Writeable;
+use lightning::util::sweep::OutputSpendStatus;
 use lightning_background_processor::process_events_async;
-use liquidity::{LSPS1Liquidity, LiquiditySource};
-use logger::{log_debug, log_error, log_info, log_trace, LdkLogger, Logger};
+use liquidity::{LSPS1Liquidity, LSPS2Liquidity, LiquiditySource};
+use logger::{log_debug, log_error, log_info, log_trace, log_warn, LdkLogger, Logger};
+use message_handler::RawOnionMessage;
 use payment::asynchronous::om_mailbox::OnionMessageMailbox;
 use payment::asynchronous::static_invoice_store::StaticInvoiceStore;
+use payment::claim_watchdog::{ClaimDeadlineWatchdog, CLAIM_TIMEOUT_CHECK_INTERVAL};
+use payment::idempotency::PaymentIdempotencyKeys;
+use payment::recurring::{
+	RecurringPaymentSchedule, RecurringPaymentScheduler, RECURRING_PAYMENT_CHECK_INTERVAL,
+};
+#[cfg(feature = "lnurl")]
+use payment::Lnurl;
 use payment::{
-	Bolt11Payment, Bolt12Payment, OnchainPayment, PaymentDetails, SpontaneousPayment,
-	UnifiedQrPayment,
+	Bolt11Payment, Bolt12Payment, OnchainPayment, PaymentDetails, PaymentDirection, PaymentFilter,
+	PaymentKind, PaymentStats, PaymentStatus, RecurrenceInterval, RecurringPaymentDetails,
+	RecurringPaymentId, Route, SpontaneousPayment, UnifiedQrPayment,
 };
 use peer_store::{PeerInfo, PeerStore};
 use rand::Rng;
 use runtime::Runtime;
 use types::{
-	Broadcaster, BumpTransactionEventHandler, ChainMonitor, ChannelManager, Graph, KeysManager,
-	OnionMessenger, PaymentStore, PeerManager, Router, Scorer, Sweeper, Wallet,
+	Broadcaster, BumpTransactionEventHandler, ChainMonitor, ChannelManager, ChannelReadyWaiters,
+	ClosedChannelStore, FundingConfirmationTargets, Graph, KeysManager, MessageRouter,
+	OnionMessenger, PaymentRetryStrategies, PaymentStore, PeerManager, PendingBolt12Invoices,
+	PendingChannelCloseBumps, PendingExternalFunding, PendingExternalFundings,
+	RecurringPaymentStore, Router, Scorer, Sweeper, Wallet,
 };
 pub use types::{
-	ChannelDetails, CustomTlvRecord, DynStore, PeerDetails, SyncAndAsyncKVStore, UserChannelId,
+	ChannelDetails, ChannelForceCloseResult, ChannelFundingPsbt, CloseAllChannelsSummary,
+	ConnectResult, ConnectionFailureReason, CustomTlvRecord, DynStore, ForceCloseOutcome,
+	PeerDetails, PowerMode, SyncAndAsyncKVStore, SyncReport, UserChannelId, WordCount,
 };
+pub use wallet::external::OnchainWalletInterface;
+use watchtower::WatchtowerClient;
+pub use watchtower::{ChannelWatchtowerStatus, WatchtowerStatus};
 
 pub use {
 	bip39, bitcoin, lightning, lightning_invoice, lightning_liquidity, lightning_types, tokio,
@@ -177,13 +248,21 @@ pub struct Node {
 	config: Arc<Config>,
 	wallet: Arc<Wallet>,
 	chain_source: Arc<ChainSource>,
+	fee_estimator: Arc<OnchainFeeEstimator>,
 	tx_broadcaster: Arc<Broadcaster>,
+	bump_tx_event_handler: Arc<BumpTransactionEventHandler>,
+	pending_channel_close_bumps: Arc<PendingChannelCloseBumps>,
+	channel_ready_waiters: Arc<ChannelReadyWaiters>,
+	funding_confirmation_targets: Arc<FundingConfirmationTargets>,
+	pending_external_fundings: Arc<PendingExternalFundings>,
+	pending_bolt12_invoices: Arc<PendingBolt12Invoices>,
 	event_queue: Arc<EventQueue<Arc<Logger>>>,
 	channel_manager: Arc<ChannelManager>,
 	chain_monitor: Arc<ChainMonitor>,
 	output_sweeper: Arc<Sweeper>,
 	peer_manager: Arc<PeerManager>,
 	onion_messenger: Arc<OnionMessenger>,
+	message_router: Arc<MessageRouter>,
 	connection_manager: Arc<ConnectionManager<Arc<Logger>>>,
 	keys_manager: Arc<KeysManager>,
 	network_graph: Arc<Graph>,
@@ -192,14 +271,31 @@ pub struct Node {
 	liquidity_source: Option<Arc<LiquiditySource<Arc<Logger>>>>,
 	kv_store: Arc<DynStore>,
 	logger: Arc<Logger>,
-	_router: Arc<Router>,
+	router: Arc<Router>,
 	scorer: Arc<Mutex<Scorer>>,
 	peer_store: Arc<PeerStore<Arc<Logger>>>,
+	label_store: Arc<LabelStore<Arc<Logger>>>,
+	peer_connection_policy: Arc<RwLock<PeerConnectionPolicy>>,
+	power_mode: Arc<RwLock<PowerMode>>,
+	default_offer: Arc<Mutex<Option<lightning::offers::offer::Offer>>>,
 	payment_store: Arc<PaymentStore>,
+	closed_channel_store: Arc<ClosedChannelStore>,
+	recurring_payment_store: Arc<RecurringPaymentStore>,
+	watchtower_client: Option<Arc<WatchtowerClient>>,
+	payment_retry_strategies: Arc<PaymentRetryStrategies>,
+	payment_idempotency_keys: Arc<PaymentIdempotencyKeys>,
+	claim_watchdog: Arc<ClaimDeadlineWatchdog>,
 	is_running: Arc<RwLock<bool>>,
+	// The time we were last started at, in seconds since the UNIX epoch.
+	started_at_unix_secs: RwLock<Option<u64>>,
+	node_alias: Arc<RwLock<Option<NodeAlias>>>,
 	node_metrics: Arc<RwLock<NodeMetrics>>,
 	om_mailbox: Option<Arc<OnionMessageMailbox>>,
 	async_payments_role: Option<AsyncPaymentsRole>,
+	onion_service: Option<Arc<OnionService>>,
+	vss_store: Option<Arc<VssStore>>,
+	static_channel_backup_key: [u8; 32],
+	event_dispatcher: Mutex<Option<EventDispatcher>>,
 }
 
 impl Node {
@@ -225,6 +321,19 @@ impl Node {
 			self.config.network
 		);
 
+		let now_unix_secs = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap_or(Duration::from_secs(0))
+			.as_secs();
+		*self.started_at_unix_secs.write().unwrap() = Some(now_unix_secs);
+
+		if self.config.anchor_channels_config.as_ref().map_or(false, |c| !c.require_reserve) {
+			log_warn!(
+				self.logger,
+				"The on-chain reserve for Anchor channels is disabled. Force-closed channels might not get confirmed in time if on-chain funds are insufficient."
+			);
+		}
+
 		// Start up any runtime-dependant chain sources (e.g. Electrum)
 		self.chain_source.start(Arc::clone(&self.runtime)).map_err(|e| {
 			log_error!(self.logger, "Failed to start chain syncing: {}", e);
@@ -235,15 +344,48 @@ impl Node {
 		let chain_source = Arc::clone(&self.chain_source);
 		self.runtime.block_on(async move { chain_source.update_fee_rate_estimates().await })?;
 
+		// Apply any configured dust exposure limit to our existing channels.
+		if let Some(limit_msat) = self.config.max_dust_htlc_exposure_msat {
+			let config_update = LdkChannelConfigUpdate {
+				max_dust_htlc_exposure_msat: Some(LdkMaxDustHTLCExposure::FixedLimitMsat(
+					limit_msat,
+				)),
+				..Default::default()
+			};
+			for channel_details in self.channel_manager.list_channels() {
+				if let Err(e) = self.channel_manager.update_partial_channel_config(
+					&channel_details.counterparty.node_id,
+					&[channel_details.channel_id],
+					&config_update,
+				) {
+					log_error!(
+						self.logger,
+						"Failed to apply dust exposure limit to channel {}: {:?}",
+						channel_details.channel_id,
+						e
+					);
+				}
+			}
+		}
+
 		// Spawn background task continuously syncing onchain, lightning, and fee rate cache.
 		let stop_sync_receiver = self.stop_sender.subscribe();
 		let chain_source = Arc::clone(&self.chain_source);
 		let sync_cman = Arc::clone(&self.channel_manager);
 		let sync_cmon = Arc::clone(&self.chain_monitor);
 		let sync_sweeper = Arc::clone(&self.output_sweeper);
+		let sync_claim_watchdog = Arc::clone(&self.claim_watchdog);
+		let sync_power_mode = Arc::clone(&self.power_mode);
 		self.runtime.spawn_background_task(async move {
 			chain_source
-				.continuously_sync_wallets(stop_sync_receiver, sync_cman, sync_cmon, sync_sweeper)
+				.continuously_sync_wallets(
+					stop_sync_receiver,
+					sync_cman,
+					sync_cmon,
+					sync_sweeper,
+					sync_claim_watchdog,
+					sync_power_mode,
+				)
 				.await;
 		});
 
@@ -252,9 +394,13 @@ impl Node {
 			let gossip_sync_store = Arc::clone(&self.kv_store);
 			let gossip_sync_logger = Arc::clone(&self.logger);
 			let gossip_node_metrics = Arc::clone(&self.node_metrics);
+			let gossip_network_graph = Arc::clone(&self.network_graph);
+			let gossip_event_queue = Arc::clone(&self.event_queue);
+			let gossip_power_mode = Arc::clone(&self.power_mode);
 			let mut stop_gossip_sync = self.stop_sender.subscribe();
 			self.runtime.spawn_cancellable_background_task(async move {
 				let mut interval = tokio::time::interval(RGS_SYNC_INTERVAL);
+				let mut initial_sync_completed = false;
 				loop {
 					tokio::select! {
 						_ = stop_gossip_sync.changed() => {
@@ -265,6 +411,9 @@ impl Node {
 							return;
 						}
 						_ = interval.tick() => {
+							if *gossip_power_mode.read().unwrap() == PowerMode::LowPower {
+								continue;
+							}
 							let now = Instant::now();
 							match gossip_source.update_rgs_snapshot().await {
 								Ok(updated_timestamp) => {
@@ -281,6 +430,19 @@ impl Node {
 												log_error!(gossip_sync_logger, "Persistence failed: {}", e);
 											});
 									}
+									if !initial_sync_completed {
+										initial_sync_completed = true;
+										let event = {
+											let graph = gossip_network_graph.read_only();
+											Event::GossipSyncCompleted {
+												total_nodes: graph.nodes().len() as u64,
+												total_channels: graph.channels().len() as u64,
+											}
+										};
+										gossip_event_queue.add_event(event).unwrap_or_else(|e| {
+											log_error!(gossip_sync_logger, "Failed to push to event queue: {}", e);
+										});
+									}
 								}
 								Err(e) => {
 									log_error!(
@@ -304,10 +466,23 @@ impl Node {
 				Arc::clone(&self.kv_store),
 				Arc::clone(&self.logger),
 				Arc::clone(&self.runtime),
+				Arc::clone(&self.power_mode),
 				self.stop_sender.subscribe(),
 			);
 		}
 
+		let scorer_persist_interval = Duration::from_secs(
+			self.config.scorer_persist_interval_secs.max(SCORER_PERSIST_INTERVAL_MINIMUM_SECS),
+		);
+		setup_background_scorer_persistence(
+			scorer_persist_interval,
+			Arc::clone(&self.scorer),
+			Arc::clone(&self.kv_store),
+			Arc::clone(&self.logger),
+			Arc::clone(&self.runtime),
+			self.stop_sender.subscribe(),
+		);
+
 		if let Some(listening_addresses) = &self.config.listening_addresses {
 			// Setup networking
 			let peer_manager_connection_handler = Arc::clone(&self.peer_manager);
@@ -392,9 +567,10 @@ impl Node {
 		let connect_pm = Arc::clone(&self.peer_manager);
 		let connect_logger = Arc::clone(&self.logger);
 		let connect_peer_store = Arc::clone(&self.peer_store);
+		let connect_event_queue = Arc::clone(&self.event_queue);
 		let mut stop_connect = self.stop_sender.subscribe();
 		self.runtime.spawn_cancellable_background_task(async move {
-			let mut interval = tokio::time::interval(PEER_RECONNECTION_INTERVAL);
+			let mut interval = tokio::time::interval(connect_cm.reconnection_interval());
 			interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
 			loop {
 				tokio::select! {
@@ -413,10 +589,16 @@ impl Node {
 								.collect::<Vec<_>>();
 
 							for peer_info in connect_peer_store.list_peers().iter().filter(|info| !pm_peers.contains(&info.node_id)) {
-								let _ = connect_cm.do_connect_peer(
+								let abandoned = connect_cm.reconnect_peer_if_due(
 									peer_info.node_id,
 									peer_info.address.clone(),
 									).await;
+								if abandoned {
+									let event = Event::PeerConnectionAbandoned { node_id: peer_info.node_id };
+									connect_event_queue.add_event(event).unwrap_or_else(|e| {
+										log_error!(connect_logger, "Failed to push to event queue: {}", e);
+									});
+								}
 							}
 						}
 				}
@@ -426,12 +608,210 @@ impl Node {
 		// Regularly broadcast node announcements.
 		let bcast_cm = Arc::clone(&self.channel_manager);
 		let bcast_pm = Arc::clone(&self.peer_manager);
+		if let Some(onion_service) = self.onion_service.as_ref() {
+			let onion_service = Arc::clone(onion_service);
+			let stop_onion_service = self.stop_sender.subscribe();
+			self.runtime.spawn_cancellable_background_task(async move {
+				onion_service.run(stop_onion_service).await
+			});
+		}
+
+		if let Some(vss_store) = self.vss_store.as_ref() {
+			// Surface hard failures of our remote storage backend.
+			let vss_store = Arc::clone(vss_store);
+			let storage_event_queue = Arc::clone(&self.event_queue);
+			let storage_logger = Arc::clone(&self.logger);
+			let mut stop_storage_monitor = self.stop_sender.subscribe();
+			self.runtime.spawn_cancellable_background_task(async move {
+				loop {
+					tokio::select! {
+						_ = stop_storage_monitor.changed() => {
+							log_debug!(
+								storage_logger,
+								"Stopping monitoring storage availability.",
+							);
+							return;
+						}
+						reason = vss_store.wait_for_storage_unavailable() => {
+							log_error!(storage_logger, "Remote storage is unavailable: {}", reason);
+							// We can't persist to the failing store, so we deliver the event
+							// without persisting it.
+							let event = Event::StorageUnavailable { reason };
+							storage_event_queue.add_ephemeral_event(event);
+						}
+					}
+				}
+			});
+		}
+
+		// Regularly execute recurring payments that are due.
+		let recurring_payment_scheduler = RecurringPaymentScheduler::new(
+			Arc::clone(&self.recurring_payment_store),
+			Arc::clone(&self.payment_store),
+			Bolt12Payment::new(
+				Arc::clone(&self.channel_manager),
+				Arc::clone(&self.payment_store),
+				Arc::clone(&self.payment_idempotency_keys),
+				Arc::clone(&self.default_offer),
+				Arc::clone(&self.kv_store),
+				Arc::clone(&self.pending_bolt12_invoices),
+				Arc::clone(&self.runtime),
+				Arc::clone(&self.is_running),
+				Arc::clone(&self.logger),
+				self.async_payments_role,
+			),
+			Arc::clone(&self.event_queue),
+			Arc::clone(&self.logger),
+		);
+		let recurring_payment_logger = Arc::clone(&self.logger);
+		let mut stop_recurring_payments = self.stop_sender.subscribe();
+		self.runtime.spawn_cancellable_background_task(async move {
+			let mut interval = tokio::time::interval(RECURRING_PAYMENT_CHECK_INTERVAL);
+			// Skip the immediate first tick, giving the node time to finish starting up.
+			interval.reset();
+			loop {
+				tokio::select! {
+					_ = stop_recurring_payments.changed() => {
+						log_debug!(
+							recurring_payment_logger,
+							"Stopping executing recurring payments.",
+						);
+						return;
+					}
+					_ = interval.tick() => {
+						recurring_payment_scheduler.process_schedules();
+					}
+				}
+			}
+		});
+
+		let claim_timeout_watchdog = Arc::clone(&self.claim_watchdog);
+		let claim_timeout_logger = Arc::clone(&self.logger);
+		let mut stop_claim_timeouts = self.stop_sender.subscribe();
+		self.runtime.spawn_cancellable_background_task(async move {
+			let mut interval = tokio::time::interval(CLAIM_TIMEOUT_CHECK_INTERVAL);
+			interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+			loop {
+				tokio::select! {
+					_ = stop_claim_timeouts.changed() => {
+						log_debug!(
+							claim_timeout_logger,
+							"Stopping checking claim timeouts of held payments.",
+						);
+						return;
+					}
+					_ = interval.tick() => {
+						claim_timeout_watchdog.check_claim_timeouts();
+					}
+				}
+			}
+		});
+
+		let connection_history_peer_store = Arc::clone(&self.peer_store);
+		let connection_history_logger = Arc::clone(&self.logger);
+		let mut stop_connection_history = self.stop_sender.subscribe();
+		self.runtime.spawn_cancellable_background_task(async move {
+			let mut interval = tokio::time::interval(PEER_CONNECTION_HISTORY_PERSIST_INTERVAL);
+			interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+			loop {
+				tokio::select! {
+					_ = stop_connection_history.changed() => {
+						log_debug!(
+							connection_history_logger,
+							"Stopping persisting peer connection history.",
+						);
+						return;
+					}
+					_ = interval.tick() => {
+						connection_history_peer_store.persist_connection_history_if_dirty();
+					}
+				}
+			}
+		});
+
+		let close_bumps = Arc::clone(&self.pending_channel_close_bumps);
+		let close_bumps_chain_monitor = Arc::clone(&self.chain_monitor);
+		let close_bumps_logger = Arc::clone(&self.logger);
+		let mut stop_close_bumps_pruning = self.stop_sender.subscribe();
+		self.runtime.spawn_cancellable_background_task(async move {
+			let mut interval = tokio::time::interval(PENDING_CHANNEL_CLOSE_BUMPS_PRUNE_INTERVAL);
+			interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+			loop {
+				tokio::select! {
+					_ = stop_close_bumps_pruning.changed() => {
+						log_debug!(
+							close_bumps_logger,
+							"Stopping pruning pending channel close bumps.",
+						);
+						return;
+					}
+					_ = interval.tick() => {
+						close_bumps.lock().unwrap().retain(|channel_id, bump_event| {
+							!is_commitment_tx_confirmed(&close_bumps_chain_monitor, channel_id, bump_event)
+						});
+					}
+				}
+			}
+		});
+
+		if self.config.enable_bolt12_invoice_requests {
+			let bolt12_payment = self.bolt12_payment();
+			let bolt12_invoice_logger = Arc::clone(&self.logger);
+			let mut stop_bolt12_invoice_expiry = self.stop_sender.subscribe();
+			self.runtime.spawn_cancellable_background_task(async move {
+				let mut interval = tokio::time::interval(BOLT12_INVOICE_EXPIRY_CHECK_INTERVAL);
+				interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+				loop {
+					tokio::select! {
+						_ = stop_bolt12_invoice_expiry.changed() => {
+							log_debug!(
+								bolt12_invoice_logger,
+								"Stopping abandoning expired BOLT12 invoices.",
+							);
+							return;
+						}
+						_ = interval.tick() => {
+							bolt12_payment.abandon_expired_invoices();
+						}
+					}
+				}
+			});
+		}
+
+		if let Some(watchtower_client) = self.watchtower_client.as_ref() {
+			let watchtower_client = Arc::clone(watchtower_client);
+			let watchtower_cm = Arc::clone(&self.channel_manager);
+			let watchtower_logger = Arc::clone(&self.logger);
+			let mut stop_watchtower_sync = self.stop_sender.subscribe();
+			self.runtime.spawn_cancellable_background_task(async move {
+				let mut interval = tokio::time::interval(WATCHTOWER_SYNC_INTERVAL);
+				interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+				loop {
+					tokio::select! {
+						_ = stop_watchtower_sync.changed() => {
+							log_debug!(
+								watchtower_logger,
+								"Stopping syncing channel states to watchtower.",
+							);
+							return;
+						}
+						_ = interval.tick() => {
+							let current_height = watchtower_cm.current_best_block().height;
+							watchtower_client.sync(current_height).await;
+						}
+					}
+				}
+			});
+		}
+
 		let bcast_config = Arc::clone(&self.config);
+		let bcast_onion_service = self.onion_service.clone();
 		let bcast_store = Arc::clone(&self.kv_store);
 		let bcast_logger = Arc::clone(&self.logger);
 		let bcast_node_metrics = Arc::clone(&self.node_metrics);
 		let mut stop_bcast = self.stop_sender.subscribe();
-		let node_alias = self.config.node_alias.clone();
+		let bcast_node_alias = Arc::clone(&self.node_alias);
+		let bcast_power_mode = Arc::clone(&self.power_mode);
 		if may_announce_channel(&self.config).is_ok() {
 			self.runtime.spawn_cancellable_background_task(async move {
 				// We check every 30 secs whether our last broadcast is NODE_ANN_BCAST_INTERVAL away.
@@ -449,6 +829,9 @@ impl Node {
 							return;
 						}
 						_ = interval.tick() => {
+							if *bcast_power_mode.read().unwrap() == PowerMode::LowPower {
+								continue;
+							}
 							let skip_broadcast = match bcast_node_metrics.read().unwrap().latest_node_announcement_broadcast_timestamp {
 								Some(latest_bcast_time_secs) => {
 									// Skip if the time hasn't elapsed yet.
@@ -475,7 +858,7 @@ impl Node {
 								continue;
 							}
 
-							let addresses = if let Some(announcement_addresses) = bcast_config.announcement_addresses.clone() {
+							let mut addresses = if let Some(announcement_addresses) = bcast_config.announcement_addresses.clone() {
 								announcement_addresses
 							} else if let Some(listening_addresses) = bcast_config.listening_addresses.clone() {
 								listening_addresses
@@ -484,8 +867,15 @@ impl Node {
 								continue;
 							};
 
-							if let Some(node_alias) = node_alias.as_ref() {
-								bcast_pm.broadcast_node_announcement([0; 3], node_alias.0, addresses);
+							if let Some(onion_address) = bcast_onion_service.as_ref().and_then(|s| s.onion_address()) {
+								if !addresses.contains(&onion_address) {
+									addresses.push(onion_address);
+								}
+							}
+
+							let node_alias_opt = *bcast_node_alias.read().unwrap();
+							if let Some(node_alias) = node_alias_opt {
+								bcast_pm.broadcast_node_announcement(bcast_config.node_color, node_alias.0, addresses);
 
 								let unix_time_secs_opt =
 									SystemTime::now().duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs());
@@ -513,13 +903,6 @@ impl Node {
 			chain_source.continuously_process_broadcast_queue(stop_tx_bcast).await
 		});
 
-		let bump_tx_event_handler = Arc::new(BumpTransactionEventHandler::new(
-			Arc::clone(&self.tx_broadcaster),
-			Arc::new(LdkWallet::new(Arc::clone(&self.wallet), Arc::clone(&self.logger))),
-			Arc::clone(&self.keys_manager),
-			Arc::clone(&self.logger),
-		));
-
 		let static_invoice_store = if let Some(AsyncPaymentsRole::Server) = self.async_payments_role
 		{
 			Some(StaticInvoiceStore::new(Arc::clone(&self.kv_store)))
@@ -530,14 +913,25 @@ impl Node {
 		let event_handler = Arc::new(EventHandler::new(
 			Arc::clone(&self.event_queue),
 			Arc::clone(&self.wallet),
-			bump_tx_event_handler,
+			Arc::clone(&self.bump_tx_event_handler),
+			Arc::clone(&self.pending_channel_close_bumps),
+			Arc::clone(&self.channel_ready_waiters),
+			Arc::clone(&self.funding_confirmation_targets),
+			Arc::clone(&self.pending_external_fundings),
+			Arc::clone(&self.pending_bolt12_invoices),
 			Arc::clone(&self.channel_manager),
 			Arc::clone(&self.connection_manager),
 			Arc::clone(&self.output_sweeper),
+			Arc::clone(&self.tx_broadcaster),
 			Arc::clone(&self.network_graph),
 			self.liquidity_source.clone(),
 			Arc::clone(&self.payment_store),
+			Arc::clone(&self.closed_channel_store),
+			Arc::clone(&self.payment_retry_strategies),
 			Arc::clone(&self.peer_store),
+			Arc::clone(&self.claim_watchdog),
+			Arc::clone(&self.node_metrics),
+			Arc::clone(&self.kv_store),
 			static_invoice_store,
 			Arc::clone(&self.onion_messenger),
 			self.om_mailbox.clone(),
@@ -555,7 +949,12 @@ impl Node {
 		let background_peer_man = Arc::clone(&self.peer_manager);
 		let background_liquidity_man_opt =
 			self.liquidity_source.as_ref().map(|ls| ls.liquidity_manager());
-		let background_sweeper = Arc::clone(&self.output_sweeper);
+		// If sweeps are to be batched, we take care of triggering them ourselves below.
+		let background_sweeper = if self.config.spendable_output_sweep_config.is_some() {
+			None
+		} else {
+			Some(Arc::clone(&self.output_sweeper))
+		};
 		let background_onion_messenger = Arc::clone(&self.onion_messenger);
 		let background_logger = Arc::clone(&self.logger);
 		let background_error_logger = Arc::clone(&self.logger);
@@ -591,7 +990,7 @@ impl Node {
 				background_gossip_sync,
 				background_peer_man,
 				background_liquidity_man_opt,
-				Some(background_sweeper),
+				background_sweeper,
 				background_logger,
 				Some(background_scorer),
 				sleeper,
@@ -623,8 +1022,205 @@ impl Node {
 					}
 				}
 			});
+
+			// Regularly check the status of pending LSPS1 orders.
+			if let Some((lsp_node_id, lsp_address)) = liquidity_source.get_lsps1_lsp_details() {
+				let mut stop_order_poll = self.stop_sender.subscribe();
+				let order_poll_liquidity = Arc::clone(&liquidity_source);
+				let order_poll_cm = Arc::clone(&self.connection_manager);
+				let order_poll_logger = Arc::clone(&self.logger);
+				self.runtime.spawn_cancellable_background_task(async move {
+					let mut interval = tokio::time::interval(LSPS1_ORDER_STATUS_POLL_INTERVAL);
+					interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+					loop {
+						tokio::select! {
+							_ = stop_order_poll.changed() => {
+								log_debug!(
+									order_poll_logger,
+									"Stopping polling pending LSPS1 orders.",
+								);
+								return;
+							}
+							_ = interval.tick() => {
+								if !order_poll_liquidity.has_pending_lsps1_orders() {
+									continue;
+								}
+
+								if let Err(e) = order_poll_cm
+									.connect_peer_if_necessary(lsp_node_id, lsp_address.clone())
+									.await
+								{
+									log_debug!(
+										order_poll_logger,
+										"Failed to connect to LSP to poll pending orders: {}",
+										e
+									);
+									continue;
+								}
+
+								order_poll_liquidity.lsps1_poll_pending_orders().await;
+							}
+						}
+					}
+				});
+			}
+		}
+
+		// Regularly check for confirmed sweeps of our spendable outputs.
+		let mut stop_sweep_check = self.stop_sender.subscribe();
+		let sweep_tracker = SweepTracker::new(
+			Arc::clone(&self.output_sweeper),
+			Arc::clone(&self.event_queue),
+			self.config.network,
+			Arc::clone(&self.kv_store),
+			Arc::clone(&self.logger),
+		);
+		let sweep_check_logger = Arc::clone(&self.logger);
+		self.runtime.spawn_cancellable_background_task(async move {
+			let mut interval = tokio::time::interval(SWEEP_CONFIRMATION_CHECK_INTERVAL);
+			interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+			loop {
+				tokio::select! {
+					_ = stop_sweep_check.changed() => {
+						log_debug!(
+							sweep_check_logger,
+							"Stopping checking for confirmed sweeps.",
+						);
+						return;
+					}
+					_ = interval.tick() => {
+						sweep_tracker.check_sweeps();
+					}
+				}
+			}
+		});
+
+		// Regularly sweep our spendable outputs in batches, if configured.
+		if let Some(sweep_config) = self.config.spendable_output_sweep_config {
+			let batch_window = Duration::from_secs(sweep_config.batch_window_secs);
+			let mut stop_sweep_batching = self.stop_sender.subscribe();
+			let sweep_batching_sweeper = Arc::clone(&self.output_sweeper);
+			let sweep_batching_logger = Arc::clone(&self.logger);
+			self.runtime.spawn_cancellable_background_task(async move {
+				// The time at which we first saw each of the mature outputs awaiting their sweep.
+				let mut first_seen = HashMap::new();
+				let mut interval = tokio::time::interval(SWEEP_BATCH_CHECK_INTERVAL);
+				interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+				loop {
+					tokio::select! {
+						_ = stop_sweep_batching.changed() => {
+							log_debug!(
+								sweep_batching_logger,
+								"Stopping sweeping spendable outputs.",
+							);
+							return;
+						}
+						_ = interval.tick() => {
+							let now = Instant::now();
+							let cur_height = sweep_batching_sweeper.current_best_block().height;
+							let mut mature_outputs = HashMap::new();
+							for output in sweep_batching_sweeper.tracked_spendable_outputs() {
+								// Outputs still delayed are left alone by the sweeper in any case.
+								if let OutputSpendStatus::PendingInitialBroadcast {
+									delayed_until_height,
+								} = output.status
+								{
+									if delayed_until_height.map_or(true, |height| height <= cur_height)
+									{
+										let seen = first_seen.get(&output.descriptor).copied();
+										mature_outputs.insert(output.descriptor, seen.unwrap_or(now));
+									}
+								}
+							}
+							first_seen = mature_outputs;
+
+							// Hold off on (re-)sweeping until the batch window of the oldest mature
+							// output elapsed, as any sweep would include all mature outputs.
+							if let Some(oldest) = first_seen.values().min() {
+								if now.duration_since(*oldest) < batch_window {
+									continue;
+								}
+								log_info!(
+									sweep_batching_logger,
+									"Sweeping {} spendable outputs in a batch.",
+									first_seen.len()
+								);
+							}
+
+							if sweep_batching_sweeper
+								.regenerate_and_broadcast_spend_if_necessary()
+								.await
+								.is_err()
+							{
+								log_error!(sweep_batching_logger, "Failed to sweep spendable outputs");
+							}
+						}
+					}
+				}
+			});
 		}
 
+		// Regularly abandon channel opens whose funding PSBT wasn't handed back in time.
+		let mut stop_funding_timeouts = self.stop_sender.subscribe();
+		let funding_timeouts_pending_fundings = Arc::clone(&self.pending_external_fundings);
+		let funding_timeouts_cm = Arc::clone(&self.channel_manager);
+		let funding_timeouts_logger = Arc::clone(&self.logger);
+		self.runtime.spawn_cancellable_background_task(async move {
+			let mut interval = tokio::time::interval(EXTERNAL_FUNDING_TIMEOUT_CHECK_INTERVAL);
+			interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+			loop {
+				tokio::select! {
+					_ = stop_funding_timeouts.changed() => {
+						log_debug!(
+							funding_timeouts_logger,
+							"Stopping checking for expired external channel fundings.",
+						);
+						return;
+					}
+					_ = interval.tick() => {
+						let now = Instant::now();
+						let mut expired_fundings = Vec::new();
+						funding_timeouts_pending_fundings.lock().unwrap().retain(|_, pending_funding| {
+							match pending_funding {
+								PendingExternalFunding::AwaitingSignedPsbt {
+									temporary_channel_id,
+									counterparty_node_id,
+									deadline,
+									..
+								} if *deadline <= now => {
+									expired_fundings.push((*temporary_channel_id, *counterparty_node_id));
+									false
+								},
+								_ => true,
+							}
+						});
+
+						for (temporary_channel_id, counterparty_node_id) in expired_fundings {
+							log_info!(
+								funding_timeouts_logger,
+								"Abandoning channel {} as its funding PSBT wasn't provided in time",
+								temporary_channel_id
+							);
+							funding_timeouts_cm
+								.force_close_broadcasting_latest_txn(
+									&temporary_channel_id,
+									&counterparty_node_id,
+									"Funding PSBT wasn't provided in time".to_string(),
+								)
+								.unwrap_or_else(|e| {
+									log_error!(
+										funding_timeouts_logger,
+										"Failed to abandon channel {}: {:?}",
+										temporary_channel_id,
+										e
+									);
+								});
+						}
+					}
+				}
+			}
+		});
+
 		log_info!(self.logger, "Startup complete.");
 		*is_running_lock = true;
 		Ok(())
@@ -632,8 +1228,16 @@ impl Node {
 
 	/// Disconnects all peers, stops all running background tasks, and shuts down [`Node`].
 	///
+	/// Our channels are left open. Use [`Node::close_all_channels`] beforehand to close them.
+	///
+	/// Any event handler registered via [`Node::set_event_handler`] is unregistered.
+	///
 	/// After this returns most API methods will return [`Error::NotRunning`].
 	pub fn stop(&self) -> Result<(), Error> {
+		// Unregister any event handler first, as it might call back into us while we wait for it.
+		let event_dispatcher = self.event_dispatcher.lock().unwrap().take();
+		drop(event_dispatcher);
+
 		let mut is_running_lock = self.is_running.write().unwrap();
 		if !*is_running_lock {
 			return Err(Error::NotRunning);
@@ -663,6 +1267,9 @@ impl Node {
 		self.peer_manager.disconnect_all_peers();
 		log_debug!(self.logger, "Disconnected all network peers.");
 
+		// Persist the connection history including the disconnections above.
+		self.peer_store.persist_connection_history_if_dirty();
+
 		// Wait until non-cancellable background tasks (mod LDK's background processor) are done.
 		self.runtime.wait_on_background_tasks();
 
@@ -696,33 +1303,140 @@ impl Node {
 		Ok(())
 	}
 
-	/// Returns the status of the [`Node`].
-	pub fn status(&self) -> NodeStatus {
-		let is_running = *self.is_running.read().unwrap();
-		let current_best_block = self.channel_manager.current_best_block().into();
-		let locked_node_metrics = self.node_metrics.read().unwrap();
-		let latest_lightning_wallet_sync_timestamp =
-			locked_node_metrics.latest_lightning_wallet_sync_timestamp;
-		let latest_onchain_wallet_sync_timestamp =
-			locked_node_metrics.latest_onchain_wallet_sync_timestamp;
-		let latest_fee_rate_cache_update_timestamp =
-			locked_node_metrics.latest_fee_rate_cache_update_timestamp;
-		let latest_rgs_snapshot_timestamp =
-			locked_node_metrics.latest_rgs_snapshot_timestamp.map(|val| val as u64);
-		let latest_pathfinding_scores_sync_timestamp =
-			locked_node_metrics.latest_pathfinding_scores_sync_timestamp;
-		let latest_node_announcement_broadcast_timestamp =
-			locked_node_metrics.latest_node_announcement_broadcast_timestamp;
-		let latest_channel_monitor_archival_height =
-			locked_node_metrics.latest_channel_monitor_archival_height;
+	/// Closes all our channels, e.g., before shutting down an ephemeral node via [`Node::stop`].
+	///
+	/// We'll first attempt to close all channels cooperatively. Any channels that aren't closed
+	/// within `force_close_after` are then force-closed, after which we wait for the resulting
+	/// commitment transactions to be handed to the chain source for broadcasting.
+	///
+	/// Returns a summary of how each channel was closed. Note that this may include channels the
+	/// counterparty force-closed while we were waiting.
+	///
+	/// [`Node::stop`] never closes any channels itself. Rather than configuring it to do so, call
+	/// this method right before stopping the node. This keeps [`Node::stop`] from blocking on
+	/// channel closures and lets the caller act on the returned summary, which [`Node::stop`]
+	/// couldn't return without changing its signature.
+	///
+	/// **Note:** Force-closing channels locks our funds until the respective timelocks expire. This
+	/// is mostly useful for ephemeral deployments, e.g., test nodes or one-shot jobs, that don't
+	/// want to leave channels open after exiting.
+	pub fn close_all_channels(
+		&self, force_close_after: Duration,
+	) -> Result<CloseAllChannelsSummary, Error> {
+		if !*self.is_running.read().unwrap() {
+			return Err(Error::NotRunning);
+		}
 
-		NodeStatus {
-			is_running,
-			current_best_block,
-			latest_lightning_wallet_sync_timestamp,
-			latest_onchain_wallet_sync_timestamp,
-			latest_fee_rate_cache_update_timestamp,
-			latest_rgs_snapshot_timestamp,
+		let channels = self.channel_manager.list_channels();
+		for channel in &channels {
+			self.channel_manager
+				.close_channel(&channel.channel_id, &channel.counterparty.node_id)
+				.unwrap_or_else(|e| {
+					log_error!(
+						self.logger,
+						"Failed to initiate cooperative close of channel {}: {:?}",
+						channel.channel_id,
+						e
+					);
+				});
+		}
+
+		// We only consider a channel closed once we handled its `ChannelClosed` event, as only then
+		// we know how it was closed.
+		let wait_for_closed_channels = |deadline: Instant| loop {
+			let all_closed =
+				channels.iter().all(|c| self.closed_channel_store.get(&c.channel_id).is_some());
+			if all_closed || Instant::now() >= deadline {
+				break;
+			}
+			std::thread::sleep(CLOSE_ALL_CHANNELS_POLL_INTERVAL);
+		};
+		wait_for_closed_channels(Instant::now() + force_close_after);
+
+		let open_channels = self.channel_manager.list_channels();
+		for channel in &channels {
+			if !open_channels.iter().any(|c| c.channel_id == channel.channel_id) {
+				continue;
+			}
+
+			self.channel_manager
+				.force_close_broadcasting_latest_txn(
+					&channel.channel_id,
+					&channel.counterparty.node_id,
+					"Closing all channels".to_string(),
+				)
+				.unwrap_or_else(|e| {
+					log_error!(
+						self.logger,
+						"Failed to force-close channel {}: {:?}",
+						channel.channel_id,
+						e
+					);
+				});
+		}
+
+		// Give the broadcaster the chance to process any commitment transactions, and ourselves to
+		// handle the `ChannelClosed` events of the channels we just force-closed.
+		let broadcast_deadline = Instant::now() + Duration::from_secs(TX_BROADCAST_TIMEOUT_SECS);
+		wait_for_closed_channels(broadcast_deadline);
+		while !self.tx_broadcaster.all_packages_processed() && Instant::now() < broadcast_deadline {
+			std::thread::sleep(CLOSE_ALL_CHANNELS_POLL_INTERVAL);
+		}
+
+		let mut summary = CloseAllChannelsSummary::default();
+		for channel in &channels {
+			match self.closed_channel_store.get(&channel.channel_id).map(|c| c.close_type) {
+				Some(CloseType::Cooperative) => {
+					summary.cooperatively_closed_channels.push(channel.channel_id)
+				},
+				Some(_) => summary.force_closed_channels.push(channel.channel_id),
+				None => summary.pending_channels.push(channel.channel_id),
+			}
+		}
+
+		log_info!(
+			self.logger,
+			"Closed {} channels cooperatively and {} channels forcefully, {} channels are still pending closure.",
+			summary.cooperatively_closed_channels.len(),
+			summary.force_closed_channels.len(),
+			summary.pending_channels.len(),
+		);
+
+		Ok(summary)
+	}
+
+	/// Returns the status of the [`Node`].
+	pub fn status(&self) -> NodeStatus {
+		let is_running = *self.is_running.read().unwrap();
+		let current_best_block = self.channel_manager.current_best_block().into();
+		// The metrics are persisted, so we only report the syncs that happened since we started.
+		let started_at_unix_secs = *self.started_at_unix_secs.read().unwrap();
+		let since_startup = |timestamp: Option<u64>| {
+			timestamp.filter(|t| started_at_unix_secs.map_or(false, |s| *t >= s))
+		};
+		let locked_node_metrics = self.node_metrics.read().unwrap();
+		let latest_lightning_wallet_sync_timestamp =
+			since_startup(locked_node_metrics.latest_lightning_wallet_sync_timestamp);
+		let latest_onchain_wallet_sync_timestamp =
+			since_startup(locked_node_metrics.latest_onchain_wallet_sync_timestamp);
+		let latest_fee_rate_cache_update_timestamp =
+			since_startup(locked_node_metrics.latest_fee_rate_cache_update_timestamp);
+		let latest_rgs_snapshot_timestamp =
+			locked_node_metrics.latest_rgs_snapshot_timestamp.map(|val| val as u64);
+		let latest_pathfinding_scores_sync_timestamp =
+			since_startup(locked_node_metrics.latest_pathfinding_scores_sync_timestamp);
+		let latest_node_announcement_broadcast_timestamp =
+			since_startup(locked_node_metrics.latest_node_announcement_broadcast_timestamp);
+		let latest_channel_monitor_archival_height =
+			locked_node_metrics.latest_channel_monitor_archival_height;
+
+		NodeStatus {
+			is_running,
+			current_best_block,
+			latest_lightning_wallet_sync_timestamp,
+			latest_onchain_wallet_sync_timestamp,
+			latest_fee_rate_cache_update_timestamp,
+			latest_rgs_snapshot_timestamp,
 			latest_pathfinding_scores_sync_timestamp,
 			latest_node_announcement_broadcast_timestamp,
 			latest_channel_monitor_archival_height,
@@ -734,9 +1448,55 @@ impl Node {
 		self.config.as_ref().clone()
 	}
 
+	/// Enters low-power mode, pausing background syncing and gossip processing.
+	///
+	/// Unlike [`Node::stop`], this keeps our peers and channels connected and events continue to
+	/// be generated and handled. In particular, the following remains functional:
+	/// - receiving payments, as well as forwarding and claiming HTLCs,
+	/// - sending payments, as long as the routes found via our current view of the network graph
+	///   are still viable,
+	/// - opening and closing channels, and connecting to peers.
+	///
+	/// While in low-power mode, the following is degraded:
+	/// - on-chain and Lightning wallet syncing is paused, i.e., balances may be outdated and we
+	///   won't react to on-chain events such as channel closures or expiring HTLCs until we exit
+	///   low-power mode again,
+	/// - fee rate estimates are not updated,
+	/// - gossip received from peers or via Rapid Gossip Sync is not processed and external
+	///   pathfinding scores are not fetched, which may degrade pathfinding,
+	/// - we don't serve gossip to peers and don't broadcast our node announcement.
+	///
+	/// Manual syncing via [`Node::sync_wallets`] is still possible. As low-power mode can delay
+	/// our reaction to on-chain events, it should only be used for limited periods of time.
+	pub fn enter_low_power_mode(&self) {
+		let mut locked_power_mode = self.power_mode.write().unwrap();
+		if *locked_power_mode != PowerMode::LowPower {
+			log_info!(self.logger, "Entering low-power mode.");
+			*locked_power_mode = PowerMode::LowPower;
+		}
+	}
+
+	/// Exits low-power mode, resuming background syncing and gossip processing with their next
+	/// scheduled interval.
+	///
+	/// See [`Node::enter_low_power_mode`] for details.
+	pub fn exit_low_power_mode(&self) {
+		let mut locked_power_mode = self.power_mode.write().unwrap();
+		if *locked_power_mode != PowerMode::Normal {
+			log_info!(self.logger, "Exiting low-power mode.");
+			*locked_power_mode = PowerMode::Normal;
+		}
+	}
+
+	/// Returns the [`PowerMode`] the node is currently operating in.
+	pub fn power_mode(&self) -> PowerMode {
+		*self.power_mode.read().unwrap()
+	}
+
 	/// Returns the next event in the event queue, if currently available.
 	///
-	/// Will return `Some(..)` if an event is available and `None` otherwise.
+	/// Will return `Some(..)` if an event is available and `None` otherwise. This doesn't consume
+	/// the event, i.e., it may also be used to peek at the next event.
 	///
 	/// **Note:** this will always return the same event until handling is confirmed via [`Node::event_handled`].
 	///
@@ -758,6 +1518,46 @@ impl Node {
 		self.event_queue.next_event_async().await
 	}
 
+	/// Returns an [`EventStream`] yielding the events in the event queue.
+	///
+	/// If `auto_ack` is set, each yielded event is automatically marked handled once the next event
+	/// is requested from the stream, unless it was already marked handled via
+	/// [`Node::event_handled`] in the meantime. Otherwise, the stream will keep yielding the same event until
+	/// handling is confirmed via [`Node::event_handled`].
+	///
+	/// **Caution:** Users must handle events as quickly as possible to prevent a large event backlog,
+	/// which can increase the memory footprint of [`Node`].
+	pub fn event_stream(&self, auto_ack: bool) -> EventStream {
+		EventStream::new(Arc::clone(&self.event_queue), auto_ack)
+	}
+
+	/// Registers a [`NodeEventHandler`] that will be handed the events in the event queue.
+	///
+	/// The handler is invoked from a dedicated thread, which allows it to call back into any of
+	/// the [`Node`]'s methods. Each event is automatically marked handled once the handler
+	/// returns `Ok(())`, while events for which an `Err` is returned will be redelivered after a
+	/// short delay.
+	///
+	/// Any previously registered handler is replaced, after waiting for it to finish handling the
+	/// current event. The handler is unregistered when the [`Node`] is stopped, which also releases
+	/// any references it holds, e.g., to the [`Node`] itself.
+	///
+	/// **Note:** While a handler is registered, users must neither consume events via the event
+	/// queue API, e.g., [`Node::next_event`], nor confirm their handling via
+	/// [`Node::event_handled`].
+	pub fn set_event_handler(&self, handler: Arc<dyn NodeEventHandler>) {
+		let mut dispatcher_lock = self.event_dispatcher.lock().unwrap();
+		// Make sure the previous dispatcher stopped before spawning a new one, so that no event is
+		// handled twice. We don't hold the lock while waiting, as the handler might call back
+		// into us.
+		while let Some(previous_dispatcher) = dispatcher_lock.take() {
+			drop(dispatcher_lock);
+			drop(previous_dispatcher);
+			dispatcher_lock = self.event_dispatcher.lock().unwrap();
+		}
+		*dispatcher_lock = Some(EventDispatcher::spawn(Arc::clone(&self.event_queue), handler));
+	}
+
 	/// Returns the next event in the event queue.
 	///
 	/// Will block the current thread until the next event is available.
@@ -770,8 +1570,29 @@ impl Node {
 		self.event_queue.wait_next_event()
 	}
 
+	/// Returns the next event in the event queue, waiting for at most the given `timeout`.
+	///
+	/// Will block the current thread until the next event is available or the `timeout` elapsed,
+	/// in which case `None` is returned.
+	///
+	/// **Note:** this will always return the same event until handling is confirmed via [`Node::event_handled`].
+	///
+	/// **Caution:** Users must handle events as quickly as possible to prevent a large event backlog,
+	/// which can increase the memory footprint of [`Node`].
+	pub fn wait_next_event_timeout(&self, timeout: Duration) -> Option<Event> {
+		self.event_queue.wait_next_event_timeout(timeout)
+	}
+
+	/// Returns the number of events in the event queue that have yet to be confirmed handled.
+	pub fn pending_event_count(&self) -> u64 {
+		self.event_queue.pending_event_count() as u64
+	}
+
 	/// Confirm the last retrieved event handled.
 	///
+	/// Events that weren't confirmed handled will be redelivered in their original order after a
+	/// restart. See [`Event`] for more details on the delivery guarantees.
+	///
 	/// **Note:** This **MUST** be called after each event has been handled.
 	pub fn event_handled(&self) -> Result<(), Error> {
 		self.event_queue.event_handled().map_err(|e| {
@@ -796,15 +1617,98 @@ impl Node {
 
 	/// Returns the addresses that the node will announce to the network.
 	pub fn announcement_addresses(&self) -> Option<Vec<SocketAddress>> {
-		self.config
+		let mut addresses = self
+			.config
 			.announcement_addresses
 			.clone()
-			.or_else(|| self.config.listening_addresses.clone())
+			.or_else(|| self.config.listening_addresses.clone())?;
+
+		if let Some(onion_address) = self.onion_service.as_ref().and_then(|s| s.onion_address()) {
+			if !addresses.contains(&onion_address) {
+				addresses.push(onion_address);
+			}
+		}
+
+		Some(addresses)
 	}
 
 	/// Returns our node alias.
 	pub fn node_alias(&self) -> Option<NodeAlias> {
-		self.config.node_alias
+		*self.node_alias.read().unwrap()
+	}
+
+	/// Updates the node alias that will be used when broadcasting announcements to the gossip
+	/// network.
+	///
+	/// The provided alias must be a valid UTF-8 string and no longer than 32 bytes in total. Our
+	/// node announcement will be re-broadcast with the new alias shortly after.
+	///
+	/// The updated alias is persisted and will take precedence over [`Config::node_alias`] on
+	/// subsequent restarts.
+	///
+	/// **Note**: The alias may only be updated if the node was configured to announce itself, i.e.,
+	/// if [`Config::node_alias`] and [`Config::listening_addresses`] were set on startup.
+	pub fn set_node_alias(&self, node_alias: String) -> Result<(), Error> {
+		let node_alias = sanitize_alias(&node_alias).map_err(|_| Error::InvalidNodeAlias)?;
+
+		if let Err(err) = may_announce_channel(&self.config) {
+			log_error!(
+				self.logger,
+				"Failed to update node alias as the node isn't configured for node announcements: {}",
+				err
+			);
+			return Err(Error::InvalidNodeAlias);
+		}
+
+		write_node_alias(&node_alias, Arc::clone(&self.kv_store), Arc::clone(&self.logger))?;
+		*self.node_alias.write().unwrap() = Some(node_alias);
+
+		// Reset the broadcast timestamp, so we'll re-broadcast our announcement on the next tick.
+		let mut locked_node_metrics = self.node_metrics.write().unwrap();
+		locked_node_metrics.latest_node_announcement_broadcast_timestamp = None;
+		write_node_metrics(
+			&*locked_node_metrics,
+			Arc::clone(&self.kv_store),
+			Arc::clone(&self.logger),
+		)?;
+
+		log_info!(self.logger, "Updated node alias to {}", node_alias);
+		Ok(())
+	}
+
+	/// Returns the information we currently advertise, or would advertise, to the network in our
+	/// node announcement.
+	///
+	/// If the node isn't configured to announce itself, e.g., as [`Config::node_alias`] or
+	/// [`Config::listening_addresses`] are unset, the announcement we would broadcast is returned
+	/// with [`OwnNodeAnnouncementInfo::is_announced`] set to `false`.
+	///
+	/// Note that we only broadcast our node announcement once we have a public channel that is
+	/// ready. See [`NodeStatus::latest_node_announcement_broadcast_timestamp`] for when we last did
+	/// so.
+	pub fn node_announcement_info(&self) -> OwnNodeAnnouncementInfo {
+		let is_announced = may_announce_channel(&self.config).is_ok();
+		let alias = *self.node_alias.read().unwrap();
+		let addresses = self.announcement_addresses().unwrap_or_default();
+
+		let mut features = self.channel_manager.node_features();
+		features |= self.onion_messenger.provided_node_features();
+		if let Some(p2p_gossip_sync) = self.gossip_source.p2p_gossip_sync() {
+			features |= p2p_gossip_sync.provided_node_features();
+		}
+		if let Some(liquidity_source) = self.liquidity_source.as_ref() {
+			features |= liquidity_source.liquidity_manager().provided_node_features();
+		}
+		self.config.advertised_features().clear_disabled_node_features(&mut features);
+
+		OwnNodeAnnouncementInfo {
+			node_id: self.node_id(),
+			alias,
+			color: self.config.node_color,
+			addresses,
+			features,
+			is_announced,
+		}
 	}
 
 	/// Returns a payment handler allowing to create and pay [BOLT 11] invoices.
@@ -815,10 +1719,15 @@ impl Node {
 		Bolt11Payment::new(
 			Arc::clone(&self.runtime),
 			Arc::clone(&self.channel_manager),
+			Arc::clone(&self.router),
+			Arc::clone(&self.keys_manager),
 			Arc::clone(&self.connection_manager),
 			self.liquidity_source.clone(),
 			Arc::clone(&self.payment_store),
+			Arc::clone(&self.payment_retry_strategies),
+			Arc::clone(&self.payment_idempotency_keys),
 			Arc::clone(&self.peer_store),
+			Arc::clone(&self.claim_watchdog),
 			Arc::clone(&self.config),
 			Arc::clone(&self.is_running),
 			Arc::clone(&self.logger),
@@ -833,10 +1742,15 @@ impl Node {
 		Arc::new(Bolt11Payment::new(
 			Arc::clone(&self.runtime),
 			Arc::clone(&self.channel_manager),
+			Arc::clone(&self.router),
+			Arc::clone(&self.keys_manager),
 			Arc::clone(&self.connection_manager),
 			self.liquidity_source.clone(),
 			Arc::clone(&self.payment_store),
+			Arc::clone(&self.payment_retry_strategies),
+			Arc::clone(&self.payment_idempotency_keys),
 			Arc::clone(&self.peer_store),
+			Arc::clone(&self.claim_watchdog),
 			Arc::clone(&self.config),
 			Arc::clone(&self.is_running),
 			Arc::clone(&self.logger),
@@ -851,6 +1765,11 @@ impl Node {
 		Bolt12Payment::new(
 			Arc::clone(&self.channel_manager),
 			Arc::clone(&self.payment_store),
+			Arc::clone(&self.payment_idempotency_keys),
+			Arc::clone(&self.default_offer),
+			Arc::clone(&self.kv_store),
+			Arc::clone(&self.pending_bolt12_invoices),
+			Arc::clone(&self.runtime),
 			Arc::clone(&self.is_running),
 			Arc::clone(&self.logger),
 			self.async_payments_role,
@@ -865,6 +1784,11 @@ impl Node {
 		Arc::new(Bolt12Payment::new(
 			Arc::clone(&self.channel_manager),
 			Arc::clone(&self.payment_store),
+			Arc::clone(&self.payment_idempotency_keys),
+			Arc::clone(&self.default_offer),
+			Arc::clone(&self.kv_store),
+			Arc::clone(&self.pending_bolt12_invoices),
+			Arc::clone(&self.runtime),
 			Arc::clone(&self.is_running),
 			Arc::clone(&self.logger),
 			self.async_payments_role,
@@ -876,8 +1800,11 @@ impl Node {
 	pub fn spontaneous_payment(&self) -> SpontaneousPayment {
 		SpontaneousPayment::new(
 			Arc::clone(&self.channel_manager),
+			Arc::clone(&self.router),
 			Arc::clone(&self.keys_manager),
 			Arc::clone(&self.payment_store),
+			Arc::clone(&self.payment_retry_strategies),
+			Arc::clone(&self.payment_idempotency_keys),
 			Arc::clone(&self.config),
 			Arc::clone(&self.is_running),
 			Arc::clone(&self.logger),
@@ -889,8 +1816,11 @@ impl Node {
 	pub fn spontaneous_payment(&self) -> Arc<SpontaneousPayment> {
 		Arc::new(SpontaneousPayment::new(
 			Arc::clone(&self.channel_manager),
+			Arc::clone(&self.router),
 			Arc::clone(&self.keys_manager),
 			Arc::clone(&self.payment_store),
+			Arc::clone(&self.payment_retry_strategies),
+			Arc::clone(&self.payment_idempotency_keys),
 			Arc::clone(&self.config),
 			Arc::clone(&self.is_running),
 			Arc::clone(&self.logger),
@@ -955,6 +1885,32 @@ impl Node {
 		))
 	}
 
+	/// Returns a payment handler allowing to pay to and withdraw from [LNURL] services.
+	///
+	/// [LNURL]: https://github.com/lnurl/luds
+	#[cfg(all(feature = "lnurl", not(feature = "uniffi")))]
+	pub fn lnurl(&self) -> Lnurl {
+		Lnurl::new(
+			self.bolt11_payment().into(),
+			Arc::clone(&self.runtime),
+			Arc::clone(&self.is_running),
+			Arc::clone(&self.logger),
+		)
+	}
+
+	/// Returns a payment handler allowing to pay to and withdraw from [LNURL] services.
+	///
+	/// [LNURL]: https://github.com/lnurl/luds
+	#[cfg(feature = "uniffi")]
+	pub fn lnurl(&self) -> Arc<Lnurl> {
+		Arc::new(Lnurl::new(
+			self.bolt11_payment(),
+			Arc::clone(&self.runtime),
+			Arc::clone(&self.is_running),
+			Arc::clone(&self.logger),
+		))
+	}
+
 	/// Returns a liquidity handler allowing to request channels via the [bLIP-51 / LSPS1] protocol.
 	///
 	/// [bLIP-51 / LSPS1]: https://github.com/lightning/blips/blob/master/blip-0051.md
@@ -983,9 +1939,220 @@ impl Node {
 		))
 	}
 
+	/// Returns a liquidity handler allowing to inspect the fees of JIT channels opened via the
+	/// [bLIP-52 / LSPS2] protocol.
+	///
+	/// [bLIP-52 / LSPS2]: https://github.com/lightning/blips/blob/master/blip-0052.md
+	#[cfg(not(feature = "uniffi"))]
+	pub fn lsps2_liquidity(&self) -> LSPS2Liquidity {
+		LSPS2Liquidity::new(
+			Arc::clone(&self.runtime),
+			Arc::clone(&self.connection_manager),
+			self.liquidity_source.clone(),
+			Arc::clone(&self.logger),
+		)
+	}
+
+	/// Returns a liquidity handler allowing to inspect the fees of JIT channels opened via the
+	/// [bLIP-52 / LSPS2] protocol.
+	///
+	/// [bLIP-52 / LSPS2]: https://github.com/lightning/blips/blob/master/blip-0052.md
+	#[cfg(feature = "uniffi")]
+	pub fn lsps2_liquidity(&self) -> Arc<LSPS2Liquidity> {
+		Arc::new(LSPS2Liquidity::new(
+			Arc::clone(&self.runtime),
+			Arc::clone(&self.connection_manager),
+			self.liquidity_source.clone(),
+			Arc::clone(&self.logger),
+		))
+	}
+
 	/// Retrieve a list of known channels.
 	pub fn list_channels(&self) -> Vec<ChannelDetails> {
-		self.channel_manager.list_channels().into_iter().map(|c| c.into()).collect()
+		self.channel_manager
+			.list_channels()
+			.into_iter()
+			.map(|c| {
+				let mut details: ChannelDetails = c.into();
+				details.label = self.label_store.channel_label(&details.channel_id);
+				details
+			})
+			.collect()
+	}
+
+	/// Sets a local, human-readable label for the channel with the given [`ChannelId`].
+	///
+	/// The label will be persisted and returned via [`ChannelDetails::label`]. It is purely local
+	/// and will never be shared with the network. Passing an empty `label` clears it. Labels of
+	/// channels that were closed are dropped on the next start.
+	///
+	/// Returns [`Error::InvalidLabel`] if the label exceeds 64 bytes or contains control
+	/// characters, and [`Error::ChannelNotFound`] if we don't know the channel.
+	pub fn set_channel_label(&self, channel_id: &ChannelId, label: String) -> Result<(), Error> {
+		if !label.is_empty()
+			&& !self.channel_manager.list_channels().iter().any(|c| &c.channel_id == channel_id)
+		{
+			log_error!(self.logger, "Failed to set label of unknown channel {}.", channel_id);
+			return Err(Error::ChannelNotFound);
+		}
+		self.label_store.set_channel_label(*channel_id, label)
+	}
+
+	/// Sets a local, human-readable label for the peer with the given node id.
+	///
+	/// The label will be persisted and returned via [`PeerDetails::label`]. It is purely local and
+	/// will never be shared with the network. Passing an empty `label` clears it. Labels of peers
+	/// we neither persisted nor have a channel with are dropped on the next start.
+	///
+	/// Returns [`Error::InvalidLabel`] if the label exceeds 64 bytes or contains control
+	/// characters.
+	pub fn set_peer_label(&self, node_id: PublicKey, label: String) -> Result<(), Error> {
+		self.label_store.set_peer_label(node_id, label)
+	}
+
+	/// Retrieve a list of channels that have been closed.
+	///
+	/// The details of a channel are recorded once we're notified about it being closed, i.e., when
+	/// we emit a corresponding [`Event::ChannelClosed`].
+	pub fn list_closed_channels(&self) -> Vec<ClosedChannelDetails> {
+		self.closed_channel_store.list_filter(|_| true)
+	}
+
+	/// Schedule recurring payments of `amount_msat` to the given [BOLT 12] offer.
+	///
+	/// On each `interval`, a fresh invoice is requested from the offer's issuer and paid, starting
+	/// shortly after this call. A new execution is only started once the payment of the prior one
+	/// has succeeded or failed. For each execution, an [`Event::RecurringPaymentExecuted`] or
+	/// [`Event::RecurringPaymentFailed`] is emitted. If `max_payments` is set, the schedule ends
+	/// once the given number of payments succeeded.
+	///
+	/// The schedule is persisted and will resume after restarts. Note that executions missed while
+	/// the node was offline are not caught up on.
+	///
+	/// [BOLT 12]: https://github.com/lightning/bolts/blob/master/12-offer-encoding.md
+	#[cfg(not(feature = "uniffi"))]
+	pub fn schedule_recurring_payment(
+		&self, offer: &lightning::offers::offer::Offer, amount_msat: u64,
+		interval: RecurrenceInterval, max_payments: Option<u32>,
+	) -> Result<RecurringPaymentId, Error> {
+		self.schedule_recurring_payment_internal(offer, amount_msat, interval, max_payments)
+	}
+
+	/// Schedule recurring payments of `amount_msat` to the given [BOLT 12] offer.
+	///
+	/// On each `interval`, a fresh invoice is requested from the offer's issuer and paid, starting
+	/// shortly after this call. A new execution is only started once the payment of the prior one
+	/// has succeeded or failed. For each execution, an [`Event::RecurringPaymentExecuted`] or
+	/// [`Event::RecurringPaymentFailed`] is emitted. If `max_payments` is set, the schedule ends
+	/// once the given number of payments succeeded.
+	///
+	/// The schedule is persisted and will resume after restarts. Note that executions missed while
+	/// the node was offline are not caught up on.
+	///
+	/// [BOLT 12]: https://github.com/lightning/bolts/blob/master/12-offer-encoding.md
+	#[cfg(feature = "uniffi")]
+	pub fn schedule_recurring_payment(
+		&self, offer: &Arc<Offer>, amount_msat: u64, interval: RecurrenceInterval,
+		max_payments: Option<u32>,
+	) -> Result<RecurringPaymentId, Error> {
+		self.schedule_recurring_payment_internal(
+			maybe_deref(offer),
+			amount_msat,
+			interval,
+			max_payments,
+		)
+	}
+
+	fn schedule_recurring_payment_internal(
+		&self, offer: &lightning::offers::offer::Offer, amount_msat: u64,
+		interval: RecurrenceInterval, max_payments: Option<u32>,
+	) -> Result<RecurringPaymentId, Error> {
+		if interval.as_secs() == 0 {
+			log_error!(
+				self.logger,
+				"Failed to schedule recurring payment: interval must not be zero."
+			);
+			return Err(Error::InvalidRecurrenceInterval);
+		}
+
+		match offer.amount() {
+			Some(LdkOfferAmount::Bitcoin { amount_msats }) if amount_msat < amount_msats => {
+				log_error!(
+					self.logger,
+					"Failed to schedule recurring payment as the given amount needs to be at least the offer amount: required {}msat, gave {}msat.",
+					amount_msats,
+					amount_msat
+				);
+				return Err(Error::InvalidAmount);
+			},
+			Some(LdkOfferAmount::Bitcoin { .. }) => {},
+			Some(LdkOfferAmount::Currency { .. }) => {
+				log_error!(self.logger, "Failed to schedule recurring payment as the provided offer was denominated in an unsupported currency.");
+				return Err(Error::UnsupportedCurrency);
+			},
+			None if amount_msat == 0 => {
+				log_error!(
+					self.logger,
+					"Failed to schedule recurring payment: amount must not be zero."
+				);
+				return Err(Error::InvalidAmount);
+			},
+			None => {},
+		}
+
+		let id = RecurringPaymentId(rand::thread_rng().gen::<[u8; 32]>());
+		let now_secs = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+		let details = RecurringPaymentDetails {
+			id,
+			offer_id: offer.id(),
+			amount_msat,
+			interval,
+			max_payments,
+			payments_made: 0,
+			next_payment_at_timestamp: now_secs,
+			pending_payment_id: None,
+		};
+		let schedule = RecurringPaymentSchedule { offer: offer.to_string(), details };
+		self.recurring_payment_store.insert(schedule)?;
+
+		log_info!(
+			self.logger,
+			"Scheduled recurring payment {} of {}msat to offer {}",
+			hex_utils::to_string(&id.0),
+			amount_msat,
+			offer
+		);
+		Ok(id)
+	}
+
+	/// Cancel a schedule of recurring payments previously created via
+	/// [`Node::schedule_recurring_payment`].
+	///
+	/// A payment of the current execution that is still in flight will not be aborted.
+	pub fn cancel_recurring_payment(
+		&self, recurring_payment_id: &RecurringPaymentId,
+	) -> Result<(), Error> {
+		if self.recurring_payment_store.get(recurring_payment_id).is_none() {
+			log_error!(
+				self.logger,
+				"Failed to cancel unknown recurring payment {}",
+				hex_utils::to_string(&recurring_payment_id.0)
+			);
+			return Err(Error::InvalidPaymentId);
+		}
+
+		self.recurring_payment_store.remove(recurring_payment_id)?;
+		log_info!(
+			self.logger,
+			"Cancelled recurring payment {}",
+			hex_utils::to_string(&recurring_payment_id.0)
+		);
+		Ok(())
+	}
+
+	/// Retrieve a list of the currently active schedules of recurring payments.
+	pub fn list_recurring_payments(&self) -> Vec<RecurringPaymentDetails> {
+		self.recurring_payment_store.list_filter(|_| true).into_iter().map(|s| s.details).collect()
 	}
 
 	/// Connect to a node on the peer-to-peer network.
@@ -998,7 +2165,7 @@ impl Node {
 			return Err(Error::NotRunning);
 		}
 
-		let peer_info = PeerInfo { node_id, address };
+		let peer_info = PeerInfo::new(node_id, address);
 
 		let con_node_id = peer_info.node_id;
 		let con_addr = peer_info.address.clone();
@@ -1019,38 +2186,168 @@ impl Node {
 		Ok(())
 	}
 
-	/// Disconnects the peer with the given node id.
+	/// Connect to a node on the peer-to-peer network, giving up after `timeout_secs` seconds.
 	///
-	/// Will also remove the peer from the peer store, i.e., after this has been called we won't
-	/// try to reconnect on restart.
-	pub fn disconnect(&self, counterparty_node_id: PublicKey) -> Result<(), Error> {
+	/// Returns a [`ConnectResult`] indicating whether a new connection was established, we were
+	/// already connected, or why the connection attempt failed. If the timeout expires, the
+	/// connection attempt is aborted. Note that [`Node::connect`] doesn't apply any timeout.
+	///
+	/// If `persist` is set to `true`, we'll remember the peer and reconnect to it on restart,
+	/// unless the connection attempt failed.
+	pub fn connect_with_timeout(
+		&self, node_id: PublicKey, address: SocketAddress, persist: bool, timeout_secs: u64,
+	) -> Result<ConnectResult, Error> {
 		if !*self.is_running.read().unwrap() {
 			return Err(Error::NotRunning);
 		}
 
-		log_info!(self.logger, "Disconnecting peer {}..", counterparty_node_id);
+		let peer_info = PeerInfo::new(node_id, address);
 
-		match self.peer_store.remove_peer(&counterparty_node_id) {
-			Ok(()) => {},
-			Err(e) => {
-				log_error!(self.logger, "Failed to remove peer {}: {}", counterparty_node_id, e)
+		let con_node_id = peer_info.node_id;
+		let con_addr = peer_info.address.clone();
+		let con_cm = Arc::clone(&self.connection_manager);
+		let timeout = Duration::from_secs(timeout_secs);
+
+		// We need to use our main runtime here as a local runtime might not be around to poll
+		// connection futures going forward.
+		let res = self.runtime.block_on(async move {
+			con_cm.connect_peer_with_timeout(con_node_id, con_addr, timeout).await
+		});
+
+		match res {
+			ConnectResult::Connected | ConnectResult::AlreadyConnected => {
+				log_info!(
+					self.logger,
+					"Connected to peer {}@{}. ",
+					peer_info.node_id,
+					peer_info.address
+				);
+
+				if persist {
+					self.peer_store.add_peer(peer_info)?;
+				}
+			},
+			ConnectResult::Failed { reason } => {
+				log_error!(
+					self.logger,
+					"Failed to connect to peer {}@{}: {:?}",
+					peer_info.node_id,
+					peer_info.address,
+					reason
+				);
 			},
 		}
 
-		self.peer_manager.disconnect_by_node_id(counterparty_node_id);
-		Ok(())
+		Ok(res)
+	}
+
+	/// Disconnects the peer with the given node id.
+	///
+	/// Will also remove the peer from the peer store, i.e., after this has been called we won't
+	/// try to reconnect on restart.
+	///
+	/// Note that this only drops the transport connection, any channels with the peer will remain
+	/// open. To keep the peer from reconnecting to us, add it to the
+	/// [`PeerConnectionPolicy::denied_peers`] via [`Node::set_peer_connection_policy`].
+	pub fn disconnect(&self, counterparty_node_id: PublicKey) -> Result<(), Error> {
+		if !*self.is_running.read().unwrap() {
+			return Err(Error::NotRunning);
+		}
+
+		log_info!(self.logger, "Disconnecting peer {}..", counterparty_node_id);
+
+		match self.peer_store.remove_peer(&counterparty_node_id) {
+			Ok(()) => {},
+			Err(e) => {
+				log_error!(self.logger, "Failed to remove peer {}: {}", counterparty_node_id, e)
+			},
+		}
+
+		self.peer_manager.disconnect_by_node_id(counterparty_node_id);
+		Ok(())
+	}
+
+	/// Removes the peer with the given node id from the peer store and stops trying to reconnect
+	/// to it.
+	///
+	/// Unlike [`Node::disconnect`], this doesn't drop any existing connection to the peer, it
+	/// merely keeps us from reconnecting once the connection is lost, including after restarts.
+	pub fn forget_peer(&self, counterparty_node_id: PublicKey) -> Result<(), Error> {
+		if !*self.is_running.read().unwrap() {
+			return Err(Error::NotRunning);
+		}
+
+		log_info!(self.logger, "Forgetting peer {}..", counterparty_node_id);
+
+		self.peer_store.remove_peer(&counterparty_node_id).map_err(|e| {
+			log_error!(self.logger, "Failed to remove peer {}: {}", counterparty_node_id, e);
+			e
+		})?;
+		self.connection_manager.clear_connection_attempts(&counterparty_node_id);
+		Ok(())
+	}
+
+	/// Returns the policy currently deciding which peers may open inbound connections to us.
+	pub fn peer_connection_policy(&self) -> PeerConnectionPolicy {
+		self.peer_connection_policy.read().unwrap().clone()
+	}
+
+	/// Updates the policy deciding which peers may open inbound connections to us.
+	///
+	/// The policy is persisted and takes precedence over [`Config::peer_connection_policy`] across
+	/// restarts. Any currently connected inbound peers that are disallowed by the new policy will be
+	/// disconnected, though their channels will remain open.
+	pub fn set_peer_connection_policy(&self, policy: PeerConnectionPolicy) -> Result<(), Error> {
+		{
+			let mut locked_policy = self.peer_connection_policy.write().unwrap();
+			write_peer_connection_policy(
+				&policy,
+				Arc::clone(&self.kv_store),
+				Arc::clone(&self.logger),
+			)?;
+			*locked_policy = policy.clone();
+		}
+
+		// Note we don't hold the policy lock while disconnecting, as the peer handler might be
+		// checking the policy for newly connected peers concurrently.
+		for peer in self.peer_manager.list_peers() {
+			let node_id = peer.counterparty_node_id;
+			if peer.is_inbound_connection && !policy.allows_inbound(&node_id) {
+				log_info!(
+					self.logger,
+					"Disconnecting peer {} as it's disallowed by our updated connection policy",
+					node_id
+				);
+				self.peer_manager.disconnect_by_node_id(node_id);
+			}
+		}
+		Ok(())
 	}
 
 	fn open_channel_inner(
 		&self, node_id: PublicKey, address: SocketAddress, channel_amount_sats: u64,
-		push_to_counterparty_msat: Option<u64>, channel_config: Option<ChannelConfig>,
-		announce_for_forwarding: bool,
+		params: ChannelOpenParams, announce_for_forwarding: bool,
+		external_funding: Option<tokio::sync::oneshot::Sender<Result<(), Error>>>,
 	) -> Result<UserChannelId, Error> {
 		if !*self.is_running.read().unwrap() {
 			return Err(Error::NotRunning);
 		}
 
-		let peer_info = PeerInfo { node_id, address };
+		let push_msat = params.push_to_counterparty_msat.unwrap_or(0);
+		let max_push_msat = channel_amount_sats
+			.saturating_sub(expected_channel_reserve_sats(channel_amount_sats))
+			.saturating_mul(1000);
+		if push_msat > max_push_msat {
+			log_error!(
+				self.logger,
+				"Unable to create channel as the push amount of {}msat exceeds the maximum of {}msat",
+				push_msat,
+				max_push_msat
+			);
+			return Err(Error::InvalidAmount);
+		}
+
+		let peer_info = PeerInfo::new(node_id, address);
 
 		let con_node_id = peer_info.node_id;
 		let con_addr = peer_info.address.clone();
@@ -1061,11 +2358,18 @@ impl Node {
 		let spendable_amount_sats =
 			self.wallet.get_spendable_amount_sats(cur_anchor_reserve_sats).unwrap_or(0);
 
+		// If the funding transaction is signed externally, we only need to cover the anchor reserve.
+		let own_funding_sats = if external_funding.is_some() { 0 } else { channel_amount_sats };
+
+		// If our channels are funded by an external on-chain wallet, we can't check its funds
+		// upfront.
+		let check_funds = !self.wallet.has_external_wallet();
+
 		// Fail early if we have less than the channel value available.
-		if spendable_amount_sats < channel_amount_sats {
+		if check_funds && spendable_amount_sats < own_funding_sats {
 			log_error!(self.logger,
 				"Unable to create channel due to insufficient funds. Available: {}sats, Required: {}sats",
-				spendable_amount_sats, channel_amount_sats
+				spendable_amount_sats, own_funding_sats
 			);
 			return Err(Error::InsufficientFunds);
 		}
@@ -1082,10 +2386,10 @@ impl Node {
 			.peer_by_node_id(&node_id)
 			.ok_or(Error::ConnectionFailed)?
 			.init_features;
-		let required_funds_sats = channel_amount_sats
+		let required_funds_sats = own_funding_sats
 			+ self.config.anchor_channels_config.as_ref().map_or(0, |c| {
 				if init_features.requires_anchors_zero_fee_htlc_tx()
-					&& !c.trusted_peers_no_reserve.contains(&node_id)
+					&& c.requires_reserve_for(&node_id)
 				{
 					c.per_channel_reserve_sats
 				} else {
@@ -1093,7 +2397,7 @@ impl Node {
 				}
 			});
 
-		if spendable_amount_sats < required_funds_sats {
+		if check_funds && spendable_amount_sats < required_funds_sats {
 			log_error!(self.logger,
 				"Unable to create channel due to insufficient funds. Available: {}sats, Required: {}sats",
 				spendable_amount_sats, required_funds_sats
@@ -1103,7 +2407,9 @@ impl Node {
 
 		let mut user_config = default_user_config(&self.config);
 		user_config.channel_handshake_config.announce_for_forwarding = announce_for_forwarding;
-		user_config.channel_config = (channel_config.unwrap_or_default()).clone().into();
+		if let Some(channel_config) = params.channel_config {
+			user_config.channel_config = channel_config.into();
+		}
 		// We set the max inflight to 100% for private channels.
 		// FIXME: LDK will default to this behavior soon, too, at which point we should drop this
 		// manual override.
@@ -1113,9 +2419,22 @@ impl Node {
 				.max_inbound_htlc_value_in_flight_percent_of_channel = 100;
 		}
 
-		let push_msat = push_to_counterparty_msat.unwrap_or(0);
 		let user_channel_id: u128 = rand::thread_rng().gen::<u128>();
 
+		if let Some(confirmation_target) =
+			params.funding_confirmation_target.filter(|_| external_funding.is_none())
+		{
+			self.funding_confirmation_targets
+				.lock()
+				.unwrap()
+				.insert(user_channel_id, confirmation_target);
+		}
+
+		if let Some(sender) = external_funding {
+			let pending_funding = PendingExternalFunding::AwaitingFundingGeneration { sender };
+			self.pending_external_fundings.lock().unwrap().insert(user_channel_id, pending_funding);
+		}
+
 		match self.channel_manager.create_channel(
 			peer_info.node_id,
 			channel_amount_sats,
@@ -1135,6 +2454,8 @@ impl Node {
 			},
 			Err(e) => {
 				log_error!(self.logger, "Failed to initiate channel creation: {:?}", e);
+				self.funding_confirmation_targets.lock().unwrap().remove(&user_channel_id);
+				self.pending_external_fundings.lock().unwrap().remove(&user_channel_id);
 				Err(Error::ChannelCreationFailed)
 			},
 		}
@@ -1161,21 +2482,41 @@ impl Node {
 		&self, node_id: PublicKey, address: SocketAddress, channel_amount_sats: u64,
 		push_to_counterparty_msat: Option<u64>, channel_config: Option<ChannelConfig>,
 	) -> Result<UserChannelId, Error> {
-		self.open_channel_inner(
-			node_id,
-			address,
-			channel_amount_sats,
+		let params = ChannelOpenParams {
 			push_to_counterparty_msat,
 			channel_config,
-			false,
-		)
+			..ChannelOpenParams::default()
+		};
+		self.open_channel_inner(node_id, address, channel_amount_sats, params, false, None)
+	}
+
+	/// Connect to a node and open a new unannounced channel, using the given
+	/// [`ChannelOpenParams`].
+	///
+	/// In contrast to [`Node::open_channel`], this allows to also choose the confirmation target
+	/// for the funding transaction.
+	///
+	/// Will return an [`Error::InvalidAmount`] if the amount to push to the counterparty exceeds
+	/// the channel value minus the channel reserve we're expected to keep, i.e., 1% of the channel
+	/// value but at least 1000 satoshis.
+	///
+	/// Please refer to [`Node::open_channel`] for further details.
+	pub fn open_channel_with_params(
+		&self, node_id: PublicKey, address: SocketAddress, channel_amount_sats: u64,
+		params: ChannelOpenParams,
+	) -> Result<UserChannelId, Error> {
+		self.open_channel_inner(node_id, address, channel_amount_sats, params, false, None)
 	}
 
 	/// Connect to a node and open a new announced channel.
 	///
 	/// This will return an error if the node has not been sufficiently configured to operate as a
 	/// forwarding node that can properly announce its existence to the publip network graph, i.e.,
-	/// [`Config::listening_addresses`] and [`Config::node_alias`] are unset.
+	/// [`Config::listening_addresses`] and [`Config::node_alias`] are unset. In particular,
+	/// [`Error::NoListeningAddress`] is returned if no listening addresses are configured.
+	///
+	/// The channel will be announced to the network once the funding transaction reached six
+	/// confirmations. From then on, we'll also regularly broadcast our node announcement.
 	///
 	/// To open an unannounced channel, see [`Node::open_channel`].
 	///
@@ -1195,135 +2536,1334 @@ impl Node {
 	pub fn open_announced_channel(
 		&self, node_id: PublicKey, address: SocketAddress, channel_amount_sats: u64,
 		push_to_counterparty_msat: Option<u64>, channel_config: Option<ChannelConfig>,
+	) -> Result<UserChannelId, Error> {
+		let params = ChannelOpenParams {
+			push_to_counterparty_msat,
+			channel_config,
+			..ChannelOpenParams::default()
+		};
+		self.open_announced_channel_with_params(node_id, address, channel_amount_sats, params)
+	}
+
+	/// Connect to a node and open a new announced channel, using the given [`ChannelOpenParams`].
+	///
+	/// In contrast to [`Node::open_announced_channel`], this allows to also choose the
+	/// confirmation target for the funding transaction.
+	///
+	/// Will return an [`Error::InvalidAmount`] if the amount to push to the counterparty exceeds
+	/// the channel value minus the channel reserve we're expected to keep, i.e., 1% of the channel
+	/// value but at least 1000 satoshis.
+	///
+	/// Please refer to [`Node::open_announced_channel`] for further details.
+	pub fn open_announced_channel_with_params(
+		&self, node_id: PublicKey, address: SocketAddress, channel_amount_sats: u64,
+		params: ChannelOpenParams,
 	) -> Result<UserChannelId, Error> {
 		if let Err(err) = may_announce_channel(&self.config) {
 			log_error!(self.logger, "Failed to open announced channel as the node hasn't been sufficiently configured to act as a forwarding node: {}", err);
-			return Err(Error::ChannelCreationFailed);
+			return match err {
+				AnnounceError::MissingListeningAddresses
+				| AnnounceError::MissingAliasAndAddresses => Err(Error::NoListeningAddress),
+				AnnounceError::MissingNodeAlias => Err(Error::ChannelCreationFailed),
+			};
+		}
+
+		self.open_channel_inner(node_id, address, channel_amount_sats, params, true, None)
+	}
+
+	/// Connect to a node and open a new unannounced channel whose funding transaction is signed
+	/// externally, e.g., by a hardware wallet or a multisig setup.
+	///
+	/// Rather than funding the channel from our on-chain wallet, this blocks until the counterparty
+	/// accepted the channel and returns an unsigned PSBT that only contains the channel's funding
+	/// output. The external wallet is expected to add inputs and any change outputs, sign the
+	/// transaction, and hand it back via [`Node::finalize_channel_open`].
+	///
+	/// If the signed PSBT isn't handed back within 30 minutes, the channel open is abandoned.
+	///
+	/// If Anchor channels are enabled, this will ensure the configured
+	/// [`AnchorChannelsConfig::per_channel_reserve_sats`] is available in our on-chain wallet and
+	/// will be retained before opening the channel.
+	///
+	/// As the funding transaction is built externally, the
+	/// [`ChannelOpenParams::funding_confirmation_target`] is ignored.
+	///
+	/// Please refer to [`Node::open_channel_with_params`] for further details.
+	///
+	/// [`AnchorChannelsConfig::per_channel_reserve_sats`]: crate::config::AnchorChannelsConfig::per_channel_reserve_sats
+	pub fn open_channel_psbt(
+		&self, node_id: PublicKey, address: SocketAddress, channel_amount_sats: u64,
+		params: ChannelOpenParams,
+	) -> Result<ChannelFundingPsbt, Error> {
+		let (sender, receiver) = tokio::sync::oneshot::channel();
+		let user_channel_id = self.open_channel_inner(
+			node_id,
+			address,
+			channel_amount_sats,
+			params,
+			false,
+			Some(sender),
+		)?;
+
+		let res = self.runtime.block_on(async {
+			tokio::time::timeout(EXTERNAL_FUNDING_ACCEPT_TIMEOUT, receiver).await
+		});
+		match res {
+			Ok(Ok(res)) => res?,
+			Ok(Err(_)) => {
+				debug_assert!(false, "We never drop the senders without notifying them");
+				return Err(Error::ChannelCreationFailed);
+			},
+			Err(_) => {
+				log_error!(
+					self.logger,
+					"Timed out waiting for peer {} to accept channel with user channel ID {}",
+					node_id,
+					user_channel_id.0,
+				);
+				let channel = self
+					.channel_manager
+					.list_channels_with_counterparty(&node_id)
+					.into_iter()
+					.find(|c| c.user_channel_id == user_channel_id.0);
+				{
+					// Keep a tombstone until the channel is closed, as a late
+					// `FundingGenerationReady` would otherwise have us fund it from our on-chain
+					// wallet.
+					let mut locked_pending_fundings =
+						self.pending_external_fundings.lock().unwrap();
+					if channel.is_some() {
+						locked_pending_fundings
+							.insert(user_channel_id.0, PendingExternalFunding::Abandoned);
+					} else {
+						locked_pending_fundings.remove(&user_channel_id.0);
+					}
+				}
+				if let Some(channel) = channel {
+					let _ = self.channel_manager.force_close_broadcasting_latest_txn(
+						&channel.channel_id,
+						&node_id,
+						"Channel wasn't accepted in time".to_string(),
+					);
+				}
+				return Err(Error::ChannelCreationFailed);
+			},
+		}
+
+		let locked_pending_fundings = self.pending_external_fundings.lock().unwrap();
+		match locked_pending_fundings.get(&user_channel_id.0) {
+			Some(PendingExternalFunding::AwaitingSignedPsbt {
+				temporary_channel_id,
+				output_script,
+				channel_value_sats,
+				..
+			}) => {
+				let funding_output = TxOut {
+					value: Amount::from_sat(*channel_value_sats),
+					script_pubkey: output_script.clone(),
+				};
+				let unsigned_tx = Transaction {
+					version: Version::TWO,
+					lock_time: LockTime::ZERO,
+					input: Vec::new(),
+					output: vec![funding_output],
+				};
+				let psbt = Psbt::from_unsigned_tx(unsigned_tx).map_err(|e| {
+					log_error!(self.logger, "Failed to create funding PSBT: {}", e);
+					Error::ChannelCreationFailed
+				})?;
+				Ok(ChannelFundingPsbt {
+					temporary_channel_id: *temporary_channel_id,
+					user_channel_id,
+					psbt,
+				})
+			},
+			_ => {
+				log_error!(
+					self.logger,
+					"Channel with user channel ID {} was closed before it could be funded",
+					user_channel_id.0,
+				);
+				Err(Error::ChannelCreationFailed)
+			},
+		}
+	}
+
+	/// Finalizes opening a channel via [`Node::open_channel_psbt`] by handing back the signed
+	/// funding PSBT.
+	///
+	/// The PSBT needs to be fully signed, i.e., finalized, and spend only SegWit outputs. We verify
+	/// that it pays the exact channel value to the channel's funding output before handing the
+	/// funding transaction to the counterparty. We'll broadcast the funding transaction once the
+	/// counterparty provided its signature for our initial commitment transaction.
+	///
+	/// Will return an [`Error::InvalidPsbt`] if the PSBT isn't fully signed or doesn't fund the
+	/// channel as expected, and an [`Error::InvalidChannelId`] if no channel awaiting external
+	/// funding is known for the given temporary channel ID, e.g., as it was abandoned as the PSBT
+	/// wasn't handed back in time.
+	pub fn finalize_channel_open(
+		&self, temporary_channel_id: &ChannelId, signed_psbt: Psbt,
+	) -> Result<(), Error> {
+		let mut locked_pending_fundings = self.pending_external_fundings.lock().unwrap();
+		let (user_channel_id, counterparty_node_id, output_script, channel_value_sats) =
+			locked_pending_fundings
+				.iter()
+				.find_map(|(user_channel_id, pending_funding)| match pending_funding {
+					PendingExternalFunding::AwaitingSignedPsbt {
+						temporary_channel_id: id,
+						counterparty_node_id,
+						output_script,
+						channel_value_sats,
+						deadline,
+					} if id == temporary_channel_id && Instant::now() < *deadline => Some((
+						*user_channel_id,
+						*counterparty_node_id,
+						output_script.clone(),
+						*channel_value_sats,
+					)),
+					_ => None,
+				})
+				.ok_or_else(|| {
+					log_error!(
+						self.logger,
+						"Failed to finalize channel open as no channel {} is awaiting funding",
+						temporary_channel_id
+					);
+					Error::InvalidChannelId
+				})?;
+
+		if signed_psbt.inputs.is_empty()
+			|| signed_psbt.inputs.iter().any(|input| input.final_script_witness.is_none())
+		{
+			log_error!(
+				self.logger,
+				"Failed to finalize channel open as the PSBT isn't fully signed"
+			);
+			return Err(Error::InvalidPsbt);
+		}
+
+		let funding_tx = signed_psbt.extract_tx_unchecked_fee_rate();
+		let num_funding_outputs = funding_tx
+			.output
+			.iter()
+			.filter(|output| output.script_pubkey == output_script)
+			.map(|output| output.value)
+			.filter(|value| *value == Amount::from_sat(channel_value_sats))
+			.count();
+		if num_funding_outputs != 1 {
+			log_error!(
+				self.logger,
+				"Failed to finalize channel open as the PSBT doesn't pay {}sats to the funding output",
+				channel_value_sats
+			);
+			return Err(Error::InvalidPsbt);
+		}
+
+		self.channel_manager
+			.funding_transaction_generated(*temporary_channel_id, counterparty_node_id, funding_tx)
+			.map_err(|e| {
+				log_error!(self.logger, "Failed to finalize channel open: {:?}", e);
+				match e {
+					APIError::APIMisuseError { .. } => Error::InvalidPsbt,
+					_ => Error::ChannelCreationFailed,
+				}
+			})?;
+
+		locked_pending_fundings.remove(&user_channel_id);
+		log_info!(
+			self.logger,
+			"Handed over externally signed funding transaction for channel {}",
+			temporary_channel_id
+		);
+		Ok(())
+	}
+
+	/// Blocks until the channel with the given [`UserChannelId`] is ready to be used, e.g., to
+	/// send payments right after opening it via [`open_channel`].
+	///
+	/// Returns immediately if the channel is ready already. Will return an
+	/// [`Error::ChannelClosedBeforeReady`] if the channel is closed before it becomes ready, an
+	/// [`Error::ChannelReadyTimeout`] if it doesn't become ready within the given `timeout`, and an
+	/// [`Error::InvalidChannelId`] if no pending or open channel with the given ID is known.
+	///
+	/// **Note:** Channels only become ready once their funding transaction reached the required
+	/// number of confirmations, which requires the node to be running and its wallets to be synced.
+	///
+	/// [`open_channel`]: Self::open_channel
+	pub fn wait_for_channel_ready(
+		&self, user_channel_id: &UserChannelId, timeout: Duration,
+	) -> Result<(), Error> {
+		if !*self.is_running.read().unwrap() {
+			return Err(Error::NotRunning);
+		}
+
+		let receiver = {
+			// We hold the lock while checking the channel state so that we can't miss a
+			// concurrently handled `ChannelReady` or `ChannelClosed` event.
+			let mut locked_waiters = self.channel_ready_waiters.lock().unwrap();
+			let channel = self
+				.channel_manager
+				.list_channels()
+				.into_iter()
+				.find(|c| c.user_channel_id == user_channel_id.0)
+				.ok_or_else(|| {
+					log_error!(
+						self.logger,
+						"Failed to wait for unknown channel with user channel ID {}",
+						user_channel_id.0,
+					);
+					Error::InvalidChannelId
+				})?;
+			if channel.is_channel_ready {
+				return Ok(());
+			}
+
+			let (sender, receiver) = tokio::sync::oneshot::channel();
+			locked_waiters.entry(user_channel_id.0).or_default().push(sender);
+			receiver
+		};
+
+		let res = self.runtime.block_on(async { tokio::time::timeout(timeout, receiver).await });
+		match res {
+			Ok(Ok(res)) => res,
+			Ok(Err(_)) => {
+				debug_assert!(false, "We never drop the waiters without notifying them");
+				Err(Error::ChannelClosedBeforeReady)
+			},
+			Err(_) => {
+				// Drop our sender, whose receiver went away with the timed out future, so that
+				// waiters for channels that never become ready don't accumulate.
+				let mut locked_waiters = self.channel_ready_waiters.lock().unwrap();
+				if let Some(waiters) = locked_waiters.get_mut(&user_channel_id.0) {
+					waiters.retain(|sender| !sender.is_closed());
+					if waiters.is_empty() {
+						locked_waiters.remove(&user_channel_id.0);
+					}
+				}
+
+				log_error!(
+					self.logger,
+					"Timed out waiting for channel with user channel ID {} to become ready",
+					user_channel_id.0,
+				);
+				Err(Error::ChannelReadyTimeout)
+			},
+		}
+	}
+
+	/// Manually sync the LDK and BDK wallets with the current chain state and update the fee rate
+	/// cache.
+	///
+	/// Blocks until both the on-chain and the Lightning wallet are synced and returns a
+	/// [`SyncReport`] summarizing the changes. This is useful to have the balances reflect a
+	/// deposit or a newly broadcast transaction immediately, rather than waiting for the next
+	/// background sync.
+	///
+	/// It's safe to call this method while a background sync is in progress, in which case we'll
+	/// wait for the pending sync to complete rather than syncing again.
+	///
+	/// **Note:** The wallets are regularly synced in the background if background syncing is enabled
+	/// via [`EsploraSyncConfig::background_sync_config`]. Therefore, using this blocking sync method
+	/// is almost always redundant when background syncing is enabled and should be avoided where possible.
+	/// However, if background syncing is disabled (i.e., `background_sync_config` is set to `None`),
+	/// this method must be called manually to keep wallets in sync with the chain state.
+	///
+	/// [`EsploraSyncConfig::background_sync_config`]: crate::config::EsploraSyncConfig::background_sync_config
+	pub fn sync_wallets(&self) -> Result<SyncReport, Error> {
+		if !*self.is_running.read().unwrap() {
+			return Err(Error::NotRunning);
+		}
+
+		let chain_source = Arc::clone(&self.chain_source);
+		let sync_cman = Arc::clone(&self.channel_manager);
+		let sync_cmon = Arc::clone(&self.chain_monitor);
+		let sync_sweeper = Arc::clone(&self.output_sweeper);
+		let sync_claim_watchdog = Arc::clone(&self.claim_watchdog);
+		let pre_sync_snapshot = self.wallet.snapshot();
+		self.runtime.block_on(async move {
+			if chain_source.is_transaction_based() {
+				chain_source.update_fee_rate_estimates().await?;
+				chain_source
+					.sync_lightning_wallet(
+						sync_cman,
+						sync_cmon,
+						Arc::clone(&sync_sweeper),
+						sync_claim_watchdog,
+					)
+					.await?;
+				chain_source.sync_onchain_wallet().await?;
+			} else {
+				chain_source.update_fee_rate_estimates().await?;
+				chain_source
+					.poll_and_update_listeners(
+						sync_cman,
+						sync_cmon,
+						Arc::clone(&sync_sweeper),
+						sync_claim_watchdog,
+					)
+					.await?;
+			}
+			let _ = sync_sweeper.regenerate_and_broadcast_spend_if_necessary().await;
+			Ok::<(), Error>(())
+		})?;
+
+		let post_sync_snapshot = self.wallet.snapshot();
+		Ok(SyncReport {
+			updated_onchain_transactions: post_sync_snapshot
+				.updated_transactions_since(&pre_sync_snapshot),
+			updated_onchain_outputs: post_sync_snapshot.updated_outputs_since(&pre_sync_snapshot),
+			best_block_height: self.channel_manager.current_best_block().height,
+		})
+	}
+
+	/// Returns the fee rate currently used for the given [`ConfirmationTarget`].
+	///
+	/// This is the fee rate set via [`Node::set_fee_rate_override`], if any, or otherwise the
+	/// estimate last retrieved from the chain source, or the custom [`FeeEstimator`] set via
+	/// [`Builder::set_custom_fee_estimator`], when updating the fee rate cache. If the cache
+	/// hasn't been updated yet, a conservative fallback rate is returned.
+	#[cfg(not(feature = "uniffi"))]
+	pub fn current_fee_rate(&self, target: ConfirmationTarget) -> FeeRate {
+		self.fee_estimator.estimate_fee_rate(target)
+	}
+
+	/// Returns the fee rate currently used for the given [`ConfirmationTarget`].
+	///
+	/// This is the fee rate set via [`Node::set_fee_rate_override`], if any, or otherwise the
+	/// estimate last retrieved from the chain source, or the custom [`FeeEstimator`] set via
+	/// [`Builder::set_custom_fee_estimator`], when updating the fee rate cache. If the cache
+	/// hasn't been updated yet, a conservative fallback rate is returned.
+	#[cfg(feature = "uniffi")]
+	pub fn current_fee_rate(&self, target: ConfirmationTarget) -> Arc<FeeRate> {
+		Arc::new(self.fee_estimator.estimate_fee_rate(target))
+	}
+
+	/// Overrides the fee rate used for the given [`ConfirmationTarget`], or clears the override if
+	/// `None` is given.
+	///
+	/// While an override is set, it is used for all on-chain and channel operations in place of
+	/// the estimates retrieved from the chain source. This is mostly useful for testing, e.g., on
+	/// regtest where fee estimates are often unavailable, or to bridge fee estimator outages.
+	///
+	/// Note that, just as estimates, overrides are raised to the minimum relay fee rate if they are
+	/// lower.
+	///
+	/// **Note:** Overrides are not persisted and will hence be reset when the node is restarted.
+	#[cfg(not(feature = "uniffi"))]
+	pub fn set_fee_rate_override(&self, target: ConfirmationTarget, fee_rate: Option<FeeRate>) {
+		self.fee_estimator.set_fee_rate_override(target, fee_rate);
+	}
+
+	/// Overrides the fee rate used for the given [`ConfirmationTarget`], or clears the override if
+	/// `None` is given.
+	///
+	/// While an override is set, it is used for all on-chain and channel operations in place of
+	/// the estimates retrieved from the chain source. This is mostly useful for testing, e.g., on
+	/// regtest where fee estimates are often unavailable, or to bridge fee estimator outages.
+	///
+	/// Note that, just as estimates, overrides are raised to the minimum relay fee rate if they are
+	/// lower.
+	///
+	/// **Note:** Overrides are not persisted and will hence be reset when the node is restarted.
+	#[cfg(feature = "uniffi")]
+	pub fn set_fee_rate_override(
+		&self, target: ConfirmationTarget, fee_rate: Option<Arc<FeeRate>>,
+	) {
+		self.fee_estimator.set_fee_rate_override(target, fee_rate.map(|f| *f));
+	}
+
+	/// Close a previously opened channel.
+	///
+	/// Will attempt to close a channel coopertively. If this fails, users might need to resort to
+	/// [`Node::force_close_channel`].
+	pub fn close_channel(
+		&self, user_channel_id: &UserChannelId, counterparty_node_id: PublicKey,
+	) -> Result<(), Error> {
+		self.close_channel_internal(user_channel_id, counterparty_node_id, false, None, None)
+	}
+
+	/// Cooperatively close a previously opened channel, proposing the given feerate for the
+	/// closing transaction.
+	///
+	/// The given `target_feerate_sat_per_vb` will be our starting proposal in the closing fee
+	/// negotiation with the counterparty. Note that the counterparty might still negotiate a
+	/// different feerate.
+	///
+	/// If `force_close_after_secs` is set and the channel isn't closed after the given number of
+	/// seconds, e.g., as the counterparty is offline or doesn't cooperate, we'll force-close the
+	/// channel instead. The deadline only applies as long as the node keeps running. Note that
+	/// initiating the cooperative closure requires the counterparty to be connected.
+	///
+	/// The fee finally paid by the closing transaction will be reported via
+	/// [`Event::ChannelClosed::closing_fee_sat`].
+	///
+	/// Returns [`Error::ChannelNotFound`] if we have no channel with the given counterparty and
+	/// `user_channel_id`.
+	pub fn close_channel_with_feerate(
+		&self, user_channel_id: &UserChannelId, counterparty_node_id: PublicKey,
+		target_feerate_sat_per_vb: u32, force_close_after_secs: Option<u64>,
+	) -> Result<(), Error> {
+		let channel_id = match self
+			.channel_manager
+			.list_channels_with_counterparty(&counterparty_node_id)
+			.iter()
+			.find(|c| c.user_channel_id == user_channel_id.0)
+		{
+			Some(channel_details) => channel_details.channel_id,
+			None => {
+				log_error!(
+					self.logger,
+					"Failed to close channel with user channel id {} as it could not be found",
+					user_channel_id.0
+				);
+				return Err(Error::ChannelNotFound);
+			},
+		};
+
+		let target_feerate_sat_per_kw = target_feerate_sat_per_vb.saturating_mul(250);
+		self.close_channel_internal(
+			user_channel_id,
+			counterparty_node_id,
+			false,
+			None,
+			Some(target_feerate_sat_per_kw),
+		)?;
+
+		if let Some(force_close_after_secs) = force_close_after_secs {
+			let channel_manager = Arc::clone(&self.channel_manager);
+			let logger = Arc::clone(&self.logger);
+			self.runtime.spawn_cancellable_background_task(async move {
+				tokio::time::sleep(Duration::from_secs(force_close_after_secs)).await;
+
+				let is_still_open = channel_manager
+					.list_channels_with_counterparty(&counterparty_node_id)
+					.iter()
+					.any(|c| c.channel_id == channel_id);
+				if !is_still_open {
+					return;
+				}
+
+				log_info!(
+					logger,
+					"Force-closing channel {} as cooperative closure didn't complete within {}s.",
+					channel_id,
+					force_close_after_secs
+				);
+				channel_manager
+					.force_close_broadcasting_latest_txn(
+						&channel_id,
+						&counterparty_node_id,
+						"Cooperative closure timed out".to_string(),
+					)
+					.unwrap_or_else(|e| {
+						log_error!(logger, "Failed to force-close channel: {:?}", e);
+					});
+			});
+		}
+
+		Ok(())
+	}
+
+	/// Force-close a previously opened channel.
+	///
+	/// Will force-close the channel, potentially broadcasting our latest state. Note that in
+	/// contrast to cooperative closure, force-closing will have the channel funds time-locked,
+	/// i.e., they will only be available after the counterparty had time to contest our claim.
+	/// Force-closing channels also more costly in terms of on-chain fees. So cooperative closure
+	/// should always be preferred (and tried first).
+	///
+	/// Broadcasting the closing transactions will be omitted for Anchor channels if we trust the
+	/// counterparty to broadcast for us (see [`AnchorChannelsConfig::trusted_peers_no_reserve`]
+	/// for more information).
+	///
+	/// [`AnchorChannelsConfig::trusted_peers_no_reserve`]: crate::config::AnchorChannelsConfig::trusted_peers_no_reserve
+	pub fn force_close_channel(
+		&self, user_channel_id: &UserChannelId, counterparty_node_id: PublicKey,
+		reason: Option<String>,
+	) -> Result<(), Error> {
+		self.close_channel_internal(user_channel_id, counterparty_node_id, true, reason, None)
+	}
+
+	/// Force-close all channels with the given counterparty, e.g., if the counterparty went
+	/// permanently offline or misbehaves.
+	///
+	/// Channels we're already in the process of closing cooperatively will be force-closed, too.
+	/// The given `reason` is used for all channels and will be recorded as
+	/// [`ClosedChannelDetails::force_close_reason`]. Afterwards, the counterparty will be removed
+	/// from our peer store, i.e., we won't try to reconnect to it.
+	///
+	/// Returns the outcome for each channel we had with the counterparty. Please refer to
+	/// [`Node::force_close_channel`] for the implications of force-closing channels.
+	pub fn force_close_all_channels_with_peer(
+		&self, counterparty_node_id: PublicKey, reason: Option<String>,
+	) -> Vec<ChannelForceCloseResult> {
+		let channels = self.channel_manager.list_channels_with_counterparty(&counterparty_node_id);
+		let reason = reason.unwrap_or_default();
+
+		let mut results = Vec::with_capacity(channels.len());
+		for channel in channels {
+			let outcome = match self.channel_manager.force_close_broadcasting_latest_txn(
+				&channel.channel_id,
+				&counterparty_node_id,
+				reason.clone(),
+			) {
+				Ok(()) => ForceCloseOutcome::ForceClosed,
+				Err(APIError::ChannelUnavailable { .. }) => {
+					// The channel was closed since we listed it.
+					ForceCloseOutcome::AlreadyClosed
+				},
+				Err(e) => {
+					log_error!(
+						self.logger,
+						"Failed to force-close channel {}: {:?}",
+						channel.channel_id,
+						e
+					);
+					ForceCloseOutcome::Failed
+				},
+			};
+			results.push(ChannelForceCloseResult {
+				channel_id: channel.channel_id,
+				user_channel_id: UserChannelId(channel.user_channel_id),
+				outcome,
+			});
+		}
+
+		let num_force_closed =
+			results.iter().filter(|r| r.outcome == ForceCloseOutcome::ForceClosed).count();
+		log_info!(
+			self.logger,
+			"Force-closed {} of {} channels with peer {}.",
+			num_force_closed,
+			results.len(),
+			counterparty_node_id
+		);
+
+		if results.iter().all(|r| r.outcome != ForceCloseOutcome::Failed) {
+			self.peer_store.remove_peer(&counterparty_node_id).unwrap_or_else(|e| {
+				log_error!(self.logger, "Failed to remove peer {}: {}", counterparty_node_id, e);
+			});
+		}
+
+		results
+	}
+
+	fn close_channel_internal(
+		&self, user_channel_id: &UserChannelId, counterparty_node_id: PublicKey, force: bool,
+		force_close_reason: Option<String>, target_feerate_sat_per_kw: Option<u32>,
+	) -> Result<(), Error> {
+		debug_assert!(
+			force_close_reason.is_none() || force,
+			"Reason can only be set for force closures"
+		);
+		let open_channels =
+			self.channel_manager.list_channels_with_counterparty(&counterparty_node_id);
+		if let Some(channel_details) =
+			open_channels.iter().find(|c| c.user_channel_id == user_channel_id.0)
+		{
+			if force {
+				self.channel_manager
+					.force_close_broadcasting_latest_txn(
+						&channel_details.channel_id,
+						&counterparty_node_id,
+						force_close_reason.unwrap_or_default(),
+					)
+					.map_err(|e| {
+						log_error!(self.logger, "Failed to force-close channel: {:?}", e);
+						Error::ChannelClosingFailed
+					})?;
+			} else {
+				self.channel_manager
+					.close_channel_with_feerate_and_script(
+						&channel_details.channel_id,
+						&counterparty_node_id,
+						target_feerate_sat_per_kw,
+						None,
+					)
+					.map_err(|e| {
+						log_error!(self.logger, "Failed to close channel: {:?}", e);
+						Error::ChannelClosingFailed
+					})?;
+			}
+
+			// Check if this was the last open channel, if so, forget the peer.
+			if open_channels.len() == 1 {
+				self.peer_store.remove_peer(&counterparty_node_id)?;
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Splices additional on-chain funds into a previously opened channel, increasing its
+	/// capacity without closing it.
+	///
+	/// The funds, as well as the fees for the splice transaction, are taken from our on-chain
+	/// wallet. If Anchor channels are enabled, this will retain the configured
+	/// [`AnchorChannelsConfig::per_channel_reserve_sats`].
+	///
+	/// Splicing requires the counterparty to support it and to be connected. The channel remains
+	/// usable while the splice is pending. Once the splice transaction confirmed and the splice
+	/// was locked by both parties, an [`Event::ChannelSpliced`] will be emitted.
+	///
+	/// Will return [`Error::ChannelNotFound`] if no channel with the given `user_channel_id`
+	/// exists.
+	///
+	/// [`AnchorChannelsConfig::per_channel_reserve_sats`]: crate::config::AnchorChannelsConfig::per_channel_reserve_sats
+	pub fn splice_in(
+		&self, user_channel_id: &UserChannelId, counterparty_node_id: PublicKey,
+		splice_amount_sats: u64,
+	) -> Result<(), Error> {
+		if !*self.is_running.read().unwrap() {
+			return Err(Error::NotRunning);
+		}
+
+		let open_channels =
+			self.channel_manager.list_channels_with_counterparty(&counterparty_node_id);
+		let channel_details = open_channels
+			.iter()
+			.find(|c| c.user_channel_id == user_channel_id.0)
+			.ok_or(Error::ChannelNotFound)?;
+		let funding_txo = channel_details.funding_txo.ok_or_else(|| {
+			log_error!(
+				self.logger,
+				"Failed to splice channel {}: channel is not funded yet",
+				channel_details.channel_id
+			);
+			Error::ChannelSplicingFailed
+		})?;
+		let funding_script = self
+			.chain_monitor
+			.get_monitor(channel_details.channel_id)
+			.map(|channel_monitor| channel_monitor.get_funding_script())
+			.map_err(|()| {
+				log_error!(
+					self.logger,
+					"Failed to splice channel {}: channel monitor not found",
+					channel_details.channel_id
+				);
+				Error::ChannelSplicingFailed
+			})?;
+
+		let cur_anchor_reserve_sats =
+			total_anchor_channels_reserve_sats(&self.channel_manager, &self.config);
+		let spendable_amount_sats =
+			self.wallet.get_spendable_amount_sats(cur_anchor_reserve_sats).unwrap_or(0);
+		if spendable_amount_sats < splice_amount_sats {
+			log_error!(self.logger,
+				"Unable to splice channel due to insufficient funds. Available: {}sats, Required: {}sats",
+				spendable_amount_sats, splice_amount_sats
+			);
+			return Err(Error::InsufficientFunds);
+		}
+
+		let funding_output = TxOut {
+			value: Amount::from_sat(channel_details.channel_value_satoshis),
+			script_pubkey: funding_script,
+		};
+		let fee_rate = self.fee_estimator.estimate_fee_rate(ConfirmationTarget::ChannelFunding);
+		let contribution = self.runtime.block_on(self.wallet.create_splice_in_contribution(
+			ClaimId(channel_details.channel_id.0),
+			funding_txo.into_bitcoin_outpoint(),
+			funding_output,
+			splice_amount_sats,
+			fee_rate,
+		))?;
+
+		self.splice_channel_internal(
+			&channel_details.channel_id,
+			counterparty_node_id,
+			contribution,
+			fee_rate,
+		)
+	}
+
+	/// Splices funds out of a previously opened channel to the given on-chain address, decreasing
+	/// its capacity without closing it.
+	///
+	/// The withdrawn amount, as well as the fees for the splice transaction, are taken from our
+	/// channel balance.
+	///
+	/// Splicing requires the counterparty to support it and to be connected. The channel remains
+	/// usable while the splice is pending. Once the splice transaction confirmed and the splice
+	/// was locked by both parties, an [`Event::ChannelSpliced`] will be emitted.
+	///
+	/// Will return [`Error::ChannelNotFound`] if no channel with the given `user_channel_id`
+	/// exists.
+	pub fn splice_out(
+		&self, user_channel_id: &UserChannelId, counterparty_node_id: PublicKey,
+		splice_amount_sats: u64, address: &Address,
+	) -> Result<(), Error> {
+		if !*self.is_running.read().unwrap() {
+			return Err(Error::NotRunning);
+		}
+
+		let address = self.wallet.parse_and_validate_address(self.config.network, address)?;
+
+		let open_channels =
+			self.channel_manager.list_channels_with_counterparty(&counterparty_node_id);
+		let channel_details = open_channels
+			.iter()
+			.find(|c| c.user_channel_id == user_channel_id.0)
+			.ok_or(Error::ChannelNotFound)?;
+
+		let outbound_capacity_sats = channel_details.outbound_capacity_msat / 1000;
+		if outbound_capacity_sats < splice_amount_sats {
+			log_error!(self.logger,
+				"Unable to splice channel due to insufficient funds. Available: {}sats, Required: {}sats",
+				outbound_capacity_sats, splice_amount_sats
+			);
+			return Err(Error::InsufficientFunds);
+		}
+
+		let contribution = SpliceContribution::SpliceOut {
+			outputs: vec![TxOut {
+				value: Amount::from_sat(splice_amount_sats),
+				script_pubkey: address.script_pubkey(),
+			}],
+		};
+		let fee_rate = self.fee_estimator.estimate_fee_rate(ConfirmationTarget::ChannelFunding);
+
+		self.splice_channel_internal(
+			&channel_details.channel_id,
+			counterparty_node_id,
+			contribution,
+			fee_rate,
+		)
+	}
+
+	fn splice_channel_internal(
+		&self, channel_id: &ChannelId, counterparty_node_id: PublicKey,
+		contribution: SpliceContribution, fee_rate: FeeRate,
+	) -> Result<(), Error> {
+		self.channel_manager
+			.splice_channel(
+				channel_id,
+				&counterparty_node_id,
+				contribution,
+				fee_rate.to_sat_per_kwu() as u32,
+				None,
+			)
+			.map_err(|e| {
+				log_error!(self.logger, "Failed to splice channel {}: {:?}", channel_id, e);
+				Error::ChannelSplicingFailed
+			})?;
+		log_info!(self.logger, "Initiated splice of channel {}", channel_id);
+		Ok(())
+	}
+
+	/// Shifts `amount_msat` of our local balance from one of our channels to another by sending a
+	/// circular payment to ourselves.
+	///
+	/// The payment is routed out via the channel with `from_user_channel_id` and comes back in via
+	/// the channel with `to_user_channel_id`, paying at most `max_fee_msat` in routing fees. Will
+	/// return [`Error::RouteNotFound`] if no such route could be found within the fee budget, and
+	/// [`Error::InvalidAmount`] if the amount including fees would overflow.
+	///
+	/// The payment is tracked as a [`PaymentKind::Rebalance`] payment and will not be retried
+	/// automatically. Once it succeeded, an [`Event::PaymentSuccessful`] will be emitted.
+	pub fn rebalance_channel(
+		&self, from_user_channel_id: &UserChannelId, to_user_channel_id: &UserChannelId,
+		amount_msat: u64, max_fee_msat: u64,
+	) -> Result<PaymentId, Error> {
+		if !*self.is_running.read().unwrap() {
+			return Err(Error::NotRunning);
+		}
+
+		if from_user_channel_id == to_user_channel_id {
+			log_error!(self.logger, "Failed to rebalance: source and target channel are the same");
+			return Err(Error::InvalidChannelId);
+		}
+
+		let usable_channels = self.channel_manager.list_usable_channels();
+		let find_channel = |user_channel_id: &UserChannelId| {
+			usable_channels.iter().find(|c| c.user_channel_id == user_channel_id.0).ok_or_else(
+				|| {
+					log_error!(
+						self.logger,
+						"Failed to rebalance: channel {} is unknown or not usable",
+						user_channel_id.0
+					);
+					Error::InvalidChannelId
+				},
+			)
+		};
+		let from_channel = find_channel(from_user_channel_id)?;
+		let to_channel = find_channel(to_user_channel_id)?;
+
+		let (to_channel_scid, forwarding_info) = match (
+			to_channel.get_inbound_payment_scid(),
+			to_channel.counterparty.forwarding_info.as_ref(),
+		) {
+			(Some(scid), Some(forwarding_info)) => (scid, forwarding_info),
+			_ => {
+				log_error!(
+					self.logger,
+					"Failed to rebalance: forwarding parameters of channel {} are unknown",
+					to_channel.channel_id
+				);
+				return Err(Error::RouteNotFound);
+			},
+		};
+
+		// The counterparty of the target channel charges us for forwarding the payment back to us.
+		let proportional_fee_msat =
+			amount_msat as u128 * forwarding_info.fee_proportional_millionths as u128 / 1_000_000;
+		let last_hop_fee_msat = u64::try_from(proportional_fee_msat)
+			.ok()
+			.and_then(|fee_msat| fee_msat.checked_add(forwarding_info.fee_base_msat as u64))
+			.ok_or(Error::InvalidAmount)?;
+		if last_hop_fee_msat > max_fee_msat {
+			log_error!(
+				self.logger,
+				"Failed to rebalance: the fee of {}msat charged by the target channel's counterparty exceeds the limit of {}msat",
+				last_hop_fee_msat,
+				max_fee_msat
+			);
+			return Err(Error::RouteNotFound);
+		}
+
+		// We route to the target channel's counterparty via the source channel and then append the
+		// final hop back to us.
+		let to_counterparty = to_channel.counterparty.node_id;
+		let mut payment_params = PaymentParameters::from_node_id(
+			to_counterparty,
+			forwarding_info.cltv_expiry_delta as u32,
+		);
+		payment_params.max_path_count = 1;
+		let amount_to_counterparty_msat =
+			amount_msat.checked_add(last_hop_fee_msat).ok_or(Error::InvalidAmount)?;
+		let mut route_params = RouteParameters::from_payment_params_and_value(
+			payment_params,
+			amount_to_counterparty_msat,
+		);
+		route_params.max_total_routing_fee_msat = Some(max_fee_msat - last_hop_fee_msat);
+
+		let our_node_id = self.channel_manager.get_our_node_id();
+		let first_hops = [from_channel];
+		let mut route = self
+			.router
+			.find_route(
+				&our_node_id,
+				&route_params,
+				Some(&first_hops),
+				self.channel_manager.compute_inflight_htlcs(),
+			)
+			.map_err(|e| {
+				log_error!(self.logger, "Failed to find a route to rebalance: {}", e);
+				Error::RouteNotFound
+			})?;
+
+		let (payment_hash, payment_secret) = self
+			.channel_manager
+			.create_inbound_payment(Some(amount_msat), 3600, None)
+			.map_err(|()| {
+				log_error!(self.logger, "Failed to register payment to rebalance");
+				Error::PaymentSendingFailed
+			})?;
+
+		let path = route.paths.first_mut().ok_or(Error::RouteNotFound)?;
+		if let Some(counterparty_hop) = path.hops.last_mut() {
+			counterparty_hop.fee_msat = last_hop_fee_msat;
+			counterparty_hop.cltv_expiry_delta = forwarding_info.cltv_expiry_delta as u32;
+		}
+		path.hops.push(LdkRouteHop {
+			pubkey: our_node_id,
+			node_features: self.channel_manager.node_features(),
+			short_channel_id: to_channel_scid,
+			channel_features: ChannelFeatures::empty(),
+			fee_msat: amount_msat,
+			cltv_expiry_delta: MIN_FINAL_CLTV_EXPIRY_DELTA as u32,
+			maybe_announced_channel: to_channel.is_announced,
+		});
+		route.route_params = None;
+		let fee_msat = path.fee_msat();
+
+		let payment_id = PaymentId(payment_hash.0);
+		let recipient_fields = RecipientOnionFields::secret_only(payment_secret);
+		let kind = PaymentKind::Rebalance {
+			hash: payment_hash,
+			preimage: None,
+			secret: Some(payment_secret),
+			from_user_channel_id: *from_user_channel_id,
+			to_user_channel_id: *to_user_channel_id,
+		};
+
+		match self.channel_manager.send_payment_with_route(
+			route,
+			payment_hash,
+			recipient_fields,
+			payment_id,
+		) {
+			Ok(()) => {
+				log_info!(
+					self.logger,
+					"Initiated rebalancing {}msat from channel {} to channel {} (fee {}msat)",
+					amount_msat,
+					from_channel.channel_id,
+					to_channel.channel_id,
+					fee_msat
+				);
+				let payment = PaymentDetails::new(
+					payment_id,
+					kind,
+					Some(amount_msat),
+					None,
+					PaymentDirection::Outbound,
+					PaymentStatus::Pending,
+				);
+				self.payment_store.insert(payment)?;
+				Ok(payment_id)
+			},
+			Err(e) => {
+				log_error!(self.logger, "Failed to send rebalancing payment: {:?}", e);
+				let payment = PaymentDetails::new(
+					payment_id,
+					kind,
+					Some(amount_msat),
+					None,
+					PaymentDirection::Outbound,
+					PaymentStatus::Failed,
+				);
+				self.payment_store.insert(payment)?;
+				Err(Error::PaymentSendingFailed)
+			},
+		}
+	}
+
+	/// Finds a single-path route delivering `amount_msat` to `destination`, without sending a
+	/// payment along it.
+	///
+	/// The route is found using our current view of the network graph and our scorer. If
+	/// `route_parameters` are provided they will override the node-wide parameters configured via
+	/// [`Config::route_parameters`], e.g., to bound the total routing fee or CLTV expiry delta. If
+	/// they don't set a maximum routing fee, the node-wide one, or otherwise LDK's default, applies.
+	/// Will return [`Error::RouteNotFound`] if no route satisfying these bounds could be found.
+	///
+	/// The returned [`Route`] lists every hop along with the fee and CLTV expiry delta it
+	/// requires, and may be paid via [`Bolt11Payment::send_using_route`]. Note that the last hop
+	/// assumes a final CLTV expiry delta of at least [`MIN_FINAL_CLTV_EXPIRY_DELTA`], which needs
+	/// to be raised if the payee's invoice requires a larger one.
+	///
+	/// [`MIN_FINAL_CLTV_EXPIRY_DELTA`]: lightning::ln::channelmanager::MIN_FINAL_CLTV_EXPIRY_DELTA
+	pub fn find_route(
+		&self, destination: PublicKey, amount_msat: u64,
+		route_parameters: Option<RouteParametersConfig>,
+	) -> Result<Route, Error> {
+		if !*self.is_running.read().unwrap() {
+			return Err(Error::NotRunning);
+		}
+
+		if amount_msat == 0 {
+			log_error!(self.logger, "Failed to find route: amount must be non-zero.");
+			return Err(Error::InvalidAmount);
+		}
+
+		let node_route_parameters = self.config.route_parameters.unwrap_or_default();
+		let mut merged_route_parameters = route_parameters.unwrap_or(node_route_parameters);
+		// Fall back to the node-wide (or else LDK's default) fee cap if none is given.
+		merged_route_parameters.max_total_routing_fee_msat = merged_route_parameters
+			.max_total_routing_fee_msat
+			.or(node_route_parameters.max_total_routing_fee_msat);
+		// We can only return (and send along) a single path.
+		merged_route_parameters.max_path_count = 1;
+
+		let payment_params =
+			PaymentParameters::from_node_id(destination, MIN_FINAL_CLTV_EXPIRY_DELTA as u32);
+		let route_params =
+			route_params_from_config(payment_params, amount_msat, &merged_route_parameters);
+
+		let first_hops = self.channel_manager.list_usable_channels();
+		let first_hops = first_hops.iter().collect::<Vec<_>>();
+		let route = self
+			.router
+			.find_route(
+				&self.channel_manager.get_our_node_id(),
+				&route_params,
+				Some(&first_hops),
+				self.channel_manager.compute_inflight_htlcs(),
+			)
+			.map_err(|e| {
+				log_error!(self.logger, "Failed to find a route to {}: {}", destination, e);
+				Error::RouteNotFound
+			})?;
+
+		let path = route.paths.first().ok_or(Error::RouteNotFound)?;
+		Ok(Route::from_ldk_path(path))
+	}
+
+	/// Bumps the fee of the commitment transaction of a force-closed channel that is still pending
+	/// confirmation, by spending our anchor output via a child transaction (CPFP).
+	///
+	/// While the commitment transaction remains unconfirmed, we already bump its fee
+	/// automatically as its deadline approaches. This allows to accelerate its confirmation
+	/// further, e.g., if the fee market spiked after the channel was closed. The child transaction
+	/// is funded from our on-chain wallet, i.e., from the funds kept as
+	/// [`BalanceDetails::total_anchor_channels_reserve_sats`], and pays enough fees for the package
+	/// of the commitment and the child transaction to reach `target_fee_rate`.
+	///
+	/// Returns the [`Txid`] of the child transaction.
+	///
+	/// Returns [`Error::InvalidChannelId`] if we're not aware of a pending commitment transaction
+	/// for the given channel, [`Error::NotReplaceable`] if the commitment transaction already
+	/// confirmed, [`Error::InvalidFeeRate`] if `target_fee_rate` doesn't exceed the fee rate we
+	/// already targeted, and [`Error::InsufficientFunds`] if our on-chain funds can't cover the
+	/// fee of the child transaction.
+	#[cfg(not(feature = "uniffi"))]
+	pub fn bump_anchor_channel_fee(
+		&self, channel_id: &ChannelId, target_fee_rate: FeeRate,
+	) -> Result<Txid, Error> {
+		self.bump_anchor_channel_fee_internal(channel_id, target_fee_rate)
+	}
+
+	/// Bumps the fee of the commitment transaction of a force-closed channel that is still pending
+	/// confirmation, by spending our anchor output via a child transaction (CPFP).
+	///
+	/// While the commitment transaction remains unconfirmed, we already bump its fee
+	/// automatically as its deadline approaches. This allows to accelerate its confirmation
+	/// further, e.g., if the fee market spiked after the channel was closed. The child transaction
+	/// is funded from our on-chain wallet, i.e., from the funds kept as
+	/// [`BalanceDetails::total_anchor_channels_reserve_sats`], and pays enough fees for the package
+	/// of the commitment and the child transaction to reach `target_fee_rate`.
+	///
+	/// Returns the [`Txid`] of the child transaction.
+	///
+	/// Returns [`Error::InvalidChannelId`] if we're not aware of a pending commitment transaction
+	/// for the given channel, [`Error::NotReplaceable`] if the commitment transaction already
+	/// confirmed, [`Error::InvalidFeeRate`] if `target_fee_rate` doesn't exceed the fee rate we
+	/// already targeted, and [`Error::InsufficientFunds`] if our on-chain funds can't cover the
+	/// fee of the child transaction.
+	#[cfg(feature = "uniffi")]
+	pub fn bump_anchor_channel_fee(
+		&self, channel_id: &ChannelId, target_fee_rate: Arc<FeeRate>,
+	) -> Result<Txid, Error> {
+		self.bump_anchor_channel_fee_internal(channel_id, *target_fee_rate)
+	}
+
+	fn bump_anchor_channel_fee_internal(
+		&self, channel_id: &ChannelId, target_fee_rate: FeeRate,
+	) -> Result<Txid, Error> {
+		if !*self.is_running.read().unwrap() {
+			return Err(Error::NotRunning);
+		}
+
+		let pending_bump =
+			self.pending_channel_close_bumps.lock().unwrap().get(channel_id).cloned();
+		let mut bump_event = match pending_bump {
+			Some(bump_event) => bump_event,
+			None => {
+				log_error!(
+					self.logger,
+					"Unable to bump fee of channel {} as no pending commitment transaction is known",
+					channel_id
+				);
+				return Err(Error::InvalidChannelId);
+			},
+		};
+
+		if is_commitment_tx_confirmed(&self.chain_monitor, channel_id, &bump_event) {
+			log_error!(
+				self.logger,
+				"Unable to bump fee of channel {} as its commitment transaction already confirmed",
+				channel_id
+			);
+			self.pending_channel_close_bumps.lock().unwrap().remove(channel_id);
+			return Err(Error::NotReplaceable);
+		}
+
+		let (previous_child_txid, required_child_fee_sats) = match bump_event {
+			BumpTransactionEvent::ChannelClose {
+				ref mut package_target_feerate_sat_per_1000_weight,
+				ref commitment_tx,
+				commitment_tx_fee_satoshis,
+				ref anchor_descriptor,
+				..
+			} => {
+				let target_sat_per_kwu =
+					u32::try_from(target_fee_rate.to_sat_per_kwu()).unwrap_or(u32::MAX);
+				if target_sat_per_kwu <= *package_target_feerate_sat_per_1000_weight {
+					log_error!(
+						self.logger,
+						"Unable to bump fee of channel {} as the target fee rate of {}sat/kwu doesn't exceed the previous target of {}sat/kwu",
+						channel_id,
+						target_sat_per_kwu,
+						package_target_feerate_sat_per_1000_weight
+					);
+					return Err(Error::InvalidFeeRate);
+				}
+				*package_target_feerate_sat_per_1000_weight = target_sat_per_kwu;
+
+				let package_weight =
+					commitment_tx.weight().to_wu() + ANCHOR_CHILD_TX_WEIGHT_ESTIMATE;
+				let required_child_fee_sats = (target_sat_per_kwu as u64 * package_weight / 1000)
+					.saturating_sub(commitment_tx_fee_satoshis);
+				let previous_child_txid = self
+					.tx_broadcaster
+					.recent_spend_of(&anchor_descriptor.outpoint)
+					.map(|tx| tx.compute_txid());
+				(previous_child_txid, required_child_fee_sats)
+			},
+			BumpTransactionEvent::HTLCResolution { .. } => {
+				debug_assert!(false, "We only ever track channel close bumps");
+				return Err(Error::InvalidChannelId);
+			},
+		};
+
+		let available_sats = self.wallet.get_fee_bump_spendable_sats();
+		if available_sats < required_child_fee_sats {
+			log_error!(
+				self.logger,
+				"Unable to bump fee of channel {} due to insufficient funds. Available: {}sats, Required: {}sats",
+				channel_id,
+				available_sats,
+				required_child_fee_sats
+			);
+			return Err(Error::InsufficientFunds);
 		}
 
-		self.open_channel_inner(
-			node_id,
-			address,
-			channel_amount_sats,
-			push_to_counterparty_msat,
-			channel_config,
-			true,
-		)
-	}
+		let bump_tx_event_handler = Arc::clone(&self.bump_tx_event_handler);
+		let handled_event = bump_event.clone();
+		self.runtime
+			.block_on(async move { bump_tx_event_handler.handle_event(&handled_event).await });
+
+		let child_txid = match &bump_event {
+			BumpTransactionEvent::ChannelClose { anchor_descriptor, .. } => self
+				.tx_broadcaster
+				.recent_spend_of(&anchor_descriptor.outpoint)
+				.map(|tx| tx.compute_txid())
+				.filter(|txid| Some(*txid) != previous_child_txid),
+			BumpTransactionEvent::HTLCResolution { .. } => None,
+		};
 
-	/// Manually sync the LDK and BDK wallets with the current chain state and update the fee rate
-	/// cache.
-	///
-	/// **Note:** The wallets are regularly synced in the background if background syncing is enabled
-	/// via [`EsploraSyncConfig::background_sync_config`]. Therefore, using this blocking sync method
-	/// is almost always redundant when background syncing is enabled and should be avoided where possible.
-	/// However, if background syncing is disabled (i.e., `background_sync_config` is set to `None`),
-	/// this method must be called manually to keep wallets in sync with the chain state.
-	///
-	/// [`EsploraSyncConfig::background_sync_config`]: crate::config::EsploraSyncConfig::background_sync_config
-	pub fn sync_wallets(&self) -> Result<(), Error> {
-		if !*self.is_running.read().unwrap() {
-			return Err(Error::NotRunning);
+		match child_txid {
+			Some(child_txid) => {
+				log_info!(
+					self.logger,
+					"Bumped fee of commitment transaction for channel {} via child transaction {}",
+					channel_id,
+					child_txid
+				);
+				self.pending_channel_close_bumps.lock().unwrap().insert(*channel_id, bump_event);
+				Ok(child_txid)
+			},
+			None => {
+				log_error!(
+					self.logger,
+					"Failed to create anchor transaction bumping the fee of channel {}",
+					channel_id
+				);
+				Err(Error::OnchainTxCreationFailed)
+			},
 		}
-
-		let chain_source = Arc::clone(&self.chain_source);
-		let sync_cman = Arc::clone(&self.channel_manager);
-		let sync_cmon = Arc::clone(&self.chain_monitor);
-		let sync_sweeper = Arc::clone(&self.output_sweeper);
-		self.runtime.block_on(async move {
-			if chain_source.is_transaction_based() {
-				chain_source.update_fee_rate_estimates().await?;
-				chain_source
-					.sync_lightning_wallet(sync_cman, sync_cmon, Arc::clone(&sync_sweeper))
-					.await?;
-				chain_source.sync_onchain_wallet().await?;
-			} else {
-				chain_source.update_fee_rate_estimates().await?;
-				chain_source
-					.poll_and_update_listeners(sync_cman, sync_cmon, Arc::clone(&sync_sweeper))
-					.await?;
-			}
-			let _ = sync_sweeper.regenerate_and_broadcast_spend_if_necessary().await;
-			Ok(())
-		})
 	}
 
-	/// Close a previously opened channel.
+	/// Returns the status of our watchtower client, i.e., how many of our channels' revoked states
+	/// were backed up to the tower configured via [`Builder::set_watchtower`].
 	///
-	/// Will attempt to close a channel coopertively. If this fails, users might need to resort to
-	/// [`Node::force_close_channel`].
-	pub fn close_channel(
-		&self, user_channel_id: &UserChannelId, counterparty_node_id: PublicKey,
-	) -> Result<(), Error> {
-		self.close_channel_internal(user_channel_id, counterparty_node_id, false, None)
+	/// Will return `None` if no watchtower was configured.
+	pub fn watchtower_status(&self) -> Option<WatchtowerStatus> {
+		self.watchtower_client.as_ref().map(|client| client.status())
 	}
 
-	/// Force-close a previously opened channel.
+	/// Returns a new intercept SCID, i.e., a fake short channel id that can be used in the route
+	/// hints of invoices to have the payer's HTLCs intercepted by us.
 	///
-	/// Will force-close the channel, potentially broadcasting our latest state. Note that in
-	/// contrast to cooperative closure, force-closing will have the channel funds time-locked,
-	/// i.e., they will only be available after the counterparty had time to contest our claim.
-	/// Force-closing channels also more costly in terms of on-chain fees. So cooperative closure
-	/// should always be preferred (and tried first).
+	/// HTLCs requesting to be forwarded over the returned SCID will be held and surfaced via
+	/// [`Event::HTLCIntercepted`] if [`Config::accept_intercept_htlcs`] is set.
 	///
-	/// Broadcasting the closing transactions will be omitted for Anchor channels if we trust the
-	/// counterparty to broadcast for us (see [`AnchorChannelsConfig::trusted_peers_no_reserve`]
-	/// for more information).
+	/// **Note:** The returned value is not guaranteed to be unique, i.e., this method might need to
+	/// be called a few times if uniqueness is required.
+	pub fn new_intercept_scid(&self) -> u64 {
+		self.channel_manager.get_intercept_scid()
+	}
+
+	/// Forwards an HTLC previously intercepted via [`Event::HTLCIntercepted`] over the channel with
+	/// the given `next_hop_channel_id`.
 	///
-	/// [`AnchorChannelsConfig::trusted_peers_no_reserve`]: crate::config::AnchorChannelsConfig::trusted_peers_no_reserve
-	pub fn force_close_channel(
-		&self, user_channel_id: &UserChannelId, counterparty_node_id: PublicKey,
-		reason: Option<String>,
+	/// The `amount_to_forward_msat` may differ from the amount of the intercepted HTLC, e.g., to
+	/// take a fee. Note that we don't enforce any fee requirements here and will not prevent
+	/// forwarding more than we received.
+	///
+	/// Returns [`Error::InvalidInterceptId`] if the HTLC is unknown, e.g., as it was already
+	/// handled or failed back automatically as its deadline passed, and
+	/// [`Error::InvalidChannelId`] if no channel with the given ID exists.
+	pub fn forward_intercepted_htlc(
+		&self, intercept_id: &InterceptId, next_hop_channel_id: &ChannelId,
+		amount_to_forward_msat: u64,
 	) -> Result<(), Error> {
-		self.close_channel_internal(user_channel_id, counterparty_node_id, true, reason)
+		let next_node_id = self
+			.channel_manager
+			.list_channels()
+			.into_iter()
+			.find(|c| c.channel_id == *next_hop_channel_id)
+			.map(|c| c.counterparty.node_id)
+			.ok_or(Error::InvalidChannelId)?;
+
+		self.channel_manager
+			.forward_intercepted_htlc(
+				*intercept_id,
+				next_hop_channel_id,
+				next_node_id,
+				amount_to_forward_msat,
+			)
+			.map_err(|e| {
+				log_error!(
+					self.logger,
+					"Failed to forward intercepted HTLC over channel {}: {:?}",
+					next_hop_channel_id,
+					e
+				);
+				match e {
+					APIError::APIMisuseError { .. } => Error::InvalidInterceptId,
+					_ => Error::HtlcForwardingFailed,
+				}
+			})
 	}
 
-	fn close_channel_internal(
-		&self, user_channel_id: &UserChannelId, counterparty_node_id: PublicKey, force: bool,
-		force_close_reason: Option<String>,
+	/// Fails back an HTLC previously intercepted via [`Event::HTLCIntercepted`].
+	///
+	/// Returns [`Error::InvalidInterceptId`] if the HTLC is unknown, e.g., as it was already
+	/// handled or failed back automatically as its deadline passed.
+	pub fn fail_intercepted_htlc(&self, intercept_id: &InterceptId) -> Result<(), Error> {
+		self.channel_manager.fail_intercepted_htlc(*intercept_id).map_err(|e| {
+			log_error!(self.logger, "Failed to fail intercepted HTLC: {:?}", e);
+			Error::InvalidInterceptId
+		})
+	}
+
+	/// Update the config for a previously opened channel.
+	pub fn update_channel_config(
+		&self, user_channel_id: &UserChannelId, counterparty_node_id: PublicKey,
+		channel_config: ChannelConfig,
 	) -> Result<(), Error> {
-		debug_assert!(
-			force_close_reason.is_none() || force,
-			"Reason can only be set for force closures"
-		);
 		let open_channels =
 			self.channel_manager.list_channels_with_counterparty(&counterparty_node_id);
 		if let Some(channel_details) =
 			open_channels.iter().find(|c| c.user_channel_id == user_channel_id.0)
 		{
-			if force {
-				self.channel_manager
-					.force_close_broadcasting_latest_txn(
-						&channel_details.channel_id,
-						&counterparty_node_id,
-						force_close_reason.unwrap_or_default(),
-					)
-					.map_err(|e| {
-						log_error!(self.logger, "Failed to force-close channel: {:?}", e);
-						Error::ChannelClosingFailed
-					})?;
-			} else {
-				self.channel_manager
-					.close_channel(&channel_details.channel_id, &counterparty_node_id)
-					.map_err(|e| {
-						log_error!(self.logger, "Failed to close channel: {:?}", e);
-						Error::ChannelClosingFailed
-					})?;
-			}
-
-			// Check if this was the last open channel, if so, forget the peer.
-			if open_channels.len() == 1 {
-				self.peer_store.remove_peer(&counterparty_node_id)?;
-			}
+			self.channel_manager
+				.update_channel_config(
+					&counterparty_node_id,
+					&[channel_details.channel_id],
+					&(channel_config).clone().into(),
+				)
+				.map_err(|_| Error::ChannelConfigUpdateFailed)
+		} else {
+			Err(Error::ChannelConfigUpdateFailed)
 		}
-
-		Ok(())
 	}
 
-	/// Update the config for a previously opened channel.
-	pub fn update_channel_config(
+	/// Update individual fields of the config for a previously opened channel.
+	///
+	/// In contrast to [`Node::update_channel_config`], only the fields set in the given
+	/// [`ChannelConfigUpdate`] are changed, while all others retain their current values.
+	///
+	/// If the forwarding fees or the CLTV expiry delta of an announced channel change, an updated
+	/// `channel_update` gossip message will be broadcast so that the network learns about the new
+	/// parameters.
+	pub fn update_partial_channel_config(
 		&self, user_channel_id: &UserChannelId, counterparty_node_id: PublicKey,
-		channel_config: ChannelConfig,
+		channel_config_update: ChannelConfigUpdate,
 	) -> Result<(), Error> {
 		let open_channels =
 			self.channel_manager.list_channels_with_counterparty(&counterparty_node_id);
@@ -1331,12 +3871,15 @@ impl Node {
 			open_channels.iter().find(|c| c.user_channel_id == user_channel_id.0)
 		{
 			self.channel_manager
-				.update_channel_config(
+				.update_partial_channel_config(
 					&counterparty_node_id,
 					&[channel_details.channel_id],
-					&(channel_config).clone().into(),
+					&channel_config_update.into(),
 				)
-				.map_err(|_| Error::ChannelConfigUpdateFailed)
+				.map_err(|e| {
+					log_error!(self.logger, "Failed to update channel config: {:?}", e);
+					Error::ChannelConfigUpdateFailed
+				})
 		} else {
 			Err(Error::ChannelConfigUpdateFailed)
 		}
@@ -1349,11 +3892,64 @@ impl Node {
 		self.payment_store.get(payment_id)
 	}
 
+	/// Retrieve the details of a specific payment with the given payment hash.
+	///
+	/// This allows to correlate payments with invoices that were handed out earlier, even if the
+	/// payment's id differs from its hash, as is the case for BOLT 12 payments. Note that the
+	/// individual parts of a multi-path payment are tracked as a single payment.
+	///
+	/// If several payments share the given hash, e.g., as a failed outbound payment was retried
+	/// via a different payment method, the most recently recorded one is returned.
+	///
+	/// Returns `Some` if a payment with the given hash was known and `None` otherwise.
+	pub fn payment_by_hash(&self, payment_hash: PaymentHash) -> Option<PaymentDetails> {
+		self.payment_store.get_by_index_key(&payment_hash)
+	}
+
+	/// Retrieve the id of the payment that was sent with the given idempotency key.
+	///
+	/// Returns `Some` if a payment was sent with the key and `None` otherwise.
+	///
+	/// See [`SendPaymentParams::idempotency_key`] for details.
+	///
+	/// [`SendPaymentParams::idempotency_key`]: crate::config::SendPaymentParams::idempotency_key
+	pub fn payment_id_for_idempotency_key(&self, idempotency_key: String) -> Option<PaymentId> {
+		self.payment_idempotency_keys.get(&idempotency_key)
+	}
+
+	/// Fails back all inbound payments that are currently held, i.e., all manually-claimable
+	/// payments for which we emitted an [`Event::PaymentClaimable`] but that weren't claimed or
+	/// failed yet.
+	///
+	/// This may be useful to resolve all held payments at once, e.g., before shutting down the
+	/// node for maintenance. An [`Event::PaymentAutoFailed`] will be emitted for each payment
+	/// failed back.
+	///
+	/// Returns the hashes of the payments that were failed back.
+	pub fn fail_all_pending_claimable(&self) -> Vec<PaymentHash> {
+		self.claim_watchdog.fail_all_pending_claims()
+	}
+
 	/// Remove the payment with the given id from the store.
+	///
+	/// This also forgets the idempotency key the payment was sent with, if any.
 	pub fn remove_payment(&self, payment_id: &PaymentId) -> Result<(), Error> {
+		self.payment_idempotency_keys.remove_payment(payment_id)?;
 		self.payment_store.remove(&payment_id)
 	}
 
+	/// Returns the currently spendable balance of our on-chain wallet.
+	///
+	/// This includes any sufficiently confirmed funds, minus the on-chain reserve we need to keep
+	/// for Anchor channels, and hence matches the amount [`OnchainPayment::send_all_to_address`]
+	/// would be allowed to move (before fees) if `retain_reserves` is set. See
+	/// [`BalanceDetails::spendable_onchain_balance_sats`] for more information.
+	pub fn spendable_onchain_balance_sats(&self) -> u64 {
+		let cur_anchor_reserve_sats =
+			total_anchor_channels_reserve_sats(&self.channel_manager, &self.config);
+		self.wallet.get_spendable_amount_sats(cur_anchor_reserve_sats).unwrap_or(0)
+	}
+
 	/// Retrieves an overview of all known balances.
 	pub fn list_balances(&self) -> BalanceDetails {
 		let cur_anchor_reserve_sats =
@@ -1363,6 +3959,8 @@ impl Node {
 
 		let total_anchor_channels_reserve_sats =
 			std::cmp::min(cur_anchor_reserve_sats, total_onchain_balance_sats);
+		let spendable_anchor_reserve_sats =
+			self.wallet.get_spendable_anchor_reserve_sats(cur_anchor_reserve_sats);
 
 		let mut total_lightning_balance_sats = 0;
 		let mut lightning_balances = Vec::new();
@@ -1370,11 +3968,23 @@ impl Node {
 			match self.chain_monitor.get_monitor(channel_id) {
 				Ok(monitor) => {
 					let counterparty_node_id = monitor.get_counterparty_node_id();
+					let funding_txo = monitor.get_funding_txo().into_bitcoin_outpoint();
+					let mut claim_outpoints = monitor
+						.get_outputs_to_watch()
+						.into_iter()
+						.flat_map(|(txid, outputs)| {
+							outputs.into_iter().map(move |(vout, _)| OutPoint { txid, vout })
+						})
+						.filter(|outpoint| *outpoint != funding_txo)
+						.collect::<Vec<_>>();
+					claim_outpoints.sort();
 					for ldk_balance in monitor.get_claimable_balances() {
 						total_lightning_balance_sats += ldk_balance.claimable_amount_satoshis();
 						lightning_balances.push(LightningBalance::from_ldk_balance(
 							channel_id,
 							counterparty_node_id,
+							funding_txo,
+							claim_outpoints.clone(),
 							ldk_balance,
 						));
 					}
@@ -1396,12 +4006,40 @@ impl Node {
 			total_onchain_balance_sats,
 			spendable_onchain_balance_sats,
 			total_anchor_channels_reserve_sats,
+			spendable_anchor_reserve_sats,
 			total_lightning_balance_sats,
 			lightning_balances,
 			pending_balances_from_channel_closures,
 		}
 	}
 
+	/// Retrieves the outputs recovered from closed channels that are awaiting their sweep, either
+	/// into our on-chain wallet or to the configured [`Config::spendable_output_sweep_destination`].
+	///
+	/// This includes outputs whose sweeping transaction has yet to confirm. Outputs are only
+	/// listed once we're able to spend them, while the funds of channels that are still awaiting
+	/// the maturity of their timelocks are reported as
+	/// [`LightningBalance::ClaimableAwaitingConfirmations`] by [`Node::list_balances`].
+	///
+	/// If [`Config::spendable_output_sweep_config`] is set, mature outputs may remain pending
+	/// for up to the configured batch window before being swept.
+	pub fn pending_sweeps(&self) -> Vec<PendingSweep> {
+		let cur_height = self.output_sweeper.current_best_block().height;
+		self.output_sweeper
+			.tracked_spendable_outputs()
+			.into_iter()
+			.filter_map(|output| PendingSweep::from_tracked_spendable_output(output, cur_height))
+			.collect()
+	}
+
+	/// Returns the total fees, in thousandths of a satoshi, we earned from forwarding payments.
+	///
+	/// This is the sum of the [`Event::PaymentForwarded::total_fee_earned_msat`] values of all
+	/// forwards we relayed and is persisted across restarts.
+	pub fn total_forwarding_fees_earned_msat(&self) -> u64 {
+		self.node_metrics.read().unwrap().total_forwarding_fees_earned_msat
+	}
+
 	/// Retrieves all payments that match the given predicate.
 	///
 	/// For example, you could retrieve all stored outbound payments as follows:
@@ -1428,10 +4066,68 @@ impl Node {
 		self.payment_store.list_filter(|_| true)
 	}
 
+	/// Retrieves the payments matching the given [`PaymentFilter`], most recently updated first.
+	///
+	/// Use [`PaymentFilter::limit`] and [`PaymentFilter::offset`] to page through the results, and
+	/// [`Node::count_payments`] to retrieve the total number of matching payments.
+	pub fn query_payments(&self, filter: PaymentFilter) -> Vec<PaymentDetails> {
+		self.payment_store.list_filter_sorted(
+			|p| filter.matches(p),
+			|p| (std::cmp::Reverse(p.latest_update_timestamp), p.id.0),
+			filter.offset as usize,
+			filter.limit.map(|l| l as usize),
+		)
+	}
+
+	/// Returns the number of payments matching the given [`PaymentFilter`].
+	///
+	/// Note that [`PaymentFilter::limit`] and [`PaymentFilter::offset`] are ignored.
+	pub fn count_payments(&self, filter: PaymentFilter) -> u64 {
+		self.payment_store.count_filter(|p| filter.matches(p)) as u64
+	}
+
+	/// Returns aggregated amounts, fees, and counts of our payments, e.g., for use in daily or
+	/// monthly reports.
+	///
+	/// If `since_unix_secs` is set, only payments last updated at or after the given time, in
+	/// seconds since the UNIX epoch, are considered.
+	///
+	/// Note that forwarding fees are accounted for in daily buckets, i.e., when windowing, the fees
+	/// earned since the start of the (UTC) day `since_unix_secs` falls on are returned. Forwarding
+	/// fees earned before upgrading to a version supporting this are only included if
+	/// `since_unix_secs` is unset.
+	pub fn payment_stats(&self, since_unix_secs: Option<u64>) -> PaymentStats {
+		// The totals over all payments are maintained as payments are stored, while windowed
+		// totals require us to consider each payment.
+		let mut stats = match since_unix_secs {
+			Some(t) => self.payment_store.fold(PaymentStats::default(), |mut stats, payment| {
+				if payment.latest_update_timestamp >= t {
+					stats.add_payment(payment);
+				}
+				stats
+			}),
+			None => self.payment_store.aggregate(),
+		};
+
+		let locked_node_metrics = self.node_metrics.read().unwrap();
+		stats.forwarding_fees_earned_msat = match since_unix_secs {
+			Some(t) => locked_node_metrics.forwarding_fees_earned_since(t),
+			None => locked_node_metrics.total_forwarding_fees_earned_msat,
+		};
+		stats
+	}
+
 	/// Retrieves a list of known peers.
 	pub fn list_peers(&self) -> Vec<PeerDetails> {
 		let mut peers = Vec::new();
 
+		let mut channel_counts: HashMap<PublicKey, u32> = HashMap::new();
+		for channel in self.channel_manager.list_channels() {
+			*channel_counts.entry(channel.counterparty.node_id).or_default() += 1;
+		}
+		let total_channels =
+			|node_id: &PublicKey| channel_counts.get(node_id).copied().unwrap_or(0);
+
 		// First add all connected peers, preferring to list the connected address if available.
 		let connected_peers = self.peer_manager.list_peers();
 		let connected_peers_len = connected_peers.len();
@@ -1447,7 +4143,21 @@ impl Node {
 
 			let is_persisted = stored_peer.is_some();
 			let is_connected = true;
-			let details = PeerDetails { node_id, address, is_persisted, is_connected };
+			let (last_connection_attempt_timestamp, last_connection_failure_reason) =
+				self.connection_manager.last_connection_attempt(&node_id);
+			let connection_history = self.peer_store.connection_history(&node_id);
+			let details = PeerDetails {
+				node_id,
+				address,
+				is_persisted,
+				is_connected,
+				last_connection_attempt_timestamp,
+				last_connection_failure_reason,
+				label: self.label_store.peer_label(&node_id),
+				last_connected_unix_secs: connection_history.last_connected_unix_secs,
+				last_disconnected_unix_secs: connection_history.last_disconnected_unix_secs,
+				total_channels: total_channels(&node_id),
+			};
 			peers.push(details);
 		}
 
@@ -1457,11 +4167,20 @@ impl Node {
 				continue;
 			}
 
+			let (last_connection_attempt_timestamp, last_connection_failure_reason) =
+				self.connection_manager.last_connection_attempt(&p.node_id);
+			let connection_history = self.peer_store.connection_history(&p.node_id);
 			let details = PeerDetails {
 				node_id: p.node_id,
 				address: p.address,
 				is_persisted: true,
 				is_connected: false,
+				last_connection_attempt_timestamp,
+				last_connection_failure_reason,
+				label: self.label_store.peer_label(&p.node_id),
+				last_connected_unix_secs: connection_history.last_connected_unix_secs,
+				last_disconnected_unix_secs: connection_history.last_disconnected_unix_secs,
+				total_channels: total_channels(&p.node_id),
 			};
 
 			peers.push(details);
@@ -1488,16 +4207,115 @@ impl Node {
 	/// can be sure that the signature was generated by the caller.
 	/// Signatures are EC recoverable, meaning that given the message and the
 	/// signature the `PublicKey` of the signer can be extracted.
+	///
+	/// The signature is zbase32-encoded, following the same "Lightning Signed Message"
+	/// convention as LND's and CLN's `signmessage`.
 	pub fn sign_message(&self, msg: &[u8]) -> String {
 		self.keys_manager.sign_message(msg)
 	}
 
 	/// Verifies that the given ECDSA signature was created for the given message with the
 	/// secret key corresponding to the given public key.
+	///
+	/// This accepts zbase32-encoded signatures as produced by [`Node::sign_message`], or by LND's
+	/// and CLN's `signmessage`.
 	pub fn verify_signature(&self, msg: &[u8], sig: &str, pkey: &PublicKey) -> bool {
 		self.keys_manager.verify_signature(msg, sig, pkey)
 	}
 
+	/// Sends a custom onion message of the given TLV type with the given contents to `recipient`.
+	///
+	/// If `path` is given, the message will be routed via the given intermediate nodes, the first
+	/// of which needs to be a connected peer. Otherwise, the recipient needs to be a connected peer
+	/// or an announced node we can connect to. Paths may consist of at most
+	/// [`MAX_ONION_MESSAGE_PATH_INTERMEDIATE_NODES`] intermediate nodes.
+	///
+	/// The TLV type needs to be at least [`MIN_CUSTOM_ONION_MESSAGE_TLV_TYPE`] and mustn't be
+	/// used by any of the protocols handled by LDK, such as BOLT12 offers. The recipient will
+	/// surface the message as an [`Event::OnionMessageReceived`] if it runs LDK Node with
+	/// [`Config::accept_custom_onion_messages`] set.
+	///
+	/// Note that onion messages are delivered on a best-effort basis, i.e., a successful return
+	/// doesn't guarantee that the message reached the recipient.
+	///
+	/// [`Config::accept_custom_onion_messages`]: crate::config::Config::accept_custom_onion_messages
+	/// [`MAX_ONION_MESSAGE_PATH_INTERMEDIATE_NODES`]: crate::config::MAX_ONION_MESSAGE_PATH_INTERMEDIATE_NODES
+	/// [`MIN_CUSTOM_ONION_MESSAGE_TLV_TYPE`]: crate::config::MIN_CUSTOM_ONION_MESSAGE_TLV_TYPE
+	pub fn send_onion_message(
+		&self, recipient: PublicKey, path: Option<Vec<PublicKey>>, tlv_type: u64, data: Vec<u8>,
+	) -> Result<(), Error> {
+		if !*self.is_running.read().unwrap() {
+			return Err(Error::NotRunning);
+		}
+
+		if !RawOnionMessage::is_permitted_tlv_type(tlv_type) {
+			log_error!(
+				self.logger,
+				"Failed to send onion message: TLV type {} not permitted",
+				tlv_type
+			);
+			return Err(Error::InvalidOnionMessage);
+		}
+
+		if path.as_ref().map_or(false, |p| p.len() > MAX_ONION_MESSAGE_PATH_INTERMEDIATE_NODES) {
+			log_error!(self.logger, "Failed to send onion message: path too long");
+			return Err(Error::InvalidOnionMessage);
+		}
+
+		let message = RawOnionMessage { tlv_type, data };
+		let destination = Destination::Node(recipient);
+		let instructions = MessageSendInstructions::WithoutReplyPath { destination };
+		let res = match path {
+			Some(intermediate_nodes) => {
+				self.message_router.with_given_path(recipient, intermediate_nodes, || {
+					self.onion_messenger.send_onion_message(message, instructions)
+				})
+			},
+			None => self.onion_messenger.send_onion_message(message, instructions),
+		};
+
+		match res {
+			Ok(_) => {
+				log_info!(
+					self.logger,
+					"Sent onion message of TLV type {} to {}",
+					tlv_type,
+					recipient
+				);
+				Ok(())
+			},
+			Err(e) => {
+				log_error!(self.logger, "Failed to send onion message: {:?}", e);
+				match e {
+					SendError::TooBigPacket | SendError::InvalidMessage => {
+						Err(Error::InvalidOnionMessage)
+					},
+					_ => Err(Error::OnionMessageSendingFailed),
+				}
+			},
+		}
+	}
+
+	/// Resets our pathfinding scorer, discarding everything it learned about the liquidity
+	/// available in the channels of the network.
+	///
+	/// This also discards any external scores merged from the source configured via
+	/// [`Builder::set_pathfinding_scores_source`], until they're synced the next time.
+	///
+	/// [`Builder::set_pathfinding_scores_source`]: crate::Builder::set_pathfinding_scores_source
+	pub fn reset_scorer(&self) -> Result<(), Error> {
+		let local_scorer = ProbabilisticScorer::new(
+			ProbabilisticScoringDecayParameters::default(),
+			Arc::clone(&self.network_graph),
+			Arc::clone(&self.logger),
+		);
+		let mut locked_scorer = self.scorer.lock().unwrap();
+		*locked_scorer = CombinedScorer::new(local_scorer);
+		write_scorer(&locked_scorer, Arc::clone(&self.kv_store), Arc::clone(&self.logger))?;
+		log_info!(self.logger, "Reset pathfinding scorer.");
+		Ok(())
+	}
+
 	/// Exports the current state of the scorer. The result can be shared with and merged by light nodes that only have
 	/// a limited view of the network.
 	pub fn export_pathfinding_scores(&self) -> Result<Vec<u8>, Error> {
@@ -1516,6 +4334,40 @@ impl Node {
 			Error::PersistenceFailed
 		})
 	}
+
+	/// Exports an encrypted static channel backup (SCB) of all current channels.
+	///
+	/// The backup is encrypted with a key derived from the node's seed and can be used to recover
+	/// the funds held in our channels via [`Builder::recover_from_static_channel_backup`] on a fresh
+	/// node using the same seed, e.g., if the node's storage was lost.
+	///
+	/// The backup reflects the channel state at the time of the call, and should therefore be
+	/// exported again whenever the channel state changes. It's safe to call this periodically.
+	///
+	/// **Caution**: Recovering from a static channel backup forfeits the ability to continue using
+	/// the backed-up channels, it only recovers the funds.
+	///
+	/// [`Builder::recover_from_static_channel_backup`]: crate::Builder::recover_from_static_channel_backup
+	pub fn export_static_channel_backup(&self) -> Vec<u8> {
+		let mut channel_monitors = Vec::new();
+		let mut channel_counterparties = HashSet::new();
+		for channel_id in self.chain_monitor.list_monitors() {
+			if let Ok(channel_monitor) = self.chain_monitor.get_monitor(channel_id) {
+				channel_counterparties.insert(channel_monitor.get_counterparty_node_id());
+				channel_monitors.push(channel_monitor.encode());
+			}
+		}
+
+		let peers = self
+			.peer_store
+			.list_peers()
+			.into_iter()
+			.filter(|p| channel_counterparties.contains(&p.node_id))
+			.collect();
+
+		let backup = StaticChannelBackup { channel_monitors, peers };
+		backup.encrypt(self.static_channel_backup_key)
+	}
 }
 
 impl Drop for Node {
@@ -1525,6 +4377,10 @@ impl Drop for Node {
 }
 
 /// Represents the status of the [`Node`].
+///
+/// The sync timestamps allow to determine how stale the node's view of the chain is. They only
+/// refer to syncs that happened since the [`Node`] was last started, i.e., they will be `None`
+/// until the respective sync succeeds after a (re-)start.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct NodeStatus {
 	/// Indicates whether the [`Node`] is running.
@@ -1534,17 +4390,17 @@ pub struct NodeStatus {
 	/// The timestamp, in seconds since start of the UNIX epoch, when we last successfully synced
 	/// our Lightning wallet to the chain tip.
 	///
-	/// Will be `None` if the wallet hasn't been synced yet.
+	/// Will be `None` if the wallet hasn't been synced since the node was started.
 	pub latest_lightning_wallet_sync_timestamp: Option<u64>,
 	/// The timestamp, in seconds since start of the UNIX epoch, when we last successfully synced
 	/// our on-chain wallet to the chain tip.
 	///
-	/// Will be `None` if the wallet hasn't been synced yet.
+	/// Will be `None` if the wallet hasn't been synced since the node was started.
 	pub latest_onchain_wallet_sync_timestamp: Option<u64>,
 	/// The timestamp, in seconds since start of the UNIX epoch, when we last successfully update
 	/// our fee rate cache.
 	///
-	/// Will be `None` if the cache hasn't been updated yet.
+	/// Will be `None` if the cache hasn't been updated since the node was started.
 	pub latest_fee_rate_cache_update_timestamp: Option<u64>,
 	/// The timestamp, in seconds since start of the UNIX epoch, when the last rapid gossip sync
 	/// (RGS) snapshot we successfully applied was generated.
@@ -1552,11 +4408,14 @@ pub struct NodeStatus {
 	/// Will be `None` if RGS isn't configured or the snapshot hasn't been updated yet.
 	pub latest_rgs_snapshot_timestamp: Option<u64>,
 	/// The timestamp, in seconds since start of the UNIX epoch, when we last successfully merged external scores.
+	///
+	/// Will be `None` if external scores haven't been merged since the node was started.
 	pub latest_pathfinding_scores_sync_timestamp: Option<u64>,
 	/// The timestamp, in seconds since start of the UNIX epoch, when we last broadcasted a node
 	/// announcement.
 	///
-	/// Will be `None` if we have no public channels or we haven't broadcasted yet.
+	/// Will be `None` if we have no public channels or we haven't broadcasted since the node was
+	/// started.
 	pub latest_node_announcement_broadcast_timestamp: Option<u64>,
 	/// The block height when we last archived closed channel monitor data.
 	///
@@ -1564,6 +4423,31 @@ pub struct NodeStatus {
 	pub latest_channel_monitor_archival_height: Option<u32>,
 }
 
+/// The information a [`Node`] advertises, or would advertise, in its node announcement.
+///
+/// Returned by [`Node::node_announcement_info`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OwnNodeAnnouncementInfo {
+	/// Our node id.
+	pub node_id: PublicKey,
+	/// The alias we announce.
+	///
+	/// Will be `None` if [`Config::node_alias`] is unset.
+	pub alias: Option<NodeAlias>,
+	/// The RGB color we announce, as set via [`Config::node_color`].
+	pub color: [u8; 3],
+	/// The addresses on which we announce to accept connections.
+	///
+	/// See [`Node::announcement_addresses`] for details.
+	pub addresses: Vec<SocketAddress>,
+	/// The feature bits we announce.
+	pub features: NodeFeatures,
+	/// Indicates whether the node is configured to announce itself to the network.
+	///
+	/// If `false`, the remaining fields describe the announcement we would broadcast if it was.
+	pub is_announced: bool,
+}
+
 /// Status fields that are persisted across restarts.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub(crate) struct NodeMetrics {
@@ -1574,6 +4458,40 @@ pub(crate) struct NodeMetrics {
 	latest_pathfinding_scores_sync_timestamp: Option<u64>,
 	latest_node_announcement_broadcast_timestamp: Option<u64>,
 	latest_channel_monitor_archival_height: Option<u32>,
+	total_forwarding_fees_earned_msat: u64,
+	// The forwarding fees earned, bucketed by the day (i.e., the number of days since the UNIX
+	// epoch) they were earned on.
+	daily_forwarding_fees_earned_msat: BTreeMap<u64, u64>,
+	// The ids of the forwards we most recently accounted fees for, oldest first.
+	recent_forward_ids: Vec<[u8; 32]>,
+}
+
+impl NodeMetrics {
+	pub(crate) fn recent_forward_ids(&self) -> &[[u8; 32]] {
+		&self.recent_forward_ids
+	}
+
+	pub(crate) fn record_forwarding_fee(&mut self, forward_id: [u8; 32], fee_earned_msat: u64) {
+		if self.recent_forward_ids.len() >= MAX_RECENT_FORWARDS {
+			self.recent_forward_ids.remove(0);
+		}
+		self.recent_forward_ids.push(forward_id);
+
+		self.total_forwarding_fees_earned_msat =
+			self.total_forwarding_fees_earned_msat.saturating_add(fee_earned_msat);
+		let now_secs = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+		let daily_fees_msat = self
+			.daily_forwarding_fees_earned_msat
+			.entry(now_secs / FORWARDING_FEES_BUCKET_SECS)
+			.or_insert(0);
+		*daily_fees_msat = daily_fees_msat.saturating_add(fee_earned_msat);
+	}
+
+	fn forwarding_fees_earned_since(&self, since_unix_secs: u64) -> u64 {
+		self.daily_forwarding_fees_earned_msat
+			.range(since_unix_secs / FORWARDING_FEES_BUCKET_SECS..)
+			.fold(0u64, |total, (_, fees_msat)| total.saturating_add(*fees_msat))
+	}
 }
 
 impl Default for NodeMetrics {
@@ -1586,6 +4504,9 @@ impl Default for NodeMetrics {
 			latest_pathfinding_scores_sync_timestamp: None,
 			latest_node_announcement_broadcast_timestamp: None,
 			latest_channel_monitor_archival_height: None,
+			total_forwarding_fees_earned_msat: 0,
+			daily_forwarding_fees_earned_msat: BTreeMap::new(),
+			recent_forward_ids: Vec::new(),
 		}
 	}
 }
@@ -1598,8 +4519,25 @@ impl_writeable_tlv_based!(NodeMetrics, {
 	(6, latest_rgs_snapshot_timestamp, option),
 	(8, latest_node_announcement_broadcast_timestamp, option),
 	(10, latest_channel_monitor_archival_height, option),
+	(11, total_forwarding_fees_earned_msat, (default_value, 0)),
+	(13, daily_forwarding_fees_earned_msat, (default_value, BTreeMap::new())),
+	(15, recent_forward_ids, optional_vec),
 });
 
+// Returns whether the commitment transaction the given `BumpTransactionEvent::ChannelClose` bumps
+// confirmed, or its channel monitor is gone already.
+fn is_commitment_tx_confirmed(
+	chain_monitor: &ChainMonitor, channel_id: &ChannelId, bump_event: &BumpTransactionEvent,
+) -> bool {
+	let commitment_txid = match bump_event {
+		BumpTransactionEvent::ChannelClose { commitment_tx, .. } => commitment_tx.compute_txid(),
+		BumpTransactionEvent::HTLCResolution { .. } => return true,
+	};
+	chain_monitor.get_monitor(*channel_id).map_or(true, |m| {
+		m.get_relevant_txids().iter().any(|(txid, _, _)| *txid == commitment_txid)
+	})
+}
+
 pub(crate) fn total_anchor_channels_reserve_sats(
 	channel_manager: &ChannelManager, config: &Config,
 ) -> u64 {
@@ -1608,7 +4546,7 @@ pub(crate) fn total_anchor_channels_reserve_sats(
 			.list_channels()
 			.into_iter()
 			.filter(|c| {
-				!anchor_channels_config.trusted_peers_no_reserve.contains(&c.counterparty.node_id)
+				anchor_channels_config.requires_reserve_for(&c.counterparty.node_id)
 					&& c.channel_shutdown_state
 						.map_or(true, |s| s != ChannelShutdownState::ShutdownComplete)
 					&& c.channel_type
@@ -7,7 +7,7 @@
 
 //! Objects related to liquidity management.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::ops::Deref;
 use std::sync::{Arc, Mutex, RwLock};
 use std::time::Duration;
@@ -16,7 +16,7 @@ use bitcoin::hashes::{sha256, Hash};
 use bitcoin::secp256k1::{PublicKey, Secp256k1};
 use chrono::Utc;
 use lightning::events::HTLCHandlingFailureType;
-use lightning::ln::channelmanager::{InterceptId, MIN_FINAL_CLTV_EXPIRY_DELTA};
+use lightning::ln::channelmanager::InterceptId;
 use lightning::ln::msgs::SocketAddress;
 use lightning::ln::types::ChannelId;
 use lightning::routing::router::{RouteHint, RouteHintHop};
@@ -26,7 +26,7 @@ use lightning_liquidity::lsps0::ser::{LSPSDateTime, LSPSRequestId};
 use lightning_liquidity::lsps1::client::LSPS1ClientConfig as LdkLSPS1ClientConfig;
 use lightning_liquidity::lsps1::event::LSPS1ClientEvent;
 use lightning_liquidity::lsps1::msgs::{
-	LSPS1ChannelInfo, LSPS1Options, LSPS1OrderId, LSPS1OrderParams,
+	LSPS1ChannelInfo, LSPS1Options, LSPS1OrderId, LSPS1OrderParams, LSPS1PaymentState,
 };
 use lightning_liquidity::lsps2::client::LSPS2ClientConfig as LdkLSPS2ClientConfig;
 use lightning_liquidity::lsps2::event::{LSPS2ClientEvent, LSPS2ServiceEvent};
@@ -41,10 +41,15 @@ use tokio::sync::oneshot;
 use crate::builder::BuildError;
 use crate::chain::ChainSource;
 use crate::connection::ConnectionManager;
+use crate::event::{Event, EventQueue};
+use crate::io::utils::{
+	read_lsps2_service_pending_jit_channels, write_lsps2_service_pending_jit_channels,
+};
 use crate::logger::{log_debug, log_error, log_info, LdkLogger, Logger};
 use crate::runtime::Runtime;
 use crate::types::{
-	Broadcaster, ChannelManager, DynStore, KeysManager, LiquidityManager, PeerManager, Wallet,
+	Broadcaster, ChannelManager, DynStore, KeysManager, LiquidityManager, PeerManager,
+	UserChannelId, Wallet,
 };
 use crate::{total_anchor_channels_reserve_sats, Config, Error};
 
@@ -53,6 +58,9 @@ const LIQUIDITY_REQUEST_TIMEOUT_SECS: u64 = 5;
 const LSPS2_GETINFO_REQUEST_EXPIRY: Duration = Duration::from_secs(60 * 60 * 24);
 const LSPS2_CLIENT_TRUSTS_LSP_MODE: bool = true;
 const LSPS2_CHANNEL_CLTV_EXPIRY_DELTA: u32 = 72;
+// Opening fee parameters expiring within this window will not be reused or accepted, as the LSP
+// might consider them expired by the time it receives our buy request.
+const LSPS2_OPENING_FEE_PARAMS_EXPIRY_BUFFER: Duration = Duration::from_secs(60);
 
 struct LSPS1Client {
 	lsp_node_id: PublicKey,
@@ -64,6 +72,8 @@ struct LSPS1Client {
 	pending_create_order_requests: Mutex<HashMap<LSPSRequestId, oneshot::Sender<LSPS1OrderStatus>>>,
 	pending_check_order_status_requests:
 		Mutex<HashMap<LSPSRequestId, oneshot::Sender<LSPS1OrderStatus>>>,
+	// Orders we placed and that haven't been completed or failed yet.
+	pending_orders: Mutex<HashSet<LSPS1OrderId>>,
 }
 
 #[derive(Debug, Clone)]
@@ -80,6 +90,8 @@ struct LSPS2Client {
 	ldk_client_config: LdkLSPS2ClientConfig,
 	pending_fee_requests: Mutex<HashMap<LSPSRequestId, oneshot::Sender<LSPS2FeeResponse>>>,
 	pending_buy_requests: Mutex<HashMap<LSPSRequestId, oneshot::Sender<LSPS2BuyResponse>>>,
+	// The opening fee parameters most recently offered by the LSP, reused until they expire.
+	cached_opening_fee_params: Mutex<Vec<LSPS2OpeningFeeParams>>,
 }
 
 #[derive(Debug, Clone)]
@@ -92,6 +104,10 @@ pub(crate) struct LSPS2ClientConfig {
 struct LSPS2Service {
 	service_config: LSPS2ServiceConfig,
 	ldk_service_config: LdkLSPS2ServiceConfig,
+	// The JIT channels we're currently opening, i.e., the channel value in satoshis by
+	// `user_channel_id`. Persisted so we can still emit `Event::LiquidityChannelOpened` for them
+	// after a restart.
+	pending_jit_channels: Mutex<BTreeMap<u128, u64>>,
 }
 
 /// Represents the configuration of the LSPS2 service.
@@ -132,6 +148,26 @@ pub struct LSPS2ServiceConfig {
 	pub max_payment_size_msat: u64,
 }
 
+/// The limits on the fees we allow an LSP to charge for opening a [bLIP-52 / LSPS2] JIT channel.
+///
+/// If the LSP quotes a fee exceeding any of the set limits, we'll refuse to create the invoice and
+/// return an [`Error::LspFeeExceedsLimit`] instead.
+///
+/// [bLIP-52 / LSPS2]: https://github.com/lightning/blips/blob/master/blip-0052.md
+/// [`Error::LspFeeExceedsLimit`]: crate::NodeError::LspFeeExceedsLimit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct JitChannelFeeLimits {
+	/// The maximal total fee, in millisatoshis, we allow the LSP to withhold for opening the
+	/// channel.
+	///
+	/// For variable-amount invoices this is checked against the minimum fee the LSP charges, as
+	/// the total fee depends on the amount paid.
+	pub max_total_opening_fee_msat: Option<u64>,
+	/// The maximal proportional fee, in parts-per-million millisatoshis, we allow the LSP to
+	/// withhold for opening the channel.
+	pub max_proportional_ppm: Option<u64>,
+}
+
 pub(crate) struct LiquiditySourceBuilder<L: Deref>
 where
 	L::Target: LdkLogger,
@@ -145,6 +181,7 @@ where
 	chain_source: Arc<ChainSource>,
 	tx_broadcaster: Arc<Broadcaster>,
 	kv_store: Arc<DynStore>,
+	event_queue: Arc<EventQueue<L>>,
 	config: Arc<Config>,
 	logger: L,
 }
@@ -156,7 +193,7 @@ where
 	pub(crate) fn new(
 		wallet: Arc<Wallet>, channel_manager: Arc<ChannelManager>, keys_manager: Arc<KeysManager>,
 		chain_source: Arc<ChainSource>, tx_broadcaster: Arc<Broadcaster>, kv_store: Arc<DynStore>,
-		config: Arc<Config>, logger: L,
+		event_queue: Arc<EventQueue<L>>, config: Arc<Config>, logger: L,
 	) -> Self {
 		let lsps1_client = None;
 		let lsps2_client = None;
@@ -171,6 +208,7 @@ where
 			chain_source,
 			tx_broadcaster,
 			kv_store,
+			event_queue,
 			config,
 			logger,
 		}
@@ -184,6 +222,7 @@ where
 		let pending_opening_params_requests = Mutex::new(HashMap::new());
 		let pending_create_order_requests = Mutex::new(HashMap::new());
 		let pending_check_order_status_requests = Mutex::new(HashMap::new());
+		let pending_orders = Mutex::new(HashSet::new());
 		self.lsps1_client = Some(LSPS1Client {
 			lsp_node_id,
 			lsp_address,
@@ -192,6 +231,7 @@ where
 			pending_opening_params_requests,
 			pending_create_order_requests,
 			pending_check_order_status_requests,
+			pending_orders,
 		});
 		self
 	}
@@ -202,6 +242,7 @@ where
 		let ldk_client_config = LdkLSPS2ClientConfig {};
 		let pending_fee_requests = Mutex::new(HashMap::new());
 		let pending_buy_requests = Mutex::new(HashMap::new());
+		let cached_opening_fee_params = Mutex::new(Vec::new());
 		self.lsps2_client = Some(LSPS2Client {
 			lsp_node_id,
 			lsp_address,
@@ -209,6 +250,7 @@ where
 			ldk_client_config,
 			pending_fee_requests,
 			pending_buy_requests,
+			cached_opening_fee_params,
 		});
 		self
 	}
@@ -217,11 +259,24 @@ where
 		&mut self, promise_secret: [u8; 32], service_config: LSPS2ServiceConfig,
 	) -> &mut Self {
 		let ldk_service_config = LdkLSPS2ServiceConfig { promise_secret };
-		self.lsps2_service = Some(LSPS2Service { service_config, ldk_service_config });
+		let pending_jit_channels = Mutex::new(BTreeMap::new());
+		self.lsps2_service =
+			Some(LSPS2Service { service_config, ldk_service_config, pending_jit_channels });
 		self
 	}
 
-	pub(crate) async fn build(self) -> Result<LiquiditySource<L>, BuildError> {
+	pub(crate) async fn build(mut self) -> Result<LiquiditySource<L>, BuildError> {
+		if let Some(lsps2_service) = self.lsps2_service.as_mut() {
+			match read_lsps2_service_pending_jit_channels(Arc::clone(&self.kv_store), &*self.logger)
+			{
+				Ok(pending_jit_channels) => {
+					*lsps2_service.pending_jit_channels.get_mut().unwrap() = pending_jit_channels;
+				},
+				Err(e) if e.kind() == std::io::ErrorKind::NotFound => {},
+				Err(_) => return Err(BuildError::ReadFailed),
+			}
+		}
+
 		let liquidity_service_config = self.lsps2_service.as_ref().map(|s| {
 			let lsps2_service_config = Some(s.ldk_service_config.clone());
 			let lsps5_service_config = None;
@@ -263,6 +318,8 @@ where
 			peer_manager: RwLock::new(None),
 			keys_manager: self.keys_manager,
 			liquidity_manager,
+			event_queue: self.event_queue,
+			kv_store: self.kv_store,
 			config: self.config,
 			logger: self.logger,
 		})
@@ -281,6 +338,8 @@ where
 	peer_manager: RwLock<Option<Arc<PeerManager>>>,
 	keys_manager: Arc<KeysManager>,
 	liquidity_manager: Arc<LiquidityManager>,
+	event_queue: Arc<EventQueue<L>>,
+	kv_store: Arc<DynStore>,
 	config: Arc<Config>,
 	logger: L,
 }
@@ -626,10 +685,8 @@ where
 					return;
 				};
 
-				let service_config = if let Some(service_config) =
-					self.lsps2_service.as_ref().map(|s| s.service_config.clone())
-				{
-					service_config
+				let lsps2_service = if let Some(lsps2_service) = self.lsps2_service.as_ref() {
+					lsps2_service
 				} else {
 					log_error!(self.logger, "Failed to handle LSPS2ServiceEvent as LSPS2 liquidity service was not configured.",);
 					return;
@@ -660,7 +717,7 @@ where
 
 				// Fail if we have insufficient onchain funds available.
 				let over_provisioning_msat = (amt_to_forward_msat
-					* service_config.channel_over_provisioning_ppm as u64)
+					* lsps2_service.service_config.channel_over_provisioning_ppm as u64)
 					/ 1_000_000;
 				let channel_amount_sats = (amt_to_forward_msat + over_provisioning_msat) / 1000;
 				let cur_anchor_reserve_sats =
@@ -670,7 +727,7 @@ where
 				let required_funds_sats = channel_amount_sats
 					+ self.config.anchor_channels_config.as_ref().map_or(0, |c| {
 						if init_features.requires_anchors_zero_fee_htlc_tx()
-							&& !c.trusted_peers_no_reserve.contains(&their_network_key)
+							&& c.requires_reserve_for(&their_network_key)
 						{
 							c.per_channel_reserve_sats
 						} else {
@@ -713,7 +770,16 @@ where
 					None,
 					Some(config),
 				) {
-					Ok(_) => {},
+					Ok(_) => {
+						let mut locked_pending_jit_channels =
+							lsps2_service.pending_jit_channels.lock().unwrap();
+						locked_pending_jit_channels.insert(user_channel_id, channel_amount_sats);
+						let _ = write_lsps2_service_pending_jit_channels(
+							&locked_pending_jit_channels,
+							Arc::clone(&self.kv_store),
+							&*self.logger,
+						);
+					},
 					Err(e) => {
 						// TODO: We just silently fail here. Eventually we will need to remember
 						// the pending requests and regularly retry opening the channel until we
@@ -830,6 +896,57 @@ where
 					);
 				}
 			},
+			LiquidityEvent::LSPS1Client(LSPS1ClientEvent::SupportedOptionsRequestFailed {
+				request_id,
+				counterparty_node_id,
+				error,
+			}) => {
+				if let Some(lsps1_client) = self.lsps1_client.as_ref() {
+					log_error!(
+						self.logger,
+						"LSP {} failed to provide supported options: {:?}",
+						counterparty_node_id,
+						error
+					);
+					// Dropping the sender fails the pending request right away.
+					lsps1_client
+						.pending_opening_params_requests
+						.lock()
+						.unwrap()
+						.remove(&request_id);
+				} else {
+					log_error!(
+						self.logger,
+						"Received unexpected LSPS1Client::SupportedOptionsRequestFailed event!"
+					);
+				}
+			},
+			LiquidityEvent::LSPS1Client(LSPS1ClientEvent::OrderRequestFailed {
+				request_id,
+				counterparty_node_id,
+				error,
+			}) => {
+				if let Some(lsps1_client) = self.lsps1_client.as_ref() {
+					log_error!(
+						self.logger,
+						"LSP {} failed to handle order request: {:?}",
+						counterparty_node_id,
+						error
+					);
+					// Dropping the sender fails the pending request right away.
+					lsps1_client.pending_create_order_requests.lock().unwrap().remove(&request_id);
+					lsps1_client
+						.pending_check_order_status_requests
+						.lock()
+						.unwrap()
+						.remove(&request_id);
+				} else {
+					log_error!(
+						self.logger,
+						"Received unexpected LSPS1Client::OrderRequestFailed event!"
+					);
+				}
+			},
 			e => {
 				log_error!(self.logger, "Received unexpected liquidity event: {:?}", e);
 			},
@@ -963,9 +1080,60 @@ where
 			return Err(Error::LiquidityRequestFailed);
 		}
 
+		lsps1_client.pending_orders.lock().unwrap().insert(response.order_id.clone());
+
 		Ok(response)
 	}
 
+	pub(crate) fn has_pending_lsps1_orders(&self) -> bool {
+		self.lsps1_client.as_ref().map_or(false, |c| !c.pending_orders.lock().unwrap().is_empty())
+	}
+
+	/// Checks the status of all pending LSPS1 orders, emitting an [`Event::LiquidityRequestFailed`]
+	/// for any order that failed.
+	pub(crate) async fn lsps1_poll_pending_orders(&self) {
+		let lsps1_client = match self.lsps1_client.as_ref() {
+			Some(lsps1_client) => lsps1_client,
+			None => return,
+		};
+
+		let pending_orders =
+			lsps1_client.pending_orders.lock().unwrap().iter().cloned().collect::<Vec<_>>();
+		for order_id in pending_orders {
+			let order_status = match self.lsps1_check_order_status(order_id.clone()).await {
+				Ok(order_status) => order_status,
+				Err(e) => {
+					log_debug!(
+						self.logger,
+						"Failed to check status of LSPS1 order {:?}, will retry: {}",
+						order_id,
+						e
+					);
+					continue;
+				},
+			};
+
+			if order_status.channel_state.is_some() {
+				log_info!(self.logger, "LSP opened the channel for LSPS1 order {:?}", order_id);
+				lsps1_client.pending_orders.lock().unwrap().remove(&order_id);
+				continue;
+			}
+
+			if lsps1_order_failed(&order_status) {
+				log_error!(self.logger, "LSPS1 order {:?} failed", order_id);
+				lsps1_client.pending_orders.lock().unwrap().remove(&order_id);
+
+				let event = Event::LiquidityRequestFailed {
+					lsp_node_id: lsps1_client.lsp_node_id,
+					order_id: order_id.0,
+				};
+				if let Err(e) = self.event_queue.add_event(event) {
+					log_error!(self.logger, "Failed to push to event queue: {}", e);
+				}
+			}
+		}
+	}
+
 	pub(crate) async fn lsps1_check_order_status(
 		&self, order_id: LSPS1OrderId,
 	) -> Result<LSPS1OrderStatus, Error> {
@@ -1002,48 +1170,74 @@ where
 
 	pub(crate) async fn lsps2_receive_to_jit_channel(
 		&self, amount_msat: u64, description: &Bolt11InvoiceDescription, expiry_secs: u32,
-		max_total_lsp_fee_limit_msat: Option<u64>, payment_hash: Option<PaymentHash>,
+		fee_limits: JitChannelFeeLimits, payment_hash: Option<PaymentHash>,
+		opening_fee_params: Option<LSPS2OpeningFeeParams>,
 	) -> Result<(Bolt11Invoice, u64), Error> {
-		let fee_response = self.lsps2_request_opening_fee_params().await?;
-
-		let (min_total_fee_msat, min_opening_params) = fee_response
-			.opening_fee_params_menu
-			.into_iter()
-			.filter_map(|params| {
-				if amount_msat < params.min_payment_size_msat
-					|| amount_msat > params.max_payment_size_msat
-				{
-					log_debug!(self.logger,
-						"Skipping LSP-offered JIT parameters as the payment of {}msat doesn't meet LSP limits (min: {}msat, max: {}msat)",
-						amount_msat,
-						params.min_payment_size_msat,
-						params.max_payment_size_msat
-					);
-					None
-				} else {
-					compute_opening_fee(amount_msat, params.min_fee_msat, params.proportional as u64)
-						.map(|fee| (fee, params))
-				}
-			})
-			.min_by_key(|p| p.0)
-			.ok_or_else(|| {
-				log_error!(self.logger, "Failed to handle response from liquidity service",);
-				Error::LiquidityRequestFailed
-			})?;
-
-		if let Some(max_total_lsp_fee_limit_msat) = max_total_lsp_fee_limit_msat {
-			if min_total_fee_msat > max_total_lsp_fee_limit_msat {
+		let (min_total_fee_msat, min_opening_params) = if let Some(params) = opening_fee_params {
+			self.lsps2_check_opening_fee_params(&params)?;
+			if amount_msat < params.min_payment_size_msat
+				|| amount_msat > params.max_payment_size_msat
+			{
 				log_error!(self.logger,
-					"Failed to request inbound JIT channel as LSP's requested total opening fee of {}msat exceeds our fee limit of {}msat",
-					min_total_fee_msat, max_total_lsp_fee_limit_msat
+					"Failed to request inbound JIT channel as the payment of {}msat doesn't meet the limits of the given JIT parameters (min: {}msat, max: {}msat)",
+					amount_msat,
+					params.min_payment_size_msat,
+					params.max_payment_size_msat
 				);
-				return Err(Error::LiquidityFeeTooHigh);
+				return Err(Error::InvalidLiquidityParams);
 			}
-		}
+			let fee =
+				compute_opening_fee(amount_msat, params.min_fee_msat, params.proportional as u64)
+					.ok_or_else(|| {
+					log_error!(
+						self.logger,
+						"Failed to compute opening fee for the given JIT parameters"
+					);
+					Error::InvalidLiquidityParams
+				})?;
+			(fee, params)
+		} else {
+			let fee_response = self.lsps2_request_opening_fee_params().await?;
+
+			fee_response
+				.opening_fee_params_menu
+				.into_iter()
+				.filter_map(|params| {
+					if amount_msat < params.min_payment_size_msat
+						|| amount_msat > params.max_payment_size_msat
+					{
+						log_debug!(self.logger,
+							"Skipping LSP-offered JIT parameters as the payment of {}msat doesn't meet LSP limits (min: {}msat, max: {}msat)",
+							amount_msat,
+							params.min_payment_size_msat,
+							params.max_payment_size_msat
+						);
+						None
+					} else {
+						compute_opening_fee(
+							amount_msat,
+							params.min_fee_msat,
+							params.proportional as u64,
+						)
+						.map(|fee| (fee, params))
+					}
+				})
+				.min_by_key(|p| p.0)
+				.ok_or_else(|| {
+					log_error!(self.logger, "Failed to handle response from liquidity service",);
+					Error::LiquidityRequestFailed
+				})?
+		};
+
+		self.lsps2_check_fee_limits(
+			&fee_limits,
+			min_total_fee_msat,
+			min_opening_params.proportional as u64,
+		)?;
 
 		log_debug!(
 			self.logger,
-			"Choosing cheapest liquidity offer, will pay {}msat in total LSP fees",
+			"Choosing liquidity offer, will pay {}msat in total LSP fees",
 			min_total_fee_msat
 		);
 
@@ -1052,6 +1246,7 @@ where
 		let invoice = self.lsps2_create_jit_invoice(
 			buy_response,
 			Some(amount_msat),
+			None,
 			description,
 			expiry_secs,
 			payment_hash,
@@ -1063,43 +1258,72 @@ where
 
 	pub(crate) async fn lsps2_receive_variable_amount_to_jit_channel(
 		&self, description: &Bolt11InvoiceDescription, expiry_secs: u32,
-		max_proportional_lsp_fee_limit_ppm_msat: Option<u64>, payment_hash: Option<PaymentHash>,
+		min_amount_msat: Option<u64>, fee_limits: JitChannelFeeLimits,
+		payment_hash: Option<PaymentHash>, opening_fee_params: Option<LSPS2OpeningFeeParams>,
 	) -> Result<(Bolt11Invoice, u64), Error> {
-		let fee_response = self.lsps2_request_opening_fee_params().await?;
-
-		let (min_prop_fee_ppm_msat, min_opening_params) = fee_response
-			.opening_fee_params_menu
-			.into_iter()
-			.map(|params| (params.proportional as u64, params))
-			.min_by_key(|p| p.0)
-			.ok_or_else(|| {
-				log_error!(self.logger, "Failed to handle response from liquidity service",);
-				Error::LiquidityRequestFailed
-			})?;
-
-		if let Some(max_proportional_lsp_fee_limit_ppm_msat) =
-			max_proportional_lsp_fee_limit_ppm_msat
-		{
-			if min_prop_fee_ppm_msat > max_proportional_lsp_fee_limit_ppm_msat {
-				log_error!(self.logger,
-					"Failed to request inbound JIT channel as LSP's requested proportional opening fee of {} ppm msat exceeds our fee limit of {} ppm msat",
-					min_prop_fee_ppm_msat,
-					max_proportional_lsp_fee_limit_ppm_msat
-				);
-				return Err(Error::LiquidityFeeTooHigh);
+		let (min_prop_fee_ppm_msat, min_opening_params) = if let Some(params) = opening_fee_params {
+			self.lsps2_check_opening_fee_params(&params)?;
+			if let Some(min_amount_msat) = min_amount_msat {
+				if min_amount_msat > params.max_payment_size_msat {
+					log_error!(self.logger,
+						"Failed to request inbound JIT channel as the minimum amount of {}msat exceeds the limit of {}msat of the given JIT parameters",
+						min_amount_msat,
+						params.max_payment_size_msat
+					);
+					return Err(Error::InvalidLiquidityParams);
+				}
 			}
-		}
+			(params.proportional as u64, params)
+		} else {
+			let fee_response = self.lsps2_request_opening_fee_params().await?;
+
+			fee_response
+				.opening_fee_params_menu
+				.into_iter()
+				.filter(|params| {
+					if let Some(min_amount_msat) = min_amount_msat {
+						if min_amount_msat > params.max_payment_size_msat {
+							log_debug!(self.logger,
+								"Skipping LSP-offered JIT parameters as the minimum amount of {}msat exceeds the LSP limit of {}msat",
+								min_amount_msat,
+								params.max_payment_size_msat
+							);
+							return false;
+						}
+					}
+					true
+				})
+				.map(|params| (params.proportional as u64, params))
+				.min_by_key(|p| p.0)
+				.ok_or_else(|| {
+					log_error!(self.logger, "Failed to handle response from liquidity service",);
+					Error::LiquidityRequestFailed
+				})?
+		};
+
+		// As the amount isn't known yet, we check the total fee limit against the minimum fee the
+		// LSP will withhold.
+		self.lsps2_check_fee_limits(
+			&fee_limits,
+			min_opening_params.min_fee_msat,
+			min_prop_fee_ppm_msat,
+		)?;
 
 		log_debug!(
 			self.logger,
-			"Choosing cheapest liquidity offer, will pay {}ppm msat in proportional LSP fees",
+			"Choosing liquidity offer, will pay {}ppm msat in proportional LSP fees",
 			min_prop_fee_ppm_msat
 		);
 
+		// If we were asked for a minimum, the payment needs to satisfy both ours and the LSP's.
+		let htlc_minimum_msat = min_amount_msat
+			.map(|min_amount_msat| min_amount_msat.max(min_opening_params.min_payment_size_msat));
+
 		let buy_response = self.lsps2_send_buy_request(None, min_opening_params).await?;
 		let invoice = self.lsps2_create_jit_invoice(
 			buy_response,
 			None,
+			htlc_minimum_msat,
 			description,
 			expiry_secs,
 			payment_hash,
@@ -1109,9 +1333,20 @@ where
 		Ok((invoice, min_prop_fee_ppm_msat))
 	}
 
-	async fn lsps2_request_opening_fee_params(&self) -> Result<LSPS2FeeResponse, Error> {
+	pub(crate) async fn lsps2_request_opening_fee_params(&self) -> Result<LSPS2FeeResponse, Error> {
 		let lsps2_client = self.lsps2_client.as_ref().ok_or(Error::LiquiditySourceUnavailable)?;
 
+		{
+			let mut cached_params_lock = lsps2_client.cached_opening_fee_params.lock().unwrap();
+			cached_params_lock.retain(|params| !opening_fee_params_expire_soon(params));
+			if !cached_params_lock.is_empty() {
+				log_debug!(self.logger, "Reusing cached LSPS2 opening fee parameters");
+				return Ok(LSPS2FeeResponse {
+					opening_fee_params_menu: cached_params_lock.clone(),
+				});
+			}
+		}
+
 		let client_handler = self.liquidity_manager.lsps2_client_handler().ok_or_else(|| {
 			log_error!(self.logger, "Liquidity client was not configured.",);
 			Error::LiquiditySourceUnavailable
@@ -1125,7 +1360,7 @@ where
 			pending_fee_requests_lock.insert(request_id, fee_request_sender);
 		}
 
-		tokio::time::timeout(
+		let fee_response = tokio::time::timeout(
 			Duration::from_secs(LIQUIDITY_REQUEST_TIMEOUT_SECS),
 			fee_request_receiver,
 		)
@@ -1137,7 +1372,61 @@ where
 		.map_err(|e| {
 			log_error!(self.logger, "Failed to handle response from liquidity service: {}", e);
 			Error::LiquidityRequestFailed
-		})
+		})?;
+
+		*lsps2_client.cached_opening_fee_params.lock().unwrap() = fee_response
+			.opening_fee_params_menu
+			.iter()
+			.filter(|params| !opening_fee_params_expire_soon(params))
+			.cloned()
+			.collect();
+
+		Ok(fee_response)
+	}
+
+	fn lsps2_check_fee_limits(
+		&self, fee_limits: &JitChannelFeeLimits, total_fee_msat: u64, proportional_ppm: u64,
+	) -> Result<(), Error> {
+		if let Some(max_total_opening_fee_msat) = fee_limits.max_total_opening_fee_msat {
+			if total_fee_msat > max_total_opening_fee_msat {
+				log_error!(self.logger,
+					"Failed to request inbound JIT channel as LSP's requested total opening fee of {}msat exceeds our fee limit of {}msat",
+					total_fee_msat, max_total_opening_fee_msat
+				);
+				return Err(Error::LspFeeExceedsLimit {
+					quoted: total_fee_msat,
+					limit: max_total_opening_fee_msat,
+				});
+			}
+		}
+
+		if let Some(max_proportional_ppm) = fee_limits.max_proportional_ppm {
+			if proportional_ppm > max_proportional_ppm {
+				log_error!(self.logger,
+					"Failed to request inbound JIT channel as LSP's requested proportional opening fee of {} ppm msat exceeds our fee limit of {} ppm msat",
+					proportional_ppm,
+					max_proportional_ppm
+				);
+				return Err(Error::LspFeeExceedsLimit {
+					quoted: proportional_ppm,
+					limit: max_proportional_ppm,
+				});
+			}
+		}
+
+		Ok(())
+	}
+
+	fn lsps2_check_opening_fee_params(&self, params: &LSPS2OpeningFeeParams) -> Result<(), Error> {
+		if opening_fee_params_expire_soon(params) {
+			log_error!(
+				self.logger,
+				"Failed to request inbound JIT channel as the given JIT parameters expire at {}",
+				params.valid_until
+			);
+			return Err(Error::InvalidLiquidityParams);
+		}
+		Ok(())
 	}
 
 	async fn lsps2_send_buy_request(
@@ -1185,13 +1474,13 @@ where
 
 	fn lsps2_create_jit_invoice(
 		&self, buy_response: LSPS2BuyResponse, amount_msat: Option<u64>,
-		description: &Bolt11InvoiceDescription, expiry_secs: u32,
+		htlc_minimum_msat: Option<u64>, description: &Bolt11InvoiceDescription, expiry_secs: u32,
 		payment_hash: Option<PaymentHash>,
 	) -> Result<Bolt11Invoice, Error> {
 		let lsps2_client = self.lsps2_client.as_ref().ok_or(Error::LiquiditySourceUnavailable)?;
 
 		// LSPS2 requires min_final_cltv_expiry_delta to be at least 2 more than usual.
-		let min_final_cltv_expiry_delta = MIN_FINAL_CLTV_EXPIRY_DELTA + 2;
+		let min_final_cltv_expiry_delta = self.config.min_final_cltv_expiry_delta.saturating_add(2);
 		let (payment_hash, payment_secret) = match payment_hash {
 			Some(payment_hash) => {
 				let payment_secret = self
@@ -1222,7 +1511,7 @@ where
 			short_channel_id: buy_response.intercept_scid,
 			fees: RoutingFees { base_msat: 0, proportional_millionths: 0 },
 			cltv_expiry_delta: buy_response.cltv_expiry_delta as u16,
-			htlc_minimum_msat: None,
+			htlc_minimum_msat,
 			htlc_maximum_msat: None,
 		}]);
 
@@ -1271,6 +1560,46 @@ where
 				);
 			}
 		}
+
+		self.forget_pending_jit_channel(user_channel_id);
+	}
+
+	/// Returns the [`Event::LiquidityChannelOpened`] to emit if the channel that just became ready
+	/// is a JIT channel we opened to one of our LSPS2 clients.
+	pub(crate) fn jit_channel_opened_event(
+		&self, user_channel_id: u128, channel_id: &ChannelId, counterparty_node_id: &PublicKey,
+	) -> Option<Event> {
+		let lsps2_service = self.lsps2_service.as_ref()?;
+		let amount_sat =
+			*lsps2_service.pending_jit_channels.lock().unwrap().get(&user_channel_id)?;
+		log_info!(
+			self.logger,
+			"Opened JIT channel {} with value {}sats to LSPS2 client {}",
+			channel_id,
+			amount_sat,
+			counterparty_node_id
+		);
+		Some(Event::LiquidityChannelOpened {
+			channel_id: *channel_id,
+			user_channel_id: UserChannelId(user_channel_id),
+			client_node_id: *counterparty_node_id,
+			amount_sat,
+		})
+	}
+
+	/// Stops tracking the given JIT channel, e.g., as it became ready or was closed.
+	pub(crate) fn forget_pending_jit_channel(&self, user_channel_id: u128) {
+		if let Some(lsps2_service) = self.lsps2_service.as_ref() {
+			let mut locked_pending_jit_channels =
+				lsps2_service.pending_jit_channels.lock().unwrap();
+			if locked_pending_jit_channels.remove(&user_channel_id).is_some() {
+				let _ = write_lsps2_service_pending_jit_channels(
+					&locked_pending_jit_channels,
+					Arc::clone(&self.kv_store),
+					&*self.logger,
+				);
+			}
+		}
 	}
 
 	pub(crate) async fn handle_htlc_intercepted(
@@ -1345,6 +1674,24 @@ pub struct LSPS1OrderStatus {
 	pub channel_state: Option<LSPS1ChannelInfo>,
 }
 
+// Returns whether the given order failed, i.e., an (expected) payment was refunded or all payment
+// options expired before we paid.
+fn lsps1_order_failed(order_status: &LSPS1OrderStatus) -> bool {
+	let payment_states = [
+		order_status.payment_options.bolt11.as_ref().map(|p| (&p.state, &p.expires_at)),
+		order_status.payment_options.onchain.as_ref().map(|p| (&p.state, &p.expires_at)),
+	];
+	let mut payment_states = payment_states.iter().flatten().peekable();
+	if payment_states.peek().is_none() {
+		return false;
+	}
+
+	let refunded = payment_states.clone().any(|(s, _)| **s == LSPS1PaymentState::Refunded);
+	let expired = payment_states
+		.all(|(s, expires_at)| **s == LSPS1PaymentState::ExpectPayment && expires_at.is_past());
+	refunded || expired
+}
+
 #[cfg(not(feature = "uniffi"))]
 type LSPS1PaymentInfo = lightning_liquidity::lsps1::msgs::LSPS1PaymentInfo;
 
@@ -1353,7 +1700,11 @@ type LSPS1PaymentInfo = crate::ffi::LSPS1PaymentInfo;
 
 #[derive(Debug, Clone)]
 pub(crate) struct LSPS2FeeResponse {
-	opening_fee_params_menu: Vec<LSPS2OpeningFeeParams>,
+	pub(crate) opening_fee_params_menu: Vec<LSPS2OpeningFeeParams>,
+}
+
+fn opening_fee_params_expire_soon(params: &LSPS2OpeningFeeParams) -> bool {
+	params.valid_until.0 <= Utc::now() + LSPS2_OPENING_FEE_PARAMS_EXPIRY_BUFFER
 }
 
 #[derive(Debug, Clone)]
@@ -1394,7 +1745,10 @@ impl LSPS1Liquidity {
 	/// Connects to the configured LSP and places an order for an inbound channel.
 	///
 	/// The channel will be opened after one of the returned payment options has successfully been
-	/// paid.
+	/// paid, at which point an [`Event::ChannelReady`] will be emitted.
+	///
+	/// While the node is running, we'll regularly check the order's status and emit an
+	/// [`Event::LiquidityRequestFailed`] if the order failed, e.g., as it expired before being paid.
 	pub fn request_channel(
 		&self, lsp_balance_sat: u64, client_balance_sat: u64, channel_expiry_blocks: u32,
 		announce_channel: bool,
@@ -1460,3 +1814,67 @@ impl LSPS1Liquidity {
 		Ok(response)
 	}
 }
+
+/// A liquidity handler allowing to inspect the fees charged for [bLIP-52 / LSPS2] JIT channels.
+///
+/// Should be retrieved by calling [`Node::lsps2_liquidity`].
+///
+/// To open JIT channels, please refer to [`Bolt11Payment::receive_via_jit_channel`].
+///
+/// [bLIP-52 / LSPS2]: https://github.com/lightning/blips/blob/master/blip-0052.md
+/// [`Node::lsps2_liquidity`]: crate::Node::lsps2_liquidity
+/// [`Bolt11Payment::receive_via_jit_channel`]: crate::payment::Bolt11Payment::receive_via_jit_channel
+#[derive(Clone)]
+pub struct LSPS2Liquidity {
+	runtime: Arc<Runtime>,
+	connection_manager: Arc<ConnectionManager<Arc<Logger>>>,
+	liquidity_source: Option<Arc<LiquiditySource<Arc<Logger>>>>,
+	logger: Arc<Logger>,
+}
+
+impl LSPS2Liquidity {
+	pub(crate) fn new(
+		runtime: Arc<Runtime>, connection_manager: Arc<ConnectionManager<Arc<Logger>>>,
+		liquidity_source: Option<Arc<LiquiditySource<Arc<Logger>>>>, logger: Arc<Logger>,
+	) -> Self {
+		Self { runtime, connection_manager, liquidity_source, logger }
+	}
+
+	/// Connects to the configured LSP and retrieves the menu of opening fee parameters it offers
+	/// for JIT channels.
+	///
+	/// The returned parameters may be displayed to the user and one of them can then be passed to
+	/// [`Bolt11Payment::receive_via_jit_channel_with_opening_params`] or
+	/// [`Bolt11Payment::receive_variable_amount_via_jit_channel_with_opening_params`] before its
+	/// `valid_until` time passed.
+	///
+	/// The parameters are cached and will be reused until they're about to expire.
+	///
+	/// [`Bolt11Payment::receive_via_jit_channel_with_opening_params`]: crate::payment::Bolt11Payment::receive_via_jit_channel_with_opening_params
+	/// [`Bolt11Payment::receive_variable_amount_via_jit_channel_with_opening_params`]: crate::payment::Bolt11Payment::receive_variable_amount_via_jit_channel_with_opening_params
+	pub fn get_opening_params(&self) -> Result<Vec<LSPS2OpeningFeeParams>, Error> {
+		let liquidity_source =
+			self.liquidity_source.as_ref().ok_or(Error::LiquiditySourceUnavailable)?;
+
+		let (lsp_node_id, lsp_address) =
+			liquidity_source.get_lsps2_lsp_details().ok_or(Error::LiquiditySourceUnavailable)?;
+
+		let con_node_id = lsp_node_id;
+		let con_addr = lsp_address.clone();
+		let con_cm = Arc::clone(&self.connection_manager);
+
+		// We need to use our main runtime here as a local runtime might not be around to poll
+		// connection futures going forward.
+		self.runtime.block_on(async move {
+			con_cm.connect_peer_if_necessary(con_node_id, con_addr).await
+		})?;
+
+		log_info!(self.logger, "Connected to LSP {}@{}. ", lsp_node_id, lsp_address);
+
+		let liquidity_source = Arc::clone(&liquidity_source);
+		let response = self
+			.runtime
+			.block_on(async move { liquidity_source.lsps2_request_opening_fee_params().await })?;
+		Ok(response.opening_fee_params_menu)
+	}
+}
@@ -9,16 +9,23 @@
 
 #[cfg(not(feature = "uniffi"))]
 use core::fmt;
+use std::collections::HashMap;
 use std::fs;
 use std::io::Write;
 use std::path::Path;
 use std::sync::Arc;
 
-use chrono::Utc;
+use bitcoin::secp256k1::PublicKey;
+use chrono::{SecondsFormat, Utc};
+use lightning::ln::types::ChannelId;
 pub use lightning::util::logger::Level as LogLevel;
 pub(crate) use lightning::util::logger::{Logger as LdkLogger, Record as LdkRecord};
-pub(crate) use lightning::{log_bytes, log_debug, log_error, log_info, log_trace};
+pub(crate) use lightning::{log_bytes, log_debug, log_error, log_info, log_trace, log_warn};
+use lightning_types::payment::PaymentHash;
 use log::{Level as LogFacadeLevel, Record as LogFacadeRecord};
+use serde_json::{Map, Value};
+
+use crate::hex_utils;
 
 /// A unit of logging output with metadata to enable filtering `module_path`,
 /// `file`, and `line` to inform on log's source.
@@ -30,6 +37,8 @@ pub struct LogRecord<'a> {
 	pub args: fmt::Arguments<'a>,
 	/// The module path of the message.
 	pub module_path: &'a str,
+	/// The source file containing the message.
+	pub file: &'a str,
 	/// The line containing the message.
 	pub line: u32,
 }
@@ -48,6 +57,8 @@ pub struct LogRecord {
 	pub args: String,
 	/// The module path of the message.
 	pub module_path: String,
+	/// The source file containing the message.
+	pub file: String,
 	/// The line containing the message.
 	pub line: u32,
 }
@@ -59,6 +70,7 @@ impl<'a> From<LdkRecord<'a>> for LogRecord {
 			level: record.level,
 			args: record.args.to_string(),
 			module_path: record.module_path.to_string(),
+			file: record.file.to_string(),
 			line: record.line,
 		}
 	}
@@ -71,6 +83,7 @@ impl<'a> From<LdkRecord<'a>> for LogRecord<'a> {
 			level: record.level,
 			args: record.args,
 			module_path: record.module_path,
+			file: record.file,
 			line: record.line,
 		}
 	}
@@ -80,7 +93,10 @@ impl<'a> From<LdkRecord<'a>> for LogRecord<'a> {
 ///
 /// Implementors of this trait are responsible for handling log messages,
 /// which may involve formatting, filtering, and forwarding them to specific
-/// outputs.
+/// outputs, e.g., `tracing`, OSLog, Android's logcat, or syslog.
+///
+/// Note that [`LogWriter::log`] may be called concurrently from multiple threads, so
+/// implementations need to take care of any required synchronization themselves.
 #[cfg(not(feature = "uniffi"))]
 pub trait LogWriter: Send + Sync {
 	/// Log the record.
@@ -91,7 +107,11 @@ pub trait LogWriter: Send + Sync {
 ///
 /// Implementors of this trait are responsible for handling log messages,
 /// which may involve formatting, filtering, and forwarding them to specific
-/// outputs.
+/// outputs, e.g., `tracing`, OSLog, Android's logcat, or syslog.
+///
+/// Note that [`LogWriter::log`] may be called concurrently from multiple threads, so
+/// implementations need to take care of any required synchronization themselves.
+///
 /// This version is used when the `uniffi` feature is enabled.
 /// It is similar to the non-`uniffi` version, but it omits the lifetime parameter
 /// for the `LogRecord`, as the Uniffi-exposed interface cannot handle lifetimes.
@@ -101,10 +121,43 @@ pub trait LogWriter: Send + Sync {
 	fn log(&self, record: LogRecord);
 }
 
+/// Filters log records based on their level, allowing to override the maximum log level for
+/// records whose target, i.e., module path, matches a given prefix.
+pub(crate) struct LogLevelFilter {
+	max_log_level: LogLevel,
+	module_log_levels: HashMap<String, LogLevel>,
+}
+
+impl LogLevelFilter {
+	pub fn new(max_log_level: LogLevel, module_log_levels: HashMap<String, LogLevel>) -> Self {
+		Self { max_log_level, module_log_levels }
+	}
+
+	/// Returns whether a record with the given level and module path should be logged.
+	///
+	/// If multiple module prefixes match, the longest, i.e., most specific, one takes precedence.
+	/// If none match, the global maximum log level applies.
+	pub fn is_enabled(&self, level: LogLevel, module_path: &str) -> bool {
+		let max_log_level = self
+			.module_log_levels
+			.iter()
+			.filter(|(prefix, _)| {
+				module_path
+					.strip_prefix(prefix.as_str())
+					.map_or(false, |rest| rest.is_empty() || rest.starts_with("::"))
+			})
+			.max_by_key(|(prefix, _)| prefix.len())
+			.map_or(self.max_log_level, |(_, level)| *level);
+		level >= max_log_level
+	}
+}
+
 /// Defines a writer for [`Logger`].
 pub(crate) enum Writer {
 	/// Writes logs to the file system.
-	FileWriter { file_path: String, max_log_level: LogLevel },
+	FileWriter { file_path: String, filter: LogLevelFilter },
+	/// Writes logs to the file system as JSON objects, one per line.
+	JsonFileWriter { file_path: String, filter: LogLevelFilter },
 	/// Forwards logs to the `log` facade.
 	LogFacadeWriter,
 	/// Forwards logs to a custom writer.
@@ -114,8 +167,12 @@ pub(crate) enum Writer {
 impl LogWriter for Writer {
 	fn log(&self, record: LogRecord) {
 		match self {
-			Writer::FileWriter { file_path, max_log_level } => {
-				if record.level < *max_log_level {
+			Writer::FileWriter { file_path, filter } => {
+				#[cfg(not(feature = "uniffi"))]
+				let module_path = record.module_path;
+				#[cfg(feature = "uniffi")]
+				let module_path = &record.module_path;
+				if !filter.is_enabled(record.level, module_path) {
 					return;
 				}
 
@@ -123,18 +180,32 @@ impl LogWriter for Writer {
 					"{} {:<5} [{}:{}] {}\n",
 					Utc::now().format("%Y-%m-%d %H:%M:%S%.3f"),
 					record.level.to_string(),
-					record.module_path,
+					module_path,
 					record.line,
 					record.args
 				);
 
-				fs::OpenOptions::new()
-					.create(true)
-					.append(true)
-					.open(file_path)
-					.expect("Failed to open log file")
-					.write_all(log.as_bytes())
-					.expect("Failed to write to log file")
+				append_to_log_file(file_path, &log)
+			},
+			Writer::JsonFileWriter { file_path, filter } => {
+				#[cfg(not(feature = "uniffi"))]
+				let module_path = record.module_path;
+				#[cfg(feature = "uniffi")]
+				let module_path = &record.module_path;
+				if !filter.is_enabled(record.level, module_path) {
+					return;
+				}
+
+				let log = format_json_log_line(
+					record.level,
+					module_path,
+					record.line,
+					&record.args.to_string(),
+					None,
+					None,
+					None,
+				);
+				append_to_log_file(file_path, &log)
 			},
 			Writer::LogFacadeWriter => {
 				let mut builder = LogFacadeRecord::builder();
@@ -152,6 +223,7 @@ impl LogWriter for Writer {
 					&builder
 						.target(record.module_path)
 						.module_path(Some(record.module_path))
+						.file(Some(record.file))
 						.line(Some(record.line))
 						.args(format_args!("{}", record.args))
 						.build(),
@@ -161,6 +233,7 @@ impl LogWriter for Writer {
 					&builder
 						.target(&record.module_path)
 						.module_path(Some(&record.module_path))
+						.file(Some(&record.file))
 						.line(Some(record.line))
 						.args(format_args!("{}", record.args))
 						.build(),
@@ -171,6 +244,63 @@ impl LogWriter for Writer {
 	}
 }
 
+fn prepare_log_file(file_path: &str) -> Result<(), ()> {
+	if let Some(parent_dir) = Path::new(file_path).parent() {
+		fs::create_dir_all(parent_dir)
+			.map_err(|e| eprintln!("ERROR: Failed to create log parent directory: {}", e))?;
+
+		// make sure the file exists.
+		fs::OpenOptions::new()
+			.create(true)
+			.append(true)
+			.open(file_path)
+			.map_err(|e| eprintln!("ERROR: Failed to open log file: {}", e))?;
+	}
+	Ok(())
+}
+
+fn append_to_log_file(file_path: &str, log: &str) {
+	fs::OpenOptions::new()
+		.create(true)
+		.append(true)
+		.open(file_path)
+		.expect("Failed to open log file")
+		.write_all(log.as_bytes())
+		.expect("Failed to write to log file")
+}
+
+/// Formats a log line as a JSON object, only including the structured fields that are present.
+fn format_json_log_line(
+	level: LogLevel, module_path: &str, line: u32, message: &str, peer_id: Option<PublicKey>,
+	channel_id: Option<ChannelId>, payment_hash: Option<PaymentHash>,
+) -> String {
+	let mut object = Map::new();
+	object.insert(
+		"timestamp".to_string(),
+		Value::String(Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)),
+	);
+	object.insert("level".to_string(), Value::String(level.to_string()));
+	object.insert("target".to_string(), Value::String(module_path.to_string()));
+	object.insert("line".to_string(), Value::from(line));
+	object.insert("message".to_string(), Value::String(message.to_string()));
+	if let Some(peer_id) = peer_id {
+		object.insert("peer_id".to_string(), Value::String(peer_id.to_string()));
+	}
+	if let Some(channel_id) = channel_id {
+		object.insert("channel_id".to_string(), Value::String(channel_id.to_string()));
+	}
+	if let Some(payment_hash) = payment_hash {
+		object.insert(
+			"payment_hash".to_string(),
+			Value::String(hex_utils::to_string(&payment_hash.0)),
+		);
+	}
+
+	let mut log = Value::Object(object).to_string();
+	log.push('\n');
+	log
+}
+
 pub(crate) struct Logger {
 	/// Specifies the logger's writer.
 	writer: Writer,
@@ -178,21 +308,17 @@ pub(crate) struct Logger {
 
 impl Logger {
 	/// Creates a new logger with a filesystem writer. The parameters to this function
-	/// are the path to the log file, and the log level.
-	pub fn new_fs_writer(file_path: String, max_log_level: LogLevel) -> Result<Self, ()> {
-		if let Some(parent_dir) = Path::new(&file_path).parent() {
-			fs::create_dir_all(parent_dir)
-				.map_err(|e| eprintln!("ERROR: Failed to create log parent directory: {}", e))?;
-
-			// make sure the file exists.
-			fs::OpenOptions::new()
-				.create(true)
-				.append(true)
-				.open(&file_path)
-				.map_err(|e| eprintln!("ERROR: Failed to open log file: {}", e))?;
-		}
+	/// are the path to the log file, and the log level filter.
+	pub fn new_fs_writer(file_path: String, filter: LogLevelFilter) -> Result<Self, ()> {
+		prepare_log_file(&file_path)?;
+		Ok(Self { writer: Writer::FileWriter { file_path, filter } })
+	}
 
-		Ok(Self { writer: Writer::FileWriter { file_path, max_log_level } })
+	/// Creates a new logger with a filesystem writer emitting one JSON object per line. The
+	/// parameters to this function are the path to the log file, and the log level filter.
+	pub fn new_json_fs_writer(file_path: String, filter: LogLevelFilter) -> Result<Self, ()> {
+		prepare_log_file(&file_path)?;
+		Ok(Self { writer: Writer::JsonFileWriter { file_path, filter } })
 	}
 
 	pub fn new_log_facade() -> Self {
@@ -207,12 +333,29 @@ impl Logger {
 impl LdkLogger for Logger {
 	fn log(&self, record: LdkRecord) {
 		match &self.writer {
-			Writer::FileWriter { file_path: _, max_log_level } => {
-				if record.level < *max_log_level {
+			Writer::FileWriter { file_path: _, filter } => {
+				if !filter.is_enabled(record.level, record.module_path) {
 					return;
 				}
 				self.writer.log(record.into());
 			},
+			Writer::JsonFileWriter { file_path, filter } => {
+				if !filter.is_enabled(record.level, record.module_path) {
+					return;
+				}
+
+				// We write JSON logs directly from LDK's record to retain its structured fields.
+				let log = format_json_log_line(
+					record.level,
+					record.module_path,
+					record.line,
+					&record.args.to_string(),
+					record.peer_id,
+					record.channel_id,
+					record.payment_hash,
+				);
+				append_to_log_file(file_path, &log)
+			},
 			Writer::LogFacadeWriter => {
 				self.writer.log(record.into());
 			},
@@ -222,3 +365,32 @@ impl LdkLogger for Logger {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn log_level_filter_applies_module_overrides() {
+		let mut module_log_levels = HashMap::new();
+		module_log_levels.insert("ldk_node::liquidity".to_string(), LogLevel::Trace);
+		module_log_levels.insert("lightning::routing".to_string(), LogLevel::Trace);
+		module_log_levels.insert("lightning::routing::gossip".to_string(), LogLevel::Error);
+		let filter = LogLevelFilter::new(LogLevel::Info, module_log_levels);
+
+		// Without a matching override, the global level applies.
+		assert!(filter.is_enabled(LogLevel::Info, "ldk_node::builder"));
+		assert!(!filter.is_enabled(LogLevel::Debug, "ldk_node::builder"));
+
+		// Overrides apply to the module itself as well as its submodules.
+		assert!(filter.is_enabled(LogLevel::Trace, "ldk_node::liquidity"));
+		assert!(filter.is_enabled(LogLevel::Trace, "lightning::routing::router"));
+
+		// Overrides only match at module boundaries.
+		assert!(!filter.is_enabled(LogLevel::Trace, "ldk_node::liquidity_extra"));
+
+		// The most specific override takes precedence.
+		assert!(!filter.is_enabled(LogLevel::Warn, "lightning::routing::gossip"));
+		assert!(filter.is_enabled(LogLevel::Error, "lightning::routing::gossip"));
+	}
+}
@@ -5,18 +5,54 @@
 // http://opensource.org/licenses/MIT>, at your option. You may not use this file except in
 // accordance with one or both of these licenses.
 
+use std::collections::HashMap;
 use std::ops::Deref;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Instant;
 
-use bitcoin::secp256k1::PublicKey;
+use bitcoin::constants::ChainHash;
+use bitcoin::secp256k1::{self, PublicKey, Secp256k1};
+use lightning::blinded_path::message::{BlindedMessagePath, MessageContext, MessageForwardNode};
+use lightning::io::{self, Read};
+use lightning::ln::msgs::{
+	AcceptChannel, AcceptChannelV2, AnnouncementSignatures, BaseMessageHandler,
+	ChannelAnnouncement, ChannelMessageHandler, ChannelReady, ChannelReestablish, ChannelUpdate,
+	ClosingSigned, CommitmentSigned, ErrorMessage, FundingCreated, FundingSigned, Init,
+	LightningError, MessageSendEvent, NodeAnnouncement, OpenChannel, OpenChannelV2, PeerStorage,
+	PeerStorageRetrieval, QueryChannelRange, QueryShortChannelIds, ReplyChannelRange,
+	ReplyShortChannelIdsEnd, RevokeAndACK, RoutingMessageHandler, Shutdown, SpliceAck, SpliceInit,
+	SpliceLocked, Stfu, TxAbort, TxAckRbf, TxAddInput, TxAddOutput, TxComplete, TxInitRbf,
+	TxRemoveInput, TxRemoveOutput, TxSignatures, UpdateAddHTLC, UpdateFailHTLC,
+	UpdateFailMalformedHTLC, UpdateFee, UpdateFulfillHTLC,
+};
 use lightning::ln::peer_handler::CustomMessageHandler;
+use lightning::ln::types::ChannelId;
 use lightning::ln::wire::CustomMessageReader;
+use lightning::onion_message::async_payments::AsyncPaymentsMessage;
+use lightning::onion_message::dns_resolution::DNSResolverMessage;
+use lightning::onion_message::messenger::{
+	CustomOnionMessageHandler, Destination, MessageRouter, MessageSendInstructions,
+	OnionMessagePath, Responder, ResponseInstruction,
+};
+use lightning::onion_message::offers::OffersMessage;
+use lightning::onion_message::packet::OnionMessageContents;
+use lightning::routing::gossip::NodeId;
+use lightning::sign::ReceiveAuthKey;
 use lightning::util::logger::Logger;
-use lightning::util::ser::LengthLimitedRead;
+use lightning::util::ser::{LengthLimitedRead, Writeable, Writer};
 use lightning_liquidity::lsps0::ser::RawLSPSMessage;
 use lightning_types::features::{InitFeatures, NodeFeatures};
 
+use crate::config::{
+	FeatureConfig, PeerConnectionPolicy, CUSTOM_ONION_MESSAGE_RATE_LIMIT_WINDOW,
+	MAX_CUSTOM_ONION_MESSAGES_PER_TLV_TYPE, MAX_CUSTOM_ONION_MESSAGES_PER_WINDOW,
+	MIN_CUSTOM_ONION_MESSAGE_TLV_TYPE,
+};
+use crate::event::{Event, EventQueue};
 use crate::liquidity::LiquiditySource;
+use crate::logger::{log_debug, log_error, log_info, LdkLogger, Logger as NodeLogger};
+use crate::peer_store::PeerStore;
+use crate::types::{ChannelManager, DefaultMessageRouter, Graph, KeysManager, PowerMode};
 
 pub(crate) enum NodeCustomMessageHandler<L: Deref>
 where
@@ -119,3 +155,608 @@ where
 		}
 	}
 }
+
+/// A custom onion message, i.e., an onion message of a TLV type not handled by LDK itself.
+#[derive(Debug, Clone)]
+pub(crate) struct RawOnionMessage {
+	pub(crate) tlv_type: u64,
+	pub(crate) data: Vec<u8>,
+}
+
+impl RawOnionMessage {
+	/// Returns whether the given TLV type may be used for custom onion messages, i.e., whether it's
+	/// in the permitted range and isn't used by any of the protocols LDK handles.
+	pub(crate) fn is_permitted_tlv_type(tlv_type: u64) -> bool {
+		tlv_type >= MIN_CUSTOM_ONION_MESSAGE_TLV_TYPE
+			&& !OffersMessage::is_known_type(tlv_type)
+			&& !AsyncPaymentsMessage::is_known_type(tlv_type)
+			&& !DNSResolverMessage::is_known_type(tlv_type)
+	}
+}
+
+impl Writeable for RawOnionMessage {
+	fn write<W: Writer>(&self, writer: &mut W) -> Result<(), io::Error> {
+		writer.write_all(&self.data)
+	}
+}
+
+impl OnionMessageContents for RawOnionMessage {
+	fn tlv_type(&self) -> u64 {
+		self.tlv_type
+	}
+
+	fn msg_type(&self) -> &'static str {
+		"Custom"
+	}
+}
+
+/// Bounds the number of custom onion messages we surface per TLV type and time window.
+struct CustomOnionMessageRateLimiter {
+	window_start: Instant,
+	counts_by_tlv_type: HashMap<u64, usize>,
+	total_count: usize,
+}
+
+impl CustomOnionMessageRateLimiter {
+	fn new(now: Instant) -> Self {
+		Self { window_start: now, counts_by_tlv_type: HashMap::new(), total_count: 0 }
+	}
+
+	/// Returns whether a message of the given TLV type received at `now` is within our bounds, and
+	/// if so, accounts for it.
+	fn check_and_count(&mut self, tlv_type: u64, now: Instant) -> bool {
+		if now.duration_since(self.window_start) >= CUSTOM_ONION_MESSAGE_RATE_LIMIT_WINDOW {
+			self.window_start = now;
+			self.counts_by_tlv_type.clear();
+			self.total_count = 0;
+		}
+
+		if self.total_count >= MAX_CUSTOM_ONION_MESSAGES_PER_WINDOW {
+			return false;
+		}
+
+		let count = self.counts_by_tlv_type.entry(tlv_type).or_insert(0);
+		if *count >= MAX_CUSTOM_ONION_MESSAGES_PER_TLV_TYPE {
+			return false;
+		}
+
+		*count += 1;
+		self.total_count += 1;
+		true
+	}
+}
+
+/// A [`CustomOnionMessageHandler`] surfacing the custom onion messages we receive as
+/// [`Event::OnionMessageReceived`], if enabled via [`Config::accept_custom_onion_messages`].
+///
+/// As anybody can send us onion messages, only a bounded number of messages per TLV type and time
+/// window is surfaced, and any messages beyond that are dropped.
+///
+/// Custom onion messages are sent via [`Node::send_onion_message`] rather than being released by
+/// this handler.
+///
+/// [`Config::accept_custom_onion_messages`]: crate::config::Config::accept_custom_onion_messages
+/// [`Node::send_onion_message`]: crate::Node::send_onion_message
+pub(crate) struct NodeCustomOnionMessageHandler<L: Deref>
+where
+	L::Target: LdkLogger,
+{
+	accept_custom_onion_messages: bool,
+	rate_limiter: Mutex<CustomOnionMessageRateLimiter>,
+	event_queue: Arc<EventQueue<L>>,
+	logger: L,
+}
+
+impl<L: Deref> NodeCustomOnionMessageHandler<L>
+where
+	L::Target: LdkLogger,
+{
+	pub(crate) fn new(
+		accept_custom_onion_messages: bool, event_queue: Arc<EventQueue<L>>, logger: L,
+	) -> Self {
+		let rate_limiter = Mutex::new(CustomOnionMessageRateLimiter::new(Instant::now()));
+		Self { accept_custom_onion_messages, rate_limiter, event_queue, logger }
+	}
+}
+
+impl<L: Deref> CustomOnionMessageHandler for NodeCustomOnionMessageHandler<L>
+where
+	L::Target: LdkLogger,
+{
+	type CustomMessage = RawOnionMessage;
+
+	fn handle_custom_message(
+		&self, message: Self::CustomMessage, _context: Option<Vec<u8>>,
+		_responder: Option<Responder>,
+	) -> Option<(Self::CustomMessage, ResponseInstruction)> {
+		if !self.rate_limiter.lock().unwrap().check_and_count(message.tlv_type, Instant::now()) {
+			log_debug!(
+				self.logger,
+				"Dropping custom onion message of TLV type {} as we exceeded our rate limit",
+				message.tlv_type
+			);
+			return None;
+		}
+
+		let event = Event::OnionMessageReceived { tlv_type: message.tlv_type, data: message.data };
+		self.event_queue.add_event(event).unwrap_or_else(|e| {
+			log_error!(self.logger, "Failed to push to event queue: {}", e);
+		});
+		None
+	}
+
+	fn read_custom_message<R: Read>(
+		&self, message_type: u64, buffer: &mut R,
+	) -> Result<Option<Self::CustomMessage>, lightning::ln::msgs::DecodeError> {
+		if !self.accept_custom_onion_messages
+			|| !RawOnionMessage::is_permitted_tlv_type(message_type)
+		{
+			return Ok(None);
+		}
+
+		let mut data = Vec::new();
+		let mut chunk = [0u8; 1024];
+		loop {
+			let read_len = buffer.read(&mut chunk)?;
+			if read_len == 0 {
+				break;
+			}
+			data.extend_from_slice(&chunk[..read_len]);
+		}
+		Ok(Some(RawOnionMessage { tlv_type: message_type, data }))
+	}
+
+	fn release_pending_custom_messages(
+		&self,
+	) -> Vec<(Self::CustomMessage, MessageSendInstructions)> {
+		Vec::new()
+	}
+}
+
+/// A [`MessageRouter`] handing off to LDK's [`DefaultMessageRouter`], unless we're sending an
+/// onion message along a path given via [`Node::send_onion_message`].
+///
+/// [`Node::send_onion_message`]: crate::Node::send_onion_message
+pub(crate) struct NodeMessageRouter {
+	inner: DefaultMessageRouter,
+	// Serializes sending onion messages along given paths.
+	path_send_lock: Mutex<()>,
+	// The recipient and the intermediate nodes of the onion message we're currently sending along
+	// a given path, if any.
+	given_path: Mutex<Option<(PublicKey, Vec<PublicKey>)>>,
+}
+
+impl NodeMessageRouter {
+	pub(crate) fn new(network_graph: Arc<Graph>, entropy_source: Arc<KeysManager>) -> Self {
+		let inner = DefaultMessageRouter::new(network_graph, entropy_source);
+		Self { inner, path_send_lock: Mutex::new(()), given_path: Mutex::new(None) }
+	}
+
+	/// Calls `send` while routing any onion message to `recipient` along the given intermediate
+	/// nodes.
+	pub(crate) fn with_given_path<R, F: FnOnce() -> R>(
+		&self, recipient: PublicKey, intermediate_nodes: Vec<PublicKey>, send: F,
+	) -> R {
+		let _send_guard = self.path_send_lock.lock().unwrap();
+		*self.given_path.lock().unwrap() = Some((recipient, intermediate_nodes));
+		let res = send();
+		*self.given_path.lock().unwrap() = None;
+		res
+	}
+}
+
+impl MessageRouter for NodeMessageRouter {
+	fn find_path(
+		&self, sender: PublicKey, peers: Vec<PublicKey>, destination: Destination,
+	) -> Result<OnionMessagePath, ()> {
+		if let Some((recipient, intermediate_nodes)) = self.given_path.lock().unwrap().as_ref() {
+			if destination == Destination::Node(*recipient) {
+				return Ok(OnionMessagePath {
+					intermediate_nodes: intermediate_nodes.clone(),
+					destination,
+					first_node_addresses: Vec::new(),
+				});
+			}
+		}
+		self.inner.find_path(sender, peers, destination)
+	}
+
+	fn create_blinded_paths<T: secp256k1::Signing + secp256k1::Verification>(
+		&self, recipient: PublicKey, local_node_receive_key: ReceiveAuthKey,
+		context: MessageContext, peers: Vec<MessageForwardNode>, secp_ctx: &Secp256k1<T>,
+	) -> Result<Vec<BlindedMessagePath>, ()> {
+		self.inner.create_blinded_paths(recipient, local_node_receive_key, context, peers, secp_ctx)
+	}
+}
+
+/// A [`RoutingMessageHandler`] enforcing our [`PeerConnectionPolicy`] and [`PowerMode`] before
+/// handing off to the wrapped gossip handler.
+///
+/// As the routing handler is the first handler to be notified about a newly connected peer, this
+/// allows us to reject disallowed inbound peers before any of the other handlers processes them.
+///
+/// While in [`PowerMode::LowPower`], gossip received from peers is dropped and we don't serve any
+/// gossip to peers.
+pub(crate) struct NodeRoutingMessageHandler<L: Deref>
+where
+	L::Target: LdkLogger,
+{
+	inner: Arc<dyn RoutingMessageHandler + Send + Sync>,
+	peer_connection_policy: Arc<RwLock<PeerConnectionPolicy>>,
+	power_mode: Arc<RwLock<PowerMode>>,
+	logger: L,
+}
+
+impl<L: Deref> NodeRoutingMessageHandler<L>
+where
+	L::Target: LdkLogger,
+{
+	pub(crate) fn new(
+		inner: Arc<dyn RoutingMessageHandler + Send + Sync>,
+		peer_connection_policy: Arc<RwLock<PeerConnectionPolicy>>,
+		power_mode: Arc<RwLock<PowerMode>>, logger: L,
+	) -> Self {
+		Self { inner, peer_connection_policy, power_mode, logger }
+	}
+
+	fn is_low_power(&self) -> bool {
+		*self.power_mode.read().unwrap() == PowerMode::LowPower
+	}
+}
+
+impl<L: Deref> BaseMessageHandler for NodeRoutingMessageHandler<L>
+where
+	L::Target: LdkLogger,
+{
+	fn get_and_clear_pending_msg_events(&self) -> Vec<MessageSendEvent> {
+		self.inner.get_and_clear_pending_msg_events()
+	}
+
+	fn peer_disconnected(&self, their_node_id: PublicKey) {
+		self.inner.peer_disconnected(their_node_id)
+	}
+
+	fn provided_node_features(&self) -> NodeFeatures {
+		self.inner.provided_node_features()
+	}
+
+	fn provided_init_features(&self, their_node_id: PublicKey) -> InitFeatures {
+		self.inner.provided_init_features(their_node_id)
+	}
+
+	fn peer_connected(
+		&self, their_node_id: PublicKey, msg: &Init, inbound: bool,
+	) -> Result<(), ()> {
+		if inbound && !self.peer_connection_policy.read().unwrap().allows_inbound(&their_node_id) {
+			log_info!(
+				self.logger,
+				"Rejecting inbound connection from peer {} as it's disallowed by our connection policy",
+				their_node_id
+			);
+			return Err(());
+		}
+		self.inner.peer_connected(their_node_id, msg, inbound)
+	}
+}
+
+impl<L: Deref> RoutingMessageHandler for NodeRoutingMessageHandler<L>
+where
+	L::Target: LdkLogger,
+{
+	fn handle_node_announcement(
+		&self, their_node_id: Option<PublicKey>, msg: &NodeAnnouncement,
+	) -> Result<bool, LightningError> {
+		if their_node_id.is_some() && self.is_low_power() {
+			return Ok(false);
+		}
+		self.inner.handle_node_announcement(their_node_id, msg)
+	}
+
+	fn handle_channel_announcement(
+		&self, their_node_id: Option<PublicKey>, msg: &ChannelAnnouncement,
+	) -> Result<bool, LightningError> {
+		if their_node_id.is_some() && self.is_low_power() {
+			return Ok(false);
+		}
+		self.inner.handle_channel_announcement(their_node_id, msg)
+	}
+
+	fn handle_channel_update(
+		&self, their_node_id: Option<PublicKey>, msg: &ChannelUpdate,
+	) -> Result<bool, LightningError> {
+		if their_node_id.is_some() && self.is_low_power() {
+			return Ok(false);
+		}
+		self.inner.handle_channel_update(their_node_id, msg)
+	}
+
+	fn get_next_channel_announcement(
+		&self, starting_point: u64,
+	) -> Option<(ChannelAnnouncement, Option<ChannelUpdate>, Option<ChannelUpdate>)> {
+		if self.is_low_power() {
+			return None;
+		}
+		self.inner.get_next_channel_announcement(starting_point)
+	}
+
+	fn get_next_node_announcement(
+		&self, starting_point: Option<&NodeId>,
+	) -> Option<NodeAnnouncement> {
+		if self.is_low_power() {
+			return None;
+		}
+		self.inner.get_next_node_announcement(starting_point)
+	}
+
+	fn handle_reply_channel_range(
+		&self, their_node_id: PublicKey, msg: ReplyChannelRange,
+	) -> Result<(), LightningError> {
+		self.inner.handle_reply_channel_range(their_node_id, msg)
+	}
+
+	fn handle_reply_short_channel_ids_end(
+		&self, their_node_id: PublicKey, msg: ReplyShortChannelIdsEnd,
+	) -> Result<(), LightningError> {
+		self.inner.handle_reply_short_channel_ids_end(their_node_id, msg)
+	}
+
+	fn handle_query_channel_range(
+		&self, their_node_id: PublicKey, msg: QueryChannelRange,
+	) -> Result<(), LightningError> {
+		self.inner.handle_query_channel_range(their_node_id, msg)
+	}
+
+	fn handle_query_short_channel_ids(
+		&self, their_node_id: PublicKey, msg: QueryShortChannelIds,
+	) -> Result<(), LightningError> {
+		self.inner.handle_query_short_channel_ids(their_node_id, msg)
+	}
+
+	fn processing_queue_high(&self) -> bool {
+		self.inner.processing_queue_high()
+	}
+}
+
+/// A [`ChannelMessageHandler`] handing off to our [`ChannelManager`], while withholding any
+/// optional features disabled via [`Config::features`] from the features we advertise.
+///
+/// Also records when peers connect and disconnect in our [`PeerStore`].
+///
+/// [`Config::features`]: crate::config::Config::features
+pub(crate) struct NodeChannelMessageHandler {
+	inner: Arc<ChannelManager>,
+	feature_config: FeatureConfig,
+	peer_store: Arc<PeerStore<Arc<NodeLogger>>>,
+}
+
+impl NodeChannelMessageHandler {
+	pub(crate) fn new(
+		inner: Arc<ChannelManager>, feature_config: FeatureConfig,
+		peer_store: Arc<PeerStore<Arc<NodeLogger>>>,
+	) -> Self {
+		Self { inner, feature_config, peer_store }
+	}
+}
+
+impl BaseMessageHandler for NodeChannelMessageHandler {
+	fn get_and_clear_pending_msg_events(&self) -> Vec<MessageSendEvent> {
+		self.inner.get_and_clear_pending_msg_events()
+	}
+
+	fn peer_disconnected(&self, their_node_id: PublicKey) {
+		self.inner.peer_disconnected(their_node_id);
+		let has_channels = !self.inner.list_channels_with_counterparty(&their_node_id).is_empty();
+		self.peer_store.peer_disconnected(&their_node_id, has_channels);
+	}
+
+	fn provided_node_features(&self) -> NodeFeatures {
+		let mut features = self.inner.provided_node_features();
+		self.feature_config.clear_disabled_node_features(&mut features);
+		features
+	}
+
+	fn provided_init_features(&self, their_node_id: PublicKey) -> InitFeatures {
+		let mut features = self.inner.provided_init_features(their_node_id);
+		self.feature_config.clear_disabled_init_features(&mut features);
+		features
+	}
+
+	fn peer_connected(
+		&self, their_node_id: PublicKey, msg: &Init, inbound: bool,
+	) -> Result<(), ()> {
+		self.inner.peer_connected(their_node_id, msg, inbound)?;
+		let has_channels = !self.inner.list_channels_with_counterparty(&their_node_id).is_empty();
+		self.peer_store.peer_connected(&their_node_id, has_channels);
+		Ok(())
+	}
+}
+
+impl ChannelMessageHandler for NodeChannelMessageHandler {
+	fn handle_open_channel(&self, their_node_id: PublicKey, msg: &OpenChannel) {
+		self.inner.handle_open_channel(their_node_id, msg)
+	}
+
+	fn handle_open_channel_v2(&self, their_node_id: PublicKey, msg: &OpenChannelV2) {
+		self.inner.handle_open_channel_v2(their_node_id, msg)
+	}
+
+	fn handle_accept_channel(&self, their_node_id: PublicKey, msg: &AcceptChannel) {
+		self.inner.handle_accept_channel(their_node_id, msg)
+	}
+
+	fn handle_accept_channel_v2(&self, their_node_id: PublicKey, msg: &AcceptChannelV2) {
+		self.inner.handle_accept_channel_v2(their_node_id, msg)
+	}
+
+	fn handle_funding_created(&self, their_node_id: PublicKey, msg: &FundingCreated) {
+		self.inner.handle_funding_created(their_node_id, msg)
+	}
+
+	fn handle_funding_signed(&self, their_node_id: PublicKey, msg: &FundingSigned) {
+		self.inner.handle_funding_signed(their_node_id, msg)
+	}
+
+	fn handle_channel_ready(&self, their_node_id: PublicKey, msg: &ChannelReady) {
+		self.inner.handle_channel_ready(their_node_id, msg)
+	}
+
+	fn handle_peer_storage(&self, their_node_id: PublicKey, msg: PeerStorage) {
+		self.inner.handle_peer_storage(their_node_id, msg)
+	}
+
+	fn handle_peer_storage_retrieval(&self, their_node_id: PublicKey, msg: PeerStorageRetrieval) {
+		self.inner.handle_peer_storage_retrieval(their_node_id, msg)
+	}
+
+	fn handle_shutdown(&self, their_node_id: PublicKey, msg: &Shutdown) {
+		self.inner.handle_shutdown(their_node_id, msg)
+	}
+
+	fn handle_closing_signed(&self, their_node_id: PublicKey, msg: &ClosingSigned) {
+		self.inner.handle_closing_signed(their_node_id, msg)
+	}
+
+	fn handle_stfu(&self, their_node_id: PublicKey, msg: &Stfu) {
+		self.inner.handle_stfu(their_node_id, msg)
+	}
+
+	fn handle_splice_init(&self, their_node_id: PublicKey, msg: &SpliceInit) {
+		self.inner.handle_splice_init(their_node_id, msg)
+	}
+
+	fn handle_splice_ack(&self, their_node_id: PublicKey, msg: &SpliceAck) {
+		self.inner.handle_splice_ack(their_node_id, msg)
+	}
+
+	fn handle_splice_locked(&self, their_node_id: PublicKey, msg: &SpliceLocked) {
+		self.inner.handle_splice_locked(their_node_id, msg)
+	}
+
+	fn handle_tx_add_input(&self, their_node_id: PublicKey, msg: &TxAddInput) {
+		self.inner.handle_tx_add_input(their_node_id, msg)
+	}
+
+	fn handle_tx_add_output(&self, their_node_id: PublicKey, msg: &TxAddOutput) {
+		self.inner.handle_tx_add_output(their_node_id, msg)
+	}
+
+	fn handle_tx_remove_input(&self, their_node_id: PublicKey, msg: &TxRemoveInput) {
+		self.inner.handle_tx_remove_input(their_node_id, msg)
+	}
+
+	fn handle_tx_remove_output(&self, their_node_id: PublicKey, msg: &TxRemoveOutput) {
+		self.inner.handle_tx_remove_output(their_node_id, msg)
+	}
+
+	fn handle_tx_complete(&self, their_node_id: PublicKey, msg: &TxComplete) {
+		self.inner.handle_tx_complete(their_node_id, msg)
+	}
+
+	fn handle_tx_signatures(&self, their_node_id: PublicKey, msg: &TxSignatures) {
+		self.inner.handle_tx_signatures(their_node_id, msg)
+	}
+
+	fn handle_tx_init_rbf(&self, their_node_id: PublicKey, msg: &TxInitRbf) {
+		self.inner.handle_tx_init_rbf(their_node_id, msg)
+	}
+
+	fn handle_tx_ack_rbf(&self, their_node_id: PublicKey, msg: &TxAckRbf) {
+		self.inner.handle_tx_ack_rbf(their_node_id, msg)
+	}
+
+	fn handle_tx_abort(&self, their_node_id: PublicKey, msg: &TxAbort) {
+		self.inner.handle_tx_abort(their_node_id, msg)
+	}
+
+	fn handle_update_add_htlc(&self, their_node_id: PublicKey, msg: &UpdateAddHTLC) {
+		self.inner.handle_update_add_htlc(their_node_id, msg)
+	}
+
+	fn handle_update_fulfill_htlc(&self, their_node_id: PublicKey, msg: UpdateFulfillHTLC) {
+		self.inner.handle_update_fulfill_htlc(their_node_id, msg)
+	}
+
+	fn handle_update_fail_htlc(&self, their_node_id: PublicKey, msg: &UpdateFailHTLC) {
+		self.inner.handle_update_fail_htlc(their_node_id, msg)
+	}
+
+	fn handle_update_fail_malformed_htlc(
+		&self, their_node_id: PublicKey, msg: &UpdateFailMalformedHTLC,
+	) {
+		self.inner.handle_update_fail_malformed_htlc(their_node_id, msg)
+	}
+
+	fn handle_commitment_signed(&self, their_node_id: PublicKey, msg: &CommitmentSigned) {
+		self.inner.handle_commitment_signed(their_node_id, msg)
+	}
+
+	fn handle_commitment_signed_batch(
+		&self, their_node_id: PublicKey, channel_id: ChannelId, batch: Vec<CommitmentSigned>,
+	) {
+		self.inner.handle_commitment_signed_batch(their_node_id, channel_id, batch)
+	}
+
+	fn handle_revoke_and_ack(&self, their_node_id: PublicKey, msg: &RevokeAndACK) {
+		self.inner.handle_revoke_and_ack(their_node_id, msg)
+	}
+
+	fn handle_update_fee(&self, their_node_id: PublicKey, msg: &UpdateFee) {
+		self.inner.handle_update_fee(their_node_id, msg)
+	}
+
+	fn handle_announcement_signatures(
+		&self, their_node_id: PublicKey, msg: &AnnouncementSignatures,
+	) {
+		self.inner.handle_announcement_signatures(their_node_id, msg)
+	}
+
+	fn handle_channel_reestablish(&self, their_node_id: PublicKey, msg: &ChannelReestablish) {
+		self.inner.handle_channel_reestablish(their_node_id, msg)
+	}
+
+	fn handle_channel_update(&self, their_node_id: PublicKey, msg: &ChannelUpdate) {
+		ChannelMessageHandler::handle_channel_update(&*self.inner, their_node_id, msg)
+	}
+
+	fn handle_error(&self, their_node_id: PublicKey, msg: &ErrorMessage) {
+		self.inner.handle_error(their_node_id, msg)
+	}
+
+	fn get_chain_hashes(&self) -> Option<Vec<ChainHash>> {
+		self.inner.get_chain_hashes()
+	}
+
+	fn message_received(&self) {
+		self.inner.message_received()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn custom_onion_message_rate_limiter_bounds_messages() {
+		let start = Instant::now();
+		let mut limiter = CustomOnionMessageRateLimiter::new(start);
+
+		// Messages of a single TLV type are bounded per window.
+		for _ in 0..MAX_CUSTOM_ONION_MESSAGES_PER_TLV_TYPE {
+			assert!(limiter.check_and_count(1 << 16, start));
+		}
+		assert!(!limiter.check_and_count(1 << 16, start));
+
+		// Messages across all TLV types are bounded per window.
+		let mut tlv_type = (1 << 16) + 1;
+		while limiter.total_count < MAX_CUSTOM_ONION_MESSAGES_PER_WINDOW {
+			assert!(limiter.check_and_count(tlv_type, start));
+			tlv_type += 1;
+		}
+		assert!(!limiter.check_and_count(tlv_type, start));
+
+		// The bounds are reset once the window passed.
+		let next_window = start + CUSTOM_ONION_MESSAGE_RATE_LIMIT_WINDOW;
+		assert!(limiter.check_and_count(1 << 16, next_window));
+		assert!(limiter.check_and_count(tlv_type, next_window));
+	}
+}
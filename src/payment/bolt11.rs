@@ -10,32 +10,48 @@
 //! [BOLT 11]: https://github.com/lightning/bolts/blob/master/11-payment-encoding.md
 
 use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
 use bitcoin::hashes::sha256::Hash as Sha256;
 use bitcoin::hashes::Hash;
+use bitcoin::secp256k1::PublicKey;
 use lightning::ln::channelmanager::{
-	Bolt11InvoiceParameters, Bolt11PaymentError, PaymentId, Retry, RetryableSendFailure,
+	Bolt11InvoiceParameters, Bolt11PaymentError, PaymentId, RecipientOnionFields, Retry,
+	RetryableSendFailure, MIN_FINAL_CLTV_EXPIRY_DELTA,
 };
-use lightning::routing::router::{PaymentParameters, RouteParameters, RouteParametersConfig};
+use lightning::ln::types::ChannelId;
+use lightning::routing::router::{
+	Path as LdkPath, PaymentParameters, Route as LdkRoute, RouteHint, RouteHintHop,
+	RouteHop as LdkRouteHop, RouteParameters, RouteParametersConfig, Router as LdkRouter,
+};
+use lightning::sign::{NodeSigner, Recipient};
+use lightning::types::features::{ChannelFeatures, NodeFeatures};
 use lightning_invoice::{
 	Bolt11Invoice as LdkBolt11Invoice, Bolt11InvoiceDescription as LdkBolt11InvoiceDescription,
+	InvoiceBuilder, RoutingFees, Sha256 as LdkSha256,
 };
+use lightning_liquidity::lsps2::msgs::LSPS2OpeningFeeParams;
 use lightning_types::payment::{PaymentHash, PaymentPreimage};
 
-use crate::config::{Config, LDK_PAYMENT_RETRY_TIMEOUT};
+use crate::config::{
+	route_params_from_config, Config, InvoiceRouteHintPolicy, SendPaymentParams,
+	MAX_INVOICE_ROUTE_HINTS,
+};
 use crate::connection::ConnectionManager;
 use crate::data_store::DataStoreUpdateResult;
 use crate::error::Error;
 use crate::ffi::{maybe_deref, maybe_try_convert_enum, maybe_wrap};
-use crate::liquidity::LiquiditySource;
-use crate::logger::{log_error, log_info, LdkLogger, Logger};
+use crate::liquidity::{JitChannelFeeLimits, LiquiditySource};
+use crate::logger::{log_error, log_info, log_warn, LdkLogger, Logger};
+use crate::payment::claim_watchdog::ClaimDeadlineWatchdog;
+use crate::payment::idempotency::PaymentIdempotencyKeys;
 use crate::payment::store::{
 	LSPFeeLimits, PaymentDetails, PaymentDetailsUpdate, PaymentDirection, PaymentKind,
 	PaymentStatus,
 };
 use crate::peer_store::{PeerInfo, PeerStore};
 use crate::runtime::Runtime;
-use crate::types::{ChannelManager, PaymentStore};
+use crate::types::{ChannelManager, KeysManager, PaymentRetryStrategies, PaymentStore, Router};
 
 #[cfg(not(feature = "uniffi"))]
 type Bolt11Invoice = LdkBolt11Invoice;
@@ -47,6 +63,88 @@ type Bolt11InvoiceDescription = LdkBolt11InvoiceDescription;
 #[cfg(feature = "uniffi")]
 type Bolt11InvoiceDescription = crate::ffi::Bolt11InvoiceDescription;
 
+/// A payment route to be used verbatim via [`Bolt11Payment::send_using_route`].
+///
+/// May be retrieved via [`Node::find_route`].
+///
+/// [`Node::find_route`]: crate::Node::find_route
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Route {
+	/// The hops of the route, starting with the counterparty of our first-hop channel and ending
+	/// with the payee.
+	pub hops: Vec<RouteHop>,
+}
+
+impl Route {
+	pub(crate) fn from_ldk_path(path: &LdkPath) -> Self {
+		let hops = path
+			.hops
+			.iter()
+			.map(|hop| RouteHop {
+				node_id: hop.pubkey,
+				short_channel_id: hop.short_channel_id,
+				fee_msat: hop.fee_msat,
+				cltv_expiry_delta: hop.cltv_expiry_delta,
+			})
+			.collect();
+		Self { hops }
+	}
+}
+
+/// A single hop of a [`Route`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteHop {
+	/// The node ID of the node reached via this hop.
+	pub node_id: PublicKey,
+	/// The short channel ID of the channel used to reach the node.
+	pub short_channel_id: u64,
+	/// The fee, in millisatoshis, taken by the node for forwarding over the next hop.
+	///
+	/// For the last hop, this is the amount delivered to the payee instead.
+	pub fee_msat: u64,
+	/// The CLTV expiry delta required by the node for forwarding over the next hop.
+	///
+	/// For the last hop, this is the final CLTV expiry delta expected by the payee instead.
+	pub cltv_expiry_delta: u32,
+}
+
+/// The expected cost of a Lightning payment, as returned by [`Bolt11Payment::estimate_fee`] and
+/// [`SpontaneousPayment::estimate_fee`].
+///
+/// [`SpontaneousPayment::estimate_fee`]: crate::payment::SpontaneousPayment::estimate_fee
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeeEstimate {
+	/// The total fee, in millisatoshis, that would be paid to intermediate hops.
+	pub fee_msat: u64,
+	/// The number of paths the payment would be split across.
+	pub path_count: u32,
+	/// The number of hops of the longest path, including the payee.
+	pub max_path_length: u32,
+}
+
+impl FeeEstimate {
+	pub(crate) fn from_route(route: &LdkRoute) -> Self {
+		let fee_msat = route.get_total_fees();
+		let path_count = route.paths.len() as u32;
+		let max_path_length =
+			route.paths.iter().map(|path| path.hops.len() as u32).max().unwrap_or(0);
+		Self { fee_msat, path_count, max_path_length }
+	}
+}
+
+/// Optional parameters for creating an invoice via [`Bolt11Payment::receive_inner`].
+#[derive(Default)]
+pub(crate) struct ReceiveParams<'a> {
+	/// The payment hash to register for a manually-claimable payment, if any.
+	pub manual_claim_payment_hash: Option<PaymentHash>,
+	/// The timeout after which a manually-claimable payment is failed back.
+	pub claim_timeout_secs: Option<u64>,
+	/// The channels to include as route hints, overriding the configured policy.
+	pub route_hint_channels: Option<&'a [ChannelId]>,
+	/// The `min_final_cltv_expiry_delta` to require, overriding the configured one.
+	pub min_final_cltv_expiry_delta: Option<u16>,
+}
+
 /// A payment handler allowing to create and pay [BOLT 11] invoices.
 ///
 /// Should be retrieved by calling [`Node::bolt11_payment`].
@@ -56,10 +154,15 @@ type Bolt11InvoiceDescription = crate::ffi::Bolt11InvoiceDescription;
 pub struct Bolt11Payment {
 	runtime: Arc<Runtime>,
 	channel_manager: Arc<ChannelManager>,
+	router: Arc<Router>,
+	keys_manager: Arc<KeysManager>,
 	connection_manager: Arc<ConnectionManager<Arc<Logger>>>,
 	liquidity_source: Option<Arc<LiquiditySource<Arc<Logger>>>>,
 	payment_store: Arc<PaymentStore>,
+	retry_strategies: Arc<PaymentRetryStrategies>,
+	idempotency_keys: Arc<PaymentIdempotencyKeys>,
 	peer_store: Arc<PeerStore<Arc<Logger>>>,
+	claim_watchdog: Arc<ClaimDeadlineWatchdog>,
 	config: Arc<Config>,
 	is_running: Arc<RwLock<bool>>,
 	logger: Arc<Logger>,
@@ -67,19 +170,26 @@ pub struct Bolt11Payment {
 
 impl Bolt11Payment {
 	pub(crate) fn new(
-		runtime: Arc<Runtime>, channel_manager: Arc<ChannelManager>,
-		connection_manager: Arc<ConnectionManager<Arc<Logger>>>,
+		runtime: Arc<Runtime>, channel_manager: Arc<ChannelManager>, router: Arc<Router>,
+		keys_manager: Arc<KeysManager>, connection_manager: Arc<ConnectionManager<Arc<Logger>>>,
 		liquidity_source: Option<Arc<LiquiditySource<Arc<Logger>>>>,
-		payment_store: Arc<PaymentStore>, peer_store: Arc<PeerStore<Arc<Logger>>>,
-		config: Arc<Config>, is_running: Arc<RwLock<bool>>, logger: Arc<Logger>,
+		payment_store: Arc<PaymentStore>, retry_strategies: Arc<PaymentRetryStrategies>,
+		idempotency_keys: Arc<PaymentIdempotencyKeys>, peer_store: Arc<PeerStore<Arc<Logger>>>,
+		claim_watchdog: Arc<ClaimDeadlineWatchdog>, config: Arc<Config>,
+		is_running: Arc<RwLock<bool>>, logger: Arc<Logger>,
 	) -> Self {
 		Self {
 			runtime,
 			channel_manager,
+			router,
+			keys_manager,
 			connection_manager,
 			liquidity_source,
 			payment_store,
+			retry_strategies,
+			idempotency_keys,
 			peer_store,
+			claim_watchdog,
 			config,
 			is_running,
 			logger,
@@ -88,16 +198,32 @@ impl Bolt11Payment {
 
 	/// Send a payment given an invoice.
 	///
-	/// If `route_parameters` are provided they will override the default as well as the
-	/// node-wide parameters configured via [`Config::route_parameters`] on a per-field basis.
+	/// If `send_params` are provided they will override the default as well as the node-wide
+	/// parameters configured via [`Config::route_parameters`] on a per-field basis. If the
+	/// payment fails after its [`SendPaymentParams::retry_strategy`] was exhausted, the
+	/// respective [`Event::PaymentFailed`] will indicate the bound that was hit.
+	///
+	/// [`Event::PaymentFailed`]: crate::Event::PaymentFailed
 	pub fn send(
-		&self, invoice: &Bolt11Invoice, route_parameters: Option<RouteParametersConfig>,
+		&self, invoice: &Bolt11Invoice, send_params: Option<SendPaymentParams>,
+	) -> Result<PaymentId, Error> {
+		let invoice = maybe_deref(invoice);
+		let payment_id = PaymentId(invoice.payment_hash().to_byte_array());
+		let send_params = send_params.unwrap_or_default();
+		let idempotency_key = send_params.idempotency_key.clone();
+		let on_duplicate = send_params.on_duplicate.unwrap_or_default();
+		self.idempotency_keys.send(idempotency_key, on_duplicate, payment_id, || {
+			self.send_inner(invoice, send_params)
+		})
+	}
+
+	fn send_inner(
+		&self, invoice: &LdkBolt11Invoice, send_params: SendPaymentParams,
 	) -> Result<PaymentId, Error> {
 		if !*self.is_running.read().unwrap() {
 			return Err(Error::NotRunning);
 		}
 
-		let invoice = maybe_deref(invoice);
 		let payment_hash = PaymentHash(invoice.payment_hash().to_byte_array());
 		let payment_id = PaymentId(invoice.payment_hash().to_byte_array());
 		if let Some(payment) = self.payment_store.get(&payment_id) {
@@ -109,19 +235,20 @@ impl Bolt11Payment {
 			}
 		}
 
-		let route_parameters =
-			route_parameters.or(self.config.route_parameters).unwrap_or_default();
-		let retry_strategy = Retry::Timeout(LDK_PAYMENT_RETRY_TIMEOUT);
+		let route_parameters = send_params.route_parameters(&self.config);
+		let retry_strategy = send_params.retry_strategy_or_default();
 		let payment_secret = Some(*invoice.payment_secret());
 
-		match self.channel_manager.pay_for_bolt11_invoice(
+		match self.pay_for_bolt11_invoice(
 			invoice,
 			payment_id,
 			None,
 			route_parameters,
-			retry_strategy,
+			retry_strategy.into(),
+			send_params.ignore_expiry,
 		) {
 			Ok(()) => {
+				self.retry_strategies.lock().unwrap().insert(payment_id, retry_strategy);
 				let payee_pubkey = invoice.recover_payee_pub_key();
 				let amt_msat = invoice.amount_milli_satoshis().unwrap();
 				log_info!(self.logger, "Initiated sending {}msat to {}", amt_msat, payee_pubkey);
@@ -184,17 +311,32 @@ impl Bolt11Payment {
 	/// This can be used to pay a so-called "zero-amount" invoice, i.e., an invoice that leaves the
 	/// amount paid to be determined by the user.
 	///
-	/// If `route_parameters` are provided they will override the default as well as the
-	/// node-wide parameters configured via [`Config::route_parameters`] on a per-field basis.
+	/// If `send_params` are provided they will override the default as well as the node-wide
+	/// parameters configured via [`Config::route_parameters`] on a per-field basis. If the
+	/// payment fails after its [`SendPaymentParams::retry_strategy`] was exhausted, the
+	/// respective [`Event::PaymentFailed`] will indicate the bound that was hit.
+	///
+	/// [`Event::PaymentFailed`]: crate::Event::PaymentFailed
 	pub fn send_using_amount(
-		&self, invoice: &Bolt11Invoice, amount_msat: u64,
-		route_parameters: Option<RouteParametersConfig>,
+		&self, invoice: &Bolt11Invoice, amount_msat: u64, send_params: Option<SendPaymentParams>,
+	) -> Result<PaymentId, Error> {
+		let invoice = maybe_deref(invoice);
+		let payment_id = PaymentId(invoice.payment_hash().to_byte_array());
+		let send_params = send_params.unwrap_or_default();
+		let idempotency_key = send_params.idempotency_key.clone();
+		let on_duplicate = send_params.on_duplicate.unwrap_or_default();
+		self.idempotency_keys.send(idempotency_key, on_duplicate, payment_id, || {
+			self.send_using_amount_inner(invoice, amount_msat, send_params)
+		})
+	}
+
+	fn send_using_amount_inner(
+		&self, invoice: &LdkBolt11Invoice, amount_msat: u64, send_params: SendPaymentParams,
 	) -> Result<PaymentId, Error> {
 		if !*self.is_running.read().unwrap() {
 			return Err(Error::NotRunning);
 		}
 
-		let invoice = maybe_deref(invoice);
 		if let Some(invoice_amount_msat) = invoice.amount_milli_satoshis() {
 			if amount_msat < invoice_amount_msat {
 				log_error!(
@@ -215,19 +357,20 @@ impl Bolt11Payment {
 			}
 		}
 
-		let route_parameters =
-			route_parameters.or(self.config.route_parameters).unwrap_or_default();
-		let retry_strategy = Retry::Timeout(LDK_PAYMENT_RETRY_TIMEOUT);
+		let route_parameters = send_params.route_parameters(&self.config);
+		let retry_strategy = send_params.retry_strategy_or_default();
 		let payment_secret = Some(*invoice.payment_secret());
 
-		match self.channel_manager.pay_for_bolt11_invoice(
+		match self.pay_for_bolt11_invoice(
 			invoice,
 			payment_id,
 			Some(amount_msat),
 			route_parameters,
-			retry_strategy,
+			retry_strategy.into(),
+			send_params.ignore_expiry,
 		) {
 			Ok(()) => {
+				self.retry_strategies.lock().unwrap().insert(payment_id, retry_strategy);
 				let payee_pubkey = invoice.recover_payee_pub_key();
 				log_info!(
 					self.logger,
@@ -288,6 +431,238 @@ impl Bolt11Payment {
 		}
 	}
 
+	fn pay_for_bolt11_invoice(
+		&self, invoice: &LdkBolt11Invoice, payment_id: PaymentId, amount_msat: Option<u64>,
+		route_parameters: RouteParametersConfig, retry_strategy: Retry, ignore_expiry: bool,
+	) -> Result<(), Bolt11PaymentError> {
+		if !ignore_expiry || !invoice.is_expired() {
+			return self.channel_manager.pay_for_bolt11_invoice(
+				invoice,
+				payment_id,
+				amount_msat,
+				route_parameters,
+				retry_strategy,
+			);
+		}
+
+		log_warn!(
+			self.logger,
+			"Attempting to pay expired invoice with payment hash {} as requested.",
+			invoice.payment_hash()
+		);
+
+		// This mirrors `ChannelManager::pay_for_bolt11_invoice`, but doesn't set the invoice's
+		// expiry time, which would have LDK refuse to send the payment.
+		let amount_msat = match (invoice.amount_milli_satoshis(), amount_msat) {
+			(Some(amount_msat), None) | (None, Some(amount_msat)) => amount_msat,
+			(Some(invoice_amount_msat), Some(amount_msat)) if amount_msat < invoice_amount_msat => {
+				return Err(Bolt11PaymentError::InvalidAmount);
+			},
+			(Some(_), Some(amount_msat)) => amount_msat,
+			(None, None) => return Err(Bolt11PaymentError::InvalidAmount),
+		};
+
+		let mut recipient_fields = RecipientOnionFields::secret_only(*invoice.payment_secret());
+		recipient_fields.payment_metadata = invoice.payment_metadata().cloned();
+
+		let mut payment_params = PaymentParameters::from_bolt11_invoice(invoice);
+		payment_params.expiry_time = None;
+		let route_params = route_params_from_config(payment_params, amount_msat, &route_parameters);
+
+		let payment_hash = PaymentHash(invoice.payment_hash().to_byte_array());
+		self.channel_manager
+			.send_payment(payment_hash, recipient_fields, payment_id, route_params, retry_strategy)
+			.map_err(Bolt11PaymentError::SendingFailed)
+	}
+
+	/// Send a payment given an invoice, using the given [`Route`] verbatim rather than finding a
+	/// route ourselves.
+	///
+	/// The route needs to start with one of our usable channels, end at the invoice's payee, and
+	/// deliver exactly the invoice amount. For zero-amount invoices, the amount delivered by the
+	/// last hop is paid. The CLTV expiry delta of the last hop must be at least the invoice's
+	/// `min_final_cltv_expiry_delta`.
+	///
+	/// **Note:** Payments sent along a given route will not be retried automatically. If the
+	/// payment fails, a [`PaymentFailed`] event is emitted and it may be re-sent, e.g., along a
+	/// different route.
+	///
+	/// [`PaymentFailed`]: crate::Event::PaymentFailed
+	pub fn send_using_route(
+		&self, invoice: &Bolt11Invoice, route: Route,
+	) -> Result<PaymentId, Error> {
+		if !*self.is_running.read().unwrap() {
+			return Err(Error::NotRunning);
+		}
+
+		let invoice = maybe_deref(invoice);
+		let (first_hop, last_hop) = match (route.hops.first(), route.hops.last()) {
+			(Some(first_hop), Some(last_hop)) => (first_hop, last_hop),
+			_ => {
+				log_error!(self.logger, "Failed to send payment as the given route is empty.");
+				return Err(Error::InvalidRoute);
+			},
+		};
+
+		let payee_pubkey = invoice.recover_payee_pub_key();
+		if last_hop.node_id != payee_pubkey {
+			log_error!(
+				self.logger,
+				"Failed to send payment as the given route doesn't terminate at the payee {}.",
+				payee_pubkey
+			);
+			return Err(Error::InvalidRoute);
+		}
+
+		let amount_msat = last_hop.fee_msat;
+		match invoice.amount_milli_satoshis() {
+			Some(invoice_amount_msat) if amount_msat != invoice_amount_msat => {
+				log_error!(
+					self.logger,
+					"Failed to send payment as the given route doesn't deliver the invoice amount: required {}msat, gave {}msat.",
+					invoice_amount_msat,
+					amount_msat
+				);
+				return Err(Error::InvalidRoute);
+			},
+			None if amount_msat == 0 => {
+				log_error!(
+					self.logger,
+					"Failed to send payment as the given route delivers no value."
+				);
+				return Err(Error::InvalidRoute);
+			},
+			_ => {},
+		}
+
+		let min_final_cltv_expiry_delta = invoice.min_final_cltv_expiry_delta();
+		if u64::from(last_hop.cltv_expiry_delta) < min_final_cltv_expiry_delta {
+			log_error!(
+				self.logger,
+				"Failed to send payment as the given route's final CLTV expiry delta of {} is below the invoice's minimum of {}.",
+				last_hop.cltv_expiry_delta,
+				min_final_cltv_expiry_delta
+			);
+			return Err(Error::InvalidRoute);
+		}
+
+		let first_hop_channel = self.channel_manager.list_usable_channels().into_iter().find(|c| {
+			c.get_outbound_payment_scid() == Some(first_hop.short_channel_id)
+				&& c.counterparty.node_id == first_hop.node_id
+		});
+		let first_hop_channel = match first_hop_channel {
+			Some(channel) => channel,
+			None => {
+				log_error!(
+					self.logger,
+					"Failed to send payment as the first hop of the given route isn't one of our usable channels: {}",
+					first_hop.short_channel_id
+				);
+				return Err(Error::InvalidRoute);
+			},
+		};
+
+		let payment_hash = PaymentHash(invoice.payment_hash().to_byte_array());
+		let payment_id = PaymentId(invoice.payment_hash().to_byte_array());
+		if let Some(payment) = self.payment_store.get(&payment_id) {
+			if payment.status == PaymentStatus::Pending
+				|| payment.status == PaymentStatus::Succeeded
+			{
+				log_error!(self.logger, "Payment error: an invoice must not be paid twice.");
+				return Err(Error::DuplicatePayment);
+			}
+		}
+
+		let hops = route
+			.hops
+			.iter()
+			.enumerate()
+			.map(|(idx, hop)| {
+				let (node_features, maybe_announced_channel) = if idx == 0 {
+					(
+						first_hop_channel.counterparty.features.to_context(),
+						first_hop_channel.is_announced,
+					)
+				} else {
+					(NodeFeatures::empty(), true)
+				};
+				LdkRouteHop {
+					pubkey: hop.node_id,
+					node_features,
+					short_channel_id: hop.short_channel_id,
+					channel_features: ChannelFeatures::empty(),
+					fee_msat: hop.fee_msat,
+					cltv_expiry_delta: hop.cltv_expiry_delta,
+					maybe_announced_channel,
+				}
+			})
+			.collect();
+		let ldk_route =
+			LdkRoute { paths: vec![LdkPath { hops, blinded_tail: None }], route_params: None };
+
+		let payment_secret = Some(*invoice.payment_secret());
+		let mut recipient_fields = RecipientOnionFields::secret_only(*invoice.payment_secret());
+		recipient_fields.payment_metadata = invoice.payment_metadata().cloned();
+
+		match self.channel_manager.send_payment_with_route(
+			ldk_route,
+			payment_hash,
+			recipient_fields,
+			payment_id,
+		) {
+			Ok(()) => {
+				log_info!(
+					self.logger,
+					"Initiated sending {} msat to {} along the given route",
+					amount_msat,
+					payee_pubkey
+				);
+
+				let kind = PaymentKind::Bolt11 {
+					hash: payment_hash,
+					preimage: None,
+					secret: payment_secret,
+				};
+
+				let payment = PaymentDetails::new(
+					payment_id,
+					kind,
+					Some(amount_msat),
+					None,
+					PaymentDirection::Outbound,
+					PaymentStatus::Pending,
+				);
+				self.payment_store.insert(payment)?;
+
+				Ok(payment_id)
+			},
+			Err(e) => {
+				log_error!(self.logger, "Failed to send payment: {:?}", e);
+				match e {
+					RetryableSendFailure::DuplicatePayment => Err(Error::DuplicatePayment),
+					_ => {
+						let kind = PaymentKind::Bolt11 {
+							hash: payment_hash,
+							preimage: None,
+							secret: payment_secret,
+						};
+						let payment = PaymentDetails::new(
+							payment_id,
+							kind,
+							Some(amount_msat),
+							None,
+							PaymentDirection::Outbound,
+							PaymentStatus::Failed,
+						);
+
+						self.payment_store.insert(payment)?;
+						Err(Error::PaymentSendingFailed)
+					},
+				}
+			},
+		}
+	}
+
 	/// Allows to attempt manually claiming payments with the given preimage that have previously
 	/// been registered via [`receive_for_hash`] or [`receive_variable_amount_for_hash`].
 	///
@@ -300,8 +675,16 @@ impl Bolt11Payment {
 	///
 	/// When claiming the payment has succeeded, a [`PaymentReceived`] event will be emitted.
 	///
+	/// **Note:** Claiming always settles the full `claimable_amount_msat`. If the payment was
+	/// split into multiple HTLCs (i.e., it's a multi-part payment), all of them are claimed at
+	/// once, as revealing the preimage for any of them allows the payer to prove payment of all.
+	/// It's therefore not possible to claim only part of the claimable amount and fail the
+	/// remainder back. If the claimable amount doesn't match your expectations, use
+	/// [`fail_for_hash`] to fail back the entire payment instead.
+	///
 	/// [`receive_for_hash`]: Self::receive_for_hash
 	/// [`receive_variable_amount_for_hash`]: Self::receive_variable_amount_for_hash
+	/// [`fail_for_hash`]: Self::fail_for_hash
 	/// [`PaymentClaimable`]: crate::Event::PaymentClaimable
 	/// [`PaymentReceived`]: crate::Event::PaymentReceived
 	pub fn claim_for_hash(
@@ -350,6 +733,7 @@ impl Bolt11Payment {
 		}
 
 		self.channel_manager.claim_funds(preimage);
+		self.claim_watchdog.unregister_claimable(&payment_hash);
 		Ok(())
 	}
 
@@ -396,6 +780,7 @@ impl Bolt11Payment {
 		}
 
 		self.channel_manager.fail_htlc_backwards(&payment_hash);
+		self.claim_watchdog.unregister_claimable(&payment_hash);
 		Ok(())
 	}
 
@@ -407,7 +792,88 @@ impl Bolt11Payment {
 		&self, amount_msat: u64, description: &Bolt11InvoiceDescription, expiry_secs: u32,
 	) -> Result<Bolt11Invoice, Error> {
 		let description = maybe_try_convert_enum(description)?;
-		let invoice = self.receive_inner(Some(amount_msat), &description, expiry_secs, None)?;
+		let invoice = self.receive_inner(
+			Some(amount_msat),
+			&description,
+			expiry_secs,
+			ReceiveParams::default(),
+		)?;
+		Ok(maybe_wrap(invoice))
+	}
+
+	/// Returns a payable invoice that can be used to request and receive a payment of the amount
+	/// given, committing to the SHA-256 hash of a description provided out of band rather than
+	/// including the description itself.
+	///
+	/// The hash is included as the invoice's `h` field. This is useful if the description is too
+	/// large to be included in the invoice, e.g., for LNURL-pay where the invoice has to commit to
+	/// the hash of the (potentially large) metadata.
+	///
+	/// The inbound payment will be automatically claimed upon arrival.
+	pub fn receive_with_description_hash(
+		&self, amount_msat: u64, description_hash: Sha256, expiry_secs: u32,
+	) -> Result<Bolt11Invoice, Error> {
+		let description = LdkBolt11InvoiceDescription::Hash(LdkSha256(description_hash));
+		let invoice = self.receive_inner(
+			Some(amount_msat),
+			&description,
+			expiry_secs,
+			ReceiveParams::default(),
+		)?;
+		Ok(maybe_wrap(invoice))
+	}
+
+	/// Returns a payable invoice that can be used to request and receive a payment of the amount
+	/// given, requiring the given `min_final_cltv_expiry_delta` rather than the configured
+	/// [`Config::min_final_cltv_expiry_delta`].
+	///
+	/// Will return an [`Error::InvoiceCreationFailed`] if the given delta is below LDK's minimum of
+	/// [`MIN_FINAL_CLTV_EXPIRY_DELTA`].
+	///
+	/// The inbound payment will be automatically claimed upon arrival.
+	///
+	/// [`Error::InvoiceCreationFailed`]: crate::NodeError::InvoiceCreationFailed
+	/// [`MIN_FINAL_CLTV_EXPIRY_DELTA`]: lightning::ln::channelmanager::MIN_FINAL_CLTV_EXPIRY_DELTA
+	pub fn receive_with_min_final_cltv_expiry_delta(
+		&self, amount_msat: u64, description: &Bolt11InvoiceDescription, expiry_secs: u32,
+		min_final_cltv_expiry_delta: u16,
+	) -> Result<Bolt11Invoice, Error> {
+		let description = maybe_try_convert_enum(description)?;
+		let invoice = self.receive_inner(
+			Some(amount_msat),
+			&description,
+			expiry_secs,
+			ReceiveParams {
+				min_final_cltv_expiry_delta: Some(min_final_cltv_expiry_delta),
+				..Default::default()
+			},
+		)?;
+		Ok(maybe_wrap(invoice))
+	}
+
+	/// Returns a payable invoice that can be used to request and receive a payment of the amount
+	/// given, only including route hints for the given channels.
+	///
+	/// This allows to steer the inbound payment towards specific channels, e.g., the ones with
+	/// sufficient inbound liquidity, overriding the configured
+	/// [`Config::invoice_route_hint_policy`]. At most three of the given channels are included,
+	/// preferring the ones with the highest inbound capacity. Will return an
+	/// [`Error::InvalidChannelId`] if none of the given channels is usable.
+	///
+	/// The inbound payment will be automatically claimed upon arrival.
+	///
+	/// [`Error::InvalidChannelId`]: crate::NodeError::InvalidChannelId
+	pub fn receive_with_route_hints(
+		&self, amount_msat: u64, description: &Bolt11InvoiceDescription, expiry_secs: u32,
+		include_channels: Vec<ChannelId>,
+	) -> Result<Bolt11Invoice, Error> {
+		let description = maybe_try_convert_enum(description)?;
+		let invoice = self.receive_inner(
+			Some(amount_msat),
+			&description,
+			expiry_secs,
+			ReceiveParams { route_hint_channels: Some(&include_channels), ..Default::default() },
+		)?;
 		Ok(maybe_wrap(invoice))
 	}
 
@@ -430,8 +896,45 @@ impl Bolt11Payment {
 		payment_hash: PaymentHash,
 	) -> Result<Bolt11Invoice, Error> {
 		let description = maybe_try_convert_enum(description)?;
-		let invoice =
-			self.receive_inner(Some(amount_msat), &description, expiry_secs, Some(payment_hash))?;
+		let invoice = self.receive_inner(
+			Some(amount_msat),
+			&description,
+			expiry_secs,
+			ReceiveParams { manual_claim_payment_hash: Some(payment_hash), ..Default::default() },
+		)?;
+		Ok(maybe_wrap(invoice))
+	}
+
+	/// Returns a payable invoice that can be used to request a payment of the amount
+	/// given for the given payment hash, failing the payment back if it isn't claimed in time.
+	///
+	/// This behaves like [`receive_for_hash`], but the inbound payment will be failed back
+	/// automatically if it isn't claimed via [`claim_for_hash`] within `claim_timeout_secs`
+	/// seconds after we emitted the [`PaymentClaimable`] event for it, independently of the
+	/// payment's `claim_deadline`. In this case, a [`PaymentAutoFailed`] event will be emitted.
+	///
+	/// **Note:** the timeout is tracked in memory only. If the node is restarted while the
+	/// payment is held, the timeout restarts once the [`PaymentClaimable`] event is replayed.
+	///
+	/// [`receive_for_hash`]: Self::receive_for_hash
+	/// [`claim_for_hash`]: Self::claim_for_hash
+	/// [`PaymentClaimable`]: crate::Event::PaymentClaimable
+	/// [`PaymentAutoFailed`]: crate::Event::PaymentAutoFailed
+	pub fn receive_for_hash_with_claim_timeout(
+		&self, amount_msat: u64, description: &Bolt11InvoiceDescription, expiry_secs: u32,
+		payment_hash: PaymentHash, claim_timeout_secs: u64,
+	) -> Result<Bolt11Invoice, Error> {
+		let description = maybe_try_convert_enum(description)?;
+		let invoice = self.receive_inner(
+			Some(amount_msat),
+			&description,
+			expiry_secs,
+			ReceiveParams {
+				manual_claim_payment_hash: Some(payment_hash),
+				claim_timeout_secs: Some(claim_timeout_secs),
+				..Default::default()
+			},
+		)?;
 		Ok(maybe_wrap(invoice))
 	}
 
@@ -443,7 +946,8 @@ impl Bolt11Payment {
 		&self, description: &Bolt11InvoiceDescription, expiry_secs: u32,
 	) -> Result<Bolt11Invoice, Error> {
 		let description = maybe_try_convert_enum(description)?;
-		let invoice = self.receive_inner(None, &description, expiry_secs, None)?;
+		let invoice =
+			self.receive_inner(None, &description, expiry_secs, ReceiveParams::default())?;
 		Ok(maybe_wrap(invoice))
 	}
 
@@ -465,19 +969,95 @@ impl Bolt11Payment {
 		&self, description: &Bolt11InvoiceDescription, expiry_secs: u32, payment_hash: PaymentHash,
 	) -> Result<Bolt11Invoice, Error> {
 		let description = maybe_try_convert_enum(description)?;
-		let invoice = self.receive_inner(None, &description, expiry_secs, Some(payment_hash))?;
+		let invoice = self.receive_inner(
+			None,
+			&description,
+			expiry_secs,
+			ReceiveParams { manual_claim_payment_hash: Some(payment_hash), ..Default::default() },
+		)?;
+		Ok(maybe_wrap(invoice))
+	}
+
+	/// Returns a payable invoice that can be used to request a payment for the given payment hash
+	/// and the amount to be determined by the user, also known as a "zero-amount" invoice, failing
+	/// the payment back if it isn't claimed in time.
+	///
+	/// This behaves like [`receive_variable_amount_for_hash`], but the inbound payment will be
+	/// failed back automatically if it isn't claimed via [`claim_for_hash`] within
+	/// `claim_timeout_secs` seconds after we emitted the [`PaymentClaimable`] event for it,
+	/// independently of the payment's `claim_deadline`. In this case, a [`PaymentAutoFailed`]
+	/// event will be emitted.
+	///
+	/// **Note:** the timeout is tracked in memory only. If the node is restarted while the
+	/// payment is held, the timeout restarts once the [`PaymentClaimable`] event is replayed.
+	///
+	/// [`receive_variable_amount_for_hash`]: Self::receive_variable_amount_for_hash
+	/// [`claim_for_hash`]: Self::claim_for_hash
+	/// [`PaymentClaimable`]: crate::Event::PaymentClaimable
+	/// [`PaymentAutoFailed`]: crate::Event::PaymentAutoFailed
+	pub fn receive_variable_amount_for_hash_with_claim_timeout(
+		&self, description: &Bolt11InvoiceDescription, expiry_secs: u32, payment_hash: PaymentHash,
+		claim_timeout_secs: u64,
+	) -> Result<Bolt11Invoice, Error> {
+		let description = maybe_try_convert_enum(description)?;
+		let invoice = self.receive_inner(
+			None,
+			&description,
+			expiry_secs,
+			ReceiveParams {
+				manual_claim_payment_hash: Some(payment_hash),
+				claim_timeout_secs: Some(claim_timeout_secs),
+				..Default::default()
+			},
+		)?;
 		Ok(maybe_wrap(invoice))
 	}
 
 	pub(crate) fn receive_inner(
 		&self, amount_msat: Option<u64>, invoice_description: &LdkBolt11InvoiceDescription,
-		expiry_secs: u32, manual_claim_payment_hash: Option<PaymentHash>,
+		expiry_secs: u32, params: ReceiveParams,
 	) -> Result<LdkBolt11Invoice, Error> {
-		let invoice = {
+		let ReceiveParams {
+			manual_claim_payment_hash,
+			claim_timeout_secs,
+			route_hint_channels,
+			min_final_cltv_expiry_delta,
+		} = params;
+
+		debug_assert!(
+			claim_timeout_secs.is_none() || manual_claim_payment_hash.is_some(),
+			"A claim timeout only applies to manually-claimable payments"
+		);
+
+		let min_final_cltv_expiry_delta =
+			min_final_cltv_expiry_delta.unwrap_or(self.config.min_final_cltv_expiry_delta);
+		if min_final_cltv_expiry_delta < MIN_FINAL_CLTV_EXPIRY_DELTA {
+			log_error!(
+				self.logger,
+				"Failed to create invoice as the min_final_cltv_expiry_delta of {} is below the minimum of {}",
+				min_final_cltv_expiry_delta,
+				MIN_FINAL_CLTV_EXPIRY_DELTA
+			);
+			return Err(Error::InvoiceCreationFailed);
+		}
+
+		let invoice = if let Some(route_hints) = self.select_route_hints(route_hint_channels)? {
+			let invoice = self.create_invoice_with_route_hints(
+				amount_msat,
+				invoice_description,
+				expiry_secs,
+				manual_claim_payment_hash,
+				route_hints,
+				min_final_cltv_expiry_delta,
+			)?;
+			log_info!(self.logger, "Invoice created: {}", invoice);
+			invoice
+		} else {
 			let invoice_params = Bolt11InvoiceParameters {
 				amount_msats: amount_msat,
 				description: invoice_description.clone(),
 				invoice_expiry_delta_secs: Some(expiry_secs),
+				min_final_cltv_expiry_delta: Some(min_final_cltv_expiry_delta),
 				payment_hash: manual_claim_payment_hash,
 				..Default::default()
 			};
@@ -514,7 +1094,7 @@ impl Bolt11Payment {
 			preimage,
 			secret: Some(payment_secret.clone()),
 		};
-		let payment = PaymentDetails::new(
+		let mut payment = PaymentDetails::new(
 			id,
 			kind,
 			amount_msat,
@@ -522,31 +1102,156 @@ impl Bolt11Payment {
 			PaymentDirection::Inbound,
 			PaymentStatus::Pending,
 		);
+		payment.claim_timeout_secs = claim_timeout_secs;
 		self.payment_store.insert(payment)?;
 
 		Ok(invoice)
 	}
 
+	// Returns the route hints to include in an invoice, or `None` if we should leave their
+	// selection to LDK.
+	fn select_route_hints(
+		&self, route_hint_channels: Option<&[ChannelId]>,
+	) -> Result<Option<Vec<RouteHint>>, Error> {
+		let channel_ids = match (route_hint_channels, &self.config.invoice_route_hint_policy) {
+			(Some(channel_ids), _) => Some(channel_ids),
+			(None, InvoiceRouteHintPolicy::AllUsable) => return Ok(None),
+			(None, InvoiceRouteHintPolicy::HighestInbound) => None,
+			(None, InvoiceRouteHintPolicy::Explicit { channel_ids }) => Some(&channel_ids[..]),
+		};
+
+		let mut channels = self
+			.channel_manager
+			.list_usable_channels()
+			.into_iter()
+			.filter(|c| channel_ids.map_or(true, |ids| ids.contains(&c.channel_id)))
+			.collect::<Vec<_>>();
+		channels.sort_by_key(|c| std::cmp::Reverse(c.inbound_capacity_msat));
+
+		let route_hints = channels
+			.into_iter()
+			.filter_map(|channel| {
+				let short_channel_id = channel.get_inbound_payment_scid()?;
+				let forwarding_info = channel.counterparty.forwarding_info?;
+				Some(RouteHint(vec![RouteHintHop {
+					src_node_id: channel.counterparty.node_id,
+					short_channel_id,
+					fees: RoutingFees {
+						base_msat: forwarding_info.fee_base_msat,
+						proportional_millionths: forwarding_info.fee_proportional_millionths,
+					},
+					cltv_expiry_delta: forwarding_info.cltv_expiry_delta,
+					htlc_minimum_msat: channel.inbound_htlc_minimum_msat,
+					htlc_maximum_msat: channel.inbound_htlc_maximum_msat,
+				}]))
+			})
+			.take(MAX_INVOICE_ROUTE_HINTS)
+			.collect::<Vec<_>>();
+
+		if route_hints.is_empty() {
+			if channel_ids.is_some() {
+				log_error!(
+					self.logger,
+					"Failed to create invoice as none of the channels to include as route hints is usable"
+				);
+				return Err(Error::InvalidChannelId);
+			}
+			// Fall back to LDK's selection if we don't have any usable channels yet.
+			return Ok(None);
+		}
+
+		Ok(Some(route_hints))
+	}
+
+	fn create_invoice_with_route_hints(
+		&self, amount_msat: Option<u64>, invoice_description: &LdkBolt11InvoiceDescription,
+		expiry_secs: u32, manual_claim_payment_hash: Option<PaymentHash>,
+		route_hints: Vec<RouteHint>, min_final_cltv_expiry_delta: u16,
+	) -> Result<LdkBolt11Invoice, Error> {
+		let (payment_hash, payment_secret) = match manual_claim_payment_hash {
+			Some(payment_hash) => {
+				let payment_secret = self
+					.channel_manager
+					.create_inbound_payment_for_hash(
+						payment_hash,
+						amount_msat,
+						expiry_secs,
+						Some(min_final_cltv_expiry_delta),
+					)
+					.map_err(|e| {
+						log_error!(self.logger, "Failed to register inbound payment: {:?}", e);
+						Error::InvoiceCreationFailed
+					})?;
+				(payment_hash, payment_secret)
+			},
+			None => self
+				.channel_manager
+				.create_inbound_payment(amount_msat, expiry_secs, Some(min_final_cltv_expiry_delta))
+				.map_err(|e| {
+					log_error!(self.logger, "Failed to register inbound payment: {:?}", e);
+					Error::InvoiceCreationFailed
+				})?,
+		};
+
+		let currency = self.config.network.into();
+		let mut invoice_builder = InvoiceBuilder::new(currency)
+			.invoice_description(invoice_description.clone())
+			.payment_hash(Sha256::from_byte_array(payment_hash.0))
+			.payment_secret(payment_secret)
+			.current_timestamp()
+			.min_final_cltv_expiry_delta(min_final_cltv_expiry_delta.into())
+			.expiry_time(Duration::from_secs(expiry_secs.into()))
+			.basic_mpp();
+
+		if let Some(amount_msat) = amount_msat {
+			invoice_builder = invoice_builder.amount_milli_satoshis(amount_msat);
+		}
+
+		for route_hint in route_hints {
+			invoice_builder = invoice_builder.private_route(route_hint);
+		}
+
+		let raw_invoice = invoice_builder.build_raw().map_err(|e| {
+			log_error!(self.logger, "Failed to build invoice: {}", e);
+			Error::InvoiceCreationFailed
+		})?;
+		let signature =
+			self.keys_manager.sign_invoice(&raw_invoice, Recipient::Node).map_err(|()| {
+				log_error!(self.logger, "Failed to sign invoice");
+				Error::InvoiceCreationFailed
+			})?;
+		let signed_raw_invoice = raw_invoice
+			.sign(|_| Ok::<_, ()>(signature))
+			.map_err(|()| Error::InvoiceCreationFailed)?;
+		LdkBolt11Invoice::from_signed(signed_raw_invoice).map_err(|e| {
+			log_error!(self.logger, "Failed to create signed invoice: {}", e);
+			Error::InvoiceCreationFailed
+		})
+	}
+
 	/// Returns a payable invoice that can be used to request a payment of the amount given and
 	/// receive it via a newly created just-in-time (JIT) channel.
 	///
 	/// When the returned invoice is paid, the configured [LSPS2]-compliant LSP will open a channel
 	/// to us, supplying just-in-time inbound liquidity.
 	///
-	/// If set, `max_total_lsp_fee_limit_msat` will limit how much fee we allow the LSP to take for opening the
-	/// channel to us. We'll use its cheapest offer otherwise.
+	/// We'll use the LSP's cheapest offer. If set, `fee_limits` will limit how much fee we allow
+	/// the LSP to take for opening the channel to us, i.e., we'll return an
+	/// [`Error::LspFeeExceedsLimit`] if the offer exceeds any of them.
 	///
 	/// [LSPS2]: https://github.com/BitcoinAndLightningLayerSpecs/lsp/blob/main/LSPS2/README.md
+	/// [`Error::LspFeeExceedsLimit`]: crate::NodeError::LspFeeExceedsLimit
 	pub fn receive_via_jit_channel(
 		&self, amount_msat: u64, description: &Bolt11InvoiceDescription, expiry_secs: u32,
-		max_total_lsp_fee_limit_msat: Option<u64>,
+		fee_limits: Option<JitChannelFeeLimits>,
 	) -> Result<Bolt11Invoice, Error> {
 		let description = maybe_try_convert_enum(description)?;
 		let invoice = self.receive_via_jit_channel_inner(
 			Some(amount_msat),
 			&description,
 			expiry_secs,
-			max_total_lsp_fee_limit_msat,
+			None,
+			fee_limits.unwrap_or_default(),
 			None,
 			None,
 		)?;
@@ -559,8 +1264,9 @@ impl Bolt11Payment {
 	/// When the returned invoice is paid, the configured [LSPS2]-compliant LSP will open a channel
 	/// to us, supplying just-in-time inbound liquidity.
 	///
-	/// If set, `max_total_lsp_fee_limit_msat` will limit how much fee we allow the LSP to take for opening the
-	/// channel to us. We'll use its cheapest offer otherwise.
+	/// We'll use the LSP's cheapest offer. If set, `fee_limits` will limit how much fee we allow
+	/// the LSP to take for opening the channel to us, i.e., we'll return an
+	/// [`Error::LspFeeExceedsLimit`] if the offer exceeds any of them.
 	///
 	/// We will register the given payment hash and emit a [`PaymentClaimable`] event once
 	/// the inbound payment arrives. The check that [`counterparty_skimmed_fee_msat`] is within the limits
@@ -576,18 +1282,20 @@ impl Bolt11Payment {
 	/// [`claim_for_hash`]: Self::claim_for_hash
 	/// [`fail_for_hash`]: Self::fail_for_hash
 	/// [`counterparty_skimmed_fee_msat`]: crate::payment::PaymentKind::Bolt11Jit::counterparty_skimmed_fee_msat
+	/// [`Error::LspFeeExceedsLimit`]: crate::NodeError::LspFeeExceedsLimit
 	pub fn receive_via_jit_channel_for_hash(
 		&self, amount_msat: u64, description: &Bolt11InvoiceDescription, expiry_secs: u32,
-		max_total_lsp_fee_limit_msat: Option<u64>, payment_hash: PaymentHash,
+		fee_limits: Option<JitChannelFeeLimits>, payment_hash: PaymentHash,
 	) -> Result<Bolt11Invoice, Error> {
 		let description = maybe_try_convert_enum(description)?;
 		let invoice = self.receive_via_jit_channel_inner(
 			Some(amount_msat),
 			&description,
 			expiry_secs,
-			max_total_lsp_fee_limit_msat,
 			None,
+			fee_limits.unwrap_or_default(),
 			Some(payment_hash),
+			None,
 		)?;
 		Ok(maybe_wrap(invoice))
 	}
@@ -598,22 +1306,29 @@ impl Bolt11Payment {
 	/// When the returned invoice is paid, the configured [LSPS2]-compliant LSP will open a channel
 	/// to us, supplying just-in-time inbound liquidity.
 	///
-	/// If set, `max_proportional_lsp_fee_limit_ppm_msat` will limit how much proportional fee, in
-	/// parts-per-million millisatoshis, we allow the LSP to take for opening the channel to us.
-	/// We'll use its cheapest offer otherwise.
+	/// If set, `min_amount_msat` will be the minimum amount we accept, and we'll only consider LSP
+	/// offers that allow payments of at least this size. The minimum will be communicated to the
+	/// payer as the minimum HTLC amount of the invoice's route hint via the LSP. If the LSP
+	/// requires a higher minimum payment size, the latter will be used instead.
+	///
+	/// We'll use the LSP's cheapest offer. If set, `fee_limits` will limit how much fee we allow
+	/// the LSP to take for opening the channel to us, i.e., we'll return an
+	/// [`Error::LspFeeExceedsLimit`] if the offer exceeds any of them.
 	///
 	/// [LSPS2]: https://github.com/BitcoinAndLightningLayerSpecs/lsp/blob/main/LSPS2/README.md
+	/// [`Error::LspFeeExceedsLimit`]: crate::NodeError::LspFeeExceedsLimit
 	pub fn receive_variable_amount_via_jit_channel(
 		&self, description: &Bolt11InvoiceDescription, expiry_secs: u32,
-		max_proportional_lsp_fee_limit_ppm_msat: Option<u64>,
+		min_amount_msat: Option<u64>, fee_limits: Option<JitChannelFeeLimits>,
 	) -> Result<Bolt11Invoice, Error> {
 		let description = maybe_try_convert_enum(description)?;
 		let invoice = self.receive_via_jit_channel_inner(
 			None,
 			&description,
 			expiry_secs,
+			min_amount_msat,
+			fee_limits.unwrap_or_default(),
 			None,
-			max_proportional_lsp_fee_limit_ppm_msat,
 			None,
 		)?;
 		Ok(maybe_wrap(invoice))
@@ -625,9 +1340,14 @@ impl Bolt11Payment {
 	/// When the returned invoice is paid, the configured [LSPS2]-compliant LSP will open a channel
 	/// to us, supplying just-in-time inbound liquidity.
 	///
-	/// If set, `max_proportional_lsp_fee_limit_ppm_msat` will limit how much proportional fee, in
-	/// parts-per-million millisatoshis, we allow the LSP to take for opening the channel to us.
-	/// We'll use its cheapest offer otherwise.
+	/// If set, `min_amount_msat` will be the minimum amount we accept, and we'll only consider LSP
+	/// offers that allow payments of at least this size. The minimum will be communicated to the
+	/// payer as the minimum HTLC amount of the invoice's route hint via the LSP. If the LSP
+	/// requires a higher minimum payment size, the latter will be used instead.
+	///
+	/// We'll use the LSP's cheapest offer. If set, `fee_limits` will limit how much fee we allow
+	/// the LSP to take for opening the channel to us, i.e., we'll return an
+	/// [`Error::LspFeeExceedsLimit`] if the offer exceeds any of them.
 	///
 	/// We will register the given payment hash and emit a [`PaymentClaimable`] event once
 	/// the inbound payment arrives. The check that [`counterparty_skimmed_fee_msat`] is within the limits
@@ -643,26 +1363,100 @@ impl Bolt11Payment {
 	/// [`claim_for_hash`]: Self::claim_for_hash
 	/// [`fail_for_hash`]: Self::fail_for_hash
 	/// [`counterparty_skimmed_fee_msat`]: crate::payment::PaymentKind::Bolt11Jit::counterparty_skimmed_fee_msat
+	/// [`Error::LspFeeExceedsLimit`]: crate::NodeError::LspFeeExceedsLimit
 	pub fn receive_variable_amount_via_jit_channel_for_hash(
 		&self, description: &Bolt11InvoiceDescription, expiry_secs: u32,
-		max_proportional_lsp_fee_limit_ppm_msat: Option<u64>, payment_hash: PaymentHash,
+		min_amount_msat: Option<u64>, fee_limits: Option<JitChannelFeeLimits>,
+		payment_hash: PaymentHash,
 	) -> Result<Bolt11Invoice, Error> {
 		let description = maybe_try_convert_enum(description)?;
 		let invoice = self.receive_via_jit_channel_inner(
 			None,
 			&description,
 			expiry_secs,
-			None,
-			max_proportional_lsp_fee_limit_ppm_msat,
+			min_amount_msat,
+			fee_limits.unwrap_or_default(),
 			Some(payment_hash),
+			None,
+		)?;
+		Ok(maybe_wrap(invoice))
+	}
+
+	/// Returns a payable invoice that can be used to request a payment of the amount given and
+	/// receive it via a newly created just-in-time (JIT) channel, using the given opening fee
+	/// parameters.
+	///
+	/// When the returned invoice is paid, the configured [LSPS2]-compliant LSP will open a channel
+	/// to us, supplying just-in-time inbound liquidity.
+	///
+	/// The `opening_fee_params` need to be chosen from the ones retrieved via
+	/// [`LSPS2Liquidity::get_opening_params`] and must not have expired yet. The LSP will
+	/// withhold the opening fee resulting from them from the payment. If set, `fee_limits` will
+	/// limit how much fee we allow the LSP to take, i.e., we'll return an
+	/// [`Error::LspFeeExceedsLimit`] if the given parameters exceed any of them.
+	///
+	/// [LSPS2]: https://github.com/BitcoinAndLightningLayerSpecs/lsp/blob/main/LSPS2/README.md
+	/// [`LSPS2Liquidity::get_opening_params`]: crate::liquidity::LSPS2Liquidity::get_opening_params
+	/// [`Error::LspFeeExceedsLimit`]: crate::NodeError::LspFeeExceedsLimit
+	pub fn receive_via_jit_channel_with_opening_params(
+		&self, amount_msat: u64, description: &Bolt11InvoiceDescription, expiry_secs: u32,
+		opening_fee_params: LSPS2OpeningFeeParams, fee_limits: Option<JitChannelFeeLimits>,
+	) -> Result<Bolt11Invoice, Error> {
+		let description = maybe_try_convert_enum(description)?;
+		let invoice = self.receive_via_jit_channel_inner(
+			Some(amount_msat),
+			&description,
+			expiry_secs,
+			None,
+			fee_limits.unwrap_or_default(),
+			None,
+			Some(opening_fee_params),
+		)?;
+		Ok(maybe_wrap(invoice))
+	}
+
+	/// Returns a payable invoice that can be used to request a variable amount payment (also known
+	/// as "zero-amount" invoice) and receive it via a newly created just-in-time (JIT) channel,
+	/// using the given opening fee parameters.
+	///
+	/// When the returned invoice is paid, the configured [LSPS2]-compliant LSP will open a channel
+	/// to us, supplying just-in-time inbound liquidity.
+	///
+	/// The `opening_fee_params` need to be chosen from the ones retrieved via
+	/// [`LSPS2Liquidity::get_opening_params`] and must not have expired yet. The LSP will
+	/// withhold the opening fee resulting from them from the payment. If set, `fee_limits` will
+	/// limit how much fee we allow the LSP to take, i.e., we'll return an
+	/// [`Error::LspFeeExceedsLimit`] if the given parameters exceed any of them.
+	///
+	/// If set, `min_amount_msat` will be the minimum amount we accept, see
+	/// [`receive_variable_amount_via_jit_channel`] for details.
+	///
+	/// [LSPS2]: https://github.com/BitcoinAndLightningLayerSpecs/lsp/blob/main/LSPS2/README.md
+	/// [`LSPS2Liquidity::get_opening_params`]: crate::liquidity::LSPS2Liquidity::get_opening_params
+	/// [`Error::LspFeeExceedsLimit`]: crate::NodeError::LspFeeExceedsLimit
+	/// [`receive_variable_amount_via_jit_channel`]: Self::receive_variable_amount_via_jit_channel
+	pub fn receive_variable_amount_via_jit_channel_with_opening_params(
+		&self, description: &Bolt11InvoiceDescription, expiry_secs: u32,
+		min_amount_msat: Option<u64>, opening_fee_params: LSPS2OpeningFeeParams,
+		fee_limits: Option<JitChannelFeeLimits>,
+	) -> Result<Bolt11Invoice, Error> {
+		let description = maybe_try_convert_enum(description)?;
+		let invoice = self.receive_via_jit_channel_inner(
+			None,
+			&description,
+			expiry_secs,
+			min_amount_msat,
+			fee_limits.unwrap_or_default(),
+			None,
+			Some(opening_fee_params),
 		)?;
 		Ok(maybe_wrap(invoice))
 	}
 
 	fn receive_via_jit_channel_inner(
 		&self, amount_msat: Option<u64>, description: &LdkBolt11InvoiceDescription,
-		expiry_secs: u32, max_total_lsp_fee_limit_msat: Option<u64>,
-		max_proportional_lsp_fee_limit_ppm_msat: Option<u64>, payment_hash: Option<PaymentHash>,
+		expiry_secs: u32, min_amount_msat: Option<u64>, fee_limits: JitChannelFeeLimits,
+		payment_hash: Option<PaymentHash>, opening_fee_params: Option<LSPS2OpeningFeeParams>,
 	) -> Result<LdkBolt11Invoice, Error> {
 		let liquidity_source =
 			self.liquidity_source.as_ref().ok_or(Error::LiquiditySourceUnavailable)?;
@@ -670,7 +1464,7 @@ impl Bolt11Payment {
 		let (node_id, address) =
 			liquidity_source.get_lsps2_lsp_details().ok_or(Error::LiquiditySourceUnavailable)?;
 
-		let peer_info = PeerInfo { node_id, address };
+		let peer_info = PeerInfo::new(node_id, address);
 
 		let con_node_id = peer_info.node_id;
 		let con_addr = peer_info.address.clone();
@@ -693,8 +1487,9 @@ impl Bolt11Payment {
 							amount_msat,
 							description,
 							expiry_secs,
-							max_total_lsp_fee_limit_msat,
+							fee_limits,
 							payment_hash,
+							opening_fee_params,
 						)
 						.await
 						.map(|(invoice, total_fee)| (invoice, Some(total_fee), None))
@@ -703,8 +1498,10 @@ impl Bolt11Payment {
 						.lsps2_receive_variable_amount_to_jit_channel(
 							description,
 							expiry_secs,
-							max_proportional_lsp_fee_limit_ppm_msat,
+							min_amount_msat,
+							fee_limits,
 							payment_hash,
+							opening_fee_params,
 						)
 						.await
 						.map(|(invoice, prop_fee)| (invoice, None, Some(prop_fee)))
@@ -744,6 +1541,51 @@ impl Bolt11Payment {
 		Ok(invoice)
 	}
 
+	/// Estimates the fee of paying the given invoice without actually sending the payment.
+	///
+	/// Runs pathfinding for the invoice's amount, respecting the node-wide parameters configured
+	/// via [`Config::route_parameters`], and returns the expected routing fee as well as the shape
+	/// of the route found. Will return [`Error::RouteNotFound`] if no route to the payee could be
+	/// found.
+	///
+	/// Note that the fee actually paid may differ, e.g., if channel balances change or if a path
+	/// fails and the payment is retried over a different route.
+	pub fn estimate_fee(&self, invoice: &Bolt11Invoice) -> Result<FeeEstimate, Error> {
+		if !*self.is_running.read().unwrap() {
+			return Err(Error::NotRunning);
+		}
+
+		let invoice = maybe_deref(invoice);
+		let amount_msat = invoice.amount_milli_satoshis().ok_or_else(|| {
+			log_error!(
+				self.logger,
+				"Failed to estimate fee due to the given invoice being \"zero-amount\"."
+			);
+			Error::InvalidInvoice
+		})?;
+
+		let payment_params = PaymentParameters::from_bolt11_invoice(invoice);
+		let route_params =
+			SendPaymentParams::default().route_params(&self.config, payment_params, amount_msat);
+
+		let first_hops = self.channel_manager.list_usable_channels();
+		let first_hops = first_hops.iter().collect::<Vec<_>>();
+		let route = self
+			.router
+			.find_route(
+				&self.channel_manager.get_our_node_id(),
+				&route_params,
+				Some(&first_hops),
+				self.channel_manager.compute_inflight_htlcs(),
+			)
+			.map_err(|e| {
+				log_error!(self.logger, "Failed to find a route to estimate fee: {}", e);
+				Error::RouteNotFound
+			})?;
+
+		Ok(FeeEstimate::from_route(&route))
+	}
+
 	/// Sends payment probes over all paths of a route that would be used to pay the given invoice.
 	///
 	/// This may be used to send "pre-flight" probes, i.e., to train our scorer before conducting
@@ -758,8 +1600,16 @@ impl Bolt11Payment {
 	/// amount times [`Config::probing_liquidity_limit_multiplier`] won't be used to send
 	/// pre-flight probes.
 	///
-	/// If `route_parameters` are provided they will override the default as well as the
-	/// node-wide parameters configured via [`Config::route_parameters`] on a per-field basis.
+	/// Probes use an unknown payment hash, so they never settle. The outcome of each probed path
+	/// is reported via [`Event::ProbeSuccessful`] or [`Event::ProbeFailed`]. Probes will not show
+	/// up in [`Node::list_payments`].
+	///
+	/// [`Event::ProbeSuccessful`]: crate::Event::ProbeSuccessful
+	/// [`Event::ProbeFailed`]: crate::Event::ProbeFailed
+	/// [`Node::list_payments`]: crate::Node::list_payments
+	///
+	/// If `route_parameters` are provided they will be used instead of the node-wide parameters
+	/// configured via [`Config::route_parameters`], i.e., they aren't merged on a per-field basis.
 	pub fn send_probes(
 		&self, invoice: &Bolt11Invoice, route_parameters: Option<RouteParametersConfig>,
 	) -> Result<(), Error> {
@@ -810,8 +1660,8 @@ impl Bolt11Payment {
 	/// This can be used to send pre-flight probes for a so-called "zero-amount" invoice, i.e., an
 	/// invoice that leaves the amount paid to be determined by the user.
 	///
-	/// If `route_parameters` are provided they will override the default as well as the
-	/// node-wide parameters configured via [`Config::route_parameters`] on a per-field basis.
+	/// If `route_parameters` are provided they will be used instead of the node-wide parameters
+	/// configured via [`Config::route_parameters`], i.e., they aren't merged on a per-field basis.
 	///
 	/// See [`Self::send_probes`] for more information.
 	pub fn send_probes_using_amount(
@@ -10,25 +10,37 @@
 //! [BOLT 12]: https://github.com/lightning/bolts/blob/master/12-offer-encoding.md
 
 use std::num::NonZeroU64;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use lightning::blinded_path::message::BlindedMessagePath;
+use lightning::blinded_path::IntroductionNode;
 use lightning::ln::channelmanager::{OptionalOfferPaymentParams, PaymentId, Retry};
+use lightning::offers::invoice_request::PAYER_NOTE_LIMIT;
 use lightning::offers::offer::{Amount, Offer as LdkOffer, Quantity};
 use lightning::offers::parse::Bolt12SemanticError;
+use lightning::offers::refund::Refund as LdkRefund;
+use lightning::onion_message::messenger::NullMessageRouter;
 use lightning::routing::router::RouteParametersConfig;
 #[cfg(feature = "uniffi")]
 use lightning::util::ser::{Readable, Writeable};
 use lightning_types::string::UntrustedString;
 use rand::RngCore;
 
-use crate::config::{AsyncPaymentsRole, LDK_PAYMENT_RETRY_TIMEOUT};
+use crate::config::{
+	AsyncPaymentsRole, DuplicatePaymentBehavior, BOLT12_INVOICE_REQUEST_TIMEOUT,
+	LDK_PAYMENT_RETRY_TIMEOUT,
+};
 use crate::error::Error;
 use crate::ffi::{maybe_deref, maybe_wrap};
+use crate::io::utils::write_default_offer;
 use crate::logger::{log_error, log_info, LdkLogger, Logger};
+use crate::payment::idempotency::PaymentIdempotencyKeys;
 use crate::payment::store::{PaymentDetails, PaymentDirection, PaymentKind, PaymentStatus};
-use crate::types::{ChannelManager, PaymentStore};
+use crate::runtime::Runtime;
+use crate::types::{
+	ChannelManager, DynStore, PaymentStore, PendingBolt12Invoice, PendingBolt12Invoices,
+};
 
 #[cfg(not(feature = "uniffi"))]
 type Bolt12Invoice = lightning::offers::invoice::Bolt12Invoice;
@@ -54,6 +66,11 @@ type Refund = Arc<crate::ffi::Refund>;
 pub struct Bolt12Payment {
 	channel_manager: Arc<ChannelManager>,
 	payment_store: Arc<PaymentStore>,
+	idempotency_keys: Arc<PaymentIdempotencyKeys>,
+	default_offer: Arc<Mutex<Option<LdkOffer>>>,
+	kv_store: Arc<DynStore>,
+	pending_bolt12_invoices: Arc<PendingBolt12Invoices>,
+	runtime: Arc<Runtime>,
 	is_running: Arc<RwLock<bool>>,
 	logger: Arc<Logger>,
 	async_payments_role: Option<AsyncPaymentsRole>,
@@ -62,10 +79,23 @@ pub struct Bolt12Payment {
 impl Bolt12Payment {
 	pub(crate) fn new(
 		channel_manager: Arc<ChannelManager>, payment_store: Arc<PaymentStore>,
-		is_running: Arc<RwLock<bool>>, logger: Arc<Logger>,
+		idempotency_keys: Arc<PaymentIdempotencyKeys>, default_offer: Arc<Mutex<Option<LdkOffer>>>,
+		kv_store: Arc<DynStore>, pending_bolt12_invoices: Arc<PendingBolt12Invoices>,
+		runtime: Arc<Runtime>, is_running: Arc<RwLock<bool>>, logger: Arc<Logger>,
 		async_payments_role: Option<AsyncPaymentsRole>,
 	) -> Self {
-		Self { channel_manager, payment_store, is_running, logger, async_payments_role }
+		Self {
+			channel_manager,
+			payment_store,
+			idempotency_keys,
+			default_offer,
+			kv_store,
+			pending_bolt12_invoices,
+			runtime,
+			is_running,
+			logger,
+			async_payments_role,
+		}
 	}
 
 	/// Send a payment given an offer.
@@ -76,16 +106,23 @@ impl Bolt12Payment {
 	/// If `quantity` is `Some` it represents the number of items requested.
 	pub fn send(
 		&self, offer: &Offer, quantity: Option<u64>, payer_note: Option<String>,
+	) -> Result<PaymentId, Error> {
+		let mut random_bytes = [0u8; 32];
+		rand::thread_rng().fill_bytes(&mut random_bytes);
+		let payment_id = PaymentId(random_bytes);
+		self.send_inner(maybe_deref(offer), quantity, payer_note, payment_id)
+	}
+
+	fn send_inner(
+		&self, offer: &LdkOffer, quantity: Option<u64>, payer_note: Option<String>,
+		payment_id: PaymentId,
 	) -> Result<PaymentId, Error> {
 		if !*self.is_running.read().unwrap() {
 			return Err(Error::NotRunning);
 		}
 
-		let offer = maybe_deref(offer);
+		self.validate_offer_payment_params(offer, quantity, payer_note.as_deref())?;
 
-		let mut random_bytes = [0u8; 32];
-		rand::thread_rng().fill_bytes(&mut random_bytes);
-		let payment_id = PaymentId(random_bytes);
 		let retry_strategy = Retry::Timeout(LDK_PAYMENT_RETRY_TIMEOUT);
 		let route_params_config = RouteParametersConfig::default();
 
@@ -183,16 +220,31 @@ impl Bolt12Payment {
 	/// response.
 	pub fn send_using_amount(
 		&self, offer: &Offer, amount_msat: u64, quantity: Option<u64>, payer_note: Option<String>,
+	) -> Result<PaymentId, Error> {
+		let mut random_bytes = [0u8; 32];
+		rand::thread_rng().fill_bytes(&mut random_bytes);
+		let payment_id = PaymentId(random_bytes);
+		self.send_using_amount_inner(
+			maybe_deref(offer),
+			amount_msat,
+			quantity,
+			payer_note,
+			payment_id,
+		)
+	}
+
+	/// Sends a payment with the given [`PaymentId`], allowing callers to persist the ID before
+	/// initiating the payment.
+	pub(crate) fn send_using_amount_inner(
+		&self, offer: &LdkOffer, amount_msat: u64, quantity: Option<u64>,
+		payer_note: Option<String>, payment_id: PaymentId,
 	) -> Result<PaymentId, Error> {
 		if !*self.is_running.read().unwrap() {
 			return Err(Error::NotRunning);
 		}
 
-		let offer = maybe_deref(offer);
+		self.validate_offer_payment_params(offer, quantity, payer_note.as_deref())?;
 
-		let mut random_bytes = [0u8; 32];
-		rand::thread_rng().fill_bytes(&mut random_bytes);
-		let payment_id = PaymentId(random_bytes);
 		let retry_strategy = Retry::Timeout(LDK_PAYMENT_RETRY_TIMEOUT);
 		let route_params_config = RouteParametersConfig::default();
 
@@ -288,6 +340,320 @@ impl Bolt12Payment {
 		}
 	}
 
+	/// Send a payment given an offer, allowing to specify all optional parameters at once.
+	///
+	/// If `amount_msat` is `Some`, it overrides the amount required by the offer, which is
+	/// necessary to pay so-called "zero-amount" offers. Otherwise, this behaves like [`send`].
+	///
+	/// If `quantity` is `Some` it represents the number of items requested and needs to be within
+	/// the bounds supported by the offer.
+	///
+	/// If `payer_note` is `Some` it will be seen by the recipient, e.g., to correlate the payment
+	/// with an order, and reflected back in the invoice response. It may be at most
+	/// [`PAYER_NOTE_LIMIT`] bytes long.
+	///
+	/// If `idempotency_key` is `Some` and a payment was previously sent with the same key, we
+	/// won't dispatch another payment but return the [`PaymentId`] of the existing one instead, or
+	/// [`Error::DuplicateIdempotencyKey`] if it failed. See [`SendPaymentParams::idempotency_key`]
+	/// and [`DuplicatePaymentBehavior::ReturnExisting`] for details.
+	///
+	/// [`send`]: Self::send
+	/// [`SendPaymentParams::idempotency_key`]: crate::config::SendPaymentParams::idempotency_key
+	/// [`Error::DuplicateIdempotencyKey`]: crate::Error::DuplicateIdempotencyKey
+	pub fn send_offer_with_options(
+		&self, offer: &Offer, amount_msat: Option<u64>, quantity: Option<u64>,
+		payer_note: Option<String>, idempotency_key: Option<String>,
+	) -> Result<PaymentId, Error> {
+		let offer = maybe_deref(offer);
+		let mut random_bytes = [0u8; 32];
+		rand::thread_rng().fill_bytes(&mut random_bytes);
+		let payment_id = PaymentId(random_bytes);
+		let send = || match amount_msat {
+			Some(amount_msat) => {
+				self.send_using_amount_inner(offer, amount_msat, quantity, payer_note, payment_id)
+			},
+			None => self.send_inner(offer, quantity, payer_note, payment_id),
+		};
+		let on_duplicate = DuplicatePaymentBehavior::ReturnExisting;
+		self.idempotency_keys.send(idempotency_key, on_duplicate, payment_id, send)
+	}
+
+	/// Requests an invoice for the given offer without paying it, returning the received
+	/// [`Bolt12Invoice`] for inspection.
+	///
+	/// This allows to show the final amount, e.g., including any fees set by the merchant, to the
+	/// user before committing to the payment. The returned invoice can then be paid via
+	/// [`pay_bolt12_invoice`].
+	///
+	/// If `amount_msat` is `Some`, it overrides the amount required by the offer, which is
+	/// necessary to request invoices for so-called "zero-amount" offers. If `quantity` is `Some` it
+	/// represents the number of items requested.
+	///
+	/// Blocks until the invoice is received. Will return an [`Error::OfferExpired`] if the offer
+	/// has expired, an [`Error::InvoiceRequestFailed`] if the recipient rejected our request, and
+	/// an [`Error::InvoiceRequestTimeout`] if the recipient didn't respond in time, e.g., as it is
+	/// unreachable.
+	///
+	/// This requires [`Config::enable_bolt12_invoice_requests`] to be set, otherwise an
+	/// [`Error::InvoiceRequestsDisabled`] is returned.
+	///
+	/// **Note:** Invoices not paid via [`pay_bolt12_invoice`] are forgotten once they expire, or
+	/// when the node is restarted.
+	///
+	/// [`pay_bolt12_invoice`]: Self::pay_bolt12_invoice
+	/// [`Bolt12Invoice`]: lightning::offers::invoice::Bolt12Invoice
+	/// [`Config::enable_bolt12_invoice_requests`]: crate::config::Config::enable_bolt12_invoice_requests
+	/// [`Error::InvoiceRequestsDisabled`]: crate::NodeError::InvoiceRequestsDisabled
+	/// [`Error::OfferExpired`]: crate::NodeError::OfferExpired
+	/// [`Error::InvoiceRequestFailed`]: crate::NodeError::InvoiceRequestFailed
+	/// [`Error::InvoiceRequestTimeout`]: crate::NodeError::InvoiceRequestTimeout
+	pub fn request_invoice(
+		&self, offer: &Offer, amount_msat: Option<u64>, quantity: Option<u64>,
+	) -> Result<Bolt12Invoice, Error> {
+		if !*self.is_running.read().unwrap() {
+			return Err(Error::NotRunning);
+		}
+
+		if !self.channel_manager.get_current_config().manually_handle_bolt12_invoices {
+			log_error!(
+				self.logger,
+				"Failed to request invoice as invoice requests are disabled. Please set Config::enable_bolt12_invoice_requests."
+			);
+			return Err(Error::InvoiceRequestsDisabled);
+		}
+
+		let offer = maybe_deref(offer);
+		self.validate_offer_payment_params(offer, quantity, None)?;
+
+		if offer.is_expired() {
+			log_error!(self.logger, "Failed to request invoice as the given offer has expired.");
+			return Err(Error::OfferExpired);
+		}
+
+		match (offer.amount(), amount_msat) {
+			(Some(Amount::Bitcoin { amount_msats }), Some(amount_msat)) => {
+				if amount_msat < amount_msats {
+					log_error!(
+						self.logger,
+						"Failed to request invoice as the given amount needs to be at least the offer amount: required {}msat, gave {}msat.", amount_msats, amount_msat);
+					return Err(Error::InvalidAmount);
+				}
+			},
+			(Some(Amount::Bitcoin { .. }), None) => {},
+			(Some(_), _) => {
+				log_error!(self.logger, "Failed to request invoice as the provided offer was denominated in an unsupported currency.");
+				return Err(Error::UnsupportedCurrency);
+			},
+			(None, Some(_)) => {},
+			(None, None) => {
+				log_error!(self.logger, "Failed to request invoice due to the given offer being \"zero-amount\". Please provide an amount.");
+				return Err(Error::InvalidOffer);
+			},
+		}
+
+		let mut random_bytes = [0u8; 32];
+		rand::thread_rng().fill_bytes(&mut random_bytes);
+		let payment_id = PaymentId(random_bytes);
+		let params = OptionalOfferPaymentParams {
+			payer_note: None,
+			retry_strategy: Retry::Timeout(LDK_PAYMENT_RETRY_TIMEOUT),
+			route_params_config: RouteParametersConfig::default(),
+		};
+
+		// We register the request before sending it so we can't miss the response.
+		let (sender, receiver) = tokio::sync::oneshot::channel();
+		let pending_invoice = PendingBolt12Invoice::AwaitingInvoice { sender };
+		self.pending_bolt12_invoices.lock().unwrap().insert(payment_id, pending_invoice);
+
+		let res = if let Some(quantity) = quantity {
+			self.channel_manager.pay_for_offer_with_quantity(
+				&offer,
+				amount_msat,
+				payment_id,
+				params,
+				quantity,
+			)
+		} else {
+			self.channel_manager.pay_for_offer(&offer, amount_msat, payment_id, params)
+		};
+		if let Err(e) = res {
+			self.pending_bolt12_invoices.lock().unwrap().remove(&payment_id);
+			log_error!(self.logger, "Failed to send invoice request: {:?}", e);
+			return match e {
+				Bolt12SemanticError::DuplicatePaymentId => Err(Error::DuplicatePayment),
+				_ => Err(Error::InvoiceRequestCreationFailed),
+			};
+		}
+
+		let res = self.runtime.block_on(async {
+			tokio::time::timeout(BOLT12_INVOICE_REQUEST_TIMEOUT, receiver).await
+		});
+		match res {
+			Ok(Ok(res)) => {
+				let invoice = res?;
+				log_info!(
+					self.logger,
+					"Received invoice for {}msat in response to our request for offer {}",
+					invoice.amount_msats(),
+					offer.id()
+				);
+				Ok(maybe_wrap(invoice))
+			},
+			Ok(Err(_)) => {
+				debug_assert!(false, "We never drop the senders without notifying them");
+				Err(Error::InvoiceRequestFailed)
+			},
+			Err(_) => {
+				log_error!(
+					self.logger,
+					"Timed out waiting for an invoice in response to our request for offer {}",
+					offer.id()
+				);
+				// We'll forget about the request once LDK notifies us the payment was abandoned.
+				self.channel_manager.abandon_payment(payment_id);
+				Err(Error::InvoiceRequestTimeout)
+			},
+		}
+	}
+
+	/// Pays an invoice previously retrieved via [`request_invoice`].
+	///
+	/// Will return an [`Error::InvalidInvoice`] if the given invoice wasn't retrieved via
+	/// [`request_invoice`], was already paid, or has expired.
+	///
+	/// [`request_invoice`]: Self::request_invoice
+	/// [`Error::InvalidInvoice`]: crate::NodeError::InvalidInvoice
+	pub fn pay_bolt12_invoice(&self, invoice: &Bolt12Invoice) -> Result<PaymentId, Error> {
+		if !*self.is_running.read().unwrap() {
+			return Err(Error::NotRunning);
+		}
+
+		let invoice = maybe_deref(invoice);
+		let (payment_id, context) = {
+			let mut locked_pending_invoices = self.pending_bolt12_invoices.lock().unwrap();
+			let payment_id = locked_pending_invoices
+				.iter()
+				.find_map(|(payment_id, pending_invoice)| match pending_invoice {
+					PendingBolt12Invoice::InvoiceReceived { invoice: pending, .. }
+						if pending == invoice =>
+					{
+						Some(*payment_id)
+					},
+					_ => None,
+				})
+				.ok_or_else(|| {
+					log_error!(
+						self.logger,
+						"Failed to pay BOLT12 invoice as it wasn't requested via request_invoice or was already paid."
+					);
+					Error::InvalidInvoice
+				})?;
+
+			if invoice.is_expired() {
+				log_error!(self.logger, "Failed to pay BOLT12 invoice as it has expired.");
+				// We'll forget about the invoice once LDK notifies us the payment was abandoned.
+				self.channel_manager.abandon_payment(payment_id);
+				return Err(Error::InvalidInvoice);
+			}
+
+			match locked_pending_invoices.remove(&payment_id) {
+				Some(PendingBolt12Invoice::InvoiceReceived { context, .. }) => {
+					(payment_id, context)
+				},
+				_ => unreachable!("We just found the received invoice"),
+			}
+		};
+
+		// We only ever request invoices for offers, so the invoice will always reference one.
+		let offer_id = invoice.offer_id().ok_or(Error::InvalidInvoice)?;
+		let kind = PaymentKind::Bolt12Offer {
+			hash: Some(invoice.payment_hash()),
+			preimage: None,
+			secret: None,
+			offer_id,
+			payer_note: None,
+			quantity: invoice.quantity(),
+		};
+		let amount_msat = invoice.amount_msats();
+		match self.channel_manager.send_payment_for_bolt12_invoice(&invoice, context.as_ref()) {
+			Ok(()) => {
+				log_info!(
+					self.logger,
+					"Initiated sending {}msat to {}",
+					amount_msat,
+					invoice.signing_pubkey()
+				);
+				let payment = PaymentDetails::new(
+					payment_id,
+					kind,
+					Some(amount_msat),
+					None,
+					PaymentDirection::Outbound,
+					PaymentStatus::Pending,
+				);
+				self.payment_store.insert(payment)?;
+				Ok(payment_id)
+			},
+			Err(e) => {
+				log_error!(self.logger, "Failed to pay BOLT12 invoice: {:?}", e);
+				let payment = PaymentDetails::new(
+					payment_id,
+					kind,
+					Some(amount_msat),
+					None,
+					PaymentDirection::Outbound,
+					PaymentStatus::Failed,
+				);
+				self.payment_store.insert(payment)?;
+				Err(Error::PaymentSendingFailed)
+			},
+		}
+	}
+
+	/// Abandons the payments of any received but unpaid invoices that expired in the meantime.
+	///
+	/// We'll forget about the invoices once LDK notifies us the payments were abandoned.
+	pub(crate) fn abandon_expired_invoices(&self) {
+		let locked_pending_invoices = self.pending_bolt12_invoices.lock().unwrap();
+		for (payment_id, pending_invoice) in locked_pending_invoices.iter() {
+			if let PendingBolt12Invoice::InvoiceReceived { invoice, .. } = pending_invoice {
+				if invoice.is_expired() {
+					self.channel_manager.abandon_payment(*payment_id);
+				}
+			}
+		}
+	}
+
+	fn validate_offer_payment_params(
+		&self, offer: &LdkOffer, quantity: Option<u64>, payer_note: Option<&str>,
+	) -> Result<(), Error> {
+		let quantity_valid = match quantity {
+			Some(quantity) => offer.is_valid_quantity(quantity),
+			None => !offer.expects_quantity(),
+		};
+		if !quantity_valid {
+			log_error!(
+				self.logger,
+				"Failed to send payment as the quantity {:?} isn't supported by the offer.",
+				quantity
+			);
+			return Err(Error::InvalidQuantity);
+		}
+
+		if let Some(payer_note) = payer_note {
+			if payer_note.len() > PAYER_NOTE_LIMIT {
+				log_error!(
+					self.logger,
+					"Failed to send payment as the payer note exceeds the limit of {} bytes.",
+					PAYER_NOTE_LIMIT
+				);
+				return Err(Error::InvalidPayerNote);
+			}
+		}
+
+		Ok(())
+	}
+
 	pub(crate) fn receive_inner(
 		&self, amount_msat: u64, description: &str, expiry_secs: Option<u32>, quantity: Option<u64>,
 	) -> Result<LdkOffer, Error> {
@@ -357,6 +723,131 @@ impl Bolt12Payment {
 		Ok(maybe_wrap(offer))
 	}
 
+	/// Returns our default offer, a long-lived, reusable "zero-amount" offer, e.g., to be printed
+	/// once as a static way to receive payments.
+	///
+	/// The offer doesn't expire and is persisted, i.e., the same offer is returned across restarts
+	/// until it's replaced via [`Bolt12Payment::rotate_default_offer`]. Its string representation
+	/// is the bech32-style `lno...` encoding, see [`Bolt12Payment::default_offer_qr_data`] for
+	/// data suitable for QR codes.
+	///
+	/// **Note:** The offer reaches us via blinded paths starting at our channel counterparties. If
+	/// none of them remains usable, e.g., as the respective channels were closed, the offer is
+	/// regenerated, i.e., a different offer will be returned.
+	pub fn default_offer(&self) -> Result<Offer, Error> {
+		let mut locked_default_offer = self.default_offer.lock().unwrap();
+		if let Some(offer) = locked_default_offer.as_ref() {
+			if self.has_usable_paths(offer) {
+				return Ok(maybe_wrap(offer.clone()));
+			}
+			log_info!(
+				self.logger,
+				"Regenerating default offer {} as none of its blinded paths is usable anymore.",
+				offer.id()
+			);
+		}
+
+		let offer = self.create_default_offer()?;
+		*locked_default_offer = Some(offer.clone());
+		Ok(maybe_wrap(offer))
+	}
+
+	/// Replaces our default offer with a newly generated one and returns it.
+	///
+	/// Note that payers can still pay the previous default offer after rotating, as offers are
+	/// stateless.
+	pub fn rotate_default_offer(&self) -> Result<Offer, Error> {
+		let mut locked_default_offer = self.default_offer.lock().unwrap();
+		let offer = self.create_default_offer()?;
+		*locked_default_offer = Some(offer.clone());
+		log_info!(self.logger, "Rotated default offer to {}", offer.id());
+		Ok(maybe_wrap(offer))
+	}
+
+	/// Returns our default offer as a `LIGHTNING:LNO1...` URI to be rendered as a QR code.
+	///
+	/// The URI is uppercase, which allows QR code generators to use the more compact alphanumeric
+	/// mode.
+	pub fn default_offer_qr_data(&self) -> Result<String, Error> {
+		let offer = self.default_offer()?;
+		Ok(format!("LIGHTNING:{}", maybe_deref(&offer).to_string().to_uppercase()))
+	}
+
+	// Returns whether any of the offer's blinded paths is still introduced by us or one of our
+	// channel counterparties.
+	fn has_usable_paths(&self, offer: &LdkOffer) -> bool {
+		if offer.paths().is_empty() {
+			return true;
+		}
+
+		let our_node_id = self.channel_manager.get_our_node_id();
+		let channels = self.channel_manager.list_channels();
+		offer.paths().iter().any(|path| match path.introduction_node() {
+			IntroductionNode::NodeId(node_id) => {
+				*node_id == our_node_id
+					|| channels.iter().any(|c| c.counterparty.node_id == *node_id)
+			},
+			IntroductionNode::DirectedShortChannelId(_, scid) => {
+				channels.iter().any(|c| c.short_channel_id == Some(*scid))
+			},
+		})
+	}
+
+	fn create_default_offer(&self) -> Result<LdkOffer, Error> {
+		let offer_builder = self.channel_manager.create_offer_builder().map_err(|e| {
+			log_error!(self.logger, "Failed to create offer builder: {:?}", e);
+			Error::OfferCreationFailed
+		})?;
+		let offer = offer_builder.build().map_err(|e| {
+			log_error!(self.logger, "Failed to create offer: {:?}", e);
+			Error::OfferCreationFailed
+		})?;
+		write_default_offer(&offer, Arc::clone(&self.kv_store), Arc::clone(&self.logger))?;
+		Ok(offer)
+	}
+
+	/// Returns a payable offer that can be used to request and receive a payment.
+	///
+	/// If `amount_msat` is `None`, the amount is to be determined by the user, i.e., a
+	/// "zero-amount" offer is returned. If `absolute_expiry_secs` is set, the offer will expire at
+	/// the given time, given in seconds since the UNIX epoch.
+	///
+	/// If `use_blinded_paths` is `false`, the offer will not include any blinded paths but rather
+	/// advertise our node id directly as the signing key. This forgoes recipient privacy, but may
+	/// improve reliability when paying counterparties that have trouble with blinded paths. Note
+	/// that payers then need to be able to reach us directly, i.e., this is only suitable for
+	/// publicly announced nodes.
+	pub fn receive_offer(
+		&self, amount_msat: Option<u64>, description: &str, absolute_expiry_secs: Option<u64>,
+		use_blinded_paths: bool,
+	) -> Result<Offer, Error> {
+		let offer_builder_res = if use_blinded_paths {
+			self.channel_manager.create_offer_builder()
+		} else {
+			self.channel_manager.create_offer_builder_using_router(&NullMessageRouter {})
+		};
+		let mut offer_builder = offer_builder_res.map_err(|e| {
+			log_error!(self.logger, "Failed to create offer builder: {:?}", e);
+			Error::OfferCreationFailed
+		})?;
+
+		if let Some(absolute_expiry_secs) = absolute_expiry_secs {
+			offer_builder =
+				offer_builder.absolute_expiry(Duration::from_secs(absolute_expiry_secs));
+		}
+
+		if let Some(amount_msat) = amount_msat {
+			offer_builder = offer_builder.amount_msats(amount_msat);
+		}
+
+		let offer = offer_builder.description(description.to_string()).build().map_err(|e| {
+			log_error!(self.logger, "Failed to create offer: {:?}", e);
+			Error::OfferCreationFailed
+		})?;
+
+		Ok(maybe_wrap(offer))
+	}
+
 	/// Requests a refund payment for the given [`Refund`].
 	///
 	/// The returned [`Bolt12Invoice`] is for informational purposes only (i.e., isn't needed to
@@ -407,13 +898,60 @@ impl Bolt12Payment {
 		&self, amount_msat: u64, expiry_secs: u32, quantity: Option<u64>,
 		payer_note: Option<String>,
 	) -> Result<Refund, Error> {
+		let absolute_expiry = (SystemTime::now() + Duration::from_secs(expiry_secs as u64))
+			.duration_since(UNIX_EPOCH)
+			.unwrap();
+		let refund =
+			self.create_refund_inner(amount_msat, absolute_expiry, quantity, payer_note, None)?;
+		Ok(maybe_wrap(refund))
+	}
+
+	/// Returns a [`Refund`] with the given description that can be handed to a customer to refund
+	/// them the given amount.
+	///
+	/// The refund will expire at the given absolute time, given in seconds since the UNIX epoch,
+	/// which must lie in the future.
+	///
+	/// Once the customer requests payment for the refund via
+	/// [`Bolt12Payment::request_refund_payment`], the resulting invoice will automatically be
+	/// paid, upon which an [`Event::RefundPaid`] is emitted in addition to the usual
+	/// [`Event::PaymentSuccessful`].
+	///
+	/// [`Refund`]: lightning::offers::refund::Refund
+	/// [`Event::RefundPaid`]: crate::Event::RefundPaid
+	/// [`Event::PaymentSuccessful`]: crate::Event::PaymentSuccessful
+	pub fn create_refund(
+		&self, amount_msat: u64, description: String, absolute_expiry_secs: u64,
+	) -> Result<Refund, Error> {
+		if !*self.is_running.read().unwrap() {
+			return Err(Error::NotRunning);
+		}
+
+		if amount_msat == 0 {
+			log_error!(self.logger, "Failed to create refund: amount must be non-zero.");
+			return Err(Error::InvalidAmount);
+		}
+
+		let absolute_expiry = Duration::from_secs(absolute_expiry_secs);
+		let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+		if absolute_expiry <= now {
+			log_error!(self.logger, "Failed to create refund: expiry lies in the past.");
+			return Err(Error::InvalidDateTime);
+		}
+
+		let refund =
+			self.create_refund_inner(amount_msat, absolute_expiry, None, None, Some(description))?;
+		Ok(maybe_wrap(refund))
+	}
+
+	fn create_refund_inner(
+		&self, amount_msat: u64, absolute_expiry: Duration, quantity: Option<u64>,
+		payer_note: Option<String>, description: Option<String>,
+	) -> Result<LdkRefund, Error> {
 		let mut random_bytes = [0u8; 32];
 		rand::thread_rng().fill_bytes(&mut random_bytes);
 		let payment_id = PaymentId(random_bytes);
 
-		let absolute_expiry = (SystemTime::now() + Duration::from_secs(expiry_secs as u64))
-			.duration_since(UNIX_EPOCH)
-			.unwrap();
 		let retry_strategy = Retry::Timeout(LDK_PAYMENT_RETRY_TIMEOUT);
 		let route_params_config = RouteParametersConfig::default();
 
@@ -439,6 +977,10 @@ impl Bolt12Payment {
 			refund_builder = refund_builder.payer_note(note);
 		}
 
+		if let Some(description) = description {
+			refund_builder = refund_builder.description(description);
+		}
+
 		let refund = refund_builder.build().map_err(|e| {
 			log_error!(self.logger, "Failed to create refund: {:?}", e);
 			Error::RefundCreationFailed
@@ -464,7 +1006,7 @@ impl Bolt12Payment {
 
 		self.payment_store.insert(payment)?;
 
-		Ok(maybe_wrap(refund))
+		Ok(refund)
 	}
 
 	/// Retrieve an [`Offer`] for receiving async payments as an often-offline recipient.
@@ -0,0 +1,199 @@
+// This file is Copyright its original authors, visible in version control history.
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. You may not use this file except in
+// accordance with one or both of these licenses.
+
+//! Holds a watchdog failing back manually-claimable payments before their claim deadline.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use bitcoin::block::Header;
+use bitcoin::{BlockHash, Txid};
+use lightning::chain::transaction::TransactionData;
+use lightning::chain::{BestBlock, Confirm, Listen};
+use lightning::ln::channelmanager::PaymentId;
+use lightning_types::payment::PaymentHash;
+
+use crate::config::Config;
+use crate::event::{Event, EventQueue, PaymentAutoFailReason};
+use crate::logger::{log_error, log_info, LdkLogger, Logger};
+use crate::payment::store::{PaymentDetailsUpdate, PaymentStatus};
+use crate::types::{ChannelManager, PaymentStore};
+
+// The interval in which we check whether the claim timeout of any held payment has elapsed.
+pub(crate) const CLAIM_TIMEOUT_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+struct PendingClaim {
+	claim_deadline: Option<u32>,
+	claim_timeout_unix_secs: Option<u64>,
+}
+
+/// Fails back manually-claimable payments once the chain tip moves within
+/// [`Config::manual_claim_auto_fail_margin_blocks`] of their `claim_deadline`, or once the claim
+/// timeout they were registered with has elapsed.
+///
+/// Payments are registered when we emit an [`Event::PaymentClaimable`] for them. Their claim
+/// deadlines are checked whenever we get notified about a new best block, while their claim
+/// timeouts are checked every [`CLAIM_TIMEOUT_CHECK_INTERVAL`].
+pub(crate) struct ClaimDeadlineWatchdog {
+	pending_claims: Mutex<HashMap<PaymentHash, PendingClaim>>,
+	channel_manager: Arc<ChannelManager>,
+	payment_store: Arc<PaymentStore>,
+	event_queue: Arc<EventQueue<Arc<Logger>>>,
+	config: Arc<Config>,
+	logger: Arc<Logger>,
+}
+
+impl ClaimDeadlineWatchdog {
+	pub(crate) fn new(
+		channel_manager: Arc<ChannelManager>, payment_store: Arc<PaymentStore>,
+		event_queue: Arc<EventQueue<Arc<Logger>>>, config: Arc<Config>, logger: Arc<Logger>,
+	) -> Self {
+		let pending_claims = Mutex::new(HashMap::new());
+		Self { pending_claims, channel_manager, payment_store, event_queue, config, logger }
+	}
+
+	/// Starts watching the manually-claimable payment with the given hash.
+	///
+	/// If `claim_timeout_secs` is set, the payment will be failed back if it isn't claimed within
+	/// the given number of seconds from now.
+	pub(crate) fn register_claimable(
+		&self, payment_hash: PaymentHash, claim_deadline: Option<u32>,
+		claim_timeout_secs: Option<u64>,
+	) {
+		let claim_timeout_unix_secs =
+			claim_timeout_secs.map(|timeout_secs| unix_time_secs().saturating_add(timeout_secs));
+		let pending_claim = PendingClaim { claim_deadline, claim_timeout_unix_secs };
+		self.pending_claims.lock().unwrap().insert(payment_hash, pending_claim);
+	}
+
+	/// Stops watching the payment with the given hash, e.g., as it was claimed or failed manually.
+	pub(crate) fn unregister_claimable(&self, payment_hash: &PaymentHash) {
+		self.pending_claims.lock().unwrap().remove(payment_hash);
+	}
+
+	fn check_claim_deadlines(&self, height: u32) {
+		let margin_blocks = match self.config.manual_claim_auto_fail_margin_blocks {
+			Some(margin_blocks) => margin_blocks,
+			None => return,
+		};
+
+		let expiring_claims = self.take_pending_claims(|pending_claim| {
+			pending_claim.claim_deadline.map_or(false, |claim_deadline| {
+				height.saturating_add(margin_blocks) >= claim_deadline
+			})
+		});
+
+		for payment_hash in expiring_claims {
+			log_info!(
+				self.logger,
+				"Automatically failing back payment with hash {} at height {} as its claim deadline is approaching.",
+				payment_hash,
+				height,
+			);
+			self.auto_fail_payment(payment_hash, PaymentAutoFailReason::ClaimDeadlineApproaching);
+		}
+	}
+
+	/// Fails back all held payments whose claim timeout has elapsed.
+	pub(crate) fn check_claim_timeouts(&self) {
+		let now = unix_time_secs();
+		let timed_out_claims = self.take_pending_claims(|pending_claim| {
+			pending_claim.claim_timeout_unix_secs.map_or(false, |timeout| now >= timeout)
+		});
+
+		for payment_hash in timed_out_claims {
+			log_info!(
+				self.logger,
+				"Automatically failing back payment with hash {} as its claim timeout elapsed.",
+				payment_hash,
+			);
+			self.auto_fail_payment(payment_hash, PaymentAutoFailReason::ClaimTimeoutElapsed);
+		}
+	}
+
+	/// Fails back all currently held payments, returning the hashes of the failed payments.
+	pub(crate) fn fail_all_pending_claims(&self) -> Vec<PaymentHash> {
+		let pending_claims = self.take_pending_claims(|_| true);
+
+		let mut failed_payment_hashes = Vec::with_capacity(pending_claims.len());
+		for payment_hash in pending_claims {
+			log_info!(self.logger, "Failing back held payment with hash {}.", payment_hash);
+			if self.auto_fail_payment(payment_hash, PaymentAutoFailReason::FailAllRequested) {
+				failed_payment_hashes.push(payment_hash);
+			}
+		}
+		failed_payment_hashes
+	}
+
+	fn take_pending_claims<F: Fn(&PendingClaim) -> bool>(&self, filter: F) -> Vec<PaymentHash> {
+		let mut locked_pending_claims = self.pending_claims.lock().unwrap();
+		let payment_hashes = locked_pending_claims
+			.iter()
+			.filter(|(_, pending_claim)| filter(pending_claim))
+			.map(|(payment_hash, _)| *payment_hash)
+			.collect::<Vec<_>>();
+		for payment_hash in &payment_hashes {
+			locked_pending_claims.remove(payment_hash);
+		}
+		payment_hashes
+	}
+
+	// Returns whether the payment was failed back, i.e., `false` if it was resolved already.
+	fn auto_fail_payment(&self, payment_hash: PaymentHash, reason: PaymentAutoFailReason) -> bool {
+		let payment_id = PaymentId(payment_hash.0);
+		match self.payment_store.get(&payment_id) {
+			Some(details) if details.status == PaymentStatus::Pending => {},
+			_ => {
+				// The payment was already resolved in the meantime, nothing to do.
+				return false;
+			},
+		}
+
+		self.channel_manager.fail_htlc_backwards(&payment_hash);
+
+		let update = PaymentDetailsUpdate {
+			status: Some(PaymentStatus::Failed),
+			..PaymentDetailsUpdate::new(payment_id)
+		};
+		if let Err(e) = self.payment_store.update(&update) {
+			log_error!(self.logger, "Failed to access payment store: {}", e);
+		}
+
+		let event = Event::PaymentAutoFailed { payment_hash, reason };
+		if let Err(e) = self.event_queue.add_event(event) {
+			log_error!(self.logger, "Failed to push to event queue: {}", e);
+		}
+		true
+	}
+}
+
+impl Listen for ClaimDeadlineWatchdog {
+	fn filtered_block_connected(&self, _header: &Header, _txdata: &TransactionData, height: u32) {
+		self.check_claim_deadlines(height);
+	}
+
+	fn blocks_disconnected(&self, _fork_point_block: BestBlock) {}
+}
+
+impl Confirm for ClaimDeadlineWatchdog {
+	fn transactions_confirmed(&self, _header: &Header, _txdata: &TransactionData, _height: u32) {}
+
+	fn transaction_unconfirmed(&self, _txid: &Txid) {}
+
+	fn best_block_updated(&self, _header: &Header, height: u32) {
+		self.check_claim_deadlines(height);
+	}
+
+	fn get_relevant_txids(&self) -> Vec<(Txid, u32, Option<BlockHash>)> {
+		Vec::new()
+	}
+}
+
+fn unix_time_secs() -> u64 {
+	SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or(Duration::from_secs(0)).as_secs()
+}
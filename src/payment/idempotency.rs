@@ -0,0 +1,331 @@
+// This file is Copyright its original authors, visible in version control history.
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. You may not use this file except in
+// accordance with one or both of these licenses.
+
+//! Tracks the idempotency keys outbound payments were sent with.
+
+use std::sync::Arc;
+
+use bitcoin::hashes::sha256::Hash as Sha256;
+use bitcoin::hashes::Hash;
+use lightning::impl_writeable_tlv_based;
+use lightning::ln::channelmanager::PaymentId;
+
+use crate::config::DuplicatePaymentBehavior;
+use crate::data_store::{DataStore, StorableObject, StorableObjectId, StorableObjectUpdate};
+use crate::error::Error;
+use crate::hex_utils;
+use crate::io::{
+	PAYMENT_IDEMPOTENCY_KEY_PERSISTENCE_PRIMARY_NAMESPACE,
+	PAYMENT_IDEMPOTENCY_KEY_PERSISTENCE_SECONDARY_NAMESPACE,
+};
+use crate::logger::{log_error, log_info, LdkLogger, Logger};
+use crate::payment::PaymentStatus;
+use crate::types::{DynStore, PaymentStore};
+
+/// The identifier under which we store an idempotency key, i.e., the SHA256 hash of the key.
+///
+/// As the keys are chosen by the user, we don't use them directly to stay within the character
+/// set and length limits of the [`KVStore`] keys.
+///
+/// [`KVStore`]: lightning::util::persist::KVStore
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct IdempotencyKeyId([u8; 32]);
+
+impl IdempotencyKeyId {
+	fn from_key(idempotency_key: &str) -> Self {
+		Self(Sha256::hash(idempotency_key.as_bytes()).to_byte_array())
+	}
+}
+
+impl StorableObjectId for IdempotencyKeyId {
+	fn encode_to_hex_str(&self) -> String {
+		hex_utils::to_string(&self.0)
+	}
+}
+
+/// An idempotency key and the id of the payment that was sent with it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct IdempotencyKeyEntry {
+	pub idempotency_key: String,
+	pub payment_id: PaymentId,
+}
+
+impl_writeable_tlv_based!(IdempotencyKeyEntry, {
+	(0, idempotency_key, required),
+	(2, payment_id, required),
+});
+
+impl StorableObject for IdempotencyKeyEntry {
+	type Id = IdempotencyKeyId;
+	type Update = IdempotencyKeyEntryUpdate;
+	type IndexKey = ();
+	type Aggregate = ();
+
+	fn id(&self) -> Self::Id {
+		IdempotencyKeyId::from_key(&self.idempotency_key)
+	}
+
+	fn index_key(&self) -> Option<Self::IndexKey> {
+		None
+	}
+
+	fn update(&mut self, update: &Self::Update) -> bool {
+		debug_assert_eq!(self.id(), update.id, "We should only ever update the same key");
+		if self.payment_id != update.payment_id {
+			self.payment_id = update.payment_id;
+			true
+		} else {
+			false
+		}
+	}
+
+	fn to_update(&self) -> Self::Update {
+		IdempotencyKeyEntryUpdate { id: self.id(), payment_id: self.payment_id }
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct IdempotencyKeyEntryUpdate {
+	pub id: IdempotencyKeyId,
+	pub payment_id: PaymentId,
+}
+
+impl StorableObjectUpdate<IdempotencyKeyEntry> for IdempotencyKeyEntryUpdate {
+	fn id(&self) -> <IdempotencyKeyEntry as StorableObject>::Id {
+		self.id
+	}
+}
+
+/// Maps the idempotency keys outbound payments were sent with to their [`PaymentId`]s.
+///
+/// A key is reserved and persisted before the payment is initiated, so that concurrent or
+/// repeated sends with the same key will be handled as duplicates of the original payment rather
+/// than dispatching another one.
+pub(crate) struct PaymentIdempotencyKeys {
+	entries: DataStore<IdempotencyKeyEntry, Arc<Logger>>,
+	payment_store: Arc<PaymentStore>,
+	logger: Arc<Logger>,
+}
+
+impl PaymentIdempotencyKeys {
+	pub(crate) fn new(
+		entries: Vec<IdempotencyKeyEntry>, payment_store: Arc<PaymentStore>,
+		kv_store: Arc<DynStore>, logger: Arc<Logger>,
+	) -> Self {
+		let entries = DataStore::new(
+			entries,
+			PAYMENT_IDEMPOTENCY_KEY_PERSISTENCE_PRIMARY_NAMESPACE.to_string(),
+			PAYMENT_IDEMPOTENCY_KEY_PERSISTENCE_SECONDARY_NAMESPACE.to_string(),
+			kv_store,
+			Arc::clone(&logger),
+		);
+		Self { entries, payment_store, logger }
+	}
+
+	/// Sends a payment with the given [`PaymentId`] via `send`, unless a payment was already sent
+	/// with the given idempotency key, in which case the duplicate is handled as configured via
+	/// `on_duplicate`.
+	///
+	/// If `send` fails before a payment was recorded, the key is released again so that the send
+	/// may be retried.
+	pub(crate) fn send<F>(
+		&self, idempotency_key: Option<String>, on_duplicate: DuplicatePaymentBehavior,
+		payment_id: PaymentId, send: F,
+	) -> Result<PaymentId, Error>
+	where
+		F: FnOnce() -> Result<PaymentId, Error>,
+	{
+		let idempotency_key = match idempotency_key {
+			Some(idempotency_key) => idempotency_key,
+			None => return send(),
+		};
+
+		let entry = IdempotencyKeyEntry { idempotency_key, payment_id };
+		let id = entry.id();
+		if let Some(existing) = self.entries.insert_if_absent(entry)? {
+			let existing_payment_id = existing.payment_id;
+			let existing_failed = self
+				.payment_store
+				.get(&existing_payment_id)
+				.map_or(false, |p| p.status == PaymentStatus::Failed);
+			if on_duplicate == DuplicatePaymentBehavior::Fail || existing_failed {
+				log_error!(
+					self.logger,
+					"Payment {} was already sent with the given idempotency key.",
+					existing_payment_id
+				);
+				return Err(Error::DuplicateIdempotencyKey { existing_payment_id });
+			}
+			log_info!(
+				self.logger,
+				"Returning existing payment {} for the given idempotency key.",
+				existing_payment_id
+			);
+			return Ok(existing_payment_id);
+		}
+
+		let res = send();
+		if res.is_err() && self.payment_store.get(&payment_id).is_none() {
+			self.entries.remove(&id).unwrap_or_else(|e| {
+				log_error!(self.logger, "Failed to release idempotency key: {}", e);
+			});
+		}
+		res
+	}
+
+	/// Returns the id of the payment that was sent with the given idempotency key, if any.
+	pub(crate) fn get(&self, idempotency_key: &str) -> Option<PaymentId> {
+		self.entries.get(&IdempotencyKeyId::from_key(idempotency_key)).map(|e| e.payment_id)
+	}
+
+	/// Forgets all idempotency keys the payment with the given id was sent with.
+	pub(crate) fn remove_payment(&self, payment_id: &PaymentId) -> Result<(), Error> {
+		for entry in self.entries.list_filter(|e| &e.payment_id == payment_id) {
+			self.entries.remove(&entry.id())?;
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use lightning::util::test_utils::TestStore;
+
+	use super::*;
+	use crate::io::{
+		PAYMENT_INFO_PERSISTENCE_PRIMARY_NAMESPACE, PAYMENT_INFO_PERSISTENCE_SECONDARY_NAMESPACE,
+	};
+	use crate::payment::{PaymentDetails, PaymentDirection, PaymentKind, PaymentStatus};
+
+	fn setup() -> (Arc<DynStore>, Arc<Logger>, Arc<PaymentStore>, PaymentIdempotencyKeys) {
+		let kv_store: Arc<DynStore> = Arc::new(TestStore::new(false));
+		let logger = Arc::new(Logger::new_log_facade());
+		let payment_store = Arc::new(PaymentStore::new(
+			Vec::new(),
+			PAYMENT_INFO_PERSISTENCE_PRIMARY_NAMESPACE.to_string(),
+			PAYMENT_INFO_PERSISTENCE_SECONDARY_NAMESPACE.to_string(),
+			Arc::clone(&kv_store),
+			Arc::clone(&logger),
+		));
+		let keys = PaymentIdempotencyKeys::new(
+			Vec::new(),
+			Arc::clone(&payment_store),
+			Arc::clone(&kv_store),
+			Arc::clone(&logger),
+		);
+		(kv_store, logger, payment_store, keys)
+	}
+
+	fn record_payment(
+		payment_store: &PaymentStore, payment_id: PaymentId, status: PaymentStatus,
+	) -> Result<PaymentId, Error> {
+		let kind = PaymentKind::Spontaneous {
+			hash: lightning_types::payment::PaymentHash(payment_id.0),
+			preimage: None,
+		};
+		let payment = PaymentDetails::new(
+			payment_id,
+			kind,
+			Some(1000),
+			None,
+			PaymentDirection::Outbound,
+			status,
+		);
+		payment_store.insert(payment).unwrap();
+		Ok(payment_id)
+	}
+
+	#[test]
+	fn idempotency_keys_deduplicate_sends() {
+		let (kv_store, logger, payment_store, keys) = setup();
+		let return_existing = DuplicatePaymentBehavior::ReturnExisting;
+
+		// A failed send that didn't record a payment releases the key again.
+		let first_id = PaymentId([1u8; 32]);
+		let key = Some("key".to_string());
+		assert_eq!(
+			keys.send(key.clone(), return_existing, first_id, || Err(Error::InvalidAmount)),
+			Err(Error::InvalidAmount)
+		);
+		assert_eq!(keys.get("key"), None);
+
+		let record_payment =
+			|payment_id| record_payment(&payment_store, payment_id, PaymentStatus::Pending);
+		assert_eq!(
+			keys.send(key.clone(), return_existing, first_id, || record_payment(first_id)),
+			Ok(first_id)
+		);
+		assert_eq!(keys.get("key"), Some(first_id));
+
+		// Sending again with the same key returns the existing payment without sending.
+		let second_id = PaymentId([2u8; 32]);
+		assert_eq!(
+			keys.send(key.clone(), return_existing, second_id, || panic!("must not send")),
+			Ok(first_id)
+		);
+
+		// The mapping is persisted.
+		let persisted = crate::io::utils::read_payment_idempotency_keys(
+			Arc::clone(&kv_store),
+			Arc::clone(&logger),
+		)
+		.unwrap();
+		assert_eq!(
+			persisted,
+			vec![IdempotencyKeyEntry { idempotency_key: "key".to_string(), payment_id: first_id }]
+		);
+
+		keys.remove_payment(&first_id).unwrap();
+		assert_eq!(keys.get("key"), None);
+		assert_eq!(
+			keys.send(key, return_existing, second_id, || record_payment(second_id)),
+			Ok(second_id)
+		);
+	}
+
+	#[test]
+	fn idempotency_keys_handle_duplicates_as_configured() {
+		let (_, _, payment_store, keys) = setup();
+		let first_id = PaymentId([1u8; 32]);
+		let second_id = PaymentId([2u8; 32]);
+		let key = Some("key".to_string());
+		let (return_existing, fail) =
+			(DuplicatePaymentBehavior::ReturnExisting, DuplicatePaymentBehavior::Fail);
+		let record_pending =
+			|payment_id| record_payment(&payment_store, payment_id, PaymentStatus::Pending);
+		assert_eq!(
+			keys.send(key.clone(), fail, first_id, || record_pending(first_id)),
+			Ok(first_id)
+		);
+
+		// Duplicates are only returned if we're configured to do so.
+		assert_eq!(
+			keys.send(key.clone(), return_existing, second_id, || panic!("must not send")),
+			Ok(first_id)
+		);
+		assert_eq!(
+			keys.send(key.clone(), fail, second_id, || panic!("must not send")),
+			Err(Error::DuplicateIdempotencyKey { existing_payment_id: first_id })
+		);
+
+		// A failed payment is never returned as if it was still successfully sent.
+		let failed_key = Some("failed_key".to_string());
+		let failed_id = PaymentId([3u8; 32]);
+		let record_failed =
+			|payment_id| record_payment(&payment_store, payment_id, PaymentStatus::Failed);
+		assert_eq!(
+			keys.send(failed_key.clone(), return_existing, failed_id, || record_failed(failed_id)),
+			Ok(failed_id)
+		);
+		for on_duplicate in [return_existing, fail] {
+			assert_eq!(
+				keys.send(failed_key.clone(), on_duplicate, second_id, || panic!("must not send")),
+				Err(Error::DuplicateIdempotencyKey { existing_payment_id: failed_id })
+			);
+		}
+	}
+}
@@ -0,0 +1,457 @@
+// This file is Copyright its original authors, visible in version control history.
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. You may not use this file except in
+// accordance with one or both of these licenses.
+
+//! Holds a payment handler allowing to pay to and withdraw from [LNURL] services.
+//!
+//! [LNURL]: https://github.com/lnurl/luds
+
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use bitcoin::bech32;
+use bitcoin::hashes::sha256::Hash as Sha256;
+use bitcoin::hashes::Hash;
+use lightning::ln::channelmanager::PaymentId;
+use lightning_invoice::{
+	Bolt11Invoice as LdkBolt11Invoice, Bolt11InvoiceDescription, Bolt11InvoiceDescriptionRef,
+	Description,
+};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use crate::config::{LNURL_REQUEST_TIMEOUT_SECS, LNURL_WITHDRAW_INVOICE_EXPIRY_SECS};
+use crate::error::Error;
+use crate::ffi::maybe_wrap;
+use crate::logger::{log_error, log_info, LdkLogger, Logger};
+use crate::payment::{Bolt11Payment, ReceiveParams};
+use crate::runtime::Runtime;
+
+const LNURL_HRP: &str = "lnurl";
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PayRequest {
+	tag: String,
+	callback: String,
+	min_sendable: u64,
+	max_sendable: u64,
+	metadata: String,
+	#[serde(default)]
+	comment_allowed: u64,
+}
+
+#[derive(Deserialize)]
+struct PayRequestResponse {
+	pr: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WithdrawRequest {
+	tag: String,
+	callback: String,
+	k1: String,
+	#[serde(default)]
+	default_description: String,
+	min_withdrawable: u64,
+	max_withdrawable: u64,
+}
+
+#[derive(Deserialize)]
+struct StatusResponse {}
+
+/// A payment handler allowing to pay to and withdraw from [LNURL] services.
+///
+/// Should be retrieved by calling [`Node::lnurl`].
+///
+/// [LNURL]: https://github.com/lnurl/luds
+/// [`Node::lnurl`]: crate::Node::lnurl
+pub struct Lnurl {
+	bolt11_payment: Arc<Bolt11Payment>,
+	runtime: Arc<Runtime>,
+	is_running: Arc<RwLock<bool>>,
+	logger: Arc<Logger>,
+}
+
+impl Lnurl {
+	pub(crate) fn new(
+		bolt11_payment: Arc<Bolt11Payment>, runtime: Arc<Runtime>, is_running: Arc<RwLock<bool>>,
+		logger: Arc<Logger>,
+	) -> Self {
+		Self { bolt11_payment, runtime, is_running, logger }
+	}
+
+	/// Pays the given amount to the given [LNURL-pay] service.
+	///
+	/// We'll request an invoice for `amount_msat` from the service and pay it. The amount has to
+	/// be within the bounds advertised by the service. An optional `comment` may be passed to the
+	/// service if it [supports comments], in which case it must not exceed the length limit given
+	/// by the service.
+	///
+	/// The `lnurl` may be given as a bech32-encoded LNURL, optionally prefixed with `lightning:`,
+	/// or via the `lnurlp://` scheme.
+	///
+	/// [LNURL-pay]: https://github.com/lnurl/luds/blob/luds/06.md
+	/// [supports comments]: https://github.com/lnurl/luds/blob/luds/12.md
+	pub fn pay(
+		&self, lnurl: &str, amount_msat: u64, comment: Option<String>,
+	) -> Result<PaymentId, Error> {
+		if !*self.is_running.read().unwrap() {
+			return Err(Error::NotRunning);
+		}
+
+		let url = decode_lnurl(lnurl)?;
+		self.pay_inner(url, amount_msat, comment)
+	}
+
+	/// Pays the given amount to the given [Lightning Address], e.g., `alice@example.com`.
+	///
+	/// The address is resolved to the LNURL-pay service at
+	/// `https://<domain>/.well-known/lnurlp/<user>` which is then paid as described in
+	/// [`Lnurl::pay`]. The domain may include an explicit port, e.g., `alice@example.com:8080`.
+	///
+	/// Returns [`Error::InvalidLightningAddress`] if the address is malformed and
+	/// [`Error::LnurlNotFound`] if the domain doesn't know about the given user.
+	///
+	/// [Lightning Address]: https://github.com/lnurl/luds/blob/luds/16.md
+	pub fn pay_lightning_address(
+		&self, address: &str, amount_msat: u64, comment: Option<String>,
+	) -> Result<PaymentId, Error> {
+		if !*self.is_running.read().unwrap() {
+			return Err(Error::NotRunning);
+		}
+
+		let url = lightning_address_to_url(address)?;
+		self.pay_inner(url, amount_msat, comment)
+	}
+
+	fn pay_inner(
+		&self, url: String, amount_msat: u64, comment: Option<String>,
+	) -> Result<PaymentId, Error> {
+		let client = build_http_client()?;
+
+		let invoice = self.runtime.block_on(async {
+			let pay_request: PayRequest = self.get_json(client.get(&url)).await?;
+			if pay_request.tag != "payRequest" {
+				log_error!(self.logger, "LNURL {} is not an LNURL-pay request", url);
+				return Err(Error::InvalidLnurl);
+			}
+
+			if amount_msat < pay_request.min_sendable || amount_msat > pay_request.max_sendable {
+				log_error!(
+					self.logger,
+					"Amount of {}msat is outside the range of {}msat to {}msat accepted by the LNURL-pay service",
+					amount_msat,
+					pay_request.min_sendable,
+					pay_request.max_sendable
+				);
+				return Err(Error::InvalidAmount);
+			}
+
+			let mut query = vec![("amount", amount_msat.to_string())];
+			if let Some(comment) = comment.filter(|c| !c.is_empty()) {
+				if comment.chars().count() as u64 > pay_request.comment_allowed {
+					log_error!(
+						self.logger,
+						"Comment exceeds the maximum length of {} characters allowed by the LNURL-pay service",
+						pay_request.comment_allowed
+					);
+					return Err(Error::InvalidComment);
+				}
+				query.push(("comment", comment));
+			}
+
+			let response: PayRequestResponse =
+				self.get_json(client.get(&pay_request.callback).query(&query)).await?;
+			let invoice = LdkBolt11Invoice::from_str(&response.pr).map_err(|e| {
+				log_error!(self.logger, "LNURL-pay service returned an invalid invoice: {}", e);
+				Error::InvalidInvoice
+			})?;
+
+			// Make sure the service didn't hand us an invoice for something else.
+			if invoice.amount_milli_satoshis() != Some(amount_msat) {
+				log_error!(
+					self.logger,
+					"LNURL-pay service returned an invoice for an unexpected amount: {:?}msat",
+					invoice.amount_milli_satoshis()
+				);
+				return Err(Error::InvalidInvoice);
+			}
+			let metadata_hash = Sha256::hash(pay_request.metadata.as_bytes());
+			match invoice.description() {
+				Bolt11InvoiceDescriptionRef::Hash(hash) if hash.0 == metadata_hash => {},
+				_ => {
+					log_error!(
+						self.logger,
+						"LNURL-pay service returned an invoice not committing to the request metadata"
+					);
+					return Err(Error::InvalidInvoice);
+				},
+			}
+
+			Ok(invoice)
+		})?;
+
+		let payment_id = self.bolt11_payment.send(&maybe_wrap(invoice), None)?;
+		log_info!(self.logger, "Initiated payment {} to LNURL-pay service", payment_id);
+		Ok(payment_id)
+	}
+
+	/// Withdraws the given amount from the given [LNURL-withdraw] service.
+	///
+	/// We'll create an invoice for `amount_msat` and hand it to the service, which will then pay
+	/// it. The amount has to be within the bounds advertised by the service. Returns the
+	/// [`PaymentId`] under which the inbound payment will be tracked once it arrives.
+	///
+	/// The `lnurl` may be given as a bech32-encoded LNURL, optionally prefixed with `lightning:`,
+	/// or via the `lnurlw://` scheme.
+	///
+	/// [LNURL-withdraw]: https://github.com/lnurl/luds/blob/luds/03.md
+	pub fn withdraw(&self, lnurl: &str, amount_msat: u64) -> Result<PaymentId, Error> {
+		if !*self.is_running.read().unwrap() {
+			return Err(Error::NotRunning);
+		}
+
+		let url = decode_lnurl(lnurl)?;
+		let client = build_http_client()?;
+
+		let withdraw_request: WithdrawRequest =
+			self.runtime.block_on(self.get_json(client.get(&url)))?;
+		if withdraw_request.tag != "withdrawRequest" {
+			log_error!(self.logger, "LNURL {} is not an LNURL-withdraw request", url);
+			return Err(Error::InvalidLnurl);
+		}
+
+		if amount_msat < withdraw_request.min_withdrawable
+			|| amount_msat > withdraw_request.max_withdrawable
+		{
+			log_error!(
+				self.logger,
+				"Amount of {}msat is outside the range of {}msat to {}msat accepted by the LNURL-withdraw service",
+				amount_msat,
+				withdraw_request.min_withdrawable,
+				withdraw_request.max_withdrawable
+			);
+			return Err(Error::InvalidAmount);
+		}
+
+		let description = Bolt11InvoiceDescription::Direct(
+			Description::new(withdraw_request.default_description)
+				.map_err(|_| Error::InvoiceCreationFailed)?,
+		);
+		let invoice = self.bolt11_payment.receive_inner(
+			Some(amount_msat),
+			&description,
+			LNURL_WITHDRAW_INVOICE_EXPIRY_SECS,
+			ReceiveParams::default(),
+		)?;
+		let payment_id = PaymentId(invoice.payment_hash().to_byte_array());
+
+		let query = [("k1", withdraw_request.k1), ("pr", invoice.to_string())];
+		let _: StatusResponse = self
+			.runtime
+			.block_on(self.get_json(client.get(&withdraw_request.callback).query(&query)))?;
+
+		log_info!(
+			self.logger,
+			"Requested withdrawal of {}msat from LNURL-withdraw service",
+			amount_msat
+		);
+		Ok(payment_id)
+	}
+
+	async fn get_json<T: DeserializeOwned>(
+		&self, request: reqwest::RequestBuilder,
+	) -> Result<T, Error> {
+		let response = request.send().await.map_err(|e| {
+			log_error!(self.logger, "Failed to reach LNURL service: {}", e);
+			Error::LnurlRequestFailed
+		})?;
+		if response.status() == reqwest::StatusCode::NOT_FOUND {
+			log_error!(self.logger, "LNURL service {} doesn't exist", response.url());
+			return Err(Error::LnurlNotFound);
+		}
+		let body: serde_json::Value = response.json().await.map_err(|e| {
+			log_error!(self.logger, "Failed to read LNURL service response: {}", e);
+			Error::LnurlRequestFailed
+		})?;
+
+		// Services may respond to any request with an error status.
+		if body
+			.get("status")
+			.and_then(|s| s.as_str())
+			.map_or(false, |s| s.eq_ignore_ascii_case("ERROR"))
+		{
+			log_error!(
+				self.logger,
+				"LNURL service responded with an error: {}",
+				body.get("reason").and_then(|r| r.as_str()).unwrap_or("unknown reason")
+			);
+			return Err(Error::LnurlRequestFailed);
+		}
+
+		serde_json::from_value(body).map_err(|e| {
+			log_error!(self.logger, "Failed to parse LNURL service response: {}", e);
+			Error::LnurlRequestFailed
+		})
+	}
+}
+
+fn build_http_client() -> Result<reqwest::Client, Error> {
+	reqwest::Client::builder()
+		.timeout(Duration::from_secs(LNURL_REQUEST_TIMEOUT_SECS))
+		.build()
+		.map_err(|_| Error::LnurlRequestFailed)
+}
+
+/// Decodes the given LNURL into the URL of the service.
+fn decode_lnurl(lnurl: &str) -> Result<String, Error> {
+	let lnurl = lnurl.trim();
+	let lnurl = strip_prefix_ignore_case(lnurl, "lightning:").unwrap_or(lnurl);
+
+	// LUD-17 allows to give the URL directly, with the protocol indicated by the scheme.
+	for scheme in ["lnurlp://", "lnurlw://", "lnurlc://", "keyauth://"] {
+		if let Some(rest) = strip_prefix_ignore_case(lnurl, scheme) {
+			let host = rest.split(|c| c == '/' || c == ':' || c == '?').next().unwrap_or("");
+			let url = if host.ends_with(".onion") {
+				format!("http://{}", rest)
+			} else {
+				format!("https://{}", rest)
+			};
+			return Ok(url);
+		}
+	}
+
+	let (hrp, data) = bech32::decode(lnurl).map_err(|_| Error::InvalidLnurl)?;
+	if !hrp.as_str().eq_ignore_ascii_case(LNURL_HRP) {
+		return Err(Error::InvalidLnurl);
+	}
+	let url = String::from_utf8(data).map_err(|_| Error::InvalidLnurl)?;
+
+	// LNURLs must either use HTTPS or refer to an onion service.
+	let is_onion = strip_prefix_ignore_case(&url, "http://")
+		.and_then(|rest| rest.split(|c| c == '/' || c == ':' || c == '?').next())
+		.map_or(false, |host| host.ends_with(".onion"));
+	if strip_prefix_ignore_case(&url, "https://").is_none() && !is_onion {
+		return Err(Error::InvalidLnurl);
+	}
+
+	Ok(url)
+}
+
+/// Resolves the given Lightning Address to the URL of its LNURL-pay service.
+fn lightning_address_to_url(address: &str) -> Result<String, Error> {
+	let address = address.trim();
+	let address = strip_prefix_ignore_case(address, "lightning:").unwrap_or(address);
+	let (user, domain) = address.rsplit_once('@').ok_or(Error::InvalidLightningAddress)?;
+
+	let is_valid_user = !user.is_empty()
+		&& user.chars().all(|c| {
+			c.is_ascii_lowercase()
+				|| c.is_ascii_digit()
+				|| c == '-' || c == '_'
+				|| c == '.' || c == '+'
+		});
+	if !is_valid_user {
+		return Err(Error::InvalidLightningAddress);
+	}
+
+	// The domain may include an explicit port.
+	let (host, port) = match domain.rsplit_once(':') {
+		Some((host, port)) => {
+			port.parse::<u16>().map_err(|_| Error::InvalidLightningAddress)?;
+			(host, Some(port))
+		},
+		None => (domain, None),
+	};
+	let is_valid_host = !host.is_empty()
+		&& host.split('.').all(|label| {
+			!label.is_empty() && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+		});
+	if !is_valid_host {
+		return Err(Error::InvalidLightningAddress);
+	}
+
+	let host = host.to_ascii_lowercase();
+	let scheme = if host.ends_with(".onion") { "http" } else { "https" };
+	let authority = match port {
+		Some(port) => format!("{}:{}", host, port),
+		None => host,
+	};
+	Ok(format!("{}://{}/.well-known/lnurlp/{}", scheme, authority, user))
+}
+
+fn strip_prefix_ignore_case<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+	if s.len() >= prefix.len()
+		&& s.is_char_boundary(prefix.len())
+		&& s[..prefix.len()].eq_ignore_ascii_case(prefix)
+	{
+		Some(&s[prefix.len()..])
+	} else {
+		None
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn lnurl_decoding() {
+		// Taken from https://github.com/lnurl/luds/blob/luds/01.md
+		let lnurl = "LNURL1DP68GURN8GHJ7UM9WFMXJCM99E3K7MF0V9CXJ0M385EKVCENXC6R2C35XVUKXEFCV5MKVV34X5EKZD3EV56NYD3HXQURZEPEXEJXXEPNXSCRVWFNV9NXZCN9XQ6XYEFHVGCXXCMYXYMNSERXFQ5FNS";
+		let expected = "https://service.com/api?q=3fc3645b439ce8e7f2553a69e5267081d96dcd340693afabe04be7b0ccd178df";
+		assert_eq!(decode_lnurl(lnurl).unwrap(), expected);
+		assert_eq!(decode_lnurl(&format!("lightning:{}", lnurl)).unwrap(), expected);
+		assert_eq!(decode_lnurl(&lnurl.to_lowercase()).unwrap(), expected);
+
+		assert_eq!(
+			decode_lnurl("lnurlp://service.com/api?q=1").unwrap(),
+			"https://service.com/api?q=1"
+		);
+		assert_eq!(
+			decode_lnurl("lnurlw://service.onion/api?q=1").unwrap(),
+			"http://service.onion/api?q=1"
+		);
+
+		// Bech32 strings with another HRP aren't LNURLs.
+		assert_eq!(
+			decode_lnurl("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"),
+			Err(Error::InvalidLnurl)
+		);
+		assert_eq!(decode_lnurl("not an lnurl"), Err(Error::InvalidLnurl));
+	}
+
+	#[test]
+	fn lightning_address_resolution() {
+		assert_eq!(
+			lightning_address_to_url("alice@example.com").unwrap(),
+			"https://example.com/.well-known/lnurlp/alice"
+		);
+		assert_eq!(
+			lightning_address_to_url("lightning:alice@Example.com:8080").unwrap(),
+			"https://example.com:8080/.well-known/lnurlp/alice"
+		);
+		assert_eq!(
+			lightning_address_to_url("bob.payments@service.onion").unwrap(),
+			"http://service.onion/.well-known/lnurlp/bob.payments"
+		);
+
+		for invalid in [
+			"alice",
+			"@example.com",
+			"alice@",
+			"Alice@example.com",
+			"alice@exa mple.com",
+			"alice@example.com:port",
+			"alice@example..com",
+		] {
+			assert_eq!(lightning_address_to_url(invalid), Err(Error::InvalidLightningAddress));
+		}
+	}
+}
@@ -10,16 +10,26 @@
 pub(crate) mod asynchronous;
 mod bolt11;
 mod bolt12;
+pub(crate) mod claim_watchdog;
+pub(crate) mod idempotency;
+#[cfg(feature = "lnurl")]
+mod lnurl;
 mod onchain;
+pub(crate) mod recurring;
 mod spontaneous;
 pub(crate) mod store;
 mod unified_qr;
 
-pub use bolt11::Bolt11Payment;
+pub(crate) use bolt11::ReceiveParams;
+pub use bolt11::{Bolt11Payment, FeeEstimate, Route, RouteHop};
 pub use bolt12::Bolt12Payment;
-pub use onchain::OnchainPayment;
+#[cfg(feature = "lnurl")]
+pub use lnurl::Lnurl;
+pub use onchain::{OnchainPayment, SpendableUtxo};
+pub use recurring::{RecurrenceInterval, RecurringPaymentDetails, RecurringPaymentId};
 pub use spontaneous::SpontaneousPayment;
 pub use store::{
-	ConfirmationStatus, LSPFeeLimits, PaymentDetails, PaymentDirection, PaymentKind, PaymentStatus,
+	ConfirmationStatus, LSPFeeLimits, PaymentDetails, PaymentDirection, PaymentFilter, PaymentKind,
+	PaymentKindFilter, PaymentStats, PaymentStatus,
 };
-pub use unified_qr::{QrPaymentResult, UnifiedQrPayment};
+pub use unified_qr::{PaymentPreference, QrPaymentResult, UnifiedPayment, UnifiedQrPayment};
@@ -9,11 +9,11 @@
 
 use std::sync::{Arc, RwLock};
 
-use bitcoin::{Address, Txid};
+use bitcoin::{Address, OutPoint, Txid};
 
 use crate::config::Config;
 use crate::error::Error;
-use crate::logger::{log_info, LdkLogger, Logger};
+use crate::logger::{log_error, log_info, LdkLogger, Logger};
 use crate::types::{ChannelManager, Wallet};
 use crate::wallet::OnchainSendAmount;
 
@@ -22,6 +22,11 @@ type FeeRate = bitcoin::FeeRate;
 #[cfg(feature = "uniffi")]
 type FeeRate = Arc<bitcoin::FeeRate>;
 
+#[cfg(not(feature = "uniffi"))]
+type OnchainPaymentOutput = (Address, u64);
+#[cfg(feature = "uniffi")]
+type OnchainPaymentOutput = crate::ffi::OnchainPaymentOutput;
+
 macro_rules! maybe_map_fee_rate_opt {
 	($fee_rate_opt:expr) => {{
 		#[cfg(not(feature = "uniffi"))]
@@ -35,6 +40,19 @@ macro_rules! maybe_map_fee_rate_opt {
 	}};
 }
 
+macro_rules! maybe_map_outputs {
+	($outputs:expr) => {{
+		#[cfg(not(feature = "uniffi"))]
+		{
+			$outputs
+		}
+		#[cfg(feature = "uniffi")]
+		{
+			$outputs.into_iter().map(|o| (o.address, o.amount_sats)).collect::<Vec<_>>()
+		}
+	}};
+}
+
 /// A payment handler allowing to send and receive on-chain payments.
 ///
 /// Should be retrieved by calling [`Node::onchain_payment`].
@@ -87,6 +105,131 @@ impl OnchainPayment {
 		self.wallet.send_to_address(address, send_amount, fee_rate_opt)
 	}
 
+	/// Send an on-chain payment to multiple addresses at once, using a single transaction.
+	///
+	/// Each output is given as a pair of the address to pay and the amount to send to it, in
+	/// satoshis. Batching payments this way is cheaper than sending them individually via
+	/// [`send_to_address`].
+	///
+	/// This will respect any on-chain reserve we need to keep, i.e., won't allow to cut into
+	/// [`BalanceDetails::total_anchor_channels_reserve_sats`]. If the combined amount plus fees
+	/// exceeds the spendable balance, none of the outputs will be paid.
+	///
+	/// If `fee_rate` is set it will be used on the resulting transaction. Otherwise we'll retrieve
+	/// a reasonable estimate from the configured chain source.
+	///
+	/// [`send_to_address`]: Self::send_to_address
+	/// [`BalanceDetails::total_anchor_channels_reserve_sats`]: crate::BalanceDetails::total_anchor_channels_reserve_sats
+	pub fn send_to_many(
+		&self, outputs: Vec<OnchainPaymentOutput>, fee_rate: Option<FeeRate>,
+	) -> Result<Txid, Error> {
+		if !*self.is_running.read().unwrap() {
+			return Err(Error::NotRunning);
+		}
+
+		let cur_anchor_reserve_sats =
+			crate::total_anchor_channels_reserve_sats(&self.channel_manager, &self.config);
+		let outputs = maybe_map_outputs!(outputs);
+		let fee_rate_opt = maybe_map_fee_rate_opt!(fee_rate);
+		self.wallet.send_to_many(outputs, cur_anchor_reserve_sats, fee_rate_opt)
+	}
+
+	/// Returns the absolute fee, in satoshis, a call to [`send_to_many`] with the given
+	/// parameters would currently pay, without actually sending anything.
+	///
+	/// Fails under the same conditions as [`send_to_many`] would.
+	///
+	/// [`send_to_many`]: Self::send_to_many
+	pub fn estimate_send_to_many_fee(
+		&self, outputs: Vec<OnchainPaymentOutput>, fee_rate: Option<FeeRate>,
+	) -> Result<u64, Error> {
+		if !*self.is_running.read().unwrap() {
+			return Err(Error::NotRunning);
+		}
+
+		let cur_anchor_reserve_sats =
+			crate::total_anchor_channels_reserve_sats(&self.channel_manager, &self.config);
+		let outputs = maybe_map_outputs!(outputs);
+		let fee_rate_opt = maybe_map_fee_rate_opt!(fee_rate);
+		self.wallet.estimate_send_to_many_fee(outputs, cur_anchor_reserve_sats, fee_rate_opt)
+	}
+
+	/// Send an on-chain payment to the given address, only spending the given UTXOs.
+	///
+	/// This allows for manual coin control, e.g., to avoid spending specific outputs. Any change
+	/// will be returned to our on-chain wallet. Use [`list_spendable_outputs`] to retrieve the
+	/// outputs available to be spent.
+	///
+	/// Returns [`Error::InsufficientFunds`] if the given UTXOs don't cover the amount plus fees.
+	/// This will also respect any on-chain reserve we need to keep, i.e., won't allow to cut into
+	/// [`BalanceDetails::total_anchor_channels_reserve_sats`].
+	///
+	/// If `fee_rate` is set it will be used on the resulting transaction. Otherwise we'll retrieve
+	/// a reasonable estimate from the configured chain source.
+	///
+	/// [`list_spendable_outputs`]: Self::list_spendable_outputs
+	/// [`BalanceDetails::total_anchor_channels_reserve_sats`]: crate::BalanceDetails::total_anchor_channels_reserve_sats
+	pub fn send_to_address_with_utxos(
+		&self, address: &bitcoin::Address, amount_sats: u64, utxos: Vec<OutPoint>,
+		fee_rate: Option<FeeRate>,
+	) -> Result<Txid, Error> {
+		if !*self.is_running.read().unwrap() {
+			return Err(Error::NotRunning);
+		}
+
+		let cur_anchor_reserve_sats =
+			crate::total_anchor_channels_reserve_sats(&self.channel_manager, &self.config);
+		let fee_rate_opt = maybe_map_fee_rate_opt!(fee_rate);
+		self.wallet.send_to_address_with_utxos(
+			address,
+			amount_sats,
+			utxos,
+			cur_anchor_reserve_sats,
+			fee_rate_opt,
+		)
+	}
+
+	/// Bumps the fee of an unconfirmed on-chain payment we sent, replacing it via RBF.
+	///
+	/// The replacement transaction spends the same inputs as the original one, and pays the
+	/// additional fee by reducing its change output, adding further inputs if necessary. Returns
+	/// the [`Txid`] of the replacement transaction.
+	///
+	/// Returns [`Error::NotReplaceable`] if the transaction is unknown, already confirmed, didn't
+	/// signal RBF, or funds a channel. Returns [`Error::InvalidFeeRate`] if `new_fee_rate` doesn't
+	/// strictly exceed the fee rate of the original transaction.
+	///
+	/// See [`Config::enable_rbf_by_default`] for how to have our transactions signal RBF.
+	///
+	/// [`Config::enable_rbf_by_default`]: crate::config::Config::enable_rbf_by_default
+	pub fn bump_fee(&self, txid: Txid, new_fee_rate: FeeRate) -> Result<Txid, Error> {
+		if !*self.is_running.read().unwrap() {
+			return Err(Error::NotRunning);
+		}
+
+		// Replacing a funding transaction would invalidate the corresponding channel.
+		let is_funding_tx = self
+			.channel_manager
+			.list_channels()
+			.iter()
+			.any(|c| c.funding_txo.map_or(false, |o| o.txid == txid));
+		if is_funding_tx {
+			log_error!(self.logger, "Unable to bump fee of channel funding transaction {}", txid);
+			return Err(Error::NotReplaceable);
+		}
+
+		#[cfg(feature = "uniffi")]
+		let new_fee_rate = *new_fee_rate;
+		self.wallet.bump_fee(txid, new_fee_rate)
+	}
+
+	/// Retrieves the UTXOs of our on-chain wallet that are currently available to be spent.
+	pub fn list_spendable_outputs(&self) -> Vec<SpendableUtxo> {
+		let cur_anchor_reserve_sats =
+			crate::total_anchor_channels_reserve_sats(&self.channel_manager, &self.config);
+		self.wallet.list_spendable_utxos(cur_anchor_reserve_sats)
+	}
+
 	/// Send an on-chain payment to the given address, draining the available funds.
 	///
 	/// This is useful if you have closed all channels and want to migrate funds to another
@@ -121,3 +264,27 @@ impl OnchainPayment {
 		self.wallet.send_to_address(address, send_amount, fee_rate_opt)
 	}
 }
+
+/// An unspent output of our on-chain wallet that is available to be spent.
+///
+/// Retrieved by calling [`OnchainPayment::list_spendable_outputs`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpendableUtxo {
+	/// The outpoint of the output.
+	pub outpoint: OutPoint,
+	/// The value of the output, in satoshis.
+	pub value_sats: u64,
+	/// The number of confirmations of the transaction creating the output.
+	///
+	/// Will be `0` for unconfirmed change outputs of our own transactions.
+	pub confirmations: u32,
+	/// Indicates whether this output is needed in full to keep the on-chain reserve for Anchor
+	/// channels, i.e., [`BalanceDetails::total_anchor_channels_reserve_sats`].
+	///
+	/// We consider the smallest outputs to hold the reserve. Outputs that aren't reserved may be
+	/// spent, as long as the sent amount doesn't cut into the reserve, which is then kept by the
+	/// reserved outputs and the change.
+	///
+	/// [`BalanceDetails::total_anchor_channels_reserve_sats`]: crate::BalanceDetails::total_anchor_channels_reserve_sats
+	pub is_reserved_for_anchors: bool,
+}
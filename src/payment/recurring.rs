@@ -0,0 +1,361 @@
+// This file is Copyright its original authors, visible in version control history.
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. You may not use this file except in
+// accordance with one or both of these licenses.
+
+//! Holds the schedules of recurring [BOLT 12] offer payments and the scheduler executing them.
+//!
+//! [BOLT 12]: https://github.com/lightning/bolts/blob/master/12-offer-encoding.md
+
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use lightning::impl_writeable_tlv_based;
+use lightning::impl_writeable_tlv_based_enum;
+use lightning::ln::channelmanager::PaymentId;
+use lightning::offers::offer::{Offer as LdkOffer, OfferId};
+use lightning::util::ser::{Readable, Writeable, Writer};
+use rand::RngCore;
+
+use crate::data_store::{StorableObject, StorableObjectId, StorableObjectUpdate};
+use crate::error::Error;
+use crate::event::{Event, EventQueue};
+use crate::hex_utils;
+use crate::logger::{log_error, log_info, LdkLogger, Logger};
+use crate::payment::store::PaymentStatus;
+use crate::payment::Bolt12Payment;
+use crate::types::{PaymentStore, RecurringPaymentStore};
+
+/// The interval in which we check whether any recurring payments are due.
+pub(crate) const RECURRING_PAYMENT_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A local identifier used to track a schedule of recurring payments.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct RecurringPaymentId(pub [u8; 32]);
+
+impl Writeable for RecurringPaymentId {
+	fn write<W: Writer>(&self, writer: &mut W) -> Result<(), lightning::io::Error> {
+		self.0.write(writer)
+	}
+}
+
+impl Readable for RecurringPaymentId {
+	fn read<R: lightning::io::Read>(
+		reader: &mut R,
+	) -> Result<Self, lightning::ln::msgs::DecodeError> {
+		Ok(Self(Readable::read(reader)?))
+	}
+}
+
+impl StorableObjectId for RecurringPaymentId {
+	fn encode_to_hex_str(&self) -> String {
+		hex_utils::to_string(&self.0)
+	}
+}
+
+/// The interval in which a recurring payment is made.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RecurrenceInterval {
+	/// A payment is made every day.
+	Daily,
+	/// A payment is made every week.
+	Weekly,
+	/// A payment is made every 30 days.
+	Monthly,
+	/// A payment is made every `interval_secs` seconds.
+	Custom {
+		/// The number of seconds between two payments.
+		interval_secs: u64,
+	},
+}
+
+impl RecurrenceInterval {
+	pub(crate) fn as_secs(&self) -> u64 {
+		match self {
+			Self::Daily => 24 * 60 * 60,
+			Self::Weekly => 7 * 24 * 60 * 60,
+			Self::Monthly => 30 * 24 * 60 * 60,
+			Self::Custom { interval_secs } => *interval_secs,
+		}
+	}
+}
+
+impl_writeable_tlv_based_enum!(RecurrenceInterval,
+	(0, Daily) => {},
+	(2, Weekly) => {},
+	(4, Monthly) => {},
+	(6, Custom) => {
+		(0, interval_secs, required),
+	}
+);
+
+/// Details of a schedule of recurring payments to a [BOLT 12] offer.
+///
+/// Retrieved via [`Node::list_recurring_payments`].
+///
+/// [BOLT 12]: https://github.com/lightning/bolts/blob/master/12-offer-encoding.md
+/// [`Node::list_recurring_payments`]: crate::Node::list_recurring_payments
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecurringPaymentDetails {
+	/// The identifier of the schedule.
+	pub id: RecurringPaymentId,
+	/// The ID of the offer that is paid.
+	pub offer_id: OfferId,
+	/// The amount, in thousands of a satoshi, paid on each execution.
+	pub amount_msat: u64,
+	/// The interval in which payments are made.
+	pub interval: RecurrenceInterval,
+	/// The number of payments after which the schedule ends.
+	///
+	/// Will be `None` if the schedule continues until it's cancelled.
+	pub max_payments: Option<u32>,
+	/// The number of payments that succeeded so far.
+	pub payments_made: u32,
+	/// The timestamp, in seconds since start of the UNIX epoch, at which the next payment is due.
+	pub next_payment_at_timestamp: u64,
+	/// The ID of the payment of the current execution, if it's still in flight.
+	pub pending_payment_id: Option<PaymentId>,
+}
+
+impl_writeable_tlv_based!(RecurringPaymentDetails, {
+	(0, id, required),
+	(2, offer_id, required),
+	(4, amount_msat, required),
+	(6, interval, required),
+	(8, max_payments, option),
+	(10, payments_made, required),
+	(12, next_payment_at_timestamp, required),
+	(14, pending_payment_id, option),
+});
+
+/// A persisted schedule of recurring payments, holding the offer to pay alongside the details we
+/// expose to the user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RecurringPaymentSchedule {
+	/// The offer that is paid, in its bech32 string encoding.
+	pub offer: String,
+	pub details: RecurringPaymentDetails,
+}
+
+impl_writeable_tlv_based!(RecurringPaymentSchedule, {
+	(0, offer, required),
+	(2, details, required),
+});
+
+impl StorableObject for RecurringPaymentSchedule {
+	type Id = RecurringPaymentId;
+	type Update = RecurringPaymentScheduleUpdate;
+	type IndexKey = ();
+	type Aggregate = ();
+
+	fn id(&self) -> Self::Id {
+		self.details.id
+	}
+
+	fn index_key(&self) -> Option<Self::IndexKey> {
+		None
+	}
+
+	fn update(&mut self, update: &Self::Update) -> bool {
+		debug_assert_eq!(
+			self.details.id, update.id,
+			"We should only ever override recurring payment data for the same schedule id"
+		);
+
+		let mut updated = false;
+
+		if self.details.payments_made != update.payments_made {
+			self.details.payments_made = update.payments_made;
+			updated = true;
+		}
+
+		if self.details.next_payment_at_timestamp != update.next_payment_at_timestamp {
+			self.details.next_payment_at_timestamp = update.next_payment_at_timestamp;
+			updated = true;
+		}
+
+		if self.details.pending_payment_id != update.pending_payment_id {
+			self.details.pending_payment_id = update.pending_payment_id;
+			updated = true;
+		}
+
+		updated
+	}
+
+	fn to_update(&self) -> Self::Update {
+		RecurringPaymentScheduleUpdate {
+			id: self.details.id,
+			payments_made: self.details.payments_made,
+			next_payment_at_timestamp: self.details.next_payment_at_timestamp,
+			pending_payment_id: self.details.pending_payment_id,
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct RecurringPaymentScheduleUpdate {
+	pub id: RecurringPaymentId,
+	pub payments_made: u32,
+	pub next_payment_at_timestamp: u64,
+	pub pending_payment_id: Option<PaymentId>,
+}
+
+impl StorableObjectUpdate<RecurringPaymentSchedule> for RecurringPaymentScheduleUpdate {
+	fn id(&self) -> <RecurringPaymentSchedule as StorableObject>::Id {
+		self.id
+	}
+}
+
+/// Executes the recurring payments that are due.
+///
+/// Each execution requests a fresh invoice for the schedule's offer and pays it. A schedule's next
+/// execution is only started once the payment of the prior one succeeded or failed, i.e.,
+/// executions of the same schedule never overlap.
+pub(crate) struct RecurringPaymentScheduler {
+	recurring_payment_store: Arc<RecurringPaymentStore>,
+	payment_store: Arc<PaymentStore>,
+	bolt12_payment: Bolt12Payment,
+	event_queue: Arc<EventQueue<Arc<Logger>>>,
+	logger: Arc<Logger>,
+}
+
+impl RecurringPaymentScheduler {
+	pub(crate) fn new(
+		recurring_payment_store: Arc<RecurringPaymentStore>, payment_store: Arc<PaymentStore>,
+		bolt12_payment: Bolt12Payment, event_queue: Arc<EventQueue<Arc<Logger>>>,
+		logger: Arc<Logger>,
+	) -> Self {
+		Self { recurring_payment_store, payment_store, bolt12_payment, event_queue, logger }
+	}
+
+	pub(crate) fn process_schedules(&self) {
+		let now_secs = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+		for schedule in self.recurring_payment_store.list_filter(|_| true) {
+			self.process_schedule(schedule, now_secs);
+		}
+	}
+
+	fn process_schedule(&self, schedule: RecurringPaymentSchedule, now_secs: u64) {
+		let mut details = schedule.details;
+		let mut events = Vec::new();
+
+		if let Some(payment_id) = details.pending_payment_id {
+			match self.payment_store.get(&payment_id).map(|p| p.status) {
+				Some(PaymentStatus::Pending) => return,
+				Some(PaymentStatus::Succeeded) => {
+					details.payments_made += 1;
+					events.push(Event::RecurringPaymentExecuted {
+						recurring_payment_id: details.id,
+						payment_id,
+						payments_made: details.payments_made,
+					});
+				},
+				_ => {
+					events.push(Event::RecurringPaymentFailed {
+						recurring_payment_id: details.id,
+						payment_id: Some(payment_id),
+					});
+				},
+			}
+			details.pending_payment_id = None;
+		}
+
+		let finished = details.max_payments.map_or(false, |max| details.payments_made >= max);
+		if finished {
+			log_info!(
+				self.logger,
+				"Recurring payment {} finished after {} payments",
+				hex_utils::to_string(&details.id.0),
+				details.payments_made
+			);
+			if let Err(e) = self.recurring_payment_store.remove(&details.id) {
+				log_error!(self.logger, "Failed to remove finished recurring payment: {}", e);
+				return;
+			}
+		} else {
+			if details.next_payment_at_timestamp <= now_secs {
+				// If we missed executions, e.g., as we were offline, we don't try to catch up but
+				// continue with the next interval.
+				let interval_secs = details.interval.as_secs();
+				let mut next_payment_at_timestamp =
+					details.next_payment_at_timestamp.saturating_add(interval_secs);
+				if next_payment_at_timestamp <= now_secs {
+					next_payment_at_timestamp = now_secs.saturating_add(interval_secs);
+				}
+				details.next_payment_at_timestamp = next_payment_at_timestamp;
+
+				// We persist the execution before initiating the payment, so that we never pay
+				// twice for the same interval, e.g., if we crash right after paying.
+				let mut random_bytes = [0u8; 32];
+				rand::thread_rng().fill_bytes(&mut random_bytes);
+				let payment_id = PaymentId(random_bytes);
+				details.pending_payment_id = Some(payment_id);
+				if let Err(e) = self.persist_details(&details) {
+					log_error!(self.logger, "Failed to update recurring payment: {}", e);
+					return;
+				}
+
+				match self.initiate_payment(&schedule.offer, details.amount_msat, payment_id) {
+					Ok(()) => {
+						log_info!(
+							self.logger,
+							"Initiated payment {} for recurring payment {}",
+							payment_id,
+							hex_utils::to_string(&details.id.0)
+						);
+					},
+					Err(e) => {
+						log_error!(
+							self.logger,
+							"Failed to initiate payment for recurring payment {}: {}",
+							hex_utils::to_string(&details.id.0),
+							e
+						);
+						events.push(Event::RecurringPaymentFailed {
+							recurring_payment_id: details.id,
+							payment_id: None,
+						});
+						// If we fail to persist this, we'll report the failure again, this time
+						// with the ID of the payment that was never initiated.
+						details.pending_payment_id = None;
+						if let Err(e) = self.persist_details(&details) {
+							log_error!(self.logger, "Failed to update recurring payment: {}", e);
+							return;
+						}
+					},
+				}
+			} else if let Err(e) = self.persist_details(&details) {
+				log_error!(self.logger, "Failed to update recurring payment: {}", e);
+				return;
+			}
+		}
+
+		for event in events {
+			if let Err(e) = self.event_queue.add_event(event) {
+				log_error!(self.logger, "Failed to push to event queue: {}", e);
+			}
+		}
+	}
+
+	fn persist_details(&self, details: &RecurringPaymentDetails) -> Result<(), Error> {
+		let update = RecurringPaymentScheduleUpdate {
+			id: details.id,
+			payments_made: details.payments_made,
+			next_payment_at_timestamp: details.next_payment_at_timestamp,
+			pending_payment_id: details.pending_payment_id,
+		};
+		self.recurring_payment_store.update(&update).map(|_| ())
+	}
+
+	fn initiate_payment(
+		&self, offer: &str, amount_msat: u64, payment_id: PaymentId,
+	) -> Result<(), Error> {
+		let offer = LdkOffer::from_str(offer).map_err(|_| Error::InvalidOffer)?;
+		self.bolt12_payment
+			.send_using_amount_inner(&offer, amount_msat, None, None, payment_id)
+			.map(|_| ())
+	}
+}
@@ -10,20 +10,27 @@
 use std::sync::{Arc, RwLock};
 
 use bitcoin::secp256k1::PublicKey;
-use lightning::ln::channelmanager::{PaymentId, RecipientOnionFields, Retry, RetryableSendFailure};
-use lightning::routing::router::{PaymentParameters, RouteParameters, RouteParametersConfig};
+use lightning::ln::channelmanager::{PaymentId, RecipientOnionFields, RetryableSendFailure};
+use lightning::routing::router::{PaymentParameters, Router as LdkRouter};
 use lightning::sign::EntropySource;
 use lightning_types::payment::{PaymentHash, PaymentPreimage};
 
-use crate::config::{Config, LDK_PAYMENT_RETRY_TIMEOUT};
+use crate::config::{Config, SendPaymentParams};
 use crate::error::Error;
 use crate::logger::{log_error, log_info, LdkLogger, Logger};
+use crate::payment::idempotency::PaymentIdempotencyKeys;
 use crate::payment::store::{PaymentDetails, PaymentDirection, PaymentKind, PaymentStatus};
-use crate::types::{ChannelManager, CustomTlvRecord, KeysManager, PaymentStore};
+use crate::payment::FeeEstimate;
+use crate::types::{
+	ChannelManager, CustomTlvRecord, KeysManager, PaymentRetryStrategies, PaymentStore, Router,
+};
 
 // The default `final_cltv_expiry_delta` we apply when not set.
 const LDK_DEFAULT_FINAL_CLTV_EXPIRY_DELTA: u32 = 144;
 
+// The smallest TLV type number permitted for custom records, per BOLT 4.
+const MIN_CUSTOM_TLV_TYPE_NUM: u64 = 1 << 16;
+
 /// A payment handler allowing to send spontaneous ("keysend") payments.
 ///
 /// Should be retrieved by calling [`Node::spontaneous_payment`].
@@ -31,8 +38,11 @@ const LDK_DEFAULT_FINAL_CLTV_EXPIRY_DELTA: u32 = 144;
 /// [`Node::spontaneous_payment`]: crate::Node::spontaneous_payment
 pub struct SpontaneousPayment {
 	channel_manager: Arc<ChannelManager>,
+	router: Arc<Router>,
 	keys_manager: Arc<KeysManager>,
 	payment_store: Arc<PaymentStore>,
+	retry_strategies: Arc<PaymentRetryStrategies>,
+	idempotency_keys: Arc<PaymentIdempotencyKeys>,
 	config: Arc<Config>,
 	is_running: Arc<RwLock<bool>>,
 	logger: Arc<Logger>,
@@ -40,59 +50,115 @@ pub struct SpontaneousPayment {
 
 impl SpontaneousPayment {
 	pub(crate) fn new(
-		channel_manager: Arc<ChannelManager>, keys_manager: Arc<KeysManager>,
-		payment_store: Arc<PaymentStore>, config: Arc<Config>, is_running: Arc<RwLock<bool>>,
-		logger: Arc<Logger>,
+		channel_manager: Arc<ChannelManager>, router: Arc<Router>, keys_manager: Arc<KeysManager>,
+		payment_store: Arc<PaymentStore>, retry_strategies: Arc<PaymentRetryStrategies>,
+		idempotency_keys: Arc<PaymentIdempotencyKeys>, config: Arc<Config>,
+		is_running: Arc<RwLock<bool>>, logger: Arc<Logger>,
 	) -> Self {
-		Self { channel_manager, keys_manager, payment_store, config, is_running, logger }
+		Self {
+			channel_manager,
+			router,
+			keys_manager,
+			payment_store,
+			retry_strategies,
+			idempotency_keys,
+			config,
+			is_running,
+			logger,
+		}
 	}
 
 	/// Send a spontaneous aka. "keysend", payment.
 	///
-	/// If `route_parameters` are provided they will override the default as well as the
-	/// node-wide parameters configured via [`Config::route_parameters`] on a per-field basis.
+	/// If `send_params` are provided they will override the default as well as the node-wide
+	/// parameters configured via [`Config::route_parameters`] on a per-field basis. If the
+	/// payment fails after its [`SendPaymentParams::retry_strategy`] was exhausted, the
+	/// respective [`Event::PaymentFailed`] will indicate the bound that was hit.
+	///
+	/// [`Event::PaymentFailed`]: crate::Event::PaymentFailed
 	pub fn send(
-		&self, amount_msat: u64, node_id: PublicKey,
-		route_parameters: Option<RouteParametersConfig>,
+		&self, amount_msat: u64, node_id: PublicKey, send_params: Option<SendPaymentParams>,
 	) -> Result<PaymentId, Error> {
-		self.send_inner(amount_msat, node_id, route_parameters, None, None)
+		self.send_inner(amount_msat, node_id, send_params, None, None)
 	}
 
 	/// Send a spontaneous payment including a list of custom TLVs.
+	///
+	/// The custom TLVs will be included in the payment onion and surfaced to the recipient via
+	/// [`Event::PaymentReceived::custom_records`].
+	///
+	/// All TLV type numbers need to be odd and at least `2^16`, otherwise
+	/// [`Error::InvalidCustomTlvs`] is returned.
+	///
+	/// [`Event::PaymentReceived::custom_records`]: crate::Event::PaymentReceived::custom_records
 	pub fn send_with_custom_tlvs(
-		&self, amount_msat: u64, node_id: PublicKey,
-		route_parameters: Option<RouteParametersConfig>, custom_tlvs: Vec<CustomTlvRecord>,
+		&self, amount_msat: u64, node_id: PublicKey, send_params: Option<SendPaymentParams>,
+		custom_tlvs: Vec<CustomTlvRecord>,
 	) -> Result<PaymentId, Error> {
-		self.send_inner(amount_msat, node_id, route_parameters, Some(custom_tlvs), None)
+		self.send_inner(amount_msat, node_id, send_params, Some(custom_tlvs), None)
 	}
 
 	/// Send a spontaneous payment with custom preimage
 	pub fn send_with_preimage(
 		&self, amount_msat: u64, node_id: PublicKey, preimage: PaymentPreimage,
-		route_parameters: Option<RouteParametersConfig>,
+		send_params: Option<SendPaymentParams>,
 	) -> Result<PaymentId, Error> {
-		self.send_inner(amount_msat, node_id, route_parameters, None, Some(preimage))
+		self.send_inner(amount_msat, node_id, send_params, None, Some(preimage))
 	}
 
 	/// Send a spontaneous payment with custom preimage including a list of custom TLVs.
+	///
+	/// See [`Self::send_with_custom_tlvs`] for the requirements the custom TLVs need to meet.
 	pub fn send_with_preimage_and_custom_tlvs(
 		&self, amount_msat: u64, node_id: PublicKey, custom_tlvs: Vec<CustomTlvRecord>,
-		preimage: PaymentPreimage, route_parameters: Option<RouteParametersConfig>,
+		preimage: PaymentPreimage, send_params: Option<SendPaymentParams>,
 	) -> Result<PaymentId, Error> {
-		self.send_inner(amount_msat, node_id, route_parameters, Some(custom_tlvs), Some(preimage))
+		self.send_inner(amount_msat, node_id, send_params, Some(custom_tlvs), Some(preimage))
 	}
 
 	fn send_inner(
-		&self, amount_msat: u64, node_id: PublicKey,
-		route_parameters: Option<RouteParametersConfig>, custom_tlvs: Option<Vec<CustomTlvRecord>>,
-		preimage: Option<PaymentPreimage>,
+		&self, amount_msat: u64, node_id: PublicKey, send_params: Option<SendPaymentParams>,
+		custom_tlvs: Option<Vec<CustomTlvRecord>>, preimage: Option<PaymentPreimage>,
+	) -> Result<PaymentId, Error> {
+		let payment_preimage = preimage
+			.unwrap_or_else(|| PaymentPreimage(self.keys_manager.get_secure_random_bytes()));
+		let payment_id = PaymentId(PaymentHash::from(payment_preimage).0);
+		let send_params = send_params.unwrap_or_default();
+		let idempotency_key = send_params.idempotency_key.clone();
+		let on_duplicate = send_params.on_duplicate.unwrap_or_default();
+		self.idempotency_keys.send(idempotency_key, on_duplicate, payment_id, || {
+			self.send_with_preimage_inner(
+				amount_msat,
+				node_id,
+				send_params,
+				custom_tlvs,
+				payment_preimage,
+			)
+		})
+	}
+
+	fn send_with_preimage_inner(
+		&self, amount_msat: u64, node_id: PublicKey, send_params: SendPaymentParams,
+		custom_tlvs: Option<Vec<CustomTlvRecord>>, payment_preimage: PaymentPreimage,
 	) -> Result<PaymentId, Error> {
 		if !*self.is_running.read().unwrap() {
 			return Err(Error::NotRunning);
 		}
 
-		let payment_preimage = preimage
-			.unwrap_or_else(|| PaymentPreimage(self.keys_manager.get_secure_random_bytes()));
+		if let Some(tlvs) = custom_tlvs.as_ref() {
+			if let Some(tlv) = tlvs
+				.iter()
+				.find(|tlv| tlv.type_num < MIN_CUSTOM_TLV_TYPE_NUM || tlv.type_num % 2 == 0)
+			{
+				log_error!(
+					self.logger,
+					"Failed to send payment due to invalid custom TLV type number {}: must be odd and at least {}.",
+					tlv.type_num,
+					MIN_CUSTOM_TLV_TYPE_NUM,
+				);
+				return Err(Error::InvalidCustomTlvs);
+			}
+		}
 
 		let payment_hash = PaymentHash::from(payment_preimage);
 		let payment_id = PaymentId(payment_hash.0);
@@ -106,24 +172,12 @@ impl SpontaneousPayment {
 			}
 		}
 
-		let mut route_params = RouteParameters::from_payment_params_and_value(
+		let route_params = send_params.route_params(
+			&self.config,
 			PaymentParameters::from_node_id(node_id, LDK_DEFAULT_FINAL_CLTV_EXPIRY_DELTA),
 			amount_msat,
 		);
-
-		if let Some(RouteParametersConfig {
-			max_total_routing_fee_msat,
-			max_total_cltv_expiry_delta,
-			max_path_count,
-			max_channel_saturation_power_of_half,
-		}) = route_parameters.as_ref().or(self.config.route_parameters.as_ref())
-		{
-			route_params.max_total_routing_fee_msat = *max_total_routing_fee_msat;
-			route_params.payment_params.max_total_cltv_expiry_delta = *max_total_cltv_expiry_delta;
-			route_params.payment_params.max_path_count = *max_path_count;
-			route_params.payment_params.max_channel_saturation_power_of_half =
-				*max_channel_saturation_power_of_half;
-		}
+		let retry_strategy = send_params.retry_strategy_or_default();
 
 		let recipient_fields = match custom_tlvs {
 			Some(tlvs) => RecipientOnionFields::spontaneous_empty()
@@ -140,9 +194,10 @@ impl SpontaneousPayment {
 			recipient_fields,
 			PaymentId(payment_hash.0),
 			route_params,
-			Retry::Timeout(LDK_PAYMENT_RETRY_TIMEOUT),
+			retry_strategy.into(),
 		) {
 			Ok(_hash) => {
+				self.retry_strategies.lock().unwrap().insert(payment_id, retry_strategy);
 				log_info!(self.logger, "Initiated sending {}msat to {}.", amount_msat, node_id);
 
 				let kind = PaymentKind::Spontaneous {
@@ -188,6 +243,43 @@ impl SpontaneousPayment {
 		}
 	}
 
+	/// Estimates the fee of sending a spontaneous payment of the given amount to the given
+	/// `node_id` without actually sending the payment.
+	///
+	/// Will return [`Error::RouteNotFound`] if no route to `node_id` could be found.
+	///
+	/// See [`Bolt11Payment::estimate_fee`] for more information.
+	///
+	/// [`Bolt11Payment::estimate_fee`]: crate::payment::Bolt11Payment::estimate_fee
+	pub fn estimate_fee(&self, amount_msat: u64, node_id: PublicKey) -> Result<FeeEstimate, Error> {
+		if !*self.is_running.read().unwrap() {
+			return Err(Error::NotRunning);
+		}
+
+		let route_params = SendPaymentParams::default().route_params(
+			&self.config,
+			PaymentParameters::from_node_id(node_id, LDK_DEFAULT_FINAL_CLTV_EXPIRY_DELTA),
+			amount_msat,
+		);
+
+		let first_hops = self.channel_manager.list_usable_channels();
+		let first_hops = first_hops.iter().collect::<Vec<_>>();
+		let route = self
+			.router
+			.find_route(
+				&self.channel_manager.get_our_node_id(),
+				&route_params,
+				Some(&first_hops),
+				self.channel_manager.compute_inflight_htlcs(),
+			)
+			.map_err(|e| {
+				log_error!(self.logger, "Failed to find a route to estimate fee: {}", e);
+				Error::RouteNotFound
+			})?;
+
+		Ok(FeeEstimate::from_route(&route))
+	}
+
 	/// Sends payment probes over all paths of a route that would be used to pay the given
 	/// amount to the given `node_id`.
 	///
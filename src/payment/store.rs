@@ -7,11 +7,12 @@
 
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+use bitcoin::secp256k1::PublicKey;
 use bitcoin::{BlockHash, Txid};
 use lightning::ln::channelmanager::PaymentId;
 use lightning::ln::msgs::DecodeError;
 use lightning::offers::offer::OfferId;
-use lightning::util::ser::{Readable, Writeable};
+use lightning::util::ser::{Readable, WithoutLength, Writeable};
 use lightning::{
 	_init_and_read_len_prefixed_tlv_fields, impl_writeable_tlv_based,
 	impl_writeable_tlv_based_enum, write_tlv_fields,
@@ -19,8 +20,11 @@ use lightning::{
 use lightning_types::payment::{PaymentHash, PaymentPreimage, PaymentSecret};
 use lightning_types::string::UntrustedString;
 
-use crate::data_store::{StorableObject, StorableObjectId, StorableObjectUpdate};
+use crate::data_store::{
+	StorableObject, StorableObjectAggregate, StorableObjectId, StorableObjectUpdate,
+};
 use crate::hex_utils;
+use crate::types::UserChannelId;
 
 /// Represents a payment.
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -46,6 +50,25 @@ pub struct PaymentDetails {
 	pub status: PaymentStatus,
 	/// The timestamp, in seconds since start of the UNIX epoch, when this entry was last updated.
 	pub latest_update_timestamp: u64,
+	/// The node ids of the hops along which the payment succeeded, ending with the recipient.
+	///
+	/// For multi-path payments, this holds the hops of the first path that succeeded. For
+	/// payments to blinded paths, the hops end with the introduction node of the blinded path.
+	///
+	/// Will only be set for outbound Lightning payments that succeeded.
+	pub route_hops: Option<Vec<PublicKey>>,
+	/// The timestamp, in seconds since start of the UNIX epoch, when the payment succeeded.
+	///
+	/// Will only be set for Lightning payments that succeeded.
+	pub settled_at_unix_secs: Option<u64>,
+	/// The number of seconds after which a manually-claimable inbound payment is failed back if
+	/// it hasn't been claimed, counted from when it became claimable.
+	///
+	/// Will only be set for payments registered via the `_with_claim_timeout` variants, e.g.,
+	/// [`Bolt11Payment::receive_for_hash_with_claim_timeout`].
+	///
+	/// [`Bolt11Payment::receive_for_hash_with_claim_timeout`]: crate::payment::Bolt11Payment::receive_for_hash_with_claim_timeout
+	pub claim_timeout_secs: Option<u64>,
 }
 
 impl PaymentDetails {
@@ -57,7 +80,43 @@ impl PaymentDetails {
 			.duration_since(UNIX_EPOCH)
 			.unwrap_or(Duration::from_secs(0))
 			.as_secs();
-		Self { id, kind, amount_msat, fee_paid_msat, direction, status, latest_update_timestamp }
+		let route_hops = None;
+		let settled_at_unix_secs = None;
+		let claim_timeout_secs = None;
+		Self {
+			id,
+			kind,
+			amount_msat,
+			fee_paid_msat,
+			direction,
+			status,
+			latest_update_timestamp,
+			route_hops,
+			settled_at_unix_secs,
+			claim_timeout_secs,
+		}
+	}
+
+	/// Returns the preimage of the payment if it succeeded.
+	///
+	/// For outbound payments, the preimage serves as proof of payment, e.g., to be presented as a
+	/// receipt. It is persisted with the payment and hence remains available until the payment is
+	/// removed. For payments that didn't succeed, `None` is returned even if the preimage is
+	/// already known, e.g., as we picked it for a spontaneous payment.
+	pub fn preimage(&self) -> Option<PaymentPreimage> {
+		if self.status != PaymentStatus::Succeeded {
+			return None;
+		}
+
+		match self.kind {
+			PaymentKind::Bolt11 { preimage, .. } => preimage,
+			PaymentKind::Bolt11Jit { preimage, .. } => preimage,
+			PaymentKind::Bolt12Offer { preimage, .. } => preimage,
+			PaymentKind::Bolt12Refund { preimage, .. } => preimage,
+			PaymentKind::Spontaneous { preimage, .. } => preimage,
+			PaymentKind::Rebalance { preimage, .. } => preimage,
+			_ => None,
+		}
 	}
 }
 
@@ -77,7 +136,10 @@ impl Writeable for PaymentDetails {
 			(6, self.amount_msat, required),
 			(7, self.fee_paid_msat, option),
 			(8, self.direction, required),
-			(10, self.status, required)
+			(10, self.status, required),
+			(11, self.route_hops.as_ref().map(WithoutLength), option),
+			(13, self.settled_at_unix_secs, option),
+			(15, self.claim_timeout_secs, option),
 		});
 		Ok(())
 	}
@@ -99,7 +161,10 @@ impl Readable for PaymentDetails {
 			(6, amount_msat, required),
 			(7, fee_paid_msat, option),
 			(8, direction, required),
-			(10, status, required)
+			(10, status, required),
+			(11, route_hops, (option, encoding: (Vec<PublicKey>, WithoutLength))),
+			(13, settled_at_unix_secs, option),
+			(15, claim_timeout_secs, option),
 		});
 
 		let id: PaymentId = id.0.ok_or(DecodeError::InvalidValue)?;
@@ -153,6 +218,9 @@ impl Readable for PaymentDetails {
 			direction,
 			status,
 			latest_update_timestamp,
+			route_hops,
+			settled_at_unix_secs,
+			claim_timeout_secs,
 		})
 	}
 }
@@ -165,11 +233,25 @@ impl StorableObjectId for PaymentId {
 impl StorableObject for PaymentDetails {
 	type Id = PaymentId;
 	type Update = PaymentDetailsUpdate;
+	type IndexKey = PaymentHash;
+	type Aggregate = PaymentStats;
 
 	fn id(&self) -> Self::Id {
 		self.id
 	}
 
+	fn index_key(&self) -> Option<Self::IndexKey> {
+		match self.kind {
+			PaymentKind::Bolt11 { hash, .. } => Some(hash),
+			PaymentKind::Bolt11Jit { hash, .. } => Some(hash),
+			PaymentKind::Bolt12Offer { hash, .. } => hash,
+			PaymentKind::Bolt12Refund { hash, .. } => hash,
+			PaymentKind::Spontaneous { hash, .. } => Some(hash),
+			PaymentKind::Rebalance { hash, .. } => Some(hash),
+			_ => None,
+		}
+	}
+
 	fn update(&mut self, update: &Self::Update) -> bool {
 		debug_assert_eq!(
 			self.id, update.id,
@@ -236,6 +318,9 @@ impl StorableObject for PaymentDetails {
 				PaymentKind::Spontaneous { ref mut preimage, .. } => {
 					update_if_necessary!(*preimage, preimage_opt)
 				},
+				PaymentKind::Rebalance { ref mut preimage, .. } => {
+					update_if_necessary!(*preimage, preimage_opt)
+				},
 				_ => {},
 			}
 		}
@@ -254,6 +339,9 @@ impl StorableObject for PaymentDetails {
 				PaymentKind::Bolt12Refund { ref mut secret, .. } => {
 					update_if_necessary!(*secret, secret_opt)
 				},
+				PaymentKind::Rebalance { ref mut secret, .. } => {
+					update_if_necessary!(*secret, secret_opt)
+				},
 				_ => {},
 			}
 		}
@@ -282,6 +370,20 @@ impl StorableObject for PaymentDetails {
 			update_if_necessary!(self.status, status);
 		}
 
+		if let Some(route_hops_opt) = update.route_hops.as_ref() {
+			if self.route_hops.is_none() || route_hops_opt.is_none() {
+				// We only keep the hops of the first successful path.
+				update_if_necessary!(self.route_hops, route_hops_opt.clone());
+			}
+		}
+
+		if let Some(settled_at_opt) = update.settled_at_unix_secs {
+			if self.settled_at_unix_secs.is_none() || settled_at_opt.is_none() {
+				// Keep the original settlement time if an event is replayed.
+				update_if_necessary!(self.settled_at_unix_secs, settled_at_opt);
+			}
+		}
+
 		if let Some(confirmation_status) = update.confirmation_status {
 			match self.kind {
 				PaymentKind::Onchain { ref mut status, .. } => {
@@ -443,6 +545,23 @@ pub enum PaymentKind {
 		/// The pre-image used by the payment.
 		preimage: Option<PaymentPreimage>,
 	},
+	/// A circular payment to ourselves shifting balance between two of our channels.
+	///
+	/// See [`Node::rebalance_channel`] for more information.
+	///
+	/// [`Node::rebalance_channel`]: crate::Node::rebalance_channel
+	Rebalance {
+		/// The payment hash, i.e., the hash of the `preimage`.
+		hash: PaymentHash,
+		/// The pre-image used by the payment.
+		preimage: Option<PaymentPreimage>,
+		/// The secret used by the payment.
+		secret: Option<PaymentSecret>,
+		/// The local `user_channel_id` of the channel the payment was sent out on.
+		from_user_channel_id: UserChannelId,
+		/// The local `user_channel_id` of the channel the payment was received back on.
+		to_user_channel_id: UserChannelId,
+	},
 }
 
 impl_writeable_tlv_based_enum!(PaymentKind,
@@ -480,9 +599,159 @@ impl_writeable_tlv_based_enum!(PaymentKind,
 		(2, preimage, option),
 		(3, quantity, option),
 		(4, secret, option),
+	},
+	(12, Rebalance) => {
+		(0, hash, required),
+		(2, preimage, option),
+		(4, secret, option),
+		(6, from_user_channel_id, required),
+		(8, to_user_channel_id, required),
 	}
 );
 
+/// Represents the kind of a payment, without any of the associated data.
+///
+/// Used to filter payments by kind, see [`PaymentFilter::kind`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PaymentKindFilter {
+	/// Matches [`PaymentKind::Onchain`] payments.
+	Onchain,
+	/// Matches [`PaymentKind::Bolt11`] payments.
+	Bolt11,
+	/// Matches [`PaymentKind::Bolt11Jit`] payments.
+	Bolt11Jit,
+	/// Matches [`PaymentKind::Bolt12Offer`] payments.
+	Bolt12Offer,
+	/// Matches [`PaymentKind::Bolt12Refund`] payments.
+	Bolt12Refund,
+	/// Matches [`PaymentKind::Spontaneous`] payments.
+	Spontaneous,
+	/// Matches [`PaymentKind::Rebalance`] payments.
+	Rebalance,
+}
+
+impl PaymentKindFilter {
+	fn matches(&self, kind: &PaymentKind) -> bool {
+		matches!(
+			(self, kind),
+			(Self::Onchain, PaymentKind::Onchain { .. })
+				| (Self::Bolt11, PaymentKind::Bolt11 { .. })
+				| (Self::Bolt11Jit, PaymentKind::Bolt11Jit { .. })
+				| (Self::Bolt12Offer, PaymentKind::Bolt12Offer { .. })
+				| (Self::Bolt12Refund, PaymentKind::Bolt12Refund { .. })
+				| (Self::Spontaneous, PaymentKind::Spontaneous { .. })
+				| (Self::Rebalance, PaymentKind::Rebalance { .. })
+		)
+	}
+}
+
+/// Selects which payments to return from [`Node::query_payments`] and to count in
+/// [`Node::count_payments`].
+///
+/// Criteria that are unset match any payment. Use [`PaymentFilter::default`] to match all
+/// payments.
+///
+/// [`Node::query_payments`]: crate::Node::query_payments
+/// [`Node::count_payments`]: crate::Node::count_payments
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PaymentFilter {
+	/// Only match payments of the given direction.
+	pub direction: Option<PaymentDirection>,
+	/// Only match payments with the given status.
+	pub status: Option<PaymentStatus>,
+	/// Only match payments of the given kind.
+	pub kind: Option<PaymentKindFilter>,
+	/// Only match payments last updated at or after the given time, in seconds since the UNIX
+	/// epoch.
+	pub after_unix_secs: Option<u64>,
+	/// Only match payments last updated before the given time, in seconds since the UNIX epoch.
+	pub before_unix_secs: Option<u64>,
+	/// The maximum number of payments to return.
+	///
+	/// This is ignored by [`Node::count_payments`].
+	///
+	/// [`Node::count_payments`]: crate::Node::count_payments
+	pub limit: Option<u32>,
+	/// The number of matching payments to skip before returning results.
+	///
+	/// This is ignored by [`Node::count_payments`].
+	///
+	/// [`Node::count_payments`]: crate::Node::count_payments
+	pub offset: u32,
+}
+
+impl PaymentFilter {
+	pub(crate) fn matches(&self, payment: &PaymentDetails) -> bool {
+		self.direction.map_or(true, |d| payment.direction == d)
+			&& self.status.map_or(true, |s| payment.status == s)
+			&& self.kind.map_or(true, |k| k.matches(&payment.kind))
+			&& self.after_unix_secs.map_or(true, |t| payment.latest_update_timestamp >= t)
+			&& self.before_unix_secs.map_or(true, |t| payment.latest_update_timestamp < t)
+	}
+}
+
+/// Aggregated payment amounts and fees, as returned by [`Node::payment_stats`].
+///
+/// [`Node::payment_stats`]: crate::Node::payment_stats
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PaymentStats {
+	/// The total amount, in thousandths of a satoshi, of all succeeded outbound payments.
+	pub sent_msat: u64,
+	/// The total amount, in thousandths of a satoshi, of all succeeded inbound payments.
+	pub received_msat: u64,
+	/// The total fees, in thousandths of a satoshi, paid for all succeeded outbound payments.
+	pub fees_paid_msat: u64,
+	/// The total fees, in thousandths of a satoshi, earned from forwarding payments.
+	pub forwarding_fees_earned_msat: u64,
+	/// The number of pending payments.
+	pub num_pending: u64,
+	/// The number of succeeded payments.
+	pub num_succeeded: u64,
+	/// The number of failed payments.
+	pub num_failed: u64,
+}
+
+impl PaymentStats {
+	pub(crate) fn add_payment(&mut self, payment: &PaymentDetails) {
+		self.apply_payment(payment, u64::saturating_add);
+	}
+
+	pub(crate) fn remove_payment(&mut self, payment: &PaymentDetails) {
+		self.apply_payment(payment, u64::saturating_sub);
+	}
+
+	fn apply_payment(&mut self, payment: &PaymentDetails, op: fn(u64, u64) -> u64) {
+		match payment.status {
+			PaymentStatus::Pending => self.num_pending = op(self.num_pending, 1),
+			PaymentStatus::Failed => self.num_failed = op(self.num_failed, 1),
+			PaymentStatus::Succeeded => {
+				self.num_succeeded = op(self.num_succeeded, 1);
+				let amount_msat = payment.amount_msat.unwrap_or(0);
+				match payment.direction {
+					PaymentDirection::Outbound => {
+						self.sent_msat = op(self.sent_msat, amount_msat);
+						self.fees_paid_msat =
+							op(self.fees_paid_msat, payment.fee_paid_msat.unwrap_or(0));
+					},
+					PaymentDirection::Inbound => {
+						self.received_msat = op(self.received_msat, amount_msat);
+					},
+				}
+			},
+		}
+	}
+}
+
+impl StorableObjectAggregate<PaymentDetails> for PaymentStats {
+	fn add(&mut self, payment: &PaymentDetails) {
+		self.add_payment(payment);
+	}
+
+	fn remove(&mut self, payment: &PaymentDetails) {
+		self.remove_payment(payment);
+	}
+}
+
 /// Represents the confirmation status of a transaction.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum ConfirmationStatus {
@@ -540,6 +809,8 @@ pub(crate) struct PaymentDetailsUpdate {
 	pub direction: Option<PaymentDirection>,
 	pub status: Option<PaymentStatus>,
 	pub confirmation_status: Option<ConfirmationStatus>,
+	pub route_hops: Option<Option<Vec<PublicKey>>>,
+	pub settled_at_unix_secs: Option<Option<u64>>,
 }
 
 impl PaymentDetailsUpdate {
@@ -555,6 +826,8 @@ impl PaymentDetailsUpdate {
 			direction: None,
 			status: None,
 			confirmation_status: None,
+			route_hops: None,
+			settled_at_unix_secs: None,
 		}
 	}
 }
@@ -567,6 +840,7 @@ impl From<&PaymentDetails> for PaymentDetailsUpdate {
 			PaymentKind::Bolt12Offer { hash, preimage, secret, .. } => (hash, preimage, secret),
 			PaymentKind::Bolt12Refund { hash, preimage, secret, .. } => (hash, preimage, secret),
 			PaymentKind::Spontaneous { hash, preimage, .. } => (Some(hash), preimage, None),
+			PaymentKind::Rebalance { hash, preimage, secret, .. } => (Some(hash), preimage, secret),
 			_ => (None, None, None),
 		};
 
@@ -593,6 +867,8 @@ impl From<&PaymentDetails> for PaymentDetailsUpdate {
 			direction: Some(value.direction),
 			status: Some(value.status),
 			confirmation_status,
+			route_hops: Some(value.route_hops.clone()),
+			settled_at_unix_secs: Some(value.settled_at_unix_secs),
 		}
 	}
 }
@@ -768,4 +1044,165 @@ mod tests {
 			}
 		}
 	}
+
+	#[test]
+	fn payment_stats_aggregation() {
+		let payment = |id: u8, amount_msat, fee_paid_msat, direction, status| {
+			let kind =
+				PaymentKind::Bolt11 { hash: PaymentHash([id; 32]), preimage: None, secret: None };
+			PaymentDetails::new(
+				PaymentId([id; 32]),
+				kind,
+				Some(amount_msat),
+				fee_paid_msat,
+				direction,
+				status,
+			)
+		};
+
+		let mut stats = PaymentStats::default();
+		stats.add_payment(&payment(
+			1,
+			10_000,
+			Some(100),
+			PaymentDirection::Outbound,
+			PaymentStatus::Succeeded,
+		));
+		stats.add_payment(&payment(
+			2,
+			20_000,
+			Some(200),
+			PaymentDirection::Outbound,
+			PaymentStatus::Failed,
+		));
+		stats.add_payment(&payment(
+			3,
+			30_000,
+			None,
+			PaymentDirection::Inbound,
+			PaymentStatus::Succeeded,
+		));
+		stats.add_payment(&payment(
+			4,
+			40_000,
+			None,
+			PaymentDirection::Inbound,
+			PaymentStatus::Pending,
+		));
+
+		// Only succeeded payments count towards the totals.
+		assert_eq!(stats.sent_msat, 10_000);
+		assert_eq!(stats.fees_paid_msat, 100);
+		assert_eq!(stats.received_msat, 30_000);
+		assert_eq!(stats.num_succeeded, 2);
+		assert_eq!(stats.num_failed, 1);
+		assert_eq!(stats.num_pending, 1);
+	}
+
+	#[test]
+	fn payment_stats_are_maintained_by_store() {
+		use std::sync::Arc;
+
+		use lightning::util::test_utils::TestStore;
+
+		use crate::io::{
+			PAYMENT_INFO_PERSISTENCE_PRIMARY_NAMESPACE,
+			PAYMENT_INFO_PERSISTENCE_SECONDARY_NAMESPACE,
+		};
+		use crate::logger::Logger;
+		use crate::types::{DynStore, PaymentStore};
+
+		let kv_store: Arc<DynStore> = Arc::new(TestStore::new(false));
+		let payment_store = PaymentStore::new(
+			Vec::new(),
+			PAYMENT_INFO_PERSISTENCE_PRIMARY_NAMESPACE.to_string(),
+			PAYMENT_INFO_PERSISTENCE_SECONDARY_NAMESPACE.to_string(),
+			kv_store,
+			Arc::new(Logger::new_log_facade()),
+		);
+
+		let id = PaymentId([1; 32]);
+		let kind = PaymentKind::Bolt11 { hash: PaymentHash([1; 32]), preimage: None, secret: None };
+		let payment = PaymentDetails::new(
+			id,
+			kind,
+			Some(10_000),
+			None,
+			PaymentDirection::Outbound,
+			PaymentStatus::Pending,
+		);
+		payment_store.insert(payment.clone()).unwrap();
+		assert_eq!(
+			payment_store.aggregate(),
+			PaymentStats { num_pending: 1, ..Default::default() }
+		);
+
+		let mut update = PaymentDetailsUpdate::new(id);
+		update.fee_paid_msat = Some(Some(100));
+		update.status = Some(PaymentStatus::Succeeded);
+		payment_store.update(&update).unwrap();
+		let expected_stats = PaymentStats {
+			sent_msat: 10_000,
+			fees_paid_msat: 100,
+			num_succeeded: 1,
+			..Default::default()
+		};
+		assert_eq!(payment_store.aggregate(), expected_stats);
+
+		// Re-inserting doesn't count the payment twice.
+		payment_store.insert_or_update(payment_store.get(&id).unwrap()).unwrap();
+		assert_eq!(payment_store.aggregate(), expected_stats);
+
+		payment_store.remove(&id).unwrap();
+		assert_eq!(payment_store.aggregate(), PaymentStats::default());
+	}
+
+	#[test]
+	fn settlement_metadata_is_persisted() {
+		let preimage = PaymentPreimage([42u8; 32]);
+		let hash = PaymentHash::from(preimage);
+		let kind = PaymentKind::Spontaneous { hash, preimage: Some(preimage) };
+		let mut payment = PaymentDetails::new(
+			PaymentId(hash.0),
+			kind,
+			Some(10_000),
+			None,
+			PaymentDirection::Outbound,
+			PaymentStatus::Pending,
+		);
+
+		// The preimage is only exposed once the payment succeeded.
+		assert_eq!(payment.preimage(), None);
+
+		let hop = PublicKey::from_slice(&[
+			0x02, 0x79, 0xbe, 0x66, 0x7e, 0xf9, 0xdc, 0xbb, 0xac, 0x55, 0xa0, 0x62, 0x95, 0xce,
+			0x87, 0x0b, 0x07, 0x02, 0x9b, 0xfc, 0xdb, 0x2d, 0xce, 0x28, 0xd9, 0x59, 0xf2, 0x81,
+			0x5b, 0x16, 0xf8, 0x17, 0x98,
+		])
+		.unwrap();
+		let update = PaymentDetailsUpdate {
+			fee_paid_msat: Some(Some(100)),
+			status: Some(PaymentStatus::Succeeded),
+			route_hops: Some(Some(vec![hop, hop])),
+			settled_at_unix_secs: Some(Some(1_700_000_000)),
+			..PaymentDetailsUpdate::new(payment.id)
+		};
+		assert!(payment.update(&update));
+		assert_eq!(payment.preimage(), Some(preimage));
+
+		// Replayed events don't override the initial settlement data.
+		let replayed_update = PaymentDetailsUpdate {
+			route_hops: Some(Some(vec![hop])),
+			settled_at_unix_secs: Some(Some(1_800_000_000)),
+			..PaymentDetailsUpdate::new(payment.id)
+		};
+		assert!(!payment.update(&replayed_update));
+		assert_eq!(payment.route_hops, Some(vec![hop, hop]));
+		assert_eq!(payment.settled_at_unix_secs, Some(1_700_000_000));
+
+		let decoded = PaymentDetails::read(&mut Cursor::new(payment.encode())).unwrap();
+		assert_eq!(decoded, payment);
+		assert_eq!(decoded.preimage(), Some(preimage));
+		assert_eq!(decoded.fee_paid_msat, Some(100));
+	}
 }
@@ -19,21 +19,31 @@ use bip21::{DeserializationError, DeserializeParams, Param, SerializeParams};
 use bitcoin::address::{NetworkChecked, NetworkUnchecked};
 use bitcoin::{Amount, Txid};
 use lightning::ln::channelmanager::PaymentId;
-use lightning::offers::offer::Offer;
-use lightning_invoice::{Bolt11Invoice, Bolt11InvoiceDescription, Description};
+use lightning::offers::offer::{Amount as OfferAmount, Offer as LdkOffer};
+use lightning_invoice::{Bolt11Invoice as LdkBolt11Invoice, Bolt11InvoiceDescription, Description};
 
 use crate::error::Error;
-use crate::ffi::maybe_wrap;
+use crate::ffi::{maybe_deref, maybe_wrap};
 use crate::logger::{log_error, LdkLogger, Logger};
-use crate::payment::{Bolt11Payment, Bolt12Payment, OnchainPayment};
+use crate::payment::{Bolt11Payment, Bolt12Payment, OnchainPayment, ReceiveParams};
 use crate::Config;
 
 type Uri<'a> = bip21::Uri<'a, NetworkChecked, Extras>;
 
+#[cfg(not(feature = "uniffi"))]
+type Bolt11Invoice = LdkBolt11Invoice;
+#[cfg(feature = "uniffi")]
+type Bolt11Invoice = Arc<crate::ffi::Bolt11Invoice>;
+
+#[cfg(not(feature = "uniffi"))]
+type Offer = LdkOffer;
+#[cfg(feature = "uniffi")]
+type Offer = Arc<crate::ffi::Offer>;
+
 #[derive(Debug, Clone)]
 struct Extras {
-	bolt11_invoice: Option<Bolt11Invoice>,
-	bolt12_offer: Option<Offer>,
+	bolt11_invoice: Option<LdkBolt11Invoice>,
+	bolt12_offer: Option<LdkOffer>,
 }
 
 /// A payment handler allowing to create [BIP 21] URIs with an on-chain, [BOLT 11], and [BOLT 12] payment
@@ -92,7 +102,7 @@ impl UnifiedQrPayment {
 	) -> Result<String, Error> {
 		let onchain_address = self.onchain_payment.new_address()?;
 
-		let amount_msats = amount_sats * 1_000;
+		let amount_msats = amount_sats.checked_mul(1_000).ok_or(Error::InvalidAmount)?;
 
 		let bolt12_offer =
 			match self.bolt12_payment.receive_inner(amount_msats, description, None, None) {
@@ -110,7 +120,7 @@ impl UnifiedQrPayment {
 			Some(amount_msats),
 			&invoice_description,
 			expiry_sec,
-			None,
+			ReceiveParams::default(),
 		) {
 			Ok(invoice) => Some(invoice),
 			Err(e) => {
@@ -134,51 +144,216 @@ impl UnifiedQrPayment {
 	/// has an offer and or invoice, it will try to pay the offer first followed by the invoice.
 	/// If they both fail, the on-chain payment will be paid.
 	///
+	/// This is equivalent to calling [`parse_unified_payment`] followed by [`pay_unified`] with
+	/// [`PaymentPreference::LightningFirst`].
+	///
 	/// Returns a `QrPaymentResult` indicating the outcome of the payment. If an error
 	/// occurs, an `Error` is returned detailing the issue encountered.
 	///
 	/// [BIP 21]: https://github.com/bitcoin/bips/blob/master/bip-0021.mediawiki
+	/// [`parse_unified_payment`]: Self::parse_unified_payment
+	/// [`pay_unified`]: Self::pay_unified
 	pub fn send(&self, uri_str: &str) -> Result<QrPaymentResult, Error> {
+		let payment = self.parse_unified_payment(uri_str)?;
+		self.pay_unified(payment, PaymentPreference::LightningFirst)
+	}
+
+	/// Parses the given [BIP 21] URI into a [`UnifiedPayment`] without paying it.
+	///
+	/// This allows to inspect the payment methods offered by the URI, e.g., to show them to the
+	/// user, before paying via [`pay_unified`].
+	///
+	/// If the URI specifies an amount, any [BOLT 11] invoice or [BOLT 12] offer whose amount
+	/// disagrees with it is considered invalid and will be omitted from the returned payment
+	/// methods, i.e., we'll never pay a lightning amount different from the one the URI states.
+	///
+	/// Will return `Error::InvalidUri` if the URI can't be parsed and `Error::InvalidNetwork` if
+	/// its on-chain address doesn't match the network we're configured for.
+	///
+	/// [BIP 21]: https://github.com/bitcoin/bips/blob/master/bip-0021.mediawiki
+	/// [BOLT 11]: https://github.com/lightning/bolts/blob/master/11-payment-encoding.md
+	/// [BOLT 12]: https://github.com/lightning/bolts/blob/master/12-offer-encoding.md
+	/// [`pay_unified`]: Self::pay_unified
+	pub fn parse_unified_payment(&self, uri_str: &str) -> Result<UnifiedPayment, Error> {
 		let uri: bip21::Uri<NetworkUnchecked, Extras> =
 			uri_str.parse().map_err(|_| Error::InvalidUri)?;
 
 		let uri_network_checked =
-			uri.clone().require_network(self.config.network).map_err(|_| Error::InvalidNetwork)?;
+			uri.require_network(self.config.network).map_err(|_| Error::InvalidNetwork)?;
+
+		let amount_sats = uri_network_checked.amount.map(|amount| amount.to_sat());
+		let message = uri_network_checked.message.and_then(|m| String::try_from(m).ok());
+
+		let bolt12_offer = uri_network_checked.extras.bolt12_offer.and_then(|offer| {
+			if offer_amount_matches(amount_sats, &offer) {
+				Some(maybe_wrap(offer))
+			} else {
+				log_error!(
+					self.logger,
+					"Ignoring BOLT12 offer as its amount doesn't match the URI amount of {:?}sats.",
+					amount_sats
+				);
+				None
+			}
+		});
+
+		let bolt11_invoice = uri_network_checked.extras.bolt11_invoice.and_then(|invoice| {
+			if invoice_amount_matches(amount_sats, &invoice) {
+				Some(maybe_wrap(invoice))
+			} else {
+				log_error!(
+					self.logger,
+					"Ignoring BOLT11 invoice as its amount doesn't match the URI amount of {:?}sats.",
+					amount_sats
+				);
+				None
+			}
+		});
+
+		Ok(UnifiedPayment {
+			address: uri_network_checked.address,
+			amount_sats,
+			message,
+			bolt11_invoice,
+			bolt12_offer,
+		})
+	}
 
-		if let Some(offer) = uri_network_checked.extras.bolt12_offer {
-			let offer = maybe_wrap(offer);
-			match self.bolt12_payment.send(&offer, None, None) {
-				Ok(payment_id) => return Ok(QrPaymentResult::Bolt12 { payment_id }),
-				Err(e) => log_error!(self.logger, "Failed to send BOLT12 offer: {:?}. This is part of a unified QR code payment. Falling back to the BOLT11 invoice.", e),
+	/// Pays the given [`UnifiedPayment`], as previously retrieved via [`parse_unified_payment`].
+	///
+	/// If the given [`PaymentPreference`] allows it, we'll first try to pay the [BOLT 12] offer,
+	/// followed by the [BOLT 11] invoice, before falling back to paying on-chain.
+	///
+	/// If an invoice or offer doesn't specify an amount, we'll pay the amount given by
+	/// [`UnifiedPayment::amount_sats`]. Paying on-chain will fail with `Error::InvalidAmount` if
+	/// no amount was specified.
+	///
+	/// Returns a `QrPaymentResult` indicating the payment method that was used. If none of the
+	/// payment methods succeeds, the error of the last attempt is returned.
+	///
+	/// [BOLT 11]: https://github.com/lightning/bolts/blob/master/11-payment-encoding.md
+	/// [BOLT 12]: https://github.com/lightning/bolts/blob/master/12-offer-encoding.md
+	/// [`parse_unified_payment`]: Self::parse_unified_payment
+	pub fn pay_unified(
+		&self, payment: UnifiedPayment, preference: PaymentPreference,
+	) -> Result<QrPaymentResult, Error> {
+		let amount_msat = payment
+			.amount_sats
+			.map(|amount_sats| amount_sats.checked_mul(1_000).ok_or(Error::InvalidAmount))
+			.transpose()?;
+		let mut last_error = Error::PaymentSendingFailed;
+
+		if preference != PaymentPreference::OnchainOnly {
+			if let Some(offer) = &payment.bolt12_offer {
+				let ldk_offer: &LdkOffer = maybe_deref(offer);
+				let res = match (ldk_offer.amount(), amount_msat) {
+					(None, Some(amount_msat)) => {
+						self.bolt12_payment.send_using_amount(offer, amount_msat, None, None)
+					},
+					_ => self.bolt12_payment.send(offer, None, None),
+				};
+				match res {
+					Ok(payment_id) => return Ok(QrPaymentResult::Bolt12 { payment_id }),
+					Err(e) => {
+						log_error!(self.logger, "Failed to send BOLT12 offer: {:?}. This is part of a unified QR code payment. Falling back to the next payment method.", e);
+						last_error = e;
+					},
+				}
 			}
-		}
 
-		if let Some(invoice) = uri_network_checked.extras.bolt11_invoice {
-			let invoice = maybe_wrap(invoice);
-			match self.bolt11_invoice.send(&invoice, None) {
-				Ok(payment_id) => return Ok(QrPaymentResult::Bolt11 { payment_id }),
-				Err(e) => log_error!(self.logger, "Failed to send BOLT11 invoice: {:?}. This is part of a unified QR code payment. Falling back to the on-chain transaction.", e),
+			if let Some(invoice) = &payment.bolt11_invoice {
+				let ldk_invoice: &LdkBolt11Invoice = maybe_deref(invoice);
+				let res = match (ldk_invoice.amount_milli_satoshis(), amount_msat) {
+					(None, Some(amount_msat)) => {
+						self.bolt11_invoice.send_using_amount(invoice, amount_msat, None)
+					},
+					_ => self.bolt11_invoice.send(invoice, None),
+				};
+				match res {
+					Ok(payment_id) => return Ok(QrPaymentResult::Bolt11 { payment_id }),
+					Err(e) => {
+						log_error!(self.logger, "Failed to send BOLT11 invoice: {:?}. This is part of a unified QR code payment. Falling back to the next payment method.", e);
+						last_error = e;
+					},
+				}
 			}
 		}
 
-		let amount = match uri_network_checked.amount {
-			Some(amount) => amount,
+		if preference == PaymentPreference::LightningOnly {
+			return Err(last_error);
+		}
+
+		let amount_sats = match payment.amount_sats {
+			Some(amount_sats) => amount_sats,
 			None => {
 				log_error!(self.logger, "No amount specified in the URI. Aborting the payment.");
 				return Err(Error::InvalidAmount);
 			},
 		};
 
-		let txid = self.onchain_payment.send_to_address(
-			&uri_network_checked.address,
-			amount.to_sat(),
-			None,
-		)?;
+		let txid = self.onchain_payment.send_to_address(&payment.address, amount_sats, None)?;
 
 		Ok(QrPaymentResult::Onchain { txid })
 	}
 }
 
+/// A payment request parsed from a [BIP 21] URI via [`UnifiedQrPayment::parse_unified_payment`].
+///
+/// [BIP 21]: https://github.com/bitcoin/bips/blob/master/bip-0021.mediawiki
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnifiedPayment {
+	/// The on-chain address to pay to.
+	pub address: bitcoin::Address,
+	/// The amount requested by the URI, if any.
+	pub amount_sats: Option<u64>,
+	/// The message included in the URI, if any.
+	pub message: Option<String>,
+	/// The [BOLT 11] invoice included in the URI, if any.
+	///
+	/// [BOLT 11]: https://github.com/lightning/bolts/blob/master/11-payment-encoding.md
+	pub bolt11_invoice: Option<Bolt11Invoice>,
+	/// The [BOLT 12] offer included in the URI, if any.
+	///
+	/// [BOLT 12]: https://github.com/lightning/bolts/blob/master/12-offer-encoding.md
+	pub bolt12_offer: Option<Offer>,
+}
+
+/// Determines which of the payment methods of a [`UnifiedPayment`] are used by
+/// [`UnifiedQrPayment::pay_unified`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaymentPreference {
+	/// Try the lightning payment methods first, and fall back to paying on-chain if they fail.
+	LightningFirst,
+	/// Only try the lightning payment methods.
+	LightningOnly,
+	/// Only pay on-chain.
+	OnchainOnly,
+}
+
+fn amount_sats_matches_msat(amount_sats: Option<u64>, amount_msat: Option<u64>) -> bool {
+	match (amount_sats, amount_msat) {
+		// BIP 21 amounts have satoshi precision, so we allow for a sub-satoshi difference.
+		(Some(amount_sats), Some(amount_msat)) => {
+			amount_sats.saturating_mul(1_000).abs_diff(amount_msat) < 1_000
+		},
+		_ => true,
+	}
+}
+
+fn invoice_amount_matches(amount_sats: Option<u64>, invoice: &LdkBolt11Invoice) -> bool {
+	amount_sats_matches_msat(amount_sats, invoice.amount_milli_satoshis())
+}
+
+fn offer_amount_matches(amount_sats: Option<u64>, offer: &LdkOffer) -> bool {
+	match offer.amount() {
+		Some(OfferAmount::Bitcoin { amount_msats }) => {
+			amount_sats_matches_msat(amount_sats, Some(amount_msats))
+		},
+		// We can't compare amounts denominated in other currencies.
+		_ => true,
+	}
+}
+
 /// Represents the result of a payment made using a [BIP 21] QR code.
 ///
 /// After a successful on-chain transaction, the transaction ID ([`Txid`]) is returned.
@@ -255,8 +430,8 @@ impl<'a> DeserializeParams<'a> for Extras {
 
 #[derive(Default)]
 struct DeserializationState {
-	bolt11_invoice: Option<Bolt11Invoice>,
-	bolt12_offer: Option<Offer>,
+	bolt11_invoice: Option<LdkBolt11Invoice>,
+	bolt12_offer: Option<LdkOffer>,
 }
 
 impl<'a> bip21::de::DeserializationState<'a> for DeserializationState {
@@ -274,7 +449,7 @@ impl<'a> bip21::de::DeserializationState<'a> for DeserializationState {
 				let bolt11_value =
 					String::try_from(value).map_err(|_| Error::UriParameterParsingFailed)?;
 				let invoice = bolt11_value
-					.parse::<Bolt11Invoice>()
+					.parse::<LdkBolt11Invoice>()
 					.map_err(|_| Error::UriParameterParsingFailed)?;
 				self.bolt11_invoice = Some(invoice);
 				Ok(bip21::de::ParamKind::Known)
@@ -282,8 +457,9 @@ impl<'a> bip21::de::DeserializationState<'a> for DeserializationState {
 			"lno" => {
 				let bolt12_value =
 					String::try_from(value).map_err(|_| Error::UriParameterParsingFailed)?;
-				let offer =
-					bolt12_value.parse::<Offer>().map_err(|_| Error::UriParameterParsingFailed)?;
+				let offer = bolt12_value
+					.parse::<LdkOffer>()
+					.map_err(|_| Error::UriParameterParsingFailed)?;
 				self.bolt12_offer = Some(offer);
 				Ok(bip21::de::ParamKind::Known)
 			},
@@ -330,7 +506,7 @@ mod tests {
 		assert_eq!(Amount::from_sat(100_000_000), Amount::from(parsed_uri.amount.unwrap()));
 
 		if let Some(invoice) = parsed_uri.extras.bolt11_invoice {
-			assert_eq!(invoice, Bolt11Invoice::from_str(expected_bolt11_invoice_1).unwrap());
+			assert_eq!(invoice, LdkBolt11Invoice::from_str(expected_bolt11_invoice_1).unwrap());
 		} else {
 			panic!("No Lightning invoice found");
 		}
@@ -355,13 +531,13 @@ mod tests {
 		);
 
 		if let Some(invoice) = parsed_uri_with_offer.extras.bolt11_invoice {
-			assert_eq!(invoice, Bolt11Invoice::from_str(expected_bolt11_invoice_2).unwrap());
+			assert_eq!(invoice, LdkBolt11Invoice::from_str(expected_bolt11_invoice_2).unwrap());
 		} else {
 			panic!("No invoice found.")
 		}
 
 		if let Some(offer) = parsed_uri_with_offer.extras.bolt12_offer {
-			assert_eq!(offer, Offer::from_str(expected_bolt12_offer_2).unwrap());
+			assert_eq!(offer, LdkOffer::from_str(expected_bolt12_offer_2).unwrap());
 		} else {
 			panic!("No offer found.");
 		}
@@ -383,7 +559,7 @@ mod tests {
 		);
 
 		if let Some(invoice) = uri_test2.extras.bolt11_invoice {
-			assert_eq!(invoice, Bolt11Invoice::from_str(expected_bolt11_invoice_3).unwrap());
+			assert_eq!(invoice, LdkBolt11Invoice::from_str(expected_bolt11_invoice_3).unwrap());
 		} else {
 			panic!("No invoice found.");
 		}
@@ -407,7 +583,7 @@ mod tests {
 		);
 
 		if let Some(invoice) = uri_test3.extras.bolt11_invoice {
-			assert_eq!(invoice, Bolt11Invoice::from_str(expected_bolt11_invoice_4).unwrap());
+			assert_eq!(invoice, LdkBolt11Invoice::from_str(expected_bolt11_invoice_4).unwrap());
 		} else {
 			panic!("No invoice found");
 		}
@@ -428,9 +604,26 @@ mod tests {
 				.unwrap()
 		);
 		if let Some(invoice) = uri_test4.extras.bolt11_invoice {
-			assert_eq!(invoice, Bolt11Invoice::from_str(expected_bolt11_invoice_5).unwrap());
+			assert_eq!(invoice, LdkBolt11Invoice::from_str(expected_bolt11_invoice_5).unwrap());
 		} else {
 			panic!("No invoice found");
 		}
 	}
+
+	#[test]
+	fn amount_mismatches_are_detected() {
+		let invoice = LdkBolt11Invoice::from_str("LNTB500U1PN89HH6PP5MA7K6DRM5SYVD05NTXMGSRNM728J7EHM8KV6VC96YNLKN7G7VDYQDQQCQZRCXQR8Q7SP5HU30L0EEXKYYPQSQYEZELZWUPT62HLJ0KV2662CALGPAML50QPXQ9QXPQYSGQDKTVFXEC8H2DG2GY3C95ETAJ0QKX50XAUCU304PPFV2SQVGFHZ6RMZWJV8MC3M0LXF3GW852C5VSK0DELK0JHLYUTYZDF7QKNAMT4PQQQN24WM").unwrap();
+		assert!(invoice_amount_matches(None, &invoice));
+		assert!(invoice_amount_matches(Some(50_000), &invoice));
+		assert!(!invoice_amount_matches(Some(50_001), &invoice));
+		assert!(!invoice_amount_matches(Some(100_000), &invoice));
+
+		let no_amount_invoice = LdkBolt11Invoice::from_str("lnbc1pn8g249pp5f6ytj32ty90jhvw69enf30hwfgdhyymjewywcmfjevflg6s4z86qdqqcqzzgxqyz5vqrzjqwnvuc0u4txn35cafc7w94gxvq5p3cu9dd95f7hlrh0fvs46wpvhdfjjzh2j9f7ye5qqqqryqqqqthqqpysp5mm832athgcal3m7h35sc29j63lmgzvwc5smfjh2es65elc2ns7dq9qrsgqu2xcje2gsnjp0wn97aknyd3h58an7sjj6nhcrm40846jxphv47958c6th76whmec8ttr2wmg6sxwchvxmsc00kqrzqcga6lvsf9jtqgqy5yexa").unwrap();
+		assert!(invoice_amount_matches(Some(42), &no_amount_invoice));
+
+		let offer = LdkOffer::from_str("LNO1QGSQVGNWGCG35Z6EE2H3YCZRADDM72XRFUA9UVE2RLRM9DEU7XYFZRCYZPGTGRDWMGU44QPYUXLHLLMLWN4QSPQ97HSSQZSYV9EKGESSWCPK7JRAAUZ6574TSTVFJFSE20LSFWH8G9GTPFHL4RRJN23VX4TH35SRWKCNQ6S8R9ZW9HU5RXMPXVYCJVK2KY3NTEA8VXZTMWJF4NAJCCAQZQ7YZ7KDDZ600LAW2S2E7Q6XDYLPSMLMV4YAY0QXX5NC8QH05JRNUYQPQCAHK8Y5KQ8H9X624LS6A9GWFTGKYYPUZVUKKM93DWETTL8A7NE84L7SNHCSGR006EACQRQP8YWY6WPS0TS").unwrap();
+		assert!(offer_amount_matches(None, &offer));
+		assert!(offer_amount_matches(Some(100_000), &offer));
+		assert!(!offer_amount_matches(Some(200_000), &offer));
+	}
 }
@@ -7,7 +7,9 @@
 
 use std::collections::HashMap;
 use std::ops::Deref;
-use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use bitcoin::secp256k1::PublicKey;
 use lightning::impl_writeable_tlv_based;
@@ -27,6 +29,10 @@ where
 	L::Target: LdkLogger,
 {
 	peers: RwLock<HashMap<PublicKey, PeerInfo>>,
+	// The connection history of the peers we persist or have channels with.
+	connection_history: Mutex<HashMap<PublicKey, PeerConnectionHistory>>,
+	// Whether the connection history of persisted peers changed since we last persisted them.
+	connection_history_dirty: AtomicBool,
 	kv_store: Arc<DynStore>,
 	logger: L,
 }
@@ -37,24 +43,98 @@ where
 {
 	pub(crate) fn new(kv_store: Arc<DynStore>, logger: L) -> Self {
 		let peers = RwLock::new(HashMap::new());
-		Self { peers, kv_store, logger }
+		let connection_history = Mutex::new(HashMap::new());
+		let connection_history_dirty = AtomicBool::new(false);
+		Self { peers, connection_history, connection_history_dirty, kv_store, logger }
 	}
 
-	pub(crate) fn add_peer(&self, peer_info: PeerInfo) -> Result<(), Error> {
+	pub(crate) fn add_peer(&self, mut peer_info: PeerInfo) -> Result<(), Error> {
 		let mut locked_peers = self.peers.write().unwrap();
 
 		if locked_peers.contains_key(&peer_info.node_id) {
 			return Ok(());
 		}
 
+		// We might have been connected to the peer before it was persisted.
+		if let Some(history) = self.connection_history.lock().unwrap().get(&peer_info.node_id) {
+			peer_info.last_connected_unix_secs =
+				peer_info.last_connected_unix_secs.or(history.last_connected_unix_secs);
+			peer_info.last_disconnected_unix_secs =
+				peer_info.last_disconnected_unix_secs.or(history.last_disconnected_unix_secs);
+		}
+
 		locked_peers.insert(peer_info.node_id, peer_info);
 		self.persist_peers(&*locked_peers)
 	}
 
+	/// Records that we just connected to the given peer.
+	///
+	/// We only keep the history of peers we persist or have channels with.
+	pub(crate) fn peer_connected(&self, node_id: &PublicKey, has_channels: bool) {
+		self.update_connection_history(node_id, has_channels, |history, now| {
+			history.last_connected_unix_secs = Some(now)
+		});
+	}
+
+	/// Records that we just disconnected from the given peer.
+	///
+	/// We only keep the history of peers we persist or have channels with.
+	pub(crate) fn peer_disconnected(&self, node_id: &PublicKey, has_channels: bool) {
+		self.update_connection_history(node_id, has_channels, |history, now| {
+			history.last_disconnected_unix_secs = Some(now)
+		});
+	}
+
+	pub(crate) fn connection_history(&self, node_id: &PublicKey) -> PeerConnectionHistory {
+		self.connection_history.lock().unwrap().get(node_id).copied().unwrap_or_default()
+	}
+
+	fn update_connection_history<F: Fn(&mut PeerConnectionHistory, u64)>(
+		&self, node_id: &PublicKey, has_channels: bool, update: F,
+	) {
+		let now = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap_or(Duration::from_secs(0))
+			.as_secs();
+
+		let mut locked_peers = self.peers.write().unwrap();
+		let mut locked_history = self.connection_history.lock().unwrap();
+		let peer_info = locked_peers.get_mut(node_id);
+		if peer_info.is_none() && !has_channels {
+			locked_history.remove(node_id);
+			return;
+		}
+
+		let history = locked_history.entry(*node_id).or_default();
+		update(history, now);
+
+		// Have the updated history persisted if we're persisting the peer. We don't persist right
+		// away, as we're called from the message handler on every (re-)connection.
+		if let Some(peer_info) = peer_info {
+			peer_info.last_connected_unix_secs = history.last_connected_unix_secs;
+			peer_info.last_disconnected_unix_secs = history.last_disconnected_unix_secs;
+			self.connection_history_dirty.store(true, Ordering::Release);
+		}
+	}
+
+	/// Persists the connection history of our persisted peers if it changed since we last
+	/// persisted them.
+	pub(crate) fn persist_connection_history_if_dirty(&self) {
+		if !self.connection_history_dirty.load(Ordering::Acquire) {
+			return;
+		}
+
+		let locked_peers = self.peers.read().unwrap();
+		self.persist_peers(&*locked_peers).unwrap_or_else(|e| {
+			log_error!(self.logger, "Failed to persist connection history of peers: {}", e)
+		});
+	}
+
 	pub(crate) fn remove_peer(&self, node_id: &PublicKey) -> Result<(), Error> {
 		let mut locked_peers = self.peers.write().unwrap();
 
 		locked_peers.remove(node_id);
+		self.connection_history.lock().unwrap().remove(node_id);
 		self.persist_peers(&*locked_peers)
 	}
 
@@ -67,6 +147,9 @@ where
 	}
 
 	fn persist_peers(&self, locked_peers: &HashMap<PublicKey, PeerInfo>) -> Result<(), Error> {
+		// We're holding the peers lock, so any concurrent updates will be included in what we
+		// write.
+		self.connection_history_dirty.store(false, Ordering::Release);
 		let data = PeerStoreSerWrapper(&*locked_peers).encode();
 		KVStoreSync::write(
 			&*self.kv_store,
@@ -84,6 +167,7 @@ where
 				PEER_INFO_PERSISTENCE_KEY,
 				e
 			);
+			self.connection_history_dirty.store(true, Ordering::Release);
 			Error::PersistenceFailed
 		})?;
 		Ok(())
@@ -100,8 +184,21 @@ where
 	) -> Result<Self, lightning::ln::msgs::DecodeError> {
 		let (kv_store, logger) = args;
 		let read_peers: PeerStoreDeserWrapper = Readable::read(reader)?;
+		let connection_history = read_peers
+			.0
+			.values()
+			.map(|peer_info| {
+				let history = PeerConnectionHistory {
+					last_connected_unix_secs: peer_info.last_connected_unix_secs,
+					last_disconnected_unix_secs: peer_info.last_disconnected_unix_secs,
+				};
+				(peer_info.node_id, history)
+			})
+			.collect();
+		let connection_history = Mutex::new(connection_history);
+		let connection_history_dirty = AtomicBool::new(false);
 		let peers: RwLock<HashMap<PublicKey, PeerInfo>> = RwLock::new(read_peers.0);
-		Ok(Self { peers, kv_store, logger })
+		Ok(Self { peers, connection_history, connection_history_dirty, kv_store, logger })
 	}
 }
 
@@ -140,13 +237,29 @@ impl Writeable for PeerStoreSerWrapper<'_> {
 pub(crate) struct PeerInfo {
 	pub node_id: PublicKey,
 	pub address: SocketAddress,
+	pub last_connected_unix_secs: Option<u64>,
+	pub last_disconnected_unix_secs: Option<u64>,
+}
+
+impl PeerInfo {
+	pub(crate) fn new(node_id: PublicKey, address: SocketAddress) -> Self {
+		Self { node_id, address, last_connected_unix_secs: None, last_disconnected_unix_secs: None }
+	}
 }
 
 impl_writeable_tlv_based!(PeerInfo, {
 	(0, node_id, required),
+	(1, last_connected_unix_secs, option),
 	(2, address, required),
+	(3, last_disconnected_unix_secs, option),
 });
 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct PeerConnectionHistory {
+	pub last_connected_unix_secs: Option<u64>,
+	pub last_disconnected_unix_secs: Option<u64>,
+}
+
 #[cfg(test)]
 mod tests {
 	use std::str::FromStr;
@@ -167,7 +280,7 @@ mod tests {
 		)
 		.unwrap();
 		let address = SocketAddress::from_str("127.0.0.1:9738").unwrap();
-		let expected_peer_info = PeerInfo { node_id, address };
+		let expected_peer_info = PeerInfo::new(node_id, address);
 		assert!(KVStoreSync::read(
 			&*store,
 			PEER_INFO_PERSISTENCE_PRIMARY_NAMESPACE,
@@ -193,4 +306,64 @@ mod tests {
 		assert_eq!(peers[0], expected_peer_info);
 		assert_eq!(deser_peer_store.get_peer(&node_id), Some(expected_peer_info));
 	}
+
+	#[test]
+	fn peer_connection_history_persistence() {
+		let store: Arc<DynStore> = Arc::new(TestStore::new(false));
+		let logger = Arc::new(TestLogger::new());
+		let peer_store = PeerStore::new(Arc::clone(&store), Arc::clone(&logger));
+
+		let node_id = PublicKey::from_str(
+			"0276607124ebe6a6c9338517b6f485825b27c2dcc0b9fc2aa6a4c0df91194e5993",
+		)
+		.unwrap();
+		let address = SocketAddress::from_str("127.0.0.1:9738").unwrap();
+
+		// We don't track the history of peers we neither persist nor have channels with.
+		peer_store.peer_connected(&node_id, false);
+		assert_eq!(peer_store.connection_history(&node_id), PeerConnectionHistory::default());
+
+		// We track the history of peers we have channels with, and keep it once we start
+		// persisting them.
+		peer_store.peer_connected(&node_id, true);
+		let history = peer_store.connection_history(&node_id);
+		assert!(history.last_connected_unix_secs.is_some());
+		assert_eq!(history.last_disconnected_unix_secs, None);
+
+		peer_store.add_peer(PeerInfo::new(node_id, address)).unwrap();
+		assert_eq!(
+			peer_store.get_peer(&node_id).unwrap().last_connected_unix_secs,
+			history.last_connected_unix_secs
+		);
+
+		peer_store.peer_disconnected(&node_id, false);
+		let history = peer_store.connection_history(&node_id);
+		assert!(history.last_disconnected_unix_secs.is_some());
+
+		// The updated history is only persisted once we're asked to.
+		let persisted_bytes = KVStoreSync::read(
+			&*store,
+			PEER_INFO_PERSISTENCE_PRIMARY_NAMESPACE,
+			PEER_INFO_PERSISTENCE_SECONDARY_NAMESPACE,
+			PEER_INFO_PERSISTENCE_KEY,
+		)
+		.unwrap();
+		let deser_peer_store =
+			PeerStore::read(&mut &persisted_bytes[..], (Arc::clone(&store), Arc::clone(&logger)))
+				.unwrap();
+		assert_eq!(deser_peer_store.connection_history(&node_id).last_disconnected_unix_secs, None);
+		peer_store.persist_connection_history_if_dirty();
+
+		// Check the history of persisted peers survives restarts.
+		let persisted_bytes = KVStoreSync::read(
+			&*store,
+			PEER_INFO_PERSISTENCE_PRIMARY_NAMESPACE,
+			PEER_INFO_PERSISTENCE_SECONDARY_NAMESPACE,
+			PEER_INFO_PERSISTENCE_KEY,
+		)
+		.unwrap();
+		let deser_peer_store =
+			PeerStore::read(&mut &persisted_bytes[..], (Arc::clone(&store), logger)).unwrap();
+		assert_eq!(deser_peer_store.connection_history(&node_id), history);
+	}
 }
@@ -8,10 +8,10 @@ use crate::{
 	config::{
 		EXTERNAL_PATHFINDING_SCORES_SYNC_INTERVAL, EXTERNAL_PATHFINDING_SCORES_SYNC_TIMEOUT_SECS,
 	},
-	io::utils::write_external_pathfinding_scores_to_cache,
+	io::utils::{write_external_pathfinding_scores_to_cache, write_scorer},
 	logger::LdkLogger,
 	runtime::Runtime,
-	NodeMetrics, Scorer,
+	NodeMetrics, PowerMode, Scorer,
 };
 use crate::{write_node_metrics, DynStore, Logger};
 use lightning::{
@@ -23,7 +23,7 @@ use lightning::{
 pub fn setup_background_pathfinding_scores_sync(
 	url: String, scorer: Arc<Mutex<crate::types::Scorer>>, node_metrics: Arc<RwLock<NodeMetrics>>,
 	kv_store: Arc<DynStore>, logger: Arc<Logger>, runtime: Arc<Runtime>,
-	mut stop_receiver: tokio::sync::watch::Receiver<()>,
+	power_mode: Arc<RwLock<PowerMode>>, mut stop_receiver: tokio::sync::watch::Receiver<()>,
 ) {
 	log_info!(logger, "External scores background syncing enabled from {}", url);
 
@@ -41,6 +41,9 @@ pub fn setup_background_pathfinding_scores_sync(
 					return;
 				}
 				_ = interval.tick() => {
+					if *power_mode.read().unwrap() == PowerMode::LowPower {
+						continue;
+					}
 					log_trace!(
 						logger,
 						"Background sync of external scores started.",
@@ -53,6 +56,37 @@ pub fn setup_background_pathfinding_scores_sync(
 	});
 }
 
+/// Start a background task that periodically persists the local pathfinding scores.
+pub fn setup_background_scorer_persistence(
+	persist_interval: Duration, scorer: Arc<Mutex<Scorer>>, kv_store: Arc<DynStore>,
+	logger: Arc<Logger>, runtime: Arc<Runtime>,
+	mut stop_receiver: tokio::sync::watch::Receiver<()>,
+) {
+	runtime.spawn_cancellable_background_task(async move {
+		let mut interval = tokio::time::interval(persist_interval);
+		// Skip the immediate first tick, as we just read the scorer from the store.
+		interval.reset();
+		loop {
+			tokio::select! {
+				_ = stop_receiver.changed() => {
+					log_trace!(
+						logger,
+						"Stopping background persistence of the scorer.",
+					);
+					return;
+				}
+				_ = interval.tick() => {
+					let res = write_scorer(&scorer.lock().unwrap(), Arc::clone(&kv_store), Arc::clone(&logger));
+					match res {
+						Ok(()) => log_trace!(logger, "Background persistence of the scorer finished."),
+						Err(e) => log_error!(logger, "Failed to persist scorer: {}", e),
+					}
+				}
+			}
+		}
+	});
+}
+
 async fn sync_external_scores(
 	logger: &Logger, scorer: &Mutex<Scorer>, node_metrics: &RwLock<NodeMetrics>,
 	kv_store: Arc<DynStore>, url: &String,
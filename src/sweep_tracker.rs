@@ -0,0 +1,364 @@
+// This file is Copyright its original authors, visible in version control history.
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. You may not use this file except in
+// accordance with one or both of these licenses.
+
+use std::collections::HashSet;
+use std::ops::Deref;
+use std::sync::{Arc, Mutex};
+
+use bitcoin::{Address, Network, OutPoint, ScriptBuf, Txid};
+use lightning::impl_writeable_tlv_based;
+use lightning::util::persist::KVStoreSync;
+use lightning::util::ser::{Readable, Writeable};
+use lightning::util::sweep::{OutputSpendStatus, TrackedSpendableOutput};
+
+use crate::event::{Event, EventQueue};
+use crate::io::{
+	SWEEP_TRACKER_PERSISTENCE_KEY, SWEEP_TRACKER_PERSISTENCE_PRIMARY_NAMESPACE,
+	SWEEP_TRACKER_PERSISTENCE_SECONDARY_NAMESPACE,
+};
+use crate::logger::{log_error, log_info, LdkLogger};
+use crate::types::{DynStore, Sweeper};
+use crate::Error;
+
+/// A transaction sweeping some of our spendable outputs that we saw being broadcast.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct TrackedSweep {
+	/// The id of the sweeping transaction.
+	txid: Txid,
+	/// The outputs being swept.
+	outpoints: Vec<OutPoint>,
+	/// The amount, in satoshis, paid to the sweep destination.
+	amount_sat: u64,
+	/// The script of the sweep destination.
+	destination_script: ScriptBuf,
+	/// Whether we already reported the sweep as confirmed.
+	reported: bool,
+}
+
+impl_writeable_tlv_based!(TrackedSweep, {
+	(0, txid, required),
+	(2, outpoints, required_vec),
+	(4, amount_sat, required),
+	(6, destination_script, required),
+	(8, reported, required),
+});
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct TrackedSweeps {
+	sweeps: Vec<TrackedSweep>,
+}
+
+impl_writeable_tlv_based!(TrackedSweeps, {
+	(0, sweeps, required_vec),
+});
+
+/// Emits [`Event::SpendableOutputsSwept`] once the sweep of any of our spendable outputs confirms.
+///
+/// As the [`Sweeper`] stops tracking outputs once their sweep is sufficiently confirmed, we
+/// persist the sweeps we saw being broadcast. This allows us to report sweeps that confirmed, and
+/// were pruned by the [`Sweeper`], while we were offline or in-between checks.
+pub(crate) struct SweepTracker<L: Deref>
+where
+	L::Target: LdkLogger,
+{
+	tracked_sweeps: Mutex<TrackedSweeps>,
+	output_sweeper: Arc<Sweeper>,
+	event_queue: Arc<EventQueue<L>>,
+	network: Network,
+	kv_store: Arc<DynStore>,
+	logger: L,
+}
+
+impl<L: Deref> SweepTracker<L>
+where
+	L::Target: LdkLogger,
+{
+	pub(crate) fn new(
+		output_sweeper: Arc<Sweeper>, event_queue: Arc<EventQueue<L>>, network: Network,
+		kv_store: Arc<DynStore>, logger: L,
+	) -> Self {
+		let tracked_sweeps = match KVStoreSync::read(
+			&*kv_store,
+			SWEEP_TRACKER_PERSISTENCE_PRIMARY_NAMESPACE,
+			SWEEP_TRACKER_PERSISTENCE_SECONDARY_NAMESPACE,
+			SWEEP_TRACKER_PERSISTENCE_KEY,
+		) {
+			Ok(bytes) => TrackedSweeps::read(&mut &bytes[..]).unwrap_or_else(|e| {
+				log_error!(logger, "Failed to deserialize tracked sweeps: {:?}", e);
+				TrackedSweeps::default()
+			}),
+			Err(e) if e.kind() == lightning::io::ErrorKind::NotFound => TrackedSweeps::default(),
+			Err(e) => {
+				log_error!(logger, "Failed to read tracked sweeps: {}", e);
+				TrackedSweeps::default()
+			},
+		};
+		let tracked_sweeps = Mutex::new(tracked_sweeps);
+		Self { tracked_sweeps, output_sweeper, event_queue, network, kv_store, logger }
+	}
+
+	/// Reports any sweeps that confirmed since we last checked.
+	pub(crate) fn check_sweeps(&self) {
+		let outputs = self.output_sweeper.tracked_spendable_outputs();
+		let mut locked_sweeps = self.tracked_sweeps.lock().unwrap();
+
+		let mut new_sweeps = TrackedSweeps::default();
+		for (mut sweep, status) in update_tracked_sweeps(&locked_sweeps.sweeps, &outputs) {
+			if status == SweepStatus::Replaced {
+				continue;
+			}
+
+			if !sweep.reported && status != SweepStatus::Unconfirmed {
+				sweep.reported = self.report_sweep(&sweep);
+			}
+
+			// Once pruned by the sweeper, we can forget about the sweep after reporting it.
+			if !(status == SweepStatus::Pruned && sweep.reported) {
+				new_sweeps.sweeps.push(sweep);
+			}
+		}
+
+		if *locked_sweeps != new_sweeps && self.persist(&new_sweeps).is_ok() {
+			*locked_sweeps = new_sweeps;
+		}
+	}
+
+	/// Returns whether the sweep was reported successfully.
+	fn report_sweep(&self, sweep: &TrackedSweep) -> bool {
+		let destination = match Address::from_script(&sweep.destination_script, self.network) {
+			Ok(destination) => destination,
+			Err(e) => {
+				log_error!(
+					self.logger,
+					"Failed to determine destination of sweep {}: {}",
+					sweep.txid,
+					e
+				);
+				// There's no use in retrying.
+				return true;
+			},
+		};
+		log_info!(
+			self.logger,
+			"Sweep {} of {}sats to {} confirmed",
+			sweep.txid,
+			sweep.amount_sat,
+			destination
+		);
+		let event = Event::SpendableOutputsSwept {
+			txid: sweep.txid,
+			amount_sat: sweep.amount_sat,
+			destination,
+		};
+		match self.event_queue.add_event(event) {
+			Ok(()) => true,
+			Err(e) => {
+				log_error!(self.logger, "Failed to push to event queue: {}", e);
+				false
+			},
+		}
+	}
+
+	fn persist(&self, tracked_sweeps: &TrackedSweeps) -> Result<(), Error> {
+		KVStoreSync::write(
+			&*self.kv_store,
+			SWEEP_TRACKER_PERSISTENCE_PRIMARY_NAMESPACE,
+			SWEEP_TRACKER_PERSISTENCE_SECONDARY_NAMESPACE,
+			SWEEP_TRACKER_PERSISTENCE_KEY,
+			tracked_sweeps.encode(),
+		)
+		.map_err(|e| {
+			log_error!(
+				self.logger,
+				"Write for key {}/{}/{} failed due to: {}",
+				SWEEP_TRACKER_PERSISTENCE_PRIMARY_NAMESPACE,
+				SWEEP_TRACKER_PERSISTENCE_SECONDARY_NAMESPACE,
+				SWEEP_TRACKER_PERSISTENCE_KEY,
+				e
+			);
+			Error::PersistenceFailed
+		})
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SweepStatus {
+	/// The sweep was broadcast but didn't confirm yet.
+	Unconfirmed,
+	/// The sweep confirmed, but the sweeper still tracks it until it's sufficiently confirmed.
+	Confirmed,
+	/// The sweeper stopped tracking the swept outputs, which it only does once the sweep is
+	/// sufficiently confirmed.
+	Pruned,
+	/// The swept outputs are now spent by a different transaction.
+	Replaced,
+}
+
+/// Merges the sweeper's current view of our outputs into the sweeps we track.
+fn update_tracked_sweeps(
+	tracked_sweeps: &[TrackedSweep], outputs: &[TrackedSpendableOutput],
+) -> Vec<(TrackedSweep, SweepStatus)> {
+	let mut current_sweeps: Vec<(TrackedSweep, SweepStatus)> = Vec::new();
+	let mut known_outpoints = HashSet::new();
+	for output in outputs {
+		let outpoint = output.descriptor.spendable_outpoint().into_bitcoin_outpoint();
+		known_outpoints.insert(outpoint);
+		let (tx, status) = match &output.status {
+			OutputSpendStatus::PendingInitialBroadcast { .. } => continue,
+			OutputSpendStatus::PendingFirstConfirmation { latest_spending_tx, .. } => {
+				(latest_spending_tx, SweepStatus::Unconfirmed)
+			},
+			OutputSpendStatus::PendingThresholdConfirmations { latest_spending_tx, .. } => {
+				(latest_spending_tx, SweepStatus::Confirmed)
+			},
+		};
+		let txid = tx.compute_txid();
+		if let Some((sweep, _)) = current_sweeps.iter_mut().find(|(s, _)| s.txid == txid) {
+			sweep.outpoints.push(outpoint);
+			continue;
+		}
+		// Our sweeping transactions pay a single output to the sweep destination.
+		let output = match tx.output.first() {
+			Some(output) => output,
+			None => continue,
+		};
+		let sweep = TrackedSweep {
+			txid,
+			outpoints: vec![outpoint],
+			amount_sat: output.value.to_sat(),
+			destination_script: output.script_pubkey.clone(),
+			reported: false,
+		};
+		current_sweeps.push((sweep, status));
+	}
+
+	let mut updated_sweeps = Vec::new();
+	for tracked_sweep in tracked_sweeps {
+		if let Some(pos) = current_sweeps.iter().position(|(s, _)| s.txid == tracked_sweep.txid) {
+			let (mut sweep, status) = current_sweeps.swap_remove(pos);
+			sweep.reported = tracked_sweep.reported;
+			updated_sweeps.push((sweep, status));
+		} else if tracked_sweep.outpoints.iter().any(|o| known_outpoints.contains(o)) {
+			updated_sweeps.push((tracked_sweep.clone(), SweepStatus::Replaced));
+		} else {
+			updated_sweeps.push((tracked_sweep.clone(), SweepStatus::Pruned));
+		}
+	}
+	updated_sweeps.extend(current_sweeps);
+	updated_sweeps
+}
+
+#[cfg(test)]
+mod tests {
+	use bitcoin::absolute::LockTime;
+	use bitcoin::hashes::Hash;
+	use bitcoin::transaction::Version;
+	use bitcoin::{Amount, BlockHash, Transaction, TxIn, TxOut};
+	use lightning::sign::SpendableOutputDescriptor;
+
+	use super::*;
+
+	fn sweeping_tx(outpoints: &[OutPoint], amount_sat: u64) -> Transaction {
+		Transaction {
+			version: Version::TWO,
+			lock_time: LockTime::ZERO,
+			input: outpoints
+				.iter()
+				.map(|o| TxIn { previous_output: *o, ..Default::default() })
+				.collect(),
+			output: vec![TxOut {
+				value: Amount::from_sat(amount_sat),
+				script_pubkey: ScriptBuf::new_op_return(&[]),
+			}],
+		}
+	}
+
+	fn tracked_output(outpoint: OutPoint, status: OutputSpendStatus) -> TrackedSpendableOutput {
+		let descriptor = SpendableOutputDescriptor::StaticOutput {
+			outpoint: lightning::chain::transaction::OutPoint {
+				txid: outpoint.txid,
+				index: outpoint.vout as u16,
+			},
+			output: TxOut { value: Amount::from_sat(1000), script_pubkey: ScriptBuf::new() },
+			channel_keys_id: None,
+		};
+		TrackedSpendableOutput { descriptor, channel_id: None, status }
+	}
+
+	fn unconfirmed(tx: &Transaction) -> OutputSpendStatus {
+		OutputSpendStatus::PendingFirstConfirmation {
+			first_broadcast_hash: BlockHash::all_zeros(),
+			latest_broadcast_height: 1,
+			latest_spending_tx: tx.clone(),
+		}
+	}
+
+	fn confirmed(tx: &Transaction) -> OutputSpendStatus {
+		OutputSpendStatus::PendingThresholdConfirmations {
+			first_broadcast_hash: BlockHash::all_zeros(),
+			latest_broadcast_height: 1,
+			latest_spending_tx: tx.clone(),
+			confirmation_height: 2,
+			confirmation_hash: BlockHash::all_zeros(),
+		}
+	}
+
+	#[test]
+	fn tracks_sweeps_across_status_changes() {
+		let outpoint_a = OutPoint { txid: Txid::from_byte_array([1; 32]), vout: 0 };
+		let outpoint_b = OutPoint { txid: Txid::from_byte_array([2; 32]), vout: 1 };
+		let tx = sweeping_tx(&[outpoint_a, outpoint_b], 1500);
+		let txid = tx.compute_txid();
+
+		// Sweeps of several outputs in one transaction are tracked once.
+		let outputs = vec![
+			tracked_output(outpoint_a, unconfirmed(&tx)),
+			tracked_output(outpoint_b, unconfirmed(&tx)),
+		];
+		let updated = update_tracked_sweeps(&[], &outputs);
+		assert_eq!(updated.len(), 1);
+		let (sweep, status) = &updated[0];
+		assert_eq!(status, &SweepStatus::Unconfirmed);
+		assert_eq!(sweep.txid, txid);
+		assert_eq!(sweep.outpoints, vec![outpoint_a, outpoint_b]);
+		assert_eq!(sweep.amount_sat, 1500);
+		assert!(!sweep.reported);
+
+		// Once confirmed, the reported flag is retained.
+		let mut tracked = sweep.clone();
+		tracked.reported = true;
+		let outputs = vec![
+			tracked_output(outpoint_a, confirmed(&tx)),
+			tracked_output(outpoint_b, confirmed(&tx)),
+		];
+		let updated = update_tracked_sweeps(&[tracked.clone()], &outputs);
+		assert_eq!(updated, vec![(tracked.clone(), SweepStatus::Confirmed)]);
+
+		// If the sweeper stopped tracking the outputs, the sweep confirmed, even if we never saw
+		// it confirm, e.g., as we were offline.
+		tracked.reported = false;
+		let updated = update_tracked_sweeps(&[tracked.clone()], &[]);
+		assert_eq!(updated, vec![(tracked.clone(), SweepStatus::Pruned)]);
+	}
+
+	#[test]
+	fn detects_replaced_sweeps() {
+		let outpoint = OutPoint { txid: Txid::from_byte_array([1; 32]), vout: 0 };
+		let tx = sweeping_tx(&[outpoint], 1500);
+		let replacement_tx = sweeping_tx(&[outpoint], 1400);
+
+		let outputs = vec![tracked_output(outpoint, unconfirmed(&tx))];
+		let (tracked, _) = update_tracked_sweeps(&[], &outputs).pop().unwrap();
+
+		let outputs = vec![tracked_output(outpoint, unconfirmed(&replacement_tx))];
+		let updated = update_tracked_sweeps(&[tracked.clone()], &outputs);
+		assert_eq!(updated.len(), 2);
+		assert_eq!(updated[0], (tracked, SweepStatus::Replaced));
+		assert_eq!(updated[1].0.txid, replacement_tx.compute_txid());
+		assert_eq!(updated[1].1, SweepStatus::Unconfirmed);
+	}
+}
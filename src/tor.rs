@@ -0,0 +1,398 @@
+// This file is Copyright its original authors, visible in version control history.
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. You may not use this file except in
+// accordance with one or both of these licenses.
+
+//! Holds the logic for dialing peers via a Tor SOCKS5 proxy and publishing an onion service via
+//! the Tor control port.
+
+use std::io;
+use std::net::ToSocketAddrs;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use lightning::ln::msgs::SocketAddress;
+use lightning::util::persist::KVStoreSync;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+use crate::hex_utils;
+use crate::io::{
+	ONION_SERVICE_KEY_PERSISTENCE_KEY, ONION_SERVICE_KEY_PERSISTENCE_PRIMARY_NAMESPACE,
+	ONION_SERVICE_KEY_PERSISTENCE_SECONDARY_NAMESPACE,
+};
+use crate::logger::{log_error, log_info, LdkLogger, Logger};
+use crate::types::DynStore;
+
+// The time we wait for the SOCKS5 proxy to establish a connection.
+const SOCKS5_CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+// The initial time we wait before retrying to publish the onion service.
+const ONION_SERVICE_INITIAL_BACKOFF: Duration = Duration::from_secs(5);
+
+// The maximum time we wait before retrying to publish the onion service.
+const ONION_SERVICE_MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+const SOCKS5_VERSION: u8 = 0x05;
+const SOCKS5_AUTH_METHOD_NONE: u8 = 0x00;
+const SOCKS5_CMD_CONNECT: u8 = 0x01;
+const SOCKS5_ATYP_IPV4: u8 = 0x01;
+const SOCKS5_ATYP_DOMAIN: u8 = 0x03;
+const SOCKS5_ATYP_IPV6: u8 = 0x04;
+const SOCKS5_REPLY_SUCCEEDED: u8 = 0x00;
+
+/// Connects to the given address via the SOCKS5 proxy at `proxy_addr`, returning the established
+/// stream.
+pub(crate) async fn connect_via_socks5_proxy(
+	proxy_addr: &SocketAddress, addr: &SocketAddress,
+) -> io::Result<std::net::TcpStream> {
+	let proxy_socket_addr = proxy_addr.to_socket_addrs()?.next().ok_or_else(|| {
+		io::Error::new(io::ErrorKind::InvalidInput, "Failed to resolve proxy address")
+	})?;
+	let (host, port) = host_and_port(addr);
+
+	let connect_fut = async {
+		let mut stream = TcpStream::connect(proxy_socket_addr).await?;
+		socks5_handshake(&mut stream, &host, port).await?;
+		stream.into_std()
+	};
+
+	tokio::time::timeout(SOCKS5_CONNECT_TIMEOUT, connect_fut)
+		.await
+		.map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "SOCKS5 proxy connection timed out"))?
+}
+
+fn host_and_port(addr: &SocketAddress) -> (String, u16) {
+	match addr {
+		SocketAddress::TcpIpV4 { addr, port } => {
+			(std::net::Ipv4Addr::from(*addr).to_string(), *port)
+		},
+		SocketAddress::TcpIpV6 { addr, port } => {
+			(std::net::Ipv6Addr::from(*addr).to_string(), *port)
+		},
+		SocketAddress::Hostname { hostname, port } => (hostname.to_string(), *port),
+		SocketAddress::OnionV2(_) | SocketAddress::OnionV3 { .. } => {
+			// The `Display` implementation yields `<host>.onion:<port>`.
+			let addr_str = addr.to_string();
+			let (host, port) = addr_str.rsplit_once(':').unwrap_or((&addr_str, "0"));
+			(host.to_string(), port.parse().unwrap_or(0))
+		},
+	}
+}
+
+async fn socks5_handshake<S: AsyncRead + AsyncWrite + Unpin>(
+	stream: &mut S, host: &str, port: u16,
+) -> io::Result<()> {
+	let host_len = u8::try_from(host.len())
+		.map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Host name too long"))?;
+
+	// Greet the proxy, offering to proceed without authentication.
+	stream.write_all(&[SOCKS5_VERSION, 1, SOCKS5_AUTH_METHOD_NONE]).await?;
+	let mut method_selection = [0u8; 2];
+	stream.read_exact(&mut method_selection).await?;
+	if method_selection != [SOCKS5_VERSION, SOCKS5_AUTH_METHOD_NONE] {
+		return Err(io::Error::new(
+			io::ErrorKind::PermissionDenied,
+			"SOCKS5 proxy requires unsupported authentication",
+		));
+	}
+
+	// Have the proxy resolve and connect to the target host.
+	let mut request = vec![SOCKS5_VERSION, SOCKS5_CMD_CONNECT, 0x00, SOCKS5_ATYP_DOMAIN, host_len];
+	request.extend_from_slice(host.as_bytes());
+	request.extend_from_slice(&port.to_be_bytes());
+	stream.write_all(&request).await?;
+
+	let mut reply = [0u8; 4];
+	stream.read_exact(&mut reply).await?;
+	if reply[0] != SOCKS5_VERSION {
+		return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid SOCKS5 reply"));
+	}
+	if reply[1] != SOCKS5_REPLY_SUCCEEDED {
+		return Err(io::Error::new(
+			io::ErrorKind::ConnectionRefused,
+			format!("SOCKS5 proxy failed to connect with reply code {}", reply[1]),
+		));
+	}
+
+	// Skip the bound address, which we don't make use of.
+	let bound_addr_len = match reply[3] {
+		SOCKS5_ATYP_IPV4 => 4,
+		SOCKS5_ATYP_IPV6 => 16,
+		SOCKS5_ATYP_DOMAIN => {
+			let mut len = [0u8; 1];
+			stream.read_exact(&mut len).await?;
+			len[0] as usize
+		},
+		_ => return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid SOCKS5 address type")),
+	};
+	let mut bound_addr = vec![0u8; bound_addr_len + 2];
+	stream.read_exact(&mut bound_addr).await?;
+
+	Ok(())
+}
+
+/// Publishes an onion service forwarding to our listening port via the Tor control port.
+///
+/// As Tor removes the onion service once the control connection is closed, we keep it open for
+/// as long as we're running and re-publish the service if it drops.
+pub(crate) struct OnionService {
+	control_addr: SocketAddress,
+	target_port: u16,
+	onion_address: RwLock<Option<SocketAddress>>,
+	kv_store: Arc<DynStore>,
+	logger: Arc<Logger>,
+}
+
+impl OnionService {
+	pub(crate) fn new(
+		control_addr: SocketAddress, target_port: u16, kv_store: Arc<DynStore>, logger: Arc<Logger>,
+	) -> Self {
+		let onion_address = RwLock::new(None);
+		Self { control_addr, target_port, onion_address, kv_store, logger }
+	}
+
+	/// Returns the address of our onion service, if it is currently published.
+	pub(crate) fn onion_address(&self) -> Option<SocketAddress> {
+		self.onion_address.read().unwrap().clone()
+	}
+
+	/// Publishes the onion service, retrying with exponential backoff until we're stopped.
+	pub(crate) async fn run(&self, mut stop_receiver: tokio::sync::watch::Receiver<()>) {
+		let mut backoff = ONION_SERVICE_INITIAL_BACKOFF;
+		loop {
+			let res = tokio::select! {
+				_ = stop_receiver.changed() => return,
+				res = self.publish_and_hold() => res,
+			};
+			*self.onion_address.write().unwrap() = None;
+
+			match res {
+				Ok(()) => {
+					log_info!(
+						self.logger,
+						"Tor control connection closed, re-publishing onion service."
+					);
+					backoff = ONION_SERVICE_INITIAL_BACKOFF;
+				},
+				Err(e) => {
+					log_error!(
+						self.logger,
+						"Failed to publish onion service, retrying in {}s: {}",
+						backoff.as_secs(),
+						e
+					);
+				},
+			}
+
+			tokio::select! {
+				_ = stop_receiver.changed() => return,
+				_ = tokio::time::sleep(backoff) => {},
+			}
+			backoff = (backoff * 2).min(ONION_SERVICE_MAX_BACKOFF);
+		}
+	}
+
+	async fn publish_and_hold(&self) -> io::Result<()> {
+		let control_socket_addr = self.control_addr.to_socket_addrs()?.next().ok_or_else(|| {
+			io::Error::new(io::ErrorKind::InvalidInput, "Failed to resolve control address")
+		})?;
+		let stream = TcpStream::connect(control_socket_addr).await?;
+		let (reader, mut writer) = stream.into_split();
+		let mut reader = BufReader::new(reader);
+
+		self.authenticate(&mut reader, &mut writer).await?;
+
+		let persisted_key = self.read_persisted_key();
+		let key = persisted_key.as_deref().unwrap_or("NEW:ED25519-V3");
+		let cmd =
+			format!("ADD_ONION {} Port={},127.0.0.1:{}", key, self.target_port, self.target_port);
+		let reply = send_command(&mut reader, &mut writer, &cmd).await?;
+
+		let service_id = reply
+			.iter()
+			.find_map(|line| line.strip_prefix("ServiceID="))
+			.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing ServiceID"))?;
+		if let Some(private_key) = reply.iter().find_map(|line| line.strip_prefix("PrivateKey=")) {
+			self.persist_key(private_key)?;
+		}
+
+		let onion_address =
+			SocketAddress::from_str(&format!("{}.onion:{}", service_id, self.target_port))
+				.map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Invalid ServiceID"))?;
+		log_info!(self.logger, "Published onion service at {}", onion_address);
+		*self.onion_address.write().unwrap() = Some(onion_address);
+
+		// Hold the connection until Tor closes it.
+		let mut line = String::new();
+		loop {
+			line.clear();
+			if reader.read_line(&mut line).await? == 0 {
+				return Ok(());
+			}
+		}
+	}
+
+	async fn authenticate<R: AsyncBufReadExt + Unpin, W: AsyncWrite + Unpin>(
+		&self, reader: &mut R, writer: &mut W,
+	) -> io::Result<()> {
+		let protocol_info = send_command(reader, writer, "PROTOCOLINFO 1").await?;
+		let auth_line = protocol_info
+			.iter()
+			.find_map(|line| line.strip_prefix("AUTH METHODS="))
+			.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing AUTH METHODS"))?;
+		let (methods, rest) = auth_line.split_once(' ').unwrap_or((auth_line, ""));
+		let methods = methods.split(',').collect::<Vec<_>>();
+
+		let cmd = if methods.contains(&"NULL") {
+			"AUTHENTICATE".to_string()
+		} else if methods.contains(&"COOKIE") {
+			let cookie_file = rest
+				.strip_prefix("COOKIEFILE=\"")
+				.and_then(|s| s.strip_suffix('"'))
+				.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing COOKIEFILE"))?;
+			let cookie = std::fs::read(cookie_file)?;
+			format!("AUTHENTICATE {}", hex_utils::to_string(&cookie))
+		} else {
+			return Err(io::Error::new(
+				io::ErrorKind::PermissionDenied,
+				format!("Unsupported Tor control authentication methods: {}", methods.join(",")),
+			));
+		};
+
+		send_command(reader, writer, &cmd).await?;
+		Ok(())
+	}
+
+	fn read_persisted_key(&self) -> Option<String> {
+		KVStoreSync::read(
+			&*self.kv_store,
+			ONION_SERVICE_KEY_PERSISTENCE_PRIMARY_NAMESPACE,
+			ONION_SERVICE_KEY_PERSISTENCE_SECONDARY_NAMESPACE,
+			ONION_SERVICE_KEY_PERSISTENCE_KEY,
+		)
+		.ok()
+		.and_then(|bytes| String::from_utf8(bytes).ok())
+	}
+
+	fn persist_key(&self, private_key: &str) -> io::Result<()> {
+		KVStoreSync::write(
+			&*self.kv_store,
+			ONION_SERVICE_KEY_PERSISTENCE_PRIMARY_NAMESPACE,
+			ONION_SERVICE_KEY_PERSISTENCE_SECONDARY_NAMESPACE,
+			ONION_SERVICE_KEY_PERSISTENCE_KEY,
+			private_key.as_bytes().to_vec(),
+		)
+		.map_err(|e| {
+			log_error!(self.logger, "Failed to persist onion service key: {}", e);
+			io::Error::new(io::ErrorKind::Other, e.to_string())
+		})
+	}
+}
+
+// Sends the given command to the Tor control port and returns the reply's lines, stripped of
+// their status codes.
+async fn send_command<R: AsyncBufReadExt + Unpin, W: AsyncWrite + Unpin>(
+	reader: &mut R, writer: &mut W, cmd: &str,
+) -> io::Result<Vec<String>> {
+	writer.write_all(format!("{}\r\n", cmd).as_bytes()).await?;
+
+	let mut lines = Vec::new();
+	loop {
+		let mut line = String::new();
+		if reader.read_line(&mut line).await? == 0 {
+			return Err(io::Error::new(
+				io::ErrorKind::UnexpectedEof,
+				"Tor control connection closed",
+			));
+		}
+		let line = line.trim_end();
+		if line.len() < 4 || !line.is_char_boundary(3) {
+			return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid Tor control reply"));
+		}
+
+		let (status, rest) = line.split_at(3);
+		if status != "250" {
+			return Err(io::Error::new(
+				io::ErrorKind::Other,
+				format!("Tor control command failed: {}", line),
+			));
+		}
+
+		let (separator, content) = rest.split_at(1);
+		lines.push(content.to_string());
+		if separator == " " {
+			return Ok(lines);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[tokio::test]
+	async fn socks5_handshake_requests_connection_to_host() {
+		let (mut client, mut proxy) = tokio::io::duplex(1024);
+		let proxy_task = tokio::spawn(async move {
+			let mut greeting = [0u8; 3];
+			proxy.read_exact(&mut greeting).await.unwrap();
+			assert_eq!(greeting, [SOCKS5_VERSION, 1, SOCKS5_AUTH_METHOD_NONE]);
+			proxy.write_all(&[SOCKS5_VERSION, SOCKS5_AUTH_METHOD_NONE]).await.unwrap();
+
+			let host = "example.onion";
+			let mut request = vec![0u8; 5 + host.len() + 2];
+			proxy.read_exact(&mut request).await.unwrap();
+			assert_eq!(
+				request[..5],
+				[SOCKS5_VERSION, SOCKS5_CMD_CONNECT, 0x00, SOCKS5_ATYP_DOMAIN, host.len() as u8]
+			);
+			assert_eq!(&request[5..5 + host.len()], host.as_bytes());
+			assert_eq!(request[5 + host.len()..], 9735u16.to_be_bytes());
+
+			let reply = [SOCKS5_VERSION, SOCKS5_REPLY_SUCCEEDED, 0x00, SOCKS5_ATYP_IPV4];
+			proxy.write_all(&reply).await.unwrap();
+			proxy.write_all(&[127, 0, 0, 1, 0, 0]).await.unwrap();
+		});
+
+		socks5_handshake(&mut client, "example.onion", 9735).await.unwrap();
+		proxy_task.await.unwrap();
+	}
+
+	#[tokio::test]
+	async fn socks5_handshake_fails_on_error_reply() {
+		let (mut client, mut proxy) = tokio::io::duplex(1024);
+		tokio::spawn(async move {
+			let mut greeting = [0u8; 3];
+			proxy.read_exact(&mut greeting).await.unwrap();
+			proxy.write_all(&[SOCKS5_VERSION, SOCKS5_AUTH_METHOD_NONE]).await.unwrap();
+			let mut request = [0u8; 5 + 9 + 2];
+			proxy.read_exact(&mut request).await.unwrap();
+			// Reply with "host unreachable".
+			proxy.write_all(&[SOCKS5_VERSION, 0x04, 0x00, SOCKS5_ATYP_IPV4]).await.unwrap();
+		});
+
+		assert!(socks5_handshake(&mut client, "127.0.0.1", 9735).await.is_err());
+	}
+
+	#[tokio::test]
+	async fn tor_control_replies_are_parsed() {
+		let (client, mut tor) = tokio::io::duplex(1024);
+		let (reader, mut writer) = tokio::io::split(client);
+		let mut reader = BufReader::new(reader);
+		tokio::spawn(async move {
+			let mut cmd = [0u8; 11];
+			tor.read_exact(&mut cmd).await.unwrap();
+			assert_eq!(&cmd, b"ADD_ONION\r\n");
+			tor.write_all(b"250-ServiceID=abc\r\n250-PrivateKey=ED25519-V3:xyz\r\n250 OK\r\n")
+				.await
+				.unwrap();
+		});
+
+		let reply = send_command(&mut reader, &mut writer, "ADD_ONION").await.unwrap();
+		assert_eq!(reply, vec!["ServiceID=abc", "PrivateKey=ED25519-V3:xyz", "OK"]);
+	}
+}
@@ -5,22 +5,37 @@
 // http://opensource.org/licenses/MIT>, at your option. You may not use this file except in
 // accordance with one or both of these licenses.
 
+use std::collections::{HashMap, VecDeque};
 use std::ops::Deref;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
-use bitcoin::Transaction;
+use bitcoin::{OutPoint, Transaction};
 use lightning::chain::chaininterface::BroadcasterInterface;
 use tokio::sync::{mpsc, Mutex, MutexGuard};
 
 use crate::logger::{log_error, LdkLogger};
+use crate::wallet::external::OnchainWalletInterface;
 
 const BCAST_PACKAGE_QUEUE_SIZE: usize = 50;
 
+// The number of recently broadcast transactions we keep around for later lookups.
+const RECENT_TRANSACTIONS_LIMIT: usize = 100;
+
 pub(crate) struct TransactionBroadcaster<L: Deref>
 where
 	L::Target: LdkLogger,
 {
 	queue_sender: mpsc::Sender<Vec<Transaction>>,
 	queue_receiver: Mutex<mpsc::Receiver<Vec<Transaction>>>,
+	// The number of packages that were queued but not yet processed.
+	pending_packages: AtomicUsize,
+	recent_transactions: std::sync::Mutex<VecDeque<Transaction>>,
+	// The funding outpoints of our channels, mapped to the latest transaction we broadcast spending
+	// them, if any.
+	funding_spends: std::sync::Mutex<HashMap<OutPoint, Option<Transaction>>>,
+	// An externally managed wallet to which we additionally hand all transactions, if any.
+	external_wallet: Option<Arc<dyn OnchainWalletInterface>>,
 	logger: L,
 }
 
@@ -28,9 +43,93 @@ impl<L: Deref> TransactionBroadcaster<L>
 where
 	L::Target: LdkLogger,
 {
-	pub(crate) fn new(logger: L) -> Self {
+	pub(crate) fn new(external_wallet: Option<Arc<dyn OnchainWalletInterface>>, logger: L) -> Self {
 		let (queue_sender, queue_receiver) = mpsc::channel(BCAST_PACKAGE_QUEUE_SIZE);
-		Self { queue_sender, queue_receiver: Mutex::new(queue_receiver), logger }
+		let recent_transactions = std::sync::Mutex::new(VecDeque::new());
+		let funding_spends = std::sync::Mutex::new(HashMap::new());
+		Self {
+			queue_sender,
+			queue_receiver: Mutex::new(queue_receiver),
+			pending_packages: AtomicUsize::new(0),
+			recent_transactions,
+			funding_spends,
+			external_wallet,
+			logger,
+		}
+	}
+
+	/// Returns a recently broadcast transaction spending the given outpoint, if any.
+	pub(crate) fn recent_spend_of(&self, outpoint: &OutPoint) -> Option<Transaction> {
+		let locked_recent_transactions = self.recent_transactions.lock().unwrap();
+		locked_recent_transactions
+			.iter()
+			.rev()
+			.find(|tx| tx.input.iter().any(|txin| txin.previous_output == *outpoint))
+			.cloned()
+	}
+
+	/// Starts tracking the transactions we broadcast spending the given channel funding outpoint.
+	pub(crate) fn watch_funding_outpoint(&self, funding_txo: OutPoint) {
+		self.funding_spends.lock().unwrap().entry(funding_txo).or_insert(None);
+	}
+
+	/// Stops tracking the transactions we broadcast spending the given channel funding outpoint.
+	pub(crate) fn unwatch_funding_outpoint(&self, funding_txo: &OutPoint) {
+		self.funding_spends.lock().unwrap().remove(funding_txo);
+	}
+
+	/// Returns the latest transaction we broadcast spending the given watched channel funding
+	/// outpoint, if any.
+	pub(crate) fn funding_spend(&self, funding_txo: &OutPoint) -> Option<Transaction> {
+		self.funding_spends.lock().unwrap().get(funding_txo).cloned().flatten()
+	}
+
+	/// Returns whether all queued transaction packages have been processed, i.e., handed to the
+	/// chain source for broadcasting.
+	pub(crate) fn all_packages_processed(&self) -> bool {
+		self.pending_packages.load(Ordering::Acquire) == 0
+	}
+
+	/// Marks a package received from the broadcast queue as processed.
+	pub(crate) fn package_processed(&self) {
+		self.pending_packages.fetch_sub(1, Ordering::AcqRel);
+	}
+
+	/// Hands the given package to the external wallet, if any.
+	///
+	/// Note this is called when processing the broadcast queue rather than from
+	/// [`BroadcasterInterface::broadcast_transactions`], as the latter may be called while LDK
+	/// holds locks and we don't want to block on user code then.
+	pub(crate) async fn broadcast_via_external_wallet(&self, package: &[Transaction]) {
+		let external_wallet = match self.external_wallet.as_ref() {
+			Some(external_wallet) => Arc::clone(external_wallet),
+			None => return,
+		};
+		let package = package.to_vec();
+		let res = tokio::task::spawn_blocking(move || {
+			package
+				.iter()
+				.map(|tx| (tx.compute_txid(), external_wallet.broadcast(tx)))
+				.collect::<Vec<_>>()
+		})
+		.await;
+		match res {
+			Ok(results) => {
+				for (txid, res) in results {
+					if let Err(e) = res {
+						log_error!(
+							self.logger,
+							"Failed to broadcast transaction {} via external wallet: {}",
+							txid,
+							e
+						);
+					}
+				}
+			},
+			Err(e) => {
+				log_error!(self.logger, "Failed to broadcast via external wallet: {}", e);
+			},
+		}
 	}
 
 	pub(crate) async fn get_broadcast_queue(
@@ -46,7 +145,26 @@ where
 {
 	fn broadcast_transactions(&self, txs: &[&Transaction]) {
 		let package = txs.iter().map(|&t| t.clone()).collect::<Vec<Transaction>>();
+		{
+			let mut locked_recent_transactions = self.recent_transactions.lock().unwrap();
+			locked_recent_transactions.extend(package.iter().cloned());
+			while locked_recent_transactions.len() > RECENT_TRANSACTIONS_LIMIT {
+				locked_recent_transactions.pop_front();
+			}
+		}
+		{
+			let mut locked_funding_spends = self.funding_spends.lock().unwrap();
+			for tx in &package {
+				for txin in &tx.input {
+					if let Some(spend) = locked_funding_spends.get_mut(&txin.previous_output) {
+						*spend = Some(tx.clone());
+					}
+				}
+			}
+		}
+		self.pending_packages.fetch_add(1, Ordering::AcqRel);
 		self.queue_sender.try_send(package).unwrap_or_else(|e| {
+			self.pending_packages.fetch_sub(1, Ordering::AcqRel);
 			log_error!(self.logger, "Failed to broadcast transactions: {}", e);
 		});
 	}
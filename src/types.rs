@@ -5,36 +5,51 @@
 // http://opensource.org/licenses/MIT>, at your option. You may not use this file except in
 // accordance with one or both of these licenses.
 
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
+use bitcoin::psbt::Psbt;
 use bitcoin::secp256k1::PublicKey;
-use bitcoin::OutPoint;
+use bitcoin::{OutPoint, ScriptBuf};
+use lightning::blinded_path::message::OffersContext;
 use lightning::chain::chainmonitor;
+use lightning::events::bump_transaction::BumpTransactionEvent;
 use lightning::impl_writeable_tlv_based;
 use lightning::ln::channel_state::ChannelDetails as LdkChannelDetails;
+use lightning::ln::channelmanager::PaymentId;
 use lightning::ln::msgs::{RoutingMessageHandler, SocketAddress};
 use lightning::ln::peer_handler::IgnoringMessageHandler;
 use lightning::ln::types::ChannelId;
+use lightning::offers::invoice::Bolt12Invoice;
 use lightning::routing::gossip;
 use lightning::routing::router::DefaultRouter;
 use lightning::routing::scoring::CombinedScorer;
 use lightning::routing::scoring::ProbabilisticScoringFeeParameters;
 use lightning::sign::InMemorySigner;
-use lightning::util::persist::{KVStore, KVStoreSync, MonitorUpdatingPersister};
+use lightning::util::persist::{KVStore, KVStoreSync, MigratableKVStore, MonitorUpdatingPersister};
 use lightning::util::ser::{Readable, Writeable, Writer};
 use lightning::util::sweep::OutputSweeper;
 use lightning_block_sync::gossip::{GossipVerifier, UtxoSource};
 use lightning_liquidity::utils::time::DefaultTimeProvider;
 use lightning_net_tokio::SocketDescriptor;
+use tokio::sync::oneshot;
 
 use crate::chain::ChainSource;
-use crate::config::ChannelConfig;
+use crate::closed_channel::ClosedChannelDetails;
+use crate::config::{ChannelConfig, RetryStrategy};
 use crate::data_store::DataStore;
-use crate::fee_estimator::OnchainFeeEstimator;
+use crate::error::Error;
+use crate::fee_estimator::{ConfirmationTarget, OnchainFeeEstimator};
 use crate::gossip::RuntimeSpawner;
 use crate::logger::Logger;
-use crate::message_handler::NodeCustomMessageHandler;
+use crate::message_handler::{
+	NodeChannelMessageHandler, NodeCustomMessageHandler, NodeCustomOnionMessageHandler,
+	NodeMessageRouter,
+};
+use crate::payment::recurring::RecurringPaymentSchedule;
 use crate::payment::PaymentDetails;
+use crate::watchtower::WatchtowerPersister;
 
 /// A supertrait that requires that a type implements both [`KVStore`] and [`KVStoreSync`] at the
 /// same time.
@@ -50,6 +65,9 @@ where
 /// A type alias for [`SyncAndAsyncKVStore`] with `Sync`/`Send` markers;
 pub type DynStore = dyn SyncAndAsyncKVStore + Sync + Send;
 
+// A store that is able to enumerate all of its keys, allowing us to migrate its data.
+pub(crate) type DynMigratableStore = dyn MigratableKVStore + Sync + Send;
+
 pub type Persister = MonitorUpdatingPersister<
 	Arc<DynStore>,
 	Arc<Logger>,
@@ -65,13 +83,13 @@ pub(crate) type ChainMonitor = chainmonitor::ChainMonitor<
 	Arc<Broadcaster>,
 	Arc<OnchainFeeEstimator>,
 	Arc<Logger>,
-	Arc<Persister>,
+	Arc<WatchtowerPersister>,
 	Arc<KeysManager>,
 >;
 
 pub(crate) type PeerManager = lightning::ln::peer_handler::PeerManager<
 	SocketDescriptor,
-	Arc<ChannelManager>,
+	Arc<NodeChannelMessageHandler>,
 	Arc<dyn RoutingMessageHandler + Send + Sync>,
 	Arc<OnionMessenger>,
 	Arc<Logger>,
@@ -143,10 +161,12 @@ pub(crate) type OnionMessenger = lightning::onion_message::messenger::OnionMesse
 	Arc<ChannelManager>,
 	Arc<ChannelManager>,
 	IgnoringMessageHandler,
-	IgnoringMessageHandler,
+	Arc<NodeCustomOnionMessageHandler<Arc<Logger>>>,
 >;
 
-pub(crate) type MessageRouter = lightning::onion_message::messenger::DefaultMessageRouter<
+pub(crate) type MessageRouter = NodeMessageRouter;
+
+pub(crate) type DefaultMessageRouter = lightning::onion_message::messenger::DefaultMessageRouter<
 	Arc<Graph>,
 	Arc<Logger>,
 	Arc<KeysManager>,
@@ -172,6 +192,65 @@ pub(crate) type BumpTransactionEventHandler =
 
 pub(crate) type PaymentStore = DataStore<PaymentDetails, Arc<Logger>>;
 
+pub(crate) type ClosedChannelStore = DataStore<ClosedChannelDetails, Arc<Logger>>;
+
+pub(crate) type RecurringPaymentStore = DataStore<RecurringPaymentSchedule, Arc<Logger>>;
+
+// The retry strategies of our pending outbound payments, allowing us to tell which bound was hit
+// once a payment fails after exhausting its retries.
+pub(crate) type PaymentRetryStrategies = Mutex<HashMap<PaymentId, RetryStrategy>>;
+
+// The callers of `Node::wait_for_channel_ready` waiting for the channel with the given user
+// channel ID to either become ready or close.
+pub(crate) type ChannelReadyWaiters = Mutex<HashMap<u128, Vec<oneshot::Sender<Result<(), Error>>>>>;
+
+// The state of a channel we're opening with an externally signed funding transaction, see
+// `Node::open_channel_psbt`.
+pub(crate) enum PendingExternalFunding {
+	// We're waiting for the counterparty to accept the channel, i.e., for LDK to ask us to generate
+	// the funding transaction.
+	AwaitingFundingGeneration {
+		sender: oneshot::Sender<Result<(), Error>>,
+	},
+	// We handed out the funding PSBT and are waiting for it to be returned signed.
+	AwaitingSignedPsbt {
+		temporary_channel_id: ChannelId,
+		counterparty_node_id: PublicKey,
+		output_script: ScriptBuf,
+		channel_value_sats: u64,
+		deadline: Instant,
+	},
+	// We gave up waiting for the counterparty to accept the channel. We keep the entry around until
+	// the channel is closed so that we never fund it from our on-chain wallet instead.
+	Abandoned,
+}
+
+// The channels we're currently opening with an externally signed funding transaction, keyed by
+// their user channel ID.
+pub(crate) type PendingExternalFundings = Mutex<HashMap<u128, PendingExternalFunding>>;
+
+// The state of a BOLT12 invoice we requested via `Bolt12Payment::request_invoice` without paying
+// it right away.
+pub(crate) enum PendingBolt12Invoice {
+	// We're waiting for the recipient to respond to our invoice request.
+	AwaitingInvoice { sender: oneshot::Sender<Result<Bolt12Invoice, Error>> },
+	// We received the invoice and are waiting for it to be paid via
+	// `Bolt12Payment::pay_bolt12_invoice`.
+	InvoiceReceived { invoice: Bolt12Invoice, context: Option<OffersContext> },
+}
+
+// The BOLT12 invoices we requested without paying them yet, keyed by the ID of the payment LDK
+// tracks for them.
+pub(crate) type PendingBolt12Invoices = Mutex<HashMap<PaymentId, PendingBolt12Invoice>>;
+
+// The confirmation targets chosen for the funding transactions of the channels with the given user
+// channel IDs we're currently opening.
+pub(crate) type FundingConfirmationTargets = Mutex<HashMap<u128, ConfirmationTarget>>;
+
+// The latest `BumpTransactionEvent::ChannelClose` we received for each channel, allowing us to
+// bump the fee of its commitment transaction on demand.
+pub(crate) type PendingChannelCloseBumps = Mutex<HashMap<ChannelId, BumpTransactionEvent>>;
+
 /// A local, potentially user-provided, identifier of a channel.
 ///
 /// By default, this will be randomly generated for the user to ensure local uniqueness.
@@ -345,6 +424,12 @@ pub struct ChannelDetails {
 	pub inbound_htlc_maximum_msat: Option<u64>,
 	/// Set of configurable parameters that affect channel operation.
 	pub config: ChannelConfig,
+	/// The local label we assigned to this channel via [`Node::set_channel_label`], if any.
+	///
+	/// Not to be confused with the channel's SCID alias, see [`Self::inbound_scid_alias`].
+	///
+	/// [`Node::set_channel_label`]: crate::Node::set_channel_label
+	pub label: Option<String>,
 }
 
 impl From<LdkChannelDetails> for ChannelDetails {
@@ -399,6 +484,7 @@ impl From<LdkChannelDetails> for ChannelDetails {
 			inbound_htlc_maximum_msat: value.inbound_htlc_maximum_msat,
 			// unwrap safety: `config` is only `None` for LDK objects serialized prior to 0.0.109.
 			config: value.config.map(|c| c.into()).unwrap(),
+			label: None,
 		}
 	}
 }
@@ -416,6 +502,168 @@ pub struct PeerDetails {
 	pub is_persisted: bool,
 	/// Indicates whether we currently have an active connection with the peer.
 	pub is_connected: bool,
+	/// The time of our last outbound connection attempt to the peer, in seconds since the UNIX
+	/// epoch.
+	///
+	/// Will be `None` if we haven't tried to connect to the peer since we were started.
+	pub last_connection_attempt_timestamp: Option<u64>,
+	/// The reason our last outbound connection attempt to the peer failed.
+	///
+	/// Will be `None` if the last attempt succeeded or we haven't tried to connect to the peer
+	/// since we were started.
+	pub last_connection_failure_reason: Option<ConnectionFailureReason>,
+	/// The local label we assigned to this peer via [`Node::set_peer_label`], if any.
+	///
+	/// [`Node::set_peer_label`]: crate::Node::set_peer_label
+	pub label: Option<String>,
+	/// The time we last established a connection with the peer, in seconds since the UNIX epoch.
+	///
+	/// Will be `None` if we haven't been connected to the peer since we were started, unless the
+	/// peer is persisted, in which case its connection history survives restarts.
+	pub last_connected_unix_secs: Option<u64>,
+	/// The time we were last disconnected from the peer, in seconds since the UNIX epoch.
+	///
+	/// Subject to the same restrictions as [`PeerDetails::last_connected_unix_secs`].
+	pub last_disconnected_unix_secs: Option<u64>,
+	/// The number of channels we have with the peer, including the ones not yet ready to be
+	/// used.
+	pub total_channels: u32,
+}
+
+/// The result of a connection attempt as returned by [`Node::connect_with_timeout`].
+///
+/// [`Node::connect_with_timeout`]: crate::Node::connect_with_timeout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectResult {
+	/// We successfully established a new connection with the peer.
+	Connected,
+	/// We were already connected to the peer.
+	AlreadyConnected,
+	/// We failed to connect to the peer.
+	Failed {
+		/// The reason the connection attempt failed.
+		reason: ConnectionFailureReason,
+	},
+}
+
+/// The outcome of force-closing one of the channels with a peer via
+/// [`Node::force_close_all_channels_with_peer`].
+///
+/// [`Node::force_close_all_channels_with_peer`]: crate::Node::force_close_all_channels_with_peer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelForceCloseResult {
+	/// The channel's ID.
+	pub channel_id: ChannelId,
+	/// The local `user_channel_id` of the channel.
+	pub user_channel_id: UserChannelId,
+	/// Whether the force-closure succeeded.
+	pub outcome: ForceCloseOutcome,
+}
+
+/// Whether we succeeded to force-close a channel, as part of a [`ChannelForceCloseResult`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForceCloseOutcome {
+	/// We force-closed the channel, broadcasting our latest state if necessary.
+	///
+	/// This includes channels we were in the process of closing cooperatively.
+	ForceClosed,
+	/// The channel was closed before we could force-close it, e.g., as a cooperative closure
+	/// completed in the meantime.
+	AlreadyClosed,
+	/// We failed to force-close the channel.
+	Failed,
+}
+
+/// A summary of how our channels were closed, as returned by [`Node::close_all_channels`].
+///
+/// [`Node::close_all_channels`]: crate::Node::close_all_channels
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CloseAllChannelsSummary {
+	/// The channels that were closed cooperatively.
+	pub cooperatively_closed_channels: Vec<ChannelId>,
+	/// The channels that were force-closed, either by us as they weren't closed cooperatively in
+	/// time, or by the counterparty.
+	pub force_closed_channels: Vec<ChannelId>,
+	/// The channels for which we didn't learn how they were closed before returning.
+	pub pending_channels: Vec<ChannelId>,
+}
+
+/// A report on the changes a manual wallet sync resulted in, as returned by [`Node::sync_wallets`].
+///
+/// [`Node::sync_wallets`]: crate::Node::sync_wallets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyncReport {
+	/// The number of on-chain wallet transactions that were newly discovered or whose confirmation
+	/// status changed.
+	pub updated_onchain_transactions: u64,
+	/// The number of on-chain wallet outputs that were newly received or spent.
+	pub updated_onchain_outputs: u64,
+	/// The height of the best block known to the node after the sync.
+	pub best_block_height: u32,
+}
+
+/// The power mode a [`Node`] is operating in, as returned by [`Node::power_mode`].
+///
+/// [`Node`]: crate::Node
+/// [`Node::power_mode`]: crate::Node::power_mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerMode {
+	/// All background tasks are running as usual.
+	Normal,
+	/// Background syncing and gossip processing are paused.
+	///
+	/// See [`Node::enter_low_power_mode`] for details.
+	///
+	/// [`Node::enter_low_power_mode`]: crate::Node::enter_low_power_mode
+	LowPower,
+}
+
+/// The number of words of a [BIP 39] mnemonic.
+///
+/// [BIP 39]: https://github.com/bitcoin/bips/blob/master/bip-0039.mediawiki
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WordCount {
+	/// A 12-word mnemonic, encoding 128 bits of entropy.
+	Words12,
+	/// A 15-word mnemonic, encoding 160 bits of entropy.
+	Words15,
+	/// An 18-word mnemonic, encoding 192 bits of entropy.
+	Words18,
+	/// A 21-word mnemonic, encoding 224 bits of entropy.
+	Words21,
+	/// A 24-word mnemonic, encoding 256 bits of entropy.
+	Words24,
+}
+
+impl WordCount {
+	/// Returns the number of entropy bytes encoded by a mnemonic of this length.
+	pub(crate) fn entropy_len(&self) -> usize {
+		match self {
+			Self::Words12 => 16,
+			Self::Words15 => 20,
+			Self::Words18 => 24,
+			Self::Words21 => 28,
+			Self::Words24 => 32,
+		}
+	}
+}
+
+/// The reason an outbound connection attempt failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionFailureReason {
+	/// The given address could not be resolved.
+	DnsResolutionFailed,
+	/// The given address is not supported, e.g., as it's an onion address but no Tor proxy is
+	/// configured.
+	UnsupportedAddress,
+	/// The peer refused the TCP connection.
+	ConnectionRefused,
+	/// The TCP connection could not be established for another reason.
+	ConnectionFailed,
+	/// The TCP connection was established but the noise handshake with the peer failed.
+	HandshakeFailed,
+	/// The connection attempt did not complete within the given timeout.
+	Timeout,
 }
 
 /// Custom TLV entry.
@@ -437,3 +685,23 @@ impl From<&(u64, Vec<u8>)> for CustomTlvRecord {
 		CustomTlvRecord { type_num: tlv.0, value: tlv.1.clone() }
 	}
 }
+
+/// An unsigned PSBT funding a channel opened via [`Node::open_channel_psbt`].
+///
+/// [`Node::open_channel_psbt`]: crate::Node::open_channel_psbt
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChannelFundingPsbt {
+	/// The temporary channel ID identifying the channel until it's funded.
+	///
+	/// Needs to be passed to [`Node::finalize_channel_open`] alongside the signed PSBT.
+	///
+	/// [`Node::finalize_channel_open`]: crate::Node::finalize_channel_open
+	pub temporary_channel_id: ChannelId,
+	/// The local identifier of the channel.
+	pub user_channel_id: UserChannelId,
+	/// The unsigned PSBT containing the channel's funding output.
+	///
+	/// The PSBT doesn't contain any inputs yet. The external wallet is expected to add inputs and
+	/// any change outputs, but must leave the funding output unchanged.
+	pub psbt: Psbt,
+}
@@ -0,0 +1,67 @@
+// This file is Copyright its original authors, visible in version control history.
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. You may not use this file except in
+// accordance with one or both of these licenses.
+
+use bitcoin::psbt::Psbt;
+use bitcoin::{Address, Transaction};
+use lightning::events::bump_transaction::Utxo;
+
+use crate::Error;
+
+/// An externally managed on-chain wallet, e.g., an existing BDK or hardware wallet, that owns the
+/// keys to the on-chain funds used by our channels.
+///
+/// Can be set via [`Builder::set_external_onchain_wallet`], in which case the following operations
+/// are routed through the external wallet rather than the node's internal on-chain wallet:
+/// - Funding transactions of channels we open are built from the UTXOs returned by
+///   [`get_utxos`], with any change going to [`get_change_address`], and signed via
+///   [`sign_psbt`].
+/// - The funds of cooperatively closed channels, as well as any outputs swept after
+///   force-closures, are sent to addresses returned by [`get_change_address`].
+/// - Fee-bumping Anchor channel closures spends the UTXOs returned by [`get_utxos`].
+/// - Any transaction we broadcast is also handed to [`broadcast`].
+///
+/// On-chain payments via [`Node::onchain_payment`] and splicing continue to use the internal
+/// on-chain wallet.
+///
+/// All methods are called synchronously, i.e., the external wallet needs to be available
+/// whenever we open a channel, when a channel is closed, and when we claim or sweep on-chain
+/// funds. As methods may be called while we're processing events, implementations must not
+/// block for extended periods of time and must not call back into [`Node`].
+///
+/// [`Builder::set_external_onchain_wallet`]: crate::Builder::set_external_onchain_wallet
+/// [`Node`]: crate::Node
+/// [`Node::onchain_payment`]: crate::Node::onchain_payment
+/// [`get_utxos`]: Self::get_utxos
+/// [`get_change_address`]: Self::get_change_address
+/// [`sign_psbt`]: Self::sign_psbt
+/// [`broadcast`]: Self::broadcast
+pub trait OnchainWalletInterface: Send + Sync {
+	/// Returns a fresh address of the external wallet to which change and any recovered funds are
+	/// sent.
+	///
+	/// The address must be a SegWit address.
+	fn get_change_address(&self) -> Result<Address, Error>;
+
+	/// Signs and finalizes all inputs of the given PSBT, returning the signed PSBT.
+	///
+	/// The PSBT will only spend UTXOs previously returned by [`get_utxos`], whose
+	/// `witness_utxo` fields are populated. The external wallet must not modify the unsigned
+	/// transaction.
+	///
+	/// [`get_utxos`]: Self::get_utxos
+	fn sign_psbt(&self, psbt: Psbt) -> Result<Psbt, Error>;
+
+	/// Broadcasts the given transaction.
+	///
+	/// We'll broadcast the transaction via the configured chain source in any case, failures to
+	/// broadcast it via the external wallet are merely logged.
+	fn broadcast(&self, tx: &Transaction) -> Result<(), Error>;
+
+	/// Returns the confirmed SegWit UTXOs that may be spent to fund channels and to bump the fees
+	/// of channel closures.
+	fn get_utxos(&self) -> Result<Vec<Utxo>, Error>;
+}
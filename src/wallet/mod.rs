@@ -5,15 +5,17 @@
 // http://opensource.org/licenses/MIT>, at your option. You may not use this file except in
 // accordance with one or both of these licenses.
 
+use std::collections::{HashMap, HashSet};
 use std::future::Future;
 use std::pin::Pin;
 use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 
 use bdk_chain::spk_client::{FullScanRequest, SyncRequest};
+use bdk_wallet::error::BuildFeeBumpError;
 #[allow(deprecated)]
 use bdk_wallet::SignOptions;
-use bdk_wallet::{Balance, KeychainKind, PersistedWallet, Update};
+use bdk_wallet::{Balance, KeychainKind, PersistedWallet, TxBuilder, Update};
 use bitcoin::address::NetworkUnchecked;
 use bitcoin::blockdata::constants::WITNESS_SCALE_FACTOR;
 use bitcoin::blockdata::locktime::absolute::LockTime;
@@ -24,14 +26,19 @@ use bitcoin::secp256k1::ecdh::SharedSecret;
 use bitcoin::secp256k1::ecdsa::{RecoverableSignature, Signature};
 use bitcoin::secp256k1::{All, PublicKey, Scalar, Secp256k1, SecretKey};
 use bitcoin::{
-	Address, Amount, FeeRate, Network, ScriptBuf, Transaction, TxOut, Txid, WPubkeyHash,
-	WitnessProgram, WitnessVersion,
+	Address, Amount, BlockHash, FeeRate, Network, OutPoint, ScriptBuf, Sequence, Transaction,
+	TxOut, Txid, WPubkeyHash, WitnessProgram, WitnessVersion,
 };
+use external::OnchainWalletInterface;
 use lightning::chain::chaininterface::BroadcasterInterface;
 use lightning::chain::channelmonitor::ANTI_REORG_DELAY;
-use lightning::chain::{BestBlock, Listen};
-use lightning::events::bump_transaction::{Utxo, WalletSource};
+use lightning::chain::{BestBlock, ClaimId, Listen};
+use lightning::events::bump_transaction::{
+	CoinSelectionSource, Input, Utxo, Wallet as LdkWallet, WalletSource,
+};
+use lightning::ln::chan_utils::FUNDING_TRANSACTION_WITNESS_WEIGHT;
 use lightning::ln::channelmanager::PaymentId;
+use lightning::ln::funding::{FundingTxInput, SpliceContribution};
 use lightning::ln::inbound_payment::ExpandedKey;
 use lightning::ln::msgs::UnsignedGossipMessage;
 use lightning::ln::script::ShutdownScript;
@@ -44,10 +51,10 @@ use lightning_invoice::RawBolt11Invoice;
 use persist::KVStoreWalletPersister;
 
 use crate::config::Config;
-use crate::fee_estimator::{ConfirmationTarget, FeeEstimator, OnchainFeeEstimator};
+use crate::fee_estimator::{ConfirmationTarget, OnchainFeeEstimator};
 use crate::logger::{log_debug, log_error, log_info, log_trace, LdkLogger, Logger};
-use crate::payment::store::ConfirmationStatus;
-use crate::payment::{PaymentDetails, PaymentDirection, PaymentStatus};
+use crate::payment::store::{ConfirmationStatus, PaymentDetailsUpdate};
+use crate::payment::{PaymentDetails, PaymentDirection, PaymentStatus, SpendableUtxo};
 use crate::types::{Broadcaster, PaymentStore};
 use crate::Error;
 
@@ -57,9 +64,49 @@ pub(crate) enum OnchainSendAmount {
 	AllDrainingReserve,
 }
 
+pub(crate) mod external;
 pub(crate) mod persist;
 pub(crate) mod ser;
 
+// The weight of a transaction's version, lock time, input and output counts, as well as of the
+// SegWit marker and flag.
+const BASE_TX_WEIGHT: u64 = 4 * (4 + 4 + 1 + 1) + 2;
+
+// The weight of an input's previous outpoint and sequence, excluding its script_sig and witness.
+const BASE_INPUT_WEIGHT: u64 = 4 * (36 + 4);
+
+/// A snapshot of the wallet's transactions and unspent outputs, allowing to determine how many of
+/// them were updated by a sync.
+pub(crate) struct WalletSnapshot {
+	// The wallet transactions, mapped to the hash of the block they're confirmed in, if any.
+	tx_confirmations: HashMap<Txid, Option<BlockHash>>,
+	utxos: HashSet<OutPoint>,
+}
+
+impl WalletSnapshot {
+	/// Returns the number of transactions that are new or whose confirmation status changed since
+	/// the given previous snapshot.
+	pub(crate) fn updated_transactions_since(&self, previous: &WalletSnapshot) -> u64 {
+		self.tx_confirmations
+			.iter()
+			.filter(|(txid, block_hash)| previous.tx_confirmations.get(*txid) != Some(*block_hash))
+			.count() as u64
+	}
+
+	/// Returns the number of unspent outputs that were received or spent since the given
+	/// previous snapshot.
+	pub(crate) fn updated_outputs_since(&self, previous: &WalletSnapshot) -> u64 {
+		self.utxos.symmetric_difference(&previous.utxos).count() as u64
+	}
+}
+
+// The kind of transaction an external wallet's UTXO was reserved for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum UtxoReservation {
+	Funding,
+	FeeBump,
+}
+
 pub(crate) struct Wallet {
 	// A BDK on-chain wallet.
 	inner: Mutex<PersistedWallet<KVStoreWalletPersister>>,
@@ -67,6 +114,12 @@ pub(crate) struct Wallet {
 	broadcaster: Arc<Broadcaster>,
 	fee_estimator: Arc<OnchainFeeEstimator>,
 	payment_store: Arc<PaymentStore>,
+	// An externally managed wallet that, if set, funds our channels and receives any funds
+	// recovered from them in place of the BDK wallet.
+	external_wallet: Option<Arc<dyn OnchainWalletInterface>>,
+	// The external wallet's UTXOs we already spend in a funding or fee-bumping transaction that
+	// might not have been broadcast yet, and which therefore must not be selected again.
+	reserved_external_utxos: Mutex<HashMap<OutPoint, UtxoReservation>>,
 	config: Arc<Config>,
 	logger: Arc<Logger>,
 }
@@ -76,11 +129,28 @@ impl Wallet {
 		wallet: bdk_wallet::PersistedWallet<KVStoreWalletPersister>,
 		wallet_persister: KVStoreWalletPersister, broadcaster: Arc<Broadcaster>,
 		fee_estimator: Arc<OnchainFeeEstimator>, payment_store: Arc<PaymentStore>,
-		config: Arc<Config>, logger: Arc<Logger>,
+		external_wallet: Option<Arc<dyn OnchainWalletInterface>>, config: Arc<Config>,
+		logger: Arc<Logger>,
 	) -> Self {
 		let inner = Mutex::new(wallet);
 		let persister = Mutex::new(wallet_persister);
-		Self { inner, persister, broadcaster, fee_estimator, payment_store, config, logger }
+		let reserved_external_utxos = Mutex::new(HashMap::new());
+		Self {
+			inner,
+			persister,
+			broadcaster,
+			fee_estimator,
+			payment_store,
+			external_wallet,
+			reserved_external_utxos,
+			config,
+			logger,
+		}
+	}
+
+	/// Returns whether our channels are funded by an external on-chain wallet.
+	pub(crate) fn has_external_wallet(&self) -> bool {
+		self.external_wallet.is_some()
 	}
 
 	pub(crate) fn get_full_scan_request(&self) -> FullScanRequest<KeychainKind> {
@@ -105,6 +175,24 @@ impl Wallet {
 			.collect()
 	}
 
+	pub(crate) fn snapshot(&self) -> WalletSnapshot {
+		let locked_wallet = self.inner.lock().unwrap();
+		let tx_confirmations = locked_wallet
+			.transactions()
+			.map(|t| {
+				let block_hash = match t.chain_position {
+					bdk_chain::ChainPosition::Confirmed { anchor, .. } => {
+						Some(anchor.block_id.hash)
+					},
+					bdk_chain::ChainPosition::Unconfirmed { .. } => None,
+				};
+				(t.tx_node.txid, block_hash)
+			})
+			.collect();
+		let utxos = locked_wallet.list_unspent().map(|u| u.outpoint).collect();
+		WalletSnapshot { tx_confirmations, utxos }
+	}
+
 	pub(crate) fn current_best_block(&self) -> BestBlock {
 		let checkpoint = self.inner.lock().unwrap().latest_checkpoint();
 		BestBlock { block_hash: checkpoint.hash(), height: checkpoint.height() }
@@ -231,10 +319,21 @@ impl Wallet {
 	) -> Result<Transaction, Error> {
 		let fee_rate = self.fee_estimator.estimate_fee_rate(confirmation_target);
 
+		if let Some(external_wallet) = self.external_wallet.as_ref() {
+			return self.create_external_funding_transaction(
+				external_wallet.as_ref(),
+				output_script,
+				amount,
+				fee_rate,
+				locktime,
+			);
+		}
+
 		let mut locked_wallet = self.inner.lock().unwrap();
 		let mut tx_builder = locked_wallet.build_tx();
 
 		tx_builder.add_recipient(output_script, amount).fee_rate(fee_rate).nlocktime(locktime);
+		self.maybe_disable_rbf(&mut tx_builder);
 
 		let mut psbt = match tx_builder.finish() {
 			Ok(psbt) => {
@@ -273,6 +372,271 @@ impl Wallet {
 		Ok(tx)
 	}
 
+	// Returns the external wallet's UTXOs that are available to a transaction of the given kind.
+	// Reservations of UTXOs the external wallet doesn't list anymore, i.e., that were spent in the
+	// meantime, are dropped.
+	//
+	// Fee bumps may reuse the UTXOs reserved by earlier fee bumps, as LDK replaces its own
+	// bumping transactions.
+	fn unreserved_external_utxos(
+		&self, external_wallet: &dyn OnchainWalletInterface,
+		locked_reserved_utxos: &mut HashMap<OutPoint, UtxoReservation>,
+		reservation: UtxoReservation,
+	) -> Result<Vec<Utxo>, Error> {
+		let utxos = external_wallet.get_utxos()?;
+		locked_reserved_utxos.retain(|outpoint, _| utxos.iter().any(|u| &u.outpoint == outpoint));
+		Ok(utxos
+			.into_iter()
+			.filter(|u| match locked_reserved_utxos.get(&u.outpoint) {
+				None => true,
+				Some(UtxoReservation::FeeBump) => reservation == UtxoReservation::FeeBump,
+				Some(UtxoReservation::Funding) => false,
+			})
+			.collect())
+	}
+
+	/// Releases the reservations of the external wallet's UTXOs spent by the given transaction,
+	/// e.g., as the channel it would have funded was closed before it was broadcast.
+	pub(crate) fn release_external_utxos(&self, tx: &Transaction) {
+		let mut locked_reserved_utxos = self.reserved_external_utxos.lock().unwrap();
+		for input in &tx.input {
+			locked_reserved_utxos.remove(&input.previous_output);
+		}
+	}
+
+	// Builds a funding transaction from the external wallet's UTXOs, selecting the largest UTXOs
+	// first, and has the external wallet sign it.
+	//
+	// The selected UTXOs are reserved so that concurrent fundings and fee bumps won't try to
+	// spend them, too.
+	fn create_external_funding_transaction(
+		&self, external_wallet: &dyn OnchainWalletInterface, output_script: ScriptBuf,
+		amount: Amount, fee_rate: FeeRate, locktime: LockTime,
+	) -> Result<Transaction, Error> {
+		let change_script = external_wallet.get_change_address()?.script_pubkey();
+		let mut locked_reserved_utxos = self.reserved_external_utxos.lock().unwrap();
+		let mut utxos = self.unreserved_external_utxos(
+			external_wallet,
+			&mut locked_reserved_utxos,
+			UtxoReservation::Funding,
+		)?;
+		utxos.sort_by_key(|u| std::cmp::Reverse(u.output.value));
+
+		let funding_output = TxOut { value: amount, script_pubkey: output_script };
+		let change_output = TxOut { value: Amount::ZERO, script_pubkey: change_script };
+		let fee_for_weight =
+			|weight: u64| Amount::from_sat((fee_rate.to_sat_per_kwu() * weight).div_ceil(1000));
+
+		let mut weight =
+			BASE_TX_WEIGHT + funding_output.weight().to_wu() + change_output.weight().to_wu();
+		let mut selected_utxos = Vec::new();
+		let mut input_value = Amount::ZERO;
+		for utxo in utxos {
+			if input_value >= amount + fee_for_weight(weight) {
+				break;
+			}
+			weight += BASE_INPUT_WEIGHT + utxo.satisfaction_weight;
+			input_value += utxo.output.value;
+			selected_utxos.push(utxo);
+		}
+
+		let fee = fee_for_weight(weight);
+		if input_value < amount + fee {
+			log_error!(
+				self.logger,
+				"Unable to create funding transaction due to insufficient funds in the external wallet. Available: {}, Required: {}",
+				input_value,
+				amount + fee
+			);
+			return Err(Error::InsufficientFunds);
+		}
+
+		let mut output = vec![funding_output];
+		// Any change below the dust limit is left to the fees.
+		let change_value = input_value - amount - fee;
+		if change_value >= change_output.script_pubkey.minimal_non_dust() {
+			output.push(TxOut { value: change_value, ..change_output });
+		}
+
+		let sequence = if self.config.enable_rbf_by_default {
+			Sequence::ENABLE_RBF_NO_LOCKTIME
+		} else {
+			Sequence::ENABLE_LOCKTIME_NO_RBF
+		};
+		let input = selected_utxos
+			.iter()
+			.map(|utxo| bitcoin::TxIn {
+				previous_output: utxo.outpoint,
+				sequence,
+				..Default::default()
+			})
+			.collect();
+		let unsigned_tx = Transaction {
+			version: bitcoin::transaction::Version::TWO,
+			lock_time: locktime,
+			input,
+			output,
+		};
+		let unsigned_txid = unsigned_tx.compute_txid();
+
+		let mut psbt = Psbt::from_unsigned_tx(unsigned_tx).map_err(|e| {
+			log_error!(self.logger, "Failed to create funding PSBT: {}", e);
+			Error::OnchainTxCreationFailed
+		})?;
+		for (psbt_input, utxo) in psbt.inputs.iter_mut().zip(selected_utxos) {
+			psbt_input.witness_utxo = Some(utxo.output);
+		}
+		log_trace!(self.logger, "Created funding PSBT: {:?}", psbt);
+
+		let reserved_tx = psbt.unsigned_tx.clone();
+		locked_reserved_utxos.extend(
+			reserved_tx.input.iter().map(|i| (i.previous_output, UtxoReservation::Funding)),
+		);
+		drop(locked_reserved_utxos);
+
+		let res = external_wallet
+			.sign_psbt(psbt)
+			.map_err(|e| {
+				log_error!(
+					self.logger,
+					"External wallet failed to sign funding transaction: {}",
+					e
+				);
+				e
+			})
+			.and_then(|signed_psbt| {
+				if signed_psbt.unsigned_tx.compute_txid() != unsigned_txid {
+					log_error!(self.logger, "External wallet modified the funding transaction");
+					return Err(Error::OnchainTxSigningFailed);
+				}
+				signed_psbt.extract_tx().map_err(|e| {
+					log_error!(self.logger, "Failed to extract transaction: {}", e);
+					e.into()
+				})
+			});
+		if res.is_err() {
+			self.release_external_utxos(&reserved_tx);
+		}
+		res
+	}
+
+	/// Selects confirmed UTXOs to splice `splice_amount_sats` into the channel with the given
+	/// funding output, covering the splice transaction fees at the given `fee_rate`.
+	pub(crate) async fn create_splice_in_contribution(
+		&self, claim_id: ClaimId, funding_txo: OutPoint, funding_output: TxOut,
+		splice_amount_sats: u64, fee_rate: FeeRate,
+	) -> Result<SpliceContribution, Error> {
+		if self.external_wallet.is_some() {
+			log_error!(self.logger, "Splicing is not supported when using an external wallet");
+			return Err(Error::OnchainTxCreationFailed);
+		}
+
+		let splice_amount = Amount::from_sat(splice_amount_sats);
+		let new_channel_value =
+			funding_output.value.checked_add(splice_amount).ok_or(Error::InvalidAmount)?;
+
+		// The splice transaction spends the current funding output and creates a new one. As the
+		// new funding script is not known yet, we approximate it by the current one, which has
+		// the same size.
+		let shared_input = Input {
+			outpoint: funding_txo,
+			previous_utxo: funding_output.clone(),
+			satisfaction_weight: WITNESS_SCALE_FACTOR as u64 /* empty script_sig */
+				+ FUNDING_TRANSACTION_WITNESS_WEIGHT,
+		};
+		let shared_output =
+			TxOut { value: new_channel_value, script_pubkey: funding_output.script_pubkey };
+
+		let coin_selection = LdkWallet::new(self, Arc::clone(&self.logger))
+			.select_confirmed_utxos(
+				claim_id,
+				vec![shared_input],
+				&[shared_output],
+				fee_rate.to_sat_per_kwu() as u32,
+			)
+			.await
+			.map_err(|()| {
+				log_error!(
+					self.logger,
+					"Failed to select UTXOs to splice in {}sats",
+					splice_amount_sats
+				);
+				Error::InsufficientFunds
+			})?;
+
+		let inputs = {
+			let locked_wallet = self.inner.lock().unwrap();
+			let mut inputs = Vec::with_capacity(coin_selection.confirmed_utxos.len());
+			for utxo in coin_selection.confirmed_utxos {
+				let prevtx = locked_wallet
+					.get_tx(utxo.outpoint.txid)
+					.map(|wallet_tx| wallet_tx.tx_node.tx.as_ref().clone())
+					.ok_or_else(|| {
+						log_error!(
+							self.logger,
+							"Failed to retrieve transaction spent by UTXO {}",
+							utxo.outpoint
+						);
+						Error::OnchainTxCreationFailed
+					})?;
+				let vout = utxo.outpoint.vout;
+				let input = if utxo.output.script_pubkey.is_p2wpkh() {
+					FundingTxInput::new_p2wpkh(prevtx, vout)
+				} else if utxo.output.script_pubkey.is_p2tr() {
+					FundingTxInput::new_p2tr_key_spend(prevtx, vout)
+				} else {
+					Err(())
+				};
+				inputs.push(input.map_err(|()| {
+					log_error!(
+						self.logger,
+						"Failed to create splice input for UTXO {}",
+						utxo.outpoint
+					);
+					Error::OnchainTxCreationFailed
+				})?);
+			}
+			inputs
+		};
+
+		let change_script = self.get_new_internal_address()?.script_pubkey();
+		Ok(SpliceContribution::SpliceIn {
+			value: splice_amount,
+			inputs,
+			change_script: Some(change_script),
+		})
+	}
+
+	/// Signs all inputs of the given interactively-constructed transaction that spend our UTXOs,
+	/// leaving any other inputs untouched.
+	pub(crate) fn sign_owned_inputs(&self, unsigned_tx: Transaction) -> Result<Transaction, ()> {
+		let locked_wallet = self.inner.lock().unwrap();
+
+		let mut psbt = Psbt::from_unsigned_tx(unsigned_tx).map_err(|e| {
+			log_error!(self.logger, "Failed to construct PSBT: {}", e);
+		})?;
+		for (txin, psbt_input) in psbt.unsigned_tx.input.iter().zip(psbt.inputs.iter_mut()) {
+			if let Some(utxo) = locked_wallet.get_utxo(txin.previous_output) {
+				psbt_input.witness_utxo = Some(utxo.txout);
+			}
+		}
+
+		let sign_options = SignOptions { trust_witness_utxo: true, ..Default::default() };
+		match locked_wallet.sign(&mut psbt, sign_options) {
+			Ok(_finalized) => {
+				// BDK will fail to finalize any inputs contributed by our counterparty, as well
+				// as the shared channel funding input, so we ignore the returned `finalized` bool.
+			},
+			Err(err) => {
+				log_error!(self.logger, "Failed to sign transaction: {}", err);
+				return Err(());
+			},
+		}
+
+		// We don't know the values of all spent outputs, so we can't sanity check the fee rate.
+		Ok(psbt.extract_tx_unchecked_fee_rate())
+	}
+
 	pub(crate) fn get_new_address(&self) -> Result<bitcoin::Address, Error> {
 		let mut locked_wallet = self.inner.lock().unwrap();
 		let mut locked_persister = self.persister.lock().unwrap();
@@ -285,7 +649,7 @@ impl Wallet {
 		Ok(address_info.address)
 	}
 
-	fn get_new_internal_address(&self) -> Result<bitcoin::Address, Error> {
+	pub(crate) fn get_new_internal_address(&self) -> Result<bitcoin::Address, Error> {
 		let mut locked_wallet = self.inner.lock().unwrap();
 		let mut locked_persister = self.persister.lock().unwrap();
 
@@ -297,6 +661,32 @@ impl Wallet {
 		Ok(address_info.address)
 	}
 
+	/// Returns a fresh address to which the funds of our closed channels are sent, i.e., an
+	/// address of the external wallet, if any.
+	pub(crate) fn get_new_destination_address(&self) -> Result<bitcoin::Address, Error> {
+		match self.external_wallet.as_ref() {
+			Some(external_wallet) => external_wallet.get_change_address(),
+			None => self.get_new_address(),
+		}
+	}
+
+	/// Returns the address to which any swept outputs are sent, i.e., the configured sweep
+	/// destination or a fresh address of the external wallet, if any.
+	pub(crate) fn get_new_change_address(&self) -> Result<bitcoin::Address, Error> {
+		if let Some(destination) = self.config.spendable_output_sweep_destination.as_ref() {
+			return Ok(destination.clone());
+		}
+
+		match self.external_wallet.as_ref() {
+			Some(external_wallet) => external_wallet.get_change_address(),
+			None => self.get_new_internal_address(),
+		}
+	}
+
+	pub(crate) fn is_mine(&self, script_pubkey: ScriptBuf) -> bool {
+		self.inner.lock().unwrap().is_mine(script_pubkey)
+	}
+
 	pub(crate) fn get_balances(
 		&self, total_anchor_channels_reserve_sats: u64,
 	) -> Result<(u64, u64), Error> {
@@ -326,13 +716,27 @@ impl Wallet {
 		Ok((total, spendable))
 	}
 
+	/// Returns the value of the confirmed UTXOs we may spend to bump the fees of our transactions,
+	/// i.e., excluding any UTXOs already spent by pending transactions of an external wallet.
+	pub(crate) fn get_fee_bump_spendable_sats(&self) -> u64 {
+		self.list_confirmed_utxos_inner()
+			.map_or(0, |utxos| utxos.iter().map(|u| u.output.value.to_sat()).sum())
+	}
+
+	pub(crate) fn get_spendable_anchor_reserve_sats(
+		&self, total_anchor_channels_reserve_sats: u64,
+	) -> u64 {
+		let balance = self.inner.lock().unwrap().balance();
+		std::cmp::min(total_anchor_channels_reserve_sats, balance.trusted_spendable().to_sat())
+	}
+
 	pub(crate) fn get_spendable_amount_sats(
 		&self, total_anchor_channels_reserve_sats: u64,
 	) -> Result<u64, Error> {
 		self.get_balances(total_anchor_channels_reserve_sats).map(|(_, s)| s)
 	}
 
-	fn parse_and_validate_address(
+	pub(crate) fn parse_and_validate_address(
 		&self, network: Network, address: &Address,
 	) -> Result<Address, Error> {
 		Address::<NetworkUnchecked>::from_str(address.to_string().as_str())
@@ -363,6 +767,7 @@ impl Wallet {
 					let mut tx_builder = locked_wallet.build_tx();
 					let amount = Amount::from_sat(amount_sats);
 					tx_builder.add_recipient(address.script_pubkey(), amount).fee_rate(fee_rate);
+					self.maybe_disable_rbf(&mut tx_builder);
 					tx_builder
 				},
 				OnchainSendAmount::AllRetainingReserve { cur_anchor_reserve_sats }
@@ -384,6 +789,7 @@ impl Wallet {
 								Amount::from_sat(cur_anchor_reserve_sats),
 							)
 							.fee_rate(fee_rate);
+						self.maybe_disable_rbf(&mut tmp_tx_builder);
 						match tmp_tx_builder.finish() {
 							Ok(psbt) => psbt.unsigned_tx,
 							Err(err) => {
@@ -426,12 +832,14 @@ impl Wallet {
 					tx_builder
 						.add_recipient(address.script_pubkey(), estimated_spendable_amount)
 						.fee_absolute(estimated_tx_fee);
+					self.maybe_disable_rbf(&mut tx_builder);
 					tx_builder
 				},
 				OnchainSendAmount::AllDrainingReserve
 				| OnchainSendAmount::AllRetainingReserve { cur_anchor_reserve_sats: _ } => {
 					let mut tx_builder = locked_wallet.build_tx();
 					tx_builder.drain_wallet().drain_to(address.script_pubkey()).fee_rate(fee_rate);
+					self.maybe_disable_rbf(&mut tx_builder);
 					tx_builder
 				},
 			};
@@ -559,7 +967,350 @@ impl Wallet {
 		Ok(txid)
 	}
 
+	pub(crate) fn send_to_many(
+		&self, outputs: Vec<(Address, u64)>, cur_anchor_reserve_sats: u64,
+		fee_rate: Option<FeeRate>,
+	) -> Result<Txid, Error> {
+		let tx = {
+			let mut locked_wallet = self.inner.lock().unwrap();
+			let (psbt, _) = self.create_psbt_paying_outputs(
+				&mut locked_wallet,
+				&outputs,
+				None,
+				cur_anchor_reserve_sats,
+				fee_rate,
+			)?;
+			self.sign_and_persist_psbt(&mut locked_wallet, psbt)?
+		};
+
+		self.broadcaster.broadcast_transactions(&[&tx]);
+
+		let txid = tx.compute_txid();
+		log_info!(
+			self.logger,
+			"Created new transaction {} sending {}sats on-chain to {} outputs",
+			txid,
+			outputs.iter().map(|(_, amount_sats)| amount_sats).sum::<u64>(),
+			outputs.len(),
+		);
+
+		Ok(txid)
+	}
+
+	pub(crate) fn estimate_send_to_many_fee(
+		&self, outputs: Vec<(Address, u64)>, cur_anchor_reserve_sats: u64,
+		fee_rate: Option<FeeRate>,
+	) -> Result<u64, Error> {
+		let mut locked_wallet = self.inner.lock().unwrap();
+		let (psbt, tx_fee_sats) = self.create_psbt_paying_outputs(
+			&mut locked_wallet,
+			&outputs,
+			None,
+			cur_anchor_reserve_sats,
+			fee_rate,
+		)?;
+
+		// 'cancel' the transaction to free up any used change addresses
+		locked_wallet.cancel_tx(&psbt.unsigned_tx);
+
+		Ok(tx_fee_sats)
+	}
+
+	pub(crate) fn send_to_address_with_utxos(
+		&self, address: &Address, amount_sats: u64, utxos: Vec<OutPoint>,
+		cur_anchor_reserve_sats: u64, fee_rate: Option<FeeRate>,
+	) -> Result<Txid, Error> {
+		let tx = {
+			let mut locked_wallet = self.inner.lock().unwrap();
+			let outputs = [(address.clone(), amount_sats)];
+			let (psbt, _) = self.create_psbt_paying_outputs(
+				&mut locked_wallet,
+				&outputs,
+				Some(&utxos),
+				cur_anchor_reserve_sats,
+				fee_rate,
+			)?;
+			self.sign_and_persist_psbt(&mut locked_wallet, psbt)?
+		};
+
+		self.broadcaster.broadcast_transactions(&[&tx]);
+
+		let txid = tx.compute_txid();
+		log_info!(
+			self.logger,
+			"Created new transaction {} sending {}sats on-chain to address {} spending {} selected UTXOs",
+			txid,
+			amount_sats,
+			address,
+			utxos.len(),
+		);
+
+		Ok(txid)
+	}
+
+	pub(crate) fn bump_fee(&self, txid: Txid, fee_rate: FeeRate) -> Result<Txid, Error> {
+		let (tx, original_fee_rate) = {
+			let mut locked_wallet = self.inner.lock().unwrap();
+
+			let original_tx = match locked_wallet.get_tx(txid) {
+				Some(wtx) if wtx.chain_position.is_confirmed() => {
+					log_error!(self.logger, "Unable to bump fee of confirmed transaction {}", txid);
+					return Err(Error::NotReplaceable);
+				},
+				Some(wtx) => Arc::clone(&wtx.tx_node.tx),
+				None => {
+					log_error!(self.logger, "Unable to bump fee of unknown transaction {}", txid);
+					return Err(Error::NotReplaceable);
+				},
+			};
+
+			if !original_tx.is_explicitly_rbf() {
+				log_error!(
+					self.logger,
+					"Unable to bump fee of transaction {} as it doesn't signal RBF",
+					txid
+				);
+				return Err(Error::NotReplaceable);
+			}
+
+			let original_fee_rate =
+				locked_wallet.calculate_fee_rate(&original_tx).map_err(|e| {
+					log_error!(
+						self.logger,
+						"Failed to calculate fee rate of transaction {}: {}",
+						txid,
+						e
+					);
+					e
+				})?;
+			if fee_rate <= original_fee_rate {
+				log_error!(
+					self.logger,
+					"Unable to bump fee of transaction {} as the new fee rate of {}sat/kwu doesn't exceed the original fee rate of {}sat/kwu",
+					txid,
+					fee_rate.to_sat_per_kwu(),
+					original_fee_rate.to_sat_per_kwu(),
+				);
+				return Err(Error::InvalidFeeRate);
+			}
+
+			let mut tx_builder = locked_wallet.build_fee_bump(txid).map_err(|e| {
+				log_error!(self.logger, "Failed to create fee bump of transaction {}: {}", txid, e);
+				match e {
+					BuildFeeBumpError::UnknownUtxo(_)
+					| BuildFeeBumpError::TransactionNotFound(_)
+					| BuildFeeBumpError::TransactionConfirmed(_)
+					| BuildFeeBumpError::IrreplaceableTransaction(_) => Error::NotReplaceable,
+					_ => Error::OnchainTxCreationFailed,
+				}
+			})?;
+			tx_builder.fee_rate(fee_rate);
+
+			let psbt = match tx_builder.finish() {
+				Ok(psbt) => {
+					log_trace!(self.logger, "Created fee bump PSBT: {:?}", psbt);
+					psbt
+				},
+				Err(err) => {
+					log_error!(self.logger, "Failed to create fee bump transaction: {}", err);
+					return Err(err.into());
+				},
+			};
+
+			(self.sign_and_persist_psbt(&mut locked_wallet, psbt)?, original_fee_rate)
+		};
+
+		self.broadcaster.broadcast_transactions(&[&tx]);
+
+		// The original transaction will be dropped from our wallet once the replacement is seen, so
+		// we mark it as failed here. If it ends up confirming after all, the next wallet sync will
+		// update its status accordingly.
+		let mut update = PaymentDetailsUpdate::new(PaymentId(txid.to_byte_array()));
+		update.status = Some(PaymentStatus::Failed);
+		self.payment_store.update(&update)?;
+
+		let new_txid = tx.compute_txid();
+		log_info!(
+			self.logger,
+			"Created transaction {} replacing transaction {}, bumping the fee rate from {}sat/kwu to {}sat/kwu",
+			new_txid,
+			txid,
+			original_fee_rate.to_sat_per_kwu(),
+			fee_rate.to_sat_per_kwu(),
+		);
+
+		Ok(new_txid)
+	}
+
+	pub(crate) fn list_spendable_utxos(&self, cur_anchor_reserve_sats: u64) -> Vec<SpendableUtxo> {
+		let locked_wallet = self.inner.lock().unwrap();
+		let tip_height = locked_wallet.latest_checkpoint().height();
+
+		let mut utxos = locked_wallet
+			.list_unspent()
+			.filter_map(|utxo| {
+				let confirmations = match utxo.chain_position {
+					bdk_chain::ChainPosition::Confirmed { anchor, .. } => {
+						tip_height.saturating_sub(anchor.block_id.height) + 1
+					},
+					// Only unconfirmed change outputs of our own transactions are spendable.
+					bdk_chain::ChainPosition::Unconfirmed { .. } => {
+						if utxo.keychain != KeychainKind::Internal {
+							return None;
+						}
+						0
+					},
+				};
+				Some(SpendableUtxo {
+					outpoint: utxo.outpoint,
+					value_sats: utxo.txout.value.to_sat(),
+					confirmations,
+					is_reserved_for_anchors: false,
+				})
+			})
+			.collect::<Vec<_>>();
+
+		// We consider the smallest outputs to hold the reserve, as far as they're needed in full.
+		// Any output exceeding what remains of the reserve may still be spent, as the change will
+		// cover the remainder.
+		utxos.sort_by_key(|u| u.value_sats);
+		let mut remaining_reserve_sats = cur_anchor_reserve_sats;
+		for utxo in utxos.iter_mut() {
+			if remaining_reserve_sats == 0 || utxo.value_sats > remaining_reserve_sats {
+				break;
+			}
+			utxo.is_reserved_for_anchors = true;
+			remaining_reserve_sats -= utxo.value_sats;
+		}
+		utxos
+	}
+
+	// Creates an unsigned transaction paying to all given outputs, returning it alongside its fee.
+	//
+	// If `utxos` are given, only these will be spent. Fails if the outputs and the fee can't be
+	// covered without cutting into the given reserve.
+	fn create_psbt_paying_outputs(
+		&self, locked_wallet: &mut PersistedWallet<KVStoreWalletPersister>,
+		outputs: &[(Address, u64)], utxos: Option<&[OutPoint]>, cur_anchor_reserve_sats: u64,
+		fee_rate: Option<FeeRate>,
+	) -> Result<(Psbt, u64), Error> {
+		if outputs.is_empty() {
+			log_error!(self.logger, "Unable to send payment without any outputs.");
+			return Err(Error::InvalidAmount);
+		}
+
+		for (address, _) in outputs {
+			self.parse_and_validate_address(self.config.network, address)?;
+		}
+
+		// Use the set fee_rate or default to fee estimation.
+		let confirmation_target = ConfirmationTarget::OnchainPayment;
+		let fee_rate =
+			fee_rate.unwrap_or_else(|| self.fee_estimator.estimate_fee_rate(confirmation_target));
+
+		let mut tx_builder = locked_wallet.build_tx();
+		for (address, amount_sats) in outputs {
+			tx_builder.add_recipient(address.script_pubkey(), Amount::from_sat(*amount_sats));
+		}
+		tx_builder.fee_rate(fee_rate);
+		self.maybe_disable_rbf(&mut tx_builder);
+
+		if let Some(utxos) = utxos {
+			tx_builder.add_utxos(utxos).map_err(|e| {
+				log_error!(self.logger, "Failed to select UTXOs to spend: {}", e);
+				Error::OnchainTxCreationFailed
+			})?;
+			tx_builder.manually_selected_only();
+		}
+
+		let psbt = match tx_builder.finish() {
+			Ok(psbt) => {
+				log_trace!(self.logger, "Created PSBT: {:?}", psbt);
+				psbt
+			},
+			Err(err) => {
+				log_error!(self.logger, "Failed to create transaction: {}", err);
+				return Err(err.into());
+			},
+		};
+
+		// Check the reserve requirements and return an error if they aren't met.
+		let balance = locked_wallet.balance();
+		let spendable_amount_sats =
+			self.get_balances_inner(balance, cur_anchor_reserve_sats).map(|(_, s)| s).unwrap_or(0);
+		let tx_fee_sats = match locked_wallet.calculate_fee(&psbt.unsigned_tx) {
+			Ok(tx_fee) => tx_fee.to_sat(),
+			Err(e) => {
+				log_error!(self.logger, "Failed to calculate fee of candidate transaction: {}", e);
+				locked_wallet.cancel_tx(&psbt.unsigned_tx);
+				return Err(e.into());
+			},
+		};
+		let total_amount_sats =
+			outputs.iter().fold(0u64, |acc, (_, amount_sats)| acc.saturating_add(*amount_sats));
+		if spendable_amount_sats < total_amount_sats.saturating_add(tx_fee_sats) {
+			log_error!(self.logger,
+				"Unable to send payment due to insufficient funds. Available: {}sats, Required: {}sats + {}sats fee",
+				spendable_amount_sats,
+				total_amount_sats,
+				tx_fee_sats,
+			);
+			locked_wallet.cancel_tx(&psbt.unsigned_tx);
+			return Err(Error::InsufficientFunds);
+		}
+
+		Ok((psbt, tx_fee_sats))
+	}
+
+	// Opts the transaction out of signalling RBF, unless we're configured to do so by default.
+	fn maybe_disable_rbf<Cs>(&self, tx_builder: &mut TxBuilder<'_, Cs>) {
+		if !self.config.enable_rbf_by_default {
+			tx_builder.set_exact_sequence(Sequence::ENABLE_LOCKTIME_NO_RBF);
+		}
+	}
+
+	fn sign_and_persist_psbt(
+		&self, locked_wallet: &mut PersistedWallet<KVStoreWalletPersister>, mut psbt: Psbt,
+	) -> Result<Transaction, Error> {
+		match locked_wallet.sign(&mut psbt, SignOptions::default()) {
+			Ok(finalized) => {
+				if !finalized {
+					return Err(Error::OnchainTxCreationFailed);
+				}
+			},
+			Err(err) => {
+				log_error!(self.logger, "Failed to create transaction: {}", err);
+				return Err(err.into());
+			},
+		}
+
+		let mut locked_persister = self.persister.lock().unwrap();
+		locked_wallet.persist(&mut locked_persister).map_err(|e| {
+			log_error!(self.logger, "Failed to persist wallet: {}", e);
+			Error::PersistenceFailed
+		})?;
+
+		let tx = psbt.extract_tx().map_err(|e| {
+			log_error!(self.logger, "Failed to extract transaction: {}", e);
+			e
+		})?;
+		Ok(tx)
+	}
+
 	fn list_confirmed_utxos_inner(&self) -> Result<Vec<Utxo>, ()> {
+		if let Some(external_wallet) = self.external_wallet.as_ref() {
+			let mut locked_reserved_utxos = self.reserved_external_utxos.lock().unwrap();
+			return self
+				.unreserved_external_utxos(
+					external_wallet.as_ref(),
+					&mut locked_reserved_utxos,
+					UtxoReservation::FeeBump,
+				)
+				.map_err(|e| {
+					log_error!(self.logger, "Failed to list UTXOs of external wallet: {}", e);
+				});
+		}
+
 		let locked_wallet = self.inner.lock().unwrap();
 		let mut utxos = Vec::new();
 		let confirmed_txs: Vec<Txid> = locked_wallet
@@ -653,6 +1404,18 @@ impl Wallet {
 
 	#[allow(deprecated)]
 	fn get_change_script_inner(&self) -> Result<ScriptBuf, ()> {
+		if let Some(external_wallet) = self.external_wallet.as_ref() {
+			return external_wallet.get_change_address().map(|addr| addr.script_pubkey()).map_err(
+				|e| {
+					log_error!(
+						self.logger,
+						"Failed to retrieve address from external wallet: {}",
+						e
+					);
+				},
+			);
+		}
+
 		let mut locked_wallet = self.inner.lock().unwrap();
 		let mut locked_persister = self.persister.lock().unwrap();
 
@@ -666,6 +1429,25 @@ impl Wallet {
 
 	#[allow(deprecated)]
 	fn sign_psbt_inner(&self, mut psbt: Psbt) -> Result<Transaction, ()> {
+		if let Some(external_wallet) = self.external_wallet.as_ref() {
+			let unsigned_txid = psbt.unsigned_tx.compute_txid();
+			let signed_psbt = external_wallet.sign_psbt(psbt).map_err(|e| {
+				log_error!(self.logger, "External wallet failed to sign transaction: {}", e);
+			})?;
+			if signed_psbt.unsigned_tx.compute_txid() != unsigned_txid {
+				log_error!(self.logger, "External wallet modified the transaction to sign");
+				return Err(());
+			}
+			let tx = signed_psbt.extract_tx().map_err(|e| {
+				log_error!(self.logger, "Failed to extract transaction: {}", e);
+			})?;
+			// Reserve the spent UTXOs so that concurrent fundings won't select them.
+			let mut locked_reserved_utxos = self.reserved_external_utxos.lock().unwrap();
+			locked_reserved_utxos
+				.extend(tx.input.iter().map(|i| (i.previous_output, UtxoReservation::FeeBump)));
+			return Ok(tx);
+		}
+
 		let locked_wallet = self.inner.lock().unwrap();
 
 		// While BDK populates both `witness_utxo` and `non_witness_utxo` fields, LDK does not. As
@@ -776,7 +1558,7 @@ impl WalletSource for Wallet {
 }
 
 /// Similar to [`KeysManager`], but overrides the destination and shutdown scripts so they are
-/// directly spendable by the BDK wallet.
+/// directly spendable by the BDK wallet, or the external on-chain wallet, if any.
 pub(crate) struct WalletKeysManager {
 	inner: KeysManager,
 	wallet: Arc<Wallet>,
@@ -888,14 +1670,14 @@ impl SignerProvider for WalletKeysManager {
 	}
 
 	fn get_destination_script(&self, _channel_keys_id: [u8; 32]) -> Result<ScriptBuf, ()> {
-		let address = self.wallet.get_new_address().map_err(|e| {
+		let address = self.wallet.get_new_destination_address().map_err(|e| {
 			log_error!(self.logger, "Failed to retrieve new address from wallet: {}", e);
 		})?;
 		Ok(address.script_pubkey())
 	}
 
 	fn get_shutdown_scriptpubkey(&self) -> Result<ShutdownScript, ()> {
-		let address = self.wallet.get_new_address().map_err(|e| {
+		let address = self.wallet.get_new_destination_address().map_err(|e| {
 			log_error!(self.logger, "Failed to retrieve new address from wallet: {}", e);
 		})?;
 
@@ -903,6 +1685,10 @@ impl SignerProvider for WalletKeysManager {
 			Some(program) => ShutdownScript::new_witness_program(&program).map_err(|e| {
 				log_error!(self.logger, "Invalid shutdown script: {:?}", e);
 			}),
+			_ if self.wallet.has_external_wallet() => {
+				log_error!(self.logger, "External wallet returned a non-witness address");
+				Err(())
+			},
 			_ => {
 				log_error!(
 					self.logger,
@@ -922,7 +1708,7 @@ impl ChangeDestinationSource for WalletKeysManager {
 		let logger = Arc::clone(&self.logger);
 		Box::pin(async move {
 			wallet
-				.get_new_internal_address()
+				.get_new_change_address()
 				.map_err(|e| {
 					log_error!(logger, "Failed to retrieve new address from wallet: {}", e);
 				})
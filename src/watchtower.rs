@@ -0,0 +1,957 @@
+// This file is Copyright its original authors, visible in version control history.
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. You may not use this file except in
+// accordance with one or both of these licenses.
+
+//! Objects related to backing up our channel states to a remote watchtower.
+//!
+//! We speak the HTTP API of [The Eye of Satoshi] (TEOS), which implements the appointment format
+//! proposed for BOLT 13. Before handing the tower any channel states, we register with it,
+//! which gets us a subscription with a number of available appointment slots.
+//!
+//! Whenever our counterparty revokes one of their commitment transactions, we sign a justice
+//! transaction claiming the commitment's `to_local` output and hand it to the tower as an
+//! *appointment*, consisting of:
+//! - a `locator`, i.e., the first 16 bytes of the revoked commitment transaction's txid, allowing
+//!   the tower to recognize the breach once the commitment transaction hits the chain.
+//! - an `encrypted_blob`, i.e., the justice transaction encrypted via ChaCha20-Poly1305 under the
+//!   SHA256 hash of the revoked commitment transaction's txid and an all-zero nonce. This way, the
+//!   tower learns nothing about our channels unless our counterparty broadcasts a revoked state.
+//! - the `to_self_delay` of the revoked commitment's `to_local` output.
+//!
+//! Appointments are signed with our node key. The tower acknowledges them by returning a signed
+//! receipt, which we verify against the configured tower public key. Appointments that could not
+//! be delivered, e.g., as the tower was unavailable, are persisted and retried later on.
+//!
+//! As the tower can't bump the fee of the justice transactions, we sign them at the maximum
+//! feerate we currently estimate. Note that HTLC outputs of revoked commitment transactions are
+//! not covered, as LDK doesn't allow us to pre-sign the respective justice transactions.
+//!
+//! [The Eye of Satoshi]: https://github.com/talaia-labs/rust-teos
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::Cursor;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use bitcoin::hashes::sha256::Hash as Sha256;
+use bitcoin::hashes::Hash;
+use bitcoin::script::Instruction;
+use bitcoin::secp256k1::PublicKey;
+use bitcoin::{ScriptBuf, Transaction};
+use lightning::chain::chaininterface::{
+	ConfirmationTarget as LdkConfirmationTarget, FeeEstimator as LdkFeeEstimator,
+};
+use lightning::chain::chainmonitor::Persist;
+use lightning::chain::channelmonitor::{ChannelMonitor, ChannelMonitorUpdate};
+use lightning::chain::ChannelMonitorUpdateStatus;
+use lightning::impl_writeable_tlv_based;
+use lightning::ln::chan_utils::CommitmentTransaction;
+use lightning::ln::types::ChannelId;
+use lightning::sign::{InMemorySigner, NodeSigner, Recipient, SignerProvider};
+use lightning::util::message_signing;
+use lightning::util::persist::{KVStoreSync, MonitorName};
+use lightning::util::ser::{Readable, Writeable};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN};
+use serde::{Deserialize, Serialize};
+
+use crate::config::WATCHTOWER_REQUEST_TIMEOUT_SECS;
+use crate::fee_estimator::OnchainFeeEstimator;
+use crate::hex_utils;
+use crate::io::{
+	WATCHTOWER_APPOINTMENT_PERSISTENCE_PRIMARY_NAMESPACE,
+	WATCHTOWER_APPOINTMENT_PERSISTENCE_SECONDARY_NAMESPACE, WATCHTOWER_CLIENT_PERSISTENCE_KEY,
+	WATCHTOWER_CLIENT_PERSISTENCE_PRIMARY_NAMESPACE,
+	WATCHTOWER_CLIENT_PERSISTENCE_SECONDARY_NAMESPACE,
+};
+use crate::logger::{log_debug, log_error, log_info, LdkLogger, Logger};
+use crate::types::{DynStore, KeysManager, Persister};
+
+/// The status of our watchtower client, as retrieved via [`Node::watchtower_status`].
+///
+/// [`Node::watchtower_status`]: crate::Node::watchtower_status
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchtowerStatus {
+	/// The URI of the tower we back up our channel states to.
+	pub tower_uri: String,
+	/// The public key of the tower.
+	pub tower_pubkey: PublicKey,
+	/// The backup status of the individual channels.
+	pub channels: Vec<ChannelWatchtowerStatus>,
+	/// The timestamp, in seconds since start of the UNIX epoch, when we last successfully
+	/// delivered a channel state to the tower.
+	///
+	/// Will be `None` if we never did.
+	pub latest_sync_timestamp: Option<u64>,
+}
+
+/// The watchtower backup status of a single channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelWatchtowerStatus {
+	/// The channel's ID.
+	pub channel_id: ChannelId,
+	/// The number of revoked channel states that were delivered to the tower.
+	pub backed_up_states: u64,
+	/// The number of revoked channel states that are queued for delivery to the tower.
+	pub pending_states: u64,
+}
+
+/// The data we need to sign a justice transaction once the respective counterparty commitment
+/// transaction is revoked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct UnsignedJusticeTx {
+	channel_id: ChannelId,
+	justice_tx: Transaction,
+	value_sat: u64,
+	commitment_number: u64,
+}
+
+impl_writeable_tlv_based!(UnsignedJusticeTx, {
+	(0, channel_id, required),
+	(2, justice_tx, required),
+	(4, value_sat, required),
+	(6, commitment_number, required),
+});
+
+/// An appointment queued for delivery to the tower.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Appointment {
+	channel_id: ChannelId,
+	locator: [u8; 16],
+	encrypted_blob: Vec<u8>,
+	to_self_delay: u32,
+}
+
+impl_writeable_tlv_based!(Appointment, {
+	(0, channel_id, required),
+	(2, locator, required),
+	(4, encrypted_blob, required),
+	(6, to_self_delay, required),
+});
+
+impl Appointment {
+	/// Creates an appointment for the given signed justice transaction.
+	///
+	/// Returns `None` if the justice transaction isn't spending a revokeable output.
+	fn new(channel_id: ChannelId, justice_tx: &Transaction) -> Option<Self> {
+		let input = justice_tx.input.first()?;
+		let to_self_delay = to_self_delay_from_witness_script(input.witness.last()?)?;
+
+		let commitment_txid = input.previous_output.txid.to_byte_array();
+		let mut locator = [0u8; 16];
+		locator.copy_from_slice(&commitment_txid[..16]);
+
+		// As the encryption key is unique per commitment transaction, using a fixed nonce is safe.
+		let encryption_key = Sha256::hash(&commitment_txid).to_byte_array();
+		let unbound_key = UnboundKey::new(&CHACHA20_POLY1305, &encryption_key).ok()?;
+		let mut encrypted_blob = bitcoin::consensus::serialize(justice_tx);
+		LessSafeKey::new(unbound_key)
+			.seal_in_place_append_tag(
+				Nonce::assume_unique_for_key([0u8; NONCE_LEN]),
+				Aad::empty(),
+				&mut encrypted_blob,
+			)
+			.ok()?;
+		Some(Self { channel_id, locator, encrypted_blob, to_self_delay })
+	}
+
+	/// The data we sign to commit to the appointment.
+	fn signed_data(&self) -> Vec<u8> {
+		let mut data = self.locator.to_vec();
+		data.extend_from_slice(&self.encrypted_blob);
+		data.extend_from_slice(&self.to_self_delay.to_be_bytes());
+		data
+	}
+
+	fn persistence_key(&self) -> String {
+		hex_utils::to_string(&self.locator)
+	}
+}
+
+/// Extracts the CSV delay from the witness script of a revokeable output, i.e., a script of the
+/// form `OP_IF <revocation_key> OP_ELSE <delay> OP_CSV OP_DROP <delayed_key> OP_ENDIF OP_CHECKSIG`.
+fn to_self_delay_from_witness_script(witness_script: &[u8]) -> Option<u32> {
+	use bitcoin::opcodes::all::{OP_CSV, OP_ELSE, OP_IF};
+
+	let script = bitcoin::Script::from_bytes(witness_script);
+	let instructions = script.instructions().collect::<Result<Vec<_>, _>>().ok()?;
+	if instructions.len() != 9
+		|| instructions[0] != Instruction::Op(OP_IF)
+		|| instructions[2] != Instruction::Op(OP_ELSE)
+		|| instructions[4] != Instruction::Op(OP_CSV)
+	{
+		return None;
+	}
+	let delay = match instructions[3] {
+		Instruction::PushBytes(bytes) => bitcoin::script::read_scriptint(bytes.as_bytes()).ok()?,
+		Instruction::Op(op) => {
+			let delay = op.to_u8().checked_sub(bitcoin::opcodes::OP_TRUE.to_u8())? + 1;
+			if delay > 16 {
+				return None;
+			}
+			delay as i64
+		},
+	};
+	u32::try_from(delay).ok().filter(|delay| *delay > 0)
+}
+
+/// Our subscription with the tower.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TowerSubscription {
+	available_slots: u32,
+	subscription_expiry: u32,
+}
+
+impl_writeable_tlv_based!(TowerSubscription, {
+	(0, available_slots, required),
+	(2, subscription_expiry, required),
+});
+
+/// The backup state of a single channel.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct ChannelBackupState {
+	// The name under which the channel's monitor is persisted, allowing us to forget about the
+	// channel once its monitor is archived.
+	monitor_name: String,
+	backed_up_states: u64,
+	// The lowest counterparty commitment number we processed. As commitment numbers count down,
+	// this allows us to skip commitment transactions we already processed, e.g., when monitors are
+	// re-registered on restart.
+	lowest_commitment_number: Option<u64>,
+}
+
+impl_writeable_tlv_based!(ChannelBackupState, {
+	(0, monitor_name, required),
+	(2, backed_up_states, required),
+	(4, lowest_commitment_number, option),
+});
+
+/// The state of our watchtower client.
+///
+/// Pending appointments are persisted individually, so that the size of this state only grows
+/// with the number of open channels.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct WatchtowerClientState {
+	destination_script: Option<ScriptBuf>,
+	subscription: Option<TowerSubscription>,
+	unsigned_justice_txs: Vec<UnsignedJusticeTx>,
+	channels: BTreeMap<ChannelId, ChannelBackupState>,
+	latest_sync_timestamp: Option<u64>,
+}
+
+impl_writeable_tlv_based!(WatchtowerClientState, {
+	(0, destination_script, option),
+	(2, subscription, option),
+	(4, unsigned_justice_txs, required_vec),
+	(6, channels, required),
+	(8, latest_sync_timestamp, option),
+});
+
+#[derive(Serialize)]
+struct RegisterRequest {
+	user_id: String,
+}
+
+#[derive(Deserialize)]
+struct RegisterResponse {
+	available_slots: u32,
+	subscription_start: u32,
+	subscription_expiry: u32,
+	subscription_signature: String,
+}
+
+#[derive(Serialize)]
+struct AppointmentData {
+	locator: String,
+	encrypted_blob: String,
+	to_self_delay: u32,
+}
+
+#[derive(Serialize)]
+struct AddAppointmentRequest {
+	appointment: AppointmentData,
+	signature: String,
+}
+
+#[derive(Deserialize)]
+struct AddAppointmentResponse {
+	start_block: u32,
+	signature: String,
+	available_slots: u32,
+	subscription_expiry: u32,
+}
+
+/// The data the tower signs to acknowledge our registration.
+fn registration_receipt_data(
+	user_id: &PublicKey, available_slots: u32, subscription_start: u32, subscription_expiry: u32,
+) -> Vec<u8> {
+	let mut data = user_id.serialize().to_vec();
+	data.extend_from_slice(&available_slots.to_be_bytes());
+	data.extend_from_slice(&subscription_start.to_be_bytes());
+	data.extend_from_slice(&subscription_expiry.to_be_bytes());
+	data
+}
+
+/// The data the tower signs to acknowledge an appointment.
+fn appointment_receipt_data(user_signature: &str, start_block: u32) -> Vec<u8> {
+	let mut data = user_signature.as_bytes().to_vec();
+	data.extend_from_slice(&start_block.to_be_bytes());
+	data
+}
+
+/// A client backing up our revoked channel states to a remote watchtower.
+pub(crate) struct WatchtowerClient {
+	tower_uri: String,
+	user_id: PublicKey,
+	tower_pubkey: PublicKey,
+	state: Mutex<WatchtowerClientState>,
+	pending_appointments: Mutex<Vec<Appointment>>,
+	http_client: reqwest::Client,
+	keys_manager: Arc<KeysManager>,
+	fee_estimator: Arc<OnchainFeeEstimator>,
+	kv_store: Arc<DynStore>,
+	logger: Arc<Logger>,
+}
+
+impl WatchtowerClient {
+	pub(crate) fn new(
+		tower_uri: String, tower_pubkey: PublicKey, keys_manager: Arc<KeysManager>,
+		fee_estimator: Arc<OnchainFeeEstimator>, kv_store: Arc<DynStore>, logger: Arc<Logger>,
+	) -> Result<Self, std::io::Error> {
+		let mut state = match KVStoreSync::read(
+			&*kv_store,
+			WATCHTOWER_CLIENT_PERSISTENCE_PRIMARY_NAMESPACE,
+			WATCHTOWER_CLIENT_PERSISTENCE_SECONDARY_NAMESPACE,
+			WATCHTOWER_CLIENT_PERSISTENCE_KEY,
+		) {
+			Ok(data) => WatchtowerClientState::read(&mut Cursor::new(data)).map_err(|e| {
+				log_error!(logger, "Failed to deserialize watchtower client state: {}", e);
+				std::io::Error::new(
+					std::io::ErrorKind::InvalidData,
+					"Failed to deserialize watchtower client state",
+				)
+			})?,
+			Err(e) if e.kind() == lightning::io::ErrorKind::NotFound => {
+				WatchtowerClientState::default()
+			},
+			Err(e) => return Err(e.into()),
+		};
+
+		let mut pending_appointments = Vec::new();
+		for key in KVStoreSync::list(
+			&*kv_store,
+			WATCHTOWER_APPOINTMENT_PERSISTENCE_PRIMARY_NAMESPACE,
+			WATCHTOWER_APPOINTMENT_PERSISTENCE_SECONDARY_NAMESPACE,
+		)? {
+			let data = KVStoreSync::read(
+				&*kv_store,
+				WATCHTOWER_APPOINTMENT_PERSISTENCE_PRIMARY_NAMESPACE,
+				WATCHTOWER_APPOINTMENT_PERSISTENCE_SECONDARY_NAMESPACE,
+				&key,
+			)?;
+			let appointment = Appointment::read(&mut Cursor::new(data)).map_err(|e| {
+				log_error!(logger, "Failed to deserialize watchtower appointment: {}", e);
+				std::io::Error::new(
+					std::io::ErrorKind::InvalidData,
+					"Failed to deserialize watchtower appointment",
+				)
+			})?;
+			pending_appointments.push(appointment);
+		}
+
+		if state.destination_script.is_none() {
+			// We sweep the funds claimed by the tower to a fixed address of our on-chain wallet.
+			let destination_script =
+				keys_manager.get_destination_script([0u8; 32]).map_err(|()| {
+					std::io::Error::new(
+						std::io::ErrorKind::Other,
+						"Failed to retrieve destination script for justice transactions",
+					)
+				})?;
+			state.destination_script = Some(destination_script);
+		}
+
+		let http_client = reqwest::Client::builder()
+			.timeout(Duration::from_secs(WATCHTOWER_REQUEST_TIMEOUT_SECS))
+			.build()
+			.map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+
+		let user_id = keys_manager.get_node_id(Recipient::Node).map_err(|()| {
+			std::io::Error::new(std::io::ErrorKind::Other, "Failed to retrieve our node id")
+		})?;
+
+		let client = Self {
+			tower_uri,
+			user_id,
+			tower_pubkey,
+			state: Mutex::new(state),
+			pending_appointments: Mutex::new(pending_appointments),
+			http_client,
+			keys_manager,
+			fee_estimator,
+			kv_store,
+			logger,
+		};
+		client.persist_state(&client.state.lock().unwrap())?;
+		Ok(client)
+	}
+
+	/// Tracks the given new counterparty commitment transactions and queues appointments for all
+	/// commitment transactions that have been revoked in the meantime.
+	fn process_commitment_txs(
+		&self, monitor: &ChannelMonitor<InMemorySigner>, commitment_txs: Vec<CommitmentTransaction>,
+	) {
+		let channel_id = monitor.channel_id();
+		let mut locked_state = self.state.lock().unwrap();
+		let destination_script = match locked_state.destination_script.clone() {
+			Some(script) => script,
+			None => return,
+		};
+
+		// As the tower can't bump the justice transactions' fees, we use a generous feerate.
+		let feerate_per_kw =
+			self.fee_estimator
+				.get_est_sat_per_1000_weight(LdkConfirmationTarget::MaximumFeeEstimate) as u64;
+		let channel_state = locked_state.channels.entry(channel_id).or_default();
+		channel_state.monitor_name = monitor.persistence_key().to_string();
+		let prev_lowest_commitment_number = channel_state.lowest_commitment_number;
+
+		let mut new_unsigned_justice_txs = Vec::new();
+		for commitment_tx in commitment_txs {
+			let commitment_number = commitment_tx.commitment_number();
+			if prev_lowest_commitment_number.map_or(false, |lowest| commitment_number >= lowest) {
+				continue;
+			}
+			channel_state.lowest_commitment_number = Some(
+				channel_state
+					.lowest_commitment_number
+					.map_or(commitment_number, |lowest| lowest.min(commitment_number)),
+			);
+
+			let trusted_tx = commitment_tx.trust();
+			let output_idx = match trusted_tx.revokeable_output_index() {
+				Some(idx) => idx,
+				// There's nothing for the tower to claim if our counterparty's balance is dust.
+				None => continue,
+			};
+			let value_sat = trusted_tx.built_transaction().transaction.output[output_idx].value;
+			match trusted_tx.build_to_local_justice_tx(feerate_per_kw, destination_script.clone()) {
+				Ok(justice_tx) => new_unsigned_justice_txs.push(UnsignedJusticeTx {
+					channel_id,
+					justice_tx,
+					value_sat: value_sat.to_sat(),
+					commitment_number,
+				}),
+				Err(()) => {
+					log_debug!(
+						self.logger,
+						"Skipping backup of commitment transaction of channel {} as its to_local output can't cover the justice transaction fee",
+						channel_id
+					);
+				},
+			}
+		}
+		locked_state.unsigned_justice_txs.extend(new_unsigned_justice_txs);
+
+		// Counterparty commitment transactions are revoked in order, so we try to sign the justice
+		// transactions in order and stop at the first one that is not revoked yet.
+		let mut new_appointments = Vec::new();
+		while let Some(pos) =
+			locked_state.unsigned_justice_txs.iter().position(|u| u.channel_id == channel_id)
+		{
+			let unsigned = &locked_state.unsigned_justice_txs[pos];
+			let signed_justice_tx = match monitor.sign_to_local_justice_tx(
+				unsigned.justice_tx.clone(),
+				0,
+				unsigned.value_sat,
+				unsigned.commitment_number,
+			) {
+				Ok(signed_justice_tx) => signed_justice_tx,
+				Err(()) => break,
+			};
+			locked_state.unsigned_justice_txs.remove(pos);
+			match Appointment::new(channel_id, &signed_justice_tx) {
+				Some(appointment) => new_appointments.push(appointment),
+				None => {
+					log_error!(
+						self.logger,
+						"Failed to create watchtower appointment for channel {}",
+						channel_id
+					);
+				},
+			}
+		}
+
+		if !new_appointments.is_empty() {
+			log_debug!(
+				self.logger,
+				"Queued {} channel state(s) of channel {} for delivery to watchtower",
+				new_appointments.len(),
+				channel_id
+			);
+		}
+
+		// We persist the appointments before dropping the unsigned justice transactions they were
+		// created from.
+		let mut locked_appointments = self.pending_appointments.lock().unwrap();
+		for appointment in new_appointments {
+			if let Err(e) = self.persist_appointment(&appointment) {
+				log_error!(self.logger, "Failed to persist watchtower appointment: {}", e);
+			}
+			locked_appointments.push(appointment);
+		}
+
+		if let Err(e) = self.persist_state(&locked_state) {
+			log_error!(self.logger, "Failed to persist watchtower client state: {}", e);
+		}
+	}
+
+	/// Forgets about the channel whose monitor is persisted under the given name, as its revoked
+	/// states can't be broadcast anymore once the monitor is archived.
+	fn forget_channel(&self, monitor_name: &MonitorName) {
+		let monitor_name = monitor_name.to_string();
+		let mut locked_state = self.state.lock().unwrap();
+		let channel_id = match locked_state
+			.channels
+			.iter()
+			.find(|(_, channel_state)| channel_state.monitor_name == monitor_name)
+		{
+			Some((channel_id, _)) => *channel_id,
+			None => return,
+		};
+
+		locked_state.channels.remove(&channel_id);
+		locked_state.unsigned_justice_txs.retain(|u| u.channel_id != channel_id);
+		self.pending_appointments.lock().unwrap().retain(|appointment| {
+			if appointment.channel_id != channel_id {
+				return true;
+			}
+			if let Err(e) = self.remove_appointment(appointment) {
+				log_error!(self.logger, "Failed to remove watchtower appointment: {}", e);
+			}
+			false
+		});
+
+		if let Err(e) = self.persist_state(&locked_state) {
+			log_error!(self.logger, "Failed to persist watchtower client state: {}", e);
+		}
+	}
+
+	/// Delivers all queued appointments to the tower, registering with it first if necessary.
+	///
+	/// We stop at the first failure and retry delivering the remaining appointments on the next
+	/// call.
+	pub(crate) async fn sync(&self, current_height: u32) {
+		let pending_appointments = self.pending_appointments.lock().unwrap().clone();
+		if pending_appointments.is_empty() {
+			return;
+		}
+
+		let mut num_delivered = 0;
+		for appointment in pending_appointments {
+			let subscription = self.state.lock().unwrap().subscription;
+			let needs_registration = subscription.map_or(true, |s| {
+				s.available_slots == 0 || s.subscription_expiry <= current_height
+			});
+			if needs_registration {
+				if let Err(e) = self.register().await {
+					log_error!(
+						self.logger,
+						"Failed to register with watchtower {}: {}",
+						self.tower_uri,
+						e
+					);
+					break;
+				}
+			}
+
+			if let Err(e) = self.send_appointment(&appointment).await {
+				log_error!(
+					self.logger,
+					"Failed to deliver channel state of channel {} to watchtower {}: {}",
+					appointment.channel_id,
+					self.tower_uri,
+					e
+				);
+				break;
+			}
+
+			self.pending_appointments.lock().unwrap().retain(|a| a != &appointment);
+			if let Err(e) = self.remove_appointment(&appointment) {
+				log_error!(self.logger, "Failed to remove watchtower appointment: {}", e);
+			}
+
+			let mut locked_state = self.state.lock().unwrap();
+			if let Some(channel_state) = locked_state.channels.get_mut(&appointment.channel_id) {
+				channel_state.backed_up_states += 1;
+			}
+			locked_state.latest_sync_timestamp =
+				Some(SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs());
+			if let Err(e) = self.persist_state(&locked_state) {
+				log_error!(self.logger, "Failed to persist watchtower client state: {}", e);
+			}
+			num_delivered += 1;
+		}
+
+		if num_delivered > 0 {
+			log_info!(
+				self.logger,
+				"Delivered {} channel state(s) to watchtower {}",
+				num_delivered,
+				self.tower_uri
+			);
+		}
+	}
+
+	async fn register(&self) -> Result<(), String> {
+		let request = RegisterRequest { user_id: hex_utils::to_string(&self.user_id.serialize()) };
+		let response: RegisterResponse = self.post("register", &request).await?;
+
+		let receipt_data = registration_receipt_data(
+			&self.user_id,
+			response.available_slots,
+			response.subscription_start,
+			response.subscription_expiry,
+		);
+		if !message_signing::verify(
+			&receipt_data,
+			&response.subscription_signature,
+			&self.tower_pubkey,
+		) {
+			return Err("tower returned an invalid registration receipt".to_string());
+		}
+
+		log_info!(
+			self.logger,
+			"Registered with watchtower {}, got {} appointment slots until block {}",
+			self.tower_uri,
+			response.available_slots,
+			response.subscription_expiry
+		);
+		self.update_subscription(response.available_slots, response.subscription_expiry);
+		Ok(())
+	}
+
+	async fn send_appointment(&self, appointment: &Appointment) -> Result<(), String> {
+		let signature = self.keys_manager.sign_message(&appointment.signed_data());
+		let request = AddAppointmentRequest {
+			appointment: AppointmentData {
+				locator: hex_utils::to_string(&appointment.locator),
+				encrypted_blob: hex_utils::to_string(&appointment.encrypted_blob),
+				to_self_delay: appointment.to_self_delay,
+			},
+			signature: signature.clone(),
+		};
+		let response: AddAppointmentResponse = match self.post("add_appointment", &request).await {
+			Ok(response) => response,
+			Err(e) => {
+				// The tower rejects appointments if our subscription ran out, so we'll re-register
+				// before retrying.
+				self.state.lock().unwrap().subscription = None;
+				return Err(e);
+			},
+		};
+
+		let receipt_data = appointment_receipt_data(&signature, response.start_block);
+		if !message_signing::verify(&receipt_data, &response.signature, &self.tower_pubkey) {
+			return Err("tower returned an invalid appointment receipt".to_string());
+		}
+
+		self.update_subscription(response.available_slots, response.subscription_expiry);
+		Ok(())
+	}
+
+	async fn post<Req: Serialize, Res: for<'de> Deserialize<'de>>(
+		&self, endpoint: &str, request: &Req,
+	) -> Result<Res, String> {
+		let url = format!("{}/{}", self.tower_uri.trim_end_matches('/'), endpoint);
+		let body = serde_json::to_vec(request).map_err(|e| e.to_string())?;
+		let response = self
+			.http_client
+			.post(url)
+			.header(reqwest::header::CONTENT_TYPE, "application/json")
+			.body(body)
+			.send()
+			.await
+			.and_then(|res| res.error_for_status())
+			.map_err(|e| e.to_string())?;
+		let body = response.bytes().await.map_err(|e| e.to_string())?;
+		serde_json::from_slice(&body).map_err(|e| e.to_string())
+	}
+
+	fn update_subscription(&self, available_slots: u32, subscription_expiry: u32) {
+		let mut locked_state = self.state.lock().unwrap();
+		locked_state.subscription =
+			Some(TowerSubscription { available_slots, subscription_expiry });
+		if let Err(e) = self.persist_state(&locked_state) {
+			log_error!(self.logger, "Failed to persist watchtower client state: {}", e);
+		}
+	}
+
+	pub(crate) fn status(&self) -> WatchtowerStatus {
+		let locked_state = self.state.lock().unwrap();
+		let mut channels: HashMap<ChannelId, ChannelWatchtowerStatus> = HashMap::new();
+		for (channel_id, channel_state) in locked_state.channels.iter() {
+			channels.insert(
+				*channel_id,
+				ChannelWatchtowerStatus {
+					channel_id: *channel_id,
+					backed_up_states: channel_state.backed_up_states,
+					pending_states: 0,
+				},
+			);
+		}
+		for appointment in self.pending_appointments.lock().unwrap().iter() {
+			channels
+				.entry(appointment.channel_id)
+				.or_insert(ChannelWatchtowerStatus {
+					channel_id: appointment.channel_id,
+					backed_up_states: 0,
+					pending_states: 0,
+				})
+				.pending_states += 1;
+		}
+
+		WatchtowerStatus {
+			tower_uri: self.tower_uri.clone(),
+			tower_pubkey: self.tower_pubkey,
+			channels: channels.into_values().collect(),
+			latest_sync_timestamp: locked_state.latest_sync_timestamp,
+		}
+	}
+
+	fn persist_state(&self, state: &WatchtowerClientState) -> Result<(), lightning::io::Error> {
+		KVStoreSync::write(
+			&*self.kv_store,
+			WATCHTOWER_CLIENT_PERSISTENCE_PRIMARY_NAMESPACE,
+			WATCHTOWER_CLIENT_PERSISTENCE_SECONDARY_NAMESPACE,
+			WATCHTOWER_CLIENT_PERSISTENCE_KEY,
+			state.encode(),
+		)
+	}
+
+	fn persist_appointment(&self, appointment: &Appointment) -> Result<(), lightning::io::Error> {
+		KVStoreSync::write(
+			&*self.kv_store,
+			WATCHTOWER_APPOINTMENT_PERSISTENCE_PRIMARY_NAMESPACE,
+			WATCHTOWER_APPOINTMENT_PERSISTENCE_SECONDARY_NAMESPACE,
+			&appointment.persistence_key(),
+			appointment.encode(),
+		)
+	}
+
+	fn remove_appointment(&self, appointment: &Appointment) -> Result<(), lightning::io::Error> {
+		KVStoreSync::remove(
+			&*self.kv_store,
+			WATCHTOWER_APPOINTMENT_PERSISTENCE_PRIMARY_NAMESPACE,
+			WATCHTOWER_APPOINTMENT_PERSISTENCE_SECONDARY_NAMESPACE,
+			&appointment.persistence_key(),
+		)
+	}
+}
+
+/// Persists our channel monitors, while handing the states revoked by our counterparties to the
+/// [`WatchtowerClient`], if any.
+pub(crate) struct WatchtowerPersister {
+	persister: Arc<Persister>,
+	watchtower_client: Option<Arc<WatchtowerClient>>,
+}
+
+impl WatchtowerPersister {
+	pub(crate) fn new(
+		persister: Arc<Persister>, watchtower_client: Option<Arc<WatchtowerClient>>,
+	) -> Self {
+		Self { persister, watchtower_client }
+	}
+}
+
+impl Persist<InMemorySigner> for WatchtowerPersister {
+	fn persist_new_channel(
+		&self, monitor_name: MonitorName, monitor: &ChannelMonitor<InMemorySigner>,
+	) -> ChannelMonitorUpdateStatus {
+		let res = self.persister.persist_new_channel(monitor_name, monitor);
+		if let Some(watchtower_client) = self.watchtower_client.as_ref() {
+			let commitment_txs = monitor.initial_counterparty_commitment_tx().into_iter().collect();
+			watchtower_client.process_commitment_txs(monitor, commitment_txs);
+		}
+		res
+	}
+
+	fn update_persisted_channel(
+		&self, monitor_name: MonitorName, monitor_update: Option<&ChannelMonitorUpdate>,
+		monitor: &ChannelMonitor<InMemorySigner>,
+	) -> ChannelMonitorUpdateStatus {
+		let res = self.persister.update_persisted_channel(monitor_name, monitor_update, monitor);
+		if let (Some(watchtower_client), Some(update)) =
+			(self.watchtower_client.as_ref(), monitor_update)
+		{
+			let commitment_txs = monitor.counterparty_commitment_txs_from_update(update);
+			watchtower_client.process_commitment_txs(monitor, commitment_txs);
+		}
+		res
+	}
+
+	fn archive_persisted_channel(&self, monitor_name: MonitorName) {
+		if let Some(watchtower_client) = self.watchtower_client.as_ref() {
+			watchtower_client.forget_channel(&monitor_name);
+		}
+		<Persister as Persist<InMemorySigner>>::archive_persisted_channel(
+			&self.persister,
+			monitor_name,
+		)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use bitcoin::absolute::LockTime;
+	use bitcoin::secp256k1::{Secp256k1, SecretKey};
+	use bitcoin::transaction::Version;
+	use bitcoin::{Amount, OutPoint, TxIn, TxOut, Txid, Witness};
+	use lightning::ln::chan_utils::get_revokeable_redeemscript;
+	use lightning::ln::channel_keys::{DelayedPaymentKey, RevocationKey};
+
+	use super::*;
+
+	fn justice_tx(commitment_txid: Txid, to_self_delay: u16) -> Transaction {
+		let secp_ctx = Secp256k1::new();
+		let revocation_key = RevocationKey(PublicKey::from_secret_key(
+			&secp_ctx,
+			&SecretKey::from_slice(&[1u8; 32]).unwrap(),
+		));
+		let delayed_key = DelayedPaymentKey(PublicKey::from_secret_key(
+			&secp_ctx,
+			&SecretKey::from_slice(&[2u8; 32]).unwrap(),
+		));
+		let witness_script =
+			get_revokeable_redeemscript(&revocation_key, to_self_delay, &delayed_key);
+		let witness = Witness::from_slice(&[vec![0u8; 72], vec![1u8], witness_script.to_bytes()]);
+		Transaction {
+			version: Version::TWO,
+			lock_time: LockTime::ZERO,
+			input: vec![TxIn {
+				previous_output: OutPoint { txid: commitment_txid, vout: 2 },
+				witness,
+				..Default::default()
+			}],
+			output: vec![TxOut {
+				value: Amount::from_sat(10_000),
+				script_pubkey: ScriptBuf::new(),
+			}],
+		}
+	}
+
+	#[test]
+	fn appointment_is_encrypted_under_commitment_txid() {
+		let commitment_txid = Txid::from_byte_array([42u8; 32]);
+		let justice_tx = justice_tx(commitment_txid, 144);
+
+		let appointment = Appointment::new(ChannelId([1u8; 32]), &justice_tx).unwrap();
+		assert_eq!(appointment.locator, [42u8; 16]);
+
+		// The tower can decrypt the justice transaction once the commitment transaction is known.
+		let encryption_key = Sha256::hash(&commitment_txid.to_byte_array()).to_byte_array();
+		let key = LessSafeKey::new(UnboundKey::new(&CHACHA20_POLY1305, &encryption_key).unwrap());
+		let mut encrypted_blob = appointment.encrypted_blob.clone();
+		let plaintext = key
+			.open_in_place(
+				Nonce::assume_unique_for_key([0u8; NONCE_LEN]),
+				Aad::empty(),
+				&mut encrypted_blob,
+			)
+			.unwrap();
+		let decrypted_tx: Transaction = bitcoin::consensus::deserialize(plaintext).unwrap();
+		assert_eq!(decrypted_tx, justice_tx);
+
+		// Without the commitment transaction, the blob can't be decrypted.
+		let wrong_key = LessSafeKey::new(UnboundKey::new(&CHACHA20_POLY1305, &[0u8; 32]).unwrap());
+		let mut encrypted_blob = appointment.encrypted_blob.clone();
+		assert!(wrong_key
+			.open_in_place(
+				Nonce::assume_unique_for_key([0u8; NONCE_LEN]),
+				Aad::empty(),
+				&mut encrypted_blob
+			)
+			.is_err());
+	}
+
+	#[test]
+	fn appointment_carries_to_self_delay() {
+		let commitment_txid = Txid::from_byte_array([42u8; 32]);
+		for to_self_delay in [6, 16, 17, 144, 2016] {
+			let justice_tx = justice_tx(commitment_txid, to_self_delay);
+			let appointment = Appointment::new(ChannelId([1u8; 32]), &justice_tx).unwrap();
+			assert_eq!(appointment.to_self_delay, to_self_delay as u32);
+		}
+
+		// Transactions not spending a revokeable output are rejected.
+		let mut justice_tx = justice_tx(commitment_txid, 144);
+		justice_tx.input[0].witness = Witness::from_slice(&[vec![0u8; 72]]);
+		assert_eq!(Appointment::new(ChannelId([1u8; 32]), &justice_tx), None);
+	}
+
+	#[test]
+	fn signed_data_follows_teos_format() {
+		let appointment = Appointment {
+			channel_id: ChannelId([1u8; 32]),
+			locator: [3u8; 16],
+			encrypted_blob: vec![4u8; 5],
+			to_self_delay: 144,
+		};
+		let mut expected = vec![3u8; 16];
+		expected.extend_from_slice(&[4u8; 5]);
+		expected.extend_from_slice(&[0, 0, 0, 144]);
+		assert_eq!(appointment.signed_data(), expected);
+
+		let mut expected = b"signature".to_vec();
+		expected.extend_from_slice(&[0, 0, 1, 0]);
+		assert_eq!(appointment_receipt_data("signature", 256), expected);
+
+		let secp_ctx = Secp256k1::new();
+		let user_id =
+			PublicKey::from_secret_key(&secp_ctx, &SecretKey::from_slice(&[1u8; 32]).unwrap());
+		let receipt_data = registration_receipt_data(&user_id, 10_000, 100, 4_420);
+		assert_eq!(&receipt_data[..33], &user_id.serialize()[..]);
+		assert_eq!(&receipt_data[33..], &[0, 0, 39, 16, 0, 0, 0, 100, 0, 0, 17, 68]);
+	}
+
+	#[test]
+	fn client_state_round_trips() {
+		let mut channels = BTreeMap::new();
+		channels.insert(
+			ChannelId([2u8; 32]),
+			ChannelBackupState {
+				monitor_name: "monitor".to_string(),
+				backed_up_states: 3,
+				lowest_commitment_number: Some(7),
+			},
+		);
+		let state = WatchtowerClientState {
+			destination_script: Some(ScriptBuf::new()),
+			subscription: Some(TowerSubscription {
+				available_slots: 10_000,
+				subscription_expiry: 4_420,
+			}),
+			unsigned_justice_txs: vec![UnsignedJusticeTx {
+				channel_id: ChannelId([1u8; 32]),
+				justice_tx: justice_tx(Txid::from_byte_array([42u8; 32]), 144),
+				value_sat: 10_000,
+				commitment_number: 7,
+			}],
+			channels,
+			latest_sync_timestamp: Some(1_700_000_000),
+		};
+		let decoded = WatchtowerClientState::read(&mut &state.encode()[..]).unwrap();
+		assert_eq!(decoded, state);
+
+		let appointment = Appointment::new(
+			ChannelId([1u8; 32]),
+			&justice_tx(Txid::from_byte_array([42u8; 32]), 144),
+		)
+		.unwrap();
+		let decoded = Appointment::read(&mut &appointment.encode()[..]).unwrap();
+		assert_eq!(decoded, appointment);
+	}
+}
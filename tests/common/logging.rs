@@ -87,6 +87,7 @@ impl<'a> From<MockLogRecord<'a>> for LogFacadeRecord<'a> {
 		let record = record_builder
 			.level(level)
 			.module_path(Some(&log_record.module_path))
+			.file(Some(log_record.file))
 			.line(Some(log_record.line))
 			.args(log_record.args);
 
@@ -28,9 +28,14 @@ use bitcoin::{
 use electrsd::corepc_node::{Client as BitcoindClient, Node as BitcoinD};
 use electrsd::{corepc_node, ElectrsD};
 use electrum_client::ElectrumApi;
-use ldk_node::config::{AsyncPaymentsRole, Config, ElectrumSyncConfig, EsploraSyncConfig};
+use ldk_node::config::{
+	AsyncPaymentsRole, BitcoindSyncConfig, ChannelConfig, ChannelConfigUpdate, Config,
+	ElectrumSyncConfig, EsploraSyncConfig, SendPaymentParams,
+};
 use ldk_node::io::sqlite_store::SqliteStore;
-use ldk_node::payment::{PaymentDirection, PaymentKind, PaymentStatus};
+use ldk_node::payment::{
+	PaymentDirection, PaymentFilter, PaymentKind, PaymentKindFilter, PaymentStatus,
+};
 use ldk_node::{
 	Builder, CustomTlvRecord, Event, LightningBalance, Node, NodeError, PendingSweepBalance,
 };
@@ -123,17 +128,36 @@ pub(crate) use expect_payment_received_event;
 
 macro_rules! expect_payment_claimable_event {
 	($node:expr, $payment_id:expr, $payment_hash:expr, $claimable_amount_msat:expr) => {{
+		expect_payment_claimable_event!(
+			$node,
+			$payment_id,
+			$payment_hash,
+			$claimable_amount_msat,
+			None::<(bool, bool)>
+		)
+	}};
+	($node:expr, $payment_id:expr, $payment_hash:expr, $claimable_amount_msat:expr, $channel_flags:expr) => {{
 		match $node.wait_next_event() {
 			ref e @ Event::PaymentClaimable {
 				payment_id,
 				payment_hash,
 				claimable_amount_msat,
+				claim_deadline_unix_secs,
+				via_jit_channel,
+				channel_confirmed,
 				..
 			} => {
 				println!("{} got event {:?}", std::stringify!($node), e);
 				assert_eq!(payment_hash, $payment_hash);
 				assert_eq!(payment_id, $payment_id);
+				// Block times on regtest are arbitrary, so we don't provide an estimate.
+				assert_eq!(claim_deadline_unix_secs, None);
 				assert_eq!(claimable_amount_msat, $claimable_amount_msat);
+				if let Some((expected_via_jit_channel, expected_channel_confirmed)) = $channel_flags
+				{
+					assert_eq!(via_jit_channel, expected_via_jit_channel);
+					assert_eq!(channel_confirmed, expected_channel_confirmed);
+				}
 				$node.event_handled().unwrap();
 				claimable_amount_msat
 			},
@@ -246,6 +270,9 @@ pub(crate) fn random_config(anchor_channels: bool) -> TestConfig {
 	println!("Setting random LDK node alias: {:?}", alias);
 	node_config.node_alias = alias;
 
+	// Most of our tests exercise receiving spontaneous payments.
+	node_config.accept_spontaneous_payments = true;
+
 	TestConfig { node_config, ..Default::default() }
 }
 
@@ -266,6 +293,7 @@ pub(crate) enum TestChainSource<'a> {
 pub(crate) struct TestConfig {
 	pub node_config: Config,
 	pub log_writer: TestLogWriter,
+	pub static_channel_backup: Option<Vec<u8>>,
 }
 
 macro_rules! setup_builder {
@@ -324,7 +352,8 @@ pub(crate) fn setup_node_for_async_payments(
 		},
 		TestChainSource::Electrum(electrsd) => {
 			let electrum_url = format!("tcp://{}", electrsd.electrum_url);
-			let sync_config = ElectrumSyncConfig { background_sync_config: None };
+			let sync_config =
+				ElectrumSyncConfig { background_sync_config: None, ..Default::default() };
 			builder.set_chain_source_electrum(electrum_url.clone(), Some(sync_config));
 		},
 		TestChainSource::BitcoindRpcSync(bitcoind) => {
@@ -333,7 +362,15 @@ pub(crate) fn setup_node_for_async_payments(
 			let values = bitcoind.params.get_cookie_values().unwrap().unwrap();
 			let rpc_user = values.user;
 			let rpc_password = values.password;
-			builder.set_chain_source_bitcoind_rpc(rpc_host, rpc_port, rpc_user, rpc_password);
+			let sync_config =
+				BitcoindSyncConfig { background_sync_config: None, ..Default::default() };
+			builder.set_chain_source_bitcoind_rpc(
+				rpc_host,
+				rpc_port,
+				rpc_user,
+				rpc_password,
+				Some(sync_config),
+			);
 		},
 		TestChainSource::BitcoindRestSync(bitcoind) => {
 			let rpc_host = bitcoind.params.rpc_socket.ip().to_string();
@@ -350,13 +387,14 @@ pub(crate) fn setup_node_for_async_payments(
 				rpc_port,
 				rpc_user,
 				rpc_password,
+				None,
 			);
 		},
 	}
 
 	match &config.log_writer {
 		TestLogWriter::FileWriter => {
-			builder.set_filesystem_logger(None, None);
+			builder.set_filesystem_logger(None, None, None);
 		},
 		TestLogWriter::LogFacade => {
 			builder.set_log_facade_logger();
@@ -381,6 +419,10 @@ pub(crate) fn setup_node_for_async_payments(
 
 	builder.set_async_payments_role(async_payments_role).unwrap();
 
+	if let Some(static_channel_backup) = config.static_channel_backup {
+		builder.recover_from_static_channel_backup(static_channel_backup, true);
+	}
+
 	let test_sync_store = Arc::new(TestSyncStore::new(config.node_config.storage_dir_path.into()));
 	let node = builder.build_with_store(test_sync_store).unwrap();
 	node.start().unwrap();
@@ -683,6 +725,21 @@ pub(crate) fn do_channel_full_cycle<E: ElectrumApi>(
 		0
 	);
 
+	// Check the same via the paginated query interface.
+	let inbound_onchain_filter = PaymentFilter {
+		direction: Some(PaymentDirection::Inbound),
+		kind: Some(PaymentKindFilter::Onchain),
+		..Default::default()
+	};
+	assert_eq!(node_a.count_payments(inbound_onchain_filter.clone()), 1);
+	assert_eq!(node_a.query_payments(inbound_onchain_filter.clone()).len(), 1);
+	assert!(node_a
+		.query_payments(PaymentFilter { offset: 1, ..inbound_onchain_filter })
+		.is_empty());
+	let outbound_filter =
+		PaymentFilter { direction: Some(PaymentDirection::Outbound), ..Default::default() };
+	assert_eq!(node_a.count_payments(outbound_filter), 0);
+
 	// Check we haven't got any events yet
 	assert_eq!(node_a.next_event(), None);
 	assert_eq!(node_b.next_event(), None);
@@ -760,6 +817,56 @@ pub(crate) fn do_channel_full_cycle<E: ElectrumApi>(
 	let user_channel_id = expect_channel_ready_event!(node_a, node_b.node_id());
 	expect_channel_ready_event!(node_b, node_a.node_id());
 
+	// Check the channel's liquidity is reported consistently with its balance.
+	let channel_details =
+		node_a.list_channels().into_iter().find(|c| c.user_channel_id == user_channel_id).unwrap();
+	assert!(channel_details.is_channel_ready);
+	assert!(channel_details.is_usable);
+	let channel_reserve_msat = channel_details.unspendable_punishment_reserve.unwrap() * 1000;
+	assert!(
+		channel_details.outbound_capacity_msat + channel_reserve_msat
+			<= channel_details.channel_value_sats * 1000
+	);
+	assert!(
+		channel_details.outbound_capacity_msat + channel_details.inbound_capacity_msat
+			<= channel_details.channel_value_sats * 1000
+	);
+	assert!(
+		channel_details.next_outbound_htlc_limit_msat <= channel_details.outbound_capacity_msat
+	);
+	assert!(
+		channel_details.next_outbound_htlc_minimum_msat
+			<= channel_details.next_outbound_htlc_limit_msat
+	);
+
+	// Check we can update individual fields of the channel config.
+	let channel_config_before = node_a
+		.list_channels()
+		.iter()
+		.find(|c| c.user_channel_id == user_channel_id)
+		.unwrap()
+		.config;
+	let channel_config_update = ChannelConfigUpdate {
+		forwarding_fee_base_msat: Some(channel_config_before.forwarding_fee_base_msat + 1000),
+		..Default::default()
+	};
+	node_a
+		.update_partial_channel_config(&user_channel_id, node_b.node_id(), channel_config_update)
+		.unwrap();
+	let channel_config_after = node_a
+		.list_channels()
+		.iter()
+		.find(|c| c.user_channel_id == user_channel_id)
+		.unwrap()
+		.config;
+	assert_eq!(
+		channel_config_after,
+		ChannelConfig {
+			forwarding_fee_base_msat: channel_config_before.forwarding_fee_base_msat + 1000,
+			..channel_config_before
+		}
+	);
+
 	println!("\nB receive");
 	let invoice_amount_1_msat = 2500_000;
 	let invoice_description: Bolt11InvoiceDescription =
@@ -806,6 +913,19 @@ pub(crate) fn do_channel_full_cycle<E: ElectrumApi>(
 	assert_eq!(node_b.payment(&payment_id).unwrap().amount_msat, Some(invoice_amount_1_msat));
 	assert!(matches!(node_b.payment(&payment_id).unwrap().kind, PaymentKind::Bolt11 { .. }));
 
+	// The preimage serves as proof of payment and matches on both sides.
+	let payment_a = node_a.payment(&payment_id).unwrap();
+	let payment_b = node_b.payment(&payment_id).unwrap();
+	assert!(payment_a.preimage().is_some());
+	assert_eq!(payment_a.preimage(), payment_b.preimage());
+	assert!(payment_a.settled_at_unix_secs.is_some());
+	assert!(payment_b.settled_at_unix_secs.is_some());
+
+	// Payments can be looked up by their hash, too.
+	let payment_hash = PaymentHash(invoice.payment_hash().to_byte_array());
+	assert_eq!(node_a.payment_by_hash(payment_hash).unwrap().id, payment_id);
+	assert_eq!(node_b.payment_by_hash(payment_hash).unwrap().id, payment_id);
+
 	// Assert we fail duplicate outbound payments and check the status hasn't changed.
 	assert_eq!(Err(NodeError::DuplicatePayment), node_a.bolt11_payment().send(&invoice, None));
 	assert_eq!(node_a.payment(&payment_id).unwrap().status, PaymentStatus::Succeeded);
@@ -916,7 +1036,8 @@ pub(crate) fn do_channel_full_cycle<E: ElectrumApi>(
 		node_b,
 		manual_payment_id,
 		manual_payment_hash,
-		invoice_amount_3_msat
+		invoice_amount_3_msat,
+		Some((false, true))
 	);
 	node_b
 		.bolt11_payment()
@@ -994,11 +1115,54 @@ pub(crate) fn do_channel_full_cycle<E: ElectrumApi>(
 	println!("\nA send_spontaneous_payment");
 	let keysend_amount_msat = 2500_000;
 	let custom_tlvs = vec![CustomTlvRecord { type_num: 13377331, value: vec![1, 2, 3] }];
+	for invalid_type_num in [13377330, 4242] {
+		let invalid_custom_tlvs =
+			vec![CustomTlvRecord { type_num: invalid_type_num, value: vec![] }];
+		assert_eq!(
+			Err(NodeError::InvalidCustomTlvs),
+			node_a.spontaneous_payment().send_with_custom_tlvs(
+				keysend_amount_msat,
+				node_b.node_id(),
+				None,
+				invalid_custom_tlvs
+			)
+		);
+	}
+	let keysend_send_params =
+		SendPaymentParams { idempotency_key: Some("keysend".to_string()), ..Default::default() };
 	let keysend_payment_id = node_a
 		.spontaneous_payment()
-		.send_with_custom_tlvs(keysend_amount_msat, node_b.node_id(), None, custom_tlvs.clone())
+		.send_with_custom_tlvs(
+			keysend_amount_msat,
+			node_b.node_id(),
+			Some(keysend_send_params.clone()),
+			custom_tlvs.clone(),
+		)
 		.unwrap();
 	expect_event!(node_a, PaymentSuccessful);
+
+	// Retrying with the same idempotency key doesn't send another payment.
+	assert_eq!(
+		Ok(keysend_payment_id),
+		node_a.spontaneous_payment().send_with_custom_tlvs(
+			keysend_amount_msat,
+			node_b.node_id(),
+			Some(keysend_send_params.clone()),
+			custom_tlvs.clone(),
+		)
+	);
+	assert_eq!(
+		Ok(keysend_payment_id),
+		node_a.spontaneous_payment().send(
+			keysend_amount_msat,
+			node_b.node_id(),
+			Some(keysend_send_params)
+		)
+	);
+	assert_eq!(
+		node_a.payment_id_for_idempotency_key("keysend".to_string()),
+		Some(keysend_payment_id)
+	);
 	let next_event = node_b.wait_next_event();
 	let (received_keysend_amount, received_custom_records) = match next_event {
 		ref e @ Event::PaymentReceived { amount_msat, ref custom_records, .. } => {
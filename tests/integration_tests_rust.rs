@@ -10,11 +10,16 @@ mod common;
 use std::collections::HashSet;
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+use base64::prelude::BASE64_STANDARD;
+use base64::Engine;
 use bitcoin::address::NetworkUnchecked;
 use bitcoin::hashes::sha256::Hash as Sha256Hash;
 use bitcoin::hashes::Hash;
-use bitcoin::{Address, Amount, ScriptBuf};
+use bitcoin::psbt::Psbt;
+use bitcoin::secp256k1::PublicKey;
+use bitcoin::{Address, Amount, FeeRate, OutPoint, ScriptBuf, Transaction, Txid, WPubkeyHash};
 use common::logging::{init_log_logger, validate_log_entry, MultiNodeLogger, TestLogWriter};
 use common::{
 	bump_fee_and_broadcast, distribute_funds_unconfirmed, do_channel_full_cycle,
@@ -23,19 +28,36 @@ use common::{
 	generate_blocks_and_wait, open_channel, open_channel_push_amt, premine_and_distribute_funds,
 	premine_blocks, prepare_rbf, random_config, random_listening_addresses,
 	setup_bitcoind_and_electrsd, setup_builder, setup_node, setup_node_for_async_payments,
-	setup_two_nodes, wait_for_tx, TestChainSource, TestSyncStore,
+	setup_two_nodes, wait_for_outpoint_spend, wait_for_tx, TestChainSource, TestSyncStore,
 };
-use ldk_node::config::{AsyncPaymentsRole, EsploraSyncConfig};
-use ldk_node::liquidity::LSPS2ServiceConfig;
+use electrsd::corepc_node::Node as BitcoinD;
+use electrum_client::ElectrumApi;
+use ldk_node::config::{
+	AsyncPaymentsRole, BitcoindSyncConfig, ChannelOpenParams, EsploraSyncConfig,
+	PeerReconnectPolicy, RetryStrategy, SendPaymentParams, SpendableOutputSweepConfig,
+	MAX_ONION_MESSAGE_PATH_INTERMEDIATE_NODES,
+};
+use ldk_node::liquidity::{JitChannelFeeLimits, LSPS2ServiceConfig};
+use ldk_node::logger::LogLevel;
 use ldk_node::payment::{
-	ConfirmationStatus, PaymentDetails, PaymentDirection, PaymentKind, PaymentStatus,
-	QrPaymentResult,
+	ConfirmationStatus, FeeEstimate, PaymentDetails, PaymentDirection, PaymentKind, PaymentStatus,
+	QrPaymentResult, RecurrenceInterval, Route, RouteHop,
+};
+use ldk_node::{
+	BuildError, Builder, ChannelRejectReason, CloseType, ConfirmationTarget, ConnectResult,
+	ConnectionFailureReason, DynStore, Event, FeeEstimator, ForceCloseOutcome, LightningBalance,
+	NodeError, OnchainWalletInterface, PaymentAutoFailReason, PendingSweepBalance, PowerMode,
+	UserChannelId,
 };
-use ldk_node::{Builder, DynStore, Event, NodeError};
-use lightning::ln::channelmanager::PaymentId;
+use lightning::events::bump_transaction::Utxo;
+use lightning::events::ClosureReason;
+use lightning::ln::channelmanager::{PaymentId, MIN_FINAL_CLTV_EXPIRY_DELTA};
+use lightning::ln::types::ChannelId;
+use lightning::offers::offer::Offer;
 use lightning::routing::gossip::{NodeAlias, NodeId};
 use lightning::routing::router::RouteParametersConfig;
-use lightning_invoice::{Bolt11InvoiceDescription, Description};
+use lightning_invoice::{Bolt11InvoiceDescription, Bolt11InvoiceDescriptionRef, Description};
+use lightning_liquidity::lsps0::ser::LSPSDateTime;
 use lightning_types::payment::{PaymentHash, PaymentPreimage};
 use log::LevelFilter;
 
@@ -71,6 +93,177 @@ fn channel_full_cycle_bitcoind_rest_sync() {
 	do_channel_full_cycle(node_a, node_b, &bitcoind.client, &electrsd.client, false, true, false);
 }
 
+#[test]
+fn bitcoind_rpc_cookie_auth() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let rpc_host = bitcoind.params.rpc_socket.ip().to_string();
+	let rpc_port = bitcoind.params.rpc_socket.port();
+	let sync_config = BitcoindSyncConfig { background_sync_config: None, ..Default::default() };
+
+	// Building fails if the cookie file can't be read.
+	let config = random_config(true);
+	setup_builder!(builder, config.node_config);
+	let invalid_cookie_file_path =
+		format!("{}/does_not_exist/.cookie", config.node_config.storage_dir_path);
+	builder.set_chain_source_bitcoind_rpc_with_cookie(
+		rpc_host.clone(),
+		rpc_port,
+		invalid_cookie_file_path,
+		Some(sync_config),
+	);
+	assert_eq!(builder.build_with_fs_store().err(), Some(BuildError::InvalidRpcCookieFile));
+
+	let config = random_config(true);
+	setup_builder!(builder, config.node_config);
+	let cookie_file_path = bitcoind.params.cookie_file.to_str().unwrap().to_string();
+	builder.set_chain_source_bitcoind_rpc_with_cookie(
+		rpc_host,
+		rpc_port,
+		cookie_file_path,
+		Some(sync_config),
+	);
+	let node = builder.build_with_fs_store().unwrap();
+	node.start().unwrap();
+
+	let address = node.onchain_payment().new_address().unwrap();
+	let premine_amount = Amount::from_sat(100_000);
+	premine_and_distribute_funds(&bitcoind.client, &electrsd.client, vec![address], premine_amount);
+
+	node.sync_wallets().unwrap();
+	assert_eq!(node.list_balances().spendable_onchain_balance_sats, premine_amount.to_sat());
+	node.stop().unwrap();
+}
+
+#[test]
+fn custom_fee_estimator() {
+	struct FixedFeeEstimator(FeeRate);
+
+	impl FeeEstimator for FixedFeeEstimator {
+		fn estimate_fee_rate(&self, _target: ConfirmationTarget) -> FeeRate {
+			self.0
+		}
+	}
+
+	let (_bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let config = random_config(true);
+	setup_builder!(builder, config.node_config);
+	let esplora_url = format!("http://{}", electrsd.esplora_url.as_ref().unwrap());
+	let sync_config = EsploraSyncConfig { background_sync_config: None };
+	builder.set_chain_source_esplora(esplora_url, Some(sync_config));
+
+	let custom_fee_rate = FeeRate::from_sat_per_kwu(1234);
+	builder.set_custom_fee_estimator(Arc::new(FixedFeeEstimator(custom_fee_rate)));
+	let node = builder.build_with_fs_store().unwrap();
+	node.start().unwrap();
+
+	// The custom estimator's values are cached and exposed in place of the chain source's.
+	assert_eq!(node.current_fee_rate(ConfirmationTarget::OnchainPayment), custom_fee_rate);
+	assert_eq!(node.current_fee_rate(ConfirmationTarget::ChannelFunding), custom_fee_rate);
+
+	// Overrides still take precedence.
+	let override_fee_rate = FeeRate::from_sat_per_kwu(5000);
+	node.set_fee_rate_override(ConfirmationTarget::OnchainPayment, Some(override_fee_rate));
+	assert_eq!(node.current_fee_rate(ConfirmationTarget::OnchainPayment), override_fee_rate);
+	node.stop().unwrap();
+}
+
+#[test]
+fn external_onchain_wallet() {
+	// An external wallet backed by bitcoind's wallet.
+	struct BitcoindWallet(Arc<BitcoinD>);
+
+	impl OnchainWalletInterface for BitcoindWallet {
+		fn get_change_address(&self) -> Result<Address, NodeError> {
+			let address = self.0.client.call::<serde_json::Value>("getnewaddress", &[]).unwrap();
+			let address = Address::<NetworkUnchecked>::from_str(address.as_str().unwrap()).unwrap();
+			Ok(address.assume_checked())
+		}
+
+		fn sign_psbt(&self, psbt: Psbt) -> Result<Psbt, NodeError> {
+			let psbt = serde_json::json!(BASE64_STANDARD.encode(psbt.serialize()));
+			let res =
+				self.0.client.call::<serde_json::Value>("walletprocesspsbt", &[psbt]).unwrap();
+			let signed_psbt = BASE64_STANDARD.decode(res["psbt"].as_str().unwrap()).unwrap();
+			Ok(Psbt::deserialize(&signed_psbt).unwrap())
+		}
+
+		fn broadcast(&self, tx: &Transaction) -> Result<(), NodeError> {
+			let tx_hex = serde_json::json!(bitcoin::consensus::encode::serialize_hex(tx));
+			self.0
+				.client
+				.call::<serde_json::Value>("sendrawtransaction", &[tx_hex])
+				.map(|_| ())
+				.map_err(|_| NodeError::TxBroadcastFailed)
+		}
+
+		fn get_utxos(&self) -> Result<Vec<Utxo>, NodeError> {
+			let unspent = self.0.client.call::<serde_json::Value>("listunspent", &[]).unwrap();
+			let utxos = unspent
+				.as_array()
+				.unwrap()
+				.iter()
+				.map(|u| {
+					let txid = Txid::from_str(u["txid"].as_str().unwrap()).unwrap();
+					let outpoint = OutPoint { txid, vout: u["vout"].as_u64().unwrap() as u32 };
+					let value = Amount::from_btc(u["amount"].as_f64().unwrap()).unwrap();
+					let script_pubkey =
+						ScriptBuf::from_hex(u["scriptPubKey"].as_str().unwrap()).unwrap();
+					let wpkh = WPubkeyHash::from_slice(&script_pubkey.as_bytes()[2..]).unwrap();
+					Utxo::new_v0_p2wpkh(outpoint, value, &wpkh)
+				})
+				.collect();
+			Ok(utxos)
+		}
+	}
+
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let bitcoind = Arc::new(bitcoind);
+	premine_blocks(&bitcoind.client, &electrsd.client);
+
+	let config_a = random_config(true);
+	setup_builder!(builder_a, config_a.node_config);
+	let esplora_url = format!("http://{}", electrsd.esplora_url.as_ref().unwrap());
+	let sync_config = EsploraSyncConfig { background_sync_config: None };
+	builder_a.set_chain_source_esplora(esplora_url, Some(sync_config));
+	builder_a.set_external_onchain_wallet(Arc::new(BitcoindWallet(Arc::clone(&bitcoind))));
+	let node_a = builder_a.build_with_fs_store().unwrap();
+	node_a.start().unwrap();
+
+	let chain_source = TestChainSource::Esplora(&electrsd);
+	let node_b = setup_node(&chain_source, random_config(true), None);
+
+	// The channel is funded by the external wallet, even though the internal one is empty.
+	assert_eq!(node_a.list_balances().total_onchain_balance_sats, 0);
+	let channel_amount_sat = 500_000;
+	let node_b_address = node_b.listening_addresses().unwrap().first().unwrap().clone();
+	let user_channel_id = node_a
+		.open_channel(node_b.node_id(), node_b_address, channel_amount_sat, None, None)
+		.unwrap();
+
+	let funding_txo_a = expect_channel_pending_event!(node_a, node_b.node_id());
+	let funding_txo_b = expect_channel_pending_event!(node_b, node_a.node_id());
+	assert_eq!(funding_txo_a, funding_txo_b);
+	wait_for_tx(&electrsd.client, funding_txo_a.txid);
+
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	expect_channel_ready_event!(node_a, node_b.node_id());
+	expect_channel_ready_event!(node_b, node_a.node_id());
+
+	// The funds of the closed channel are sent to the external wallet.
+	node_a.close_channel(&user_channel_id, node_b.node_id()).unwrap();
+	expect_event!(node_a, ChannelClosed);
+	expect_event!(node_b, ChannelClosed);
+
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 1);
+	node_a.sync_wallets().unwrap();
+	assert_eq!(node_a.list_balances().total_onchain_balance_sats, 0);
+
+	node_a.stop().unwrap();
+	node_b.stop().unwrap();
+}
+
 #[test]
 fn channel_full_cycle_force_close() {
 	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
@@ -103,6 +296,171 @@ fn channel_full_cycle_legacy_staticremotekey() {
 	do_channel_full_cycle(node_a, node_b, &bitcoind.client, &electrsd.client, false, false, false);
 }
 
+#[test]
+fn force_close_all_channels_with_peer() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+	let (node_a, node_b) = setup_two_nodes(&chain_source, false, true, false);
+
+	let addr_a_1 = node_a.onchain_payment().new_address().unwrap();
+	let addr_a_2 = node_a.onchain_payment().new_address().unwrap();
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![addr_a_1, addr_a_2],
+		Amount::from_sat(1_100_000),
+	);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+
+	let funding_txo_1 = open_channel(&node_a, &node_b, 500_000, false, &electrsd);
+	let funding_txo_2 = open_channel(&node_a, &node_b, 500_000, false, &electrsd);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	let user_channel_id_1 = expect_channel_ready_event!(node_a, node_b.node_id());
+	let user_channel_id_2 = expect_channel_ready_event!(node_a, node_b.node_id());
+	expect_channel_ready_event!(node_b, node_a.node_id());
+	expect_channel_ready_event!(node_b, node_a.node_id());
+
+	// Channels we're already closing cooperatively are either force-closed, too, or reported as
+	// already closed if the cooperative closure completes first.
+	node_a.close_channel(&user_channel_id_1, node_b.node_id()).unwrap();
+
+	let reason = "Peer went away".to_string();
+	let results = node_a.force_close_all_channels_with_peer(node_b.node_id(), Some(reason.clone()));
+	assert_eq!(results.len(), 2);
+	assert!(results.iter().all(|r| r.outcome != ForceCloseOutcome::Failed));
+	assert!(results
+		.iter()
+		.any(|r| r.user_channel_id == user_channel_id_2
+			&& r.outcome == ForceCloseOutcome::ForceClosed));
+	assert!(results.iter().any(|r| r.user_channel_id == user_channel_id_1));
+	assert!(results.iter().any(|r| r.user_channel_id == user_channel_id_2));
+
+	expect_event!(node_a, ChannelClosed);
+	expect_event!(node_a, ChannelClosed);
+	assert!(node_a.list_channels().is_empty());
+	assert!(node_a.list_peers().iter().all(|p| p.node_id != node_b.node_id() || !p.is_persisted));
+
+	let closed_channels = node_a.list_closed_channels();
+	assert_eq!(closed_channels.len(), 2);
+	let force_closed_channel =
+		closed_channels.iter().find(|c| c.user_channel_id == user_channel_id_2).unwrap();
+	assert_eq!(force_closed_channel.close_type, CloseType::ForceLocal);
+	assert_eq!(force_closed_channel.force_close_reason, Some(reason));
+
+	// There's nothing left to close.
+	assert!(node_a.force_close_all_channels_with_peer(node_b.node_id(), None).is_empty());
+
+	wait_for_outpoint_spend(&electrsd.client, funding_txo_1);
+	wait_for_outpoint_spend(&electrsd.client, funding_txo_2);
+}
+
+#[test]
+fn cooperative_close_with_feerate() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+	let (node_a, node_b) = setup_two_nodes(&chain_source, false, true, false);
+
+	let addr_a = node_a.onchain_payment().new_address().unwrap();
+	let addr_b = node_b.onchain_payment().new_address().unwrap();
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![addr_a, addr_b],
+		Amount::from_sat(2_125_000),
+	);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+
+	let funding_txo = open_channel(&node_a, &node_b, 1_000_000, false, &electrsd);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	let user_channel_id = expect_channel_ready_event!(node_a, node_b.node_id());
+	expect_channel_ready_event!(node_b, node_a.node_id());
+
+	node_a.close_channel_with_feerate(&user_channel_id, node_b.node_id(), 5, Some(3600)).unwrap();
+
+	match node_a.wait_next_event() {
+		Event::ChannelClosed {
+			reason,
+			closing_fee_sat,
+			closing_txid,
+			close_type,
+			balance_recovered_sat,
+			..
+		} => {
+			assert!(matches!(reason, Some(ClosureReason::LocallyInitiatedCooperativeClosure)));
+			assert!(closing_fee_sat.unwrap() > 0);
+			assert!(closing_txid.is_some());
+			assert_eq!(close_type, CloseType::Cooperative);
+			assert!(balance_recovered_sat.unwrap() > 0);
+		},
+		e => panic!("Unexpected event: {:?}", e),
+	}
+	node_a.event_handled().unwrap();
+	expect_event!(node_b, ChannelClosed);
+
+	let closed_channels = node_a.list_closed_channels();
+	assert_eq!(closed_channels.len(), 1);
+	assert_eq!(closed_channels[0].user_channel_id, user_channel_id);
+	assert_eq!(closed_channels[0].counterparty_node_id, Some(node_b.node_id()));
+	assert_eq!(closed_channels[0].funding_txo, Some(funding_txo));
+	assert_eq!(closed_channels[0].close_type, CloseType::Cooperative);
+	assert!(node_a.list_channels().is_empty());
+
+	// Closing a channel we don't know about fails.
+	assert_eq!(
+		node_a.close_channel_with_feerate(&user_channel_id, node_b.node_id(), 5, None),
+		Err(NodeError::ChannelNotFound)
+	);
+
+	wait_for_outpoint_spend(&electrsd.client, funding_txo);
+}
+
+#[test]
+fn inbound_channel_rejected_when_violating_limits() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+
+	let node_a = setup_node(&chain_source, random_config(true), None);
+	let mut config_b = random_config(true);
+	config_b.node_config.inbound_channel_limits.max_funding_satoshis = 500_000;
+	let node_b = setup_node(&chain_source, config_b, None);
+
+	let addr_a = node_a.onchain_payment().new_address().unwrap();
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![addr_a],
+		Amount::from_sat(2_125_000),
+	);
+	node_a.sync_wallets().unwrap();
+
+	node_a
+		.open_channel(
+			node_b.node_id(),
+			node_b.listening_addresses().unwrap().first().unwrap().clone(),
+			1_000_000,
+			None,
+			None,
+		)
+		.unwrap();
+
+	match node_b.wait_next_event() {
+		Event::ChannelRejected { counterparty_node_id, funding_satoshis, reason } => {
+			assert_eq!(counterparty_node_id, node_a.node_id());
+			assert_eq!(funding_satoshis, 1_000_000);
+			assert_eq!(reason, ChannelRejectReason::ExceedsLimits);
+		},
+		e => panic!("Unexpected event: {:?}", e),
+	}
+	node_b.event_handled().unwrap();
+	assert!(node_b.list_channels().is_empty());
+}
+
 #[test]
 fn channel_open_fails_when_funds_insufficient() {
 	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
@@ -138,6 +496,43 @@ fn channel_open_fails_when_funds_insufficient() {
 	);
 }
 
+#[test]
+fn announced_channel_open_fails_without_listening_address() {
+	let (_bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+
+	let mut config_a = random_config(true);
+	config_a.node_config.listening_addresses = None;
+	let node_a = setup_node(&chain_source, config_a, None);
+	let node_b = setup_node(&chain_source, random_config(true), None);
+
+	assert_eq!(
+		Err(NodeError::NoListeningAddress),
+		node_a.open_announced_channel(
+			node_b.node_id(),
+			node_b.listening_addresses().unwrap().first().unwrap().clone(),
+			100_000,
+			None,
+			None,
+		)
+	);
+
+	// Unannounced channels don't require listening addresses.
+	assert_ne!(
+		Err(NodeError::NoListeningAddress),
+		node_a.open_channel(
+			node_b.node_id(),
+			node_b.listening_addresses().unwrap().first().unwrap().clone(),
+			100_000,
+			None,
+			None,
+		)
+	);
+
+	node_a.stop().unwrap();
+	node_b.stop().unwrap();
+}
+
 #[test]
 fn multi_hop_sending() {
 	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
@@ -208,11 +603,13 @@ fn multi_hop_sending() {
 	// Sleep a bit for gossip to propagate.
 	std::thread::sleep(std::time::Duration::from_secs(1));
 
-	let route_params = RouteParametersConfig {
+	let send_params = SendPaymentParams {
 		max_total_routing_fee_msat: Some(75_000),
-		max_total_cltv_expiry_delta: 1000,
-		max_path_count: 10,
-		max_channel_saturation_power_of_half: 2,
+		max_total_cltv_expiry_delta: Some(1000),
+		max_path_count: Some(10),
+		max_channel_saturation_power_of_half: Some(2),
+		retry_strategy: Some(RetryStrategy::Attempts { max_attempts: 3 }),
+		..Default::default()
 	};
 
 	let invoice_description =
@@ -221,9 +618,25 @@ fn multi_hop_sending() {
 		.bolt11_payment()
 		.receive(2_500_000, &invoice_description.clone().into(), 9217)
 		.unwrap();
-	nodes[0].bolt11_payment().send(&invoice, Some(route_params)).unwrap();
+
+	// Probe the route first, which should succeed without settling.
+	nodes[0].bolt11_payment().send_probes(&invoice, None).unwrap();
+	match nodes[0].wait_next_event() {
+		Event::ProbeSuccessful { path_fees_msat, path_hops, .. } => {
+			assert_eq!(path_hops, 3);
+			assert!(path_fees_msat > 0);
+			nodes[0].event_handled().unwrap();
+		},
+		e => panic!("Unexpected event: {:?}", e),
+	}
+	assert!(nodes[0]
+		.list_payments_with_filter(|p| !matches!(p.kind, PaymentKind::Onchain { .. }))
+		.is_empty());
+
+	nodes[0].bolt11_payment().send(&invoice, Some(send_params)).unwrap();
 
 	expect_event!(nodes[1], PaymentForwarded);
+	assert!(nodes[1].total_forwarding_fees_earned_msat() > 0);
 
 	// We expect that the payment goes through N2 or N3, so we check both for the PaymentForwarded event.
 	let node_2_fwd_event = matches!(nodes[2].next_event(), Some(Event::PaymentForwarded { .. }));
@@ -305,286 +718,1406 @@ fn start_stop_reinit() {
 }
 
 #[test]
-fn onchain_send_receive() {
-	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
-	let chain_source = TestChainSource::Esplora(&electrsd);
-	let (node_a, node_b) = setup_two_nodes(&chain_source, false, true, false);
+fn storage_encryption_at_rest() {
+	let (_bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let config = random_config(true);
 
-	let addr_a = node_a.onchain_payment().new_address().unwrap();
-	let addr_b = node_b.onchain_payment().new_address().unwrap();
-	// This is a Bitcoin Testnet address. Sending funds to this address from the Regtest network will fail
-	let static_address = "tb1q0d40e5rta4fty63z64gztf8c3v20cvet6v2jdh";
-	let unchecked_address = Address::<NetworkUnchecked>::from_str(static_address).unwrap();
-	let addr_c = unchecked_address.assume_checked();
+	let esplora_url = format!("http://{}", electrsd.esplora_url.as_ref().unwrap());
+	let sync_config = EsploraSyncConfig { background_sync_config: None };
 
-	let premine_amount_sat = 1_100_000;
-	premine_and_distribute_funds(
-		&bitcoind.client,
-		&electrsd.client,
-		vec![addr_a.clone(), addr_b.clone()],
-		Amount::from_sat(premine_amount_sat),
-	);
+	// Start out with an unencrypted store.
+	setup_builder!(builder, config.node_config);
+	builder.set_chain_source_esplora(esplora_url.clone(), Some(sync_config));
+	let node = builder.build_with_fs_store().unwrap();
+	node.start().unwrap();
+	let expected_node_id = node.node_id();
+	let description =
+		Bolt11InvoiceDescription::Direct(Description::new("encrypted".to_string()).unwrap());
+	let invoice = node.bolt11_payment().receive(100_000, &description.into(), 3600).unwrap();
+	let payment_id = PaymentId(invoice.payment_hash().to_byte_array());
+	let expected_payment = node.payment(&payment_id).unwrap();
+	node.stop().unwrap();
+	drop(node);
 
-	node_a.sync_wallets().unwrap();
-	node_b.sync_wallets().unwrap();
-	assert_eq!(node_a.list_balances().spendable_onchain_balance_sats, premine_amount_sat);
-	assert_eq!(node_b.list_balances().spendable_onchain_balance_sats, premine_amount_sat);
+	// Enabling encryption migrates the existing store.
+	let mut encrypted_config = config.node_config.clone();
+	encrypted_config.encrypt_storage = true;
+	setup_builder!(builder, encrypted_config);
+	builder.set_chain_source_esplora(esplora_url.clone(), Some(sync_config));
+	let node = builder.build_with_fs_store().unwrap();
+	node.start().unwrap();
+	assert_eq!(node.node_id(), expected_node_id);
+	assert_eq!(node.payment(&payment_id), Some(expected_payment.clone()));
+	node.stop().unwrap();
+	drop(node);
 
-	let node_a_payments = node_a.list_payments();
-	let node_b_payments = node_b.list_payments();
-	for payments in [&node_a_payments, &node_b_payments] {
-		assert_eq!(payments.len(), 1)
-	}
-	for p in [node_a_payments.first().unwrap(), node_b_payments.first().unwrap()] {
-		assert_eq!(p.amount_msat, Some(premine_amount_sat * 1000));
-		assert_eq!(p.direction, PaymentDirection::Inbound);
-		// We got only 1-conf here, so we're only pending for now.
-		assert_eq!(p.status, PaymentStatus::Pending);
-		match p.kind {
-			PaymentKind::Onchain { status, .. } => {
-				assert!(matches!(status, ConfirmationStatus::Confirmed { .. }));
-			},
-			_ => panic!("Unexpected payment kind"),
-		}
-	}
+	// Building with a different key, or without encryption, fails loudly.
+	let mut wrong_key_config = config.node_config.clone();
+	wrong_key_config.encrypt_storage_with = Some([42u8; 32]);
+	setup_builder!(builder, wrong_key_config);
+	builder.set_chain_source_esplora(esplora_url.clone(), Some(sync_config));
+	assert_eq!(builder.build_with_fs_store().err(), Some(BuildError::InvalidStorageEncryptionKey));
 
-	let channel_amount_sat = 1_000_000;
-	let reserve_amount_sat = 25_000;
-	open_channel(&node_b, &node_a, channel_amount_sat, true, &electrsd);
-	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	setup_builder!(builder, config.node_config);
+	builder.set_chain_source_esplora(esplora_url.clone(), Some(sync_config));
+	assert_eq!(builder.build_with_fs_store().err(), Some(BuildError::InvalidStorageEncryptionKey));
 
-	node_a.sync_wallets().unwrap();
-	node_b.sync_wallets().unwrap();
-	expect_channel_ready_event!(node_a, node_b.node_id());
-	expect_channel_ready_event!(node_b, node_a.node_id());
+	// With the right key, the encrypted store is read just fine.
+	setup_builder!(builder, encrypted_config);
+	builder.set_chain_source_esplora(esplora_url.clone(), Some(sync_config));
+	let node = builder.build_with_fs_store().unwrap();
+	node.start().unwrap();
+	assert_eq!(node.node_id(), expected_node_id);
+	assert_eq!(node.payment(&payment_id), Some(expected_payment.clone()));
+	node.stop().unwrap();
+}
 
-	let node_a_payments =
-		node_a.list_payments_with_filter(|p| matches!(p.kind, PaymentKind::Onchain { .. }));
-	assert_eq!(node_a_payments.len(), 1);
-	let node_b_payments =
-		node_b.list_payments_with_filter(|p| matches!(p.kind, PaymentKind::Onchain { .. }));
-	assert_eq!(node_b_payments.len(), 2);
+#[test]
+fn migrate_fs_store_to_custom_sqlite_path() {
+	let (_bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let config = random_config(true);
 
-	let onchain_fee_buffer_sat = 1000;
-	let expected_node_a_balance = premine_amount_sat - reserve_amount_sat;
-	let expected_node_b_balance_lower =
-		premine_amount_sat - channel_amount_sat - reserve_amount_sat - onchain_fee_buffer_sat;
-	let expected_node_b_balance_upper =
+	let esplora_url = format!("http://{}", electrsd.esplora_url.as_ref().unwrap());
+	let sync_config = EsploraSyncConfig { background_sync_config: None };
+
+	setup_builder!(builder, config.node_config);
+	builder.set_chain_source_esplora(esplora_url.clone(), Some(sync_config));
+	let node = builder.build_with_fs_store().unwrap();
+	node.start().unwrap();
+	let expected_node_id = node.node_id();
+	let description =
+		Bolt11InvoiceDescription::Direct(Description::new("migrated".to_string()).unwrap());
+	let invoice = node.bolt11_payment().receive(100_000, &description.into(), 3600).unwrap();
+	let payment_id = PaymentId(invoice.payment_hash().to_byte_array());
+	let expected_payment = node.payment(&payment_id).unwrap();
+	node.stop().unwrap();
+	drop(node);
+
+	let storage_dir = std::path::PathBuf::from(&config.node_config.storage_dir_path);
+	let db_dir = storage_dir.join("custom_db");
+	let sqlite_store = ldk_node::io::sqlite_store::SqliteStore::new(
+		db_dir.clone(),
+		Some("custom.sqlite".to_string()),
+		Some(ldk_node::io::sqlite_store::KV_TABLE_NAME.to_string()),
+	)
+	.unwrap();
+	sqlite_store.migrate_from_filesystem(storage_dir.join("fs_store")).unwrap();
+	drop(sqlite_store);
+
+	setup_builder!(builder, config.node_config);
+	builder.set_chain_source_esplora(esplora_url.clone(), Some(sync_config));
+	builder.set_storage_sqlite(db_dir.join("custom.sqlite").to_string_lossy().into_owned());
+	let node = builder.build().unwrap();
+	node.start().unwrap();
+	assert_eq!(node.node_id(), expected_node_id);
+	assert_eq!(node.payment(&payment_id), Some(expected_payment));
+	node.stop().unwrap();
+	assert!(!storage_dir.join(ldk_node::io::sqlite_store::SQLITE_DB_FILE_NAME).exists());
+}
+
+#[test]
+fn storage_encryption_refuses_unlistable_custom_store() {
+	let (_bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let config = random_config(true);
+
+	let esplora_url = format!("http://{}", electrsd.esplora_url.as_ref().unwrap());
+	let sync_config = EsploraSyncConfig { background_sync_config: None };
+
+	let test_sync_store: Arc<DynStore> =
+		Arc::new(TestSyncStore::new(config.node_config.storage_dir_path.clone().into()));
+
+	setup_builder!(builder, config.node_config);
+	builder.set_chain_source_esplora(esplora_url.clone(), Some(sync_config));
+	let node = builder.build_with_store(Arc::clone(&test_sync_store)).unwrap();
+	node.start().unwrap();
+	node.stop().unwrap();
+	drop(node);
+
+	// As we can't list the keys of custom stores, we can't migrate them.
+	let mut encrypted_config = config.node_config.clone();
+	encrypted_config.encrypt_storage = true;
+	setup_builder!(builder, encrypted_config);
+	builder.set_chain_source_esplora(esplora_url.clone(), Some(sync_config));
+	assert_eq!(
+		builder.build_with_store(Arc::clone(&test_sync_store)).err(),
+		Some(BuildError::StorageEncryptionMigrationFailed)
+	);
+}
+
+#[test]
+fn reset_scorer() {
+	let (_bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+	let mut config = random_config(false);
+	config.node_config.scoring_params.base_penalty_msat = 500;
+	let node = setup_node(&chain_source, config, None);
+
+	// Resetting the scorer persists a fresh one, which we're able to export.
+	node.reset_scorer().unwrap();
+	let exported_scores = node.export_pathfinding_scores().unwrap();
+	assert!(!exported_scores.is_empty());
+
+	node.stop().unwrap();
+}
+
+#[test]
+fn splice_channel() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+	let (node_a, node_b) = setup_two_nodes(&chain_source, false, true, false);
+
+	let addr_a = node_a.onchain_payment().new_address().unwrap();
+	let premine_amount_sat = 5_000_000;
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![addr_a],
+		Amount::from_sat(premine_amount_sat),
+	);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+
+	let channel_amount_sat = 1_000_000;
+	let funding_txo = open_channel(&node_a, &node_b, channel_amount_sat, false, &electrsd);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	let user_channel_id_a = expect_channel_ready_event!(node_a, node_b.node_id());
+	let user_channel_id_b = expect_channel_ready_event!(node_b, node_a.node_id());
+
+	let expect_channel_spliced = |expected_capacity_sat: Option<u64>| -> u64 {
+		let mut new_capacities_sat = Vec::new();
+		for (node, counterparty, expected_user_channel_id) in
+			[(&node_a, &node_b, user_channel_id_a), (&node_b, &node_a, user_channel_id_b)]
+		{
+			match node.wait_next_event() {
+				ref e @ Event::ChannelSpliced {
+					user_channel_id,
+					counterparty_node_id,
+					new_capacity_sats,
+					..
+				} => {
+					println!("{} got event {:?}", node.node_id(), e);
+					assert_eq!(user_channel_id, expected_user_channel_id);
+					assert_eq!(counterparty_node_id, counterparty.node_id());
+					if let Some(expected_capacity_sat) = expected_capacity_sat {
+						assert_eq!(new_capacity_sats, expected_capacity_sat);
+					}
+					assert_eq!(node.list_channels()[0].channel_value_sats, new_capacity_sats);
+					node.event_handled().unwrap();
+					new_capacities_sat.push(new_capacity_sats);
+				},
+				ref e => panic!("{} got unexpected event!: {:?}", node.node_id(), e),
+			}
+		}
+		assert_eq!(new_capacities_sat[0], new_capacities_sat[1]);
+		new_capacities_sat[0]
+	};
+
+	// Splice in funds from node A's on-chain wallet.
+	let splice_in_amount_sat = 500_000;
+	node_a.splice_in(&user_channel_id_a, node_b.node_id(), splice_in_amount_sat).unwrap();
+	wait_for_outpoint_spend(&electrsd.client, funding_txo);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+
+	let capacity_sat = expect_channel_spliced(Some(channel_amount_sat + splice_in_amount_sat));
+	assert!(
+		node_a.list_balances().spendable_onchain_balance_sats
+			< premine_amount_sat - channel_amount_sat - splice_in_amount_sat
+	);
+
+	// Splice out funds to node B's on-chain wallet, paying the fees from the channel balance.
+	let addr_b = node_b.onchain_payment().new_address().unwrap();
+	let splice_out_amount_sat = 200_000;
+	let spliced_funding_txo = node_a.list_channels()[0].funding_txo.unwrap();
+	assert_ne!(spliced_funding_txo, funding_txo);
+	assert_eq!(
+		Err(NodeError::InsufficientFunds),
+		node_a.splice_out(&user_channel_id_a, node_b.node_id(), capacity_sat + 1, &addr_b)
+	);
+	node_a
+		.splice_out(&user_channel_id_a, node_b.node_id(), splice_out_amount_sat, &addr_b)
+		.unwrap();
+	wait_for_outpoint_spend(&electrsd.client, spliced_funding_txo);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+
+	let new_capacity_sat = expect_channel_spliced(None);
+	assert!(new_capacity_sat < capacity_sat - splice_out_amount_sat);
+	assert_eq!(node_b.list_balances().total_onchain_balance_sats, splice_out_amount_sat);
+}
+
+#[test]
+fn send_using_route() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+	let (node_a, node_b) = setup_two_nodes(&chain_source, false, true, false);
+
+	let addr_a = node_a.onchain_payment().new_address().unwrap();
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![addr_a],
+		Amount::from_sat(2_000_000),
+	);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+
+	open_channel(&node_a, &node_b, 1_000_000, false, &electrsd);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	expect_channel_ready_event!(node_a, node_b.node_id());
+	expect_channel_ready_event!(node_b, node_a.node_id());
+	assert_eq!(node_a.list_peers().first().unwrap().total_channels, 1);
+
+	let invoice_description =
+		Bolt11InvoiceDescription::Direct(Description::new(String::from("asdf")).unwrap());
+	let amount_msat = 2_500_000;
+	let invoice =
+		node_b.bolt11_payment().receive(amount_msat, &invoice_description.into(), 3600).unwrap();
+
+	let short_channel_id = node_a.list_channels()[0].short_channel_id.unwrap();
+	let hop = RouteHop {
+		node_id: node_b.node_id(),
+		short_channel_id,
+		fee_msat: amount_msat,
+		cltv_expiry_delta: invoice.min_final_cltv_expiry_delta() as u32,
+	};
+
+	// Routes need to be non-empty, start with one of our channels, and deliver the invoice amount
+	// to the payee with sufficient final CLTV expiry delta.
+	let invalid_routes = [
+		Route { hops: vec![] },
+		Route { hops: vec![RouteHop { short_channel_id: short_channel_id + 1, ..hop.clone() }] },
+		Route { hops: vec![RouteHop { node_id: node_a.node_id(), ..hop.clone() }] },
+		Route { hops: vec![RouteHop { fee_msat: amount_msat + 1, ..hop.clone() }] },
+		Route {
+			hops: vec![RouteHop { cltv_expiry_delta: hop.cltv_expiry_delta - 1, ..hop.clone() }],
+		},
+	];
+	for route in invalid_routes {
+		assert_eq!(
+			Err(NodeError::InvalidRoute),
+			node_a.bolt11_payment().send_using_route(&invoice, route)
+		);
+	}
+
+	let payment_id =
+		node_a.bolt11_payment().send_using_route(&invoice, Route { hops: vec![hop] }).unwrap();
+	expect_payment_successful_event!(node_a, Some(payment_id), Some(Some(0)));
+	expect_payment_received_event!(node_b, amount_msat);
+	assert_eq!(node_a.payment(&payment_id).unwrap().status, PaymentStatus::Succeeded);
+}
+
+#[test]
+fn find_route() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+	let (node_a, node_b) = setup_two_nodes(&chain_source, false, true, false);
+
+	let addr_a = node_a.onchain_payment().new_address().unwrap();
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![addr_a],
+		Amount::from_sat(2_000_000),
+	);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+
+	open_channel(&node_a, &node_b, 1_000_000, false, &electrsd);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	expect_channel_ready_event!(node_a, node_b.node_id());
+	expect_channel_ready_event!(node_b, node_a.node_id());
+
+	let amount_msat = 2_500_000;
+	let short_channel_id = node_a.list_channels()[0].short_channel_id.unwrap();
+	let route = node_a.find_route(node_b.node_id(), amount_msat, None).unwrap();
+	assert_eq!(route.hops.len(), 1);
+	assert_eq!(route.hops[0].node_id, node_b.node_id());
+	assert_eq!(route.hops[0].short_channel_id, short_channel_id);
+	assert_eq!(route.hops[0].fee_msat, amount_msat);
+	assert!(route.hops[0].cltv_expiry_delta >= MIN_FINAL_CLTV_EXPIRY_DELTA as u32);
+
+	// Giving route parameters without a fee cap still finds the direct route.
+	let route_parameters = RouteParametersConfig {
+		max_total_routing_fee_msat: None,
+		..RouteParametersConfig::default()
+	};
+	assert_eq!(
+		node_a.find_route(node_b.node_id(), amount_msat, Some(route_parameters)).unwrap(),
+		route
+	);
+
+	// We can't route more than our channel's capacity.
+	assert_eq!(
+		Err(NodeError::RouteNotFound),
+		node_a.find_route(node_b.node_id(), 1_000_000_000, None)
+	);
+
+	// The found route can be paid as-is via a matching invoice.
+	let invoice_description =
+		Bolt11InvoiceDescription::Direct(Description::new(String::from("asdf")).unwrap());
+	let invoice =
+		node_b.bolt11_payment().receive(amount_msat, &invoice_description.into(), 3600).unwrap();
+	let mut route = route;
+	route.hops[0].cltv_expiry_delta =
+		route.hops[0].cltv_expiry_delta.max(invoice.min_final_cltv_expiry_delta() as u32);
+	let payment_id = node_a.bolt11_payment().send_using_route(&invoice, route).unwrap();
+	expect_payment_successful_event!(node_a, Some(payment_id), Some(Some(0)));
+	expect_payment_received_event!(node_b, amount_msat);
+}
+
+#[test]
+fn estimate_fee_without_sending() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+	let (node_a, node_b) = setup_two_nodes(&chain_source, false, true, false);
+	let node_c = setup_node(&chain_source, random_config(true), None);
+
+	let addr_a = node_a.onchain_payment().new_address().unwrap();
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![addr_a],
+		Amount::from_sat(2_000_000),
+	);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+
+	open_channel(&node_a, &node_b, 1_000_000, false, &electrsd);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	expect_channel_ready_event!(node_a, node_b.node_id());
+	expect_channel_ready_event!(node_b, node_a.node_id());
+
+	let invoice_description =
+		Bolt11InvoiceDescription::Direct(Description::new(String::from("asdf")).unwrap());
+	let amount_msat = 2_500_000;
+	let invoice = node_b
+		.bolt11_payment()
+		.receive(amount_msat, &invoice_description.clone().into(), 3600)
+		.unwrap();
+
+	// Paying our direct counterparty doesn't incur any routing fees.
+	let expected_estimate = FeeEstimate { fee_msat: 0, path_count: 1, max_path_length: 1 };
+	assert_eq!(node_a.bolt11_payment().estimate_fee(&invoice).unwrap(), expected_estimate);
+	assert_eq!(
+		node_a.spontaneous_payment().estimate_fee(amount_msat, node_b.node_id()).unwrap(),
+		expected_estimate
+	);
+
+	// Estimating the fee doesn't send anything.
+	assert!(node_a
+		.list_payments_with_filter(|p| !matches!(p.kind, PaymentKind::Onchain { .. }))
+		.is_empty());
+	assert_eq!(node_a.next_event(), None);
+	assert_eq!(node_b.next_event(), None);
+
+	// We can't estimate the fee of paying a node we have no route to.
+	let unreachable_invoice =
+		node_c.bolt11_payment().receive(amount_msat, &invoice_description.into(), 3600).unwrap();
+	assert_eq!(
+		Err(NodeError::RouteNotFound),
+		node_a.bolt11_payment().estimate_fee(&unreachable_invoice)
+	);
+	assert_eq!(
+		Err(NodeError::RouteNotFound),
+		node_a.spontaneous_payment().estimate_fee(amount_msat, node_c.node_id())
+	);
+}
+
+#[test]
+fn intercepted_htlcs() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+
+	let config_a = random_config(true);
+	let node_a = setup_node(&chain_source, config_a, None);
+	let mut config_b = random_config(true);
+	config_b.node_config.accept_intercept_htlcs = true;
+	let node_b = setup_node(&chain_source, config_b, None);
+	let config_c = random_config(true);
+	let node_c = setup_node(&chain_source, config_c, None);
+
+	let addr_a = node_a.onchain_payment().new_address().unwrap();
+	let addr_b = node_b.onchain_payment().new_address().unwrap();
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![addr_a, addr_b],
+		Amount::from_sat(2_000_000),
+	);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+
+	open_channel(&node_a, &node_b, 1_000_000, false, &electrsd);
+	open_channel(&node_b, &node_c, 1_000_000, false, &electrsd);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	node_c.sync_wallets().unwrap();
+	expect_channel_ready_event!(node_a, node_b.node_id());
+	expect_channel_ready_event!(node_b, node_a.node_id());
+	expect_channel_ready_event!(node_b, node_c.node_id());
+	expect_channel_ready_event!(node_c, node_b.node_id());
+
+	// Node A pays node C via an intercept SCID of node B, as if it was provided in a route hint.
+	let intercept_scid = node_b.new_intercept_scid();
+	let channel_ab = node_a.list_channels()[0].short_channel_id.unwrap();
+	let channel_bc = node_b
+		.list_channels()
+		.into_iter()
+		.find(|c| c.counterparty_node_id == node_c.node_id())
+		.unwrap()
+		.channel_id;
+
+	let invoice_description =
+		Bolt11InvoiceDescription::Direct(Description::new(String::from("asdf")).unwrap());
+	let amount_msat = 2_500_000;
+	let send_via_intercept_scid = || {
+		let invoice = node_c
+			.bolt11_payment()
+			.receive(amount_msat, &invoice_description.clone().into(), 3600)
+			.unwrap();
+		let hops = vec![
+			RouteHop {
+				node_id: node_b.node_id(),
+				short_channel_id: channel_ab,
+				fee_msat: 1_000,
+				cltv_expiry_delta: 144,
+			},
+			RouteHop {
+				node_id: node_c.node_id(),
+				short_channel_id: intercept_scid,
+				fee_msat: amount_msat,
+				cltv_expiry_delta: invoice.min_final_cltv_expiry_delta() as u32,
+			},
+		];
+		let payment_id =
+			node_a.bolt11_payment().send_using_route(&invoice, Route { hops }).unwrap();
+		let intercept_id = match node_b.wait_next_event() {
+			Event::HTLCIntercepted {
+				intercept_id,
+				requested_next_hop_scid,
+				amount_msat: intercepted_amount_msat,
+				expiry,
+				..
+			} => {
+				assert_eq!(requested_next_hop_scid, intercept_scid);
+				assert_eq!(intercepted_amount_msat, amount_msat);
+				assert!(expiry.is_some());
+				node_b.event_handled().unwrap();
+				intercept_id
+			},
+			e => panic!("Unexpected event: {:?}", e),
+		};
+		(payment_id, intercept_id)
+	};
+
+	// Failing the intercepted HTLC fails the payment.
+	let (payment_id, intercept_id) = send_via_intercept_scid();
+	node_b.fail_intercepted_htlc(&intercept_id).unwrap();
+	assert_eq!(Err(NodeError::InvalidInterceptId), node_b.fail_intercepted_htlc(&intercept_id));
+	expect_event!(node_a, PaymentFailed);
+	assert_eq!(node_a.payment(&payment_id).unwrap().status, PaymentStatus::Failed);
+
+	// Forwarding it over the channel to node C has the payment succeed.
+	let (payment_id, intercept_id) = send_via_intercept_scid();
+	node_b.forward_intercepted_htlc(&intercept_id, &channel_bc, amount_msat).unwrap();
+	expect_payment_received_event!(node_c, amount_msat);
+	expect_payment_successful_event!(node_a, Some(payment_id), Some(Some(1_000)));
+	expect_event!(node_b, PaymentForwarded);
+}
+
+#[test]
+fn close_all_channels() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+
+	let node_a = setup_node(&chain_source, random_config(true), None);
+	let node_b = setup_node(&chain_source, random_config(true), None);
+	let node_c = setup_node(&chain_source, random_config(true), None);
+
+	let addr_a = node_a.onchain_payment().new_address().unwrap();
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![addr_a],
+		Amount::from_sat(5_000_000),
+	);
+	node_a.sync_wallets().unwrap();
+
+	open_channel(&node_a, &node_b, 1_000_000, false, &electrsd);
+	node_a.sync_wallets().unwrap();
+	open_channel(&node_a, &node_c, 1_000_000, false, &electrsd);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	node_c.sync_wallets().unwrap();
+	expect_channel_ready_event!(node_a, node_b.node_id());
+	expect_channel_ready_event!(node_a, node_c.node_id());
+	expect_channel_ready_event!(node_b, node_a.node_id());
+	expect_channel_ready_event!(node_c, node_a.node_id());
+
+	let find_channel_id = |counterparty_node_id: PublicKey| {
+		node_a
+			.list_channels()
+			.into_iter()
+			.find(|c| c.counterparty_node_id == counterparty_node_id)
+			.unwrap()
+			.channel_id
+	};
+	let channel_id_ab = find_channel_id(node_b.node_id());
+	let channel_id_ac = find_channel_id(node_c.node_id());
+
+	// As node C is offline, the channel to it can't be closed cooperatively.
+	node_c.stop().unwrap();
+
+	let summary = node_a.close_all_channels(Duration::from_secs(10)).unwrap();
+	assert_eq!(summary.cooperatively_closed_channels, vec![channel_id_ab]);
+	assert_eq!(summary.force_closed_channels, vec![channel_id_ac]);
+	assert!(summary.pending_channels.is_empty());
+	assert!(node_a.list_channels().is_empty());
+	expect_event!(node_b, ChannelClosed);
+
+	node_a.stop().unwrap();
+	node_b.stop().unwrap();
+}
+
+#[test]
+fn rebalance_channel() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+	let (node_a, node_b) = setup_two_nodes(&chain_source, false, true, false);
+
+	let addr_a = node_a.onchain_payment().new_address().unwrap();
+	let addr_b = node_b.onchain_payment().new_address().unwrap();
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![addr_a, addr_b],
+		Amount::from_sat(2_000_000),
+	);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+
+	// Open one channel in each direction, so that node A has outbound liquidity on the first and
+	// inbound liquidity on the second.
+	open_channel(&node_a, &node_b, 1_000_000, false, &electrsd);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	let from_user_channel_id = expect_channel_ready_event!(node_a, node_b.node_id());
+	expect_channel_ready_event!(node_b, node_a.node_id());
+
+	open_channel(&node_b, &node_a, 1_000_000, false, &electrsd);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	let to_user_channel_id = expect_channel_ready_event!(node_a, node_b.node_id());
+	expect_channel_ready_event!(node_b, node_a.node_id());
+
+	let outbound_capacity_msat = |user_channel_id| {
+		node_a
+			.list_channels()
+			.into_iter()
+			.find(|c| c.user_channel_id == user_channel_id)
+			.unwrap()
+			.outbound_capacity_msat
+	};
+	let from_capacity_before_msat = outbound_capacity_msat(from_user_channel_id);
+	let to_capacity_before_msat = outbound_capacity_msat(to_user_channel_id);
+
+	// Node B charges its default base fee for forwarding the payment back to us.
+	let amount_msat = 100_000_000;
+	let expected_fee_msat = 1000;
+	assert_eq!(
+		Err(NodeError::InvalidChannelId),
+		node_a.rebalance_channel(&from_user_channel_id, &from_user_channel_id, amount_msat, 10_000)
+	);
+	assert_eq!(
+		Err(NodeError::RouteNotFound),
+		node_a.rebalance_channel(
+			&from_user_channel_id,
+			&to_user_channel_id,
+			amount_msat,
+			expected_fee_msat - 1
+		)
+	);
+
+	let payment_id = node_a
+		.rebalance_channel(&from_user_channel_id, &to_user_channel_id, amount_msat, 10_000)
+		.unwrap();
+	expect_event!(node_b, PaymentForwarded);
+	expect_payment_successful_event!(node_a, Some(payment_id), Some(Some(expected_fee_msat)));
+
+	let payment = node_a.payment(&payment_id).unwrap();
+	assert_eq!(payment.status, PaymentStatus::Succeeded);
+	assert_eq!(payment.amount_msat, Some(amount_msat));
+	match payment.kind {
+		PaymentKind::Rebalance { from_user_channel_id: from, to_user_channel_id: to, .. } => {
+			assert_eq!(from, from_user_channel_id);
+			assert_eq!(to, to_user_channel_id);
+		},
+		_ => panic!("Unexpected payment kind"),
+	}
+	assert!(
+		outbound_capacity_msat(from_user_channel_id)
+			<= from_capacity_before_msat - amount_msat - expected_fee_msat
+	);
+	assert!(outbound_capacity_msat(to_user_channel_id) >= to_capacity_before_msat + amount_msat);
+}
+
+#[test]
+fn onchain_send_receive() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+	let (node_a, node_b) = setup_two_nodes(&chain_source, false, true, false);
+
+	let addr_a = node_a.onchain_payment().new_address().unwrap();
+	let addr_b = node_b.onchain_payment().new_address().unwrap();
+	// This is a Bitcoin Testnet address. Sending funds to this address from the Regtest network will fail
+	let static_address = "tb1q0d40e5rta4fty63z64gztf8c3v20cvet6v2jdh";
+	let unchecked_address = Address::<NetworkUnchecked>::from_str(static_address).unwrap();
+	let addr_c = unchecked_address.assume_checked();
+
+	let premine_amount_sat = 1_100_000;
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![addr_a.clone(), addr_b.clone()],
+		Amount::from_sat(premine_amount_sat),
+	);
+
+	let sync_report_a = node_a.sync_wallets().unwrap();
+	assert_eq!(sync_report_a.updated_onchain_transactions, 1);
+	assert_eq!(sync_report_a.updated_onchain_outputs, 1);
+	assert_eq!(
+		sync_report_a.best_block_height,
+		bitcoind.client.get_blockchain_info().unwrap().blocks as u32
+	);
+	node_b.sync_wallets().unwrap();
+	assert_eq!(node_a.list_balances().spendable_onchain_balance_sats, premine_amount_sat);
+	assert_eq!(node_b.list_balances().spendable_onchain_balance_sats, premine_amount_sat);
+
+	// Syncing again without any chain activity doesn't report any updates.
+	let sync_report_a = node_a.sync_wallets().unwrap();
+	assert_eq!(sync_report_a.updated_onchain_transactions, 0);
+	assert_eq!(sync_report_a.updated_onchain_outputs, 0);
+
+	let node_a_payments = node_a.list_payments();
+	let node_b_payments = node_b.list_payments();
+	for payments in [&node_a_payments, &node_b_payments] {
+		assert_eq!(payments.len(), 1)
+	}
+	for p in [node_a_payments.first().unwrap(), node_b_payments.first().unwrap()] {
+		assert_eq!(p.amount_msat, Some(premine_amount_sat * 1000));
+		assert_eq!(p.direction, PaymentDirection::Inbound);
+		// We got only 1-conf here, so we're only pending for now.
+		assert_eq!(p.status, PaymentStatus::Pending);
+		match p.kind {
+			PaymentKind::Onchain { status, .. } => {
+				assert!(matches!(status, ConfirmationStatus::Confirmed { .. }));
+			},
+			_ => panic!("Unexpected payment kind"),
+		}
+	}
+
+	let channel_amount_sat = 1_000_000;
+	let reserve_amount_sat = 25_000;
+	open_channel(&node_b, &node_a, channel_amount_sat, true, &electrsd);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	expect_channel_ready_event!(node_a, node_b.node_id());
+	expect_channel_ready_event!(node_b, node_a.node_id());
+
+	let node_a_payments =
+		node_a.list_payments_with_filter(|p| matches!(p.kind, PaymentKind::Onchain { .. }));
+	assert_eq!(node_a_payments.len(), 1);
+	let node_b_payments =
+		node_b.list_payments_with_filter(|p| matches!(p.kind, PaymentKind::Onchain { .. }));
+	assert_eq!(node_b_payments.len(), 2);
+
+	let onchain_fee_buffer_sat = 1000;
+	let expected_node_a_balance = premine_amount_sat - reserve_amount_sat;
+	let expected_node_b_balance_lower =
+		premine_amount_sat - channel_amount_sat - reserve_amount_sat - onchain_fee_buffer_sat;
+	let expected_node_b_balance_upper =
 		premine_amount_sat - channel_amount_sat - reserve_amount_sat;
 	assert_eq!(node_a.list_balances().spendable_onchain_balance_sats, expected_node_a_balance);
 	assert!(node_b.list_balances().spendable_onchain_balance_sats > expected_node_b_balance_lower);
 	assert!(node_b.list_balances().spendable_onchain_balance_sats < expected_node_b_balance_upper);
 
 	assert_eq!(
-		Err(NodeError::InsufficientFunds),
-		node_a.onchain_payment().send_to_address(&addr_b, expected_node_a_balance + 1, None)
+		Err(NodeError::InsufficientFunds),
+		node_a.onchain_payment().send_to_address(&addr_b, expected_node_a_balance + 1, None)
+	);
+
+	assert_eq!(
+		Err(NodeError::InvalidAddress),
+		node_a.onchain_payment().send_to_address(&addr_c, expected_node_a_balance + 1, None)
+	);
+
+	assert_eq!(
+		Err(NodeError::InvalidAddress),
+		node_a.onchain_payment().send_all_to_address(&addr_c, true, None)
+	);
+
+	let amount_to_send_sats = 54321;
+	let txid =
+		node_b.onchain_payment().send_to_address(&addr_a, amount_to_send_sats, None).unwrap();
+	wait_for_tx(&electrsd.client, txid);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+
+	let payment_id = PaymentId(txid.to_byte_array());
+	let payment_a = node_a.payment(&payment_id).unwrap();
+	assert_eq!(payment_a.status, PaymentStatus::Pending);
+	match payment_a.kind {
+		PaymentKind::Onchain { status, .. } => {
+			assert!(matches!(status, ConfirmationStatus::Unconfirmed));
+		},
+		_ => panic!("Unexpected payment kind"),
+	}
+	assert!(payment_a.fee_paid_msat > Some(0));
+	let payment_b = node_b.payment(&payment_id).unwrap();
+	assert_eq!(payment_b.status, PaymentStatus::Pending);
+	match payment_a.kind {
+		PaymentKind::Onchain { status, .. } => {
+			assert!(matches!(status, ConfirmationStatus::Unconfirmed));
+		},
+		_ => panic!("Unexpected payment kind"),
+	}
+	assert!(payment_b.fee_paid_msat > Some(0));
+	assert_eq!(payment_a.amount_msat, Some(amount_to_send_sats * 1000));
+	assert_eq!(payment_a.amount_msat, payment_b.amount_msat);
+	assert_eq!(payment_a.fee_paid_msat, payment_b.fee_paid_msat);
+
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+
+	let expected_node_a_balance = expected_node_a_balance + amount_to_send_sats;
+	let expected_node_b_balance_lower = expected_node_b_balance_lower - amount_to_send_sats;
+	let expected_node_b_balance_upper = expected_node_b_balance_upper - amount_to_send_sats;
+	assert_eq!(node_a.list_balances().spendable_onchain_balance_sats, expected_node_a_balance);
+	assert!(node_b.list_balances().spendable_onchain_balance_sats > expected_node_b_balance_lower);
+	assert!(node_b.list_balances().spendable_onchain_balance_sats < expected_node_b_balance_upper);
+
+	let node_a_payments =
+		node_a.list_payments_with_filter(|p| matches!(p.kind, PaymentKind::Onchain { .. }));
+	assert_eq!(node_a_payments.len(), 2);
+	let node_b_payments =
+		node_b.list_payments_with_filter(|p| matches!(p.kind, PaymentKind::Onchain { .. }));
+	assert_eq!(node_b_payments.len(), 3);
+
+	let payment_a = node_a.payment(&payment_id).unwrap();
+	match payment_a.kind {
+		PaymentKind::Onchain { txid: _txid, status } => {
+			assert_eq!(_txid, txid);
+			assert!(matches!(status, ConfirmationStatus::Confirmed { .. }));
+		},
+		_ => panic!("Unexpected payment kind"),
+	}
+
+	let payment_b = node_a.payment(&payment_id).unwrap();
+	match payment_b.kind {
+		PaymentKind::Onchain { txid: _txid, status } => {
+			assert_eq!(_txid, txid);
+			assert!(matches!(status, ConfirmationStatus::Confirmed { .. }));
+		},
+		_ => panic!("Unexpected payment kind"),
+	}
+
+	let addr_b = node_b.onchain_payment().new_address().unwrap();
+	let txid = node_a.onchain_payment().send_all_to_address(&addr_b, true, None).unwrap();
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	wait_for_tx(&electrsd.client, txid);
+
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+
+	let expected_node_b_balance_lower = expected_node_b_balance_lower + expected_node_a_balance;
+	let expected_node_b_balance_upper = expected_node_b_balance_upper + expected_node_a_balance;
+	let expected_node_a_balance = 0;
+	assert_eq!(node_a.list_balances().spendable_onchain_balance_sats, expected_node_a_balance);
+	assert_eq!(node_a.list_balances().total_onchain_balance_sats, reserve_amount_sat);
+	assert!(node_b.list_balances().spendable_onchain_balance_sats > expected_node_b_balance_lower);
+	assert!(node_b.list_balances().spendable_onchain_balance_sats < expected_node_b_balance_upper);
+
+	let node_a_payments =
+		node_a.list_payments_with_filter(|p| matches!(p.kind, PaymentKind::Onchain { .. }));
+	assert_eq!(node_a_payments.len(), 3);
+	let node_b_payments =
+		node_b.list_payments_with_filter(|p| matches!(p.kind, PaymentKind::Onchain { .. }));
+	assert_eq!(node_b_payments.len(), 4);
+
+	let addr_b = node_b.onchain_payment().new_address().unwrap();
+	let txid = node_a.onchain_payment().send_all_to_address(&addr_b, false, None).unwrap();
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	wait_for_tx(&electrsd.client, txid);
+
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+
+	let expected_node_b_balance_lower = expected_node_b_balance_lower + reserve_amount_sat;
+	let expected_node_b_balance_upper = expected_node_b_balance_upper + reserve_amount_sat;
+	let expected_node_a_balance = 0;
+
+	assert_eq!(node_a.list_balances().spendable_onchain_balance_sats, expected_node_a_balance);
+	assert_eq!(node_a.list_balances().total_onchain_balance_sats, expected_node_a_balance);
+	assert!(node_b.list_balances().spendable_onchain_balance_sats > expected_node_b_balance_lower);
+	assert!(node_b.list_balances().spendable_onchain_balance_sats < expected_node_b_balance_upper);
+
+	let node_a_payments =
+		node_a.list_payments_with_filter(|p| matches!(p.kind, PaymentKind::Onchain { .. }));
+	assert_eq!(node_a_payments.len(), 4);
+	let node_b_payments =
+		node_b.list_payments_with_filter(|p| matches!(p.kind, PaymentKind::Onchain { .. }));
+	assert_eq!(node_b_payments.len(), 5);
+}
+
+#[test]
+fn onchain_send_all_retains_reserve() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+	let (node_a, node_b) = setup_two_nodes(&chain_source, false, true, false);
+
+	// Setup nodes
+	let addr_a = node_a.onchain_payment().new_address().unwrap();
+	let addr_b = node_b.onchain_payment().new_address().unwrap();
+
+	let premine_amount_sat = 1_000_000;
+	let reserve_amount_sat = 25_000;
+	let onchain_fee_buffer_sat = 1000;
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![addr_a.clone(), addr_b.clone()],
+		Amount::from_sat(premine_amount_sat),
+	);
+
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	assert_eq!(node_a.list_balances().spendable_onchain_balance_sats, premine_amount_sat);
+	assert_eq!(node_b.list_balances().spendable_onchain_balance_sats, premine_amount_sat);
+
+	// Send all over, with 0 reserve as we don't have any channels open.
+	let txid = node_a.onchain_payment().send_all_to_address(&addr_b, true, None).unwrap();
+
+	wait_for_tx(&electrsd.client, txid);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	// Check node a sent all and node b received it
+	assert_eq!(node_a.list_balances().spendable_onchain_balance_sats, 0);
+	assert!(((premine_amount_sat * 2 - onchain_fee_buffer_sat)..=(premine_amount_sat * 2))
+		.contains(&node_b.list_balances().spendable_onchain_balance_sats));
+
+	// Refill to make sure we have enough reserve for the channel open.
+	let txid = bitcoind
+		.client
+		.send_to_address(&addr_a, Amount::from_sat(reserve_amount_sat))
+		.unwrap()
+		.0
+		.parse()
+		.unwrap();
+	wait_for_tx(&electrsd.client, txid);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	assert_eq!(node_a.list_balances().spendable_onchain_balance_sats, reserve_amount_sat);
+
+	// Open a channel.
+	open_channel(&node_b, &node_a, premine_amount_sat, false, &electrsd);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	expect_channel_ready_event!(node_a, node_b.node_id());
+	expect_channel_ready_event!(node_b, node_a.node_id());
+
+	// Check node a sent all and node b received it
+	assert_eq!(node_a.list_balances().spendable_onchain_balance_sats, 0);
+	assert_eq!(node_a.spendable_onchain_balance_sats(), 0);
+	assert_eq!(node_a.list_balances().total_anchor_channels_reserve_sats, reserve_amount_sat);
+	assert_eq!(node_a.list_balances().spendable_anchor_reserve_sats, reserve_amount_sat);
+	assert!(((premine_amount_sat - reserve_amount_sat - onchain_fee_buffer_sat)
+		..=premine_amount_sat)
+		.contains(&node_b.list_balances().spendable_onchain_balance_sats));
+	assert_eq!(
+		node_b.spendable_onchain_balance_sats(),
+		node_b.list_balances().spendable_onchain_balance_sats
+	);
+
+	// Send all over again, this time ensuring the reserve is accounted for
+	let txid = node_b.onchain_payment().send_all_to_address(&addr_a, true, None).unwrap();
+
+	wait_for_tx(&electrsd.client, txid);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+
+	// Check node b sent all and node a received it
+	assert_eq!(node_b.list_balances().total_onchain_balance_sats, reserve_amount_sat);
+	assert_eq!(node_b.list_balances().spendable_onchain_balance_sats, 0);
+	assert!(((premine_amount_sat - reserve_amount_sat - onchain_fee_buffer_sat)
+		..=premine_amount_sat)
+		.contains(&node_a.list_balances().spendable_onchain_balance_sats));
+}
+
+#[test]
+fn onchain_send_to_many() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+	let (node_a, node_b) = setup_two_nodes(&chain_source, false, true, false);
+
+	let addr_a = node_a.onchain_payment().new_address().unwrap();
+	let addr_b_1 = node_b.onchain_payment().new_address().unwrap();
+	let addr_b_2 = node_b.onchain_payment().new_address().unwrap();
+
+	let premine_amount_sat = 1_000_000;
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![addr_a.clone()],
+		Amount::from_sat(premine_amount_sat),
+	);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	assert_eq!(node_a.list_balances().spendable_onchain_balance_sats, premine_amount_sat);
+
+	// Sending without any outputs fails.
+	assert_eq!(
+		Err(NodeError::InvalidAmount),
+		node_a.onchain_payment().estimate_send_to_many_fee(vec![], None)
 	);
+	assert_eq!(Err(NodeError::InvalidAmount), node_a.onchain_payment().send_to_many(vec![], None));
 
+	// Sending more than we have fails as a whole, even if some outputs could be paid.
+	let too_large_outputs =
+		vec![(addr_b_1.clone(), 100_000), (addr_b_2.clone(), premine_amount_sat)];
 	assert_eq!(
-		Err(NodeError::InvalidAddress),
-		node_a.onchain_payment().send_to_address(&addr_c, expected_node_a_balance + 1, None)
+		Err(NodeError::InsufficientFunds),
+		node_a.onchain_payment().estimate_send_to_many_fee(too_large_outputs.clone(), None)
+	);
+	assert_eq!(
+		Err(NodeError::InsufficientFunds),
+		node_a.onchain_payment().send_to_many(too_large_outputs, None)
 	);
+	assert_eq!(node_a.list_balances().spendable_onchain_balance_sats, premine_amount_sat);
+
+	let amount_1_sat = 100_000;
+	let amount_2_sat = 200_000;
+	let outputs = vec![(addr_b_1.clone(), amount_1_sat), (addr_b_2.clone(), amount_2_sat)];
+	let estimated_fee_sat =
+		node_a.onchain_payment().estimate_send_to_many_fee(outputs.clone(), None).unwrap();
+	assert!(estimated_fee_sat > 0);
+
+	let txid = node_a.onchain_payment().send_to_many(outputs, None).unwrap();
+	wait_for_tx(&electrsd.client, txid);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
 
 	assert_eq!(
-		Err(NodeError::InvalidAddress),
-		node_a.onchain_payment().send_all_to_address(&addr_c, true, None)
+		node_a.list_balances().spendable_onchain_balance_sats,
+		premine_amount_sat - amount_1_sat - amount_2_sat - estimated_fee_sat
 	);
+	assert_eq!(node_b.list_balances().spendable_onchain_balance_sats, amount_1_sat + amount_2_sat);
 
-	let amount_to_send_sats = 54321;
-	let txid =
-		node_b.onchain_payment().send_to_address(&addr_a, amount_to_send_sats, None).unwrap();
+	let payment_a = node_a.payment(&PaymentId(txid.to_byte_array())).unwrap();
+	assert_eq!(payment_a.direction, PaymentDirection::Outbound);
+	assert_eq!(payment_a.fee_paid_msat, Some(estimated_fee_sat * 1000));
+}
+
+#[test]
+fn onchain_bump_fee() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+	let (node_a, node_b) = setup_two_nodes(&chain_source, false, true, false);
+
+	let mut config_c = random_config(true);
+	config_c.node_config.enable_rbf_by_default = false;
+	let node_c = setup_node(&chain_source, config_c, None);
+
+	let addr_a = node_a.onchain_payment().new_address().unwrap();
+	let addr_b = node_b.onchain_payment().new_address().unwrap();
+	let addr_c = node_c.onchain_payment().new_address().unwrap();
+
+	let premine_amount_sat = 1_000_000;
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![addr_a.clone(), addr_c.clone()],
+		Amount::from_sat(premine_amount_sat),
+	);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	node_c.sync_wallets().unwrap();
+
+	let amount_sat = 100_000;
+	let original_fee_rate = FeeRate::from_sat_per_vb(2).unwrap();
+	let txid = node_a
+		.onchain_payment()
+		.send_to_address(&addr_b, amount_sat, Some(original_fee_rate))
+		.unwrap();
 	wait_for_tx(&electrsd.client, txid);
 	node_a.sync_wallets().unwrap();
+
+	// Bumping requires a strictly higher fee rate.
+	assert_eq!(
+		Err(NodeError::InvalidFeeRate),
+		node_a.onchain_payment().bump_fee(txid, original_fee_rate)
+	);
+
+	// Unknown transactions can't be bumped.
+	assert_eq!(
+		Err(NodeError::NotReplaceable),
+		node_a.onchain_payment().bump_fee(Txid::all_zeros(), FeeRate::from_sat_per_vb(10).unwrap())
+	);
+
+	let new_fee_rate = FeeRate::from_sat_per_vb(10).unwrap();
+	let new_txid = node_a.onchain_payment().bump_fee(txid, new_fee_rate).unwrap();
+	assert_ne!(new_txid, txid);
+	wait_for_tx(&electrsd.client, new_txid);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	node_a.sync_wallets().unwrap();
 	node_b.sync_wallets().unwrap();
 
-	let payment_id = PaymentId(txid.to_byte_array());
-	let payment_a = node_a.payment(&payment_id).unwrap();
-	assert_eq!(payment_a.status, PaymentStatus::Pending);
-	match payment_a.kind {
-		PaymentKind::Onchain { status, .. } => {
-			assert!(matches!(status, ConfirmationStatus::Unconfirmed));
-		},
-		_ => panic!("Unexpected payment kind"),
-	}
-	assert!(payment_a.fee_paid_msat > Some(0));
-	let payment_b = node_b.payment(&payment_id).unwrap();
-	assert_eq!(payment_b.status, PaymentStatus::Pending);
-	match payment_a.kind {
-		PaymentKind::Onchain { status, .. } => {
-			assert!(matches!(status, ConfirmationStatus::Unconfirmed));
-		},
-		_ => panic!("Unexpected payment kind"),
-	}
-	assert!(payment_b.fee_paid_msat > Some(0));
-	assert_eq!(payment_a.amount_msat, Some(amount_to_send_sats * 1000));
-	assert_eq!(payment_a.amount_msat, payment_b.amount_msat);
-	assert_eq!(payment_a.fee_paid_msat, payment_b.fee_paid_msat);
+	assert_eq!(node_b.list_balances().spendable_onchain_balance_sats, amount_sat);
+
+	let original_payment = node_a.payment(&PaymentId(txid.to_byte_array())).unwrap();
+	assert_eq!(original_payment.status, PaymentStatus::Failed);
+	let replacement_payment = node_a.payment(&PaymentId(new_txid.to_byte_array())).unwrap();
+	assert_eq!(replacement_payment.status, PaymentStatus::Succeeded);
+	assert_eq!(replacement_payment.amount_msat, Some(amount_sat * 1000));
+	let replacement_fee_sat = replacement_payment.fee_paid_msat.unwrap() / 1000;
+	assert_eq!(
+		node_a.list_balances().spendable_onchain_balance_sats,
+		premine_amount_sat - amount_sat - replacement_fee_sat
+	);
+
+	// Confirmed transactions can't be bumped.
+	assert_eq!(
+		Err(NodeError::NotReplaceable),
+		node_a.onchain_payment().bump_fee(new_txid, FeeRate::from_sat_per_vb(20).unwrap())
+	);
+
+	// Transactions not signalling RBF can't be bumped.
+	let txid_c = node_c.onchain_payment().send_to_address(&addr_b, amount_sat, None).unwrap();
+	wait_for_tx(&electrsd.client, txid_c);
+	node_c.sync_wallets().unwrap();
+	assert_eq!(
+		Err(NodeError::NotReplaceable),
+		node_c.onchain_payment().bump_fee(txid_c, new_fee_rate)
+	);
+}
+
+#[test]
+fn bump_anchor_channel_fee() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+	let (node_a, node_b) = setup_two_nodes(&chain_source, false, true, false);
+
+	let addr_a = node_a.onchain_payment().new_address().unwrap();
+	let addr_b = node_b.onchain_payment().new_address().unwrap();
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![addr_a, addr_b],
+		Amount::from_sat(2_100_000),
+	);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
 
+	let funding_txo = open_channel(&node_a, &node_b, 1_000_000, false, &electrsd);
 	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
 	node_a.sync_wallets().unwrap();
 	node_b.sync_wallets().unwrap();
+	expect_channel_ready_event!(node_a, node_b.node_id());
+	expect_channel_ready_event!(node_b, node_a.node_id());
 
-	let expected_node_a_balance = expected_node_a_balance + amount_to_send_sats;
-	let expected_node_b_balance_lower = expected_node_b_balance_lower - amount_to_send_sats;
-	let expected_node_b_balance_upper = expected_node_b_balance_upper - amount_to_send_sats;
-	assert_eq!(node_a.list_balances().spendable_onchain_balance_sats, expected_node_a_balance);
-	assert!(node_b.list_balances().spendable_onchain_balance_sats > expected_node_b_balance_lower);
-	assert!(node_b.list_balances().spendable_onchain_balance_sats < expected_node_b_balance_upper);
+	let channel = node_a.list_channels().first().unwrap().clone();
+	let target_fee_rate = FeeRate::from_sat_per_vb(50).unwrap();
 
-	let node_a_payments =
-		node_a.list_payments_with_filter(|p| matches!(p.kind, PaymentKind::Onchain { .. }));
-	assert_eq!(node_a_payments.len(), 2);
-	let node_b_payments =
-		node_b.list_payments_with_filter(|p| matches!(p.kind, PaymentKind::Onchain { .. }));
-	assert_eq!(node_b_payments.len(), 3);
+	// There's nothing to bump as long as the channel is open.
+	assert_eq!(
+		Err(NodeError::InvalidChannelId),
+		node_a.bump_anchor_channel_fee(&channel.channel_id, target_fee_rate)
+	);
 
-	let payment_a = node_a.payment(&payment_id).unwrap();
-	match payment_a.kind {
-		PaymentKind::Onchain { txid: _txid, status } => {
-			assert_eq!(_txid, txid);
-			assert!(matches!(status, ConfirmationStatus::Confirmed { .. }));
+	node_a.force_close_channel(&channel.user_channel_id, node_b.node_id(), None).unwrap();
+	expect_event!(node_a, ChannelClosed);
+	expect_event!(node_b, ChannelClosed);
+	wait_for_outpoint_spend(&electrsd.client, funding_txo);
+
+	let child_txid = node_a.bump_anchor_channel_fee(&channel.channel_id, target_fee_rate).unwrap();
+	wait_for_tx(&electrsd.client, child_txid);
+
+	// We need to target a higher fee rate than before.
+	assert_eq!(
+		Err(NodeError::InvalidFeeRate),
+		node_a.bump_anchor_channel_fee(&channel.channel_id, target_fee_rate)
+	);
+
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 1);
+	node_a.sync_wallets().unwrap();
+
+	// Once the commitment transaction confirmed, it can't be bumped anymore.
+	assert_eq!(
+		Err(NodeError::NotReplaceable),
+		node_a.bump_anchor_channel_fee(&channel.channel_id, FeeRate::from_sat_per_vb(100).unwrap())
+	);
+}
+
+#[test]
+fn sweep_to_destination() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+	premine_blocks(&bitcoind.client, &electrsd.client);
+
+	let destination = bitcoind.client.call::<serde_json::Value>("getnewaddress", &[]).unwrap();
+	let destination = Address::<NetworkUnchecked>::from_str(destination.as_str().unwrap())
+		.unwrap()
+		.assume_checked();
+
+	let mut config_a = random_config(true);
+	config_a.node_config.spendable_output_sweep_destination = Some(destination.clone());
+	let node_a = setup_node(&chain_source, config_a, None);
+	let node_b = setup_node(&chain_source, random_config(true), None);
+
+	// Sweep destinations need to be valid for the configured network.
+	let mut invalid_config = random_config(true);
+	invalid_config.node_config.network = bitcoin::Network::Bitcoin;
+	invalid_config.node_config.spendable_output_sweep_destination = Some(destination.clone());
+	setup_builder!(builder, invalid_config.node_config);
+	assert_eq!(builder.build().err(), Some(BuildError::InvalidSweepDestination));
+
+	let addr_a = node_a.onchain_payment().new_address().unwrap();
+	distribute_funds_unconfirmed(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![addr_a],
+		Amount::from_sat(2_100_000),
+	);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 1);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+
+	let funding_txo = open_channel(&node_a, &node_b, 1_000_000, false, &electrsd);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	expect_channel_ready_event!(node_a, node_b.node_id());
+	expect_channel_ready_event!(node_b, node_a.node_id());
+
+	let channel = node_a.list_channels().first().unwrap().clone();
+	node_a.force_close_channel(&channel.user_channel_id, node_b.node_id(), None).unwrap();
+	expect_event!(node_a, ChannelClosed);
+	expect_event!(node_b, ChannelClosed);
+	wait_for_outpoint_spend(&electrsd.client, funding_txo);
+
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 1);
+	node_a.sync_wallets().unwrap();
+
+	// Wait for our delayed output to become spendable.
+	match node_a.list_balances().lightning_balances[0] {
+		LightningBalance::ClaimableAwaitingConfirmations { confirmation_height, .. } => {
+			let cur_height = node_a.status().current_best_block.height;
+			let blocks_to_go = confirmation_height - cur_height;
+			generate_blocks_and_wait(&bitcoind.client, &electrsd.client, blocks_to_go as usize);
+			node_a.sync_wallets().unwrap();
 		},
-		_ => panic!("Unexpected payment kind"),
+		ref balance => panic!("Unexpected balance state: {:?}", balance),
 	}
 
-	let payment_b = node_a.payment(&payment_id).unwrap();
-	match payment_b.kind {
-		PaymentKind::Onchain { txid: _txid, status } => {
-			assert_eq!(_txid, txid);
-			assert!(matches!(status, ConfirmationStatus::Confirmed { .. }));
+	let sweep_txid = match node_a.list_balances().pending_balances_from_channel_closures[0] {
+		PendingSweepBalance::BroadcastAwaitingConfirmation { latest_spending_txid, .. } => {
+			latest_spending_txid
 		},
-		_ => panic!("Unexpected payment kind"),
+		ref balance => panic!("Unexpected balance state: {:?}", balance),
+	};
+	wait_for_tx(&electrsd.client, sweep_txid);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 1);
+	node_a.sync_wallets().unwrap();
+
+	match node_a.wait_next_event() {
+		Event::SpendableOutputsSwept { txid, amount_sat, destination: swept_to } => {
+			assert_eq!(txid, sweep_txid);
+			assert!(amount_sat > 0);
+			assert_eq!(swept_to, destination);
+			node_a.event_handled().unwrap();
+		},
+		e => panic!("Unexpected event: {:?}", e),
 	}
 
-	let addr_b = node_b.onchain_payment().new_address().unwrap();
-	let txid = node_a.onchain_payment().send_all_to_address(&addr_b, true, None).unwrap();
-	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
-	wait_for_tx(&electrsd.client, txid);
+	// The swept funds didn't go to our on-chain wallet.
+	let sweep_tx = electrsd.client.transaction_get(&sweep_txid).unwrap();
+	assert_eq!(sweep_tx.output.len(), 1);
+	assert_eq!(sweep_tx.output[0].script_pubkey, destination.script_pubkey());
+}
 
-	node_a.sync_wallets().unwrap();
-	node_b.sync_wallets().unwrap();
+#[test]
+fn batched_sweep_of_spendable_outputs() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+	premine_blocks(&bitcoind.client, &electrsd.client);
 
-	let expected_node_b_balance_lower = expected_node_b_balance_lower + expected_node_a_balance;
-	let expected_node_b_balance_upper = expected_node_b_balance_upper + expected_node_a_balance;
-	let expected_node_a_balance = 0;
-	assert_eq!(node_a.list_balances().spendable_onchain_balance_sats, expected_node_a_balance);
-	assert_eq!(node_a.list_balances().total_onchain_balance_sats, reserve_amount_sat);
-	assert!(node_b.list_balances().spendable_onchain_balance_sats > expected_node_b_balance_lower);
-	assert!(node_b.list_balances().spendable_onchain_balance_sats < expected_node_b_balance_upper);
+	let mut config_a = random_config(true);
+	config_a.node_config.spendable_output_sweep_config =
+		Some(SpendableOutputSweepConfig { batch_window_secs: 5, ..Default::default() });
+	let node_a = setup_node(&chain_source, config_a, None);
+	let node_b = setup_node(&chain_source, random_config(true), None);
 
-	let node_a_payments =
-		node_a.list_payments_with_filter(|p| matches!(p.kind, PaymentKind::Onchain { .. }));
-	assert_eq!(node_a_payments.len(), 3);
-	let node_b_payments =
-		node_b.list_payments_with_filter(|p| matches!(p.kind, PaymentKind::Onchain { .. }));
-	assert_eq!(node_b_payments.len(), 4);
+	let addr_a_1 = node_a.onchain_payment().new_address().unwrap();
+	let addr_a_2 = node_a.onchain_payment().new_address().unwrap();
+	distribute_funds_unconfirmed(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![addr_a_1, addr_a_2],
+		Amount::from_sat(1_100_000),
+	);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 1);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
 
-	let addr_b = node_b.onchain_payment().new_address().unwrap();
-	let txid = node_a.onchain_payment().send_all_to_address(&addr_b, false, None).unwrap();
+	let funding_txo_1 = open_channel(&node_a, &node_b, 500_000, false, &electrsd);
+	let funding_txo_2 = open_channel(&node_a, &node_b, 500_000, false, &electrsd);
 	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
-	wait_for_tx(&electrsd.client, txid);
-
 	node_a.sync_wallets().unwrap();
 	node_b.sync_wallets().unwrap();
+	for _ in 0..2 {
+		expect_channel_ready_event!(node_a, node_b.node_id());
+		expect_channel_ready_event!(node_b, node_a.node_id());
+	}
 
-	let expected_node_b_balance_lower = expected_node_b_balance_lower + reserve_amount_sat;
-	let expected_node_b_balance_upper = expected_node_b_balance_upper + reserve_amount_sat;
-	let expected_node_a_balance = 0;
+	for channel in node_a.list_channels() {
+		node_a.force_close_channel(&channel.user_channel_id, node_b.node_id(), None).unwrap();
+		expect_event!(node_a, ChannelClosed);
+		expect_event!(node_b, ChannelClosed);
+	}
+	wait_for_outpoint_spend(&electrsd.client, funding_txo_1);
+	wait_for_outpoint_spend(&electrsd.client, funding_txo_2);
 
-	assert_eq!(node_a.list_balances().spendable_onchain_balance_sats, expected_node_a_balance);
-	assert_eq!(node_a.list_balances().total_onchain_balance_sats, expected_node_a_balance);
-	assert!(node_b.list_balances().spendable_onchain_balance_sats > expected_node_b_balance_lower);
-	assert!(node_b.list_balances().spendable_onchain_balance_sats < expected_node_b_balance_upper);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 1);
+	node_a.sync_wallets().unwrap();
+	assert!(node_a.pending_sweeps().is_empty());
+
+	// Wait for both of our delayed outputs to become spendable.
+	let maturity_height = node_a
+		.list_balances()
+		.lightning_balances
+		.iter()
+		.map(|balance| match balance {
+			LightningBalance::ClaimableAwaitingConfirmations { confirmation_height, .. } => {
+				*confirmation_height
+			},
+			balance => panic!("Unexpected balance state: {:?}", balance),
+		})
+		.max()
+		.unwrap();
+	let cur_height = node_a.status().current_best_block.height;
+	let blocks_to_go = maturity_height - cur_height;
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, blocks_to_go as usize);
+	node_a.sync_wallets().unwrap();
 
-	let node_a_payments =
-		node_a.list_payments_with_filter(|p| matches!(p.kind, PaymentKind::Onchain { .. }));
-	assert_eq!(node_a_payments.len(), 4);
-	let node_b_payments =
-		node_b.list_payments_with_filter(|p| matches!(p.kind, PaymentKind::Onchain { .. }));
-	assert_eq!(node_b_payments.len(), 5);
+	let pending_sweeps = node_a.pending_sweeps();
+	assert_eq!(pending_sweeps.len(), 2);
+	assert!(pending_sweeps.iter().all(|sweep| sweep.maturity_height.is_none()));
+
+	// Both outputs are swept in a single transaction once the batch window elapsed.
+	let mut pending_sweeps = node_a.pending_sweeps();
+	for _ in 0..60 {
+		if pending_sweeps.iter().all(|sweep| sweep.latest_spending_txid.is_some()) {
+			break;
+		}
+		std::thread::sleep(Duration::from_secs(1));
+		pending_sweeps = node_a.pending_sweeps();
+	}
+	let sweep_txids = pending_sweeps
+		.iter()
+		.filter_map(|sweep| sweep.latest_spending_txid)
+		.collect::<HashSet<_>>();
+	assert_eq!(sweep_txids.len(), 1);
+	let sweep_txid = *sweep_txids.iter().next().unwrap();
+	wait_for_tx(&electrsd.client, sweep_txid);
+	let sweep_tx = electrsd.client.transaction_get(&sweep_txid).unwrap();
+	assert_eq!(sweep_tx.input.len(), 2);
+
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 1);
+	node_a.sync_wallets().unwrap();
+	assert!(node_a.pending_sweeps().is_empty());
 }
 
 #[test]
-fn onchain_send_all_retains_reserve() {
+fn onchain_send_with_coin_control() {
 	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
 	let chain_source = TestChainSource::Esplora(&electrsd);
 	let (node_a, node_b) = setup_two_nodes(&chain_source, false, true, false);
 
-	// Setup nodes
-	let addr_a = node_a.onchain_payment().new_address().unwrap();
-	let addr_b = node_b.onchain_payment().new_address().unwrap();
-
-	let premine_amount_sat = 1_000_000;
-	let reserve_amount_sat = 25_000;
-	let onchain_fee_buffer_sat = 1000;
+	let addr_a_1 = node_a.onchain_payment().new_address().unwrap();
+	let addr_a_2 = node_a.onchain_payment().new_address().unwrap();
+	let addr_b = node_b.onchain_payment().new_address().unwrap();
+
+	let premine_amount_sat = 500_000;
 	premine_and_distribute_funds(
 		&bitcoind.client,
 		&electrsd.client,
-		vec![addr_a.clone(), addr_b.clone()],
+		vec![addr_a_1.clone(), addr_a_2.clone()],
 		Amount::from_sat(premine_amount_sat),
 	);
-
 	node_a.sync_wallets().unwrap();
 	node_b.sync_wallets().unwrap();
-	assert_eq!(node_a.list_balances().spendable_onchain_balance_sats, premine_amount_sat);
-	assert_eq!(node_b.list_balances().spendable_onchain_balance_sats, premine_amount_sat);
-
-	// Send all over, with 0 reserve as we don't have any channels open.
-	let txid = node_a.onchain_payment().send_all_to_address(&addr_b, true, None).unwrap();
 
-	wait_for_tx(&electrsd.client, txid);
-	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	// We don't have any channels open, so none of the outputs are reserved.
+	let utxos = node_a.onchain_payment().list_spendable_outputs();
+	assert_eq!(utxos.len(), 2);
+	assert!(utxos.iter().all(|u| u.value_sats == premine_amount_sat));
+	assert!(utxos.iter().all(|u| u.confirmations > 0));
+	assert!(utxos.iter().all(|u| !u.is_reserved_for_anchors));
+	let spent_utxo = utxos[0].outpoint;
+	let kept_utxo = utxos[1].outpoint;
 
-	node_a.sync_wallets().unwrap();
-	node_b.sync_wallets().unwrap();
-	// Check node a sent all and node b received it
-	assert_eq!(node_a.list_balances().spendable_onchain_balance_sats, 0);
-	assert!(((premine_amount_sat * 2 - onchain_fee_buffer_sat)..=(premine_amount_sat * 2))
-		.contains(&node_b.list_balances().spendable_onchain_balance_sats));
+	// A single one of the outputs doesn't cover the amount.
+	assert_eq!(
+		Err(NodeError::InsufficientFunds),
+		node_a.onchain_payment().send_to_address_with_utxos(
+			&addr_b,
+			premine_amount_sat,
+			vec![spent_utxo],
+			None
+		)
+	);
 
-	// Refill to make sure we have enough reserve for the channel open.
-	let txid = bitcoind
-		.client
-		.send_to_address(&addr_a, Amount::from_sat(reserve_amount_sat))
-		.unwrap()
-		.0
-		.parse()
+	let amount_to_send_sats = 100_000;
+	let txid = node_a
+		.onchain_payment()
+		.send_to_address_with_utxos(&addr_b, amount_to_send_sats, vec![spent_utxo], None)
 		.unwrap();
 	wait_for_tx(&electrsd.client, txid);
 	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
-	node_a.sync_wallets().unwrap();
-	node_b.sync_wallets().unwrap();
-	assert_eq!(node_a.list_balances().spendable_onchain_balance_sats, reserve_amount_sat);
-
-	// Open a channel.
-	open_channel(&node_b, &node_a, premine_amount_sat, false, &electrsd);
-	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
-	node_a.sync_wallets().unwrap();
-	node_b.sync_wallets().unwrap();
-	expect_channel_ready_event!(node_a, node_b.node_id());
-	expect_channel_ready_event!(node_b, node_a.node_id());
-
-	// Check node a sent all and node b received it
-	assert_eq!(node_a.list_balances().spendable_onchain_balance_sats, 0);
-	assert!(((premine_amount_sat - reserve_amount_sat - onchain_fee_buffer_sat)
-		..=premine_amount_sat)
-		.contains(&node_b.list_balances().spendable_onchain_balance_sats));
-
-	// Send all over again, this time ensuring the reserve is accounted for
-	let txid = node_b.onchain_payment().send_all_to_address(&addr_a, true, None).unwrap();
-
-	wait_for_tx(&electrsd.client, txid);
-	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
-
 	node_a.sync_wallets().unwrap();
 	node_b.sync_wallets().unwrap();
 
-	// Check node b sent all and node a received it
-	assert_eq!(node_b.list_balances().total_onchain_balance_sats, reserve_amount_sat);
-	assert_eq!(node_b.list_balances().spendable_onchain_balance_sats, 0);
-	assert!(((premine_amount_sat - reserve_amount_sat - onchain_fee_buffer_sat)
-		..=premine_amount_sat)
-		.contains(&node_a.list_balances().spendable_onchain_balance_sats));
+	// Only the selected output was spent, the change was returned to us.
+	let utxos = node_a.onchain_payment().list_spendable_outputs();
+	assert_eq!(utxos.len(), 2);
+	assert!(utxos.iter().all(|u| u.outpoint != spent_utxo));
+	assert!(utxos.iter().any(|u| u.outpoint == kept_utxo));
+	assert!(utxos.iter().any(|u| u.outpoint.txid == txid));
+	assert_eq!(node_b.list_balances().spendable_onchain_balance_sats, amount_to_send_sats);
 }
 
 #[test]
@@ -667,6 +2200,89 @@ fn onchain_wallet_recovery() {
 	);
 }
 
+#[test]
+fn static_channel_backup_recovery() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+
+	let seed_bytes = vec![23u8; 64];
+	let node_a = setup_node(&chain_source, random_config(true), Some(seed_bytes.clone()));
+	let node_b = setup_node(&chain_source, random_config(true), None);
+
+	let addr_a = node_a.onchain_payment().new_address().unwrap();
+	let addr_b = node_b.onchain_payment().new_address().unwrap();
+	let premine_amount_sat = 2_125_000;
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![addr_a, addr_b],
+		Amount::from_sat(premine_amount_sat),
+	);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+
+	let funding_amount_sat = 1_000_000;
+	let funding_txo = open_channel(&node_a, &node_b, funding_amount_sat, false, &electrsd);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	expect_channel_ready_event!(node_a, node_b.node_id());
+	expect_channel_ready_event!(node_b, node_a.node_id());
+
+	let static_channel_backup = node_a.export_static_channel_backup();
+
+	node_a.stop().unwrap();
+	drop(node_a);
+
+	// Recovering with a different seed fails.
+	let other_config = random_config(true);
+	setup_builder!(other_builder, other_config.node_config);
+	other_builder.recover_from_static_channel_backup(static_channel_backup.clone(), false);
+	assert_eq!(other_builder.build().err(), Some(BuildError::InvalidStaticChannelBackup));
+
+	// Now we start from scratch, only the seed and the static channel backup remain.
+	let mut recovered_config = random_config(true);
+	recovered_config.static_channel_backup = Some(static_channel_backup);
+	let recovered_node = setup_node(&chain_source, recovered_config, Some(seed_bytes));
+	assert!(recovered_node.list_channels().is_empty());
+
+	// As the backup is the most recent one, we broadcast the latest commitment transaction upon
+	// recovery.
+	wait_for_outpoint_spend(&electrsd.client, funding_txo);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 1);
+	recovered_node.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	expect_event!(node_b, ChannelClosed);
+
+	// Check the recovered node sees the channel balance and sweeps it.
+	assert_eq!(recovered_node.list_balances().lightning_balances.len(), 1);
+	match recovered_node.list_balances().lightning_balances[0] {
+		LightningBalance::ClaimableAwaitingConfirmations {
+			counterparty_node_id,
+			funding_txo: balance_funding_txo,
+			confirmation_height,
+			..
+		} => {
+			assert_eq!(counterparty_node_id, node_b.node_id());
+			assert_eq!(balance_funding_txo, funding_txo);
+			let cur_height = recovered_node.status().current_best_block.height;
+			let blocks_to_go = confirmation_height - cur_height;
+			generate_blocks_and_wait(&bitcoind.client, &electrsd.client, blocks_to_go as usize);
+			recovered_node.sync_wallets().unwrap();
+		},
+		_ => panic!("Unexpected balance state!"),
+	}
+
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	recovered_node.sync_wallets().unwrap();
+
+	assert!(recovered_node.list_balances().lightning_balances.is_empty());
+	assert!(
+		recovered_node.list_balances().total_onchain_balance_sats
+			> premine_amount_sat - funding_amount_sat
+	);
+}
+
 #[test]
 fn test_rbf_via_mempool() {
 	run_rbf_test(false);
@@ -802,6 +2418,105 @@ fn run_rbf_test(is_insert_block: bool) {
 	validate_balances!(0, true);
 }
 
+#[test]
+fn send_to_expired_invoice_with_ignore_expiry() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+	let (node_a, node_b) = setup_two_nodes(&chain_source, false, true, false);
+
+	let address_a = node_a.onchain_payment().new_address().unwrap();
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![address_a],
+		Amount::from_sat(5_000_000),
+	);
+	node_a.sync_wallets().unwrap();
+	open_channel(&node_a, &node_b, 4_000_000, true, &electrsd);
+
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	expect_channel_ready_event!(node_a, node_b.node_id());
+	expect_channel_ready_event!(node_b, node_a.node_id());
+
+	let description =
+		Bolt11InvoiceDescription::Direct(Description::new(String::from("asdf")).unwrap());
+	let invoice = node_b.bolt11_payment().receive(100_000, &description.into(), 1).unwrap();
+	std::thread::sleep(Duration::from_secs(2));
+	assert!(invoice.is_expired());
+
+	// Expired invoices are rejected by default.
+	assert_eq!(Err(NodeError::PaymentSendingFailed), node_a.bolt11_payment().send(&invoice, None));
+
+	// With the override, the payment is attempted anyway. Whether it succeeds is up to the payee.
+	let send_params = SendPaymentParams { ignore_expiry: true, ..Default::default() };
+	let payment_id = node_a.bolt11_payment().send(&invoice, Some(send_params)).unwrap();
+	match node_a.wait_next_event() {
+		Event::PaymentSuccessful { payment_id: Some(id), .. }
+		| Event::PaymentFailed { payment_id: Some(id), .. } => assert_eq!(id, payment_id),
+		e => panic!("Unexpected event: {:?}", e),
+	}
+}
+
+#[test]
+fn receive_with_description_hash() {
+	let (_bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let config = random_config(true);
+	let chain_source = TestChainSource::Esplora(&electrsd);
+	let node = setup_node(&chain_source, config, None);
+
+	let metadata = "[[\"text/plain\",\"A description too large to be included directly\"]]";
+	let description_hash = Sha256Hash::hash(metadata.as_bytes());
+	let invoice = node
+		.bolt11_payment()
+		.receive_with_description_hash(100_000, description_hash, 3600)
+		.unwrap();
+
+	// The invoice commits to the hash via the `h` field rather than including the description.
+	match invoice.description() {
+		Bolt11InvoiceDescriptionRef::Hash(hash) => assert_eq!(hash.0, description_hash),
+		Bolt11InvoiceDescriptionRef::Direct(_) => panic!("Unexpected direct description"),
+	}
+	assert_eq!(invoice.amount_milli_satoshis(), Some(100_000));
+
+	node.stop().unwrap();
+}
+
+#[test]
+fn receive_with_configured_min_final_cltv_expiry_delta() {
+	let (_bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let mut config = random_config(true);
+	config.node_config.min_final_cltv_expiry_delta = 80;
+	let chain_source = TestChainSource::Esplora(&electrsd);
+	let node = setup_node(&chain_source, config, None);
+
+	let description =
+		Bolt11InvoiceDescription::Direct(Description::new(String::from("asdf")).unwrap());
+	let invoice =
+		node.bolt11_payment().receive(100_000, &description.clone().into(), 3600).unwrap();
+	assert_eq!(invoice.min_final_cltv_expiry_delta(), 80);
+
+	let invoice = node
+		.bolt11_payment()
+		.receive_with_min_final_cltv_expiry_delta(100_000, &description.clone().into(), 3600, 144)
+		.unwrap();
+	assert_eq!(invoice.min_final_cltv_expiry_delta(), 144);
+
+	// Deltas below LDK's minimum are rejected.
+	assert_eq!(
+		Err(NodeError::InvoiceCreationFailed),
+		node.bolt11_payment().receive_with_min_final_cltv_expiry_delta(
+			100_000,
+			&description.into(),
+			3600,
+			1
+		)
+	);
+
+	node.stop().unwrap();
+}
+
 #[test]
 fn sign_verify_msg() {
 	let (_bitcoind, electrsd) = setup_bitcoind_and_electrsd();
@@ -814,6 +2529,89 @@ fn sign_verify_msg() {
 	let sig = node.sign_message(msg);
 	let pkey = node.node_id();
 	assert!(node.verify_signature(msg, sig.as_str(), &pkey));
+
+	// Signatures made with another key or for another message don't verify.
+	assert!(!node.verify_signature("OK computer?".as_bytes(), sig.as_str(), &pkey));
+
+	// Signatures produced by CLN's `signmessage` verify, too.
+	// Taken from https://github.com/ElementsProject/lightning/blob/1275af6fbb02460c8eb2f00990bb0ef9179ce8f3/tests/test_misc.py#L1925-L1938
+	let cln_vectors = [
+		(
+			"is this compatible?",
+			"rbgfioj114mh48d8egqx8o9qxqw4fmhe8jbeeabdioxnjk8z3t1ma1hu1fiswpakgucwwzwo6ofycffbsqusqdimugbh41n1g698hr9t",
+			"02b80cabdf82638aac86948e4c06e82064f547768dcef977677b9ea931ea75bab5",
+		),
+		(
+			"hi",
+			"rnrphcjswusbacjnmmmrynh9pqip7sy5cx695h6mfu64iac6qmcmsd8xnsyczwmpqp9shqkth3h4jmkgyqu5z47jfn1q7gpxtaqpx4xg",
+			"02de60d194e1ca5947b59fe8e2efd6aadeabfb67f2e89e13ae1a799c1e08e4a43b",
+		),
+	];
+	for (msg, sig, pkey) in cln_vectors {
+		let pkey = PublicKey::from_str(pkey).unwrap();
+		assert!(node.verify_signature(msg.as_bytes(), sig, &pkey));
+		assert!(!node.verify_signature(msg.as_bytes(), sig, &node.node_id()));
+	}
+}
+
+#[test]
+fn send_custom_onion_message() {
+	let (_bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+	let node_a = setup_node(&chain_source, random_config(false), None);
+	let mut config_b = random_config(false);
+	config_b.node_config.accept_custom_onion_messages = true;
+	let node_b = setup_node(&chain_source, config_b, None);
+	let mut config_c = random_config(false);
+	config_c.node_config.accept_custom_onion_messages = true;
+	let node_c = setup_node(&chain_source, config_c, None);
+
+	let node_addr_b = node_b.listening_addresses().unwrap().first().unwrap().clone();
+	let node_addr_c = node_c.listening_addresses().unwrap().first().unwrap().clone();
+	node_a.connect(node_b.node_id(), node_addr_b, false).unwrap();
+	node_b.connect(node_c.node_id(), node_addr_c, false).unwrap();
+
+	// TLV types below the custom range, or used by LDK, e.g., for BOLT12 invoice requests, are
+	// rejected.
+	assert_eq!(
+		node_a.send_onion_message(node_b.node_id(), None, 42, vec![1, 2, 3]),
+		Err(NodeError::InvalidOnionMessage)
+	);
+	assert_eq!(
+		node_a.send_onion_message(node_b.node_id(), None, 64, vec![1, 2, 3]),
+		Err(NodeError::InvalidOnionMessage)
+	);
+
+	// Paths exceeding the maximum length are rejected.
+	let long_path = vec![node_b.node_id(); MAX_ONION_MESSAGE_PATH_INTERMEDIATE_NODES + 1];
+	assert_eq!(
+		node_a.send_onion_message(node_c.node_id(), Some(long_path), 77777, vec![1, 2, 3]),
+		Err(NodeError::InvalidOnionMessage)
+	);
+
+	// Send to our direct peer.
+	node_a.send_onion_message(node_b.node_id(), None, 77777, vec![1, 2, 3]).unwrap();
+	match node_b.wait_next_event() {
+		Event::OnionMessageReceived { tlv_type, data } => {
+			assert_eq!(tlv_type, 77777);
+			assert_eq!(data, vec![1, 2, 3]);
+			node_b.event_handled().unwrap();
+		},
+		e => panic!("Unexpected event: {:?}", e),
+	}
+
+	// Send along the given path via our peer.
+	node_a
+		.send_onion_message(node_c.node_id(), Some(vec![node_b.node_id()]), 77779, vec![4, 5])
+		.unwrap();
+	match node_c.wait_next_event() {
+		Event::OnionMessageReceived { tlv_type, data } => {
+			assert_eq!(tlv_type, 77779);
+			assert_eq!(data, vec![4, 5]);
+			node_c.event_handled().unwrap();
+		},
+		e => panic!("Unexpected event: {:?}", e),
+	}
 }
 
 #[test]
@@ -852,6 +2650,9 @@ fn do_connection_restart_behavior(persist: bool) {
 	assert_eq!(peer_details_a.node_id, node_id_b);
 	assert_eq!(peer_details_a.is_persisted, persist);
 	assert!(peer_details_a.is_connected);
+	assert!(peer_details_a.last_connected_unix_secs.is_some());
+	assert_eq!(peer_details_a.last_disconnected_unix_secs, None);
+	assert_eq!(peer_details_a.total_channels, 0);
 
 	let peer_details_b = node_b.list_peers().first().unwrap().clone();
 	assert_eq!(peer_details_b.node_id, node_id_a);
@@ -872,6 +2673,8 @@ fn do_connection_restart_behavior(persist: bool) {
 		assert_eq!(peer_details_a.node_id, node_id_b);
 		assert_eq!(peer_details_a.is_persisted, persist);
 		assert!(peer_details_a.is_connected);
+		// The connection history of persisted peers survives restarts.
+		assert!(peer_details_a.last_disconnected_unix_secs.is_some());
 
 		let peer_details_b = node_b.list_peers().first().unwrap().clone();
 		assert_eq!(peer_details_b.node_id, node_id_a);
@@ -883,6 +2686,68 @@ fn do_connection_restart_behavior(persist: bool) {
 	}
 }
 
+#[test]
+fn forget_peer_stops_reconnecting() {
+	let (_bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+	let (node_a, node_b) = setup_two_nodes(&chain_source, false, false, false);
+
+	let node_id_b = node_b.node_id();
+	let node_addr_b = node_b.listening_addresses().unwrap().first().unwrap().clone();
+	node_a.connect(node_id_b, node_addr_b, true).unwrap();
+	assert!(node_a.list_peers().first().unwrap().is_persisted);
+
+	// Forgetting the peer doesn't drop the connection.
+	node_a.forget_peer(node_id_b).unwrap();
+	let peer_details_a = node_a.list_peers().first().unwrap().clone();
+	assert_eq!(peer_details_a.node_id, node_id_b);
+	assert!(!peer_details_a.is_persisted);
+	assert!(peer_details_a.is_connected);
+
+	// Restart nodes.
+	node_a.stop().unwrap();
+	node_b.stop().unwrap();
+	node_b.start().unwrap();
+	node_a.start().unwrap();
+
+	// Sleep a bit to give a reconnect the chance to happen.
+	std::thread::sleep(std::time::Duration::from_secs(5));
+
+	assert!(node_a.list_peers().is_empty());
+	assert!(node_b.list_peers().is_empty());
+}
+
+#[test]
+fn peer_reconnection_abandoned_after_max_attempts() {
+	let (_bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+
+	let mut config_a = random_config(true);
+	config_a.node_config.peer_reconnect_policy =
+		PeerReconnectPolicy { initial_backoff_secs: 1, max_backoff_secs: 1, max_attempts: Some(2) };
+	let node_a = setup_node(&chain_source, config_a, None);
+	let node_b = setup_node(&chain_source, random_config(true), None);
+
+	let node_id_b = node_b.node_id();
+	let node_addr_b = node_b.listening_addresses().unwrap().first().unwrap().clone();
+	node_a.connect(node_id_b, node_addr_b, true).unwrap();
+
+	// Once node_b goes offline, node_a gives up on reconnecting after two failed attempts.
+	node_b.stop().unwrap();
+	expect_event!(node_a, PeerConnectionAbandoned);
+	let peer_details_a = node_a.list_peers().first().unwrap().clone();
+	assert_eq!(peer_details_a.node_id, node_id_b);
+	assert!(peer_details_a.is_persisted);
+	assert!(!peer_details_a.is_connected);
+	assert!(peer_details_a.last_connection_failure_reason.is_some());
+
+	// Reconnection policies need to be sane.
+	let mut config = random_config(true);
+	config.node_config.peer_reconnect_policy.max_attempts = Some(0);
+	setup_builder!(builder, config.node_config);
+	assert_eq!(builder.build().err(), Some(BuildError::InvalidPeerReconnectPolicy));
+}
+
 #[test]
 fn concurrent_connections_succeed() {
 	let (_bitcoind, electrsd) = setup_bitcoind_and_electrsd();
@@ -905,9 +2770,72 @@ fn concurrent_connections_succeed() {
 		handles.push(handle);
 	}
 
-	for h in handles {
-		h.join().unwrap();
-	}
+	for h in handles {
+		h.join().unwrap();
+	}
+}
+
+#[test]
+fn connect_with_timeout_reports_result() {
+	let (_bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+	let (node_a, node_b) = setup_two_nodes(&chain_source, false, true, false);
+
+	let node_id_b = node_b.node_id();
+	let node_addr_b = node_b.listening_addresses().unwrap().first().unwrap().clone();
+
+	// Nobody is listening on a fresh random port.
+	let unused_addr = random_listening_addresses().first().unwrap().clone();
+	assert_eq!(
+		node_a.connect_with_timeout(node_id_b, unused_addr, true, 10).unwrap(),
+		ConnectResult::Failed { reason: ConnectionFailureReason::ConnectionRefused }
+	);
+	// Failed attempts don't get persisted.
+	assert!(node_a.list_peers().is_empty());
+
+	assert_eq!(
+		node_a.connect_with_timeout(node_id_b, node_addr_b.clone(), true, 10).unwrap(),
+		ConnectResult::Connected
+	);
+	assert_eq!(
+		node_a.connect_with_timeout(node_id_b, node_addr_b, true, 10).unwrap(),
+		ConnectResult::AlreadyConnected
+	);
+
+	let peer_details_a = node_a.list_peers().first().unwrap().clone();
+	assert_eq!(peer_details_a.node_id, node_id_b);
+	assert!(peer_details_a.is_persisted);
+	assert!(peer_details_a.is_connected);
+	assert!(peer_details_a.last_connection_attempt_timestamp.is_some());
+	assert_eq!(peer_details_a.last_connection_failure_reason, None);
+
+	node_a.stop().unwrap();
+	node_b.stop().unwrap();
+}
+
+#[test]
+fn low_power_mode_keeps_peers_connected() {
+	let (_bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+	let (node_a, node_b) = setup_two_nodes(&chain_source, false, true, false);
+
+	assert_eq!(node_a.power_mode(), PowerMode::Normal);
+	node_a.enter_low_power_mode();
+	assert_eq!(node_a.power_mode(), PowerMode::LowPower);
+
+	// We're still able to connect to peers and manually sync while in low-power mode.
+	let node_id_b = node_b.node_id();
+	let node_addr_b = node_b.listening_addresses().unwrap().first().unwrap().clone();
+	node_a.connect(node_id_b, node_addr_b, false).unwrap();
+	assert!(node_a.list_peers().first().unwrap().is_connected);
+	node_a.sync_wallets().unwrap();
+
+	node_a.exit_low_power_mode();
+	assert_eq!(node_a.power_mode(), PowerMode::Normal);
+	assert!(node_a.list_peers().first().unwrap().is_connected);
+
+	node_a.stop().unwrap();
+	node_b.stop().unwrap();
 }
 
 #[test]
@@ -1087,6 +3015,14 @@ fn simple_bolt12_send_receive() {
 		.unwrap()
 		.id;
 	expect_payment_successful_event!(node_b, Some(node_b_payment_id), None);
+	match node_b.wait_next_event() {
+		Event::RefundPaid { payment_id, amount_msat, .. } => {
+			assert_eq!(payment_id, node_b_payment_id);
+			assert_eq!(amount_msat, overpaid_amount);
+			node_b.event_handled().unwrap();
+		},
+		ref e => panic!("{} got unexpected event!: {:?}", std::stringify!(node_b), e),
+	}
 
 	let node_b_payments = node_b.list_payments_with_filter(|p| {
 		matches!(p.kind, PaymentKind::Bolt12Refund { .. }) && p.id == node_b_payment_id
@@ -1129,6 +3065,404 @@ fn simple_bolt12_send_receive() {
 		},
 	}
 	assert_eq!(node_a_payments.first().unwrap().amount_msat, Some(overpaid_amount));
+
+	// Test send_offer_with_options, validating the quantity and payer note.
+	let offer = node_b.bolt12_payment().receive(offer_amount_msat, "asdf", None, Some(5)).unwrap();
+	assert_eq!(
+		Err(NodeError::InvalidQuantity),
+		node_a.bolt12_payment().send_offer_with_options(&offer, None, Some(6), None, None)
+	);
+	assert_eq!(
+		Err(NodeError::InvalidQuantity),
+		node_a.bolt12_payment().send_offer_with_options(&offer, None, None, None, None)
+	);
+	assert_eq!(
+		Err(NodeError::InvalidPayerNote),
+		node_a.bolt12_payment().send_offer_with_options(
+			&offer,
+			None,
+			Some(2),
+			Some("a".repeat(513)),
+			None
+		)
+	);
+
+	let expected_payer_note = "order-42".to_string();
+	let idempotency_key = Some("order-42".to_string());
+	let payment_id = node_a
+		.bolt12_payment()
+		.send_offer_with_options(
+			&offer,
+			None,
+			Some(2),
+			Some(expected_payer_note.clone()),
+			idempotency_key.clone(),
+		)
+		.unwrap();
+	expect_payment_successful_event!(node_a, Some(payment_id), None);
+	// Retrying with the same idempotency key doesn't send another payment.
+	assert_eq!(
+		Ok(payment_id),
+		node_a.bolt12_payment().send_offer_with_options(
+			&offer,
+			None,
+			Some(2),
+			Some(expected_payer_note.clone()),
+			idempotency_key,
+		)
+	);
+	match node_b.wait_next_event() {
+		ref e @ Event::PaymentReceived { amount_msat, ref payer_note, .. } => {
+			println!("{} got event {:?}", node_b.node_id(), e);
+			assert_eq!(amount_msat, 2 * offer_amount_msat);
+			assert_eq!(payer_note.as_ref().map(|n| n.0.clone()), Some(expected_payer_note));
+			node_b.event_handled().unwrap();
+		},
+		ref e => panic!("{} got unexpected event!: {:?}", node_b.node_id(), e),
+	}
+}
+
+#[test]
+fn recurring_bolt12_payments() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+	let (node_a, node_b) = setup_two_nodes(&chain_source, false, true, false);
+
+	let address_a = node_a.onchain_payment().new_address().unwrap();
+	let premine_amount_sat = 5_000_000;
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![address_a],
+		Amount::from_sat(premine_amount_sat),
+	);
+
+	node_a.sync_wallets().unwrap();
+	open_channel(&node_a, &node_b, 4_000_000, true, &electrsd);
+
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+
+	expect_channel_ready_event!(node_a, node_b.node_id());
+	expect_channel_ready_event!(node_b, node_a.node_id());
+
+	// Sleep until we broadcasted a node announcement.
+	while node_b.status().latest_node_announcement_broadcast_timestamp.is_none() {
+		std::thread::sleep(std::time::Duration::from_millis(10));
+	}
+
+	// Sleep one more sec to make sure the node announcement propagates.
+	std::thread::sleep(std::time::Duration::from_secs(1));
+
+	let offer_amount_msat = 1_000_000;
+	let offer =
+		node_b.bolt12_payment().receive(offer_amount_msat, "subscription", None, None).unwrap();
+
+	// We refuse zero intervals and amounts below the offer amount.
+	assert_eq!(
+		Err(NodeError::InvalidRecurrenceInterval),
+		node_a.schedule_recurring_payment(
+			&offer,
+			offer_amount_msat,
+			RecurrenceInterval::Custom { interval_secs: 0 },
+			None
+		)
+	);
+	assert_eq!(
+		Err(NodeError::InvalidAmount),
+		node_a.schedule_recurring_payment(
+			&offer,
+			offer_amount_msat - 1,
+			RecurrenceInterval::Daily,
+			None
+		)
+	);
+
+	let interval = RecurrenceInterval::Custom { interval_secs: 1 };
+	let recurring_payment_id =
+		node_a.schedule_recurring_payment(&offer, offer_amount_msat, interval, Some(2)).unwrap();
+	let schedules = node_a.list_recurring_payments();
+	assert_eq!(schedules.len(), 1);
+	assert_eq!(schedules[0].id, recurring_payment_id);
+	assert_eq!(schedules[0].offer_id, offer.id());
+	assert_eq!(schedules[0].payments_made, 0);
+
+	let mut payments_successful = 0;
+	let mut payments_executed = 0;
+	while payments_executed < 2 {
+		match node_a.wait_next_event() {
+			Event::PaymentSuccessful { .. } => payments_successful += 1,
+			Event::RecurringPaymentExecuted { recurring_payment_id: id, payments_made, .. } => {
+				assert_eq!(id, recurring_payment_id);
+				payments_executed += 1;
+				assert_eq!(payments_made, payments_executed);
+			},
+			ref e => panic!("{} got unexpected event!: {:?}", node_a.node_id(), e),
+		}
+		node_a.event_handled().unwrap();
+	}
+	assert_eq!(payments_successful, 2);
+
+	expect_payment_received_event!(node_b, offer_amount_msat);
+	expect_payment_received_event!(node_b, offer_amount_msat);
+
+	// The schedule is removed once the maximum number of payments has been made.
+	assert!(node_a.list_recurring_payments().is_empty());
+	assert_eq!(
+		Err(NodeError::InvalidPaymentId),
+		node_a.cancel_recurring_payment(&recurring_payment_id)
+	);
+
+	// Active schedules can be cancelled.
+	let recurring_payment_id = node_a
+		.schedule_recurring_payment(&offer, offer_amount_msat, RecurrenceInterval::Weekly, None)
+		.unwrap();
+	node_a.cancel_recurring_payment(&recurring_payment_id).unwrap();
+	assert!(node_a.list_recurring_payments().is_empty());
+}
+
+#[test]
+fn bolt12_receive_offer_without_blinded_paths() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+	let (node_a, node_b) = setup_two_nodes(&chain_source, false, true, false);
+
+	let address_a = node_a.onchain_payment().new_address().unwrap();
+	let premine_amount_sat = 5_000_000;
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![address_a],
+		Amount::from_sat(premine_amount_sat),
+	);
+
+	node_a.sync_wallets().unwrap();
+	open_channel(&node_a, &node_b, 4_000_000, true, &electrsd);
+
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+
+	expect_channel_ready_event!(node_a, node_b.node_id());
+	expect_channel_ready_event!(node_b, node_a.node_id());
+
+	let absolute_expiry_secs =
+		SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() + 60 * 60;
+	let expected_amount_msat = 100_000_000;
+	let offer = node_b
+		.bolt12_payment()
+		.receive_offer(Some(expected_amount_msat), "asdf", Some(absolute_expiry_secs), false)
+		.unwrap();
+
+	// The offer advertises our node id directly, and round-trips through the parser.
+	assert!(offer.paths().is_empty());
+	assert_eq!(offer.issuer_signing_pubkey(), Some(node_b.node_id()));
+	assert_eq!(offer.absolute_expiry(), Some(Duration::from_secs(absolute_expiry_secs)));
+	assert_eq!(Offer::from_str(&offer.to_string()).unwrap(), offer);
+
+	let payment_id = node_a.bolt12_payment().send(&offer, None, None).unwrap();
+	expect_payment_successful_event!(node_a, Some(payment_id), None);
+	expect_payment_received_event!(node_b, expected_amount_msat);
+
+	// Zero-amount offers work, too.
+	let offer = node_b.bolt12_payment().receive_offer(None, "asdf", None, false).unwrap();
+	assert!(offer.paths().is_empty());
+	assert_eq!(offer.amount(), None);
+	assert_eq!(Offer::from_str(&offer.to_string()).unwrap(), offer);
+}
+
+#[test]
+fn bolt12_request_and_pay_invoice() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+	let mut config_a = random_config(true);
+	config_a.node_config.enable_bolt12_invoice_requests = true;
+	let node_a = setup_node(&chain_source, config_a, None);
+	let node_b = setup_node(&chain_source, random_config(true), None);
+
+	// Requesting invoices needs to be enabled.
+	let offer = node_a.bolt12_payment().receive_offer(None, "asdf", None, false).unwrap();
+	assert_eq!(
+		Err(NodeError::InvoiceRequestsDisabled),
+		node_b.bolt12_payment().request_invoice(&offer, Some(1000), None)
+	);
+
+	let address_a = node_a.onchain_payment().new_address().unwrap();
+	let premine_amount_sat = 5_000_000;
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![address_a],
+		Amount::from_sat(premine_amount_sat),
+	);
+
+	node_a.sync_wallets().unwrap();
+	open_channel(&node_a, &node_b, 4_000_000, true, &electrsd);
+
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+
+	expect_channel_ready_event!(node_a, node_b.node_id());
+	expect_channel_ready_event!(node_b, node_a.node_id());
+
+	let expected_amount_msat = 100_000_000;
+	let offer = node_b
+		.bolt12_payment()
+		.receive_offer(Some(expected_amount_msat), "asdf", None, false)
+		.unwrap();
+
+	// Requesting the invoice doesn't pay it.
+	let invoice = node_a.bolt12_payment().request_invoice(&offer, None, None).unwrap();
+	assert_eq!(invoice.amount_msats(), expected_amount_msat);
+	assert_eq!(invoice.offer_id(), Some(offer.id()));
+	assert!(node_a
+		.list_payments_with_filter(|p| matches!(p.kind, PaymentKind::Bolt12Offer { .. }))
+		.is_empty());
+	assert_eq!(node_a.next_event(), None);
+
+	let payment_id = node_a.bolt12_payment().pay_bolt12_invoice(&invoice).unwrap();
+	expect_payment_successful_event!(node_a, Some(payment_id), None);
+	expect_payment_received_event!(node_b, expected_amount_msat);
+	assert_eq!(node_a.payment(&payment_id).unwrap().amount_msat, Some(expected_amount_msat));
+
+	// The invoice can only be paid once.
+	assert_eq!(
+		Err(NodeError::InvalidInvoice),
+		node_a.bolt12_payment().pay_bolt12_invoice(&invoice)
+	);
+
+	// Requests for expired offers are rejected.
+	let absolute_expiry_secs = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() + 1;
+	let expiring_offer = node_b
+		.bolt12_payment()
+		.receive_offer(Some(expected_amount_msat), "asdf", Some(absolute_expiry_secs), false)
+		.unwrap();
+	std::thread::sleep(Duration::from_secs(2));
+	assert_eq!(
+		Err(NodeError::OfferExpired),
+		node_a.bolt12_payment().request_invoice(&expiring_offer, None, None)
+	);
+}
+
+#[test]
+fn bolt12_create_and_pay_refund() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+	let (node_a, node_b) = setup_two_nodes(&chain_source, false, true, false);
+
+	let address_a = node_a.onchain_payment().new_address().unwrap();
+	let premine_amount_sat = 5_000_000;
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![address_a],
+		Amount::from_sat(premine_amount_sat),
+	);
+
+	node_a.sync_wallets().unwrap();
+	open_channel(&node_a, &node_b, 4_000_000, true, &electrsd);
+
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+
+	expect_channel_ready_event!(node_a, node_b.node_id());
+	expect_channel_ready_event!(node_b, node_a.node_id());
+
+	let now_secs = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+	let refund_amount_msat = 10_000_000;
+
+	// Refunds need to have a non-zero amount and may not be expired already.
+	assert_eq!(
+		Err(NodeError::InvalidAmount),
+		node_a.bolt12_payment().create_refund(0, "refund".to_string(), now_secs + 3600)
+	);
+	assert_eq!(
+		Err(NodeError::InvalidDateTime),
+		node_a.bolt12_payment().create_refund(refund_amount_msat, "refund".to_string(), now_secs)
+	);
+
+	let refund = node_a
+		.bolt12_payment()
+		.create_refund(refund_amount_msat, "refund".to_string(), now_secs + 3600)
+		.unwrap();
+	assert_eq!(refund.amount_msats(), refund_amount_msat);
+	assert_eq!(refund.absolute_expiry(), Some(Duration::from_secs(now_secs + 3600)));
+
+	node_b.bolt12_payment().request_refund_payment(&refund).unwrap();
+	expect_payment_received_event!(node_b, refund_amount_msat);
+
+	let node_a_payment_id = node_a
+		.list_payments_with_filter(|p| matches!(p.kind, PaymentKind::Bolt12Refund { .. }))
+		.first()
+		.unwrap()
+		.id;
+	expect_payment_successful_event!(node_a, Some(node_a_payment_id), None);
+	match node_a.wait_next_event() {
+		Event::RefundPaid { payment_id, amount_msat, .. } => {
+			assert_eq!(payment_id, node_a_payment_id);
+			assert_eq!(amount_msat, refund_amount_msat);
+			node_a.event_handled().unwrap();
+		},
+		ref e => panic!("{} got unexpected event!: {:?}", std::stringify!(node_a), e),
+	}
+	assert_eq!(node_a.payment(&node_a_payment_id).unwrap().status, PaymentStatus::Succeeded);
+}
+
+#[test]
+fn bolt12_default_offer() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+	let (node_a, node_b) = setup_two_nodes(&chain_source, false, true, false);
+
+	let address_a = node_a.onchain_payment().new_address().unwrap();
+	let premine_amount_sat = 5_000_000;
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![address_a],
+		Amount::from_sat(premine_amount_sat),
+	);
+
+	node_a.sync_wallets().unwrap();
+	open_channel(&node_a, &node_b, 4_000_000, true, &electrsd);
+
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+
+	expect_channel_ready_event!(node_a, node_b.node_id());
+	expect_channel_ready_event!(node_b, node_a.node_id());
+
+	// The default offer is stable, including across restarts.
+	let default_offer = node_b.bolt12_payment().default_offer().unwrap();
+	assert_eq!(default_offer.amount(), None);
+	assert_eq!(default_offer.absolute_expiry(), None);
+	assert_eq!(node_b.bolt12_payment().default_offer().unwrap(), default_offer);
+	node_b.stop().unwrap();
+	node_b.start().unwrap();
+	assert_eq!(node_b.bolt12_payment().default_offer().unwrap(), default_offer);
+
+	let qr_data = node_b.bolt12_payment().default_offer_qr_data().unwrap();
+	assert_eq!(qr_data, format!("LIGHTNING:{}", default_offer.to_string().to_uppercase()));
+
+	let amount_msat = 100_000_000;
+	let payment_id =
+		node_a.bolt12_payment().send_using_amount(&default_offer, amount_msat, None, None).unwrap();
+	expect_payment_successful_event!(node_a, Some(payment_id), None);
+	expect_payment_received_event!(node_b, amount_msat);
+
+	// Rotating yields a new default offer.
+	let rotated_offer = node_b.bolt12_payment().rotate_default_offer().unwrap();
+	assert_ne!(rotated_offer, default_offer);
+	assert_eq!(node_b.bolt12_payment().default_offer().unwrap(), rotated_offer);
 }
 
 #[test]
@@ -1281,6 +3615,8 @@ fn test_node_announcement_propagation() {
 	let node_a_node_alias = Some(NodeAlias(node_a_alias_bytes));
 	let node_a_announcement_addresses = random_listening_addresses();
 	config_a.node_config.node_alias = node_a_node_alias.clone();
+	let node_a_node_color = [0x12, 0x34, 0x56];
+	config_a.node_config.node_color = node_a_node_color;
 	config_a.node_config.listening_addresses = Some(random_listening_addresses());
 	config_a.node_config.announcement_addresses = Some(node_a_announcement_addresses.clone());
 
@@ -1342,6 +3678,11 @@ fn test_node_announcement_propagation() {
 	#[cfg(feature = "uniffi")]
 	assert_eq!(node_a_announcement_info.alias, node_a_alias_string);
 
+	#[cfg(not(feature = "uniffi"))]
+	assert_eq!(node_a_announcement_info.rgb(), node_a_node_color);
+	#[cfg(feature = "uniffi")]
+	assert_eq!(node_a_announcement_info.rgb, node_a_node_color);
+
 	#[cfg(not(feature = "uniffi"))]
 	assert_eq!(node_a_announcement_info.addresses(), &node_a_announcement_addresses);
 	#[cfg(feature = "uniffi")]
@@ -1352,10 +3693,29 @@ fn test_node_announcement_propagation() {
 	#[cfg(feature = "uniffi")]
 	assert_eq!(node_b_announcement_info.alias, node_b_alias_string);
 
+	#[cfg(not(feature = "uniffi"))]
+	assert_eq!(node_b_announcement_info.rgb(), [0; 3]);
+	#[cfg(feature = "uniffi")]
+	assert_eq!(node_b_announcement_info.rgb, [0; 3]);
+
 	#[cfg(not(feature = "uniffi"))]
 	assert_eq!(node_b_announcement_info.addresses(), &node_b_listening_addresses);
 	#[cfg(feature = "uniffi")]
 	assert_eq!(node_b_announcement_info.addresses, node_b_listening_addresses);
+
+	// Assert that our announced channel is part of both nodes' graphs
+	for node in [&node_a, &node_b] {
+		let graph = node.network_graph();
+		assert_eq!(graph.total_nodes(), 2);
+		assert_eq!(graph.total_channels(), 1);
+
+		let short_channel_id = graph.list_channels()[0];
+		for node_id in [node_a.node_id(), node_b.node_id()] {
+			let node_id = NodeId::from_pubkey(&node_id);
+			assert_eq!(graph.list_channels_for_node(&node_id), vec![short_channel_id]);
+		}
+		assert!(graph.channel(short_channel_id).is_some());
+	}
 }
 
 #[test]
@@ -1452,6 +3812,13 @@ fn unified_qr_send_receive() {
 
 	let uqr_payment = node_b.unified_qr_payment().receive(expected_amount_sats, "asdf", expiry_sec);
 	let uri_str = uqr_payment.clone().unwrap();
+
+	let unified_payment = node_a.unified_qr_payment().parse_unified_payment(&uri_str).unwrap();
+	assert_eq!(unified_payment.amount_sats, Some(expected_amount_sats));
+	assert_eq!(unified_payment.message.as_deref(), Some("asdf"));
+	assert!(unified_payment.bolt11_invoice.is_some());
+	assert!(unified_payment.bolt12_offer.is_some());
+
 	let offer_payment_id: PaymentId = match node_a.unified_qr_payment().send(&uri_str) {
 		Ok(QrPaymentResult::Bolt12 { payment_id }) => {
 			println!("\nBolt12 payment sent successfully with PaymentID: {:?}", payment_id);
@@ -1609,6 +3976,14 @@ fn lsps2_client_service_integration() {
 	let payment_id = payer_node.bolt11_payment().send(&jit_invoice, None).unwrap();
 	expect_channel_pending_event!(service_node, client_node.node_id());
 	expect_channel_ready_event!(service_node, client_node.node_id());
+	match service_node.wait_next_event() {
+		Event::LiquidityChannelOpened { client_node_id, amount_sat, .. } => {
+			assert_eq!(client_node_id, client_node.node_id());
+			assert!(amount_sat > 0);
+		},
+		ref e => panic!("{} got unexpected event!: {:?}", service_node.node_id(), e),
+	}
+	service_node.event_handled().unwrap();
 	expect_event!(service_node, PaymentForwarded);
 	expect_channel_pending_event!(client_node, service_node.node_id());
 	expect_channel_ready_event!(client_node, service_node.node_id());
@@ -1648,6 +4023,131 @@ fn lsps2_client_service_integration() {
 	expect_event!(service_node, PaymentForwarded);
 	expect_payment_received_event!(client_node, amount_msat);
 
+	println!("Generating variable-amount JIT invoice with minimum!");
+	let min_amount_msat = 1_000_000;
+	let variable_jit_invoice = client_node
+		.bolt11_payment()
+		.receive_variable_amount_via_jit_channel(
+			&invoice_description,
+			1024,
+			Some(min_amount_msat),
+			None,
+		)
+		.unwrap();
+	assert_eq!(variable_jit_invoice.amount_milli_satoshis(), None);
+	let route_hint_hop = &variable_jit_invoice.route_hints()[0].0[0];
+	assert_eq!(route_hint_hop.src_node_id, service_node_id);
+	assert_eq!(route_hint_hop.htlc_minimum_msat, Some(min_amount_msat));
+
+	// A minimum exceeding the LSP's maximum payment size can't be satisfied.
+	assert_eq!(
+		Err(NodeError::LiquidityRequestFailed),
+		client_node.bolt11_payment().receive_variable_amount_via_jit_channel(
+			&invoice_description,
+			1024,
+			Some(2_000_000_000),
+			None,
+		)
+	);
+
+	println!("Inspecting LSP opening fee parameters!");
+	let opening_params_menu = client_node.lsps2_liquidity().get_opening_params().unwrap();
+	assert_eq!(opening_params_menu.len(), 1);
+	let opening_params = opening_params_menu.first().unwrap().clone();
+	assert_eq!(opening_params.proportional, channel_opening_fee_ppm);
+	assert_eq!(opening_params.min_fee_msat, 0);
+	assert_eq!(opening_params.max_payment_size_msat, 1_000_000_000);
+	assert!(!opening_params.valid_until.is_past());
+
+	// The parameters are cached while they're valid.
+	assert_eq!(client_node.lsps2_liquidity().get_opening_params().unwrap(), opening_params_menu);
+
+	let variable_jit_invoice = client_node
+		.bolt11_payment()
+		.receive_variable_amount_via_jit_channel_with_opening_params(
+			&invoice_description,
+			1024,
+			Some(min_amount_msat),
+			opening_params.clone(),
+			None,
+		)
+		.unwrap();
+	let route_hint_hop = &variable_jit_invoice.route_hints()[0].0[0];
+	assert_eq!(route_hint_hop.src_node_id, service_node_id);
+
+	// Amounts beyond the limits of the parameters and expired parameters are refused.
+	assert_eq!(
+		Err(NodeError::InvalidLiquidityParams),
+		client_node.bolt11_payment().receive_via_jit_channel_with_opening_params(
+			2_000_000_000,
+			&invoice_description,
+			1024,
+			opening_params.clone(),
+			None,
+		)
+	);
+	let mut expired_opening_params = opening_params.clone();
+	expired_opening_params.valid_until =
+		LSPSDateTime::new_from_duration_since_epoch(Duration::from_secs(1));
+	assert_eq!(
+		Err(NodeError::InvalidLiquidityParams),
+		client_node.bolt11_payment().receive_via_jit_channel_with_opening_params(
+			jit_amount_msat,
+			&invoice_description,
+			1024,
+			expired_opening_params,
+			None,
+		)
+	);
+
+	// We refuse to proceed if the LSP quotes fees exceeding our limits.
+	let expected_fee_msat = (jit_amount_msat * channel_opening_fee_ppm as u64) / 1_000_000;
+	let total_fee_limits = JitChannelFeeLimits {
+		max_total_opening_fee_msat: Some(expected_fee_msat - 1),
+		max_proportional_ppm: None,
+	};
+	assert_eq!(
+		Err(NodeError::LspFeeExceedsLimit {
+			quoted: expected_fee_msat,
+			limit: expected_fee_msat - 1
+		}),
+		client_node.bolt11_payment().receive_via_jit_channel(
+			jit_amount_msat,
+			&invoice_description,
+			1024,
+			Some(total_fee_limits),
+		)
+	);
+	let proportional_fee_limits = JitChannelFeeLimits {
+		max_total_opening_fee_msat: None,
+		max_proportional_ppm: Some(channel_opening_fee_ppm as u64 - 1),
+	};
+	assert_eq!(
+		Err(NodeError::LspFeeExceedsLimit {
+			quoted: channel_opening_fee_ppm as u64,
+			limit: channel_opening_fee_ppm as u64 - 1
+		}),
+		client_node.bolt11_payment().receive_variable_amount_via_jit_channel(
+			&invoice_description,
+			1024,
+			None,
+			Some(proportional_fee_limits),
+		)
+	);
+	assert_eq!(
+		Err(NodeError::LspFeeExceedsLimit {
+			quoted: channel_opening_fee_ppm as u64,
+			limit: channel_opening_fee_ppm as u64 - 1
+		}),
+		client_node.bolt11_payment().receive_via_jit_channel_with_opening_params(
+			jit_amount_msat,
+			&invoice_description,
+			1024,
+			opening_params.clone(),
+			Some(proportional_fee_limits),
+		)
+	);
+
 	////////////////////////////////////////////////////////////////////////////
 	// receive_via_jit_channel_for_hash and claim_for_hash
 	////////////////////////////////////////////////////////////////////////////
@@ -1672,16 +4172,19 @@ fn lsps2_client_service_integration() {
 	let payment_id = payer_node.bolt11_payment().send(&jit_invoice, None).unwrap();
 	expect_channel_pending_event!(service_node, client_node.node_id());
 	expect_channel_ready_event!(service_node, client_node.node_id());
+	expect_event!(service_node, LiquidityChannelOpened);
 	expect_channel_pending_event!(client_node, service_node.node_id());
 	expect_channel_ready_event!(client_node, service_node.node_id());
 
 	let service_fee_msat = (jit_amount_msat * channel_opening_fee_ppm as u64) / 1_000_000;
 	let expected_received_amount_msat = jit_amount_msat - service_fee_msat;
+	// The payment arrives via the zero-conf JIT channel, i.e., before its funding confirmed.
 	let claimable_amount_msat = expect_payment_claimable_event!(
 		client_node,
 		payment_id,
 		manual_payment_hash,
-		expected_received_amount_msat
+		expected_received_amount_msat,
+		Some((true, false))
 	);
 	println!("Claiming payment!");
 	client_node
@@ -1701,68 +4204,373 @@ fn lsps2_client_service_integration() {
 		_ => panic!("Unexpected payment kind"),
 	}
 
-	////////////////////////////////////////////////////////////////////////////
-	// receive_via_jit_channel_for_hash and fail_for_hash
-	////////////////////////////////////////////////////////////////////////////
-	println!("Generating JIT invoice!");
-	// Increase the amount to make sure it does not fit into the existing channels.
-	let jit_amount_msat = 400_000_000;
-	let manual_preimage = PaymentPreimage([43u8; 32]);
-	let manual_payment_hash: PaymentHash = manual_preimage.into();
-	let jit_invoice = client_node
+	////////////////////////////////////////////////////////////////////////////
+	// receive_via_jit_channel_for_hash and fail_for_hash
+	////////////////////////////////////////////////////////////////////////////
+	println!("Generating JIT invoice!");
+	// Increase the amount to make sure it does not fit into the existing channels.
+	let jit_amount_msat = 400_000_000;
+	let manual_preimage = PaymentPreimage([43u8; 32]);
+	let manual_payment_hash: PaymentHash = manual_preimage.into();
+	let jit_invoice = client_node
+		.bolt11_payment()
+		.receive_via_jit_channel_for_hash(
+			jit_amount_msat,
+			&invoice_description,
+			1024,
+			None,
+			manual_payment_hash,
+		)
+		.unwrap();
+
+	// Have the payer_node pay the invoice, therby triggering channel open service_node -> client_node.
+	println!("Paying JIT invoice!");
+	let payment_id = payer_node.bolt11_payment().send(&jit_invoice, None).unwrap();
+	expect_channel_pending_event!(service_node, client_node.node_id());
+	expect_channel_ready_event!(service_node, client_node.node_id());
+	expect_event!(service_node, LiquidityChannelOpened);
+	expect_channel_pending_event!(client_node, service_node.node_id());
+	expect_channel_ready_event!(client_node, service_node.node_id());
+
+	let service_fee_msat = (jit_amount_msat * channel_opening_fee_ppm as u64) / 1_000_000;
+	let expected_received_amount_msat = jit_amount_msat - service_fee_msat;
+	expect_payment_claimable_event!(
+		client_node,
+		payment_id,
+		manual_payment_hash,
+		expected_received_amount_msat
+	);
+	println!("Failing payment!");
+	client_node.bolt11_payment().fail_for_hash(manual_payment_hash).unwrap();
+
+	expect_event!(payer_node, PaymentFailed);
+	assert_eq!(client_node.payment(&payment_id).unwrap().status, PaymentStatus::Failed);
+}
+
+#[test]
+fn facade_logging() {
+	let (_bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+
+	let logger = init_log_logger(LevelFilter::Trace);
+	let mut config = random_config(false);
+	config.log_writer = TestLogWriter::LogFacade;
+
+	println!("== Facade logging starts ==");
+	let _node = setup_node(&chain_source, config, None);
+
+	assert!(!logger.retrieve_logs().is_empty());
+	for (_, entry) in logger.retrieve_logs().iter().enumerate() {
+		validate_log_entry(entry);
+	}
+}
+
+#[test]
+fn json_logging() {
+	let (_bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let esplora_url = format!("http://{}", electrsd.esplora_url.as_ref().unwrap());
+
+	// Filesystem and JSON loggers are mutually exclusive.
+	let config = random_config(false);
+	setup_builder!(builder, config.node_config);
+	builder.set_chain_source_esplora(esplora_url.clone(), None);
+	builder.set_filesystem_logger(None, None, None);
+	builder.set_json_logger(None, None, None);
+	assert_eq!(builder.build_with_fs_store().err(), Some(BuildError::ConflictingLoggers));
+
+	let config = random_config(false);
+	let log_file_path =
+		format!("{}/json_logs/ldk_node.json.log", config.node_config.storage_dir_path);
+	setup_builder!(builder, config.node_config);
+	builder.set_chain_source_esplora(esplora_url, None);
+	builder.set_json_logger(Some(log_file_path.clone()), Some(LogLevel::Trace), None);
+	let node = builder.build_with_fs_store().unwrap();
+	node.start().unwrap();
+	node.stop().unwrap();
+
+	let logs = std::fs::read_to_string(log_file_path).unwrap();
+	assert!(!logs.is_empty());
+	for line in logs.lines() {
+		let entry: serde_json::Value = serde_json::from_str(line).unwrap();
+		for field in ["timestamp", "level", "target", "line", "message"] {
+			assert!(entry.get(field).is_some(), "Missing field {} in {}", field, line);
+		}
+	}
+}
+
+#[test]
+fn spontaneous_send_with_custom_preimage() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+	let (node_a, node_b) = setup_two_nodes(&chain_source, false, true, false);
+
+	let address_a = node_a.onchain_payment().new_address().unwrap();
+	let premine_sat = 1_000_000;
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![address_a],
+		Amount::from_sat(premine_sat),
+	);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	open_channel(&node_a, &node_b, 500_000, true, &electrsd);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	expect_channel_ready_event!(node_a, node_b.node_id());
+	expect_channel_ready_event!(node_b, node_a.node_id());
+
+	let seed = b"test_payment_preimage";
+	let bytes: Sha256Hash = Sha256Hash::hash(seed);
+	let custom_bytes = bytes.to_byte_array();
+	let custom_preimage = PaymentPreimage(custom_bytes);
+
+	let amount_msat = 100_000;
+	let payment_id = node_a
+		.spontaneous_payment()
+		.send_with_preimage(amount_msat, node_b.node_id(), custom_preimage, None)
+		.unwrap();
+
+	// check payment status and verify stored preimage
+	expect_payment_successful_event!(node_a, Some(payment_id), None);
+	let details: PaymentDetails =
+		node_a.list_payments_with_filter(|p| p.id == payment_id).first().unwrap().clone();
+	assert_eq!(details.status, PaymentStatus::Succeeded);
+	if let PaymentKind::Spontaneous { preimage: Some(pi), .. } = details.kind {
+		assert_eq!(pi.0, custom_bytes);
+	} else {
+		panic!("Expected a spontaneous PaymentKind with a preimage");
+	}
+
+	// Verify receiver side (node_b)
+	expect_payment_received_event!(node_b, amount_msat);
+	let receiver_payments: Vec<PaymentDetails> = node_b.list_payments_with_filter(|p| {
+		p.direction == PaymentDirection::Inbound
+			&& matches!(p.kind, PaymentKind::Spontaneous { .. })
+	});
+
+	assert_eq!(receiver_payments.len(), 1);
+	let receiver_details = &receiver_payments[0];
+	assert_eq!(receiver_details.status, PaymentStatus::Succeeded);
+	assert_eq!(receiver_details.amount_msat, Some(amount_msat));
+	assert_eq!(receiver_details.direction, PaymentDirection::Inbound);
+
+	// Verify receiver also has the same preimage
+	if let PaymentKind::Spontaneous { preimage: Some(pi), .. } = &receiver_details.kind {
+		assert_eq!(pi.0, custom_bytes);
+	} else {
+		panic!("Expected receiver to have spontaneous PaymentKind with preimage");
+	}
+}
+
+#[test]
+fn wait_for_channel_ready() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+	let (node_a, node_b) = setup_two_nodes(&chain_source, false, true, false);
+
+	let address_a = node_a.onchain_payment().new_address().unwrap();
+	let premine_sat = 1_000_000;
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![address_a],
+		Amount::from_sat(premine_sat),
+	);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	open_channel(&node_a, &node_b, 500_000, true, &electrsd);
+
+	let user_channel_id = node_a.list_channels().first().unwrap().user_channel_id;
+	assert_eq!(
+		Err(NodeError::InvalidChannelId),
+		node_a.wait_for_channel_ready(&UserChannelId(42), Duration::from_secs(1))
+	);
+
+	// The channel can't become ready before the funding transaction is confirmed.
+	assert_eq!(
+		Err(NodeError::ChannelReadyTimeout),
+		node_a.wait_for_channel_ready(&user_channel_id, Duration::from_secs(1))
+	);
+
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+
+	node_a.wait_for_channel_ready(&user_channel_id, Duration::from_secs(30)).unwrap();
+	expect_channel_ready_event!(node_a, node_b.node_id());
+	expect_channel_ready_event!(node_b, node_a.node_id());
+
+	// Once the channel is ready, we return immediately.
+	node_a.wait_for_channel_ready(&user_channel_id, Duration::from_secs(1)).unwrap();
+}
+
+#[test]
+fn receive_with_route_hints() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+	let (node_a, node_b) = setup_two_nodes(&chain_source, false, true, false);
+
+	let address_a = node_a.onchain_payment().new_address().unwrap();
+	let premine_sat = 1_000_000;
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![address_a],
+		Amount::from_sat(premine_sat),
+	);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	open_channel(&node_a, &node_b, 500_000, false, &electrsd);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	expect_channel_ready_event!(node_a, node_b.node_id());
+	expect_channel_ready_event!(node_b, node_a.node_id());
+
+	let channel = node_b.list_channels().first().unwrap().clone();
+	let invoice_description =
+		Bolt11InvoiceDescription::Direct(Description::new(String::from("asdf")).unwrap());
+	let amount_msat = 100_000;
+
+	// Only usable channels may be included.
+	let unknown_channel_id = ChannelId([42u8; 32]);
+	assert_eq!(
+		Err(NodeError::InvalidChannelId),
+		node_b.bolt11_payment().receive_with_route_hints(
+			amount_msat,
+			&invoice_description.clone().into(),
+			9217,
+			vec![unknown_channel_id],
+		)
+	);
+
+	let invoice = node_b
 		.bolt11_payment()
-		.receive_via_jit_channel_for_hash(
-			jit_amount_msat,
-			&invoice_description,
-			1024,
-			None,
-			manual_payment_hash,
+		.receive_with_route_hints(
+			amount_msat,
+			&invoice_description.into(),
+			9217,
+			vec![channel.channel_id, unknown_channel_id],
 		)
 		.unwrap();
+	let route_hints = invoice.route_hints();
+	assert_eq!(route_hints.len(), 1);
+	assert_eq!(route_hints[0].0[0].src_node_id, node_a.node_id());
+	assert_eq!(
+		Some(route_hints[0].0[0].short_channel_id),
+		channel.inbound_scid_alias.or(channel.short_channel_id)
+	);
 
-	// Have the payer_node pay the invoice, therby triggering channel open service_node -> client_node.
-	println!("Paying JIT invoice!");
-	let payment_id = payer_node.bolt11_payment().send(&jit_invoice, None).unwrap();
-	expect_channel_pending_event!(service_node, client_node.node_id());
-	expect_channel_ready_event!(service_node, client_node.node_id());
-	expect_channel_pending_event!(client_node, service_node.node_id());
-	expect_channel_ready_event!(client_node, service_node.node_id());
+	let payment_id = node_a.bolt11_payment().send(&invoice, None).unwrap();
+	expect_payment_successful_event!(node_a, Some(payment_id), None);
+	expect_payment_received_event!(node_b, amount_msat);
+}
 
-	let service_fee_msat = (jit_amount_msat * channel_opening_fee_ppm as u64) / 1_000_000;
-	let expected_received_amount_msat = jit_amount_msat - service_fee_msat;
-	expect_payment_claimable_event!(
-		client_node,
-		payment_id,
-		manual_payment_hash,
-		expected_received_amount_msat
+#[test]
+fn spontaneous_receive_refused_if_disabled() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+
+	let config_a = random_config(true);
+	let node_a = setup_node(&chain_source, config_a, None);
+
+	let mut config_b = random_config(true);
+	config_b.node_config.accept_spontaneous_payments = false;
+	let node_b = setup_node(&chain_source, config_b, None);
+
+	let address_a = node_a.onchain_payment().new_address().unwrap();
+	let premine_sat = 1_000_000;
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![address_a],
+		Amount::from_sat(premine_sat),
 	);
-	println!("Failing payment!");
-	client_node.bolt11_payment().fail_for_hash(manual_payment_hash).unwrap();
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	open_channel(&node_a, &node_b, 500_000, true, &electrsd);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	expect_channel_ready_event!(node_a, node_b.node_id());
+	expect_channel_ready_event!(node_b, node_a.node_id());
 
-	expect_event!(payer_node, PaymentFailed);
-	assert_eq!(client_node.payment(&payment_id).unwrap().status, PaymentStatus::Failed);
+	let payment_id = node_a.spontaneous_payment().send(100_000, node_b.node_id(), None).unwrap();
+
+	expect_event!(node_a, PaymentFailed);
+	assert_eq!(node_a.payment(&payment_id).unwrap().status, PaymentStatus::Failed);
+	assert!(node_b
+		.list_payments_with_filter(|p| matches!(p.kind, PaymentKind::Spontaneous { .. }))
+		.is_empty());
 }
 
 #[test]
-fn facade_logging() {
-	let (_bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+fn manual_claim_auto_fail() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
 	let chain_source = TestChainSource::Esplora(&electrsd);
 
-	let logger = init_log_logger(LevelFilter::Trace);
-	let mut config = random_config(false);
-	config.log_writer = TestLogWriter::LogFacade;
+	let config_a = random_config(true);
+	let node_a = setup_node(&chain_source, config_a, None);
 
-	println!("== Facade logging starts ==");
-	let _node = setup_node(&chain_source, config, None);
+	// Choose a margin large enough that the next block will be within it.
+	let mut config_b = random_config(true);
+	config_b.node_config.manual_claim_auto_fail_margin_blocks = Some(1000);
+	let node_b = setup_node(&chain_source, config_b, None);
 
-	assert!(!logger.retrieve_logs().is_empty());
-	for (_, entry) in logger.retrieve_logs().iter().enumerate() {
-		validate_log_entry(entry);
+	let address_a = node_a.onchain_payment().new_address().unwrap();
+	let premine_sat = 1_000_000;
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![address_a],
+		Amount::from_sat(premine_sat),
+	);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	open_channel(&node_a, &node_b, 500_000, true, &electrsd);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	expect_channel_ready_event!(node_a, node_b.node_id());
+	expect_channel_ready_event!(node_b, node_a.node_id());
+
+	let invoice_description =
+		Bolt11InvoiceDescription::Direct(Description::new(String::from("asdf")).unwrap());
+	let amount_msat = 100_000;
+	let manual_preimage = PaymentPreimage([42u8; 32]);
+	let manual_payment_hash = PaymentHash(Sha256Hash::hash(&manual_preimage.0).to_byte_array());
+	let manual_invoice = node_b
+		.bolt11_payment()
+		.receive_for_hash(amount_msat, &invoice_description.into(), 9217, manual_payment_hash)
+		.unwrap();
+	let manual_payment_id = node_a.bolt11_payment().send(&manual_invoice, None).unwrap();
+
+	expect_payment_claimable_event!(node_b, manual_payment_id, manual_payment_hash, amount_msat);
+
+	// Once the next block is connected, the payment should be failed back automatically.
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 1);
+	node_b.sync_wallets().unwrap();
+
+	match node_b.wait_next_event() {
+		ref e @ Event::PaymentAutoFailed { payment_hash, reason } => {
+			println!("{} got event {:?}", std::stringify!(node_b), e);
+			assert_eq!(payment_hash, manual_payment_hash);
+			assert_eq!(reason, PaymentAutoFailReason::ClaimDeadlineApproaching);
+			node_b.event_handled().unwrap();
+		},
+		ref e => {
+			panic!("{} got unexpected event!: {:?}", std::stringify!(node_b), e);
+		},
 	}
+	expect_event!(node_a, PaymentFailed);
+	assert_eq!(node_a.payment(&manual_payment_id).unwrap().status, PaymentStatus::Failed);
+	assert_eq!(node_b.payment(&manual_payment_id).unwrap().status, PaymentStatus::Failed);
 }
 
 #[test]
-fn spontaneous_send_with_custom_preimage() {
+fn manual_claim_timeout_and_fail_all() {
 	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
 	let chain_source = TestChainSource::Esplora(&electrsd);
 	let (node_a, node_b) = setup_two_nodes(&chain_source, false, true, false);
@@ -1784,47 +4592,68 @@ fn spontaneous_send_with_custom_preimage() {
 	expect_channel_ready_event!(node_a, node_b.node_id());
 	expect_channel_ready_event!(node_b, node_a.node_id());
 
-	let seed = b"test_payment_preimage";
-	let bytes: Sha256Hash = Sha256Hash::hash(seed);
-	let custom_bytes = bytes.to_byte_array();
-	let custom_preimage = PaymentPreimage(custom_bytes);
-
+	let invoice_description =
+		Bolt11InvoiceDescription::Direct(Description::new(String::from("asdf")).unwrap());
 	let amount_msat = 100_000;
-	let payment_id = node_a
-		.spontaneous_payment()
-		.send_with_preimage(amount_msat, node_b.node_id(), custom_preimage, None)
+
+	// A payment registered with a claim timeout is failed back once the timeout elapses.
+	let timeout_preimage = PaymentPreimage([42u8; 32]);
+	let timeout_payment_hash = PaymentHash(Sha256Hash::hash(&timeout_preimage.0).to_byte_array());
+	let timeout_invoice = node_b
+		.bolt11_payment()
+		.receive_for_hash_with_claim_timeout(
+			amount_msat,
+			&invoice_description.clone().into(),
+			9217,
+			timeout_payment_hash,
+			1,
+		)
 		.unwrap();
+	let timeout_payment_id = node_a.bolt11_payment().send(&timeout_invoice, None).unwrap();
+	assert_eq!(node_b.payment(&timeout_payment_id).unwrap().claim_timeout_secs, Some(1));
 
-	// check payment status and verify stored preimage
-	expect_payment_successful_event!(node_a, Some(payment_id), None);
-	let details: PaymentDetails =
-		node_a.list_payments_with_filter(|p| p.id == payment_id).first().unwrap().clone();
-	assert_eq!(details.status, PaymentStatus::Succeeded);
-	if let PaymentKind::Spontaneous { preimage: Some(pi), .. } = details.kind {
-		assert_eq!(pi.0, custom_bytes);
-	} else {
-		panic!("Expected a spontaneous PaymentKind with a preimage");
+	expect_payment_claimable_event!(node_b, timeout_payment_id, timeout_payment_hash, amount_msat);
+
+	match node_b.wait_next_event() {
+		ref e @ Event::PaymentAutoFailed { payment_hash, reason } => {
+			println!("{} got event {:?}", std::stringify!(node_b), e);
+			assert_eq!(payment_hash, timeout_payment_hash);
+			assert_eq!(reason, PaymentAutoFailReason::ClaimTimeoutElapsed);
+			node_b.event_handled().unwrap();
+		},
+		ref e => {
+			panic!("{} got unexpected event!: {:?}", std::stringify!(node_b), e);
+		},
 	}
+	expect_event!(node_a, PaymentFailed);
+	assert_eq!(node_b.payment(&timeout_payment_id).unwrap().status, PaymentStatus::Failed);
 
-	// Verify receiver side (node_b)
-	expect_payment_received_event!(node_b, amount_msat);
-	let receiver_payments: Vec<PaymentDetails> = node_b.list_payments_with_filter(|p| {
-		p.direction == PaymentDirection::Inbound
-			&& matches!(p.kind, PaymentKind::Spontaneous { .. })
-	});
+	// All held payments can be failed back at once.
+	let held_preimage = PaymentPreimage([43u8; 32]);
+	let held_payment_hash = PaymentHash(Sha256Hash::hash(&held_preimage.0).to_byte_array());
+	let held_invoice = node_b
+		.bolt11_payment()
+		.receive_for_hash(amount_msat, &invoice_description.into(), 9217, held_payment_hash)
+		.unwrap();
+	let held_payment_id = node_a.bolt11_payment().send(&held_invoice, None).unwrap();
 
-	assert_eq!(receiver_payments.len(), 1);
-	let receiver_details = &receiver_payments[0];
-	assert_eq!(receiver_details.status, PaymentStatus::Succeeded);
-	assert_eq!(receiver_details.amount_msat, Some(amount_msat));
-	assert_eq!(receiver_details.direction, PaymentDirection::Inbound);
+	expect_payment_claimable_event!(node_b, held_payment_id, held_payment_hash, amount_msat);
 
-	// Verify receiver also has the same preimage
-	if let PaymentKind::Spontaneous { preimage: Some(pi), .. } = &receiver_details.kind {
-		assert_eq!(pi.0, custom_bytes);
-	} else {
-		panic!("Expected receiver to have spontaneous PaymentKind with preimage");
+	assert_eq!(node_b.fail_all_pending_claimable(), vec![held_payment_hash]);
+	match node_b.wait_next_event() {
+		ref e @ Event::PaymentAutoFailed { payment_hash, reason } => {
+			println!("{} got event {:?}", std::stringify!(node_b), e);
+			assert_eq!(payment_hash, held_payment_hash);
+			assert_eq!(reason, PaymentAutoFailReason::FailAllRequested);
+			node_b.event_handled().unwrap();
+		},
+		ref e => {
+			panic!("{} got unexpected event!: {:?}", std::stringify!(node_b), e);
+		},
 	}
+	expect_event!(node_a, PaymentFailed);
+	assert_eq!(node_b.payment(&held_payment_id).unwrap().status, PaymentStatus::Failed);
+	assert!(node_b.fail_all_pending_claimable().is_empty());
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
@@ -1837,3 +4666,202 @@ async fn drop_in_async_context() {
 	let node = setup_node(&chain_source, config, Some(seed_bytes));
 	node.stop().unwrap();
 }
+
+#[test]
+fn node_announcement_info() {
+	let (_bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+
+	let mut config_a = random_config(true);
+	config_a.node_config.node_color = [0x12, 0x34, 0x56];
+	let node_a = setup_node(&chain_source, config_a.clone(), None);
+
+	let info_a = node_a.node_announcement_info();
+	assert!(info_a.is_announced);
+	assert_eq!(info_a.node_id, node_a.node_id());
+	assert_eq!(info_a.alias, config_a.node_config.node_alias);
+	assert_eq!(info_a.color, [0x12, 0x34, 0x56]);
+	assert_eq!(Some(info_a.addresses), node_a.announcement_addresses());
+	assert!(info_a.features.supports_static_remote_key());
+	assert!(info_a.features.supports_onion_messages());
+
+	// Unannounced nodes return the announcement they would broadcast.
+	let mut config_b = random_config(true);
+	config_b.node_config.listening_addresses = None;
+	let node_b = setup_node(&chain_source, config_b.clone(), None);
+
+	let info_b = node_b.node_announcement_info();
+	assert!(!info_b.is_announced);
+	assert_eq!(info_b.alias, config_b.node_config.node_alias);
+	assert!(info_b.addresses.is_empty());
+
+	node_a.stop().unwrap();
+	node_b.stop().unwrap();
+}
+
+#[test]
+fn disabled_features_are_not_advertised() {
+	let (_bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+
+	let mut config = random_config(true);
+	config.node_config.features.zero_conf = false;
+	config.node_config.features.keysend = false;
+	let node = setup_node(&chain_source, config, None);
+
+	let features = node.node_announcement_info().features;
+	// Neither of the zero-conf feature bits (50/51) is set.
+	assert_eq!(features.le_flags().get(6).map_or(0, |byte| byte & 0b1100), 0);
+	assert!(!features.supports_keysend());
+	assert!(features.supports_scid_privacy());
+	assert!(features.supports_route_blinding());
+	node.stop().unwrap();
+
+	// Enabling a feature without its prerequisite is rejected.
+	let mut config = random_config(true);
+	config.node_config.features.scid_alias = false;
+	setup_builder!(builder, config.node_config);
+	assert_eq!(builder.build().err(), Some(BuildError::InvalidFeatureConfig));
+}
+
+#[test]
+fn open_channel_with_params() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+	let (node_a, node_b) = setup_two_nodes(&chain_source, false, true, false);
+
+	let address_a = node_a.onchain_payment().new_address().unwrap();
+	let premine_sat = 1_000_000;
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![address_a],
+		Amount::from_sat(premine_sat),
+	);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+
+	let channel_amount_sat = 500_000;
+	let node_b_address = node_b.listening_addresses().unwrap().first().unwrap().clone();
+
+	// We can't push more than the channel value minus the channel reserve.
+	let params = ChannelOpenParams {
+		push_to_counterparty_msat: Some((channel_amount_sat - 1000) * 1000),
+		..Default::default()
+	};
+	assert_eq!(
+		Err(NodeError::InvalidAmount),
+		node_a.open_channel_with_params(
+			node_b.node_id(),
+			node_b_address.clone(),
+			channel_amount_sat,
+			params
+		)
+	);
+
+	let push_msat = 100_000_000;
+	let params = ChannelOpenParams {
+		push_to_counterparty_msat: Some(push_msat),
+		funding_confirmation_target: Some(ConfirmationTarget::OnchainPayment),
+		..Default::default()
+	};
+	node_a
+		.open_channel_with_params(node_b.node_id(), node_b_address, channel_amount_sat, params)
+		.unwrap();
+
+	let funding_txo_a = expect_channel_pending_event!(node_a, node_b.node_id());
+	let funding_txo_b = expect_channel_pending_event!(node_b, node_a.node_id());
+	assert_eq!(funding_txo_a, funding_txo_b);
+	wait_for_tx(&electrsd.client, funding_txo_a.txid);
+
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	expect_channel_ready_event!(node_a, node_b.node_id());
+	expect_channel_ready_event!(node_b, node_a.node_id());
+
+	assert_eq!(node_b.list_balances().total_lightning_balance_sats, push_msat / 1000);
+}
+
+#[test]
+fn open_channel_psbt() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+	let (node_a, node_b) = setup_two_nodes(&chain_source, false, true, false);
+
+	// We still need on-chain funds to cover the Anchor reserve.
+	let address_a = node_a.onchain_payment().new_address().unwrap();
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![address_a],
+		Amount::from_sat(100_000),
+	);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+
+	// Unknown channels can't be finalized.
+	let channel_amount_sat = 500_000;
+	let node_b_address = node_b.listening_addresses().unwrap().first().unwrap().clone();
+	let funding_psbt = node_a
+		.open_channel_psbt(
+			node_b.node_id(),
+			node_b_address,
+			channel_amount_sat,
+			ChannelOpenParams::default(),
+		)
+		.unwrap();
+	assert_eq!(
+		Err(NodeError::InvalidChannelId),
+		node_a.finalize_channel_open(&ChannelId([42; 32]), funding_psbt.psbt.clone())
+	);
+
+	// The unsigned PSBT isn't accepted.
+	assert_eq!(
+		Err(NodeError::InvalidPsbt),
+		node_a.finalize_channel_open(&funding_psbt.temporary_channel_id, funding_psbt.psbt.clone())
+	);
+
+	// Have an external wallet fund and sign the funding output.
+	let funding_tx = &funding_psbt.psbt.unsigned_tx;
+	assert_eq!(funding_tx.output.len(), 1);
+	assert_eq!(funding_tx.output[0].value, Amount::from_sat(channel_amount_sat));
+	let funding_address =
+		Address::from_script(&funding_tx.output[0].script_pubkey, bitcoin::Network::Regtest)
+			.unwrap();
+	let outputs = serde_json::json!([{
+		funding_address.to_string(): Amount::from_sat(channel_amount_sat).to_btc()
+	}]);
+	let funded_psbt = bitcoind
+		.client
+		.call::<serde_json::Value>("walletcreatefundedpsbt", &[serde_json::json!([]), outputs])
+		.unwrap()["psbt"]
+		.clone();
+	let signed_psbt =
+		bitcoind.client.call::<serde_json::Value>("walletprocesspsbt", &[funded_psbt]).unwrap()
+			["psbt"]
+			.as_str()
+			.unwrap()
+			.to_string();
+	let signed_psbt = Psbt::deserialize(&BASE64_STANDARD.decode(signed_psbt).unwrap()).unwrap();
+
+	node_a.finalize_channel_open(&funding_psbt.temporary_channel_id, signed_psbt).unwrap();
+
+	let funding_txo_a = expect_channel_pending_event!(node_a, node_b.node_id());
+	let funding_txo_b = expect_channel_pending_event!(node_b, node_a.node_id());
+	assert_eq!(funding_txo_a, funding_txo_b);
+	wait_for_tx(&electrsd.client, funding_txo_a.txid);
+
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	let user_channel_id = expect_channel_ready_event!(node_a, node_b.node_id());
+	expect_channel_ready_event!(node_b, node_a.node_id());
+	assert_eq!(user_channel_id, funding_psbt.user_channel_id);
+
+	// The funding PSBT was consumed.
+	assert_eq!(
+		Err(NodeError::InvalidChannelId),
+		node_a.finalize_channel_open(&funding_psbt.temporary_channel_id, funding_psbt.psbt)
+	);
+}
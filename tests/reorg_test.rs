@@ -52,7 +52,9 @@ proptest! {
 
 		macro_rules! sync_wallets {
 			() => {
-				nodes.iter().for_each(|node| node.sync_wallets().unwrap())
+				nodes.iter().for_each(|node| {
+					node.sync_wallets().unwrap();
+				})
 			};
 		}
 		sync_wallets!();